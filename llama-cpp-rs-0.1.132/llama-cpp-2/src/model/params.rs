@@ -352,6 +352,15 @@ impl LlamaModelParams {
         self
     }
 
+    // Local patch (not in upstream llama-cpp-2 0.1.132): muggle-translator's per-backend
+    // `use_mmap` option needs this setter. Re-apply it when the vendored crate is updated.
+    /// sets `use_mmap`
+    #[must_use]
+    pub fn with_use_mmap(mut self, use_mmap: bool) -> Self {
+        self.params.use_mmap = use_mmap;
+        self
+    }
+
     /// sets `use_mlock`
     #[must_use]
     pub fn with_use_mlock(mut self, use_mlock: bool) -> Self {
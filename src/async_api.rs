@@ -0,0 +1,215 @@
+//! Async facade for servers and GUI frontends.
+//!
+//! The pipeline itself is blocking (llama.cpp calls, file I/O). `spawn_translate_docx` /
+//! `spawn_translate_text` move a `TranslatorPipeline` onto a worker thread and return a
+//! `TranslationTask`, a future any executor can await; the pipeline is handed back with the
+//! result. Console messages reach the embedder through an `EventReceiver` set up with
+//! `ConsoleProgress::with_sink`. Nothing here depends on a particular runtime.
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, JoinHandle};
+
+use crate::cancel::CancellationToken;
+use crate::pipeline::TranslatorPipeline;
+
+fn lock<T>(m: &Mutex<T>) -> MutexGuard<'_, T> {
+    m.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+struct Channel<T> {
+    queue: VecDeque<T>,
+    waker: Option<Waker>,
+    senders: usize,
+    receiver_alive: bool,
+}
+
+/// Sending half of an event channel; cheap to clone, never blocks.
+pub struct EventSender<T> {
+    chan: Arc<Mutex<Channel<T>>>,
+}
+
+/// Receiving half of an event channel: `recv().await`, or `try_recv` from a polling UI loop.
+pub struct EventReceiver<T> {
+    chan: Arc<Mutex<Channel<T>>>,
+}
+
+/// Unbounded channel whose receiver can be awaited from any executor.
+pub fn event_channel<T>() -> (EventSender<T>, EventReceiver<T>) {
+    let chan = Arc::new(Mutex::new(Channel {
+        queue: VecDeque::new(),
+        waker: None,
+        senders: 1,
+        receiver_alive: true,
+    }));
+    (EventSender { chan: chan.clone() }, EventReceiver { chan })
+}
+
+impl<T> EventSender<T> {
+    /// Queue `event`; `false` once the receiver is gone.
+    pub fn send(&self, event: T) -> bool {
+        let mut chan = lock(&self.chan);
+        if !chan.receiver_alive {
+            return false;
+        }
+        chan.queue.push_back(event);
+        if let Some(waker) = chan.waker.take() {
+            waker.wake();
+        }
+        true
+    }
+}
+
+impl<T> Clone for EventSender<T> {
+    fn clone(&self) -> Self {
+        lock(&self.chan).senders += 1;
+        Self {
+            chan: self.chan.clone(),
+        }
+    }
+}
+
+impl<T> Drop for EventSender<T> {
+    fn drop(&mut self) {
+        let mut chan = lock(&self.chan);
+        chan.senders -= 1;
+        if chan.senders == 0 {
+            if let Some(waker) = chan.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl<T> EventReceiver<T> {
+    /// Next event; `None` once every sender is dropped (the pipeline is gone) and the queue is
+    /// drained.
+    pub async fn recv(&mut self) -> Option<T> {
+        std::future::poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let mut chan = lock(&self.chan);
+        if let Some(event) = chan.queue.pop_front() {
+            return Poll::Ready(Some(event));
+        }
+        if chan.senders == 0 {
+            return Poll::Ready(None);
+        }
+        chan.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    /// Next queued event without waiting.
+    pub fn try_recv(&mut self) -> Option<T> {
+        lock(&self.chan).queue.pop_front()
+    }
+}
+
+impl<T> Drop for EventReceiver<T> {
+    fn drop(&mut self) {
+        let mut chan = lock(&self.chan);
+        chan.receiver_alive = false;
+        chan.queue.clear();
+    }
+}
+
+#[derive(Default)]
+struct TaskSignal {
+    done: bool,
+    waker: Option<Waker>,
+}
+
+/// Marks the task done when the worker returns or unwinds.
+struct DoneGuard(Arc<Mutex<TaskSignal>>);
+
+impl Drop for DoneGuard {
+    fn drop(&mut self) {
+        let mut signal = lock(&self.0);
+        signal.done = true;
+        if let Some(waker) = signal.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// A translation running on its worker thread. Resolves to the pipeline (for `run_stats`,
+/// `write_trace_bundle` or the next document) and the result; a panic on the worker is
+/// resumed in the awaiting task. Dropping the task does not stop the run — call `cancel`.
+pub struct TranslationTask<T> {
+    handle: Option<JoinHandle<(TranslatorPipeline, anyhow::Result<T>)>>,
+    signal: Arc<Mutex<TaskSignal>>,
+    cancel: CancellationToken,
+}
+
+impl<T: Send + 'static> TranslationTask<T> {
+    fn spawn(
+        pipeline: TranslatorPipeline,
+        run: impl FnOnce(&mut TranslatorPipeline) -> anyhow::Result<T> + Send + 'static,
+    ) -> Self {
+        let signal = Arc::new(Mutex::new(TaskSignal::default()));
+        let cancel = pipeline.cancellation();
+        let done = DoneGuard(signal.clone());
+        let handle = thread::spawn(move || {
+            let _done = done;
+            let mut pipeline = pipeline;
+            let result = run(&mut pipeline);
+            (pipeline, result)
+        });
+        Self {
+            handle: Some(handle),
+            signal,
+            cancel,
+        }
+    }
+}
+
+impl<T> TranslationTask<T> {
+    /// Cancel the run (see `TranslatorPipeline::with_cancellation`); the task then resolves to a
+    /// `Cancelled` error.
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    pub fn is_finished(&self) -> bool {
+        lock(&self.signal).done
+    }
+}
+
+impl<T> Future for TranslationTask<T> {
+    type Output = (TranslatorPipeline, anyhow::Result<T>);
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        {
+            let mut signal = lock(&self.signal);
+            if !signal.done {
+                signal.waker = Some(cx.waker().clone());
+                return Poll::Pending;
+            }
+        }
+        let handle = self
+            .handle
+            .take()
+            .expect("TranslationTask polled after completion");
+        match handle.join() {
+            Ok(out) => Poll::Ready(out),
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    }
+}
+
+impl TranslatorPipeline {
+    /// `translate_docx` on a worker thread (any input type `translate_docx` accepts).
+    pub fn spawn_translate_docx(self, input: PathBuf, output: PathBuf) -> TranslationTask<()> {
+        TranslationTask::spawn(self, move |p| p.translate_docx(&input, &output))
+    }
+
+    /// `translate_text` on a worker thread.
+    pub fn spawn_translate_text(self, source: String) -> TranslationTask<String> {
+        TranslationTask::spawn(self, move |p| p.translate_text(&source))
+    }
+}
@@ -0,0 +1,53 @@
+//! Cooperative cancellation of a running translation.
+//!
+//! An embedder keeps a clone of the token given to `TranslatorPipeline::with_cancellation` and
+//! calls `cancel()` from any thread; the pipeline stops at the next chunk boundary or generated
+//! token with a `Cancelled` error (kind `cancelled`). Trace files and the autosaved progress
+//! written so far are left in place.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// `Err(Cancelled)` once `cancel` was called.
+    pub fn check(&self) -> anyhow::Result<()> {
+        if self.is_cancelled() {
+            return Err(Cancelled.into());
+        }
+        Ok(())
+    }
+}
+
+/// The error a cancelled translation returns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("translation cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Whether `err` (or any error it wraps) is a cancellation.
+pub fn is_cancelled(err: &anyhow::Error) -> bool {
+    err.chain()
+        .any(|cause| cause.downcast_ref::<Cancelled>().is_some())
+}
@@ -4,7 +4,7 @@ use std::path::{Path, PathBuf};
 use anyhow::{anyhow, Context};
 use serde::Deserialize;
 
-use crate::models::native::find_file_upwards;
+use crate::models::native::{find_file_upwards, CompletionFormat};
 
 #[derive(Clone, Debug, Deserialize, Default)]
 pub struct AppConfig {
@@ -14,6 +14,23 @@ pub struct AppConfig {
     pub prompts: PromptsSection,
     #[serde(default)]
     pub models: ModelsSection,
+    #[serde(default)]
+    pub trace: TraceSection,
+    #[serde(default)]
+    pub input: InputSection,
+    #[serde(default)]
+    pub links: LinksSection,
+    #[serde(default)]
+    pub hooks: HooksSection,
+    #[serde(default)]
+    pub pdf: PdfSection,
+    /// `[routing]`: translate backend per detected language pair (`"en->zh" = "hy_mt"`), with an
+    /// optional `default` for the other pairs.
+    #[serde(default)]
+    pub routing: HashMap<String, String>,
+    /// `[pivot."de->zh"]`: translate the pair through an intermediate language.
+    #[serde(default)]
+    pub pivot: HashMap<String, PivotSection>,
 }
 
 #[derive(Clone, Debug, Deserialize, Default)]
@@ -25,8 +42,10 @@ pub struct PipelineSection {
     #[serde(default)]
     pub mode: Option<String>,
 
+    /// One backend name, or a fallback chain (`["hy_mt", "translategemma_4b"]`) tried in order
+    /// when a model file is missing, fails to load, or its chat calls keep failing.
     #[serde(default)]
-    pub translate_backend: Option<String>,
+    pub translate_backend: Option<BackendChain>,
     #[serde(default)]
     pub alt_translate_backend: Option<String>,
     #[serde(default)]
@@ -35,6 +54,10 @@ pub struct PipelineSection {
     pub polish_backend: Option<String>,
     #[serde(default)]
     pub controller_backend: Option<String>,
+    /// Full mode: run the controller's para_notes for the whole document on a worker thread
+    /// while Translate A runs (both models are loaded at once). Default false.
+    #[serde(default)]
+    pub overlap_controller: Option<bool>,
 
     #[serde(default)]
     pub threads: Option<i32>,
@@ -45,6 +68,9 @@ pub struct PipelineSection {
     pub autosave_every: Option<usize>,
     #[serde(default)]
     pub autosave_suffix: Option<String>,
+    /// Also keep the last N autosaves as `<autosave>.<YYYYMMDD-HHMMSS>.docx` (0 = off).
+    #[serde(default)]
+    pub autosave_keep: Option<usize>,
 
     #[serde(default)]
     pub trace_dir: Option<String>,
@@ -53,6 +79,10 @@ pub struct PipelineSection {
     #[serde(default)]
     pub log_max_chars: Option<usize>,
 
+    /// Console message language ("en" / "zh"). Also selects the default output suffix.
+    #[serde(default)]
+    pub ui_lang: Option<String>,
+
     /// Optional DOCX filter rules TOML. When set, the input DOCX is normalized (non-visual tags
     /// stripped + adjacent runs merged) before extraction/translation, to reduce fragmentation.
     #[serde(default)]
@@ -61,6 +91,365 @@ pub struct PipelineSection {
     /// Optional dev-only limiter: process at most N translation units.
     #[serde(default)]
     pub max_tus: Option<usize>,
+
+    /// Previous translated pairs (source/target) shown read-only at the top of the next chunk's
+    /// prompt to keep terminology consistent across chunk boundaries. 0 / unset = off.
+    #[serde(default)]
+    pub context_overlap: Option<usize>,
+
+    /// Size cap (chars) of that context; the oldest pairs are dropped first. Default 1200.
+    #[serde(default)]
+    pub context_overlap_max_chars: Option<usize>,
+
+    /// Optional project directory. Paragraph translations and learned terms are accumulated in
+    /// `<project_dir>/project_memory.sqlite` and preloaded into prompts for later documents.
+    /// Relative paths are resolved against the config file directory.
+    #[serde(default)]
+    pub project_dir: Option<String>,
+
+    /// Also translate OOXML packages embedded under `word/embeddings/` (Word documents with the
+    /// same pipeline, Excel workbooks' shared strings) and re-embed the results. Default false.
+    #[serde(default)]
+    pub translate_embedded: Option<bool>,
+
+    /// OCR command run on every image under `word/media/` (`{image}` = image file path, the
+    /// recognized text on stdout). Its text is appended to the document as paragraphs marked
+    /// `[OCR: <image>]` and translated with the rest. Unset = off.
+    #[serde(default)]
+    pub ocr_command: Option<String>,
+
+    /// Write `<stem>.aligned.jsonl` (source/target pairs) with plain text instead of the raw
+    /// `<<MT_...>>` control tokens (tabs/breaks become `\t`/`\n`). Default false.
+    #[serde(default)]
+    pub corpus_strip_tokens: Option<bool>,
+
+    /// Highlight (yellow) the DOCX runs whose translation fell back to the source text after
+    /// validation kept failing (basic mode). Default false.
+    #[serde(default)]
+    pub highlight_fallback: Option<bool>,
+
+    /// Highlight the DOCX runs of low-confidence slots: hard quality flags or a source fallback
+    /// (basic mode; includes `highlight_fallback`). Default false.
+    #[serde(default)]
+    pub highlight_low_confidence: Option<bool>,
+
+    /// `w:highlight` color for the two options above (Word's names, e.g. "yellow",
+    /// "lightGray"). Default "yellow".
+    #[serde(default)]
+    pub highlight_color: Option<String>,
+
+    /// Repair attempts per unit when validation/quality checks fail. Default 2.
+    #[serde(default)]
+    pub max_repairs: Option<usize>,
+
+    /// Repair attempts per unit when placeholders/control tokens are broken. Default 6.
+    #[serde(default)]
+    pub max_repairs_token_errors: Option<usize>,
+
+    /// Repair calls for the whole run (forced retranslations included); once spent, units keep
+    /// their best candidate and are flagged `repair_budget_exhausted`. Default unlimited.
+    #[serde(default)]
+    pub max_repairs_total: Option<usize>,
+
+    /// Full mode: stitch audit + patch rounds; the loop stops early once the audit finds no
+    /// issue, and issues left after the last round are flagged `stitch_unresolved` in the
+    /// validation report. Default 2.
+    #[serde(default)]
+    pub stitch_rounds: Option<usize>,
+
+    /// Full mode: patch calls over all stitch rounds. Each round patches its issues most severe
+    /// first (meaning errors, then the rest, then punctuation/wording trivia); issues past the
+    /// budget stay unresolved. Default unlimited.
+    #[serde(default)]
+    pub patch_budget: Option<usize>,
+
+    /// Full mode: patch models generating at once. Issues on non-adjacent paragraphs are batched
+    /// and a batch is spread over the workers, each loading its own copy of the patch model.
+    /// Default 1.
+    #[serde(default)]
+    pub patch_workers: Option<usize>,
+
+    /// Shrink/grow translate chunks (item count and char budget) per backend from the observed
+    /// segmented-parse failure rate. Default true.
+    #[serde(default)]
+    pub adaptive_chunks: Option<bool>,
+
+    /// Full mode: how a paragraph translation is laid out over its runs. "markers" (default):
+    /// the model places a marker per text node; "spans": runs are grouped by style and the model
+    /// marks the ranges in non-dominant styles (bold, italic, links, ...); "paragraph": the runs
+    /// collapse to the dominant one, which takes the whole translation.
+    #[serde(default)]
+    pub slot_projection: Option<String>,
+
+    /// Units holding only numbers, dates, currency codes and magnitude suffixes: "copy"
+    /// (default) passes them through without a model call, "locale" does too and rewrites the
+    /// decimal/group separators for the target language, "translate" sends them to the model.
+    #[serde(default)]
+    pub numeric_cells: Option<String>,
+
+    /// Quotation marks of the translation: "keep" (default), "curly" (“ ” ‘ ’), "guillemets"
+    /// (« » ‹ ›), "corner" (「 」 『 』) or "auto" (by target language).
+    #[serde(default)]
+    pub quote_style: Option<String>,
+
+    /// Rewrite `REF` field results, heading titles quoted in the text ("see Section 5
+    /// 'Termination'") and typed table-of-contents entries to the final translated headings;
+    /// TOC entries it can't fix are reported. Default true.
+    #[serde(default)]
+    pub cross_refs: Option<bool>,
+
+    /// Set `w:updateFields` in documents with a TOC field so Word rebuilds it (and the other
+    /// fields) from the translated headings on open; Word asks before updating. Default true.
+    #[serde(default)]
+    pub update_fields: Option<bool>,
+
+    /// Sampling seed for every model: a number, or "random" for a fresh one per run (recorded in
+    /// the trace). Default 42.
+    #[serde(default)]
+    pub seed: Option<SeedSetting>,
+
+    /// Full-mode stage switches, e.g. `fuse = false` (all on by default; Translate A always runs).
+    /// Stages: notes, alt_translate, fuse, stitch_audit, patch, polish. fuse, stitch_audit and
+    /// patch also take `"flagged"`: only units with quality flags.
+    #[serde(default)]
+    pub stages: HashMap<String, StageSetting>,
+
+    /// Translate chunk limits.
+    #[serde(default)]
+    pub chunking: ChunkingSection,
+
+    /// Casing applied to the final target text per paragraph kind.
+    #[serde(default)]
+    pub casing: CasingSection,
+
+    /// Acronyms frozen as NT tokens before translation.
+    #[serde(default)]
+    pub acronyms: AcronymsSection,
+}
+
+/// `[pipeline.chunking]`: how many units go into one translate prompt.
+#[derive(Clone, Debug, Deserialize, Default)]
+pub struct ChunkingSection {
+    /// Units per chunk (default 64 in basic mode, 32 in full mode).
+    #[serde(default)]
+    pub max_items: Option<usize>,
+
+    /// Source chars per context token used to size chunks (default 2.0; CJK text needs less).
+    #[serde(default)]
+    pub char_per_token_ratio: Option<f64>,
+
+    /// Context tokens kept free of source text for the prompt template (default 900).
+    #[serde(default)]
+    pub reserve_tokens: Option<u32>,
+}
+
+/// `[pipeline.casing]`: casing rules for the translated text, by paragraph kind. Styles:
+/// "keep" (default), "capitalize" (first letter only), "sentence" and "title" (English targets
+/// only).
+#[derive(Clone, Debug, Deserialize, Default)]
+pub struct CasingSection {
+    /// Heading paragraphs (heading styles, outline levels, "Title").
+    #[serde(default)]
+    pub headings: Option<String>,
+
+    /// Other paragraphs of the document body.
+    #[serde(default)]
+    pub body: Option<String>,
+
+    #[serde(default)]
+    pub table_cells: Option<String>,
+
+    /// Upper-case the translation of a paragraph written in capitals in the source.
+    #[serde(default)]
+    pub preserve_all_caps: Option<bool>,
+}
+
+/// `[pipeline.acronyms]`: all-caps words (GDPR, API) are frozen like numbers and URLs, so they
+/// come back exactly as written.
+#[derive(Clone, Debug, Deserialize, Default)]
+pub struct AcronymsSection {
+    /// Default true.
+    #[serde(default)]
+    pub enabled: Option<bool>,
+
+    /// Words frozen whatever their case (SaaS, iOS).
+    #[serde(default)]
+    pub allow: Vec<String>,
+
+    /// All-caps words the model may translate (NATO for a target that spells it in its script).
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum BackendChain {
+    One(String),
+    Chain(Vec<String>),
+}
+
+impl BackendChain {
+    /// Backend names in fallback order (blank entries dropped).
+    pub fn names(&self) -> Vec<String> {
+        let names: Vec<&String> = match self {
+            Self::One(name) => vec![name],
+            Self::Chain(names) => names.iter().collect(),
+        };
+        names
+            .into_iter()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Default)]
+pub struct TraceSection {
+    /// Master switch for prompt/output trace files (ANDed with `pipeline.trace_prompts`).
+    /// Pipeline artifacts (mask/offsets/text JSON) are always written to the trace dir.
+    #[serde(default)]
+    pub enabled: Option<bool>,
+
+    /// Keep at most N files in the trace dir. Oldest files are pruned at startup; once the
+    /// current run reaches the limit, further trace files are skipped.
+    #[serde(default)]
+    pub max_files: Option<usize>,
+
+    /// Same as `max_files`, but for the total size of the trace dir (MiB).
+    #[serde(default)]
+    pub max_size_mb: Option<u64>,
+
+    /// Write `<output_stem>.trace-bundle.zip` next to the output when the pipeline fails.
+    #[serde(default)]
+    pub bundle_on_failure: Option<bool>,
+
+    /// Redact document text (letters) in trace bundles.
+    #[serde(default)]
+    pub bundle_redact: Option<bool>,
+
+    /// Per-stage switches, e.g. `translate_b = false`.
+    /// Stages: translate_a, translate_b, para_notes, fuse, stitch_audit, patch, validate.
+    #[serde(default)]
+    pub stages: HashMap<String, bool>,
+}
+
+/// Resource caps for input packages (zip bomb protection).
+#[derive(Clone, Debug, Deserialize, Default)]
+pub struct InputSection {
+    /// Maximum number of zip entries (default 20000).
+    #[serde(default)]
+    pub max_entries: Option<usize>,
+
+    /// Maximum decompressed size of a single entry, MiB (default 1024).
+    #[serde(default)]
+    pub max_entry_mb: Option<u64>,
+
+    /// Maximum decompressed size of the whole package, MiB (default 4096).
+    #[serde(default)]
+    pub max_total_mb: Option<u64>,
+
+    /// Placeholder prefix of mask/offsets/text artifacts: `hash` (of the file, default),
+    /// `content` (of the document content, stable across re-saves) or a fixed prefix.
+    #[serde(default)]
+    pub placeholder_prefix: Option<String>,
+}
+
+/// Hyperlink handling at merge time.
+#[derive(Clone, Debug, Deserialize, Default)]
+pub struct LinksSection {
+    /// Regex rewrites of external link targets in `word/_rels/*.rels`, applied in order.
+    #[serde(default)]
+    pub rewrite: Vec<LinkRewriteRule>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct LinkRewriteRule {
+    pub pattern: String,
+    /// May reference capture groups (`$1`, `${name}`).
+    pub replacement: String,
+}
+
+/// `--from-pdf` converters. Placeholders: `{input}`, `{outdir}`, and `{output}` (the file the
+/// command must produce: `<outdir>/<input stem>.<docx|pdf>`, where LibreOffice writes it).
+#[derive(Clone, Debug, Deserialize, Default)]
+pub struct PdfSection {
+    /// PDF -> DOCX. Default: LibreOffice headless with the PDF import filter.
+    #[serde(default)]
+    pub to_docx: Option<String>,
+
+    /// DOCX -> PDF, used when the output path ends in `.pdf`. Default: LibreOffice headless.
+    #[serde(default)]
+    pub to_pdf: Option<String>,
+}
+
+/// Two-hop translation of one language pair (`source -> via -> target`).
+#[derive(Clone, Debug, Deserialize, Default)]
+pub struct PivotSection {
+    /// Intermediate language code (e.g. "en").
+    pub via: String,
+
+    /// Backend for `source -> via` (default: the `[routing]` entry for that pair, else the
+    /// translate backend).
+    #[serde(default)]
+    pub first: Option<String>,
+
+    /// Backend for `via -> target` (same defaults as `first`).
+    #[serde(default)]
+    pub second: Option<String>,
+}
+
+/// Notifications at the end of a translation run.
+#[derive(Clone, Debug, Deserialize, Default)]
+pub struct HooksSection {
+    /// Shell commands or webhook URLs (`http(s)://`) run after a successful translation.
+    #[serde(default)]
+    pub on_success: Option<HookTargets>,
+
+    /// Same as `on_success`, for failed runs.
+    #[serde(default)]
+    pub on_failure: Option<HookTargets>,
+
+    /// Webhook request timeout, seconds (default 30).
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum SeedSetting {
+    Fixed(u32),
+    /// "random" (or a number written as a string).
+    Named(String),
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum StageSetting {
+    Enabled(bool),
+    /// `"flagged"`.
+    Named(String),
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum HookTargets {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl HookTargets {
+    /// Targets in run order (blank entries dropped).
+    pub fn targets(&self) -> Vec<String> {
+        let targets: Vec<&String> = match self {
+            Self::One(target) => vec![target],
+            Self::Many(targets) => targets.iter().collect(),
+        };
+        targets
+            .into_iter()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Default)]
@@ -91,7 +480,38 @@ pub struct ModelBackend {
     pub ubatch_size: Option<u32>,
     #[serde(default)]
     pub offload_kqv: Option<bool>,
-    /// Optional backend-specific prompt overrides.
+    /// Map the GGUF file instead of reading it into memory (llama.cpp default: true). With
+    /// `false` the weights are copied into RAM at load.
+    #[serde(default)]
+    pub use_mmap: Option<bool>,
+    /// Lock the weights in RAM so they are not paged out between jobs (llama.cpp default:
+    /// false). Needs a sufficient `ulimit -l`.
+    #[serde(default)]
+    pub use_mlock: Option<bool>,
+    /// Drop `<think>...</think>` (also `<thinking>`/`<reasoning>`) blocks from the model's
+    /// output before it is parsed. Default true.
+    #[serde(default)]
+    pub strip_reasoning: Option<bool>,
+    /// Extra generation tokens per call for the reasoning block of a thinking model, granted in
+    /// the controller stages (notes, fuse, stitch audit) only. Default 0.
+    #[serde(default)]
+    pub thinking_budget: Option<u32>,
+    /// Send prompts raw instead of through a chat template (completion models without one,
+    /// e.g. NLLB-style GGUFs). Default false.
+    #[serde(default)]
+    pub completion: Option<bool>,
+    /// Completion mode: prepend the BOS token (default: unless the prompt starts with one).
+    #[serde(default)]
+    pub add_bos: Option<bool>,
+    /// Completion mode: append the EOS token to the prompt. Default false.
+    #[serde(default)]
+    pub add_eos: Option<bool>,
+    /// Completion mode: strings that end generation (besides the end-of-generation token).
+    #[serde(default)]
+    pub stop: Vec<String>,
+    /// Optional backend-specific prompt overrides, for any stage the backend runs (translate
+    /// stages and their repair, or the controller stages: para_notes, fuse_ab, stitch_audit,
+    /// json_repair, patch).
     ///
     /// Example:
     /// [models.backends.hy_mt.prompts]
@@ -111,6 +531,12 @@ pub struct ResolvedBackend {
     pub batch_size: Option<u32>,
     pub ubatch_size: Option<u32>,
     pub offload_kqv: Option<bool>,
+    pub use_mmap: Option<bool>,
+    pub use_mlock: Option<bool>,
+    pub strip_reasoning: bool,
+    pub thinking_budget: u32,
+    /// Raw prompts without chat template (`completion = true`).
+    pub completion: Option<CompletionFormat>,
 }
 
 #[derive(Clone, Debug, Deserialize, Default)]
@@ -124,13 +550,18 @@ pub struct PromptsSection {
     #[serde(default)]
     pub para_notes: Option<String>,
     #[serde(default)]
-    pub json_repair: Option<String>,
+    pub key_terms: Option<String>,
     #[serde(default)]
+    pub json_repair: Option<String>,
+    /// Also accepted as `fuse` (the stage name).
+    #[serde(default, alias = "fuse")]
     pub fuse_ab: Option<String>,
     #[serde(default)]
     pub stitch_audit: Option<String>,
     #[serde(default)]
     pub patch: Option<String>,
+    #[serde(default)]
+    pub proofread: Option<String>,
 }
 
 pub fn find_default_config(workdir: &Path, filename: &str) -> Option<PathBuf> {
@@ -152,13 +583,55 @@ pub fn find_default_config(workdir: &Path, filename: &str) -> Option<PathBuf> {
     None
 }
 
-pub fn load_config(path: &Path) -> anyhow::Result<AppConfig> {
+/// Read the config at `path`; `profile` (`--profile`) merges its `[profile.<name>]` table over
+/// the rest of the file.
+pub fn load_config(path: &Path, profile: Option<&str>) -> anyhow::Result<AppConfig> {
     let text = std::fs::read_to_string(path)
         .with_context(|| format!("read config: {}", path.display()))?;
-    let cfg: AppConfig = toml::from_str(&text).context("parse config toml")?;
+    let Some(name) = profile.map(str::trim).filter(|s| !s.is_empty()) else {
+        let cfg: AppConfig = toml::from_str(&text).context("parse config toml")?;
+        return Ok(cfg);
+    };
+    let mut table: toml::Table = toml::from_str(&text).context("parse config toml")?;
+    let mut profiles = match table.remove("profile") {
+        Some(toml::Value::Table(profiles)) => profiles,
+        _ => toml::Table::new(),
+    };
+    let Some(toml::Value::Table(profile)) = profiles.remove(name) else {
+        let known: Vec<&str> = profiles.keys().map(String::as_str).collect();
+        let known = if known.is_empty() {
+            "none".to_string()
+        } else {
+            known.join(", ")
+        };
+        return Err(anyhow!(
+            "unknown profile {name:?} in {} (defined: {known})",
+            path.display()
+        ));
+    };
+    merge_toml_table(&mut table, profile);
+    let cfg: AppConfig = toml::Value::Table(table)
+        .try_into()
+        .with_context(|| format!("parse config toml (profile {name:?})"))?;
     Ok(cfg)
 }
 
+/// Merge `overlay` into `base`: tables merge key by key, any other value replaces.
+fn merge_toml_table(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match value {
+            toml::Value::Table(sub) if base.get(&key).is_some_and(toml::Value::is_table) => {
+                if let Some(toml::Value::Table(base_sub)) = base.get_mut(&key) {
+                    merge_toml_table(base_sub, sub);
+                }
+            }
+            value => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
 pub fn resolve_backend(
     cfg: &AppConfig,
     config_path: &Path,
@@ -241,6 +714,15 @@ pub fn resolve_backend(
             batch_size: b.batch_size,
             ubatch_size: b.ubatch_size,
             offload_kqv: b.offload_kqv,
+            use_mmap: b.use_mmap,
+            use_mlock: b.use_mlock,
+            strip_reasoning: b.strip_reasoning.unwrap_or(true),
+            thinking_budget: b.thinking_budget.unwrap_or(0),
+            completion: b.completion.unwrap_or(false).then(|| CompletionFormat {
+                add_bos: b.add_bos,
+                add_eos: b.add_eos.unwrap_or(false),
+                stop: b.stop.iter().filter(|s| !s.is_empty()).cloned().collect(),
+            }),
         });
     }
 
@@ -258,6 +740,11 @@ pub fn resolve_backend(
                     batch_size: None,
                     ubatch_size: None,
                     offload_kqv: None,
+                    use_mmap: None,
+                    use_mlock: None,
+                    strip_reasoning: true,
+                    thinking_budget: 0,
+                    completion: None,
                 });
             }
         }
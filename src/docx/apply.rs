@@ -3,7 +3,7 @@ use std::collections::HashMap;
 use anyhow::{anyhow, Context};
 
 use crate::docx::project::{distribute_span_text_to_nodes, project_translation_to_spans};
-use crate::docx::xml::{XmlEvent, XmlPart};
+use crate::docx::xml::{escape_attr, XmlEvent, XmlPart};
 use crate::ir::{TextNodeKind, TextNodeRef, TranslationUnit};
 
 pub fn apply_translation_unit(
@@ -15,7 +15,8 @@ pub fn apply_translation_unit(
         project_translation_to_spans(&tu.spans, &tu.frozen_surface, translated_frozen, &tu.nt_map)
             .context("project translation to spans")?;
     for span_slice in span_slices {
-        for (node_ref, node_text) in distribute_span_text_to_nodes(&span_slice.span, &span_slice.text)
+        for (node_ref, node_text) in
+            distribute_span_text_to_nodes(&span_slice.span, &span_slice.text)
         {
             let part = parts
                 .get_mut(&node_ref.part_name)
@@ -26,7 +27,11 @@ pub fn apply_translation_unit(
     Ok(())
 }
 
-pub fn apply_node_text(part: &mut XmlPart, node_ref: &TextNodeRef, node_text: &str) -> anyhow::Result<()> {
+pub fn apply_node_text(
+    part: &mut XmlPart,
+    node_ref: &TextNodeRef,
+    node_text: &str,
+) -> anyhow::Result<()> {
     match node_ref.kind {
         TextNodeKind::Attr => {
             let attr_name = node_ref
@@ -37,7 +42,7 @@ pub fn apply_node_text(part: &mut XmlPart, node_ref: &TextNodeRef, node_text: &s
                 .events
                 .get_mut(node_ref.elem_event_index)
                 .context("attr elem index out of range")?;
-            set_attr_value(ev, attr_name.as_str(), node_text);
+            set_attr_value(ev, attr_name.as_str(), &escape_attr(node_text));
             Ok(())
         }
         TextNodeKind::Wt | TextNodeKind::At => {
@@ -70,7 +75,7 @@ fn set_attr_value(ev: &mut XmlEvent, key: &str, value: &str) {
                     return;
                 }
             }
-            attrs.push((key.to_string(), value.to_string()));
+            attrs.push((key.into(), value.to_string()));
         }
         _ => {}
     }
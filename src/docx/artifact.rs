@@ -0,0 +1,120 @@
+//! Who wrote an intermediate artifact (mask, offsets, text and structure JSON): tool version,
+//! source file hash and creation time. Merge and `--continue` check it together with the
+//! schema `version`, so an artifact from another release or another document fails with a
+//! clear message instead of a slot mismatch deep inside the merge.
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+
+pub const TOOL_NAME: &str = env!("CARGO_PKG_NAME");
+pub const TOOL_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactProducer {
+    pub tool: String,
+    pub tool_version: String,
+    /// SHA-256 of the source file the artifact was extracted from.
+    pub source_sha256: String,
+    /// UTC, `YYYY-MM-DDTHH:MM:SSZ`.
+    pub created: String,
+}
+
+impl ArtifactProducer {
+    /// This build, now, for a source with hash `source_sha256`.
+    pub fn new(source_sha256: &str) -> Self {
+        let (year, month, day, hour, minute, second) = utc_now();
+        Self {
+            tool: TOOL_NAME.to_string(),
+            tool_version: TOOL_VERSION.to_string(),
+            source_sha256: source_sha256.to_string(),
+            created: format!("{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}Z"),
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!("{} {}, {}", self.tool, self.tool_version, self.created)
+    }
+}
+
+/// Schema version and producer of any artifact JSON, read before the full document so a file
+/// of another release is reported as such rather than as a parse error.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ArtifactHead {
+    pub version: u32,
+    #[serde(default)]
+    pub producer: Option<ArtifactProducer>,
+}
+
+/// Error unless this build reads schema `version` of `kind` (`supported` = oldest..=newest).
+pub fn check_schema(
+    kind: &str,
+    version: u32,
+    supported: (u32, u32),
+    producer: Option<&ArtifactProducer>,
+) -> anyhow::Result<()> {
+    let (oldest, newest) = supported;
+    if (oldest..=newest).contains(&version) {
+        return Ok(());
+    }
+    let by = producer
+        .map(|p| format!(" ({})", p.describe()))
+        .unwrap_or_default();
+    let this = format!("{TOOL_NAME} {TOOL_VERSION}");
+    if version > newest {
+        Err(anyhow!(
+            "{kind} v{version} was produced by a newer version{by}; {this} reads up to v{newest} \
+             (upgrade, or re-extract with this version)"
+        ))
+    } else {
+        Err(anyhow!(
+            "{kind} v{version} was produced by an older version{by}; {this} reads v{oldest} and \
+             later (re-extract with this version)"
+        ))
+    }
+}
+
+/// Error when two artifacts that must come from the same file record different sources.
+pub fn check_same_source(
+    (kind_a, a): (&str, Option<&ArtifactProducer>),
+    (kind_b, b): (&str, Option<&ArtifactProducer>),
+) -> anyhow::Result<()> {
+    match (a, b) {
+        (Some(a), Some(b)) if a.source_sha256 != b.source_sha256 => Err(anyhow!(
+            "{kind_a} and {kind_b} come from different source files (sha256 {} vs {}); \
+             re-extract them together",
+            short_hash(&a.source_sha256),
+            short_hash(&b.source_sha256)
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn short_hash(hash: &str) -> &str {
+    hash.get(..12).unwrap_or(hash)
+}
+
+/// Current UTC time as `(year, month, day, hour, minute, second)`.
+pub(crate) fn utc_now() -> (i64, i64, i64, u64, u64, u64) {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    utc_from_secs(secs)
+}
+
+/// `secs` since the Unix epoch as UTC `(year, month, day, hour, minute, second)`.
+pub(crate) fn utc_from_secs(secs: u64) -> (i64, i64, i64, u64, u64, u64) {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+    // Civil-from-days (proleptic Gregorian), see H. Hinnant's date algorithms.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day, rem / 3600, (rem % 3600) / 60, rem % 60)
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+use crate::docx::session::DocumentSession;
+use crate::docx::xml::{XmlEvent, XmlPart};
+
+/// Part holding the comment texts.
+pub const COMMENTS_PART: &str = "word/comments.xml";
+
+/// A Word comment and the paragraph its range starts in.
+#[derive(Clone, Debug)]
+pub struct DocComment {
+    pub id: String,
+    pub author: Option<String>,
+    /// Comment text, one line per comment paragraph.
+    pub text: String,
+    pub part_name: String,
+    /// Event index of the anchoring `w:p` start (see `PureParagraph::xml_event_index`).
+    pub para_event_index: usize,
+}
+
+/// The comments of a document with their anchors. A comment's anchor is its
+/// `w:commentRangeStart` (its `w:commentReference` without a range); a range starting between
+/// paragraphs is anchored to the next one. Comments without text or anchor are dropped.
+pub fn read_comments(session: &DocumentSession) -> Vec<DocComment> {
+    let Some(comments) = session.part(COMMENTS_PART) else {
+        return Vec::new();
+    };
+    let texts = comment_texts(comments);
+    let mut anchors: HashMap<String, (String, usize)> = HashMap::new();
+    for part in session.xml_parts() {
+        let mut paras: Vec<usize> = Vec::new();
+        // Ranges seen outside any paragraph, anchored at the next one.
+        let mut pending: Vec<String> = Vec::new();
+        for (idx, ev) in part.events.iter().enumerate() {
+            match ev {
+                XmlEvent::Start { name, .. } if name == "w:p" => {
+                    for id in pending.drain(..) {
+                        anchors.entry(id).or_insert((part.name.clone(), idx));
+                    }
+                    paras.push(idx);
+                }
+                XmlEvent::End { name } if name == "w:p" => {
+                    paras.pop();
+                }
+                XmlEvent::Start { name, attrs } | XmlEvent::Empty { name, attrs }
+                    if name == "w:commentRangeStart" || name == "w:commentReference" =>
+                {
+                    let Some((_, id)) = attrs.iter().find(|(k, _)| k == "w:id") else {
+                        continue;
+                    };
+                    match paras.first() {
+                        Some(&p) => {
+                            anchors.entry(id.clone()).or_insert((part.name.clone(), p));
+                        }
+                        None => pending.push(id.clone()),
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    let mut out: Vec<DocComment> = texts
+        .into_iter()
+        .filter(|(_, _, text)| !text.trim().is_empty())
+        .filter_map(|(id, author, text)| {
+            let (part_name, para_event_index) = anchors.get(&id)?.clone();
+            Some(DocComment {
+                id,
+                author,
+                text,
+                part_name,
+                para_event_index,
+            })
+        })
+        .collect();
+    out.sort_by(|a, b| {
+        (a.part_name.as_str(), a.para_event_index).cmp(&(b.part_name.as_str(), b.para_event_index))
+    });
+    out
+}
+
+/// `(id, author, text)` of every `w:comment`.
+fn comment_texts(part: &XmlPart) -> Vec<(String, Option<String>, String)> {
+    let mut out = Vec::new();
+    let mut cur: Option<(String, Option<String>, Vec<String>)> = None;
+    let mut in_t = false;
+    for ev in &part.events {
+        match ev {
+            XmlEvent::Start { name, attrs } if name == "w:comment" => {
+                let attr = |key: &str| attrs.iter().find(|(k, _)| k == key).map(|(_, v)| v.clone());
+                cur = Some((
+                    attr("w:id").unwrap_or_default(),
+                    attr("w:author"),
+                    Vec::new(),
+                ));
+            }
+            XmlEvent::End { name } if name == "w:comment" => {
+                if let Some((id, author, lines)) = cur.take() {
+                    out.push((id, author, lines.join("\n").trim().to_string()));
+                }
+            }
+            XmlEvent::Start { name, .. } if name == "w:p" => {
+                if let Some((_, _, lines)) = cur.as_mut() {
+                    lines.push(String::new());
+                }
+            }
+            XmlEvent::Start { name, .. } if name == "w:t" => in_t = true,
+            XmlEvent::End { name } if name == "w:t" => in_t = false,
+            XmlEvent::Text { text } | XmlEvent::CData { text } if in_t => {
+                if let Some(line) = cur.as_mut().and_then(|(_, _, lines)| lines.last_mut()) {
+                    line.push_str(text);
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
@@ -0,0 +1,854 @@
+//! Blackline comparison: `new.docx` with the differences from `old.docx` written as tracked
+//! changes (`w:ins` / `w:del`). Top-level body paragraphs of `word/document.xml` are aligned by
+//! text; paired paragraphs that only hold plain runs get a word-level diff, any other changed
+//! paragraph shows the old content deleted and the new content inserted. Everything else (tables,
+//! headers, footnotes, styles) comes from the new document unchanged.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+
+use crate::docx::package::{DocxPackage, PackageOptions};
+use crate::docx::xml::{
+    escape_attr, parse_xml_part_with, write_xml_part, ParseFix, XmlAttr, XmlEvent, XmlName, XmlPart,
+};
+
+const DOCUMENT_PART: &str = "word/document.xml";
+/// Above this many LCS cells a hunk is shown as delete-all + insert-all.
+const MAX_LCS_CELLS: usize = 4_000_000;
+
+#[derive(Clone, Debug)]
+pub struct CompareOptions {
+    /// `w:author` on every revision.
+    pub author: String,
+    /// How both documents are opened.
+    pub package: PackageOptions,
+}
+
+impl Default for CompareOptions {
+    fn default() -> Self {
+        Self {
+            author: "MuggleTranslator".to_string(),
+            package: PackageOptions::default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CompareSummary {
+    pub paragraphs_inserted: usize,
+    pub paragraphs_deleted: usize,
+    pub paragraphs_changed: usize,
+    /// Repairs `--lenient-parse` applied to either document.
+    pub parse_fixes: Vec<ParseFix>,
+}
+
+pub fn compare_docx(
+    old_docx: &Path,
+    new_docx: &Path,
+    output_docx: &Path,
+    opts: &CompareOptions,
+) -> anyhow::Result<CompareSummary> {
+    let old_pkg = DocxPackage::read_streaming_with(old_docx, &opts.package)?;
+    let (old_part, mut parse_fixes) = read_document_part(&old_pkg, old_docx)?;
+    let new_pkg = DocxPackage::read_streaming_with(new_docx, &opts.package)?;
+    let (mut new_part, new_fixes) = read_document_part(&new_pkg, new_docx)?;
+    parse_fixes.extend(new_fixes);
+
+    let (events, mut summary) = blackline_events(&old_part.events, &new_part.events, opts);
+    summary.parse_fixes = parse_fixes;
+    new_part.events = events;
+    let bytes = write_xml_part(&new_part).context("serialize compare document")?;
+    let mut replacements = HashMap::new();
+    replacements.insert(DOCUMENT_PART.to_string(), bytes);
+    new_pkg.write_with_replacements(output_docx, &replacements)?;
+    Ok(summary)
+}
+
+fn read_document_part(pkg: &DocxPackage, path: &Path) -> anyhow::Result<(XmlPart, Vec<ParseFix>)> {
+    let ent = pkg
+        .entries
+        .iter()
+        .find(|e| e.name == DOCUMENT_PART)
+        .ok_or_else(|| anyhow!("{} has no {DOCUMENT_PART}", path.display()))?;
+    parse_xml_part_with(&ent.name, &ent.data, pkg.options.lenient)
+        .with_context(|| format!("parse xml: {}", ent.name))
+}
+
+/// Top-level `w:p` of `w:body`: inclusive event range plus its plain text.
+struct BodyPara {
+    start: usize,
+    end: usize,
+    text: String,
+}
+
+fn body_paragraphs(events: &[XmlEvent]) -> Vec<BodyPara> {
+    let mut out = Vec::new();
+    let mut stack: Vec<&str> = Vec::new();
+    let mut cur: Option<(usize, usize)> = None;
+    for (i, ev) in events.iter().enumerate() {
+        match ev {
+            XmlEvent::Start { name, .. } => {
+                if cur.is_none() && name == "w:p" && stack.last() == Some(&"w:body") {
+                    cur = Some((i, stack.len()));
+                }
+                stack.push(name);
+            }
+            XmlEvent::Empty { name, .. }
+                if cur.is_none() && name == "w:p" && stack.last() == Some(&"w:body") =>
+            {
+                out.push(BodyPara {
+                    start: i,
+                    end: i,
+                    text: String::new(),
+                });
+            }
+            XmlEvent::End { .. } => {
+                let _ = stack.pop();
+                if let Some((start, depth)) = cur {
+                    if stack.len() == depth {
+                        out.push(BodyPara {
+                            start,
+                            end: i,
+                            text: paragraph_text(&events[start..=i]),
+                        });
+                        cur = None;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+fn paragraph_text(events: &[XmlEvent]) -> String {
+    let mut text = String::new();
+    let mut stack: Vec<&str> = Vec::new();
+    for ev in events {
+        match ev {
+            XmlEvent::Start { name, .. } => stack.push(name),
+            XmlEvent::End { .. } => {
+                let _ = stack.pop();
+            }
+            XmlEvent::Empty { name, .. } => match name.as_str() {
+                "w:tab" | "w:ptab" => text.push('\t'),
+                "w:br" | "w:cr" => text.push('\n'),
+                _ => {}
+            },
+            XmlEvent::Text { text: t } | XmlEvent::CData { text: t }
+                if stack.last() == Some(&"w:t") =>
+            {
+                text.push_str(t);
+            }
+            _ => {}
+        }
+    }
+    text
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// LCS diff with common prefix/suffix trimmed first (most edits are local).
+fn diff<T: PartialEq>(a: &[T], b: &[T]) -> Vec<Op> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+    let (n, m) = (a_mid.len(), b_mid.len());
+
+    let mut ops: Vec<Op> = (0..prefix).map(|i| Op::Equal(i, i)).collect();
+    if (n + 1).saturating_mul(m + 1) > MAX_LCS_CELLS {
+        ops.extend((0..n).map(|i| Op::Delete(prefix + i)));
+        ops.extend((0..m).map(|j| Op::Insert(prefix + j)));
+    } else {
+        // lcs[i][j] = LCS length of a_mid[i..] and b_mid[j..].
+        let w = m + 1;
+        let mut lcs = vec![0u32; (n + 1) * w];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lcs[i * w + j] = if a_mid[i] == b_mid[j] {
+                    lcs[(i + 1) * w + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * w + j].max(lcs[i * w + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            if i < n && j < m && a_mid[i] == b_mid[j] {
+                ops.push(Op::Equal(prefix + i, prefix + j));
+                i += 1;
+                j += 1;
+            } else if j == m || (i < n && lcs[(i + 1) * w + j] >= lcs[i * w + j + 1]) {
+                ops.push(Op::Delete(prefix + i));
+                i += 1;
+            } else {
+                ops.push(Op::Insert(prefix + j));
+                j += 1;
+            }
+        }
+    }
+    ops.extend((0..suffix).map(|k| Op::Equal(a.len() - suffix + k, b.len() - suffix + k)));
+    ops
+}
+
+enum ParaOp {
+    Same(usize),
+    Changed(usize, usize),
+    Deleted(usize),
+    Inserted(usize),
+}
+
+/// Within each hunk, pairs a deleted paragraph with the next similar inserted one (keeping
+/// order) as a changed paragraph.
+fn paragraph_ops(old: &[BodyPara], new: &[BodyPara]) -> Vec<ParaOp> {
+    let old_text: Vec<&str> = old.iter().map(|p| p.text.as_str()).collect();
+    let new_text: Vec<&str> = new.iter().map(|p| p.text.as_str()).collect();
+    let mut out = Vec::new();
+    let mut dels = Vec::new();
+    let mut ins = Vec::new();
+    let flush = |out: &mut Vec<ParaOp>, dels: &mut Vec<usize>, ins: &mut Vec<usize>| {
+        let mut next_ins = 0usize;
+        for &i in dels.iter() {
+            let found = ins[next_ins..]
+                .iter()
+                .position(|&j| similar(old_text[i], new_text[j]));
+            match found {
+                Some(k) => {
+                    out.extend(
+                        ins[next_ins..next_ins + k]
+                            .iter()
+                            .map(|&j| ParaOp::Inserted(j)),
+                    );
+                    out.push(ParaOp::Changed(i, ins[next_ins + k]));
+                    next_ins += k + 1;
+                }
+                None => out.push(ParaOp::Deleted(i)),
+            }
+        }
+        out.extend(ins[next_ins..].iter().map(|&j| ParaOp::Inserted(j)));
+        dels.clear();
+        ins.clear();
+    };
+    for op in diff(&old_text, &new_text) {
+        match op {
+            Op::Equal(_, j) => {
+                flush(&mut out, &mut dels, &mut ins);
+                out.push(ParaOp::Same(j));
+            }
+            Op::Delete(i) => dels.push(i),
+            Op::Insert(j) => ins.push(j),
+        }
+    }
+    flush(&mut out, &mut dels, &mut ins);
+    out
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Revision {
+    Ins,
+    Del,
+}
+
+struct Writer<'a> {
+    out: Vec<XmlEvent>,
+    next_id: u64,
+    author: &'a str,
+}
+
+impl Writer<'_> {
+    fn revision_attrs(&mut self) -> Vec<XmlAttr> {
+        let id = self.next_id;
+        self.next_id += 1;
+        vec![
+            ("w:id".into(), id.to_string()),
+            ("w:author".into(), escape_attr(self.author)),
+        ]
+    }
+
+    fn revision_start(&mut self, rev: Revision) {
+        let attrs = self.revision_attrs();
+        self.out.push(XmlEvent::Start {
+            name: rev.tag(),
+            attrs,
+        });
+    }
+
+    fn revision_end(&mut self, rev: Revision) {
+        self.out.push(XmlEvent::End { name: rev.tag() });
+    }
+
+    /// `<w:rPr><w:ins/></w:rPr>` marker for the paragraph mark.
+    fn mark_event(&mut self, rev: Revision) -> XmlEvent {
+        XmlEvent::Empty {
+            name: rev.tag(),
+            attrs: self.revision_attrs(),
+        }
+    }
+
+    /// Copies a paragraph's `w:pPr` (or writes one) with the paragraph mark flagged as `rev`.
+    fn paragraph_props(&mut self, para: &[XmlEvent], rev: Option<Revision>) {
+        let Some((start, end)) = child_range(para, "w:pPr") else {
+            if let Some(rev) = rev {
+                let mark = self.mark_event(rev);
+                self.out.push(start_tag("w:pPr"));
+                self.out.push(start_tag("w:rPr"));
+                self.out.push(mark);
+                self.out.push(end_tag("w:rPr"));
+                self.out.push(end_tag("w:pPr"));
+            }
+            return;
+        };
+        let Some(rev) = rev else {
+            self.out.extend_from_slice(&para[start..=end]);
+            return;
+        };
+        let mark = self.mark_event(rev);
+        if let XmlEvent::Empty { attrs, .. } = &para[start] {
+            self.out.push(XmlEvent::Start {
+                name: "w:pPr".into(),
+                attrs: attrs.clone(),
+            });
+            self.push_mark_rpr(&mark);
+            self.out.push(end_tag("w:pPr"));
+            return;
+        }
+        let ppr = &para[start..=end];
+        let mut depth = 0usize;
+        let mut placed = false;
+        for (k, ev) in ppr.iter().enumerate() {
+            // Direct children of w:pPr sit at depth 1.
+            let at_child_level = depth == 1;
+            match ev {
+                XmlEvent::Empty { name, .. } if at_child_level && name == "w:rPr" && !placed => {
+                    self.out.push(start_tag("w:rPr"));
+                    self.out.push(mark.clone());
+                    self.out.push(end_tag("w:rPr"));
+                    placed = true;
+                    continue;
+                }
+                XmlEvent::Start { name, .. } if at_child_level && name == "w:rPr" && !placed => {
+                    self.out.push(ev.clone());
+                    self.out.push(mark.clone());
+                    placed = true;
+                    depth += 1;
+                    continue;
+                }
+                XmlEvent::Start { name, .. } | XmlEvent::Empty { name, .. }
+                    if at_child_level
+                        && !placed
+                        && matches!(name.as_str(), "w:sectPr" | "w:pPrChange") =>
+                {
+                    self.push_mark_rpr(&mark);
+                    placed = true;
+                }
+                XmlEvent::End { .. } if k + 1 == ppr.len() && !placed => {
+                    self.push_mark_rpr(&mark);
+                    placed = true;
+                }
+                _ => {}
+            }
+            match ev {
+                XmlEvent::Start { .. } => depth += 1,
+                XmlEvent::End { .. } => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            self.out.push(ev.clone());
+        }
+    }
+
+    fn push_mark_rpr(&mut self, mark: &XmlEvent) {
+        self.out.push(start_tag("w:rPr"));
+        self.out.push(mark.clone());
+        self.out.push(end_tag("w:rPr"));
+    }
+
+    /// Paragraph content (everything but `w:pPr`) with each top-level run wrapped in `rev`.
+    /// Deleted content drops bookmark / comment / permission range markers: their ids and names
+    /// belong to the old document and would clash with the new one.
+    fn revised_content(&mut self, para: &[XmlEvent], rev: Revision) {
+        let ppr = child_range(para, "w:pPr");
+        let inner = match para.first() {
+            Some(XmlEvent::Start { .. }) if para.len() >= 2 => &para[1..para.len() - 1],
+            _ => return,
+        };
+        let mut stack: Vec<&str> = Vec::new();
+        for (k, ev) in inner.iter().enumerate() {
+            let idx = k + 1;
+            if ppr.is_some_and(|(s, e)| idx >= s && idx <= e) {
+                continue;
+            }
+            let in_revision = stack
+                .iter()
+                .any(|n| matches!(*n, "w:ins" | "w:del" | "w:r"));
+            match ev {
+                XmlEvent::Start { name, attrs } => {
+                    if name == "w:r" && !in_revision {
+                        self.revision_start(rev);
+                    }
+                    stack.push(name);
+                    self.out.push(XmlEvent::Start {
+                        name: rev.rename(name),
+                        attrs: attrs.clone(),
+                    });
+                }
+                XmlEvent::End { name } => {
+                    let _ = stack.pop();
+                    self.out.push(XmlEvent::End {
+                        name: rev.rename(name),
+                    });
+                    let still_in = stack
+                        .iter()
+                        .any(|n| matches!(*n, "w:ins" | "w:del" | "w:r"));
+                    if name == "w:r" && !still_in {
+                        self.revision_end(rev);
+                    }
+                }
+                XmlEvent::Empty { name, .. } if rev == Revision::Del && is_range_marker(name) => {}
+                XmlEvent::Empty { name, attrs } => {
+                    let wrap = name == "w:r" && !in_revision;
+                    if wrap {
+                        self.revision_start(rev);
+                    }
+                    self.out.push(XmlEvent::Empty {
+                        name: rev.rename(name),
+                        attrs: attrs.clone(),
+                    });
+                    if wrap {
+                        self.revision_end(rev);
+                    }
+                }
+                other => self.out.push(other.clone()),
+            }
+        }
+    }
+
+    fn revised_paragraph(&mut self, para: &[XmlEvent], rev: Revision) {
+        self.out.push(paragraph_start(para));
+        self.paragraph_props(para, Some(rev));
+        self.revised_content(para, rev);
+        self.out.push(end_tag("w:p"));
+    }
+
+    fn changed_paragraph(&mut self, old: &[XmlEvent], new: &[XmlEvent]) {
+        self.out.push(paragraph_start(new));
+        self.paragraph_props(new, None);
+        match (plain_segments(old), plain_segments(new)) {
+            (Some(old_segs), Some(new_segs)) => self.word_diff(&old_segs, &new_segs),
+            _ => {
+                self.revised_content(old, Revision::Del);
+                self.revised_content(new, Revision::Ins);
+            }
+        }
+        self.out.push(end_tag("w:p"));
+    }
+
+    fn word_diff(&mut self, old: &[Segment], new: &[Segment]) {
+        let old_tokens = tokens(old);
+        let new_tokens = tokens(new);
+        let old_words: Vec<&str> = old_tokens.iter().map(|t| t.text.as_str()).collect();
+        let new_words: Vec<&str> = new_tokens.iter().map(|t| t.text.as_str()).collect();
+
+        // Consecutive tokens with the same revision and run properties share one run.
+        let mut pending: Option<(Option<Revision>, usize, bool, String)> = None;
+        for op in diff(&old_words, &new_words) {
+            let (rev, tok, from_old) = match op {
+                Op::Equal(_, j) => (None, &new_tokens[j], false),
+                Op::Delete(i) => (Some(Revision::Del), &old_tokens[i], true),
+                Op::Insert(j) => (Some(Revision::Ins), &new_tokens[j], false),
+            };
+            match pending.as_mut() {
+                Some((r, seg, o, text)) if *r == rev && *seg == tok.segment && *o == from_old => {
+                    text.push_str(&tok.text)
+                }
+                _ => {
+                    if let Some((r, seg, o, text)) = pending.take() {
+                        let rpr = if o { &old[seg].rpr } else { &new[seg].rpr };
+                        self.text_run(r, rpr, &text);
+                    }
+                    pending = Some((rev, tok.segment, from_old, tok.text.clone()));
+                }
+            }
+        }
+        if let Some((r, seg, o, text)) = pending {
+            let rpr = if o { &old[seg].rpr } else { &new[seg].rpr };
+            self.text_run(r, rpr, &text);
+        }
+    }
+
+    fn text_run(&mut self, rev: Option<Revision>, rpr: &[XmlEvent], text: &str) {
+        if let Some(rev) = rev {
+            self.revision_start(rev);
+        }
+        let text_tag = if rev == Some(Revision::Del) {
+            "w:delText"
+        } else {
+            "w:t"
+        };
+        self.out.push(start_tag("w:r"));
+        self.out.extend_from_slice(rpr);
+        let mut buf = String::new();
+        let flush = |out: &mut Vec<XmlEvent>, buf: &mut String| {
+            if buf.is_empty() {
+                return;
+            }
+            out.push(XmlEvent::Start {
+                name: text_tag.into(),
+                attrs: vec![("xml:space".into(), "preserve".to_string())],
+            });
+            out.push(XmlEvent::Text {
+                text: std::mem::take(buf),
+            });
+            out.push(end_tag(text_tag));
+        };
+        for ch in text.chars() {
+            match ch {
+                '\t' | '\n' => {
+                    flush(&mut self.out, &mut buf);
+                    let tag = if ch == '\t' { "w:tab" } else { "w:br" };
+                    self.out.push(XmlEvent::Empty {
+                        name: tag.into(),
+                        attrs: Vec::new(),
+                    });
+                }
+                _ => buf.push(ch),
+            }
+        }
+        flush(&mut self.out, &mut buf);
+        self.out.push(end_tag("w:r"));
+        if let Some(rev) = rev {
+            self.revision_end(rev);
+        }
+    }
+}
+
+impl Revision {
+    fn tag(self) -> XmlName {
+        match self {
+            Revision::Ins => "w:ins".into(),
+            Revision::Del => "w:del".into(),
+        }
+    }
+
+    /// Deleted text must use the `del*` element names.
+    fn rename(self, name: &XmlName) -> XmlName {
+        match (self, name.as_str()) {
+            (Revision::Del, "w:t") => "w:delText".into(),
+            (Revision::Del, "w:instrText") => "w:delInstrText".into(),
+            _ => name.clone(),
+        }
+    }
+}
+
+fn is_range_marker(name: &str) -> bool {
+    matches!(
+        name,
+        "w:bookmarkStart"
+            | "w:bookmarkEnd"
+            | "w:commentRangeStart"
+            | "w:commentRangeEnd"
+            | "w:permStart"
+            | "w:permEnd"
+    )
+}
+
+/// `<w:p ...>` with the attributes of `para` (which may be an empty `<w:p/>`).
+fn paragraph_start(para: &[XmlEvent]) -> XmlEvent {
+    let attrs = match para.first() {
+        Some(XmlEvent::Start { attrs, .. } | XmlEvent::Empty { attrs, .. }) => attrs.clone(),
+        _ => Vec::new(),
+    };
+    XmlEvent::Start {
+        name: "w:p".into(),
+        attrs,
+    }
+}
+
+fn start_tag(name: &str) -> XmlEvent {
+    XmlEvent::Start {
+        name: name.into(),
+        attrs: Vec::new(),
+    }
+}
+
+fn end_tag(name: &str) -> XmlEvent {
+    XmlEvent::End { name: name.into() }
+}
+
+/// Inclusive range of the first direct child `name` of the paragraph `para`.
+fn child_range(para: &[XmlEvent], name: &str) -> Option<(usize, usize)> {
+    let mut depth = 0usize;
+    let mut start = None;
+    for (i, ev) in para.iter().enumerate() {
+        match ev {
+            XmlEvent::Start { name: n, .. } => {
+                if depth == 1 && n == name && start.is_none() {
+                    start = Some(i);
+                }
+                depth += 1;
+            }
+            XmlEvent::End { .. } => {
+                depth = depth.saturating_sub(1);
+                if depth == 1 {
+                    if let Some(s) = start {
+                        return Some((s, i));
+                    }
+                }
+            }
+            XmlEvent::Empty { name: n, .. } if depth == 1 && n == name => return Some((i, i)),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Text of one run with its `w:rPr` events.
+struct Segment {
+    rpr: Vec<XmlEvent>,
+    text: String,
+}
+
+/// Runs of a paragraph that holds nothing but plain text runs (anything else: `None`).
+fn plain_segments(para: &[XmlEvent]) -> Option<Vec<Segment>> {
+    let ppr = child_range(para, "w:pPr");
+    let inner = match para.first() {
+        Some(XmlEvent::Start { .. }) => &para[1..para.len() - 1],
+        _ => return Some(Vec::new()),
+    };
+    let mut segs = Vec::new();
+    let mut k = 0;
+    while k < inner.len() {
+        let idx = k + 1;
+        if let Some((_, e)) = ppr.filter(|(s, _)| *s == idx) {
+            k = e;
+            continue;
+        }
+        match &inner[k] {
+            XmlEvent::Start { name, .. } if name == "w:r" => {
+                let mut depth = 0usize;
+                let mut end = k;
+                for (off, ev) in inner[k..].iter().enumerate() {
+                    match ev {
+                        XmlEvent::Start { .. } => depth += 1,
+                        XmlEvent::End { .. } => {
+                            depth -= 1;
+                            if depth == 0 {
+                                end = k + off;
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                segs.push(plain_run(&inner[k..=end])?);
+                k = end + 1;
+            }
+            XmlEvent::Empty { name, .. } if matches!(name.as_str(), "w:r" | "w:proofErr") => {
+                k += 1;
+            }
+            _ => return None,
+        }
+    }
+    Some(segs)
+}
+
+fn plain_run(run: &[XmlEvent]) -> Option<Segment> {
+    let mut seg = Segment {
+        rpr: Vec::new(),
+        text: String::new(),
+    };
+    let inner = &run[1..run.len() - 1];
+    let mut k = 0;
+    while k < inner.len() {
+        match &inner[k] {
+            XmlEvent::Start { name, .. } if name == "w:rPr" => {
+                let end = inner[k..]
+                    .iter()
+                    .position(|ev| matches!(ev, XmlEvent::End { name } if name == "w:rPr"))?;
+                seg.rpr.extend_from_slice(&inner[k..=k + end]);
+                k += end + 1;
+            }
+            XmlEvent::Empty { name, .. } if name == "w:rPr" => {
+                seg.rpr.push(inner[k].clone());
+                k += 1;
+            }
+            XmlEvent::Start { name, .. } if name == "w:t" => {
+                k += 1;
+                loop {
+                    match inner.get(k)? {
+                        XmlEvent::Text { text } => seg.text.push_str(text),
+                        XmlEvent::End { .. } => break,
+                        _ => return None,
+                    }
+                    k += 1;
+                }
+                k += 1;
+            }
+            XmlEvent::Empty { name, .. } => {
+                match name.as_str() {
+                    "w:tab" => seg.text.push('\t'),
+                    "w:br" | "w:cr" => seg.text.push('\n'),
+                    "w:t" | "w:lastRenderedPageBreak" => {}
+                    _ => return None,
+                }
+                k += 1;
+            }
+            _ => return None,
+        }
+    }
+    Some(seg)
+}
+
+struct Token {
+    text: String,
+    /// Index of the run (segment) the token starts in.
+    segment: usize,
+}
+
+/// Words, whitespace runs and single other characters (punctuation, CJK), so a diff never
+/// splits a Latin word but still works for scripts without spaces. Yields byte offsets.
+fn split_words(text: &str) -> Vec<(usize, &str)> {
+    #[derive(PartialEq)]
+    enum Class {
+        Word,
+        Space,
+        Other,
+    }
+    fn class(ch: char) -> Class {
+        if ch.is_whitespace() {
+            Class::Space
+        } else if ch.is_alphanumeric() && (ch as u32) < 0x2E80 {
+            Class::Word
+        } else {
+            Class::Other
+        }
+    }
+    let mut out: Vec<(usize, &str)> = Vec::new();
+    let mut start = 0usize;
+    let mut prev: Option<Class> = None;
+    for (i, ch) in text.char_indices() {
+        let c = class(ch);
+        let joins = matches!(c, Class::Word | Class::Space) && prev.as_ref() == Some(&c);
+        if !joins && i > start {
+            out.push((start, &text[start..i]));
+            start = i;
+        }
+        prev = Some(c);
+    }
+    if start < text.len() {
+        out.push((start, &text[start..]));
+    }
+    out
+}
+
+fn tokens(segs: &[Segment]) -> Vec<Token> {
+    let mut text = String::new();
+    let mut seg_starts = Vec::with_capacity(segs.len());
+    for seg in segs {
+        seg_starts.push(text.len());
+        text.push_str(&seg.text);
+    }
+    split_words(&text)
+        .into_iter()
+        .map(|(at, word)| Token {
+            text: word.to_string(),
+            segment: seg_starts.partition_point(|&s| s <= at).saturating_sub(1),
+        })
+        .collect()
+}
+
+/// Paragraphs sharing at least half of their tokens are shown as one edited paragraph;
+/// anything less is a deletion plus an insertion.
+fn similar(a: &str, b: &str) -> bool {
+    let a_words = split_words(a);
+    let b_words = split_words(b);
+    if a_words.is_empty() || b_words.is_empty() {
+        return a_words.is_empty() && b_words.is_empty();
+    }
+    let mut counts: HashMap<&str, i32> = HashMap::new();
+    for (_, w) in &a_words {
+        *counts.entry(w).or_default() += 1;
+    }
+    let mut common = 0usize;
+    for (_, w) in &b_words {
+        if let Some(c) = counts.get_mut(w).filter(|c| **c > 0) {
+            *c -= 1;
+            common += 1;
+        }
+    }
+    common * 4 >= a_words.len() + b_words.len()
+}
+
+fn max_numeric_id(events: &[XmlEvent]) -> u64 {
+    events
+        .iter()
+        .filter_map(|ev| match ev {
+            XmlEvent::Start { attrs, .. } | XmlEvent::Empty { attrs, .. } => Some(attrs),
+            _ => None,
+        })
+        .flatten()
+        .filter(|(k, _)| k == "w:id")
+        .filter_map(|(_, v)| v.parse::<u64>().ok())
+        .max()
+        .unwrap_or(0)
+}
+
+fn blackline_events(
+    old: &[XmlEvent],
+    new: &[XmlEvent],
+    opts: &CompareOptions,
+) -> (Vec<XmlEvent>, CompareSummary) {
+    let old_paras = body_paragraphs(old);
+    let new_paras = body_paragraphs(new);
+    let mut w = Writer {
+        out: Vec::with_capacity(new.len()),
+        next_id: max_numeric_id(new).max(max_numeric_id(old)) + 1,
+        author: &opts.author,
+    };
+    let mut summary = CompareSummary::default();
+    let mut copied = 0usize;
+    for op in paragraph_ops(&old_paras, &new_paras) {
+        let new_idx = match op {
+            ParaOp::Same(j) | ParaOp::Changed(_, j) | ParaOp::Inserted(j) => Some(j),
+            ParaOp::Deleted(_) => None,
+        };
+        if let Some(j) = new_idx {
+            w.out.extend_from_slice(&new[copied..new_paras[j].start]);
+            copied = new_paras[j].end + 1;
+        }
+        match op {
+            ParaOp::Same(j) => {
+                let p = &new_paras[j];
+                w.out.extend_from_slice(&new[p.start..=p.end]);
+            }
+            ParaOp::Changed(i, j) => {
+                let (o, n) = (&old_paras[i], &new_paras[j]);
+                w.changed_paragraph(&old[o.start..=o.end], &new[n.start..=n.end]);
+                summary.paragraphs_changed += 1;
+            }
+            ParaOp::Inserted(j) => {
+                let p = &new_paras[j];
+                w.revised_paragraph(&new[p.start..=p.end], Revision::Ins);
+                summary.paragraphs_inserted += 1;
+            }
+            ParaOp::Deleted(i) => {
+                let p = &old_paras[i];
+                w.revised_paragraph(&old[p.start..=p.end], Revision::Del);
+                summary.paragraphs_deleted += 1;
+            }
+        }
+    }
+    w.out.extend_from_slice(&new[copied..]);
+    (w.out, summary)
+}
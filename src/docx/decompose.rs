@@ -1,19 +1,29 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::fs::File;
-use std::io::Write;
+use std::io::{self, BufReader, BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, Context};
 use base64::engine::general_purpose::STANDARD as B64;
 use base64::Engine;
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use zip::{CompressionMethod, DateTime};
 
-use crate::docx::package::{DocxEntry, DocxPackage};
-use crate::docx::pure_text::PureTextJson;
-use crate::docx::xml::{full_hash, parse_xml_part, write_xml_part, XmlEvent, XmlPart};
+use crate::docx::artifact::{check_same_source, check_schema, ArtifactProducer};
+use crate::docx::fields::{set_update_fields, SETTINGS_PART};
+use crate::docx::highlight::{enclosing_run_text, highlight_run_text, DEFAULT_HIGHLIGHT};
+use crate::docx::links::{rewrite_rels_targets, LinkRewrite};
+use crate::docx::package::{DocxEntry, DocxPackage, PackageOptions};
+use crate::docx::pure_text::PURE_TEXT_VERSION;
+use crate::docx::sanitize::is_xml_char;
+use crate::docx::session::DocumentSession;
+use crate::docx::xml::{
+    escape_attr, first_event_difference, full_hash, parse_xml_part, parse_xml_part_with,
+    text_attr_key, unescape_attr, write_xml_part, EventDiff, XmlAttr, XmlEvent, XmlName, XmlPart,
+};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -30,11 +40,58 @@ pub struct TextSlot {
     pub kind: SlotKind,
     pub event_index: usize,
     pub attr_name: Option<String>,
+    /// Structural anchor of the slot's event (offsets v2, see `event_anchors`), used when the
+    /// masked XML was rewritten and `event_index` no longer points at the placeholder.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anchor: Option<String>,
+    /// Slot text at extraction time; `--merge-lenient` falls back to it for bad slots.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_text: Option<String>,
 }
 
+/// Merge input: a full text.json (`slot_texts` array), or a sparse `{"<slot_id>": text}` map
+/// that leaves every other slot at its extracted text.
+#[derive(Clone, Debug, Deserialize)]
+pub struct MergeTextJson {
+    /// Absent in hand-written sparse maps.
+    #[serde(default)]
+    pub version: Option<u32>,
+    #[serde(default)]
+    pub producer: Option<ArtifactProducer>,
+    pub placeholder_prefix: String,
+    pub slot_texts: SlotTexts,
+    /// Text slots whose run gets a highlight in the merged document.
+    #[serde(default)]
+    pub highlight_slots: Vec<usize>,
+    /// `w:highlight` color for `highlight_slots` (default yellow).
+    #[serde(default)]
+    pub highlight_color: Option<String>,
+    /// Text slots removed from the merged document, with their run when nothing else is left in
+    /// it.
+    #[serde(default)]
+    pub collapsed_slots: Vec<usize>,
+    /// Set `w:updateFields` in the settings part (Word refreshes the fields on open).
+    #[serde(default)]
+    pub update_fields: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum SlotTexts {
+    Full(Vec<String>),
+    Sparse(BTreeMap<String, String>),
+}
+
+/// Offsets format written by extraction: v2 adds `TextSlot::anchor`; v1 files still merge.
+pub const OFFSETS_VERSION: u32 = 2;
+/// Mask format written by extraction (external blobs); merge reads no other.
+pub const MASK_VERSION: u32 = 2;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct OffsetsJson {
     pub version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub producer: Option<ArtifactProducer>,
     pub placeholder_prefix: String,
     pub slots: Vec<TextSlot>,
 }
@@ -63,31 +120,159 @@ pub struct MaskEntryJson {
     pub unix_mode: Option<u32>,
     pub is_dir: bool,
     pub data: MaskEntryData,
+    /// Original XML bytes (`--strict-roundtrip`), merged back verbatim when no slot changed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original: Option<MaskBlobRef>,
+    /// Zip extra fields (base64), see `DocxEntry::extra_data`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra_data: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub large_file: bool,
+}
+
+/// Compression of the whole mask blobs file; blob offsets always refer to the decompressed bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlobCompression {
+    #[default]
+    None,
+    Zstd,
+}
+
+impl BlobCompression {
+    fn is_none(&self) -> bool {
+        *self == BlobCompression::None
+    }
+}
+
+/// How a mask is extracted.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MaskOptions {
+    pub compression: BlobCompression,
+    /// Also store the original bytes of XML parts that have slots, so parts whose text comes
+    /// back unchanged merge byte-identical. Off by default: it puts document text in the blobs.
+    pub keep_originals: bool,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct MaskJson {
     pub version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub producer: Option<ArtifactProducer>,
     pub placeholder_prefix: String,
     pub blobs_file: Option<String>,
+    #[serde(default, skip_serializing_if = "BlobCompression::is_none")]
+    pub blobs_compression: BlobCompression,
+    /// Zip archive comment (base64).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
     pub entries: Vec<MaskEntryJson>,
 }
 
+/// Extracted mask (blobs already streamed out); `mask.blobs_file` is left for the writer.
+pub(crate) struct MaskParts {
+    pub mask: MaskJson,
+    pub offsets: OffsetsJson,
+}
+
+/// Appends entry bytes to the blobs stream, hashing on the way through.
+struct BlobSink<'a> {
+    out: &'a mut dyn Write,
+    offset: u64,
+}
+
+impl BlobSink<'_> {
+    fn append(&mut self, data: &mut dyn Read) -> anyhow::Result<MaskEntryData> {
+        let mut hasher = Sha256::new();
+        let mut buf = vec![0u8; 64 * 1024];
+        let mut len: u64 = 0;
+        loop {
+            let n = data.read(&mut buf).context("read entry data")?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+            self.out.write_all(&buf[..n]).context("write mask blobs")?;
+            len += n as u64;
+        }
+        if len == 0 {
+            return Ok(MaskEntryData::Empty);
+        }
+        let r = MaskBlobRef {
+            offset: self.offset,
+            length: len,
+            sha256: hex::encode(hasher.finalize()),
+        };
+        self.offset = self.offset.saturating_add(len);
+        Ok(MaskEntryData::External(r))
+    }
+}
+
 pub struct MaskOutputs {
     pub mask_json_path: PathBuf,
     pub offsets_json_path: PathBuf,
     pub blobs_bin_path: PathBuf,
 }
 
-fn hash_file_prefix(path: &Path) -> anyhow::Result<String> {
-    let bytes = fs::read(path).with_context(|| format!("read file: {}", path.display()))?;
+/// How the `__MT_MASK_<prefix>_` placeholder prefix of a document is chosen.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum PlaceholderPrefix {
+    /// Hash of the input file bytes: re-saving the same document changes it.
+    #[default]
+    FileHash,
+    /// Hash of the document content without editor noise (rsids, paragraph ids, document
+    /// properties), so artifacts of a regenerated copy stay interchangeable.
+    Content,
+    /// Given by the user, e.g. one prefix for every revision of a document.
+    Fixed(String),
+}
+
+impl PlaceholderPrefix {
+    /// `hash`, `content`, or a fixed prefix of 1..=32 ASCII letters/digits.
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        let value = value.trim();
+        match value {
+            "hash" => Ok(Self::FileHash),
+            "content" => Ok(Self::Content),
+            _ if !value.is_empty()
+                && value.len() <= 32
+                && value.bytes().all(|b| b.is_ascii_alphanumeric()) =>
+            {
+                Ok(Self::Fixed(value.to_string()))
+            }
+            _ => Err(anyhow!(
+                "invalid placeholder prefix {value:?}: expected hash, content or 1-32 ASCII \
+                 letters/digits"
+            )),
+        }
+    }
+}
+
+/// Prefix for a file without package structure (HTML), where content and file hash coincide.
+pub(crate) fn file_placeholder_prefix(
+    path: &Path,
+    mode: &PlaceholderPrefix,
+) -> anyhow::Result<String> {
+    match mode {
+        PlaceholderPrefix::Fixed(prefix) => Ok(prefix.clone()),
+        PlaceholderPrefix::FileHash | PlaceholderPrefix::Content => hash_file_prefix(path),
+    }
+}
+
+pub(crate) fn hash_file_prefix(path: &Path) -> anyhow::Result<String> {
+    Ok(hash_file(path)?.chars().take(10).collect())
+}
+
+/// SHA-256 (hex) of the file at `path`, read in buffered chunks rather than all at once.
+pub(crate) fn hash_file(path: &Path) -> anyhow::Result<String> {
+    let file = File::open(path).with_context(|| format!("read file: {}", path.display()))?;
     let mut hasher = Sha256::new();
-    hasher.update(&bytes);
-    let hex = hex::encode(hasher.finalize());
-    Ok(hex.chars().take(10).collect())
+    io::copy(&mut BufReader::new(file), &mut hasher)
+        .with_context(|| format!("read file: {}", path.display()))?;
+    Ok(hex::encode(hasher.finalize()))
 }
 
-fn placeholder(prefix: &str, id: usize) -> String {
+pub(crate) fn placeholder(prefix: &str, id: usize) -> String {
     format!("__MT_MASK_{prefix}_{id:08}__")
 }
 
@@ -96,14 +281,59 @@ fn is_placeholder(s: &str, prefix: &str) -> bool {
     s.starts_with(&pfx) && s.ends_with("__") && s.len() >= pfx.len() + 8 + 2
 }
 
-fn find_attr_mut<'a>(attrs: &'a mut Vec<(String, String)>, key: &str) -> Option<&'a mut String> {
-    attrs
-        .iter_mut()
-        .find(|(k, _)| k == key)
-        .map(|(_, v)| v)
+fn find_attr_mut<'a>(attrs: &'a mut Vec<XmlAttr>, key: &str) -> Option<&'a mut String> {
+    attrs.iter_mut().find(|(k, _)| k == key).map(|(_, v)| v)
+}
+
+/// Index of the `End` event closing the element that starts at `start`.
+fn element_end(part: &XmlPart, start: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    for i in start + 1..part.events.len() {
+        match &part.events[i] {
+            XmlEvent::Start { .. } => depth += 1,
+            XmlEvent::End { .. } if depth == 0 => return Some(i),
+            XmlEvent::End { .. } => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Remove the `w:t` around the text event at `text_idx`, and its `w:r` when only run properties
+/// are left in it. Returns whether anything was removed.
+fn drop_run_text(part: &mut XmlPart, text_idx: usize) -> bool {
+    let Some((t_idx, r_idx)) = enclosing_run_text(part, text_idx) else {
+        return false;
+    };
+    let Some(t_end) = element_end(part, t_idx) else {
+        return false;
+    };
+    part.events.drain(t_idx..=t_end);
+    let Some(r_end) = element_end(part, r_idx) else {
+        return true;
+    };
+    let mut depth = 0usize;
+    let mut has_content = false;
+    for ev in &part.events[r_idx + 1..r_end] {
+        match ev {
+            XmlEvent::Start { name, .. } => {
+                has_content |= depth == 0 && name != "w:rPr";
+                depth += 1;
+            }
+            XmlEvent::End { .. } => depth = depth.saturating_sub(1),
+            XmlEvent::Empty { name, .. } => has_content |= depth == 0 && name != "w:rPr",
+            XmlEvent::Text { text } => has_content |= depth == 0 && !text.trim().is_empty(),
+            _ => has_content |= depth == 0,
+        }
+    }
+    if !has_content {
+        part.events.drain(r_idx..=r_end);
+    }
+    true
 }
 
 fn verify_part_mask_pure(part: &XmlPart, prefix: &str) -> anyhow::Result<()> {
+    let mut stack: Vec<XmlName> = Vec::new();
     for ev in &part.events {
         match ev {
             XmlEvent::Text { text } | XmlEvent::CData { text } => {
@@ -116,11 +346,11 @@ fn verify_part_mask_pure(part: &XmlPart, prefix: &str) -> anyhow::Result<()> {
                 }
             }
             XmlEvent::Start { name, attrs } | XmlEvent::Empty { name, attrs } => {
-                if name == "w:lvlText" {
-                    if let Some(v) = attrs.iter().find(|(k, _)| k == "w:val").map(|(_, v)| v) {
+                if let Some(key) = text_attr_key(name, stack.last().map(XmlName::as_str), attrs) {
+                    if let Some(v) = attrs.iter().find(|(k, _)| k == key).map(|(_, v)| v) {
                         if !is_placeholder(v, prefix) {
                             return Err(anyhow!(
-                                "mask not pure: found non-placeholder w:lvlText@w:val in {}: {:?}",
+                                "mask not pure: found non-placeholder {name}@{key} in {}: {:?}",
                                 part.name,
                                 v
                             ));
@@ -130,6 +360,7 @@ fn verify_part_mask_pure(part: &XmlPart, prefix: &str) -> anyhow::Result<()> {
             }
             _ => {}
         }
+        track_parent(&mut stack, ev);
     }
     Ok(())
 }
@@ -179,6 +410,15 @@ fn resolve_blobs_path(mask_json: &Path, blobs_file: &str) -> anyhow::Result<Path
     Ok(mask_dir.join(p))
 }
 
+pub(crate) fn decode_blobs(raw: Vec<u8>, compression: BlobCompression) -> anyhow::Result<Vec<u8>> {
+    match compression {
+        BlobCompression::None => Ok(raw),
+        BlobCompression::Zstd => {
+            zstd::decode_all(raw.as_slice()).context("zstd-decompress mask blobs")
+        }
+    }
+}
+
 fn decode_entry_data(data: &MaskEntryData, blobs: Option<&[u8]>) -> anyhow::Result<Vec<u8>> {
     match data {
         MaskEntryData::Empty => Ok(Vec::new()),
@@ -187,7 +427,8 @@ fn decode_entry_data(data: &MaskEntryData, blobs: Option<&[u8]>) -> anyhow::Resu
             .decode(s.as_bytes())
             .context("decode base64 entry data")?),
         MaskEntryData::External(r) => {
-            let blobs = blobs.ok_or_else(|| anyhow!("mask entry requires blobs_file (external)"))?;
+            let blobs =
+                blobs.ok_or_else(|| anyhow!("mask entry requires blobs_file (external)"))?;
             let start = r.offset as usize;
             let end = start.saturating_add(r.length as usize);
             if start > blobs.len() || end > blobs.len() {
@@ -222,17 +463,283 @@ pub fn extract_mask_json_and_offsets(
     offsets_json: &Path,
     blobs_bin: &Path,
 ) -> anyhow::Result<()> {
-    let pkg = DocxPackage::read(input_docx)?;
-    let prefix = hash_file_prefix(input_docx)?;
-    let mut blobs = File::create(blobs_bin)
+    extract_mask_json_and_offsets_with(
+        input_docx,
+        mask_json,
+        offsets_json,
+        blobs_bin,
+        MaskOptions::default(),
+    )
+}
+
+pub fn extract_mask_json_and_offsets_with(
+    input_docx: &Path,
+    mask_json: &Path,
+    offsets_json: &Path,
+    blobs_bin: &Path,
+    opts: MaskOptions,
+) -> anyhow::Result<()> {
+    let session = DocumentSession::open(input_docx)?;
+    extract_mask_json_and_offsets_from(&session, mask_json, offsets_json, blobs_bin, opts)?;
+    Ok(())
+}
+
+/// Write mask/offsets/blobs for an open session; returns the offsets for further stages.
+pub fn extract_mask_json_and_offsets_from(
+    session: &DocumentSession,
+    mask_json: &Path,
+    offsets_json: &Path,
+    blobs_bin: &Path,
+    opts: MaskOptions,
+) -> anyhow::Result<OffsetsJson> {
+    let f = File::create(blobs_bin)
         .with_context(|| format!("create mask blobs: {}", blobs_bin.display()))?;
-    let mut blob_offset: u64 = 0;
+    let mut blobs = BufWriter::new(f);
+    let MaskParts { mut mask, offsets } = build_mask(session, opts, &mut blobs)?;
+    blobs
+        .flush()
+        .with_context(|| format!("write mask blobs: {}", blobs_bin.display()))?;
+    mask.blobs_file = Some(blob_path_for_json(mask_json, blobs_bin)?);
+    fs::write(
+        mask_json,
+        serde_json::to_vec_pretty(&mask).context("serialize mask json")?,
+    )
+    .with_context(|| format!("write mask json: {}", mask_json.display()))?;
+    fs::write(
+        offsets_json,
+        serde_json::to_vec_pretty(&offsets).context("serialize offsets json")?,
+    )
+    .with_context(|| format!("write offsets json: {}", offsets_json.display()))?;
+    Ok(offsets)
+}
+
+/// Mask the session's document, streaming every entry's (masked) bytes into `blobs_out`.
+pub(crate) fn build_mask(
+    session: &DocumentSession,
+    opts: MaskOptions,
+    blobs_out: &mut dyn Write,
+) -> anyhow::Result<MaskParts> {
+    let mut parts = match opts.compression {
+        BlobCompression::None => build_mask_into(session, opts.keep_originals, blobs_out)?,
+        BlobCompression::Zstd => {
+            let mut enc = zstd::Encoder::new(blobs_out, 0).context("zstd encoder")?;
+            let parts = build_mask_into(session, opts.keep_originals, &mut enc)?;
+            enc.finish().context("zstd-compress mask blobs")?;
+            parts
+        }
+    };
+    parts.mask.blobs_compression = opts.compression;
+    Ok(parts)
+}
+
+/// Replace the part's translatable text with placeholders, numbering slots after `slots`.
+fn mask_part(part: &mut XmlPart, prefix: &str, slots: &mut Vec<TextSlot>) {
+    let first = slots.len();
+    let mut stack: Vec<XmlName> = Vec::new();
+    for (idx, ev) in part.events.iter_mut().enumerate() {
+        let next_id = slots.len() + 1;
+        match ev {
+            XmlEvent::Text { text } => {
+                let orig = std::mem::replace(text, placeholder(prefix, next_id));
+                slots.push(TextSlot {
+                    id: next_id,
+                    part_name: part.name.clone(),
+                    kind: SlotKind::Text,
+                    event_index: idx,
+                    attr_name: None,
+                    anchor: None,
+                    source_text: Some(orig),
+                });
+            }
+            XmlEvent::CData { text } => {
+                let orig = std::mem::replace(text, placeholder(prefix, next_id));
+                slots.push(TextSlot {
+                    id: next_id,
+                    part_name: part.name.clone(),
+                    kind: SlotKind::CData,
+                    event_index: idx,
+                    attr_name: None,
+                    anchor: None,
+                    source_text: Some(orig),
+                });
+            }
+            XmlEvent::Start { name, attrs } | XmlEvent::Empty { name, attrs } => {
+                let key = text_attr_key(name, stack.last().map(XmlName::as_str), attrs);
+                if let Some(v) = key.and_then(|key| find_attr_mut(attrs, key)) {
+                    let orig = std::mem::replace(v, placeholder(prefix, next_id));
+                    slots.push(TextSlot {
+                        id: next_id,
+                        part_name: part.name.clone(),
+                        kind: SlotKind::Attr,
+                        event_index: idx,
+                        attr_name: key.map(str::to_string),
+                        anchor: None,
+                        source_text: Some(unescape_attr(&orig)),
+                    });
+                }
+            }
+            _ => {}
+        }
+        track_parent(&mut stack, ev);
+    }
+    // Anchors of the masked part: every slot text is a placeholder there, so none is blank.
+    let anchors = event_anchors(part);
+    for slot in &mut slots[first..] {
+        slot.anchor = anchors[slot.event_index].clone();
+    }
+}
+
+/// Structural anchor of every element and non-blank text event: the element path with 1-based
+/// ordinals among same-named siblings (`/w:document[1]/w:body[1]/w:p[3]/w:r[1]/w:t[1]`), plus
+/// `/text()[k]` for the parent's k-th non-blank text or CDATA child. Unlike event indices,
+/// anchors survive a serializer that adds or drops indentation, declarations or comments.
+fn event_anchors(part: &XmlPart) -> Vec<Option<String>> {
+    struct Frame {
+        path: String,
+        children: HashMap<XmlName, usize>,
+        texts: usize,
+    }
+    let mut stack = vec![Frame {
+        path: String::new(),
+        children: HashMap::new(),
+        texts: 0,
+    }];
+    let mut out = Vec::with_capacity(part.events.len());
+    for ev in &part.events {
+        let top = stack.last_mut().expect("root frame");
+        let anchor = match ev {
+            XmlEvent::Start { name, .. } | XmlEvent::Empty { name, .. } => {
+                let n = top.children.entry(name.clone()).or_insert(0);
+                *n += 1;
+                let path = format!("{}/{}[{n}]", top.path, name.as_str());
+                if matches!(ev, XmlEvent::Start { .. }) {
+                    stack.push(Frame {
+                        path: path.clone(),
+                        children: HashMap::new(),
+                        texts: 0,
+                    });
+                }
+                Some(path)
+            }
+            XmlEvent::End { .. } => {
+                if stack.len() > 1 {
+                    stack.pop();
+                }
+                None
+            }
+            XmlEvent::Text { text } | XmlEvent::CData { text } if !text.trim().is_empty() => {
+                top.texts += 1;
+                Some(format!("{}/text()[{}]", top.path, top.texts))
+            }
+            _ => None,
+        };
+        out.push(anchor);
+    }
+    out
+}
+
+/// Whether event `index` of `part` still holds `slot`'s placeholder.
+fn holds_placeholder(part: &XmlPart, index: usize, slot: &TextSlot, ph: &str) -> bool {
+    match (&slot.kind, part.events.get(index)) {
+        (SlotKind::Text, Some(XmlEvent::Text { text }))
+        | (SlotKind::CData, Some(XmlEvent::CData { text })) => text == ph,
+        (SlotKind::Attr, Some(XmlEvent::Start { attrs, .. } | XmlEvent::Empty { attrs, .. })) => {
+            let name = slot.attr_name.as_deref().unwrap_or_default();
+            attrs.iter().any(|(k, v)| k == name && v == ph)
+        }
+        _ => false,
+    }
+}
+
+/// Event index of every slot: the recorded one while it still holds the placeholder, else the
+/// event its v2 anchor resolves to (masked XML rewritten by another tool). Slots that resolve
+/// nowhere keep the recorded index and fail the merge with a mask mismatch.
+fn resolve_slot_events(offsets: &OffsetsJson, parts: &HashMap<String, XmlPart>) -> Vec<usize> {
+    let mut by_anchor: HashMap<&str, HashMap<String, usize>> = HashMap::new();
+    offsets
+        .slots
+        .iter()
+        .map(|slot| {
+            let Some(part) = parts.get(&slot.part_name) else {
+                return slot.event_index;
+            };
+            let ph = placeholder(&offsets.placeholder_prefix, slot.id);
+            if holds_placeholder(part, slot.event_index, slot, &ph) {
+                return slot.event_index;
+            }
+            let Some(anchor) = slot.anchor.as_deref() else {
+                return slot.event_index;
+            };
+            let index = by_anchor.entry(slot.part_name.as_str()).or_insert_with(|| {
+                event_anchors(part)
+                    .into_iter()
+                    .enumerate()
+                    .filter_map(|(i, a)| a.map(|a| (a, i)))
+                    .collect()
+            });
+            index.get(anchor).copied().unwrap_or(slot.event_index)
+        })
+        .collect()
+}
+
+/// Keep `stack` at the open elements after `ev` (for `text_attr_key`'s parent).
+fn track_parent(stack: &mut Vec<XmlName>, ev: &XmlEvent) {
+    match ev {
+        XmlEvent::Start { name, .. } => stack.push(name.clone()),
+        XmlEvent::End { .. } => {
+            let _ = stack.pop();
+        }
+        _ => {}
+    }
+}
+
+fn build_mask_into(
+    session: &DocumentSession,
+    keep_originals: bool,
+    blobs_out: &mut dyn Write,
+) -> anyhow::Result<MaskParts> {
+    let pkg = session.package();
+    let mut source = pkg.open_source()?;
+    let guard = pkg.limit_guard()?;
+    let prefix = session.placeholder_prefix().to_string();
+    let mut blobs = BlobSink {
+        out: blobs_out,
+        offset: 0,
+    };
+
+    // Serialization runs in parallel; slot numbering and blob writes stay in entry order.
+    let mut parts: Vec<Option<XmlPart>> = (0..pkg.entries.len())
+        .into_par_iter()
+        .map(|i| session.part_at(i).cloned())
+        .collect();
 
-    let mut entries_out: Vec<MaskEntryJson> = Vec::with_capacity(pkg.entries.len());
     let mut slots: Vec<TextSlot> = Vec::new();
-    let mut next_id = 1usize;
+    let mut part_slots: Vec<usize> = vec![0; parts.len()];
+    for (i, part) in parts.iter_mut().enumerate() {
+        if let Some(part) = part {
+            let first = slots.len();
+            mask_part(part, &prefix, &mut slots);
+            verify_part_mask_pure(part, &prefix)?;
+            part_slots[i] = slots.len() - first;
+        }
+    }
+
+    let lenient = pkg.options.lenient;
+    let mut masked: Vec<Option<Vec<u8>>> = parts
+        .par_iter()
+        .enumerate()
+        .map(|(i, part)| match part {
+            // No slots: the original bytes are already a pure mask.
+            Some(part) if part_slots[i] > 0 || lenient => write_xml_part(part)
+                .with_context(|| format!("serialize masked xml: {}", part.name))
+                .map(Some),
+            _ => Ok(None),
+        })
+        .collect::<anyhow::Result<_>>()?;
+    drop(parts);
 
-    for ent in &pkg.entries {
+    let mut entries_out: Vec<MaskEntryJson> = Vec::with_capacity(pkg.entries.len());
+    for (i, ent) in pkg.entries.iter().enumerate() {
         let (datepart, timepart): (u16, u16) = ent.last_modified.into();
         let mut out_ent = MaskEntryJson {
             name: ent.name.clone(),
@@ -241,6 +748,9 @@ pub fn extract_mask_json_and_offsets(
             unix_mode: ent.unix_mode,
             is_dir: ent.is_dir,
             data: MaskEntryData::Empty,
+            original: None,
+            extra_data: (!ent.extra_data.is_empty()).then(|| B64.encode(&ent.extra_data)),
+            large_file: ent.large_file,
         };
 
         if ent.is_dir || ent.name.ends_with('/') {
@@ -248,139 +758,72 @@ pub fn extract_mask_json_and_offsets(
             continue;
         }
 
-        let out_bytes: Vec<u8> = if ent.name.to_lowercase().ends_with(".xml") && !ent.data.is_empty() {
-            let mut part = parse_xml_part(&ent.name, &ent.data)
-                .with_context(|| format!("parse xml: {}", ent.name))?;
-            for (idx, ev) in part.events.iter_mut().enumerate() {
-                match ev {
-                    XmlEvent::Text { text } => {
-                        let ph = placeholder(&prefix, next_id);
-                        let _orig = std::mem::replace(text, ph);
-                        slots.push(TextSlot {
-                            id: next_id,
-                            part_name: part.name.clone(),
-                            kind: SlotKind::Text,
-                            event_index: idx,
-                            attr_name: None,
-                        });
-                        next_id += 1;
-                    }
-                    XmlEvent::CData { text } => {
-                        let ph = placeholder(&prefix, next_id);
-                        let _orig = std::mem::replace(text, ph);
-                        slots.push(TextSlot {
-                            id: next_id,
-                            part_name: part.name.clone(),
-                            kind: SlotKind::CData,
-                            event_index: idx,
-                            attr_name: None,
-                        });
-                        next_id += 1;
-                    }
-                    XmlEvent::Start { name, attrs } | XmlEvent::Empty { name, attrs } => {
-                        if name == "w:lvlText" {
-                            if let Some(v) = find_attr_mut(attrs, "w:val") {
-                                let ph = placeholder(&prefix, next_id);
-                                let _orig = std::mem::replace(v, ph);
-                                slots.push(TextSlot {
-                                    id: next_id,
-                                    part_name: part.name.clone(),
-                                    kind: SlotKind::Attr,
-                                    event_index: idx,
-                                    attr_name: Some("w:val".to_string()),
-                                });
-                                next_id += 1;
-                            }
-                        }
-                    }
-                    _ => {}
-                }
+        if pkg.is_streamed(i) {
+            let zip = source
+                .as_mut()
+                .context("streaming package without source")?;
+            let file = zip.by_index(i).context("zip entry")?;
+            out_ent.data = blobs
+                .append(&mut guard.entry_reader(file))
+                .with_context(|| format!("copy entry: {}", ent.name))?;
+            if let MaskEntryData::External(r) = &out_ent.data {
+                guard.check_declared(&ent.name, r.length)?;
             }
-
-            verify_part_mask_pure(&part, &prefix)?;
-
-            write_xml_part(&part).with_context(|| format!("serialize masked xml: {}", ent.name))?
-        } else {
-            ent.data.clone()
-        };
-
-        if out_bytes.is_empty() {
-            out_ent.data = MaskEntryData::Empty;
             entries_out.push(out_ent);
             continue;
         }
-        let mut hasher = Sha256::new();
-        hasher.update(&out_bytes);
-        let sha256 = hex::encode(hasher.finalize());
-        let len = out_bytes.len() as u64;
-        blobs
-            .write_all(&out_bytes)
-            .with_context(|| format!("write mask blobs: {}", blobs_bin.display()))?;
-        out_ent.data = MaskEntryData::External(MaskBlobRef {
-            offset: blob_offset,
-            length: len,
-            sha256,
-        });
-        blob_offset = blob_offset.saturating_add(len);
+
+        let out_bytes = masked[i].take();
+        out_ent.data = blobs.append(&mut out_bytes.as_deref().unwrap_or(&ent.data))?;
+        if keep_originals && part_slots[i] > 0 {
+            if let MaskEntryData::External(r) = blobs.append(&mut ent.data.as_slice())? {
+                out_ent.original = Some(r);
+            }
+        }
         entries_out.push(out_ent);
     }
 
+    let producer = ArtifactProducer::new(session.source_sha256());
     let mask = MaskJson {
-        version: 2,
+        version: MASK_VERSION,
+        producer: Some(producer.clone()),
         placeholder_prefix: prefix.clone(),
-        blobs_file: Some(blob_path_for_json(mask_json, blobs_bin)?),
+        blobs_file: None,
+        blobs_compression: BlobCompression::None,
+        comment: (!pkg.comment.is_empty()).then(|| B64.encode(&pkg.comment)),
         entries: entries_out,
     };
-    fs::write(
-        mask_json,
-        serde_json::to_vec_pretty(&mask).context("serialize mask json")?,
-    )
-    .with_context(|| format!("write mask json: {}", mask_json.display()))?;
-
     let offsets = OffsetsJson {
-        version: 1,
+        version: OFFSETS_VERSION,
+        producer: Some(producer),
         placeholder_prefix: prefix,
         slots,
     };
-    fs::write(
-        offsets_json,
-        serde_json::to_vec_pretty(&offsets).context("serialize offsets json")?,
-    )
-    .with_context(|| format!("write offsets json: {}", offsets_json.display()))?;
-
-    Ok(())
+    Ok(MaskParts { mask, offsets })
 }
 
-pub fn extract_slot_texts(input_docx: &Path) -> anyhow::Result<(String, Vec<String>)> {
-    let pkg = DocxPackage::read(input_docx)?;
-    let prefix = hash_file_prefix(input_docx)?;
-
+/// Every slot's text in slot-id order (what masking would replace with placeholders).
+pub fn extract_slot_texts(session: &DocumentSession) -> Vec<String> {
     let mut out: Vec<String> = Vec::new();
-    for ent in &pkg.entries {
-        if ent.is_dir || ent.name.ends_with('/') || ent.data.is_empty() {
-            continue;
-        }
-        if !ent.name.to_lowercase().ends_with(".xml") {
-            continue;
-        }
-        let part =
-            parse_xml_part(&ent.name, &ent.data).with_context(|| format!("parse xml: {}", ent.name))?;
+    for part in session.xml_parts() {
+        let mut stack: Vec<XmlName> = Vec::new();
         for ev in &part.events {
             match ev {
                 XmlEvent::Text { text } | XmlEvent::CData { text } => out.push(text.clone()),
                 XmlEvent::Start { name, attrs } | XmlEvent::Empty { name, attrs } => {
-                    if name == "w:lvlText" {
-                        if let Some(v) = attrs.iter().find(|(k, _)| k == "w:val").map(|(_, v)| v) {
-                            out.push(v.clone());
+                    if let Some(key) = text_attr_key(name, stack.last().map(XmlName::as_str), attrs)
+                    {
+                        if let Some(v) = attrs.iter().find(|(k, _)| k == key).map(|(_, v)| v) {
+                            out.push(unescape_attr(v));
                         }
                     }
                 }
                 _ => {}
             }
+            track_parent(&mut stack, ev);
         }
     }
-
-    Ok((prefix, out))
+    out
 }
 
 pub fn merge_mask_json_and_offsets(
@@ -389,6 +832,44 @@ pub fn merge_mask_json_and_offsets(
     text_json: &Path,
     output_docx: &Path,
 ) -> anyhow::Result<()> {
+    merge_mask_json_and_offsets_with(
+        mask_json,
+        offsets_json,
+        text_json,
+        output_docx,
+        &MergeOptions::default(),
+    )
+    .map(|_| ())
+}
+
+/// Merge settings that do not come from the artifacts.
+#[derive(Clone, Debug, Default)]
+pub struct MergeOptions {
+    /// `--merge-lenient`: missing or invalid slot texts fall back to the source text recorded in
+    /// the offsets instead of failing the merge.
+    pub lenient: bool,
+    /// `[[links.rewrite]]` rules for external hyperlink targets.
+    pub link_rewrites: Vec<LinkRewrite>,
+}
+
+/// A slot whose merged text was rejected and replaced by its source text (`--merge-lenient`).
+#[derive(Clone, Debug, Serialize)]
+pub struct SlotSubstitution {
+    pub slot_id: usize,
+    pub part_name: String,
+    pub reason: String,
+    /// The rejected text (`None` when the slot was missing).
+    pub rejected: Option<String>,
+}
+
+/// Like `merge_mask_json_and_offsets`, with `opts`; returns the lenient substitutions.
+pub fn merge_mask_json_and_offsets_with(
+    mask_json: &Path,
+    offsets_json: &Path,
+    text_json: &Path,
+    output_docx: &Path,
+    opts: &MergeOptions,
+) -> anyhow::Result<Vec<SlotSubstitution>> {
     let mask: MaskJson = serde_json::from_slice(
         &fs::read(mask_json).with_context(|| format!("read mask json: {}", mask_json.display()))?,
     )
@@ -398,11 +879,92 @@ pub fn merge_mask_json_and_offsets(
             .with_context(|| format!("read offsets json: {}", offsets_json.display()))?,
     )
     .context("parse offsets json")?;
-    let text: PureTextJson = serde_json::from_slice(
+    let text: MergeTextJson = serde_json::from_slice(
         &fs::read(text_json).with_context(|| format!("read text json: {}", text_json.display()))?,
     )
     .context("parse text json")?;
 
+    let blobs = match mask.blobs_file.as_deref() {
+        Some(p) => {
+            let p = resolve_blobs_path(mask_json, p)?;
+            let raw = fs::read(&p).with_context(|| format!("read mask blobs: {}", p.display()))?;
+            Some(decode_blobs(raw, mask.blobs_compression)?)
+        }
+        None => None,
+    };
+    merge_mask_parts(&mask, &offsets, &text, blobs.as_deref(), output_docx, opts)
+}
+
+/// `SlotTexts` with sparse ids parsed and range-checked.
+enum SlotLookup<'a> {
+    Full(&'a [String]),
+    Sparse(HashMap<usize, &'a String>),
+}
+
+/// `<output stem>.merge-report.json` next to the merged docx.
+pub fn merge_report_path(output_docx: &Path) -> PathBuf {
+    let stem = output_docx
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("docx");
+    output_docx.with_file_name(format!("{stem}.merge-report.json"))
+}
+
+pub fn write_merge_report(
+    output_docx: &Path,
+    subs: &[SlotSubstitution],
+) -> anyhow::Result<PathBuf> {
+    let path = merge_report_path(output_docx);
+    let data = serde_json::to_vec_pretty(subs).context("serialize merge report")?;
+    fs::write(&path, data).with_context(|| format!("write merge report: {}", path.display()))?;
+    Ok(path)
+}
+
+/// Why a merged slot text cannot be used as-is.
+fn slot_text_problem(text: &str, ph_marker: &str) -> Option<&'static str> {
+    if text.contains(ph_marker) {
+        Some("leftover placeholder")
+    } else if !text.chars().all(is_xml_char) {
+        Some("invalid XML characters")
+    } else {
+        None
+    }
+}
+
+pub(crate) fn merge_mask_parts(
+    mask: &MaskJson,
+    offsets: &OffsetsJson,
+    text: &MergeTextJson,
+    blobs: Option<&[u8]>,
+    output_docx: &Path,
+    opts: &MergeOptions,
+) -> anyhow::Result<Vec<SlotSubstitution>> {
+    let lenient = opts.lenient;
+    check_schema(
+        "mask.json",
+        mask.version,
+        (MASK_VERSION, MASK_VERSION),
+        mask.producer.as_ref(),
+    )?;
+    check_schema(
+        "offsets.json",
+        offsets.version,
+        (1, OFFSETS_VERSION),
+        offsets.producer.as_ref(),
+    )?;
+    if let Some(version) = text.version {
+        check_schema(
+            "text.json",
+            version,
+            (1, PURE_TEXT_VERSION),
+            text.producer.as_ref(),
+        )?;
+    }
+    // Text may come from a re-saved copy (same placeholder prefix); mask and offsets may not.
+    check_same_source(
+        ("mask.json", mask.producer.as_ref()),
+        ("offsets.json", offsets.producer.as_ref()),
+    )?;
     if mask.placeholder_prefix != offsets.placeholder_prefix {
         return Err(anyhow!(
             "placeholder_prefix mismatch: mask={} offsets={}",
@@ -421,7 +983,9 @@ pub fn merge_mask_json_and_offsets(
     let min_id = offsets.slots.iter().map(|s| s.id).min().unwrap_or(0);
     if !offsets.slots.is_empty() {
         if min_id != 1 {
-            return Err(anyhow!("offsets slot ids must start at 1 (min_id={min_id})"));
+            return Err(anyhow!(
+                "offsets slot ids must start at 1 (min_id={min_id})"
+            ));
         }
         if max_id != offsets.slots.len() {
             return Err(anyhow!(
@@ -430,28 +994,33 @@ pub fn merge_mask_json_and_offsets(
             ));
         }
     }
-    if text.slot_texts.len() != max_id {
-        return Err(anyhow!(
-            "text slot_texts length mismatch: text_len={} expected={}",
-            text.slot_texts.len(),
-            max_id
-        ));
+    match &text.slot_texts {
+        SlotTexts::Full(v) if v.len() != max_id && !lenient => {
+            return Err(anyhow!(
+                "text slot_texts length mismatch: text_len={} expected={}",
+                v.len(),
+                max_id
+            ));
+        }
+        _ => {}
     }
-
-    let blobs_path = if let Some(p) = mask.blobs_file.as_deref() {
-        Some(resolve_blobs_path(mask_json, p)?)
-    } else {
-        None
-    };
-    let blobs: Option<Vec<u8>> = if let Some(p) = blobs_path.as_ref() {
-        Some(fs::read(p).with_context(|| format!("read mask blobs: {}", p.display()))?)
-    } else {
-        None
+    let lookup = match &text.slot_texts {
+        SlotTexts::Full(v) => SlotLookup::Full(v),
+        SlotTexts::Sparse(m) => SlotLookup::Sparse(
+            m.iter()
+                .map(|(k, v)| match k.trim().parse::<usize>() {
+                    Ok(id) if (1..=max_id).contains(&id) => Ok((id, v)),
+                    _ => Err(anyhow!(
+                        "sparse slot_texts: unknown slot id {k:?} (max_id={max_id})"
+                    )),
+                })
+                .collect::<anyhow::Result<_>>()?,
+        ),
     };
 
     let mut entries: Vec<DocxEntry> = Vec::with_capacity(mask.entries.len());
     for ent in &mask.entries {
-        let data = decode_entry_data(&ent.data, blobs.as_deref())
+        let data = decode_entry_data(&ent.data, blobs)
             .with_context(|| format!("decode entry: {}", ent.name))?;
         let last_modified = DateTime::try_from(ent.last_modified).unwrap_or_default();
         entries.push(DocxEntry {
@@ -461,35 +1030,107 @@ pub fn merge_mask_json_and_offsets(
             last_modified,
             unix_mode: ent.unix_mode,
             is_dir: ent.is_dir,
+            extra_data: decode_b64_field(ent.extra_data.as_deref())
+                .with_context(|| format!("decode extra data: {}", ent.name))?,
+            large_file: ent.large_file,
         });
     }
+    for ent in &mut entries {
+        if let Some(data) = rewrite_rels_targets(&opts.link_rewrites, &ent.name, &ent.data)? {
+            ent.data = data;
+        }
+    }
 
+    // Only parts with slots are parsed and re-serialized; the rest keep their bytes.
+    let slot_parts: HashSet<&str> = offsets.slots.iter().map(|s| s.part_name.as_str()).collect();
+    let ph_marker = format!("__MT_MASK_{}_", offsets.placeholder_prefix);
+    let parsed: Vec<Option<XmlPart>> = entries
+        .par_iter()
+        .map(|ent| {
+            if !ent.name.to_lowercase().ends_with(".xml") || ent.data.is_empty() {
+                return Ok(None);
+            }
+            if !slot_parts.contains(ent.name.as_str()) {
+                if ent
+                    .data
+                    .windows(ph_marker.len())
+                    .any(|w| w == ph_marker.as_bytes())
+                {
+                    return Err(anyhow!("leftover placeholder in {}", ent.name));
+                }
+                return Ok(None);
+            }
+            parse_xml_part(&ent.name, &ent.data)
+                .with_context(|| format!("parse masked xml: {}", ent.name))
+                .map(Some)
+        })
+        .collect::<anyhow::Result<_>>()?;
     let mut parts: HashMap<String, XmlPart> = HashMap::new();
     let mut part_to_entry_idx: HashMap<String, usize> = HashMap::new();
-    for (i, ent) in entries.iter().enumerate() {
-        if !ent.name.to_lowercase().ends_with(".xml") || ent.data.is_empty() {
-            continue;
+    for (i, part) in parsed.into_iter().enumerate() {
+        if let Some(part) = part {
+            part_to_entry_idx.insert(part.name.clone(), i);
+            parts.insert(part.name.clone(), part);
         }
-        let part = parse_xml_part(&ent.name, &ent.data)
-            .with_context(|| format!("parse masked xml: {}", ent.name))?;
-        part_to_entry_idx.insert(ent.name.clone(), i);
-        parts.insert(ent.name.clone(), part);
     }
 
-    for slot in &offsets.slots {
+    let slot_events = resolve_slot_events(offsets, &parts);
+    let mut substitutions = Vec::new();
+    for (slot, &event_index) in offsets.slots.iter().zip(&slot_events) {
         let ph = placeholder(&offsets.placeholder_prefix, slot.id);
-        let replacement = text
-            .slot_texts
-            .get(slot.id.saturating_sub(1))
-            .ok_or_else(|| anyhow!("missing slot_texts[{}] for id={}", slot.id.saturating_sub(1), slot.id))?
-            .clone();
+        let given = match &lookup {
+            SlotLookup::Full(v) => v.get(slot.id.saturating_sub(1)),
+            SlotLookup::Sparse(m) => match m.get(&slot.id) {
+                Some(t) => Some(*t),
+                // Partial merge: slots outside the map keep their extracted text.
+                None => Some(slot.source_text.as_ref().ok_or_else(|| {
+                    anyhow!(
+                        "slot {} is not in the sparse text.json and offsets record no source text (re-extract the mask)",
+                        slot.id
+                    )
+                })?),
+            },
+        };
+        let problem = match given {
+            None => Some("missing"),
+            Some(t) => slot_text_problem(t, &ph_marker),
+        };
+        let replacement = match (problem, given) {
+            (None, Some(t)) => t.clone(),
+            (Some(reason), _) if lenient => {
+                let source = slot.source_text.clone().ok_or_else(|| {
+                    anyhow!(
+                        "slot {} is {reason} and offsets record no source text (re-extract the mask)",
+                        slot.id
+                    )
+                })?;
+                substitutions.push(SlotSubstitution {
+                    slot_id: slot.id,
+                    part_name: slot.part_name.clone(),
+                    reason: reason.to_string(),
+                    rejected: given.cloned(),
+                });
+                source
+            }
+            (_, None) => {
+                return Err(anyhow!(
+                    "missing slot_texts[{}] for id={}",
+                    slot.id.saturating_sub(1),
+                    slot.id
+                ))
+            }
+            // Strict: leftover placeholders are reported by the scan below.
+            (Some(_), Some(t)) => t.clone(),
+        };
         let part = parts
             .get_mut(&slot.part_name)
             .with_context(|| format!("missing part: {}", slot.part_name))?;
-        let ev = part
-            .events
-            .get_mut(slot.event_index)
-            .with_context(|| format!("event index out of range: {}@{}", slot.part_name, slot.event_index))?;
+        let ev = part.events.get_mut(event_index).with_context(|| {
+            format!(
+                "event index out of range: {}@{}",
+                slot.part_name, event_index
+            )
+        })?;
         match slot.kind {
             SlotKind::Text => match ev {
                 XmlEvent::Text { text } => {
@@ -497,13 +1138,19 @@ pub fn merge_mask_json_and_offsets(
                         return Err(anyhow!(
                             "mask mismatch: expected placeholder {ph} at {}#{} (got={:?})",
                             slot.part_name,
-                            slot.event_index,
+                            event_index,
                             text
                         ));
                     }
                     *text = replacement;
                 }
-                _ => return Err(anyhow!("expected Text event at {}#{}", slot.part_name, slot.event_index)),
+                _ => {
+                    return Err(anyhow!(
+                        "expected Text event at {}#{}",
+                        slot.part_name,
+                        event_index
+                    ))
+                }
             },
             SlotKind::CData => match ev {
                 XmlEvent::CData { text } => {
@@ -511,13 +1158,19 @@ pub fn merge_mask_json_and_offsets(
                         return Err(anyhow!(
                             "mask mismatch: expected placeholder {ph} at {}#{} (got={:?})",
                             slot.part_name,
-                            slot.event_index,
+                            event_index,
                             text
                         ));
                     }
                     *text = replacement;
                 }
-                _ => return Err(anyhow!("expected CData event at {}#{}", slot.part_name, slot.event_index)),
+                _ => {
+                    return Err(anyhow!(
+                        "expected CData event at {}#{}",
+                        slot.part_name,
+                        event_index
+                    ))
+                }
             },
             SlotKind::Attr => {
                 let attr_name = slot
@@ -527,19 +1180,55 @@ pub fn merge_mask_json_and_offsets(
                 match ev {
                     XmlEvent::Start { attrs, .. } | XmlEvent::Empty { attrs, .. } => {
                         let v = find_attr_mut(attrs, attr_name).ok_or_else(|| {
-                            anyhow!("missing attr {attr_name} at {}#{}", slot.part_name, slot.event_index)
+                            anyhow!(
+                                "missing attr {attr_name} at {}#{}",
+                                slot.part_name,
+                                event_index
+                            )
                         })?;
                         if *v != ph {
                             return Err(anyhow!(
                                 "mask mismatch: expected placeholder {ph} for attr {attr_name} at {}#{} (got={:?})",
                                 slot.part_name,
-                                slot.event_index,
+                                event_index,
                                 v
                             ));
                         }
-                        *v = replacement;
+                        *v = escape_attr(&replacement);
                     }
-                    _ => return Err(anyhow!("expected Start/Empty event at {}#{}", slot.part_name, slot.event_index)),
+                    _ => {
+                        return Err(anyhow!(
+                            "expected Start/Empty event at {}#{}",
+                            slot.part_name,
+                            event_index
+                        ))
+                    }
+                }
+            }
+        }
+    }
+
+    if !text.highlight_slots.is_empty() || !text.collapsed_slots.is_empty() {
+        let color = text.highlight_color.as_deref().unwrap_or(DEFAULT_HIGHLIGHT);
+        let wanted: HashSet<usize> = text.highlight_slots.iter().copied().collect();
+        let collapsed: HashSet<usize> = text.collapsed_slots.iter().copied().collect();
+        let mut targets: Vec<(&str, usize, bool)> = offsets
+            .slots
+            .iter()
+            .zip(&slot_events)
+            .filter(|(s, _)| matches!(s.kind, SlotKind::Text))
+            .filter(|(s, _)| wanted.contains(&s.id) || collapsed.contains(&s.id))
+            .map(|(s, &i)| (s.part_name.as_str(), i, collapsed.contains(&s.id)))
+            .collect();
+        // Both edits insert or remove events inside the run: go from the back so recorded
+        // indices stay valid.
+        targets.sort_by_key(|t| std::cmp::Reverse(t.1));
+        for (part_name, event_index, drop) in targets {
+            if let Some(part) = parts.get_mut(part_name) {
+                if drop {
+                    drop_run_text(part, event_index);
+                } else {
+                    highlight_run_text(part, event_index, color);
                 }
             }
         }
@@ -550,13 +1239,13 @@ pub fn merge_mask_json_and_offsets(
         for ev in &part.events {
             match ev {
                 XmlEvent::Text { text } | XmlEvent::CData { text } => {
-                    if text.contains(&offsets.placeholder_prefix) {
+                    if text.contains(&ph_marker) {
                         return Err(anyhow!("leftover placeholder in {name}: {:?}", text));
                     }
                 }
                 XmlEvent::Start { attrs, .. } | XmlEvent::Empty { attrs, .. } => {
                     for (_, v) in attrs {
-                        if v.contains(&offsets.placeholder_prefix) {
+                        if v.contains(&ph_marker) {
                             return Err(anyhow!("leftover placeholder in {name} attr: {:?}", v));
                         }
                     }
@@ -566,24 +1255,95 @@ pub fn merge_mask_json_and_offsets(
         }
     }
 
-    for (part_name, part) in parts.iter() {
-        let bytes =
-            write_xml_part(part).with_context(|| format!("serialize restored xml: {part_name}"))?;
-        let entry_idx = *part_to_entry_idx
-            .get(part_name)
-            .with_context(|| format!("missing entry index for part: {part_name}"))?;
+    let restored: Vec<(usize, Vec<u8>)> = parts
+        .par_iter()
+        .map(|(part_name, part)| {
+            let entry_idx = *part_to_entry_idx
+                .get(part_name)
+                .with_context(|| format!("missing entry index for part: {part_name}"))?;
+            if let Some(original) = unchanged_original(&mask.entries[entry_idx], part, blobs)? {
+                return Ok((entry_idx, original));
+            }
+            let bytes = write_xml_part(part)
+                .with_context(|| format!("serialize restored xml: {part_name}"))?;
+            Ok((entry_idx, bytes))
+        })
+        .collect::<anyhow::Result<_>>()?;
+    for (entry_idx, bytes) in restored {
         entries[entry_idx].data = bytes;
     }
 
-    let pkg = DocxPackage { entries };
+    if text.update_fields {
+        if let Some(ent) = entries.iter_mut().find(|e| e.name == SETTINGS_PART) {
+            let mut part = parse_xml_part(&ent.name, &ent.data)
+                .with_context(|| format!("parse xml: {}", ent.name))?;
+            if set_update_fields(&mut part) {
+                ent.data = write_xml_part(&part)
+                    .with_context(|| format!("serialize xml: {}", ent.name))?;
+            }
+        }
+    }
+
+    let pkg = DocxPackage::from_entries(
+        entries,
+        decode_b64_field(mask.comment.as_deref()).context("decode zip comment")?,
+    );
     pkg.write_with_replacements(output_docx, &HashMap::new())?;
-    Ok(())
+    Ok(substitutions)
+}
+
+fn decode_b64_field(value: Option<&str>) -> anyhow::Result<Vec<u8>> {
+    match value {
+        Some(s) => B64.decode(s.as_bytes()).context("base64 decode"),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// The stored original bytes of a part, when the restored part is event-for-event identical.
+fn unchanged_original(
+    ent: &MaskEntryJson,
+    restored: &XmlPart,
+    blobs: Option<&[u8]>,
+) -> anyhow::Result<Option<Vec<u8>>> {
+    let Some(r) = ent.original.as_ref() else {
+        return Ok(None);
+    };
+    let original = decode_entry_data(&MaskEntryData::External(r.clone()), blobs)
+        .with_context(|| format!("decode original: {}", ent.name))?;
+    // An original that only parsed after `--lenient-parse` repairs cannot equal the restored part.
+    let Ok(parsed) = parse_xml_part(&ent.name, &original) else {
+        return Ok(None);
+    };
+    if full_hash(&parsed.events) == full_hash(&restored.events) {
+        Ok(Some(original))
+    } else {
+        Ok(None)
+    }
 }
 
 pub fn verify_docx_roundtrip(original_docx: &Path, restored_docx: &Path) -> anyhow::Result<()> {
-    let orig = DocxPackage::read(original_docx)?;
-    let restored = DocxPackage::read(restored_docx)?;
+    verify_docx_roundtrip_with(
+        original_docx,
+        restored_docx,
+        false,
+        &PackageOptions::default(),
+    )
+}
 
+/// `strict`: XML parts whose events are unchanged must also be byte-identical, so serializer
+/// drift (quoting, entity spelling, whitespace in tags) is reported instead of hidden.
+pub fn verify_docx_roundtrip_with(
+    original_docx: &Path,
+    restored_docx: &Path,
+    strict: bool,
+    package: &PackageOptions,
+) -> anyhow::Result<()> {
+    let orig = DocxPackage::read_with(original_docx, package)?;
+    let restored = DocxPackage::read_with(restored_docx, package)?;
+
+    if orig.comment != restored.comment {
+        return Err(anyhow!("zip archive comment differs"));
+    }
     if orig.entries.len() != restored.entries.len() {
         return Err(anyhow!(
             "zip entry count mismatch: orig={} restored={}",
@@ -592,40 +1352,116 @@ pub fn verify_docx_roundtrip(original_docx: &Path, restored_docx: &Path) -> anyh
         ));
     }
 
-    for (a, b) in orig.entries.iter().zip(restored.entries.iter()) {
-        if a.name != b.name {
-            return Err(anyhow!("zip entry order/name mismatch: {} vs {}", a.name, b.name));
-        }
-        if a.compression != b.compression {
-            return Err(anyhow!("zip entry compression differs: {}", a.name));
-        }
-        if a.last_modified != b.last_modified {
-            return Err(anyhow!("zip entry timestamp differs: {}", a.name));
-        }
-        if a.is_dir != b.is_dir {
-            return Err(anyhow!("zip entry is_dir differs: {}", a.name));
-        }
-        let is_xml = a.name.to_lowercase().ends_with(".xml");
-        if !is_xml {
-            if a.data != b.data {
-                return Err(anyhow!("non-xml entry bytes differ: {}", a.name));
-            }
-            continue;
-        }
-        if a.data.is_empty() && b.data.is_empty() {
-            continue;
-        }
-        let pa =
-            parse_xml_part(&a.name, &a.data).with_context(|| format!("parse orig xml: {}", a.name))?;
-        let pb = parse_xml_part(&b.name, &b.data)
-            .with_context(|| format!("parse restored xml: {}", b.name))?;
-        let ha = full_hash(&pa.events);
-        let hb = full_hash(&pb.events);
-        if ha != hb {
-            return Err(anyhow!("xml entry differs (full hash): {}", a.name));
+    // Entries are compared in parallel; errors and mismatches are reported in entry order.
+    let results: Vec<anyhow::Result<Option<PartMismatch>>> = orig
+        .entries
+        .par_iter()
+        .zip(restored.entries.par_iter())
+        .map(|(a, b)| compare_entries(a, b, strict, package.lenient))
+        .collect();
+    let mut mismatches: Vec<PartMismatch> = Vec::new();
+    for r in results {
+        mismatches.extend(r?);
+    }
+    if mismatches.is_empty() {
+        return Ok(());
+    }
+    let report_path = roundtrip_report_path(restored_docx);
+    let report = serde_json::to_vec_pretty(&mismatches).context("serialize roundtrip diff")?;
+    fs::write(&report_path, report)
+        .with_context(|| format!("write roundtrip diff: {}", report_path.display()))?;
+    let details: Vec<String> = mismatches
+        .iter()
+        .map(|m| format!("{}: {}", m.part, m.diff))
+        .collect();
+    Err(anyhow!(
+        "xml entry differs (full hash): {} part(s), report: {}\n{}",
+        mismatches.len(),
+        report_path.display(),
+        details.join("\n")
+    ))
+}
+
+/// One entry of `verify_docx_roundtrip_with`; `Some` when an XML part's events differ.
+fn compare_entries(
+    a: &DocxEntry,
+    b: &DocxEntry,
+    strict: bool,
+    lenient: bool,
+) -> anyhow::Result<Option<PartMismatch>> {
+    if a.name != b.name {
+        return Err(anyhow!(
+            "zip entry order/name mismatch: {} vs {}",
+            a.name,
+            b.name
+        ));
+    }
+    if a.compression != b.compression {
+        return Err(anyhow!("zip entry compression differs: {}", a.name));
+    }
+    if a.last_modified != b.last_modified {
+        return Err(anyhow!("zip entry timestamp differs: {}", a.name));
+    }
+    if a.is_dir != b.is_dir {
+        return Err(anyhow!("zip entry is_dir differs: {}", a.name));
+    }
+    if a.extra_data != b.extra_data {
+        return Err(anyhow!("zip entry extra fields differ: {}", a.name));
+    }
+    if a.large_file != b.large_file {
+        return Err(anyhow!("zip entry zip64 layout differs: {}", a.name));
+    }
+    let is_xml = a.name.to_lowercase().ends_with(".xml");
+    if !is_xml {
+        if a.data != b.data {
+            return Err(anyhow!("non-xml entry bytes differ: {}", a.name));
         }
+        return Ok(None);
     }
-    Ok(())
+    if a.data == b.data {
+        return Ok(None);
+    }
+    // Repairs were reported when the original was extracted; only the events are compared here.
+    let (pa, _) = parse_xml_part_with(&a.name, &a.data, lenient)
+        .with_context(|| format!("parse orig xml: {}", a.name))?;
+    let (pb, _) = parse_xml_part_with(&b.name, &b.data, lenient)
+        .with_context(|| format!("parse restored xml: {}", b.name))?;
+    let ha = full_hash(&pa.events);
+    let hb = full_hash(&pb.events);
+    if ha != hb {
+        return Ok(
+            first_event_difference(&pa.events, &pb.events, DIFF_CONTEXT).map(|diff| PartMismatch {
+                part: a.name.clone(),
+                diff,
+            }),
+        );
+    }
+    if strict {
+        return Err(anyhow!(
+            "xml entry not byte-identical (same events, serializer drift): {}",
+            a.name
+        ));
+    }
+    Ok(None)
+}
+
+/// Records of context kept around the first differing event of a part.
+const DIFF_CONTEXT: usize = 3;
+
+#[derive(Serialize)]
+struct PartMismatch {
+    part: String,
+    #[serde(flatten)]
+    diff: EventDiff,
+}
+
+/// `<restored stem>.roundtrip-diff.json` next to the restored docx.
+fn roundtrip_report_path(restored_docx: &Path) -> PathBuf {
+    let stem = restored_docx
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("docx");
+    restored_docx.with_file_name(format!("{stem}.roundtrip-diff.json"))
 }
 
 pub fn default_outputs_for(input_docx: &Path) -> MaskOutputs {
@@ -640,3 +1476,77 @@ pub fn default_outputs_for(input_docx: &Path) -> MaskOutputs {
         blobs_bin_path: dir.join(format!("{stem}.mask.blobs.bin")),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use zip::write::SimpleFileOptions;
+    use zip::{ZipArchive, ZipWriter};
+
+    use super::*;
+
+    fn write_docx(path: &Path, document_xml: &str) {
+        let mut zout = ZipWriter::new(File::create(path).expect("create docx"));
+        let opts = SimpleFileOptions::default();
+        zout.start_file("[Content_Types].xml", opts)
+            .expect("start types");
+        zout.write_all(br#"<?xml version="1.0" encoding="UTF-8"?><Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"/>"#)
+            .expect("write types");
+        zout.start_file("word/document.xml", opts)
+            .expect("start document");
+        zout.write_all(document_xml.as_bytes())
+            .expect("write document");
+        zout.finish().expect("finish docx");
+    }
+
+    #[test]
+    fn short_fixed_prefix_in_translation_is_not_a_leftover() {
+        let dir = std::env::temp_dir().join(format!("mt-prefix-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("create temp dir");
+        let input = dir.join("in.docx");
+        let output = dir.join("out.docx");
+        write_docx(
+            &input,
+            r#"<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:body><w:p><w:r><w:t>Hello world</w:t></w:r></w:p></w:body></w:document>"#,
+        );
+
+        let prefix = PlaceholderPrefix::parse("MT").expect("parse prefix");
+        let session = DocumentSession::open_with(&input, &PackageOptions::default(), &prefix)
+            .expect("open session");
+        let mut blobs = Vec::new();
+        let MaskParts { mask, offsets } =
+            build_mask(&session, MaskOptions::default(), &mut blobs).expect("build mask");
+        assert_eq!(offsets.placeholder_prefix, "MT");
+
+        let text = MergeTextJson {
+            version: None,
+            producer: None,
+            placeholder_prefix: offsets.placeholder_prefix.clone(),
+            slot_texts: SlotTexts::Full(vec!["MTV news".to_string(); offsets.slots.len()]),
+            highlight_slots: Vec::new(),
+            highlight_color: None,
+            collapsed_slots: Vec::new(),
+            update_fields: false,
+        };
+        merge_mask_parts(
+            &mask,
+            &offsets,
+            &text,
+            Some(&blobs),
+            &output,
+            &MergeOptions::default(),
+        )
+        .expect("merge");
+
+        let mut zip = ZipArchive::new(Cursor::new(fs::read(&output).expect("read output")))
+            .expect("open output");
+        let mut document = String::new();
+        zip.by_name("word/document.xml")
+            .expect("document part")
+            .read_to_string(&mut document)
+            .expect("read document");
+        assert!(document.contains("MTV news"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
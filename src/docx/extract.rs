@@ -3,7 +3,7 @@ use std::collections::HashMap;
 use crate::ir::{Atom, AtomKind, FormatSpan, TextNodeKind, TextNodeRef, TranslationUnit};
 use crate::sentinels::{BR, NBH, SHY, TAB};
 
-use super::xml::{XmlEvent, XmlPart};
+use super::xml::{text_attr_key, unescape_attr, XmlAttr, XmlEvent, XmlPart};
 
 #[derive(Default, Clone)]
 struct WRunStyle {
@@ -103,8 +103,17 @@ pub fn extract_translation_units(
     let mut in_a_rpr = false;
 
     let mut current_text_elem: Option<(TextNodeKind, usize)> = None;
+    let mut stack: Vec<&str> = Vec::new();
 
     for (idx, ev) in part.events.iter().enumerate() {
+        let parent = stack.last().copied();
+        match ev {
+            XmlEvent::Start { name, .. } => stack.push(name),
+            XmlEvent::End { .. } => {
+                let _ = stack.pop();
+            }
+            _ => {}
+        }
         match ev {
             XmlEvent::Start { name, attrs } => {
                 let name_s = name.as_str();
@@ -186,10 +195,11 @@ pub fn extract_translation_units(
                     }
                 }
 
-                if name_s == "w:lvlText" {
-                    if let Some(val) = find_attr(attrs, "w:val") {
+                if let Some(key) = text_attr_key(name_s, parent, attrs) {
+                    if let Some(val) = find_attr(attrs, key) {
+                        let val = unescape_attr(val);
                         if !val.trim().is_empty() {
-                            add_attr_tu(part, idx, val, &mut tus, &mut next_id);
+                            add_attr_tu(part, idx, name_s, key, &val, &mut tus, &mut next_id);
                         }
                     }
                 }
@@ -220,10 +230,11 @@ pub fn extract_translation_units(
                     }
                 }
 
-                if name_s == "w:lvlText" {
-                    if let Some(val) = find_attr(attrs, "w:val") {
+                if let Some(key) = text_attr_key(name_s, parent, attrs) {
+                    if let Some(val) = find_attr(attrs, key) {
+                        let val = unescape_attr(val);
                         if !val.trim().is_empty() {
-                            add_attr_tu(part, idx, val, &mut tus, &mut next_id);
+                            add_attr_tu(part, idx, name_s, key, &val, &mut tus, &mut next_id);
                         }
                     }
                 }
@@ -320,14 +331,14 @@ fn push_control(atoms: &mut Vec<Atom>, kind: AtomKind, value: &str) {
     });
 }
 
-fn find_attr<'a>(attrs: &'a [(String, String)], key: &str) -> Option<&'a str> {
+fn find_attr<'a>(attrs: &'a [XmlAttr], key: &str) -> Option<&'a str> {
     attrs
         .iter()
         .find(|(k, _)| k == key)
         .map(|(_, v)| v.as_str())
 }
 
-fn parse_w_rpr_property(style: &mut WRunStyle, name: &str, attrs: &[(String, String)]) {
+fn parse_w_rpr_property(style: &mut WRunStyle, name: &str, attrs: &[XmlAttr]) {
     match name {
         "w:b" => style.b = Some(parse_w_bool(attrs)),
         "w:i" => style.i = Some(parse_w_bool(attrs)),
@@ -348,7 +359,7 @@ fn parse_w_rpr_property(style: &mut WRunStyle, name: &str, attrs: &[(String, Str
     }
 }
 
-fn parse_w_bool(attrs: &[(String, String)]) -> bool {
+fn parse_w_bool(attrs: &[XmlAttr]) -> bool {
     if let Some(v) = find_attr(attrs, "w:val") {
         let s = v.trim().to_ascii_lowercase();
         return !(s == "0" || s == "false" || s == "off" || s == "none");
@@ -453,6 +464,8 @@ fn finalize_paragraph(
 fn add_attr_tu(
     part: &XmlPart,
     idx: usize,
+    elem: &str,
+    attr: &str,
     val_s: &str,
     tus: &mut Vec<TranslationUnit>,
     next_id: &mut usize,
@@ -462,7 +475,7 @@ fn add_attr_tu(
         kind: TextNodeKind::Attr,
         elem_event_index: idx,
         text_event_index: None,
-        attr_name: Some("w:val".to_string()),
+        attr_name: Some(attr.to_string()),
         original_text: val_s.to_string(),
     };
     let atom = Atom {
@@ -479,7 +492,7 @@ fn add_attr_tu(
     tus.push(TranslationUnit {
         tu_id: *next_id,
         part_name: part.name.clone(),
-        scope_key: format!("{}#w:lvlText@{}", part.name, idx),
+        scope_key: format!("{}#{elem}@{}", part.name, idx),
         para_style: None,
         atoms: vec![atom],
         spans: vec![span],
@@ -0,0 +1,90 @@
+use crate::docx::xml::{XmlEvent, XmlName, XmlPart};
+
+/// Part holding the document settings.
+pub const SETTINGS_PART: &str = "word/settings.xml";
+
+/// `w:settings` children that follow `w:updateFields` (CT_Settings is a sequence).
+const SETTINGS_AFTER_UPDATE_FIELDS: [&str; 21] = [
+    "w:hdrShapeDefaults",
+    "w:footnotePr",
+    "w:endnotePr",
+    "w:compat",
+    "w:docVars",
+    "w:rsids",
+    "m:mathPr",
+    "w:attachedSchema",
+    "w:themeFontLang",
+    "w:clrSchemeMapping",
+    "w:doNotIncludeSubdocsInStats",
+    "w:doNotAutoCompressPictures",
+    "w:forceUpgrade",
+    "w:captions",
+    "w:readModeInkLockDown",
+    "w:smartTagType",
+    "sl:schemaLibrary",
+    "w:shapeDefaults",
+    "w:doNotEmbedSmartTags",
+    "w:decimalSymbol",
+    "w:listSeparator",
+];
+
+/// Set `<w:updateFields w:val="true"/>` in the settings part so Word refreshes the document's
+/// fields (TOC, REF, PAGEREF, ...) when it opens it. Returns whether the part changed.
+pub fn set_update_fields(part: &mut XmlPart) -> bool {
+    let Some(root) = part
+        .events
+        .iter()
+        .position(|ev| matches!(ev, XmlEvent::Start { name, .. } if name == "w:settings"))
+    else {
+        return false;
+    };
+    let mut depth = 0usize;
+    let mut insert_at = None;
+    for i in root + 1..part.events.len() {
+        let (name, is_start) = match &part.events[i] {
+            XmlEvent::End { .. } if depth == 0 => {
+                insert_at = insert_at.or(Some(i));
+                break;
+            }
+            XmlEvent::End { .. } => {
+                depth -= 1;
+                continue;
+            }
+            XmlEvent::Start { name, .. } => (name.as_str(), true),
+            XmlEvent::Empty { name, .. } => (name.as_str(), false),
+            _ => continue,
+        };
+        if depth == 0 {
+            if name == "w:updateFields" {
+                if let XmlEvent::Start { attrs, .. } | XmlEvent::Empty { attrs, .. } =
+                    &mut part.events[i]
+                {
+                    match attrs.iter_mut().find(|(k, _)| k == "w:val") {
+                        Some((_, v)) if matches!(v.as_str(), "true" | "1" | "on") => return false,
+                        Some((_, v)) => *v = "true".to_string(),
+                        // A bare `w:updateFields` is already on.
+                        None => return false,
+                    }
+                }
+                return true;
+            }
+            if insert_at.is_none() && SETTINGS_AFTER_UPDATE_FIELDS.contains(&name) {
+                insert_at = Some(i);
+            }
+        }
+        if is_start {
+            depth += 1;
+        }
+    }
+    let Some(at) = insert_at else {
+        return false;
+    };
+    part.events.insert(
+        at,
+        XmlEvent::Empty {
+            name: XmlName::from("w:updateFields"),
+            attrs: vec![(XmlName::from("w:val"), "true".to_string())],
+        },
+    );
+    true
+}
@@ -2,10 +2,13 @@ use std::collections::{HashMap, HashSet};
 use std::path::Path;
 
 use anyhow::{anyhow, Context};
+use rayon::prelude::*;
 use serde::Deserialize;
 
-use crate::docx::package::DocxPackage;
-use crate::docx::xml::{parse_xml_part, write_xml_part, XmlEvent, XmlPart};
+use crate::docx::package::{DocxPackage, PackageOptions};
+use crate::docx::xml::{
+    parse_xml_part_with, write_xml_part, ParseFix, XmlAttr, XmlEvent, XmlName, XmlPart,
+};
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct DocxFilterRules {
@@ -32,10 +35,14 @@ pub struct DocxFilterRules {
 
 impl DocxFilterRules {
     pub fn from_toml_path(path: &Path) -> anyhow::Result<Self> {
-        let bytes =
-            std::fs::read(path).with_context(|| format!("read filter rules: {}", path.display()))?;
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("read filter rules: {}", path.display()))?;
         let s = String::from_utf8(bytes).context("filter rules must be utf-8")?;
-        let rules: DocxFilterRules = toml::from_str(&s).context("parse filter rules (toml)")?;
+        Self::from_toml_str(&s)
+    }
+
+    pub fn from_toml_str(s: &str) -> anyhow::Result<Self> {
+        let rules: DocxFilterRules = toml::from_str(s).context("parse filter rules (toml)")?;
         if rules.version != 1 {
             return Err(anyhow!(
                 "unsupported filter rules version: {} (expected 1)",
@@ -75,7 +82,11 @@ fn wildcard_match(pattern: &str, text: &str) -> bool {
         return false;
     }
     if !pattern.ends_with('*') {
-        let last_seg = pattern.split('*').filter(|s| !s.is_empty()).last().unwrap_or("");
+        let last_seg = pattern
+            .split('*')
+            .filter(|s| !s.is_empty())
+            .last()
+            .unwrap_or("");
         return text.ends_with(last_seg);
     }
     true
@@ -94,33 +105,57 @@ fn should_merge_runs_for_part(rules: &DocxFilterRules, part_name: &str) -> bool
         .any(|p| wildcard_match(p, part_name))
 }
 
-pub fn filter_docx_with_rules(input_docx: &Path, output_docx: &Path, rules: &DocxFilterRules) -> anyhow::Result<()> {
-    let pkg = DocxPackage::read(input_docx)?;
+/// Returns the repairs `PackageOptions::lenient` applied while parsing.
+pub fn filter_docx_with_rules(
+    input_docx: &Path,
+    output_docx: &Path,
+    rules: &DocxFilterRules,
+    package: &PackageOptions,
+) -> anyhow::Result<Vec<ParseFix>> {
+    let pkg = DocxPackage::read_streaming_with(input_docx, package)?;
     let strip_attrs: HashSet<&str> = rules.strip_attributes.iter().map(|s| s.as_str()).collect();
     let drop_elements: HashSet<&str> = rules.drop_elements.iter().map(|s| s.as_str()).collect();
-    let drop_rpr: HashSet<&str> = rules.drop_run_properties.iter().map(|s| s.as_str()).collect();
+    let drop_rpr: HashSet<&str> = rules
+        .drop_run_properties
+        .iter()
+        .map(|s| s.as_str())
+        .collect();
     let preserve_ws_in: HashSet<&str> = rules
         .preserve_whitespace_text_in
         .iter()
         .map(|s| s.as_str())
         .collect();
 
+    let filtered: Vec<(String, Vec<u8>, Vec<ParseFix>)> = pkg
+        .xml_entries()
+        .into_par_iter()
+        .filter(|ent| !ent.data.is_empty())
+        .map(|ent| {
+            let (mut part, fixes) = parse_xml_part_with(&ent.name, &ent.data, package.lenient)
+                .with_context(|| format!("parse xml: {}", ent.name))?;
+            filter_xml_part(
+                &mut part,
+                &strip_attrs,
+                &drop_elements,
+                &drop_rpr,
+                &preserve_ws_in,
+            )?;
+            if should_merge_runs_for_part(rules, &part.name) {
+                part.events = merge_adjacent_text_runs_in_paragraphs(&part.events);
+            }
+            let bytes =
+                write_xml_part(&part).with_context(|| format!("serialize xml: {}", ent.name))?;
+            Ok((ent.name.clone(), bytes, fixes))
+        })
+        .collect::<anyhow::Result<_>>()?;
+    let mut fixes = Vec::new();
     let mut replacements: HashMap<String, Vec<u8>> = HashMap::new();
-    for ent in pkg.xml_entries() {
-        if ent.data.is_empty() {
-            continue;
-        }
-        let mut part = parse_xml_part(&ent.name, &ent.data)
-            .with_context(|| format!("parse xml: {}", ent.name))?;
-        filter_xml_part(&mut part, &strip_attrs, &drop_elements, &drop_rpr, &preserve_ws_in)?;
-        if should_merge_runs_for_part(rules, &part.name) {
-            part.events = merge_adjacent_text_runs_in_paragraphs(&part.events);
-        }
-        let bytes = write_xml_part(&part).with_context(|| format!("serialize xml: {}", ent.name))?;
-        replacements.insert(ent.name.clone(), bytes);
+    for (name, bytes, part_fixes) in filtered {
+        fixes.extend(part_fixes);
+        replacements.insert(name, bytes);
     }
     pkg.write_with_replacements(output_docx, &replacements)?;
-    Ok(())
+    Ok(fixes)
 }
 
 fn filter_xml_part(
@@ -131,10 +166,10 @@ fn filter_xml_part(
     preserve_ws_in: &HashSet<&str>,
 ) -> anyhow::Result<()> {
     let mut out: Vec<XmlEvent> = Vec::with_capacity(part.events.len());
-    let mut stack: Vec<String> = Vec::new();
+    let mut stack: Vec<XmlName> = Vec::new();
 
     let mut skip_level: usize = 0;
-    let mut skip_element: Option<String> = None;
+    let mut skip_element: Option<XmlName> = None;
 
     let mut i = 0usize;
     while i < part.events.len() {
@@ -185,7 +220,9 @@ fn filter_xml_part(
             }
             XmlEvent::Empty { name, mut attrs } => {
                 let in_rpr = stack.last().map(|s| s.as_str()) == Some("w:rPr");
-                if (in_rpr && drop_rpr.contains(name.as_str())) || drop_elements.contains(name.as_str()) {
+                if (in_rpr && drop_rpr.contains(name.as_str()))
+                    || drop_elements.contains(name.as_str())
+                {
                     i += 1;
                     continue;
                 }
@@ -236,7 +273,7 @@ fn filter_xml_part(
 
 #[derive(Clone)]
 struct NormalizedRun {
-    run_start_attrs: Vec<(String, String)>,
+    run_start_attrs: Vec<XmlAttr>,
     rpr_events: Vec<XmlEvent>,
     rpr_fingerprint: Vec<String>,
     text: String,
@@ -245,7 +282,7 @@ struct NormalizedRun {
 
 fn merge_adjacent_text_runs_in_paragraphs(events: &[XmlEvent]) -> Vec<XmlEvent> {
     let mut out: Vec<XmlEvent> = Vec::with_capacity(events.len());
-    let mut stack: Vec<String> = Vec::new();
+    let mut stack: Vec<XmlName> = Vec::new();
     let mut pending: Option<NormalizedRun> = None;
 
     let mut i = 0usize;
@@ -361,7 +398,7 @@ fn normalize_text_run(run_events: &[XmlEvent]) -> Option<NormalizedRun> {
         return None;
     }
 
-    let mut stack: Vec<String> = Vec::new();
+    let mut stack: Vec<XmlName> = Vec::new();
     let mut rpr_events: Vec<XmlEvent> = Vec::new();
     let mut rpr_fingerprint: Vec<String> = Vec::new();
     let mut text = String::new();
@@ -371,7 +408,11 @@ fn normalize_text_run(run_events: &[XmlEvent]) -> Option<NormalizedRun> {
     let mut in_t = false;
     let mut allowed = true;
 
-    for ev in run_events.iter().skip(1).take(run_events.len().saturating_sub(2)) {
+    for ev in run_events
+        .iter()
+        .skip(1)
+        .take(run_events.len().saturating_sub(2))
+    {
         match ev {
             XmlEvent::Start { name, attrs } => {
                 if !in_rpr && !in_t && stack.is_empty() {
@@ -385,7 +426,10 @@ fn normalize_text_run(run_events: &[XmlEvent]) -> Option<NormalizedRun> {
                     }
                     if name == "w:t" {
                         in_t = true;
-                        if attrs.iter().any(|(k, v)| k == "xml:space" && v == "preserve") {
+                        if attrs
+                            .iter()
+                            .any(|(k, v)| k == "xml:space" && v == "preserve")
+                        {
                             has_xml_space_preserve = true;
                         }
                         stack.push(name.clone());
@@ -494,7 +538,9 @@ fn normalize_text_run(run_events: &[XmlEvent]) -> Option<NormalizedRun> {
 
     let rpr_events = if rpr_events.is_empty() {
         Vec::new()
-    } else if rpr_events.len() == 2 && matches!(rpr_events.first(), Some(XmlEvent::Start { name, .. }) if name == "w:rPr") {
+    } else if rpr_events.len() == 2
+        && matches!(rpr_events.first(), Some(XmlEvent::Start { name, .. }) if name == "w:rPr")
+    {
         // Only <w:rPr></w:rPr>
         Vec::new()
     } else {
@@ -510,7 +556,7 @@ fn normalize_text_run(run_events: &[XmlEvent]) -> Option<NormalizedRun> {
     })
 }
 
-fn fingerprint_start_like(name: &str, attrs: &[(String, String)], empty: bool) -> String {
+fn fingerprint_start_like(name: &str, attrs: &[XmlAttr], empty: bool) -> String {
     let mut a = attrs.to_vec();
     a.sort_by(|(ka, va), (kb, vb)| ka.cmp(kb).then(va.cmp(vb)));
     let mut s = String::new();
@@ -533,25 +579,27 @@ fn fingerprint_start_like(name: &str, attrs: &[(String, String)], empty: bool) -
 fn render_run(run: &NormalizedRun) -> Vec<XmlEvent> {
     let mut out: Vec<XmlEvent> = Vec::new();
     out.push(XmlEvent::Start {
-        name: "w:r".to_string(),
+        name: "w:r".into(),
         attrs: run.run_start_attrs.clone(),
     });
     out.extend(run.rpr_events.clone());
 
-    let mut t_attrs: Vec<(String, String)> = Vec::new();
+    let mut t_attrs: Vec<XmlAttr> = Vec::new();
     if run.has_xml_space_preserve
         || run.text.starts_with(|c: char| c.is_whitespace())
         || run.text.ends_with(|c: char| c.is_whitespace())
     {
-        t_attrs.push(("xml:space".to_string(), "preserve".to_string()));
+        t_attrs.push(("xml:space".into(), "preserve".to_string()));
     }
     out.push(XmlEvent::Start {
-        name: "w:t".to_string(),
+        name: "w:t".into(),
         attrs: t_attrs,
     });
-    out.push(XmlEvent::Text { text: run.text.clone() });
-    out.push(XmlEvent::End { name: "w:t".to_string() });
+    out.push(XmlEvent::Text {
+        text: run.text.clone(),
+    });
+    out.push(XmlEvent::End { name: "w:t".into() });
 
-    out.push(XmlEvent::End { name: "w:r".to_string() });
+    out.push(XmlEvent::End { name: "w:r".into() });
     out
 }
@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::Context;
+use rayon::prelude::*;
+
+use crate::docx::package::{DocxPackage, PackageOptions};
+use crate::docx::xml::{parse_xml_part_with, write_xml_part, ParseFix, XmlEvent, XmlName, XmlPart};
+
+/// `w:highlight` color used when none is configured.
+pub const DEFAULT_HIGHLIGHT: &str = "yellow";
+
+/// ST_HighlightColor values accepted by Word.
+pub const HIGHLIGHT_COLORS: [&str; 16] = [
+    "yellow",
+    "green",
+    "cyan",
+    "magenta",
+    "blue",
+    "red",
+    "darkBlue",
+    "darkCyan",
+    "darkGreen",
+    "darkMagenta",
+    "darkRed",
+    "darkYellow",
+    "darkGray",
+    "lightGray",
+    "black",
+    "white",
+];
+
+/// `w:rPr` children that follow `w:highlight` (CT_RPr is a sequence).
+const RPR_AFTER_HIGHLIGHT: [&str; 14] = [
+    "w:u",
+    "w:effect",
+    "w:bdr",
+    "w:shd",
+    "w:fitText",
+    "w:vertAlign",
+    "w:rtl",
+    "w:cs",
+    "w:em",
+    "w:lang",
+    "w:eastAsianLayout",
+    "w:specVanish",
+    "w:oMath",
+    "w:rPrChange",
+];
+
+/// Start events of the `w:t` and `w:r` around the text event at `text_idx`; `None` for text
+/// anywhere else.
+pub(crate) fn enclosing_run_text(part: &XmlPart, text_idx: usize) -> Option<(usize, usize)> {
+    // The two innermost open elements around the text: w:t inside w:r.
+    let mut open: Vec<(usize, bool)> = Vec::new();
+    let mut depth = 0usize;
+    for i in (0..text_idx).rev() {
+        match &part.events[i] {
+            XmlEvent::End { .. } => depth += 1,
+            XmlEvent::Start { name, .. } if depth == 0 => {
+                let expected = if open.is_empty() { "w:t" } else { "w:r" };
+                open.push((i, name == expected));
+                if open.len() == 2 {
+                    break;
+                }
+            }
+            XmlEvent::Start { .. } => depth -= 1,
+            _ => {}
+        }
+    }
+    match open[..] {
+        [(t_idx, true), (r_idx, true)] => Some((t_idx, r_idx)),
+        _ => None,
+    }
+}
+
+/// Give the run around the text event at `text_idx` a `w:highlight` (only `w:r/w:t` text;
+/// anything else is left alone). Returns whether the run was highlighted.
+pub(crate) fn highlight_run_text(part: &mut XmlPart, text_idx: usize, color: &str) -> bool {
+    let Some((_, r_idx)) = enclosing_run_text(part, text_idx) else {
+        return false;
+    };
+
+    let highlight = || XmlEvent::Empty {
+        name: XmlName::from("w:highlight"),
+        attrs: vec![(XmlName::from("w:val"), color.to_string())],
+    };
+    let first_child = (r_idx + 1..part.events.len())
+        .find(|&i| !matches!(&part.events[i], XmlEvent::Text { text } if text.trim().is_empty()));
+    let Some(first_child) = first_child else {
+        return false;
+    };
+    match &part.events[first_child] {
+        XmlEvent::Start { name, .. } if name == "w:rPr" => {
+            let mut depth = 0usize;
+            let mut insert_at = None;
+            for i in first_child + 1..part.events.len() {
+                let (name, is_start) = match &part.events[i] {
+                    XmlEvent::End { .. } if depth == 0 => {
+                        insert_at = insert_at.or(Some(i));
+                        break;
+                    }
+                    XmlEvent::End { .. } => {
+                        depth -= 1;
+                        continue;
+                    }
+                    XmlEvent::Start { name, .. } => (name.as_str(), true),
+                    XmlEvent::Empty { name, .. } => (name.as_str(), false),
+                    _ => continue,
+                };
+                if depth == 0 {
+                    if name == "w:highlight" {
+                        if let XmlEvent::Start { attrs, .. } | XmlEvent::Empty { attrs, .. } =
+                            &mut part.events[i]
+                        {
+                            match attrs.iter_mut().find(|(k, _)| k == "w:val") {
+                                Some((_, v)) => *v = color.to_string(),
+                                None => attrs.push((XmlName::from("w:val"), color.to_string())),
+                            }
+                        }
+                        return true;
+                    }
+                    if insert_at.is_none() && RPR_AFTER_HIGHLIGHT.contains(&name) {
+                        insert_at = Some(i);
+                    }
+                }
+                if is_start {
+                    depth += 1;
+                }
+            }
+            let Some(at) = insert_at else {
+                return false;
+            };
+            part.events.insert(at, highlight());
+        }
+        XmlEvent::Empty { name, attrs } if name == "w:rPr" => {
+            let (name, attrs) = (name.clone(), attrs.clone());
+            part.events.splice(
+                first_child..=first_child,
+                [
+                    XmlEvent::Start {
+                        name: name.clone(),
+                        attrs,
+                    },
+                    highlight(),
+                    XmlEvent::End { name },
+                ],
+            );
+        }
+        _ => {
+            let rpr = XmlName::from("w:rPr");
+            part.events.splice(
+                r_idx + 1..r_idx + 1,
+                [
+                    XmlEvent::Start {
+                        name: rpr.clone(),
+                        attrs: Vec::new(),
+                    },
+                    highlight(),
+                    XmlEvent::End { name: rpr },
+                ],
+            );
+        }
+    }
+    true
+}
+
+/// Remove the `w:highlight` of the given color from every run (`--clear-highlights`, after
+/// review), dropping run properties that end up empty. Other colors and paragraph-mark
+/// properties are kept. Returns the number of highlights removed and the `--lenient-parse`
+/// repairs of the parts that were rewritten.
+pub fn clear_highlights(
+    input_docx: &Path,
+    output_docx: &Path,
+    color: &str,
+    package: &PackageOptions,
+) -> anyhow::Result<(usize, Vec<ParseFix>)> {
+    let pkg = DocxPackage::read_streaming_with(input_docx, package)?;
+    let cleared: Vec<(String, Vec<u8>, usize, Vec<ParseFix>)> = pkg
+        .xml_entries()
+        .into_par_iter()
+        .filter(|ent| ent.data.windows(11).any(|w| w == b"w:highlight"))
+        .map(|ent| {
+            let (mut part, fixes) = parse_xml_part_with(&ent.name, &ent.data, package.lenient)
+                .with_context(|| format!("parse xml: {}", ent.name))?;
+            let removed = clear_part_highlights(&mut part, color);
+            let bytes =
+                write_xml_part(&part).with_context(|| format!("serialize xml: {}", ent.name))?;
+            Ok((ent.name.clone(), bytes, removed, fixes))
+        })
+        .collect::<anyhow::Result<_>>()?;
+    let mut removed = 0usize;
+    let mut fixes = Vec::new();
+    let mut replacements: HashMap<String, Vec<u8>> = HashMap::new();
+    for (name, bytes, n, part_fixes) in cleared {
+        if n > 0 {
+            removed += n;
+            fixes.extend(part_fixes);
+            replacements.insert(name, bytes);
+        }
+    }
+    pkg.write_with_replacements(output_docx, &replacements)?;
+    Ok((removed, fixes))
+}
+
+fn clear_part_highlights(part: &mut XmlPart, color: &str) -> usize {
+    let mut out: Vec<XmlEvent> = Vec::with_capacity(part.events.len());
+    let mut stack: Vec<XmlName> = Vec::new();
+    let mut removed = 0usize;
+    let mut removed_in_rpr = false;
+    for ev in part.events.drain(..) {
+        let in_run_rpr = stack.len() >= 2
+            && stack[stack.len() - 1] == "w:rPr"
+            && stack[stack.len() - 2] == "w:r";
+        match &ev {
+            XmlEvent::Empty { name, attrs }
+                if in_run_rpr
+                    && name == "w:highlight"
+                    && attrs.iter().any(|(k, v)| k == "w:val" && v == color) =>
+            {
+                removed += 1;
+                removed_in_rpr = true;
+                continue;
+            }
+            XmlEvent::Start { name, .. } => {
+                if name == "w:rPr" {
+                    removed_in_rpr = false;
+                }
+                stack.push(name.clone());
+            }
+            XmlEvent::End { name } => {
+                let _ = stack.pop();
+                // `<w:rPr></w:rPr>` left behind by the removal goes too.
+                if in_run_rpr
+                    && name == "w:rPr"
+                    && removed_in_rpr
+                    && matches!(out.last(), Some(XmlEvent::Start { name, .. }) if name == "w:rPr")
+                {
+                    out.pop();
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        out.push(ev);
+    }
+    part.events = out;
+    removed
+}
@@ -0,0 +1,85 @@
+//! `[[links.rewrite]]`: regex rewrites of external hyperlink targets (`word/_rels/*.rels`),
+//! applied when a translated document is merged, e.g. `en.wikipedia.org` -> `zh.wikipedia.org`.
+
+use anyhow::{anyhow, Context};
+use quick_xml::escape::{escape, unescape};
+use regex::Regex;
+
+use crate::docx::xml::{parse_xml_part, write_xml_part, XmlEvent};
+
+#[derive(Clone, Debug)]
+pub struct LinkRewrite {
+    pattern: Regex,
+    replacement: String,
+}
+
+impl LinkRewrite {
+    /// `replacement` may use `$1` / `${name}` capture references.
+    pub fn new(pattern: &str, replacement: &str) -> anyhow::Result<Self> {
+        let pattern = Regex::new(pattern)
+            .with_context(|| format!("invalid link rewrite pattern: {pattern:?}"))?;
+        Ok(Self {
+            pattern,
+            replacement: replacement.to_string(),
+        })
+    }
+}
+
+fn is_word_rels(name: &str) -> bool {
+    name.strip_prefix("word/_rels/")
+        .is_some_and(|rest| !rest.contains('/') && rest.ends_with(".rels"))
+}
+
+/// Every rule in order applied to `url`.
+pub fn rewrite_url(rules: &[LinkRewrite], url: &str) -> String {
+    rules.iter().fold(url.to_string(), |cur, rule| {
+        rule.pattern
+            .replace_all(&cur, rule.replacement.as_str())
+            .into_owned()
+    })
+}
+
+/// The rewritten part when `part_name` is a `word/_rels/*.rels` part with at least one
+/// external target changed by `rules`.
+pub fn rewrite_rels_targets(
+    rules: &[LinkRewrite],
+    part_name: &str,
+    data: &[u8],
+) -> anyhow::Result<Option<Vec<u8>>> {
+    if rules.is_empty() || !is_word_rels(part_name) || data.is_empty() {
+        return Ok(None);
+    }
+    let mut part =
+        parse_xml_part(part_name, data).with_context(|| format!("parse xml: {part_name}"))?;
+    let mut changed = false;
+    for ev in &mut part.events {
+        let (XmlEvent::Empty { name, attrs } | XmlEvent::Start { name, attrs }) = ev else {
+            continue;
+        };
+        if name != "Relationship"
+            || !attrs
+                .iter()
+                .any(|(k, v)| k == "TargetMode" && v.eq_ignore_ascii_case("External"))
+        {
+            continue;
+        }
+        let Some((_, target)) = attrs.iter_mut().find(|(k, _)| k == "Target") else {
+            continue;
+        };
+        // Attribute values are kept escaped; rules see the plain URL.
+        let url = unescape(target)
+            .map_err(|e| anyhow!("{part_name}: bad Target {target:?}: {e}"))?
+            .into_owned();
+        let rewritten = rewrite_url(rules, &url);
+        if rewritten != url {
+            *target = escape(rewritten.as_str()).into_owned();
+            changed = true;
+        }
+    }
+    if !changed {
+        return Ok(None);
+    }
+    write_xml_part(&part)
+        .with_context(|| format!("serialize xml: {part_name}"))
+        .map(Some)
+}
@@ -0,0 +1,109 @@
+//! `.mtb` bundle: mask.json + offsets.json + mask blobs + text.json packed into one zip, so a
+//! DOCX can be extracted, translated (edit `text.json`) and merged without juggling four files.
+
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::docx::decompose::{
+    build_mask, decode_blobs, merge_mask_parts, BlobCompression, MaskJson, MaskOptions, MaskParts,
+    MergeOptions, MergeTextJson, OffsetsJson, SlotSubstitution,
+};
+use crate::docx::pure_text::pure_text_from_session;
+use crate::docx::session::DocumentSession;
+
+const MASK_JSON: &str = "mask.json";
+const OFFSETS_JSON: &str = "offsets.json";
+const BLOBS_BIN: &str = "mask.blobs.bin";
+const TEXT_JSON: &str = "text.json";
+
+/// Extract the session's document into a single bundle (the bundled text.json holds the
+/// source text).
+pub fn write_mask_bundle(
+    session: &DocumentSession,
+    bundle: &Path,
+    opts: MaskOptions,
+) -> anyhow::Result<()> {
+    let text = pure_text_from_session(session)?;
+    let f = File::create(bundle).with_context(|| format!("create bundle: {}", bundle.display()))?;
+    let mut zout = ZipWriter::new(f);
+
+    // Blobs first, streamed straight into the bundle; zstd output does not deflate any further.
+    let blob_opts = SimpleFileOptions::default().compression_method(match opts.compression {
+        BlobCompression::None => CompressionMethod::Deflated,
+        BlobCompression::Zstd => CompressionMethod::Stored,
+    });
+    zout.start_file(BLOBS_BIN, blob_opts)
+        .with_context(|| format!("start bundle entry: {BLOBS_BIN}"))?;
+    let MaskParts { mut mask, offsets } = build_mask(session, opts, &mut zout)?;
+    mask.blobs_file = Some(BLOBS_BIN.to_string());
+
+    let json_opts = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let files: [(&str, Vec<u8>); 3] = [
+        (
+            MASK_JSON,
+            serde_json::to_vec_pretty(&mask).context("serialize mask json")?,
+        ),
+        (
+            OFFSETS_JSON,
+            serde_json::to_vec_pretty(&offsets).context("serialize offsets json")?,
+        ),
+        (
+            TEXT_JSON,
+            serde_json::to_vec_pretty(&text).context("serialize pure text json")?,
+        ),
+    ];
+    for (name, data) in files {
+        zout.start_file(name, json_opts)
+            .with_context(|| format!("start bundle entry: {name}"))?;
+        zout.write_all(&data)
+            .with_context(|| format!("write bundle entry: {name}"))?;
+    }
+    zout.finish().context("finish bundle")?;
+    Ok(())
+}
+
+/// Merge a bundle into `output_docx`; `text_json` overrides the bundled text.json when given.
+pub fn merge_mask_bundle(
+    bundle: &Path,
+    text_json: Option<&Path>,
+    output_docx: &Path,
+    opts: &MergeOptions,
+) -> anyhow::Result<Vec<SlotSubstitution>> {
+    let f = File::open(bundle).with_context(|| format!("open bundle: {}", bundle.display()))?;
+    let mut zip = ZipArchive::new(f).context("read bundle zip")?;
+
+    let mask: MaskJson =
+        serde_json::from_slice(&read_entry(&mut zip, MASK_JSON)?).context("parse mask json")?;
+    let offsets: OffsetsJson = serde_json::from_slice(&read_entry(&mut zip, OFFSETS_JSON)?)
+        .context("parse offsets json")?;
+    let text: MergeTextJson = match text_json {
+        Some(p) => serde_json::from_slice(
+            &fs::read(p).with_context(|| format!("read text json: {}", p.display()))?,
+        ),
+        None => serde_json::from_slice(&read_entry(&mut zip, TEXT_JSON)?),
+    }
+    .context("parse text json")?;
+    let blobs = match mask.blobs_file.as_deref() {
+        Some(name) => Some(decode_blobs(
+            read_entry(&mut zip, name)?,
+            mask.blobs_compression,
+        )?),
+        None => None,
+    };
+    merge_mask_parts(&mask, &offsets, &text, blobs.as_deref(), output_docx, opts)
+}
+
+fn read_entry(zip: &mut ZipArchive<File>, name: &str) -> anyhow::Result<Vec<u8>> {
+    let mut file = zip
+        .by_name(name)
+        .map_err(|_| anyhow!("bundle is missing {name}"))?;
+    let mut data = Vec::with_capacity(file.size() as usize);
+    file.read_to_end(&mut data)
+        .with_context(|| format!("read bundle entry: {name}"))?;
+    Ok(data)
+}
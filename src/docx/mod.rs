@@ -1,9 +1,20 @@
-pub mod extract;
 pub mod apply;
+pub mod artifact;
+pub mod comments;
+pub mod compare;
 pub mod decompose;
+pub mod encrypted;
+pub mod extract;
+pub mod fields;
 pub mod filter;
-pub mod pure_text;
-pub mod structure;
+pub mod highlight;
+pub mod links;
+pub mod mask_bundle;
 pub mod package;
 pub mod project;
+pub mod pure_text;
+pub mod sanitize;
+pub mod session;
+pub mod structure;
+pub mod unsupported;
 pub mod xml;
@@ -1,14 +1,170 @@
 use std::collections::HashMap;
+use std::fmt;
 use std::fs::File;
-use std::io::{Read, Write};
-use std::path::Path;
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-use anyhow::Context;
-use zip::write::SimpleFileOptions;
+use anyhow::{anyhow, Context};
+use zip::read::ZipFile;
+use zip::write::FullFileOptions;
 use zip::{CompressionMethod, ZipArchive, ZipWriter};
 
+use crate::docx::encrypted::{decrypt_package, is_compound_file};
+use crate::errors::{ErrorKind, ResultExt};
+
+/// Caps applied while opening a package, so a zip bomb fails fast instead of exhausting memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PackageLimits {
+    pub max_entries: usize,
+    pub max_entry_bytes: u64,
+    pub max_total_bytes: u64,
+}
+
+impl Default for PackageLimits {
+    fn default() -> Self {
+        Self {
+            max_entries: 20_000,
+            max_entry_bytes: 1024 << 20,
+            max_total_bytes: 4096 << 20,
+        }
+    }
+}
+
+/// How a package is opened: size caps, the password of an encrypted input and whether its XML is
+/// parsed leniently. Passed with every
+/// read (`PipelineConfig::package` in the pipeline), so one process can open several documents
+/// with different settings.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct PackageOptions {
+    pub limits: PackageLimits,
+    /// Password for encrypted inputs (`--password`).
+    pub password: Option<String>,
+    /// Repair known XML problems before parsing instead of failing (`--lenient-parse`).
+    pub lenient: bool,
+}
+
+impl fmt::Debug for PackageOptions {
+    // Configs are dumped into trace bundles; never the password.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PackageOptions")
+            .field("limits", &self.limits)
+            .field("password", &self.password.as_ref().map(|_| "<set>"))
+            .field("lenient", &self.lenient)
+            .finish()
+    }
+}
+
+/// Zip bytes of a package: the file itself, or the decrypted copy of a password-protected one.
+pub enum PackageReader {
+    File(File),
+    Memory(Cursor<Arc<[u8]>>),
+}
+
+impl Read for PackageReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Self::File(f) => f.read(buf),
+            Self::Memory(c) => c.read(buf),
+        }
+    }
+}
+
+impl Seek for PackageReader {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        match self {
+            Self::File(f) => f.seek(pos),
+            Self::Memory(c) => c.seek(pos),
+        }
+    }
+}
+
+/// Zip bytes of a DOCX, decrypted first when it is an encrypted OLE package. The decrypted
+/// bytes are returned too, so the package can be reopened without decrypting again.
+fn open_package_reader(
+    path: &Path,
+    options: &PackageOptions,
+) -> anyhow::Result<(PackageReader, Option<Arc<[u8]>>)> {
+    let mut f = File::open(path).with_context(|| format!("open docx: {}", path.display()))?;
+    let mut head = [0u8; 8];
+    let n = f.read(&mut head).context("read docx header")?;
+    if is_compound_file(&head[..n]) {
+        let bytes: Arc<[u8]> = decrypt_package(path, options.password.as_deref())
+            .kind(ErrorKind::InputDocx, "open docx")?
+            .into();
+        Ok((
+            PackageReader::Memory(Cursor::new(bytes.clone())),
+            Some(bytes),
+        ))
+    } else {
+        f.seek(SeekFrom::Start(0)).context("read docx header")?;
+        Ok((PackageReader::File(f), None))
+    }
+}
+
+/// Tracks entry count and decompressed bytes against the package limits while reading.
+pub(crate) struct LimitGuard {
+    limits: PackageLimits,
+    total: u64,
+}
+
+impl LimitGuard {
+    pub(crate) fn new(limits: PackageLimits, entries: usize) -> anyhow::Result<Self> {
+        if entries > limits.max_entries {
+            return Err(anyhow!(
+                "package has {entries} zip entries, limit is {} ([input] max_entries)",
+                limits.max_entries
+            ))
+            .kind(ErrorKind::InputDocx, "package limits");
+        }
+        Ok(Self { limits, total: 0 })
+    }
+
+    /// Check an entry's declared size before reading it.
+    pub(crate) fn check_declared(&self, name: &str, size: u64) -> anyhow::Result<()> {
+        self.check(name, size, self.total.saturating_add(size))
+    }
+
+    /// Account for bytes actually read (declared sizes can lie).
+    pub(crate) fn add(&mut self, name: &str, size: u64) -> anyhow::Result<()> {
+        self.total = self.total.saturating_add(size);
+        self.check(name, size, self.total)
+    }
+
+    /// Reader over one entry that stops one byte past the per-entry limit.
+    pub(crate) fn entry_reader<R: Read>(&self, r: R) -> std::io::Take<R> {
+        r.take(self.limits.max_entry_bytes.saturating_add(1))
+    }
+
+    fn check(&self, name: &str, size: u64, total: u64) -> anyhow::Result<()> {
+        if size > self.limits.max_entry_bytes {
+            return Err(anyhow!(
+                "zip entry {name} decompresses to more than {} MiB ([input] max_entry_mb)",
+                self.limits.max_entry_bytes >> 20
+            ))
+            .kind(ErrorKind::InputDocx, "package limits");
+        }
+        if total > self.limits.max_total_bytes {
+            return Err(anyhow!(
+                "package decompresses to more than {} MiB ([input] max_total_mb)",
+                self.limits.max_total_bytes >> 20
+            ))
+            .kind(ErrorKind::InputDocx, "package limits");
+        }
+        Ok(())
+    }
+}
+
 pub struct DocxPackage {
     pub entries: Vec<DocxEntry>,
+    /// Set by `read_streaming`: non-markup entries were not loaded and are copied from here.
+    pub source: Option<PathBuf>,
+    /// Archive comment (end of central directory record).
+    pub comment: Vec<u8>,
+    /// Options the package was read with (reused when the source is reopened).
+    pub options: PackageOptions,
+    /// Decrypted zip of an encrypted `source`, kept so `open_source` does not decrypt again.
+    decrypted: Option<Arc<[u8]>>,
 }
 
 pub struct DocxEntry {
@@ -18,27 +174,181 @@ pub struct DocxEntry {
     pub last_modified: zip::DateTime,
     pub unix_mode: Option<u32>,
     pub is_dir: bool,
+    /// Raw extra fields minus the zip64 record, which the writer regenerates.
+    pub extra_data: Vec<u8>,
+    /// Stored with zip64 sizes (some packagers always do; required past 4 GiB).
+    pub large_file: bool,
+}
+
+/// zip64 extended information extra field.
+const ZIP64_EXTRA_ID: u16 = 0x0001;
+
+/// Split raw extra data into `(header id, body)` records; a truncated tail is dropped.
+pub fn extra_fields(raw: &[u8]) -> Vec<(u16, &[u8])> {
+    let mut out = Vec::new();
+    let mut rest = raw;
+    while rest.len() >= 4 {
+        let id = u16::from_le_bytes([rest[0], rest[1]]);
+        let len = u16::from_le_bytes([rest[2], rest[3]]) as usize;
+        if rest.len() < 4 + len {
+            break;
+        }
+        out.push((id, &rest[4..4 + len]));
+        rest = &rest[4 + len..];
+    }
+    out
+}
+
+impl DocxEntry {
+    fn from_zip(file: &ZipFile<'_>, data: Vec<u8>) -> Self {
+        let mut extra_data = Vec::new();
+        let mut large_file = file.size().max(file.compressed_size()) >= u32::MAX as u64;
+        for (id, body) in extra_fields(file.extra_data().unwrap_or_default()) {
+            if id == ZIP64_EXTRA_ID {
+                large_file = true;
+                continue;
+            }
+            extra_data.extend_from_slice(&id.to_le_bytes());
+            extra_data.extend_from_slice(&(body.len() as u16).to_le_bytes());
+            extra_data.extend_from_slice(body);
+        }
+        Self {
+            name: file.name().to_string(),
+            data,
+            compression: file.compression(),
+            last_modified: file.last_modified().unwrap_or_default(),
+            unix_mode: file.unix_mode(),
+            is_dir: file.is_dir(),
+            extra_data,
+            large_file,
+        }
+    }
+
+    /// Writer options reproducing this entry's metadata.
+    fn write_options(&self) -> anyhow::Result<FullFileOptions<'static>> {
+        let mut opts = FullFileOptions::default()
+            .compression_method(self.compression)
+            .last_modified_time(self.last_modified)
+            .large_file(self.large_file || self.data.len() as u64 >= u32::MAX as u64);
+        if let Some(mode) = self.unix_mode {
+            opts = opts.unix_permissions(mode);
+        }
+        for (id, body) in extra_fields(&self.extra_data) {
+            opts.add_extra_data(id, body.into(), false)
+                .with_context(|| format!("zip extra field {id:#06x}: {}", self.name))?;
+        }
+        Ok(opts)
+    }
 }
 
 impl DocxPackage {
+    /// Package without a source zip, written from `entries` alone.
+    pub fn from_entries(entries: Vec<DocxEntry>, comment: Vec<u8>) -> Self {
+        Self {
+            entries,
+            source: None,
+            comment,
+            options: PackageOptions::default(),
+            decrypted: None,
+        }
+    }
+
     pub fn read(path: &Path) -> anyhow::Result<Self> {
-        let f = File::open(path).with_context(|| format!("open docx: {}", path.display()))?;
-        let mut zip = ZipArchive::new(f).context("read zip")?;
+        Self::read_with(path, &PackageOptions::default())
+    }
+
+    pub fn read_with(path: &Path, options: &PackageOptions) -> anyhow::Result<Self> {
+        let (reader, _) = open_package_reader(path, options)?;
+        let mut zip = ZipArchive::new(reader).context("read zip")?;
+        let mut guard = LimitGuard::new(options.limits, zip.len())?;
         let mut entries = Vec::new();
+        let mut header_starts = Vec::new();
         for i in 0..zip.len() {
             let mut file = zip.by_index(i).context("zip entry")?;
+            header_starts.push(file.header_start());
+            guard.check_declared(file.name(), file.size())?;
             let mut data = Vec::with_capacity(file.size() as usize);
-            file.read_to_end(&mut data).context("read zip entry")?;
-            entries.push(DocxEntry {
-                name: file.name().to_string(),
-                data,
-                compression: file.compression(),
-                last_modified: file.last_modified().unwrap_or_default(),
-                unix_mode: file.unix_mode(),
-                is_dir: file.is_dir(),
-            });
+            guard
+                .entry_reader(&mut file)
+                .read_to_end(&mut data)
+                .context("read zip entry")?;
+            guard.add(file.name(), data.len() as u64)?;
+            entries.push(DocxEntry::from_zip(&file, data));
+        }
+        let comment = read_archive_meta(zip, &mut entries, &header_starts)?;
+        Ok(Self {
+            entries,
+            source: None,
+            comment,
+            options: options.clone(),
+            decrypted: None,
+        })
+    }
+
+    /// Low-memory read: only XML / `.rels` parts are loaded. Other entries (media, embedded
+    /// objects) keep empty `data` and are raw-copied from the source zip on write, so a large
+    /// DOCX never holds its media in RAM.
+    pub fn read_streaming(path: &Path) -> anyhow::Result<Self> {
+        Self::read_streaming_with(path, &PackageOptions::default())
+    }
+
+    pub fn read_streaming_with(path: &Path, options: &PackageOptions) -> anyhow::Result<Self> {
+        let (reader, decrypted) = open_package_reader(path, options)?;
+        let mut zip = ZipArchive::new(reader).context("read zip")?;
+        let mut guard = LimitGuard::new(options.limits, zip.len())?;
+        let mut entries = Vec::new();
+        let mut header_starts = Vec::new();
+        for i in 0..zip.len() {
+            let mut file = zip.by_index(i).context("zip entry")?;
+            header_starts.push(file.header_start());
+            guard.check_declared(file.name(), file.size())?;
+            let mut data = Vec::new();
+            if is_markup_part(file.name()) {
+                data.reserve(file.size() as usize);
+                guard
+                    .entry_reader(&mut file)
+                    .read_to_end(&mut data)
+                    .context("read zip entry")?;
+                guard.add(file.name(), data.len() as u64)?;
+            } else {
+                // Streamed later; count the declared size so the total cap still holds.
+                guard.add(file.name(), file.size())?;
+            }
+            entries.push(DocxEntry::from_zip(&file, data));
         }
-        Ok(Self { entries })
+        let comment = read_archive_meta(zip, &mut entries, &header_starts)?;
+        Ok(Self {
+            entries,
+            source: Some(path.to_path_buf()),
+            comment,
+            options: options.clone(),
+            decrypted,
+        })
+    }
+
+    /// Reopen the source zip of a streaming package (entry indices match `entries`).
+    pub fn open_source(&self) -> anyhow::Result<Option<ZipArchive<PackageReader>>> {
+        let Some(path) = self.source.as_ref() else {
+            return Ok(None);
+        };
+        let reader = match &self.decrypted {
+            Some(bytes) => PackageReader::Memory(Cursor::new(bytes.clone())),
+            None => PackageReader::File(
+                File::open(path).with_context(|| format!("open docx: {}", path.display()))?,
+            ),
+        };
+        Ok(Some(ZipArchive::new(reader).context("read zip")?))
+    }
+
+    /// Limit guard for reading this package's entries again (mask blobs, raw copies).
+    pub(crate) fn limit_guard(&self) -> anyhow::Result<LimitGuard> {
+        LimitGuard::new(self.options.limits, 0)
+    }
+
+    /// Whether entry `index` was left in the source zip (see `read_streaming`).
+    pub fn is_streamed(&self, index: usize) -> bool {
+        let ent = &self.entries[index];
+        self.source.is_some() && !ent.is_dir && !is_markup_part(&ent.name)
     }
 
     pub fn write_with_replacements(
@@ -49,17 +359,34 @@ impl DocxPackage {
         let f = File::create(output_path)
             .with_context(|| format!("create output docx: {}", output_path.display()))?;
         let mut zout = ZipWriter::new(f);
-        for ent in &self.entries {
+        zout.set_raw_comment(self.comment.clone().into_boxed_slice());
+        let mut source = self.open_source()?;
+        let mut guard = self.limit_guard()?;
+        for (i, ent) in self.entries.iter().enumerate() {
+            if let Some(zip) = source.as_mut() {
+                if self.is_streamed(i) && !replacements.contains_key(&ent.name) {
+                    if ent.extra_data.is_empty() && !ent.large_file {
+                        let file = zip.by_index_raw(i).context("zip entry")?;
+                        zout.raw_copy_file(file)
+                            .with_context(|| format!("copy zip file: {}", ent.name))?;
+                    } else {
+                        // Raw copies drop extra fields and zip64; re-encode to keep them.
+                        zout.start_file(&ent.name, ent.write_options()?)
+                            .with_context(|| format!("start zip file: {}", ent.name))?;
+                        let mut file = zip.by_index(i).context("zip entry")?;
+                        guard.check_declared(&ent.name, file.size())?;
+                        let copied = std::io::copy(&mut guard.entry_reader(&mut file), &mut zout)
+                            .with_context(|| format!("copy zip file: {}", ent.name))?;
+                        guard.add(&ent.name, copied)?;
+                    }
+                    continue;
+                }
+            }
             let data = replacements
                 .get(&ent.name)
                 .cloned()
                 .unwrap_or_else(|| ent.data.clone());
-            let mut opts = SimpleFileOptions::default()
-                .compression_method(ent.compression)
-                .last_modified_time(ent.last_modified);
-            if let Some(mode) = ent.unix_mode {
-                opts = opts.unix_permissions(mode);
-            }
+            let opts = ent.write_options()?;
             if ent.is_dir || ent.name.ends_with('/') {
                 zout.add_directory(&ent.name, opts)
                     .with_context(|| format!("add zip dir: {}", ent.name))?;
@@ -81,3 +408,41 @@ impl DocxPackage {
             .collect()
     }
 }
+
+/// What the per-entry reads cannot see: the archive comment, and zip64 records that some
+/// packagers write only in local headers.
+fn read_archive_meta(
+    zip: ZipArchive<PackageReader>,
+    entries: &mut [DocxEntry],
+    header_starts: &[u64],
+) -> anyhow::Result<Vec<u8>> {
+    let comment = zip.comment().to_vec();
+    let mut reader = zip.into_inner();
+    for (ent, &offset) in entries.iter_mut().zip(header_starts) {
+        if !ent.large_file {
+            ent.large_file = local_header_has_zip64(&mut reader, offset)
+                .with_context(|| format!("read local header: {}", ent.name))?;
+        }
+    }
+    Ok(comment)
+}
+
+fn local_header_has_zip64<R: Read + Seek>(r: &mut R, offset: u64) -> std::io::Result<bool> {
+    let mut head = [0u8; 30];
+    r.seek(SeekFrom::Start(offset))?;
+    r.read_exact(&mut head)?;
+    let name_len = u16::from_le_bytes([head[26], head[27]]);
+    let extra_len = u16::from_le_bytes([head[28], head[29]]);
+    r.seek(SeekFrom::Current(name_len as i64))?;
+    let mut extra = vec![0u8; extra_len as usize];
+    r.read_exact(&mut extra)?;
+    Ok(extra_fields(&extra)
+        .iter()
+        .any(|(id, _)| *id == ZIP64_EXTRA_ID))
+}
+
+/// Parts `read_streaming` materializes: XML and relationship parts.
+pub fn is_markup_part(name: &str) -> bool {
+    let lower = name.to_lowercase();
+    lower.ends_with(".xml") || lower.ends_with(".rels")
+}
@@ -116,13 +116,25 @@ pub fn distribute_span_text_to_nodes(span: &FormatSpan, text: &str) -> Vec<(Text
         .iter()
         .map(|n| n.original_text.len().max(1))
         .collect();
+    span.node_refs
+        .iter()
+        .cloned()
+        .zip(split_text_by_weights(text, &weights))
+        .collect()
+}
+
+/// Split `text` into `weights.len()` consecutive pieces sized in proportion to `weights`,
+/// nudging each boundary (within a few chars) off ASCII words and numbers.
+pub fn split_text_by_weights(text: &str, weights: &[usize]) -> Vec<String> {
+    if weights.len() <= 1 {
+        return weights.iter().map(|_| text.to_string()).collect();
+    }
     let units: Vec<char> = text.chars().collect();
-    let desired = allocate_plain_counts(units.len(), &weights);
-    let mut out: Vec<(TextNodeRef, String)> = Vec::new();
+    let desired = allocate_plain_counts(units.len(), weights);
 
     let mut boundaries: Vec<usize> = Vec::new();
     let mut pos = 0usize;
-    for count in desired.iter().take(span.node_refs.len().saturating_sub(1)) {
+    for count in desired.iter().take(weights.len() - 1) {
         pos = (pos + *count).min(units.len());
         boundaries.push(pos);
     }
@@ -172,15 +184,15 @@ pub fn distribute_span_text_to_nodes(span: &FormatSpan, text: &str) -> Vec<(Text
         prev_b = best;
     }
 
+    let mut out: Vec<String> = Vec::with_capacity(weights.len());
     let mut start_idx = 0usize;
-    for (i, nr) in span.node_refs.iter().cloned().enumerate() {
+    for i in 0..weights.len() {
         let end_idx = if i < boundaries.len() {
             boundaries[i].min(units.len())
         } else {
             units.len()
         };
-        let piece: String = units[start_idx..end_idx].iter().collect();
-        out.push((nr, piece));
+        out.push(units[start_idx..end_idx].iter().collect());
         start_idx = end_idx;
     }
     out
@@ -5,9 +5,10 @@ use std::path::{Path, PathBuf};
 use anyhow::{anyhow, Context};
 use serde::{Deserialize, Serialize};
 
+use crate::docx::artifact::ArtifactProducer;
 use crate::docx::decompose::extract_slot_texts;
-use crate::docx::package::DocxPackage;
-use crate::docx::xml::{parse_xml_part, XmlEvent, XmlPart};
+use crate::docx::session::DocumentSession;
+use crate::docx::xml::{parse_xml_part, XmlAttr, XmlEvent, XmlName, XmlPart};
 
 #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -37,30 +38,66 @@ pub struct PureParagraph {
     pub text: String,
 }
 
+/// text.json format written by extraction.
+pub const PURE_TEXT_VERSION: u32 = 3;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PureTextJson {
     pub version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub producer: Option<ArtifactProducer>,
     pub placeholder_prefix: String,
     pub slot_texts: Vec<String>,
     pub paragraphs: Vec<PureParagraph>,
+    /// Per translated slot: how the translation was produced (output text.json of basic-mode
+    /// runs; empty for extracted text).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub provenance: Vec<SlotProvenance>,
+    /// Slots whose runs the merge highlights (`pipeline.highlight_fallback` /
+    /// `highlight_low_confidence`), in `highlight_color`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub highlight_slots: Vec<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub highlight_color: Option<String>,
+    /// Slots whose text the merge drops together with a run left empty by it
+    /// (`pipeline.slot_projection = "paragraph"`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub collapsed_slots: Vec<usize>,
+    /// The merge sets `w:updateFields` so Word refreshes the fields (TOC, ...) on open.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub update_fields: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SlotProvenance {
+    pub slot_id: usize,
+    /// Backend that produced the translation.
+    pub backend: Option<String>,
+    pub repairs: usize,
+    pub forced_retranslate: bool,
+    /// Validation kept failing and the source text was kept.
+    pub source_fallback: bool,
+    pub qe_score: Option<i32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub qe_flags: Vec<String>,
 }
 
 pub struct PureTextOutputs {
     pub text_json_path: PathBuf,
 }
 
-fn find_attr<'a>(attrs: &'a [(String, String)], key: &str) -> Option<&'a str> {
+fn find_attr<'a>(attrs: &'a [XmlAttr], key: &str) -> Option<&'a str> {
     attrs
         .iter()
         .find(|(k, _)| k == key)
         .map(|(_, v)| v.as_str())
 }
 
-fn parse_i32_attr(attrs: &[(String, String)], key: &str) -> Option<i32> {
+fn parse_i32_attr(attrs: &[XmlAttr], key: &str) -> Option<i32> {
     find_attr(attrs, key).and_then(|v| v.trim().parse::<i32>().ok())
 }
 
-fn control_append(buf: &mut String, name: &str, attrs: &[(String, String)]) {
+fn control_append(buf: &mut String, name: &str, attrs: &[XmlAttr]) {
     match name {
         "w:tab" | "w:ptab" => buf.push('\t'),
         "w:cr" => buf.push('\n'),
@@ -132,14 +169,20 @@ fn extract_body_and_tables_from_document(
     out: &mut Vec<PureParagraph>,
     next_para_id: &mut usize,
 ) {
-    let mut stack: Vec<String> = Vec::new();
+    let mut stack: Vec<XmlName> = Vec::new();
 
     let mut tbl_depth = 0usize;
     let mut current_table_index = 0usize;
     let mut current_row_index = 0usize;
     let mut current_cell_index = 0usize;
 
-    let mut capturing: Option<(ParaCapture, ParaContainer, Option<usize>, Option<usize>, Option<usize>)> = None;
+    let mut capturing: Option<(
+        ParaCapture,
+        ParaContainer,
+        Option<usize>,
+        Option<usize>,
+        Option<usize>,
+    )> = None;
 
     for (idx, ev) in part.events.iter().enumerate() {
         match ev {
@@ -164,17 +207,17 @@ fn extract_body_and_tables_from_document(
                     }
                 }
 
-                    if name == "w:p" {
-                        if parent == "w:body" && tbl_depth == 0 {
-                            capturing = Some((
-                                ParaCapture {
-                                    start_event_index: idx,
-                                    p_stack_len: stack.len() + 1,
-                                    ..Default::default()
-                                },
-                                ParaContainer::DocumentBody,
-                                None,
-                                None,
+                if name == "w:p" {
+                    if parent == "w:body" && tbl_depth == 0 {
+                        capturing = Some((
+                            ParaCapture {
+                                start_event_index: idx,
+                                p_stack_len: stack.len() + 1,
+                                ..Default::default()
+                            },
+                            ParaContainer::DocumentBody,
+                            None,
+                            None,
                             None,
                         ));
                     } else if parent == "w:tc" && tbl_depth == 1 {
@@ -252,7 +295,8 @@ fn extract_body_and_tables_from_document(
                                 }
                             }
                         }
-                        "w:tab" | "w:ptab" | "w:cr" | "w:br" | "w:noBreakHyphen" | "w:softHyphen" => {
+                        "w:tab" | "w:ptab" | "w:cr" | "w:br" | "w:noBreakHyphen"
+                        | "w:softHyphen" => {
                             if parent == "w:r"
                                 && (cap.direct_r_stack_len == Some(stack.len())
                                     || cap.hyperlink_r_stack_len == Some(stack.len()))
@@ -315,7 +359,8 @@ fn extract_body_and_tables_from_document(
                                 }
                             }
                         }
-                        "w:tab" | "w:ptab" | "w:cr" | "w:br" | "w:noBreakHyphen" | "w:softHyphen" => {
+                        "w:tab" | "w:ptab" | "w:cr" | "w:br" | "w:noBreakHyphen"
+                        | "w:softHyphen" => {
                             if parent == "w:r"
                                 && (cap.direct_r_stack_len == Some(stack.len())
                                     || cap.hyperlink_r_stack_len == Some(stack.len()))
@@ -412,7 +457,7 @@ fn extract_direct_paragraphs_from_part(
     out: &mut Vec<PureParagraph>,
     next_para_id: &mut usize,
 ) {
-    let mut stack: Vec<String> = Vec::new();
+    let mut stack: Vec<XmlName> = Vec::new();
     let mut capturing: Option<ParaCapture> = None;
 
     for (idx, ev) in part.events.iter().enumerate() {
@@ -489,7 +534,8 @@ fn extract_direct_paragraphs_from_part(
                                 }
                             }
                         }
-                        "w:tab" | "w:ptab" | "w:cr" | "w:br" | "w:noBreakHyphen" | "w:softHyphen" => {
+                        "w:tab" | "w:ptab" | "w:cr" | "w:br" | "w:noBreakHyphen"
+                        | "w:softHyphen" => {
                             if parent == "w:r"
                                 && (cap.direct_r_stack_len == Some(stack.len())
                                     || cap.hyperlink_r_stack_len == Some(stack.len()))
@@ -559,7 +605,8 @@ fn extract_direct_paragraphs_from_part(
                                 }
                             }
                         }
-                        "w:tab" | "w:ptab" | "w:cr" | "w:br" | "w:noBreakHyphen" | "w:softHyphen" => {
+                        "w:tab" | "w:ptab" | "w:cr" | "w:br" | "w:noBreakHyphen"
+                        | "w:softHyphen" => {
                             if parent == "w:r"
                                 && (cap.direct_r_stack_len == Some(stack.len())
                                     || cap.hyperlink_r_stack_len == Some(stack.len()))
@@ -664,7 +711,7 @@ struct SectionRefs {
 
 fn extract_sections_from_document_xml(doc: &XmlPart) -> Vec<SectionRefs> {
     let mut sections: Vec<SectionRefs> = Vec::new();
-    let mut stack: Vec<String> = Vec::new();
+    let mut stack: Vec<XmlName> = Vec::new();
 
     let mut in_sectpr = false;
     let mut cur_sect = SectionRefs::default();
@@ -686,7 +733,8 @@ fn extract_sections_from_document_xml(doc: &XmlPart) -> Vec<SectionRefs> {
                     in_sectpr = true;
                     pending_header = None;
                     pending_footer = None;
-                } else if in_sectpr && (name == "w:headerReference" || name == "w:footerReference") {
+                } else if in_sectpr && (name == "w:headerReference" || name == "w:footerReference")
+                {
                     let typ = find_attr(attrs, "w:type").unwrap_or("default");
                     if typ != "default" {
                         continue;
@@ -763,22 +811,19 @@ fn extract_sections_from_document_xml(doc: &XmlPart) -> Vec<SectionRefs> {
 }
 
 pub fn extract_pure_text(input_docx: &Path) -> anyhow::Result<PureTextJson> {
-    let pkg = DocxPackage::read(input_docx)?;
-    let mut by_name: HashMap<String, Vec<u8>> = HashMap::new();
-    for ent in &pkg.entries {
-        by_name.insert(ent.name.clone(), ent.data.clone());
-    }
+    pure_text_from_session(&DocumentSession::open(input_docx)?)
+}
 
-    let doc_bytes = by_name
-        .get("word/document.xml")
+pub fn pure_text_from_session(session: &DocumentSession) -> anyhow::Result<PureTextJson> {
+    let doc = session
+        .part("word/document.xml")
         .ok_or_else(|| anyhow!("missing word/document.xml"))?;
-    let doc = parse_xml_part("word/document.xml", doc_bytes).context("parse word/document.xml")?;
 
     let mut doc_paras: Vec<PureParagraph> = Vec::new();
     let mut next_para_id = 1usize;
-    extract_body_and_tables_from_document(&doc, &mut doc_paras, &mut next_para_id);
+    extract_body_and_tables_from_document(doc, &mut doc_paras, &mut next_para_id);
 
-    let rels_map = if let Some(rels_bytes) = by_name.get("word/_rels/document.xml.rels") {
+    let rels_map = if let Some(rels_bytes) = session.entry_data("word/_rels/document.xml.rels") {
         let rels = parse_xml_part("word/_rels/document.xml.rels", rels_bytes)
             .context("parse word/_rels/document.xml.rels")?;
         extract_doc_rels_map(&rels)
@@ -786,45 +831,33 @@ pub fn extract_pure_text(input_docx: &Path) -> anyhow::Result<PureTextJson> {
         HashMap::new()
     };
 
-    let sections = extract_sections_from_document_xml(&doc);
+    let sections = extract_sections_from_document_xml(doc);
     let mut header_footer_paras: Vec<PureParagraph> = Vec::new();
 
     for (i, s) in sections.iter().enumerate() {
         let section_index = i + 1;
         if let Some(rid) = s.header_rid.as_ref() {
-            if let Some(part_name) = rels_map.get(rid) {
-                if let Some(bytes) = by_name.get(part_name) {
-                    if !bytes.is_empty() {
-                        let part = parse_xml_part(part_name, bytes)
-                            .with_context(|| format!("parse header part: {}", part_name))?;
-                        extract_direct_paragraphs_from_part(
-                            &part,
-                            "w:hdr",
-                            ParaContainer::Header,
-                            Some(section_index),
-                            &mut header_footer_paras,
-                            &mut next_para_id,
-                        );
-                    }
-                }
+            if let Some(part) = rels_map.get(rid).and_then(|name| session.part(name)) {
+                extract_direct_paragraphs_from_part(
+                    part,
+                    "w:hdr",
+                    ParaContainer::Header,
+                    Some(section_index),
+                    &mut header_footer_paras,
+                    &mut next_para_id,
+                );
             }
         }
         if let Some(rid) = s.footer_rid.as_ref() {
-            if let Some(part_name) = rels_map.get(rid) {
-                if let Some(bytes) = by_name.get(part_name) {
-                    if !bytes.is_empty() {
-                        let part = parse_xml_part(part_name, bytes)
-                            .with_context(|| format!("parse footer part: {}", part_name))?;
-                        extract_direct_paragraphs_from_part(
-                            &part,
-                            "w:ftr",
-                            ParaContainer::Footer,
-                            Some(section_index),
-                            &mut header_footer_paras,
-                            &mut next_para_id,
-                        );
-                    }
-                }
+            if let Some(part) = rels_map.get(rid).and_then(|name| session.part(name)) {
+                extract_direct_paragraphs_from_part(
+                    part,
+                    "w:ftr",
+                    ParaContainer::Footer,
+                    Some(section_index),
+                    &mut header_footer_paras,
+                    &mut next_para_id,
+                );
             }
         }
     }
@@ -833,18 +866,25 @@ pub fn extract_pure_text(input_docx: &Path) -> anyhow::Result<PureTextJson> {
     paragraphs.extend(doc_paras);
     paragraphs.extend(header_footer_paras);
 
-    let (placeholder_prefix, slot_texts) = extract_slot_texts(input_docx)?;
-
     Ok(PureTextJson {
-        version: 3,
-        placeholder_prefix,
-        slot_texts,
+        version: PURE_TEXT_VERSION,
+        producer: Some(ArtifactProducer::new(session.source_sha256())),
+        placeholder_prefix: session.placeholder_prefix().to_string(),
+        slot_texts: extract_slot_texts(session),
         paragraphs,
+        provenance: Vec::new(),
+        highlight_slots: Vec::new(),
+        highlight_color: None,
+        collapsed_slots: Vec::new(),
+        update_fields: false,
     })
 }
 
 pub fn extract_pure_text_json(input_docx: &Path, output_json: &Path) -> anyhow::Result<()> {
-    let out = extract_pure_text(input_docx)?;
+    write_pure_text_json(&extract_pure_text(input_docx)?, output_json)
+}
+
+pub fn write_pure_text_json(out: &PureTextJson, output_json: &Path) -> anyhow::Result<()> {
     fs::write(
         output_json,
         serde_json::to_vec_pretty(&out).context("serialize pure text json")?,
@@ -0,0 +1,288 @@
+//! `--lenient-parse`: repair XML issues that some web-tool exports produce before it reaches
+//! the XML parser (stray control characters, HTML entities, bare `&`, invalid UTF-8,
+//! undeclared namespace prefixes). Each repair is described so it can be reported.
+
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static ENTITY_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"&(?:(#x[0-9A-Fa-f]+|#[0-9]+|[A-Za-z_][A-Za-z0-9_.-]*);)?").expect("entity regex")
+});
+static TAG_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"<[^!?/][^>]*>").expect("tag regex"));
+static ELEMENT_PREFIX_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^<([A-Za-z_][\w.-]*):").expect("element prefix regex"));
+static ATTR_PREFIX_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\s([A-Za-z_][\w.-]*):[\w.-]+\s*=").expect("attr prefix regex"));
+static XMLNS_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\sxmlns:([\w.-]+)\s*=").expect("xmlns regex"));
+static ROOT_NAME_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"<[A-Za-z_][\w.:-]*").expect("root name regex"));
+
+/// Namespace URIs for prefixes Word documents commonly use without declaring them.
+const KNOWN_NAMESPACES: &[(&str, &str)] = &[
+    (
+        "w",
+        "http://schemas.openxmlformats.org/wordprocessingml/2006/main",
+    ),
+    (
+        "r",
+        "http://schemas.openxmlformats.org/officeDocument/2006/relationships",
+    ),
+    (
+        "wp",
+        "http://schemas.openxmlformats.org/drawingml/2006/wordprocessingDrawing",
+    ),
+    ("a", "http://schemas.openxmlformats.org/drawingml/2006/main"),
+    (
+        "pic",
+        "http://schemas.openxmlformats.org/drawingml/2006/picture",
+    ),
+    (
+        "m",
+        "http://schemas.openxmlformats.org/officeDocument/2006/math",
+    ),
+    (
+        "mc",
+        "http://schemas.openxmlformats.org/markup-compatibility/2006",
+    ),
+    ("v", "urn:schemas-microsoft-com:vml"),
+    ("o", "urn:schemas-microsoft-com:office:office"),
+    ("w10", "urn:schemas-microsoft-com:office:word"),
+    (
+        "w14",
+        "http://schemas.microsoft.com/office/word/2010/wordml",
+    ),
+    (
+        "w15",
+        "http://schemas.microsoft.com/office/word/2012/wordml",
+    ),
+    (
+        "wp14",
+        "http://schemas.microsoft.com/office/word/2010/wordprocessingDrawing",
+    ),
+    (
+        "wps",
+        "http://schemas.microsoft.com/office/word/2010/wordprocessingShape",
+    ),
+    (
+        "wpg",
+        "http://schemas.microsoft.com/office/word/2010/wordprocessingGroup",
+    ),
+    (
+        "wne",
+        "http://schemas.microsoft.com/office/word/2006/wordml",
+    ),
+];
+
+/// HTML entities seen in exported text, mapped to code points.
+const HTML_ENTITIES: &[(&str, u32)] = &[
+    ("nbsp", 160),
+    ("copy", 169),
+    ("reg", 174),
+    ("deg", 176),
+    ("middot", 183),
+    ("laquo", 171),
+    ("raquo", 187),
+    ("ndash", 8211),
+    ("mdash", 8212),
+    ("lsquo", 8216),
+    ("rsquo", 8217),
+    ("ldquo", 8220),
+    ("rdquo", 8221),
+    ("bull", 8226),
+    ("hellip", 8230),
+    ("euro", 8364),
+    ("trade", 8482),
+];
+
+/// Repair `xml`; returns the (possibly unchanged) bytes and one line per kind of fix.
+pub fn sanitize_xml(xml: &[u8]) -> (Cow<'_, [u8]>, Vec<String>) {
+    let mut fixes = Vec::new();
+    let text: Cow<'_, str> = match std::str::from_utf8(xml) {
+        Ok(s) => Cow::Borrowed(s),
+        Err(_) => {
+            fixes.push("replaced invalid UTF-8 sequences".to_string());
+            String::from_utf8_lossy(xml)
+        }
+    };
+
+    let mut out = String::with_capacity(text.len());
+    let mut stats = FixStats::default();
+    let mut rest: &str = &text;
+    // CDATA sections and comments are copied verbatim: `&` is literal there.
+    while let Some((start, open, close)) = next_verbatim_region(rest) {
+        fix_markup(&rest[..start], &mut out, &mut stats);
+        let body_end = rest[start + open.len()..]
+            .find(close)
+            .map(|i| start + open.len() + i + close.len())
+            .unwrap_or(rest.len());
+        strip_control_chars(&rest[start..body_end], &mut out, &mut stats);
+        rest = &rest[body_end..];
+    }
+    fix_markup(rest, &mut out, &mut stats);
+    fixes.extend(stats.describe());
+
+    let undeclared = undeclared_prefixes(&out);
+    if !undeclared.is_empty() {
+        if let Some(root) = ROOT_NAME_RE.find(&out) {
+            let decls: String = undeclared
+                .iter()
+                .map(|p| format!(" xmlns:{p}=\"{}\"", namespace_uri(p)))
+                .collect();
+            out.insert_str(root.end(), &decls);
+            fixes.push(format!(
+                "declared namespace prefixes: {}",
+                undeclared.iter().cloned().collect::<Vec<_>>().join(", ")
+            ));
+        }
+    }
+
+    if fixes.is_empty() {
+        (Cow::Borrowed(xml), fixes)
+    } else {
+        (Cow::Owned(out.into_bytes()), fixes)
+    }
+}
+
+#[derive(Default)]
+struct FixStats {
+    control_chars: usize,
+    html_entities: usize,
+    bare_ampersands: usize,
+}
+
+impl FixStats {
+    fn describe(&self) -> Vec<String> {
+        let mut out = Vec::new();
+        if self.control_chars > 0 {
+            out.push(format!("removed {} control characters", self.control_chars));
+        }
+        if self.html_entities > 0 {
+            out.push(format!(
+                "converted {} HTML entities to character references",
+                self.html_entities
+            ));
+        }
+        if self.bare_ampersands > 0 {
+            out.push(format!("escaped {} bare '&'", self.bare_ampersands));
+        }
+        out
+    }
+}
+
+fn next_verbatim_region(s: &str) -> Option<(usize, &'static str, &'static str)> {
+    let cdata = s.find("<![CDATA[").map(|i| (i, "<![CDATA[", "]]>"));
+    let comment = s.find("<!--").map(|i| (i, "<!--", "-->"));
+    match (cdata, comment) {
+        (Some(a), Some(b)) => Some(if a.0 < b.0 { a } else { b }),
+        (a, b) => a.or(b),
+    }
+}
+
+pub(crate) fn is_xml_char(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r') || (c >= ' ' && c != '\u{FFFE}' && c != '\u{FFFF}')
+}
+
+fn strip_control_chars(s: &str, out: &mut String, stats: &mut FixStats) {
+    for c in s.chars() {
+        if is_xml_char(c) {
+            out.push(c);
+        } else {
+            stats.control_chars += 1;
+        }
+    }
+}
+
+fn fix_markup(s: &str, out: &mut String, stats: &mut FixStats) {
+    let mut last = 0;
+    for m in ENTITY_RE.captures_iter(s) {
+        let whole = m.get(0).expect("match");
+        strip_control_chars(&s[last..whole.start()], out, stats);
+        last = whole.end();
+        let Some(name) = m.get(1).map(|g| g.as_str()) else {
+            stats.bare_ampersands += 1;
+            out.push_str("&amp;");
+            continue;
+        };
+        if let Some(num) = name.strip_prefix('#') {
+            let code = match num.strip_prefix('x') {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => num.parse::<u32>().ok(),
+            };
+            match code.and_then(char::from_u32) {
+                Some(c) if is_xml_char(c) => out.push_str(whole.as_str()),
+                _ => stats.control_chars += 1,
+            }
+            continue;
+        }
+        if matches!(name, "amp" | "lt" | "gt" | "quot" | "apos") {
+            out.push_str(whole.as_str());
+        } else if let Some((_, code)) = HTML_ENTITIES.iter().find(|(n, _)| *n == name) {
+            stats.html_entities += 1;
+            out.push_str(&format!("&#{code};"));
+        } else {
+            stats.bare_ampersands += 1;
+            out.push_str("&amp;");
+            out.push_str(&whole.as_str()[1..]);
+        }
+    }
+    strip_control_chars(&s[last..], out, stats);
+}
+
+fn undeclared_prefixes(xml: &str) -> BTreeSet<String> {
+    let mut used = BTreeSet::new();
+    let mut declared = BTreeSet::new();
+    for tag in TAG_RE.find_iter(xml) {
+        let tag = tag.as_str();
+        if let Some(c) = ELEMENT_PREFIX_RE.captures(tag) {
+            used.insert(c[1].to_string());
+        }
+        for c in ATTR_PREFIX_RE.captures_iter(tag) {
+            used.insert(c[1].to_string());
+        }
+        for c in XMLNS_RE.captures_iter(tag) {
+            declared.insert(c[1].to_string());
+        }
+    }
+    used.into_iter()
+        .filter(|p| p != "xmlns" && p != "xml" && !declared.contains(p))
+        .collect()
+}
+
+fn namespace_uri(prefix: &str) -> String {
+    KNOWN_NAMESPACES
+        .iter()
+        .find(|(p, _)| *p == prefix)
+        .map(|(_, uri)| uri.to_string())
+        .unwrap_or_else(|| format!("urn:muggle-translator:undeclared:{prefix}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sanitize_xml;
+
+    #[test]
+    fn repairs_entities_control_chars_and_namespaces() {
+        let xml = "<w:document><w:t>A &nbsp;B & C\u{1}&#x2;</w:t><w14:x/></w:document>";
+        let (out, fixes) = sanitize_xml(xml.as_bytes());
+        let out = String::from_utf8(out.into_owned()).expect("utf8");
+        assert_eq!(
+            out,
+            "<w:document xmlns:w=\"http://schemas.openxmlformats.org/wordprocessingml/2006/main\" \
+             xmlns:w14=\"http://schemas.microsoft.com/office/word/2010/wordml\">\
+             <w:t>A &#160;B &amp; C</w:t><w14:x/></w:document>"
+        );
+        assert_eq!(fixes.len(), 4);
+    }
+
+    #[test]
+    fn leaves_valid_xml_and_cdata_alone() {
+        let xml = br#"<r xmlns:o="urn:o" o:a="A&#xD;&amp;"><![CDATA[a & b]]><!-- & --></r>"#;
+        let (out, fixes) = sanitize_xml(xml);
+        assert!(fixes.is_empty());
+        assert_eq!(&out[..], &xml[..]);
+    }
+}
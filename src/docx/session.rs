@@ -0,0 +1,202 @@
+//! One read of a DOCX shared by the extraction stages (pure text, structure, mask/offsets,
+//! docmap). Each stage used to reopen the zip and re-parse every XML part on its own.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
+
+use crate::docx::decompose::{hash_file, PlaceholderPrefix};
+use crate::docx::package::{DocxPackage, PackageOptions};
+use crate::docx::xml::{parse_xml_part_with, ParseFix, XmlAttr, XmlEvent, XmlPart};
+
+pub struct DocumentSession {
+    path: PathBuf,
+    package: DocxPackage,
+    /// Parsed `.xml` parts, indexed like `package.entries` (`None` for every other entry).
+    parts: Vec<Option<XmlPart>>,
+    /// Repairs applied while parsing `parts` (only with `PackageOptions::lenient`).
+    parse_fixes: Vec<ParseFix>,
+    by_name: HashMap<String, usize>,
+    placeholder_prefix: String,
+    source_sha256: String,
+}
+
+impl DocumentSession {
+    /// Read the package (streaming: media stays in the zip) and parse its XML parts once.
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        Self::open_with(
+            path,
+            &PackageOptions::default(),
+            &PlaceholderPrefix::default(),
+        )
+    }
+
+    pub fn open_with(
+        path: &Path,
+        package: &PackageOptions,
+        prefix: &PlaceholderPrefix,
+    ) -> anyhow::Result<Self> {
+        let lenient = package.lenient;
+        let package = DocxPackage::read_streaming_with(path, package)?;
+        let parsed: Vec<Option<(XmlPart, Vec<ParseFix>)>> = package
+            .entries
+            .par_iter()
+            .map(|ent| {
+                let is_xml = !ent.is_dir
+                    && !ent.name.ends_with('/')
+                    && ent.name.to_lowercase().ends_with(".xml")
+                    && !ent.data.is_empty();
+                if !is_xml {
+                    return Ok(None);
+                }
+                parse_xml_part_with(&ent.name, &ent.data, lenient)
+                    .with_context(|| format!("parse xml: {}", ent.name))
+                    .map(Some)
+            })
+            .collect::<anyhow::Result<_>>()?;
+        let mut parse_fixes = Vec::new();
+        let parts: Vec<Option<XmlPart>> = parsed
+            .into_iter()
+            .map(|p| {
+                p.map(|(part, fixes)| {
+                    parse_fixes.extend(fixes);
+                    part
+                })
+            })
+            .collect();
+        let by_name = package
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(i, e)| (e.name.clone(), i))
+            .collect();
+        let source_sha256 = hash_file(path)?;
+        let placeholder_prefix = match prefix {
+            PlaceholderPrefix::FileHash => source_sha256.chars().take(10).collect(),
+            PlaceholderPrefix::Content => content_prefix(&package, &parts),
+            PlaceholderPrefix::Fixed(prefix) => prefix.clone(),
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            placeholder_prefix,
+            source_sha256,
+            package,
+            parts,
+            parse_fixes,
+            by_name,
+        })
+    }
+
+    /// Repairs `--lenient-parse` applied while opening; the caller decides how to report them.
+    pub fn parse_fixes(&self) -> &[ParseFix] {
+        &self.parse_fixes
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn package(&self) -> &DocxPackage {
+        &self.package
+    }
+
+    /// Placeholder prefix (file hash, content hash or fixed; same for every stage).
+    pub fn placeholder_prefix(&self) -> &str {
+        &self.placeholder_prefix
+    }
+
+    /// SHA-256 of the input file, recorded as the source of every extracted artifact.
+    pub fn source_sha256(&self) -> &str {
+        &self.source_sha256
+    }
+
+    /// Parsed XML part of entry `index`.
+    pub fn part_at(&self, index: usize) -> Option<&XmlPart> {
+        self.parts.get(index).and_then(Option::as_ref)
+    }
+
+    pub fn part(&self, name: &str) -> Option<&XmlPart> {
+        self.by_name.get(name).and_then(|&i| self.part_at(i))
+    }
+
+    /// Raw bytes of a materialized entry (XML / `.rels`); empty for streamed media.
+    pub fn entry_data(&self, name: &str) -> Option<&[u8]> {
+        self.by_name
+            .get(name)
+            .map(|&i| self.package.entries[i].data.as_slice())
+    }
+
+    /// Parsed XML parts in entry order.
+    pub fn xml_parts(&self) -> impl Iterator<Item = &XmlPart> {
+        self.parts.iter().flatten()
+    }
+}
+
+/// Hash of the parsed XML parts (by name) minus what Word rewrites on every save: `docProps/`,
+/// the `w:rsids` table and `rsid*` / `w14:paraId` / `w14:textId` attributes. Media and
+/// relationships are left out; the prefix only has to tell documents apart.
+fn content_prefix(package: &DocxPackage, parts: &[Option<XmlPart>]) -> String {
+    let mut order: Vec<usize> = (0..parts.len())
+        .filter(|&i| parts[i].is_some() && !package.entries[i].name.starts_with("docProps/"))
+        .collect();
+    order.sort_by(|&a, &b| package.entries[a].name.cmp(&package.entries[b].name));
+
+    let mut hasher = Sha256::new();
+    for part in order.iter().filter_map(|&i| parts[i].as_ref()) {
+        hasher.update(part.name.as_bytes());
+        hasher.update([0]);
+        let mut skip_depth = 0usize;
+        for ev in &part.events {
+            if skip_depth > 0 {
+                match ev {
+                    XmlEvent::Start { .. } => skip_depth += 1,
+                    XmlEvent::End { .. } => skip_depth -= 1,
+                    _ => {}
+                }
+                continue;
+            }
+            match ev {
+                XmlEvent::Start { name, .. } if name.as_str() == "w:rsids" => skip_depth = 1,
+                XmlEvent::Empty { name, .. } if name.as_str() == "w:rsids" => {}
+                XmlEvent::Start { name, attrs } | XmlEvent::Empty { name, attrs } => {
+                    let tag: &[u8] = if matches!(ev, XmlEvent::Start { .. }) {
+                        b"<"
+                    } else {
+                        b"/"
+                    };
+                    hasher.update(tag);
+                    hasher.update(name.as_str().as_bytes());
+                    for (key, value) in attrs.iter().filter(|a| !is_save_noise(a)) {
+                        hasher.update([1]);
+                        hasher.update(key.as_str().as_bytes());
+                        hasher.update([2]);
+                        hasher.update(value.as_bytes());
+                    }
+                    hasher.update([0]);
+                }
+                XmlEvent::End { name } => {
+                    hasher.update(b">");
+                    hasher.update(name.as_str().as_bytes());
+                    hasher.update([0]);
+                }
+                XmlEvent::Text { text } | XmlEvent::CData { text } => {
+                    hasher.update(b"t");
+                    hasher.update(text.as_bytes());
+                    hasher.update([0]);
+                }
+                _ => {}
+            }
+        }
+    }
+    hex::encode(hasher.finalize()).chars().take(10).collect()
+}
+
+/// Attributes Word regenerates on save without a content change.
+fn is_save_noise((key, _): &XmlAttr) -> bool {
+    let key = key.as_str();
+    let local = key.rsplit(':').next().unwrap_or(key);
+    local.starts_with("rsid") || key == "w14:paraId" || key == "w14:textId"
+}
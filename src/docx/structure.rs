@@ -5,6 +5,7 @@ use std::path::{Path, PathBuf};
 use anyhow::Context;
 use serde::{Deserialize, Serialize};
 
+use crate::docx::artifact::ArtifactProducer;
 use crate::docx::pure_text::{extract_pure_text, ParaContainer, PureParagraph, PureTextJson};
 
 #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
@@ -55,6 +56,8 @@ pub struct StructureNode {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct StructureJson {
     pub version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub producer: Option<ArtifactProducer>,
     pub placeholder_prefix: String,
     pub root: StructureNode,
 }
@@ -139,7 +142,8 @@ fn para_loc(p: &PureParagraph) -> ParaLoc {
     }
 }
 
-fn heading_level(p: &PureParagraph) -> Option<usize> {
+/// Heading level of `p` (1-based) from its outline level, a "Heading N" style or "Title".
+pub fn heading_level(p: &PureParagraph) -> Option<usize> {
     if let Some(lvl) = p.outline_lvl {
         if lvl >= 0 {
             return Some(lvl as usize + 1);
@@ -245,7 +249,8 @@ fn build_part_tree(arena: &mut Vec<ArenaNode>, part_idx: usize, paras: &[PurePar
 
                 while list_ctx.list_node_stack.len() < level + 1 {
                     let prev_level = list_ctx.list_node_stack.len() - 1;
-                    let parent_item = list_ctx.last_item_stack[prev_level].unwrap_or(section_parent);
+                    let parent_item =
+                        list_ctx.last_item_stack[prev_level].unwrap_or(section_parent);
                     let new_level = list_ctx.list_node_stack.len() as i32;
                     let list_node = arena_add(
                         arena,
@@ -309,7 +314,10 @@ fn build_part_tree(arena: &mut Vec<ArenaNode>, part_idx: usize, paras: &[PurePar
 pub fn build_structure(pure: &PureTextJson) -> StructureJson {
     let mut by_part: BTreeMap<String, Vec<PureParagraph>> = BTreeMap::new();
     for p in &pure.paragraphs {
-        by_part.entry(p.part_name.clone()).or_default().push(p.clone());
+        by_part
+            .entry(p.part_name.clone())
+            .or_default()
+            .push(p.clone());
     }
 
     let mut arena: Vec<ArenaNode> = Vec::new();
@@ -349,14 +357,19 @@ pub fn build_structure(pure: &PureTextJson) -> StructureJson {
 
     StructureJson {
         version: 1,
+        producer: pure.producer.clone(),
         placeholder_prefix: pure.placeholder_prefix.clone(),
         root: arena_to_tree(root_idx, &arena),
     }
 }
 
 pub fn extract_structure_json(input_docx: &Path, output_json: &Path) -> anyhow::Result<()> {
-    let pure = extract_pure_text(input_docx)?;
-    let out = build_structure(&pure);
+    write_structure_json(&extract_pure_text(input_docx)?, output_json)
+}
+
+/// Structure JSON from already-extracted pure text.
+pub fn write_structure_json(pure: &PureTextJson, output_json: &Path) -> anyhow::Result<()> {
+    let out = build_structure(pure);
     fs::write(
         output_json,
         serde_json::to_vec_pretty(&out).context("serialize structure json")?,
@@ -375,4 +388,3 @@ pub fn default_structure_output_for(input_docx: &Path) -> StructureOutputs {
         structure_json_path: dir.join(format!("{stem}.structure.json")),
     }
 }
-
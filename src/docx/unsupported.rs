@@ -0,0 +1,79 @@
+//! Package content the pipeline does not translate: SmartArt, charts, embedded objects and
+//! images. Their text (if any) passes through unchanged, so runs report it up front and in the
+//! coverage report.
+
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::docx::package::DocxPackage;
+
+/// Kinds in report order.
+pub const UNSUPPORTED_KINDS: [&str; 5] = [
+    "smartart",
+    "chart",
+    "ole_object",
+    "embedded_document",
+    "image",
+];
+
+#[derive(Clone, Debug, Serialize)]
+pub struct UnsupportedContent {
+    /// One of `UNSUPPORTED_KINDS`.
+    pub kind: &'static str,
+    pub count: usize,
+    /// Package entries counted (SmartArt: its data part).
+    pub entries: Vec<String>,
+}
+
+/// Unsupported content of `pkg` by kind, in `UNSUPPORTED_KINDS` order; kinds without entries
+/// are left out.
+pub fn find_unsupported_content(pkg: &DocxPackage) -> Vec<UnsupportedContent> {
+    let mut found: Vec<UnsupportedContent> = UNSUPPORTED_KINDS
+        .iter()
+        .map(|&kind| UnsupportedContent {
+            kind,
+            count: 0,
+            entries: Vec::new(),
+        })
+        .collect();
+    for entry in pkg.entries.iter().filter(|e| !e.is_dir) {
+        let Some(kind) = content_kind(&entry.name) else {
+            continue;
+        };
+        if let Some(c) = found.iter_mut().find(|c| c.kind == kind) {
+            c.count += 1;
+            c.entries.push(entry.name.clone());
+        }
+    }
+    found.retain(|c| c.count > 0);
+    found
+}
+
+/// Whether `name` is an image under `word/media/` (the inputs of `pipeline.ocr_command`).
+pub fn is_media_image(name: &str) -> bool {
+    content_kind(name) == Some("image")
+}
+
+fn content_kind(name: &str) -> Option<&'static str> {
+    let lower = name.to_lowercase();
+    let (dir, file) = lower.rsplit_once('/')?;
+    let ext = Path::new(file).extension().and_then(|e| e.to_str())?;
+    match dir {
+        // One data part per SmartArt graphic (layout, colors, ... belong to it).
+        "word/diagrams" if file.starts_with("data") && ext == "xml" => Some("smartart"),
+        "word/charts" if file.starts_with("chart") && ext == "xml" => Some("chart"),
+        // Word/Excel packages are translated with `pipeline.translate_embedded`.
+        "word/embeddings" => match ext {
+            "docx" | "docm" | "xlsx" | "xlsm" => Some("embedded_document"),
+            _ => Some("ole_object"),
+        },
+        "word/media" => match ext {
+            "png" | "jpg" | "jpeg" | "gif" | "bmp" | "tif" | "tiff" | "emf" | "wmf" | "svg" => {
+                Some("image")
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -1,10 +1,226 @@
-use std::collections::BTreeMap;
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Arc;
 
 use anyhow::{anyhow, Context};
+use once_cell::sync::Lazy;
 use quick_xml::events::{BytesDecl, BytesStart, Event};
 use quick_xml::Reader;
+use serde::Serialize;
 use sha2::{Digest, Sha256};
 
+use crate::docx::sanitize::sanitize_xml;
+use crate::i18n::tr_args;
+
+/// Element / attribute name. Names repeat constantly (`w:r`, `w:t`, `w:val`, ...), so they are
+/// interned per parse and shared through an `Arc<str>` instead of one `String` per event.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct XmlName(Arc<str>);
+
+impl XmlName {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for XmlName {
+    type Target = str;
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for XmlName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for XmlName {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for XmlName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Display for XmlName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<&str> for XmlName {
+    fn from(s: &str) -> Self {
+        Self(Arc::from(s))
+    }
+}
+
+impl From<String> for XmlName {
+    fn from(s: String) -> Self {
+        Self(Arc::from(s))
+    }
+}
+
+impl PartialEq<str> for XmlName {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for XmlName {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl PartialEq<String> for XmlName {
+    fn eq(&self, other: &String) -> bool {
+        *self.0 == **other
+    }
+}
+
+pub type XmlAttr = (XmlName, String);
+
+/// Names shared by every part, so parts parsed on different threads still share storage.
+static COMMON_NAMES: Lazy<HashSet<XmlName>> = Lazy::new(|| {
+    [
+        "w:p",
+        "w:pPr",
+        "w:r",
+        "w:rPr",
+        "w:t",
+        "w:tab",
+        "w:br",
+        "w:pStyle",
+        "w:rStyle",
+        "w:rFonts",
+        "w:b",
+        "w:bCs",
+        "w:i",
+        "w:iCs",
+        "w:u",
+        "w:color",
+        "w:sz",
+        "w:szCs",
+        "w:lang",
+        "w:spacing",
+        "w:ind",
+        "w:jc",
+        "w:numPr",
+        "w:ilvl",
+        "w:numId",
+        "w:outlineLvl",
+        "w:keepNext",
+        "w:keepLines",
+        "w:noProof",
+        "w:vertAlign",
+        "w:highlight",
+        "w:shd",
+        "w:kern",
+        "w:w",
+        "w:position",
+        "w:tbl",
+        "w:tblPr",
+        "w:tblGrid",
+        "w:gridCol",
+        "w:tr",
+        "w:trPr",
+        "w:tc",
+        "w:tcPr",
+        "w:tcW",
+        "w:tcBorders",
+        "w:vMerge",
+        "w:gridSpan",
+        "w:hyperlink",
+        "w:bookmarkStart",
+        "w:bookmarkEnd",
+        "w:proofErr",
+        "w:fldChar",
+        "w:instrText",
+        "w:fldSimple",
+        "w:sectPr",
+        "w:drawing",
+        "w:sdt",
+        "w:sdtPr",
+        "w:sdtContent",
+        "w:ins",
+        "w:del",
+        "w:delText",
+        "w:smartTag",
+        "w:lastRenderedPageBreak",
+        "w:footnoteReference",
+        "w:endnoteReference",
+        "w:commentRangeStart",
+        "w:commentRangeEnd",
+        "w:commentReference",
+        "w:val",
+        "w:type",
+        "w:after",
+        "w:before",
+        "w:line",
+        "w:lineRule",
+        "w:ascii",
+        "w:hAnsi",
+        "w:eastAsia",
+        "w:cs",
+        "w:hint",
+        "w:fill",
+        "w:id",
+        "w:name",
+        "w:rsidR",
+        "w:rsidRPr",
+        "w:rsidRDefault",
+        "w:rsidP",
+        "w:rsidDel",
+        "w:fldCharType",
+        "w:left",
+        "w:right",
+        "w:firstLine",
+        "w:hanging",
+        "w14:paraId",
+        "w14:textId",
+        "xml:space",
+        "r:id",
+        "Relationship",
+        "Id",
+        "Type",
+        "Target",
+        "TargetMode",
+    ]
+    .into_iter()
+    .map(XmlName::from)
+    .collect()
+});
+
+/// Per-parse name table: common OOXML names come from [`COMMON_NAMES`], anything else is
+/// allocated once per part.
+#[derive(Default)]
+struct NameInterner {
+    local: HashSet<XmlName>,
+}
+
+impl NameInterner {
+    fn intern(&mut self, bytes: &[u8]) -> XmlName {
+        let s = String::from_utf8_lossy(bytes);
+        if let Some(n) = COMMON_NAMES.get(s.as_ref()) {
+            return n.clone();
+        }
+        if let Some(n) = self.local.get(s.as_ref()) {
+            return n.clone();
+        }
+        let n = XmlName::from(s.as_ref());
+        self.local.insert(n.clone());
+        n
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum XmlEvent {
     Decl {
@@ -13,15 +229,15 @@ pub enum XmlEvent {
         standalone: Option<String>,
     },
     Start {
-        name: String,
-        attrs: Vec<(String, String)>,
+        name: XmlName,
+        attrs: Vec<XmlAttr>,
     },
     End {
-        name: String,
+        name: XmlName,
     },
     Empty {
-        name: String,
-        attrs: Vec<(String, String)>,
+        name: XmlName,
+        attrs: Vec<XmlAttr>,
     },
     Text {
         text: String,
@@ -47,15 +263,97 @@ pub struct XmlPart {
     pub baseline_hash: String,
 }
 
+/// A repair `--lenient-parse` applied to a part before parsing it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseFix {
+    pub part: String,
+    pub fix: String,
+}
+
+impl fmt::Display for ParseFix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&tr_args(
+            "pipeline.lenient_fixed",
+            &[("part", &self.part), ("fix", &self.fix)],
+        ))
+    }
+}
+
 pub fn parse_xml_part(name: &str, xml_bytes: &[u8]) -> anyhow::Result<XmlPart> {
+    parse_xml_events(name, xml_bytes)
+}
+
+/// Like `parse_xml_part`; with `lenient` (`--lenient-parse`) known XML issues are repaired first
+/// instead of failing the parse, and the repairs are returned with the part.
+pub fn parse_xml_part_with(
+    name: &str,
+    xml_bytes: &[u8],
+    lenient: bool,
+) -> anyhow::Result<(XmlPart, Vec<ParseFix>)> {
+    if !lenient {
+        return Ok((parse_xml_events(name, xml_bytes)?, Vec::new()));
+    }
+    let (fixed, fixes) = sanitize_xml(xml_bytes);
+    let fixes = fixes
+        .into_iter()
+        .map(|fix| ParseFix {
+            part: name.to_string(),
+            fix,
+        })
+        .collect();
+    Ok((parse_xml_events(name, &fixed)?, fixes))
+}
+
+/// Deepest element nesting a part may have. Word stays within a few dozen levels; the limit
+/// keeps the per-level state of the event walkers bounded on hostile input.
+const MAX_XML_DEPTH: usize = 1024;
+
+/// Parse `xml` and write it back: no filesystem, no repairs (as without `--lenient-parse`), and
+/// an error rather than a panic on any input. The rewritten bytes must parse back
+/// to the same events, so this is the entry point for fuzzing the XML layer and for embedders
+/// that get parts from untrusted clients.
+pub fn parse_and_rewrite(xml: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let part = parse_xml_events("part.xml", xml)?;
+    let out = write_xml_part(&part)?;
+    let again = parse_xml_events("part.xml", &out).context("parse rewritten xml")?;
+    if full_hash(&again.events) != full_hash(&part.events) {
+        let diff = first_event_difference(&part.events, &again.events, 2)
+            .map(|d| d.to_string())
+            .unwrap_or_default();
+        return Err(anyhow!(
+            "rewritten xml does not parse back to the same events: {diff}"
+        ));
+    }
+    Ok(out)
+}
+
+fn parse_xml_events(name: &str, xml_bytes: &[u8]) -> anyhow::Result<XmlPart> {
     let mut reader = Reader::from_reader(xml_bytes);
     reader.config_mut().trim_text(false);
 
     let mut events: Vec<XmlEvent> = Vec::new();
+    let mut names = NameInterner::default();
     let mut buf = Vec::new();
+    let mut depth = 0usize;
     loop {
         buf.clear();
         let ev = reader.read_event_into(&mut buf).context("read xml event")?;
+        match &ev {
+            Event::Start(_) => {
+                depth += 1;
+                if depth > MAX_XML_DEPTH {
+                    return Err(anyhow!(
+                        "xml nesting deeper than {MAX_XML_DEPTH} levels at byte {}",
+                        reader.buffer_position()
+                    ));
+                }
+            }
+            Event::End(_) => depth = depth.saturating_sub(1),
+            Event::Eof if depth > 0 => {
+                return Err(anyhow!("xml ends with {depth} unclosed element(s)"));
+            }
+            _ => {}
+        }
         match ev {
             Event::Eof => break,
             Event::Decl(d) => {
@@ -78,19 +376,19 @@ pub fn parse_xml_part(name: &str, xml_bytes: &[u8]) -> anyhow::Result<XmlPart> {
             }
             Event::Start(s) => {
                 events.push(XmlEvent::Start {
-                    name: bytes_to_string(s.name().as_ref()),
-                    attrs: collect_attrs(&s)?,
+                    name: names.intern(s.name().as_ref()),
+                    attrs: collect_attrs(&s, &mut names)?,
                 });
             }
             Event::End(e) => {
                 events.push(XmlEvent::End {
-                    name: bytes_to_string(e.name().as_ref()),
+                    name: names.intern(e.name().as_ref()),
                 });
             }
             Event::Empty(s) => {
                 events.push(XmlEvent::Empty {
-                    name: bytes_to_string(s.name().as_ref()),
-                    attrs: collect_attrs(&s)?,
+                    name: names.intern(s.name().as_ref()),
+                    attrs: collect_attrs(&s, &mut names)?,
                 });
             }
             Event::Text(t) => {
@@ -127,23 +425,54 @@ pub fn parse_xml_part(name: &str, xml_bytes: &[u8]) -> anyhow::Result<XmlPart> {
     })
 }
 
-fn collect_attrs(s: &BytesStart<'_>) -> anyhow::Result<Vec<(String, String)>> {
-    let mut attrs: Vec<(String, String)> = Vec::new();
+fn collect_attrs(s: &BytesStart<'_>, names: &mut NameInterner) -> anyhow::Result<Vec<XmlAttr>> {
+    let mut attrs: Vec<XmlAttr> = Vec::new();
     for a in s.attributes() {
         let a = a.context("attr")?;
-        let key = bytes_to_string(a.key.as_ref());
+        let key = names.intern(a.key.as_ref());
         // Keep raw (already-escaped) attribute bytes.
         // This is required for lossless round-trip of attribute values such as `o:gfxdata`
         // (VML), which encodes CRLF using character references (e.g. `&#13;&#10;`). If we
         // unescape those references into literal newlines and then write them back, XML
         // normalization will change the value (newlines in attribute values become spaces),
         // corrupting embedded objects.
-        let val = bytes_to_string(a.value.as_ref());
+        let mut val = bytes_to_string(a.value.as_ref());
+        // Written back in double quotes: a `"` of a single-quoted value becomes a reference, as
+        // does a raw `<` (not well-formed, but accepted by the reader).
+        if val.contains(['"', '<']) {
+            val = val.replace('"', "&quot;").replace('<', "&lt;");
+        }
         attrs.push((key, val));
     }
     Ok(attrs)
 }
 
+/// Text of an attribute value as stored by `collect_attrs` (still escaped). A value with a
+/// malformed reference is returned as-is.
+pub fn unescape_attr(raw: &str) -> String {
+    quick_xml::escape::unescape(raw)
+        .map(|s| s.into_owned())
+        .unwrap_or_else(|_| raw.to_string())
+}
+
+/// Stored (escaped) form of attribute text, the inverse of `unescape_attr`. Tabs and line
+/// breaks become character references so attribute-value normalization keeps them.
+pub fn escape_attr(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '"' => out.push_str("&quot;"),
+            '\t' => out.push_str("&#9;"),
+            '\n' => out.push_str("&#10;"),
+            '\r' => out.push_str("&#13;"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
 fn bytes_to_string(bytes: impl AsRef<[u8]>) -> String {
     String::from_utf8_lossy(bytes.as_ref()).into_owned()
 }
@@ -165,15 +494,27 @@ pub fn write_xml_part(part: &XmlPart) -> anyhow::Result<Vec<u8>> {
         }
     }
 
-    fn write_start_like(out: &mut Vec<u8>, name: &str, attrs: &[(String, String)], empty: bool) {
+    fn write_start_like(
+        out: &mut Vec<u8>,
+        name: &str,
+        attrs: &[XmlAttr],
+        empty: bool,
+    ) -> anyhow::Result<()> {
+        check_name(name)?;
         out.extend_from_slice(b"<");
         out.extend_from_slice(name.as_bytes());
-        // Attribute values are stored as raw (already-escaped) XML bytes. Do NOT escape again.
+        // Attribute values are stored as raw (already-escaped) XML bytes. Do NOT escape again;
+        // only the delimiter and `<` (never valid raw) are replaced.
         for (k, v) in attrs {
+            check_name(k)?;
             out.extend_from_slice(b" ");
             out.extend_from_slice(k.as_bytes());
             out.extend_from_slice(b"=\"");
-            out.extend_from_slice(v.as_bytes());
+            if v.contains(['"', '<']) {
+                out.extend_from_slice(v.replace('"', "&quot;").replace('<', "&lt;").as_bytes());
+            } else {
+                out.extend_from_slice(v.as_bytes());
+            }
             out.extend_from_slice(b"\"");
         }
         if empty {
@@ -181,6 +522,7 @@ pub fn write_xml_part(part: &XmlPart) -> anyhow::Result<Vec<u8>> {
         } else {
             out.extend_from_slice(b">");
         }
+        Ok(())
     }
 
     for ev in &part.events {
@@ -197,31 +539,40 @@ pub fn write_xml_part(part: &XmlPart) -> anyhow::Result<Vec<u8>> {
                 out.extend_from_slice(&writer.into_inner());
             }
             XmlEvent::Start { name, attrs } => {
-                write_start_like(&mut out, name, attrs, false);
+                write_start_like(&mut out, name, attrs, false)?;
             }
             XmlEvent::End { name } => {
+                check_name(name)?;
                 out.extend_from_slice(b"</");
                 out.extend_from_slice(name.as_bytes());
                 out.extend_from_slice(b">");
             }
             XmlEvent::Empty { name, attrs } => {
-                write_start_like(&mut out, name, attrs, true);
+                write_start_like(&mut out, name, attrs, true)?;
             }
             XmlEvent::Text { text } => {
                 escape_text_into(&mut out, text);
             }
             XmlEvent::CData { text } => {
-                // CDATA must remain unescaped.
+                // CDATA must remain unescaped; a `]]>` inside is split over two sections.
                 out.extend_from_slice(b"<![CDATA[");
-                out.extend_from_slice(text.as_bytes());
+                out.extend_from_slice(text.replace("]]>", "]]]]><![CDATA[>").as_bytes());
                 out.extend_from_slice(b"]]>");
             }
             XmlEvent::Comment { text } => {
+                if text.contains("-->") {
+                    return Err(anyhow!("comment cannot be written: contains \"-->\""));
+                }
                 out.extend_from_slice(b"<!--");
                 out.extend_from_slice(text.as_bytes());
                 out.extend_from_slice(b"-->");
             }
             XmlEvent::PI { content } => {
+                if content.contains("?>") {
+                    return Err(anyhow!(
+                        "processing instruction cannot be written: contains \"?>\""
+                    ));
+                }
                 out.extend_from_slice(b"<?");
                 out.extend_from_slice(content.as_bytes());
                 out.extend_from_slice(b"?>");
@@ -237,6 +588,15 @@ pub fn write_xml_part(part: &XmlPart) -> anyhow::Result<Vec<u8>> {
     Ok(out)
 }
 
+/// An element or attribute name that can be written without breaking the markup.
+fn check_name(name: &str) -> anyhow::Result<()> {
+    let bad = |c: char| c.is_whitespace() || matches!(c, '<' | '>' | '/' | '=' | '"' | '\'' | '&');
+    if name.is_empty() || name.contains(bad) {
+        return Err(anyhow!("invalid xml name: {name:?}"));
+    }
+    Ok(())
+}
+
 pub fn verify_structure_unchanged(part: &XmlPart) -> anyhow::Result<()> {
     let cur = structure_hash(&part.events);
     if cur != part.baseline_hash {
@@ -250,18 +610,35 @@ pub fn verify_structure_unchanged(part: &XmlPart) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// The attribute of element `name` (inside `parent`) whose value is display text and therefore
+/// translated like text: numbering level text and the strings of legacy form fields
+/// (`w:ffData`: dropdown entries, text input default, help/status text). Field names,
+/// bookmarks and AutoText references stay untouched.
+pub fn text_attr_key(name: &str, parent: Option<&str>, attrs: &[XmlAttr]) -> Option<&'static str> {
+    let has_val = || attrs.iter().any(|(k, _)| k == "w:val");
+    match name {
+        "w:lvlText" | "w:listEntry" => has_val().then_some("w:val"),
+        "w:default" if parent == Some("w:textInput") => has_val().then_some("w:val"),
+        "w:helpText" | "w:statusText" => {
+            let auto_text = attrs.iter().any(|(k, v)| k == "w:type" && v == "autoText");
+            (!auto_text && has_val()).then_some("w:val")
+        }
+        _ => None,
+    }
+}
+
 fn structure_hash(events: &[XmlEvent]) -> String {
     let mut hasher = Sha256::new();
-    let mut stack: Vec<String> = Vec::new();
+    let mut stack: Vec<XmlName> = Vec::new();
 
     for ev in events {
         match ev {
             XmlEvent::Start { name, attrs } => {
+                hash_start_like(&mut hasher, name, stack.last().map(XmlName::as_str), attrs);
                 stack.push(name.clone());
-                hash_start_like(&mut hasher, name, attrs);
             }
             XmlEvent::Empty { name, attrs } => {
-                hash_start_like(&mut hasher, name, attrs);
+                hash_start_like(&mut hasher, name, stack.last().map(XmlName::as_str), attrs);
                 hash_end_like(&mut hasher, name);
             }
             XmlEvent::End { name } => {
@@ -319,9 +696,183 @@ fn structure_hash(events: &[XmlEvent]) -> String {
     hex::encode(hasher.finalize())
 }
 
+/// Where two parts first differ under `full_hash` semantics, with surrounding context.
+#[derive(Clone, Debug, Serialize)]
+pub struct EventDiff {
+    /// Event index in the original part (`None`: the original ended first).
+    pub orig_event: Option<usize>,
+    /// Event index in the restored part (`None`: the restored part ended first).
+    pub restored_event: Option<usize>,
+    pub expected: Option<String>,
+    pub actual: Option<String>,
+    /// Records just before the difference (identical on both sides).
+    pub before: Vec<String>,
+    pub orig_after: Vec<String>,
+    pub restored_after: Vec<String>,
+}
+
+impl std::fmt::Display for EventDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let idx = |i: Option<usize>| i.map(|i| format!("#{i}")).unwrap_or_else(|| "end".into());
+        writeln!(
+            f,
+            "first difference at event {} (restored {})",
+            idx(self.orig_event),
+            idx(self.restored_event)
+        )?;
+        for line in &self.before {
+            writeln!(f, "    {line}")?;
+        }
+        writeln!(
+            f,
+            "  - {}",
+            self.expected.as_deref().unwrap_or("(end of part)")
+        )?;
+        write!(
+            f,
+            "  + {}",
+            self.actual.as_deref().unwrap_or("(end of part)")
+        )
+    }
+}
+
+/// First differing `full_hash` record between `orig` and `restored`.
+pub fn first_event_difference(
+    orig: &[XmlEvent],
+    restored: &[XmlEvent],
+    context: usize,
+) -> Option<EventDiff> {
+    let a = full_records(orig);
+    let b = full_records(restored);
+    let pos =
+        (0..a.len().max(b.len())).find(|&i| a.get(i).map(|r| &r.1) != b.get(i).map(|r| &r.1))?;
+    let after = |recs: &[(usize, String)]| {
+        recs.iter()
+            .skip(pos + 1)
+            .take(context)
+            .map(|r| r.1.clone())
+            .collect::<Vec<_>>()
+    };
+    Some(EventDiff {
+        orig_event: a.get(pos).map(|r| r.0),
+        restored_event: b.get(pos).map(|r| r.0),
+        expected: a.get(pos).map(|r| r.1.clone()),
+        actual: b.get(pos).map(|r| r.1.clone()),
+        before: a[pos.saturating_sub(context)..pos]
+            .iter()
+            .map(|r| r.1.clone())
+            .collect(),
+        orig_after: after(&a),
+        restored_after: after(&b),
+    })
+}
+
+/// Readable form of the records `full_hash` hashes, tagged with their event index.
+/// `<name a="1" b="2">` with attributes sorted, so attribute order never shows up as a change.
+fn canonical_start(name: &str, attrs: &[XmlAttr]) -> String {
+    let sorted: BTreeMap<&str, &str> = attrs
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+    let attrs: String = sorted.iter().map(|(k, v)| format!(" {k}={v:?}")).collect();
+    format!("<{name}{attrs}>")
+}
+
+fn full_records(events: &[XmlEvent]) -> Vec<(usize, String)> {
+    let mut out = Vec::with_capacity(events.len());
+    let mut stack: Vec<&str> = Vec::new();
+    for (i, ev) in events.iter().enumerate() {
+        match ev {
+            XmlEvent::Start { name, attrs } => {
+                stack.push(name);
+                out.push((i, canonical_start(name, attrs)));
+            }
+            XmlEvent::Empty { name, attrs } => {
+                out.push((i, canonical_start(name, attrs)));
+                out.push((i, format!("</{name}>")));
+            }
+            XmlEvent::End { name } => {
+                out.push((i, format!("</{name}>")));
+                let _ = stack.pop();
+            }
+            XmlEvent::Text { text } => {
+                let cur = stack.last().copied().unwrap_or("");
+                out.push((i, format!("text in <{cur}>: {text:?}")));
+            }
+            XmlEvent::Decl {
+                version,
+                encoding,
+                standalone,
+            } => out.push((
+                i,
+                format!(
+                    "<?xml version={version:?} encoding={encoding:?} standalone={standalone:?}?>"
+                ),
+            )),
+            XmlEvent::CData { text } => out.push((i, format!("CDATA {text:?}"))),
+            XmlEvent::Comment { text } => out.push((i, format!("<!--{text}-->"))),
+            XmlEvent::PI { content } => out.push((i, format!("<?{content}?>"))),
+            XmlEvent::DocType { text } => out.push((i, format!("<!DOCTYPE {text}>"))),
+        }
+    }
+    out
+}
+
+/// Indented, canonicalized rendering of a part's event stream, one event per line prefixed with
+/// its index (the `event_index` used by offsets.json / text.json). Attributes are sorted and text
+/// is quoted so whitespace is visible; `<x/>` stays a single event.
+pub fn dump_xml_part(part: &XmlPart) -> String {
+    let width = part.events.len().saturating_sub(1).to_string().len();
+    let mut out = String::new();
+    let mut depth = 0usize;
+    for (i, ev) in part.events.iter().enumerate() {
+        if matches!(ev, XmlEvent::End { .. }) {
+            depth = depth.saturating_sub(1);
+        }
+        let line = match ev {
+            XmlEvent::Start { name, attrs } => canonical_start(name, attrs),
+            XmlEvent::Empty { name, attrs } => {
+                let mut s = canonical_start(name, attrs);
+                s.insert(s.len() - 1, '/');
+                s
+            }
+            XmlEvent::End { name } => format!("</{name}>"),
+            XmlEvent::Text { text } => format!("{text:?}"),
+            XmlEvent::CData { text } => format!("<![CDATA[{text:?}]]>"),
+            XmlEvent::Decl {
+                version,
+                encoding,
+                standalone,
+            } => {
+                let mut s = format!("<?xml version={version:?}");
+                if let Some(e) = encoding {
+                    s.push_str(&format!(" encoding={e:?}"));
+                }
+                if let Some(sa) = standalone {
+                    s.push_str(&format!(" standalone={sa:?}"));
+                }
+                s.push_str("?>");
+                s
+            }
+            XmlEvent::Comment { text } => format!("<!--{text}-->"),
+            XmlEvent::PI { content } => format!("<?{content}?>"),
+            XmlEvent::DocType { text } => format!("<!DOCTYPE{text}>"),
+        };
+        out.push_str(&format!(
+            "[{i:>width$}] {:indent$}{line}\n",
+            "",
+            indent = depth * 2
+        ));
+        if matches!(ev, XmlEvent::Start { .. }) {
+            depth += 1;
+        }
+    }
+    out
+}
+
 pub fn full_hash(events: &[XmlEvent]) -> String {
     let mut hasher = Sha256::new();
-    let mut stack: Vec<String> = Vec::new();
+    let mut stack: Vec<XmlName> = Vec::new();
 
     for ev in events {
         match ev {
@@ -389,7 +940,13 @@ pub fn full_hash(events: &[XmlEvent]) -> String {
 
 #[cfg(test)]
 mod tests {
-    use super::{parse_xml_part, write_xml_part};
+    use std::sync::Arc;
+
+    use super::{
+        first_event_difference, parse_xml_part, parse_xml_part_with, write_xml_part, XmlEvent,
+    };
+    use crate::docx::apply::apply_node_text;
+    use crate::docx::extract::extract_translation_units;
 
     #[test]
     fn write_preserves_attr_entity_refs() {
@@ -401,28 +958,93 @@ mod tests {
         assert!(s.contains(r#"o:gfxdata="A&#xD;&#xA;B""#));
         assert!(!s.contains(r#"o:gfxdata="A&amp;#xD;"#));
     }
+
+    #[test]
+    fn list_entry_ampersand_round_trips() {
+        let xml = br#"<w:ffData><w:ddList><w:listEntry w:val="R&amp;D"/></w:ddList></w:ffData>"#;
+        let mut part = parse_xml_part("word/document.xml", xml).expect("parse xml");
+        let (tus, _) = extract_translation_units(&part, 1).expect("extract");
+        let span = &tus[0].spans[0];
+        assert_eq!(span.source_text, "R&D");
+
+        apply_node_text(&mut part, &span.node_refs[0], "F&E \"<neu>\"").expect("apply");
+        let out = write_xml_part(&part).expect("write xml");
+        assert_eq!(
+            out,
+            br#"<w:ffData><w:ddList><w:listEntry w:val="F&amp;E &quot;&lt;neu>&quot;"/></w:ddList></w:ffData>"#
+        );
+        let reparsed = parse_xml_part("word/document.xml", &out).expect("reparse xml");
+        let (tus, _) = extract_translation_units(&reparsed, 1).expect("extract again");
+        assert_eq!(tus[0].spans[0].source_text, "F&E \"<neu>\"");
+    }
+
+    #[test]
+    fn lenient_parse_returns_its_fixes() {
+        let xml = b"<r><t>A &nbsp;B</t></r>";
+        assert!(parse_xml_part_with("word/document.xml", xml, false).is_err());
+
+        let (part, fixes) = parse_xml_part_with("word/document.xml", xml, true).expect("parse");
+        let out = write_xml_part(&part).expect("write xml");
+        assert_eq!(out, "<r><t>A \u{a0}B</t></r>".as_bytes());
+        assert!(!fixes.is_empty());
+        assert!(fixes.iter().all(|f| f.part == "word/document.xml"));
+    }
+
+    #[test]
+    fn first_difference_pinpoints_event() {
+        let a = parse_xml_part("a.xml", br#"<r><p b="2" a="1"/><t>x</t></r>"#).expect("parse a");
+        let b = parse_xml_part("b.xml", br#"<r><p a="1" b="2"></p><t>y</t></r>"#).expect("parse b");
+        assert!(first_event_difference(&a.events, &a.events, 2).is_none());
+
+        let diff = first_event_difference(&a.events, &b.events, 2).expect("diff");
+        assert_eq!(diff.orig_event, Some(3));
+        assert_eq!(diff.restored_event, Some(4));
+        assert_eq!(diff.expected.as_deref(), Some(r#"text in <t>: "x""#));
+        assert_eq!(diff.actual.as_deref(), Some(r#"text in <t>: "y""#));
+        assert_eq!(diff.before, vec!["</p>".to_string(), "<t>".to_string()]);
+    }
+
+    #[test]
+    fn repeated_names_share_storage() {
+        let xml = br#"<x:root><x:item k="1"/><x:item k="2"/><w:p/><w:p/></x:root>"#;
+        let part = parse_xml_part("test.xml", xml).expect("parse xml");
+        let names: Vec<_> = part
+            .events
+            .iter()
+            .filter_map(|ev| match ev {
+                XmlEvent::Empty { name, attrs } => Some((name, attrs)),
+                _ => None,
+            })
+            .collect();
+        assert!(Arc::ptr_eq(&names[0].0 .0, &names[1].0 .0));
+        assert!(Arc::ptr_eq(&names[0].1[0].0 .0, &names[1].1[0].0 .0));
+        assert!(Arc::ptr_eq(&names[2].0 .0, &names[3].0 .0));
+        assert_eq!(names[2].0, "w:p");
+        assert_eq!(write_xml_part(&part).expect("write xml"), xml);
+    }
 }
 
 fn is_text_tag(name: &str) -> bool {
     name == "w:t" || name == "a:t" || name == "w:delText"
 }
 
-fn hash_start_like(hasher: &mut Sha256, name: &str, attrs: &[(String, String)]) {
+fn hash_start_like(hasher: &mut Sha256, name: &str, parent: Option<&str>, attrs: &[XmlAttr]) {
     hasher.update(b"S:");
     hasher.update(name.as_bytes());
     hasher.update(b"|");
 
-    let mut map: BTreeMap<String, String> = BTreeMap::new();
+    let text_attr = text_attr_key(name, parent, attrs);
+    let mut map: BTreeMap<&str, &str> = BTreeMap::new();
     for (k, v) in attrs {
         if k == "xml:space" {
             continue;
         }
-        let val = if is_lvltext(name) && k == "w:val" {
-            String::new()
+        let val = if text_attr == Some(k.as_str()) {
+            ""
         } else {
-            v.clone()
+            v.as_str()
         };
-        map.insert(k.clone(), val);
+        map.insert(k.as_str(), val);
     }
     for (k, v) in map {
         hasher.update(k.as_bytes());
@@ -433,14 +1055,14 @@ fn hash_start_like(hasher: &mut Sha256, name: &str, attrs: &[(String, String)])
     hasher.update(b"\n");
 }
 
-fn hash_start_like_full(hasher: &mut Sha256, name: &str, attrs: &[(String, String)]) {
+fn hash_start_like_full(hasher: &mut Sha256, name: &str, attrs: &[XmlAttr]) {
     hasher.update(b"S:");
     hasher.update(name.as_bytes());
     hasher.update(b"|");
 
-    let mut map: BTreeMap<String, String> = BTreeMap::new();
+    let mut map: BTreeMap<&str, &str> = BTreeMap::new();
     for (k, v) in attrs {
-        map.insert(k.clone(), v.clone());
+        map.insert(k.as_str(), v.as_str());
     }
     for (k, v) in map {
         hasher.update(k.as_bytes());
@@ -456,7 +1078,3 @@ fn hash_end_like(hasher: &mut Sha256, name: &str) {
     hasher.update(name.as_bytes());
     hasher.update(b"\n");
 }
-
-fn is_lvltext(name: &str) -> bool {
-    name == "w:lvlText"
-}
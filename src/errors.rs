@@ -0,0 +1,216 @@
+//! Failure classes, process exit codes and the `--error-json` report.
+//!
+//! Exit codes (stable; scripts may rely on them):
+//!
+//! | code | kind               | meaning                                              |
+//! |------|--------------------|------------------------------------------------------|
+//! | 0    | -                  | success                                              |
+//! | 1    | `internal`         | unclassified error                                   |
+//! | 2    | `usage`            | bad command line (also used by clap)                 |
+//! | 3    | `io`               | file not found / not readable / not writable         |
+//! | 4    | `output_exists`    | output exists and `--force` was not given            |
+//! | 5    | `cancelled`        | the embedder cancelled the run (`CancellationToken`) |
+//! | 10   | `config`           | config file / prompts / backend resolution failed    |
+//! | 20   | `input_docx`       | input DOCX cannot be read or decomposed              |
+//! | 21   | `input_file`       | input text / HTML file cannot be read or parsed      |
+//! | 22   | `password`         | encrypted input: password missing or wrong           |
+//! | 30   | `model`            | model file missing or failed to load                 |
+//! | 31   | `model_oom`        | llama.cpp ran out of (GPU) memory                    |
+//! | 32   | `context_overflow` | prompt does not fit the model context                |
+//! | 40   | `validation`       | translation output failed validation irrecoverably   |
+//! | 50   | `merge`            | writing the output DOCX failed                       |
+
+use std::fmt;
+use std::path::Path;
+
+use anyhow::Context;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+
+static TU_ID_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"tu_id=(\d+)").expect("tu_id regex"));
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    Internal,
+    Usage,
+    Io,
+    OutputExists,
+    Cancelled,
+    Config,
+    InputDocx,
+    InputFile,
+    Password,
+    Model,
+    ModelOom,
+    ContextOverflow,
+    Validation,
+    Merge,
+}
+
+impl ErrorKind {
+    /// Same name as in `--error-json`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorKind::Internal => "internal",
+            ErrorKind::Usage => "usage",
+            ErrorKind::Io => "io",
+            ErrorKind::OutputExists => "output_exists",
+            ErrorKind::Cancelled => "cancelled",
+            ErrorKind::Config => "config",
+            ErrorKind::InputDocx => "input_docx",
+            ErrorKind::InputFile => "input_file",
+            ErrorKind::Password => "password",
+            ErrorKind::Model => "model",
+            ErrorKind::ModelOom => "model_oom",
+            ErrorKind::ContextOverflow => "context_overflow",
+            ErrorKind::Validation => "validation",
+            ErrorKind::Merge => "merge",
+        }
+    }
+
+    pub fn exit_code(self) -> u8 {
+        match self {
+            ErrorKind::Internal => 1,
+            ErrorKind::Usage => 2,
+            ErrorKind::Io => 3,
+            ErrorKind::OutputExists => 4,
+            ErrorKind::Cancelled => 5,
+            ErrorKind::Config => 10,
+            ErrorKind::InputDocx => 20,
+            ErrorKind::InputFile => 21,
+            ErrorKind::Password => 22,
+            ErrorKind::Model => 30,
+            ErrorKind::ModelOom => 31,
+            ErrorKind::ContextOverflow => 32,
+            ErrorKind::Validation => 40,
+            ErrorKind::Merge => 50,
+        }
+    }
+}
+
+/// Wrapper recording the failure class and/or pipeline stage of an error.
+/// Displays as the plain message with the wrapped error as its source, so it reads like a normal
+/// `.context("...")` but stays visible to `err.chain()` downcasts at every layer.
+#[derive(Debug)]
+pub struct ErrorTag {
+    pub kind: Option<ErrorKind>,
+    pub stage: Option<String>,
+    pub message: String,
+    source: anyhow::Error,
+}
+
+impl fmt::Display for ErrorTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ErrorTag {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+pub trait ResultExt<T> {
+    /// Like `.context(msg)`, additionally classifying the error.
+    fn kind(self, kind: ErrorKind, msg: &str) -> anyhow::Result<T>;
+    /// Record the pipeline stage the error happened in (`stage: <name>`).
+    fn stage(self, stage: &str) -> anyhow::Result<T>;
+}
+
+impl<T> ResultExt<T> for anyhow::Result<T> {
+    fn kind(self, kind: ErrorKind, msg: &str) -> anyhow::Result<T> {
+        self.map_err(|source| {
+            anyhow::Error::new(ErrorTag {
+                kind: Some(kind),
+                stage: None,
+                message: msg.to_string(),
+                source,
+            })
+        })
+    }
+
+    fn stage(self, stage: &str) -> anyhow::Result<T> {
+        self.map_err(|source| {
+            anyhow::Error::new(ErrorTag {
+                kind: None,
+                stage: Some(stage.to_string()),
+                message: format!("stage: {stage}"),
+                source,
+            })
+        })
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ErrorReport {
+    pub kind: ErrorKind,
+    pub exit_code: u8,
+    pub stage: Option<String>,
+    pub tu_id: Option<usize>,
+    pub message: String,
+    pub causes: Vec<String>,
+}
+
+impl ErrorReport {
+    pub fn from_error(err: &anyhow::Error) -> Self {
+        let causes: Vec<String> = err.chain().map(|e| e.to_string()).collect();
+        let full = causes.join(" | ");
+
+        // Innermost tags are the most specific.
+        let mut kind: Option<ErrorKind> = None;
+        let mut stage: Option<String> = None;
+        let mut io = false;
+        for cause in err.chain() {
+            if let Some(tag) = cause.downcast_ref::<ErrorTag>() {
+                if tag.kind.is_some() {
+                    kind = tag.kind;
+                }
+                if tag.stage.is_some() {
+                    stage = tag.stage.clone();
+                }
+            }
+            if cause.downcast_ref::<std::io::Error>().is_some() {
+                io = true;
+            }
+        }
+
+        // Model runtime failures are recognized by message wherever they were raised.
+        let kind = if crate::cancel::is_cancelled(err) {
+            ErrorKind::Cancelled
+        } else if full.contains("likely OOM") || full.contains("out of memory") {
+            ErrorKind::ModelOom
+        } else if full.contains("prompt_too_long") || full.contains("no room for generation") {
+            ErrorKind::ContextOverflow
+        } else if let Some(k) = kind {
+            k
+        } else if io {
+            ErrorKind::Io
+        } else {
+            ErrorKind::Internal
+        };
+
+        let tu_id = TU_ID_RE
+            .captures(&full)
+            .and_then(|c| c.get(1))
+            .and_then(|m| m.as_str().parse().ok());
+
+        Self {
+            kind,
+            exit_code: kind.exit_code(),
+            stage,
+            tu_id,
+            message: err.to_string(),
+            causes,
+        }
+    }
+
+    pub fn write_json(&self, path: &Path) -> anyhow::Result<()> {
+        let json = serde_json::to_vec_pretty(self).context("serialize error json")?;
+        std::fs::write(path, json)
+            .with_context(|| format!("write error json: {}", path.display()))?;
+        Ok(())
+    }
+}
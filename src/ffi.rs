@@ -81,6 +81,7 @@ pub extern "C" fn mt_translate_docx(
         None,
         None,
         None,
+        None,
     ) {
         Ok(v) => v,
         Err(err) => {
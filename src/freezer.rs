@@ -1,6 +1,6 @@
 use once_cell::sync::Lazy;
 use regex::Regex;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::ir::FreezeMaskSpan;
 use crate::sentinels::{nt_token, ANY_SENTINEL_RE, NT_RE};
@@ -29,21 +29,174 @@ static FREEZE_RE: Lazy<Regex> = Lazy::new(|| {
     let underscore_leader = r"_{5,}";
     let dash_leader = r"[-\u2010\u2011\u2012\u2013\u2014\u2015\u2212]{5,}";
     let trademark_token = r"[\p{L}\p{N}]{2,24}[\u00AE\u2122\u00A9]";
-    let other_script_run =
-        r"[\u0900-\u097F\u0980-\u09FF\u0600-\u06FF\u0400-\u04FF\u0370-\u03FF\u0590-\u05FF\u0E00-\u0E7F\uAC00-\uD7AF\u3040-\u309F\u30A0-\u30FF]+";
+    let other_script_run = r"[\u0900-\u097F\u0980-\u09FF\u0600-\u06FF\u0400-\u04FF\u0370-\u03FF\u0590-\u05FF\u0E00-\u0E7F\uAC00-\uD7AF\u3040-\u309F\u30A0-\u30FF]+";
     let var_marker = r"\b[XYZ]\b";
+    // NBSP, narrow NBSP (French guillemets, "10 %"), thin / figure / punctuation spaces, word
+    // joiner and soft hyphen: models turn them into plain spaces or drop them, so they travel as
+    // tokens and their counts are validated like any other NT token.
+    let typo_space = r"[\u00A0\u202F\u2009\u2007\u2008\u2060\u00AD]+";
 
     let pat = format!(
-        "({trademark_token}|{other_script_run}|{url}|{email}|{win_path}|{placeholder}|{percent_slot}|{clause_ref}|{enum_num}|{enum_roman}|{enum_alpha}|{dot_leader}|{underscore_leader}|{dash_leader}|{time_hhmm}|{number_plain}|{var_marker})"
+        "({typo_space}|{trademark_token}|{other_script_run}|{url}|{email}|{win_path}|{placeholder}|{percent_slot}|{clause_ref}|{enum_num}|{enum_roman}|{enum_alpha}|{dot_leader}|{underscore_leader}|{dash_leader}|{time_hhmm}|{number_plain}|{var_marker})"
     );
     Regex::new(&pat).expect("freeze regex")
 });
 
-pub fn freeze_text(text: &str) -> FreezeResult {
+/// All-caps words (digits allowed, e.g. B2B), a plural "s" excluded: APIs freezes "API". ASCII
+/// word boundaries, so CJK text may touch the word (适用于GDPR); `is_glued` rejects other letters.
+static ACRONYM_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?-u:\b)([A-Z0-9]*[A-Z][A-Z0-9]*[A-Z][A-Z0-9]*)s?(?-u:\b)").expect("acronym regex")
+});
+
+/// Longest all-caps word still taken for an acronym.
+const ACRONYM_MAX_CHARS: usize = 10;
+
+/// All-caps words that are emphasis rather than acronyms (contract and RFC 2119 wording, notice
+/// labels): always left to the model.
+const ACRONYM_STOPWORDS: &[&str] = &[
+    "A",
+    "ALL",
+    "AND",
+    "ANY",
+    "AS",
+    "BE",
+    "BY",
+    "CAUTION",
+    "DO",
+    "FOR",
+    "IF",
+    "IMPORTANT",
+    "IN",
+    "IS",
+    "IT",
+    "MAY",
+    "MUST",
+    "NO",
+    "NOT",
+    "NOTE",
+    "OF",
+    "ON",
+    "OPTIONAL",
+    "OR",
+    "RECOMMENDED",
+    "REQUIRED",
+    "SHALL",
+    "SHOULD",
+    "THE",
+    "TO",
+    "WARNING",
+    "WILL",
+];
+
+/// Acronym freezing (`[pipeline.acronyms]`): all-caps words such as GDPR or API travel as NT
+/// tokens, so the model can neither lower-case nor transliterate them, and their count is
+/// validated like any other NT token.
+#[derive(Clone, Debug)]
+pub struct AcronymRules {
+    pub enabled: bool,
+    /// Whole words frozen wherever they appear, whatever their case (SaaS, iOS).
+    allow: Option<Regex>,
+    /// All-caps words left to the model (on top of `ACRONYM_STOPWORDS`).
+    deny: HashSet<String>,
+}
+
+impl Default for AcronymRules {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            allow: None,
+            deny: HashSet::new(),
+        }
+    }
+}
+
+impl AcronymRules {
+    pub fn new(enabled: bool, allow: &[String], deny: &[String]) -> anyhow::Result<Self> {
+        let mut words: Vec<&str> = allow
+            .iter()
+            .map(|w| w.trim())
+            .filter(|w| !w.is_empty())
+            .collect();
+        // Longest first, so "SaaS" wins over "S" inside an alternation.
+        words.sort_by_key(|w| std::cmp::Reverse(w.len()));
+        let allow = if words.is_empty() {
+            None
+        } else {
+            let alts: Vec<String> = words.iter().map(|w| regex::escape(w)).collect();
+            Some(Regex::new(&format!(
+                r"(?-u:\b)(?:{})(?-u:\b)",
+                alts.join("|")
+            ))?)
+        };
+        Ok(Self {
+            enabled,
+            allow,
+            deny: deny.iter().map(|w| w.trim().to_string()).collect(),
+        })
+    }
+
+    /// Acronym spans of `plain` (byte ranges, in order). A text without lower-case letters (an
+    /// all-caps heading or label) only gets its allowlisted words: its capitals are not acronyms.
+    fn spans(&self, plain: &str, shouting: bool) -> Vec<(usize, usize)> {
+        if !self.enabled {
+            return Vec::new();
+        }
+        let mut spans: Vec<(usize, usize)> = self
+            .allow
+            .iter()
+            .flat_map(|re| re.find_iter(plain))
+            .map(|m| (m.start(), m.end()))
+            .collect();
+        if !shouting {
+            for caps in ACRONYM_RE.captures_iter(plain) {
+                let word = caps.get(1).expect("acronym group");
+                let text = word.as_str();
+                if text.len() > ACRONYM_MAX_CHARS
+                    || ACRONYM_STOPWORDS.contains(&text)
+                    || self.deny.contains(text)
+                    || is_glued(plain, word.start(), caps.get(0).unwrap().end())
+                {
+                    continue;
+                }
+                if !spans
+                    .iter()
+                    .any(|&(s, e)| word.start() < e && s < word.end())
+                {
+                    spans.push((word.start(), word.end()));
+                }
+            }
+        }
+        spans.sort_unstable();
+        spans
+    }
+}
+
+/// `plain[start..end]` continues a word of a cased script (GRÜNDE) or a number on either side.
+fn is_glued(plain: &str, start: usize, end: usize) -> bool {
+    let word_char = |c: char| c.is_uppercase() || c.is_lowercase() || c.is_numeric() || c == '_';
+    plain[..start].chars().next_back().is_some_and(word_char)
+        || plain[end..].chars().next().is_some_and(word_char)
+}
+
+/// Cased letters all upper-case, and no letters of uncased scripts (CJK) around them.
+fn is_shouting(text: &str) -> bool {
+    let plain = ANY_SENTINEL_RE.replace_all(text, " ");
+    let mut upper = false;
+    for c in plain.chars().filter(|c| c.is_alphabetic()) {
+        if c.is_lowercase() || !c.is_uppercase() {
+            return false;
+        }
+        upper = true;
+    }
+    upper
+}
+
+pub fn freeze_text_with(text: &str, acronyms: &AcronymRules) -> FreezeResult {
     let mut nt_map: HashMap<String, String> = HashMap::new();
     let mut rev_map: HashMap<String, String> = HashMap::new();
     let mut mask: Vec<FreezeMaskSpan> = Vec::new();
     let mut next_id: usize = 1;
+    let shouting = is_shouting(text);
 
     let mut add_token = |original: &str| -> String {
         if let Some(tok) = rev_map.get(original) {
@@ -64,22 +217,34 @@ pub fn freeze_text(text: &str) -> FreezeResult {
         if plain.is_empty() {
             return String::new();
         }
+        let mut spans: Vec<(usize, usize)> = FREEZE_RE
+            .find_iter(plain)
+            .map(|m| (m.start(), m.end()))
+            .collect();
+        let fixed = spans.len();
+        for (start, end) in acronyms.spans(plain, shouting) {
+            if !spans[..fixed].iter().any(|&(s, e)| start < e && s < end) {
+                spans.push((start, end));
+            }
+        }
+        spans.sort_unstable();
+
         let mut out = String::with_capacity(plain.len());
         let mut pos = 0usize;
-        for m in FREEZE_RE.find_iter(plain) {
-            if m.start() > pos {
-                out.push_str(&plain[pos..m.start()]);
+        for (start, end) in spans {
+            if start > pos {
+                out.push_str(&plain[pos..start]);
             }
-            let original = &plain[m.start()..m.end()];
+            let original = &plain[start..end];
             let token = add_token(original);
             mask.push(FreezeMaskSpan {
-                src_start: base.saturating_add(m.start()),
-                src_end: base.saturating_add(m.end()),
+                src_start: base.saturating_add(start),
+                src_end: base.saturating_add(end),
                 token: token.clone(),
                 original: original.to_string(),
             });
             out.push_str(&token);
-            pos = m.end();
+            pos = end;
         }
         if pos < plain.len() {
             out.push_str(&plain[pos..]);
@@ -120,6 +285,48 @@ pub fn unfreeze_text(text: &str, nt_map: &HashMap<String, String>) -> String {
         .into_owned()
 }
 
+/// Freeze `text` with an existing NT map, e.g. an intermediate translation of the unit the map
+/// came from: spans the freeze rules match and whose text is in `nt_map` become their tokens
+/// again; everything else is kept as is.
+pub fn refreeze_text(text: &str, nt_map: &HashMap<String, String>) -> String {
+    if nt_map.is_empty() || text.is_empty() {
+        return text.to_string();
+    }
+    let tokens: HashMap<&str, &str> = nt_map
+        .iter()
+        .map(|(tok, original)| (original.as_str(), tok.as_str()))
+        .collect();
+    let refreeze_plain = |plain: &str| {
+        let frozen = FREEZE_RE.replace_all(plain, |caps: &regex::Captures<'_>| {
+            let original = caps.get(0).unwrap().as_str();
+            tokens
+                .get(original)
+                .copied()
+                .unwrap_or(original)
+                .to_string()
+        });
+        ACRONYM_RE
+            .replace_all(&frozen, |caps: &regex::Captures<'_>| {
+                let (word, core) = (caps.get(0).unwrap().as_str(), caps.get(1).unwrap().as_str());
+                match tokens.get(core) {
+                    Some(tok) => format!("{tok}{}", &word[core.len()..]),
+                    None => word.to_string(),
+                }
+            })
+            .into_owned()
+    };
+
+    let mut out = String::with_capacity(text.len());
+    let mut pos = 0usize;
+    for m in ANY_SENTINEL_RE.find_iter(text) {
+        out.push_str(&refreeze_plain(&text[pos..m.start()]));
+        out.push_str(m.as_str());
+        pos = m.end();
+    }
+    out.push_str(&refreeze_plain(&text[pos..]));
+    out
+}
+
 pub fn normalize_nt_tokens(
     source_frozen: &str,
     nt_map: &HashMap<String, String>,
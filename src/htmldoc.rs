@@ -0,0 +1,549 @@
+//! HTML documents: mask markup with placeholders (like `docx::decompose` masks XML), extract text
+//! slots, and merge translated slots back.
+//!
+//! The mask is the source HTML with every translatable text node / attribute value replaced by
+//! `__MT_MASK_<prefix>_<id>__`; the offsets reuse `OffsetsJson` (part_name = file name,
+//! event_index = position in the token stream). Slot texts are entity-decoded with collapsed
+//! whitespace (non-breaking spaces are kept); leading/trailing whitespace of a text node stays
+//! in the mask.
+//!
+//! Translation groups the text slots of each block element into one `HtmlBlock`, like the DOCX
+//! paragraph units of `pipeline::docmap`: inline tags stay in the mask, and the slot markers in
+//! the block surface say where each piece of the translation goes.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::docx::artifact::ArtifactProducer;
+use crate::docx::decompose::{
+    file_placeholder_prefix, placeholder, OffsetsJson, PlaceholderPrefix, SlotKind, TextSlot,
+};
+use crate::sentinels::{parse_slot_output, slot_token};
+
+/// Elements whose content is never translated (kept verbatim in the mask).
+const RAW_ELEMENTS: [&str; 6] = ["script", "style", "pre", "code", "svg", "math"];
+/// Attributes holding human-readable text.
+const TEXT_ATTRS: [&str; 4] = ["alt", "title", "placeholder", "aria-label"];
+/// Elements that stay inside the text block around them; any other tag starts a new block.
+const INLINE_ELEMENTS: [&str; 30] = [
+    "a", "abbr", "b", "bdi", "bdo", "br", "cite", "code", "data", "del", "dfn", "em", "font", "i",
+    "img", "ins", "kbd", "mark", "q", "s", "samp", "small", "span", "strong", "sub", "sup", "time",
+    "u", "var", "wbr",
+];
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HtmlMaskJson {
+    pub version: u32,
+    pub placeholder_prefix: String,
+    pub template: String,
+}
+
+static ATTR_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"([^\s=/>"']+)(?:\s*=\s*("[^"]*"|'[^']*'|[^\s>"']+))?"#).expect("html attr regex")
+});
+static ENTITY_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"&(#[0-9]{1,7}|#[xX][0-9a-fA-F]{1,6}|[A-Za-z][A-Za-z0-9]{1,31});")
+        .expect("html entity regex")
+});
+
+pub fn is_html_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_ascii_lowercase())
+        .is_some_and(|s| matches!(s.as_str(), "html" | "htm" | "xhtml"))
+}
+
+/// Mask `input_html` and return `(mask, offsets, slot_texts)`; slot `id` maps to
+/// `slot_texts[id - 1]`.
+pub fn extract_html(
+    input_html: &Path,
+    prefix: &PlaceholderPrefix,
+) -> anyhow::Result<(HtmlMaskJson, OffsetsJson, Vec<String>)> {
+    let bytes =
+        fs::read(input_html).with_context(|| format!("read html: {}", input_html.display()))?;
+    let source_sha256 = hex::encode(Sha256::digest(&bytes));
+    let source = String::from_utf8(bytes)
+        .map_err(|_| anyhow!("html is not UTF-8: {}", input_html.display()))?;
+    let prefix = file_placeholder_prefix(input_html, prefix)?;
+    let part_name = input_html
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or("index.html")
+        .to_string();
+
+    let mut template = String::with_capacity(source.len());
+    let mut slots: Vec<TextSlot> = Vec::new();
+    let mut slot_texts: Vec<String> = Vec::new();
+    let mut add_slot = |kind: SlotKind, event_index: usize, attr: Option<&str>, text: String| {
+        let id = slot_texts.len() + 1;
+        slots.push(TextSlot {
+            id,
+            part_name: part_name.clone(),
+            kind,
+            event_index,
+            attr_name: attr.map(|s| s.to_string()),
+            anchor: None,
+            source_text: None,
+        });
+        slot_texts.push(text);
+        placeholder(&prefix, id)
+    };
+
+    for (event_index, tok) in tokenize(&source).into_iter().enumerate() {
+        match tok {
+            HtmlToken::Markup(s) => template.push_str(s),
+            HtmlToken::Tag { raw, name } => {
+                template.push_str(&mask_tag_attrs(raw, &name, |attr, value| {
+                    add_slot(SlotKind::Attr, event_index, Some(attr), value)
+                }));
+            }
+            HtmlToken::Text(raw) => {
+                let body = trim_ws(raw);
+                let text = collapse_ws(&decode_entities(body));
+                if text.chars().all(char::is_whitespace) {
+                    template.push_str(raw);
+                    continue;
+                }
+                let lead = raw.len() - raw.trim_start_matches(is_collapsible_ws).len();
+                template.push_str(&raw[..lead]);
+                template.push_str(&add_slot(SlotKind::Text, event_index, None, text));
+                template.push_str(&raw[lead + body.len()..]);
+            }
+        }
+    }
+
+    let mask = HtmlMaskJson {
+        version: 1,
+        placeholder_prefix: prefix.clone(),
+        template,
+    };
+    let offsets = OffsetsJson {
+        version: 1,
+        producer: Some(ArtifactProducer::new(&source_sha256)),
+        placeholder_prefix: prefix,
+        slots,
+    };
+    Ok((mask, offsets, slot_texts))
+}
+
+/// Fill the mask with `slot_texts` (escaped for their context). Every slot placeholder must be
+/// present exactly once.
+pub fn merge_html(
+    mask: &HtmlMaskJson,
+    offsets: &OffsetsJson,
+    slot_texts: &[String],
+) -> anyhow::Result<String> {
+    if mask.placeholder_prefix != offsets.placeholder_prefix {
+        return Err(anyhow!(
+            "placeholder prefix mismatch: mask={} offsets={}",
+            mask.placeholder_prefix,
+            offsets.placeholder_prefix
+        ));
+    }
+    let mut out = mask.template.clone();
+    for slot in &offsets.slots {
+        let ph = placeholder(&offsets.placeholder_prefix, slot.id);
+        let text = slot_texts
+            .get(slot.id.saturating_sub(1))
+            .ok_or_else(|| anyhow!("slot_id_out_of_range slot_id={}", slot.id))?;
+        let escaped = match slot.kind {
+            SlotKind::Attr => escape_html(text, true),
+            SlotKind::Text | SlotKind::CData => escape_html(text, false),
+        };
+        if out.matches(&ph).count() != 1 {
+            return Err(anyhow!(
+                "placeholder missing or duplicated in html mask: {ph}"
+            ));
+        }
+        out = out.replacen(&ph, &escaped, 1);
+    }
+    let leftover = format!("__MT_MASK_{}_", offsets.placeholder_prefix);
+    if out.contains(&leftover) {
+        return Err(anyhow!("unmerged placeholders left in html output"));
+    }
+    Ok(out)
+}
+
+/// The text slots of one block element (`<p>`, `<li>`, `<td>`, ...), translated as one unit.
+/// A text attribute is a block of its own.
+#[derive(Clone, Debug, Default)]
+pub struct HtmlBlock {
+    pub slot_ids: Vec<usize>,
+    /// Whether the mask has whitespace between each slot and the next one.
+    space_after: Vec<bool>,
+}
+
+impl HtmlBlock {
+    /// The block text with a slot marker before each slot and `<<MT_SLOT:000000>>` at the end,
+    /// like the DOCX paragraph surfaces.
+    pub fn surface(&self, slot_texts: &[String]) -> String {
+        let mut out = String::new();
+        for (slot_id, space) in self.slot_ids.iter().zip(&self.space_after) {
+            out.push_str(&slot_token(*slot_id));
+            out.push_str(
+                slot_texts
+                    .get(slot_id - 1)
+                    .map(String::as_str)
+                    .unwrap_or(""),
+            );
+            if *space {
+                out.push(' ');
+            }
+        }
+        out.push_str(&slot_token(0));
+        out
+    }
+
+    /// Split a translated `surface` at its slot markers into `slot_texts`; the whitespace
+    /// between slots stays the mask's.
+    pub fn project(&self, translated: &str, slot_texts: &mut [String]) -> anyhow::Result<()> {
+        let mut expected = self.slot_ids.clone();
+        expected.push(0);
+        let segs = parse_slot_output(translated, &expected)?;
+        if segs.get(&0).is_some_and(|tail| !tail.trim().is_empty()) {
+            return Err(anyhow!("slot_terminator_has_content"));
+        }
+        for slot_id in &self.slot_ids {
+            let text = slot_texts
+                .get_mut(slot_id - 1)
+                .ok_or_else(|| anyhow!("slot_id_out_of_range slot_id={slot_id}"))?;
+            *text = segs
+                .get(slot_id)
+                .map(|s| trim_ws(s))
+                .unwrap_or("")
+                .to_string();
+        }
+        Ok(())
+    }
+}
+
+/// Group the slots of `mask` into blocks, in document order of their first slot.
+pub fn html_blocks(mask: &HtmlMaskJson) -> anyhow::Result<Vec<HtmlBlock>> {
+    let ph_re = Regex::new(&format!(
+        "__MT_MASK_{}_([0-9]{{8}})__",
+        regex::escape(&mask.placeholder_prefix)
+    ))
+    .context("placeholder regex")?;
+    let slot_id = |caps: &regex::Captures<'_>| -> anyhow::Result<usize> {
+        caps[1].parse().context("placeholder id")
+    };
+    let mut blocks: Vec<HtmlBlock> = Vec::new();
+    let mut cur = HtmlBlock::default();
+    // Whitespace in the mask since the last text slot of `cur`.
+    let mut space = false;
+    let end_block = |cur: &mut HtmlBlock, blocks: &mut Vec<HtmlBlock>| {
+        if !cur.slot_ids.is_empty() {
+            blocks.push(std::mem::take(cur));
+        }
+    };
+    for tok in tokenize(&mask.template) {
+        let name = match tok {
+            HtmlToken::Tag { raw, name } => {
+                for caps in ph_re.captures_iter(raw) {
+                    blocks.push(HtmlBlock {
+                        slot_ids: vec![slot_id(&caps)?],
+                        space_after: vec![false],
+                    });
+                }
+                name
+            }
+            HtmlToken::Markup(raw) => match raw.strip_prefix("</") {
+                Some(rest) => rest
+                    .chars()
+                    .take_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == ':')
+                    .collect::<String>()
+                    .to_ascii_lowercase(),
+                None => continue,
+            },
+            HtmlToken::Text(raw) => {
+                let Some(caps) = ph_re.captures(raw) else {
+                    space |= !raw.is_empty();
+                    continue;
+                };
+                let m = caps.get(0).expect("whole match");
+                space |= raw[..m.start()].contains(char::is_whitespace);
+                if let Some(last) = cur.space_after.last_mut() {
+                    *last = space;
+                }
+                cur.slot_ids.push(slot_id(&caps)?);
+                cur.space_after.push(false);
+                space = raw[m.end()..].contains(char::is_whitespace);
+                continue;
+            }
+        };
+        if name == "br" {
+            space = true;
+        } else if !INLINE_ELEMENTS.contains(&name.as_str()) {
+            end_block(&mut cur, &mut blocks);
+            space = false;
+        }
+    }
+    end_block(&mut cur, &mut blocks);
+    blocks.sort_by_key(|b| b.slot_ids[0]);
+    Ok(blocks)
+}
+
+enum HtmlToken<'a> {
+    /// Comments, doctype, processing instructions, closing tags, raw element content.
+    Markup(&'a str),
+    /// Opening / self-closing tag (attributes may hold text).
+    Tag {
+        raw: &'a str,
+        name: String,
+    },
+    Text(&'a str),
+}
+
+fn tokenize(src: &str) -> Vec<HtmlToken<'_>> {
+    let mut out: Vec<HtmlToken<'_>> = Vec::new();
+    let bytes = src.as_bytes();
+    let mut pos = 0usize;
+    while pos < src.len() {
+        let rest = &src[pos..];
+        let markup_end = if rest.starts_with("<!--") {
+            Some(find_after(rest, "-->", 4))
+        } else if rest.starts_with("<![CDATA[") {
+            Some(find_after(rest, "]]>", 9))
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            Some(find_after(rest, ">", 2))
+        } else if rest.starts_with("</")
+            && bytes.get(pos + 2).is_some_and(|b| b.is_ascii_alphabetic())
+        {
+            Some(tag_end(rest))
+        } else {
+            None
+        };
+        if let Some(len) = markup_end {
+            out.push(HtmlToken::Markup(&rest[..len]));
+            pos += len;
+            continue;
+        }
+
+        if rest.starts_with('<') && bytes.get(pos + 1).is_some_and(|b| b.is_ascii_alphabetic()) {
+            let len = tag_end(rest);
+            let raw = &rest[..len];
+            let name: String = raw[1..]
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == ':')
+                .collect::<String>()
+                .to_ascii_lowercase();
+            pos += len;
+            let self_closing = raw.ends_with("/>");
+            out.push(HtmlToken::Tag {
+                raw,
+                name: name.clone(),
+            });
+            if !self_closing && RAW_ELEMENTS.contains(&name.as_str()) {
+                let body = &src[pos..];
+                let close = format!("</{name}");
+                let body_len = body.to_ascii_lowercase().find(&close).unwrap_or(body.len());
+                if body_len > 0 {
+                    out.push(HtmlToken::Markup(&body[..body_len]));
+                }
+                pos += body_len;
+            }
+            continue;
+        }
+
+        // Text up to the next markup start (a bare '<' that does not open a tag is text).
+        let mut end = pos + 1;
+        while end < src.len() {
+            if bytes[end] == b'<'
+                && bytes
+                    .get(end + 1)
+                    .is_some_and(|b| b.is_ascii_alphabetic() || matches!(b, b'/' | b'!' | b'?'))
+            {
+                break;
+            }
+            end += 1;
+        }
+        out.push(HtmlToken::Text(&src[pos..end]));
+        pos = end;
+    }
+    out
+}
+
+fn find_after(s: &str, pat: &str, from: usize) -> usize {
+    s.get(from..)
+        .and_then(|t| t.find(pat))
+        .map(|i| from + i + pat.len())
+        .unwrap_or(s.len())
+}
+
+/// Length of the tag starting at `s[0] == '<'`, honoring quoted attribute values.
+fn tag_end(s: &str) -> usize {
+    let mut quote: Option<u8> = None;
+    for (i, b) in s.bytes().enumerate().skip(1) {
+        match quote {
+            Some(q) if b == q => quote = None,
+            Some(_) => {}
+            None if b == b'"' || b == b'\'' => quote = Some(b),
+            None if b == b'>' => return i + 1,
+            None => {}
+        }
+    }
+    s.len()
+}
+
+fn mask_tag_attrs(
+    raw: &str,
+    name: &str,
+    mut add_slot: impl FnMut(&str, String) -> String,
+) -> String {
+    let body_start = 1 + name.len();
+    if body_start >= raw.len() {
+        return raw.to_string();
+    }
+    let mut out = String::with_capacity(raw.len());
+    out.push_str(&raw[..body_start]);
+    let mut cursor = body_start;
+    for caps in ATTR_RE.captures_iter(&raw[body_start..]) {
+        let (Some(attr), Some(value)) = (caps.get(1), caps.get(2)) else {
+            continue;
+        };
+        let attr_name = attr.as_str().to_ascii_lowercase();
+        if !TEXT_ATTRS.contains(&attr_name.as_str()) {
+            continue;
+        }
+        let v = value.as_str();
+        let inner = v
+            .strip_prefix('"')
+            .and_then(|s| s.strip_suffix('"'))
+            .or_else(|| v.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')))
+            .unwrap_or(v);
+        let text = collapse_ws(&decode_entities(inner));
+        if text.chars().all(char::is_whitespace) {
+            continue;
+        }
+        let start = body_start + value.start();
+        let end = body_start + value.end();
+        out.push_str(&raw[cursor..start]);
+        out.push('"');
+        out.push_str(&add_slot(&attr_name, text));
+        out.push('"');
+        cursor = end;
+    }
+    out.push_str(&raw[cursor..]);
+    out
+}
+
+/// Whitespace that HTML collapses; U+00A0 (`&nbsp;`) is not, so it stays in the slot text.
+fn is_collapsible_ws(c: char) -> bool {
+    c.is_whitespace() && c != '\u{00A0}'
+}
+
+fn trim_ws(s: &str) -> &str {
+    s.trim_matches(is_collapsible_ws)
+}
+
+fn collapse_ws(s: &str) -> String {
+    s.split(is_collapsible_ws)
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn decode_entities(s: &str) -> String {
+    ENTITY_RE
+        .replace_all(s, |caps: &regex::Captures<'_>| {
+            let ent = &caps[1];
+            let ch = if let Some(hex) = ent.strip_prefix("#x").or_else(|| ent.strip_prefix("#X")) {
+                u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
+            } else if let Some(dec) = ent.strip_prefix('#') {
+                dec.parse::<u32>().ok().and_then(char::from_u32)
+            } else {
+                named_entity(ent)
+            };
+            match ch {
+                Some(c) => c.to_string(),
+                None => caps[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+fn named_entity(name: &str) -> Option<char> {
+    Some(match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{00A0}',
+        "copy" => '\u{00A9}',
+        "reg" => '\u{00AE}',
+        "trade" => '\u{2122}',
+        "hellip" => '\u{2026}',
+        "mdash" => '\u{2014}',
+        "ndash" => '\u{2013}',
+        "lsquo" => '\u{2018}',
+        "rsquo" => '\u{2019}',
+        "ldquo" => '\u{201C}',
+        "rdquo" => '\u{201D}',
+        _ => return None,
+    })
+}
+
+fn escape_html(s: &str, attr: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' if attr => out.push_str("&quot;"),
+            '\u{00A0}' => out.push_str("&nbsp;"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_text_is_one_unit_and_keeps_nbsp() {
+        let dir = std::env::temp_dir().join(format!("mt-html-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("temp dir");
+        let input = dir.join("page.html");
+        fs::write(
+            &input,
+            "<p>Hello <b>big</b> world&nbsp;!</p><img alt=\"A  cat\"><ul><li>One</li></ul>",
+        )
+        .expect("write html");
+
+        let (mask, offsets, mut slots) =
+            extract_html(&input, &PlaceholderPrefix::Fixed("T".to_string())).expect("extract");
+        assert_eq!(slots, ["Hello", "big", "world\u{a0}!", "A cat", "One"]);
+        let blocks = html_blocks(&mask).expect("blocks");
+        let ids: Vec<&[usize]> = blocks.iter().map(|b| b.slot_ids.as_slice()).collect();
+        assert_eq!(ids, [&[1, 2, 3][..], &[4], &[5]]);
+        assert_eq!(
+            blocks[0].surface(&slots),
+            "<<MT_SLOT:000001>>Hello <<MT_SLOT:000002>>big <<MT_SLOT:000003>>world\u{a0}!\
+             <<MT_SLOT:000000>>"
+        );
+
+        blocks[0]
+            .project(
+                "<<MT_SLOT:000001>>Bonjour <<MT_SLOT:000002>>grand <<MT_SLOT:000003>>monde\u{a0}!\
+                 <<MT_SLOT:000000>>",
+                &mut slots,
+            )
+            .expect("project");
+        let html = merge_html(&mask, &offsets, &slots).expect("merge");
+        assert!(
+            html.starts_with("<p>Bonjour <b>grand</b> monde&nbsp;!</p>"),
+            "{html}"
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
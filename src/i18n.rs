@@ -0,0 +1,111 @@
+//! Console message catalogs (`ui_lang`).
+//!
+//! Catalogs are embedded from `locales/<lang>.toml`; keys are `section.name`, values use
+//! `{placeholder}` arguments. Missing keys fall back to English, then to the key itself.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use once_cell::sync::{Lazy, OnceCell};
+
+const LEGACY_OUTPUT_SUFFIX: &str = "_翻译";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UiLang {
+    En,
+    Zh,
+}
+
+impl UiLang {
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim().to_ascii_lowercase();
+        match s.as_str() {
+            "en" | "english" => Some(Self::En),
+            "zh" | "cn" | "chinese" => Some(Self::Zh),
+            _ if s.starts_with("en-") || s.starts_with("en_") => Some(Self::En),
+            _ if s.starts_with("zh-") || s.starts_with("zh_") => Some(Self::Zh),
+            _ => None,
+        }
+    }
+}
+
+static UI_LANG: OnceCell<UiLang> = OnceCell::new();
+
+static CATALOG_EN: Lazy<HashMap<String, String>> =
+    Lazy::new(|| parse_catalog(include_str!("../locales/en.toml")));
+static CATALOG_ZH: Lazy<HashMap<String, String>> =
+    Lazy::new(|| parse_catalog(include_str!("../locales/zh.toml")));
+
+/// Set the console language once per process (first call wins). Unknown names are ignored
+/// with a warning so a typo never aborts a run.
+pub fn set_ui_lang(name: &str) {
+    match UiLang::parse(name) {
+        Some(lang) => {
+            let _ = UI_LANG.set(lang);
+        }
+        None => eprintln!("[warn] unknown ui_lang {name:?} (expected \"en\" or \"zh\")"),
+    }
+}
+
+pub fn ui_lang() -> UiLang {
+    UI_LANG.get().copied().unwrap_or(UiLang::En)
+}
+
+/// Localized message without arguments.
+pub fn tr(key: &str) -> String {
+    lookup(key).to_string()
+}
+
+/// Localized message with `{name}` arguments.
+pub fn tr_args(key: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut out = lookup(key).to_string();
+    for (name, value) in args {
+        out = out.replace(&format!("{{{name}}}"), &value.to_string());
+    }
+    out
+}
+
+/// Suffix for the default output name (`<stem><suffix>.docx`). Without an explicit `ui_lang`
+/// the historical `_翻译` suffix is kept.
+pub fn output_suffix() -> String {
+    match UI_LANG.get() {
+        Some(_) => tr("output_suffix"),
+        None => LEGACY_OUTPUT_SUFFIX.to_string(),
+    }
+}
+
+fn lookup(key: &str) -> &str {
+    let catalog = match ui_lang() {
+        UiLang::En => &*CATALOG_EN,
+        UiLang::Zh => &*CATALOG_ZH,
+    };
+    catalog
+        .get(key)
+        .or_else(|| CATALOG_EN.get(key))
+        .map(|s| s.as_str())
+        .unwrap_or(key)
+}
+
+fn parse_catalog(text: &str) -> HashMap<String, String> {
+    let mut out = HashMap::new();
+    let table: toml::Table = toml::from_str(text).expect("embedded locale catalog");
+    flatten("", &table, &mut out);
+    out
+}
+
+fn flatten(prefix: &str, table: &toml::Table, out: &mut HashMap<String, String>) {
+    for (k, v) in table {
+        let key = if prefix.is_empty() {
+            k.clone()
+        } else {
+            format!("{prefix}.{k}")
+        };
+        match v {
+            toml::Value::String(s) => {
+                out.insert(key, s.clone());
+            }
+            toml::Value::Table(t) => flatten(&key, t, out),
+            _ => {}
+        }
+    }
+}
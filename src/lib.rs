@@ -1,9 +1,14 @@
 pub mod agent;
 pub mod agentflow;
+pub mod async_api;
+pub mod cancel;
 pub mod config;
 pub mod docx;
+pub mod errors;
 pub mod ffi;
 pub mod freezer;
+pub mod htmldoc;
+pub mod i18n;
 pub mod ir;
 pub mod models;
 pub mod pipeline;
@@ -11,4 +16,5 @@ pub mod progress;
 pub mod quality;
 pub mod sentinels;
 pub mod terminology;
+pub mod textdoc;
 pub mod textutil;
@@ -1,19 +1,44 @@
-use std::path::PathBuf;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use std::process::ExitCode;
+use std::time::Instant;
 
 use anyhow::Context;
 use clap::{CommandFactory, Parser};
+use rayon::prelude::*;
 
-use muggle_translator::docx::package::DocxPackage;
-use muggle_translator::docx::pure_text::{default_text_output_for, extract_pure_text_json};
-use muggle_translator::docx::structure::{default_structure_output_for, extract_structure_json};
-use muggle_translator::docx::xml::{parse_xml_part, write_xml_part};
+use muggle_translator::docx::compare::{compare_docx, CompareOptions};
 use muggle_translator::docx::decompose::{
-    default_outputs_for, extract_mask_json_and_offsets, merge_mask_json_and_offsets,
-    verify_docx_roundtrip,
+    default_outputs_for, extract_mask_json_and_offsets_from, merge_mask_json_and_offsets,
+    merge_mask_json_and_offsets_with, verify_docx_roundtrip_with, write_merge_report,
+    BlobCompression, MaskOptions, MergeOptions, PlaceholderPrefix, SlotSubstitution,
 };
 use muggle_translator::docx::filter::{filter_docx_with_rules, DocxFilterRules};
-use muggle_translator::pipeline::{init_default_config, PipelineConfig, TranslatorPipeline};
+use muggle_translator::docx::highlight::clear_highlights;
+use muggle_translator::docx::mask_bundle::{merge_mask_bundle, write_mask_bundle};
+use muggle_translator::docx::package::{DocxPackage, PackageOptions};
+use muggle_translator::docx::pure_text::{
+    default_text_output_for, pure_text_from_session, write_pure_text_json,
+};
+use muggle_translator::docx::session::DocumentSession;
+use muggle_translator::docx::structure::{default_structure_output_for, write_structure_json};
+use muggle_translator::docx::xml::{dump_xml_part, parse_xml_part_with, write_xml_part, ParseFix};
+use muggle_translator::errors::{ErrorKind, ErrorReport, ResultExt};
+use muggle_translator::htmldoc::is_html_path;
+use muggle_translator::i18n::{output_suffix, set_ui_lang, tr, tr_args};
+use muggle_translator::pipeline::{
+    configured_highlight_color, configured_hooks, configured_link_rewrites,
+    configured_package_limits, configured_placeholder_prefix, configured_project_dir,
+    configured_ui_lang, default_workdir_for, flagged_unit_ids, in_workdir, init_default_config,
+    list_backends, list_prompts, parse_percent, parse_seed, project_term_report, random_seed,
+    record_artifacts, run_selftest, ExperimentSpec, HookPayload, OfflineMode, PipelineConfig,
+    PipelineMode, RunStats, TranslatorPipeline, UnitFilter, WorkdirManifest,
+    ARTIFACT_AUTOSAVE_TEXT, ARTIFACT_BLOBS, ARTIFACT_FINAL_TEXT, ARTIFACT_MASK, ARTIFACT_OFFSETS,
+    ARTIFACT_STRUCTURE, ARTIFACT_TEXT, DEFAULT_PSEUDO_EXPANSION,
+};
 use muggle_translator::progress::ConsoleProgress;
+use muggle_translator::textdoc::TextDocKind;
 
 #[derive(Parser, Debug)]
 #[command(name = "muggle-translator")]
@@ -27,18 +52,60 @@ struct Args {
     #[arg(long, value_name = "DIR")]
     init_config_dir: Option<PathBuf>,
 
-    /// Overwrite existing config/prompt files when used with --init-config
-    #[arg(long)]
+    /// Overwrite existing files: config/prompt files with --init-config, otherwise an existing output DOCX (not needed with --range/--scope/--from-report/--from-review, which update the previous output in place)
+    #[arg(long, alias = "overwrite")]
     force: bool,
 
-    /// Input .docx (drag-and-drop supported)
+    /// Input .docx, or .txt/.md/.html for plain-text/Markdown/HTML translation (drag-and-drop supported)
     #[arg(value_name = "DOCX")]
     input: Option<PathBuf>,
 
-    /// Output .docx (default: <input_stem>_翻译.docx)
+    /// Repair known XML problems (control characters, HTML entities, undeclared namespaces) instead of failing; fixes are reported
+    #[arg(long)]
+    lenient_parse: bool,
+
+    /// Placeholder prefix of mask/offsets/text artifacts: `hash` of the input file (default), `content` (hash of the document content, stable when the DOCX is re-saved) or a fixed prefix of 1-32 ASCII letters/digits; overrides input.placeholder_prefix
+    #[arg(long, value_name = "MODE")]
+    placeholder_prefix: Option<String>,
+
+    /// Password for an encrypted DOCX (agile encryption, Office 2010+); the output is not encrypted. Visible in process listings: prefer --password-prompt or MUGGLE_TRANSLATOR_PASSWORD
+    #[arg(long, value_name = "PASSWORD")]
+    password: Option<String>,
+
+    /// Ask for the password of an encrypted DOCX on the terminal (not echoed)
+    #[arg(long, conflicts_with = "password")]
+    password_prompt: bool,
+
+    /// List configured model backends (role, model path, exists?, ctx, template hint), then exit
+    #[arg(long)]
+    list_backends: bool,
+
+    /// List prompt files per stage (plus per-backend overrides) from the active config, then exit
+    #[arg(long)]
+    list_prompts: bool,
+
+    /// Translate UTF-8 text from stdin and print the translation to stdout (no DOCX; for pipes/editors)
+    #[arg(long, conflicts_with = "input")]
+    translate_text: bool,
+
+    /// Output .docx (default: <input_stem>_翻译.docx; <input_stem>_translated.docx with ui_lang = en)
     #[arg(short, long, value_name = "DOCX")]
     output: Option<PathBuf>,
 
+    /// Write the output DOCX and all artifacts (trace, autosave, variants) into this directory
+    #[arg(long, value_name = "DIR")]
+    output_dir: Option<PathBuf>,
+
+    /// Keep the intermediate artifacts (mask/offsets/blobs, text/structure JSON, autosaves, trace) in DIR, listed in DIR/manifest.json, instead of next to the input/output (`--workdir=DIR`; bare `--workdir`: `<input_stem>.mtwork/` next to the input); merge, --continue and --from-review find them there with the same --workdir, and without an input DIR is merged into `-o`
+    #[arg(
+        long,
+        value_name = "DIR",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = ""
+    )]
+    workdir: Option<String>,
+
     /// Force source language code (e.g. en, zh)
     #[arg(long)]
     source_lang: Option<String>,
@@ -51,7 +118,7 @@ struct Args {
     #[arg(long)]
     config: Option<PathBuf>,
 
-    /// Translation backend name from config (e.g. hy_mt, translategemma_12b)
+    /// Translation backend name from config (e.g. hy_mt); comma-separate names for a fallback chain
     #[arg(long)]
     translate_backend: Option<String>,
 
@@ -103,6 +170,99 @@ struct Args {
     #[arg(long)]
     ctx_controller: Option<u32>,
 
+    /// Project directory: accumulate terms/paragraph memory across documents (SQLite) and preload it into prompts
+    #[arg(long, value_name = "DIR")]
+    project: Option<PathBuf>,
+
+    /// Disable prompt/output trace files (pipeline artifacts are still written to the trace dir)
+    #[arg(long)]
+    no_trace: bool,
+
+    /// Pack trace dir + resolved config + validation report into a zip after the run (for bug reports)
+    #[arg(long, value_name = "ZIP")]
+    trace_bundle: Option<PathBuf>,
+
+    /// Redact document text in the trace bundle
+    #[arg(long)]
+    redact: bool,
+
+    /// Config profile: merge the config's `[profile.NAME]` table over the rest of the file (e.g. `fast`, `quality`)
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Console message language: en | zh (default: pipeline.ui_lang in the config, else en)
+    #[arg(long, value_name = "LANG")]
+    ui_lang: Option<String>,
+
+    /// On failure, write a structured error (kind, exit_code, stage, tu_id, message) to this JSON file
+    #[arg(long, value_name = "JSON")]
+    error_json: Option<PathBuf>,
+
+    /// Continue from the autosave text.json of a previous run (same input); only untranslated slots are sent to the model
+    #[arg(long = "continue")]
+    resume: bool,
+
+    /// Review every translated chunk before it is committed: accept, edit a line, or send units back for retranslation (basic mode)
+    #[arg(long)]
+    interactive: bool,
+
+    /// Sampling seed for every model: a number, or "random" for a fresh one per run (default: pipeline.seed, else 42)
+    #[arg(long, value_name = "N|random")]
+    seed: Option<String>,
+
+    /// Reproducible output: greedy decoding, fixed seed, sequential stages, no backend fallback (runs with a project memory also depend on what it has learned)
+    #[arg(long)]
+    deterministic: bool,
+
+    /// Pseudo-translate without a model: accented letters, every paragraph in [brackets] and longer by --pseudo-expansion percent, tokens and numbers untouched; for testing layout expansion and placeholder handling (DOCX only)
+    #[arg(long, conflicts_with_all = ["experiment", "emit_prompts_only", "proofread"])]
+    pseudo: bool,
+
+    /// Write the input back without translating it: extraction, filter rules (built-in ones, which merge adjacent runs, unless `pipeline.docx_filter_rules` is set) and merge give a cleaned, normalized DOCX for CAT tools (DOCX only, no LLM)
+    #[arg(long, conflicts_with_all = ["experiment", "emit_prompts_only", "proofread", "pseudo"])]
+    copy_source: bool,
+
+    /// Text growth of --pseudo, percent (default 30)
+    #[arg(long, value_name = "PCT", requires = "pseudo")]
+    pseudo_expansion: Option<u32>,
+
+    /// Proofread instead of translating: the text stays in its language and the model fixes grammar, spelling and style with the `proofread` prompt; all structure checks still apply
+    #[arg(long)]
+    proofread: bool,
+
+    /// Abort (validation error, no output) when more than PCT percent of the translated units fell back to the source text
+    #[arg(long, value_name = "PCT")]
+    fail_on_fallback: Option<f64>,
+
+    /// Re-check a random sample (seeded by --seed) of PCT percent of the translated units, e.g. `5%`: n-best alternatives and a backtranslation per unit, reported in `<stem>.qa-sample.json` in the trace dir; divergent units are flagged
+    #[arg(long, value_name = "PCT")]
+    qa_sample: Option<String>,
+
+    /// DOCX: retranslate only the paragraphs with ids START..END (end exclusive; also START..=END, START.., ..END); the rest keep the previous output's translation (source text without one)
+    #[arg(long, value_name = "START..END")]
+    range: Option<String>,
+
+    /// DOCX: retranslate only the paragraphs whose scope key matches PATTERN, e.g. `word/document.xml#w:p@*` (`*` matches any text; repeatable); the rest keep the previous output's translation
+    #[arg(long, value_name = "PATTERN")]
+    scope: Vec<String>,
+
+    /// DOCX: retranslate only the paragraphs flagged in a previous run's `<stem>.validation.json` (trace dir) and merge them onto that run's output (pass the same -o); combine with --translate-backend to use another model
+    #[arg(long, value_name = "PATH")]
+    from_report: Option<PathBuf>,
+
+    /// Flags --from-report selects: `hard_flags` (source fallbacks and hard quality flags), `all`, or a comma-separated list of flag names (`prefix*` allowed)
+    #[arg(
+        long,
+        value_name = "FLAGS",
+        default_value = "hard_flags",
+        requires = "from_report"
+    )]
+    only: String,
+
+    /// DOCX, full mode: read the Word comments a reviewer left on a previous run's output, map each to its paragraph and patch those paragraphs with the comments as instructions; the worklist goes to `<stem>.review_worklist.json` in the trace dir and the rest keeps that run's output (pass the same -o)
+    #[arg(long, value_name = "DOCX", conflicts_with = "from_report")]
+    from_review: Option<PathBuf>,
+
     /// Process at most N translation units (dev-only)
     #[arg(long)]
     max_tus: Option<usize>,
@@ -131,6 +291,18 @@ struct Args {
     #[arg(long, value_name = "BIN")]
     extract_mask_blobs: Option<PathBuf>,
 
+    /// zstd-compress the mask blobs written by `--extract-mask-json` / `--bundle`
+    #[arg(long)]
+    compress_mask_blobs: bool,
+
+    /// Extract mask + offsets + blobs + text JSON into one `.mtb` bundle (no LLM)
+    #[arg(long, value_name = "MTB")]
+    bundle: Option<PathBuf>,
+
+    /// Merge a `.mtb` bundle into `-o` (`--merge-text-json` replaces the bundled text; no LLM)
+    #[arg(long, value_name = "MTB")]
+    merge_bundle: Option<PathBuf>,
+
     /// Merge `--merge-mask-json` + `--merge-offsets-json` + `--merge-text-json` into `-o` (no LLM)
     #[arg(long, value_name = "JSON")]
     merge_mask_json: Option<PathBuf>,
@@ -139,14 +311,66 @@ struct Args {
     #[arg(long, value_name = "JSON")]
     merge_offsets_json: Option<PathBuf>,
 
-    /// Merge input pure-text JSON (must match mask/offsets placeholder_prefix; no LLM)
+    /// Merge input pure-text JSON (must match mask/offsets placeholder_prefix; `slot_texts` may be a sparse `{"<slot_id>": text}` map for a partial merge; no LLM)
     #[arg(long, value_name = "JSON")]
     merge_text_json: Option<PathBuf>,
 
+    /// Merge even with missing/invalid slot texts: use the source text instead and write `<stem>.merge-report.json`
+    #[arg(long)]
+    merge_lenient: bool,
+
     /// Verify extract->merge restores original (writes `<stem>.mask.json`, `<stem>.offsets.json`, `<stem>.text.json`)
     #[arg(long)]
     verify_extract_merge_json: bool,
 
+    /// Check every .docx in DIR (extract->merge round trip, the same after the filter rules of `--filter-rules` or the built-in ones, structure extraction) and print a pass/fail matrix, then exit (no LLM)
+    #[arg(long, value_name = "DIR")]
+    selftest: Option<PathBuf>,
+
+    /// Keep original XML bytes in the mask so unchanged parts merge byte-identical; `--verify-extract-merge-json` then requires byte identity
+    #[arg(long)]
+    strict_roundtrip: bool,
+
+    /// Print an indented dump of one XML part (e.g. `word/document.xml`) with event indices, then exit; `-o` writes it to a file
+    #[arg(long, value_name = "PART")]
+    dump_xml: Option<String>,
+
+    /// Write a blackline of DOCX against OLD_DOCX (differences as tracked changes) to `-o` (default: `<stem>.compare.docx`), then exit (no LLM)
+    #[arg(long, value_name = "OLD_DOCX")]
+    compare_with: Option<PathBuf>,
+
+    /// Remove the review highlights (`pipeline.highlight_color`, default yellow) from DOCX and write it to `-o` (default: `<stem>.clean.docx`), then exit (no LLM)
+    #[arg(long)]
+    clear_highlights: bool,
+
+    /// Report source terms translated differently across the documents of the project (`--project` or `pipeline.project_dir`), then exit; `-o` writes it to a file (no LLM)
+    #[arg(long)]
+    term_report: bool,
+
+    /// Input is a PDF: convert it to DOCX with the `[pdf] to_docx` command (LibreOffice by default), translate it, and convert back with `[pdf] to_pdf` when the output ends in .pdf
+    #[arg(long, conflicts_with = "experiment")]
+    from_pdf: bool,
+
+    /// Extract, chunk and render the Translate A prompts into the trace dir, then exit without loading a model (no output document)
+    #[arg(long)]
+    emit_prompts_only: bool,
+
+    /// Run an A/B experiment on a sample of the input's TUs and write a comparison report (no output document)
+    #[arg(long, value_name = "JSON")]
+    experiment: Option<PathBuf>,
+
+    /// Experiment backends `A,B` (default: translate_backend for both variants)
+    #[arg(long, value_name = "A,B")]
+    experiment_backends: Option<String>,
+
+    /// Experiment translate prompt files `A,B` (default: each backend's translate_a prompt)
+    #[arg(long, value_name = "A,B")]
+    experiment_prompts: Option<String>,
+
+    /// Number of TUs for --experiment, spread evenly over the document
+    #[arg(long, value_name = "N", default_value_t = 40)]
+    experiment_sample: usize,
+
     /// Filter DOCX XML (tag cleanup + optional run-merge) using `--filter-rules`, then exit (no LLM)
     #[arg(long)]
     filter_docx: bool,
@@ -156,8 +380,116 @@ struct Args {
     filter_rules: Option<PathBuf>,
 }
 
-fn main() -> anyhow::Result<()> {
+/// What `main` needs after `run`: the failure summary for drag-and-drop users and `[hooks]`.
+#[derive(Default)]
+struct RunContext {
+    trace_dir: Option<PathBuf>,
+    trace_bundle: Option<PathBuf>,
+    /// Set once a translation run starts; `[hooks]` fire only for those.
+    translation: Option<TranslationRun>,
+}
+
+struct TranslationRun {
+    input: PathBuf,
+    output: PathBuf,
+    config: Option<PathBuf>,
+    profile: Option<String>,
+    started: Instant,
+    stats: Option<RunStats>,
+}
+
+/// Exit codes are documented in `errors.rs`; `--error-json` gets the same classification.
+fn main() -> ExitCode {
     let args = Args::parse();
+    let error_json = args.error_json.clone();
+    let ui_lang = args.ui_lang.clone().or_else(|| {
+        configured_ui_lang(
+            args.input.as_deref(),
+            args.config.as_deref(),
+            args.profile.as_deref(),
+        )
+    });
+    if let Some(lang) = ui_lang {
+        set_ui_lang(&lang);
+    }
+    let pause = launched_without_terminal();
+    let mut ctx = RunContext::default();
+    let result = run(args, &mut ctx);
+    let report = result.as_ref().err().map(ErrorReport::from_error);
+    if let Err(err) = &result {
+        eprintln!("Error: {err:?}");
+    }
+    if let (Some(path), Some(report)) = (error_json.as_ref(), report.as_ref()) {
+        if let Err(err) = report.write_json(path) {
+            eprintln!("[warn] write error json failed: {err:#}");
+        }
+    }
+    if let Some(run) = ctx.translation.as_ref() {
+        let hooks = configured_hooks(
+            Some(&run.input),
+            run.config.as_deref(),
+            run.profile.as_deref(),
+        );
+        let payload = HookPayload {
+            event: if report.is_none() {
+                "success"
+            } else {
+                "failure"
+            },
+            input: &run.input,
+            output: &run.output,
+            duration_secs: run.started.elapsed().as_secs_f64(),
+            stats: run.stats.as_ref(),
+            error: report.as_ref(),
+        };
+        for (target, err) in hooks.run(&payload) {
+            eprintln!(
+                "{}",
+                tr_args(
+                    "main.hook_failed",
+                    &[("target", &target), ("err", &format!("{err:#}"))]
+                )
+            );
+        }
+    }
+    if pause {
+        match &result {
+            Ok(()) => eprintln!("\n{}", tr("main.done")),
+            Err(err) => {
+                eprintln!("\n{}", tr_args("main.error", &[("msg", err)]));
+                for cause in err.chain().skip(1).take(3) {
+                    eprintln!("{}", tr_args("main.caused_by", &[("msg", &cause)]));
+                }
+                if let Some(report) = report.as_ref() {
+                    eprintln!(
+                        "{}",
+                        tr_args(
+                            "main.error_kind",
+                            &[("kind", &report.kind.as_str()), ("code", &report.exit_code)],
+                        )
+                    );
+                }
+                if let Some(dir) = ctx.trace_dir.as_ref() {
+                    eprintln!("{}", tr_args("main.trace_dir", &[("path", &dir.display())]));
+                }
+                if let Some(zip) = ctx.trace_bundle.as_ref().filter(|p| p.exists()) {
+                    eprintln!(
+                        "{}",
+                        tr_args("main.trace_bundle", &[("path", &zip.display())])
+                    );
+                }
+            }
+        }
+        eprintln!("\n{}", tr("main.press_enter"));
+        let _ = std::io::stdin().read_line(&mut String::new());
+    }
+    match report {
+        None => ExitCode::SUCCESS,
+        Some(report) => ExitCode::from(report.exit_code),
+    }
+}
+
+fn run(args: Args, ctx: &mut RunContext) -> anyhow::Result<()> {
     let progress = ConsoleProgress::new(true);
 
     if args.init_config {
@@ -165,27 +497,146 @@ fn main() -> anyhow::Result<()> {
             .init_config_dir
             .clone()
             .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
-        let cfg_path = init_default_config(&dir, args.force).context("init default config")?;
-        eprintln!("Wrote config: {}", cfg_path.display());
+        let cfg_path =
+            init_default_config(&dir, args.force).kind(ErrorKind::Config, "init default config")?;
+        eprintln!(
+            "{}",
+            tr_args("main.wrote_config", &[("path", &cfg_path.display())])
+        );
         return Ok(());
     }
 
-    if let (Some(mask), Some(offsets), Some(text_json)) = (
-        args.merge_mask_json.as_ref(),
-        args.merge_offsets_json.as_ref(),
-        args.merge_text_json.as_ref(),
-    )
-    {
-        let output = args.output.clone().context("missing -o/--output for merge")?;
-        merge_mask_json_and_offsets(mask, offsets, text_json, &output)?;
+    if args.list_backends || args.list_prompts {
+        let input = args.input.as_deref();
+        let config = args.config.as_deref();
+        let profile = args.profile.as_deref();
+        if args.list_backends {
+            print!(
+                "{}",
+                list_backends(input, config, profile).kind(ErrorKind::Config, "load config")?
+            );
+        }
+        if args.list_prompts {
+            if args.list_backends {
+                println!();
+            }
+            print!(
+                "{}",
+                list_prompts(input, config, profile).kind(ErrorKind::Config, "load config")?
+            );
+        }
         return Ok(());
-    } else if args.merge_mask_json.is_some()
+    }
+
+    if args.term_report {
+        let dir = args
+            .project
+            .clone()
+            .or_else(|| {
+                configured_project_dir(
+                    args.input.as_deref(),
+                    args.config.as_deref(),
+                    args.profile.as_deref(),
+                )
+            })
+            .ok_or_else(|| {
+                anyhow::anyhow!("--term-report needs --project <DIR> or pipeline.project_dir")
+            })
+            .kind(ErrorKind::Usage, "term report")?;
+        let report = project_term_report(&dir).kind(ErrorKind::Io, "term report")?;
+        match args.output.as_ref() {
+            Some(path) => {
+                std::fs::write(path, report).with_context(|| format!("write {}", path.display()))?
+            }
+            None => print!("{report}"),
+        }
+        return Ok(());
+    }
+
+    if let Some(dir) = args.selftest.as_ref() {
+        let report =
+            run_selftest(dir, args.filter_rules.as_deref()).kind(ErrorKind::Usage, "selftest")?;
+        print!("{}", report.text);
+        if report.failed > 0 {
+            return Err(anyhow::anyhow!(
+                "{} of {} file(s) failed the self-test",
+                report.failed,
+                report.files
+            ))
+            .kind(ErrorKind::Validation, "selftest");
+        }
+        return Ok(());
+    }
+
+    let prefix_mode = match args.placeholder_prefix.as_deref() {
+        Some(value) => {
+            PlaceholderPrefix::parse(value).kind(ErrorKind::Usage, "--placeholder-prefix")?
+        }
+        None => configured_placeholder_prefix(
+            args.input.as_deref(),
+            args.config.as_deref(),
+            args.profile.as_deref(),
+        )
+        .kind(ErrorKind::Config, "load config")?,
+    };
+
+    let workdir = match args.workdir.as_deref() {
+        Some("") => Some(
+            args.input
+                .as_deref()
+                .map(default_workdir_for)
+                .context("bare --workdir needs an input (or pass --workdir=DIR)")
+                .kind(ErrorKind::Usage, "bad arguments")?,
+        ),
+        dir => dir.map(PathBuf::from),
+    };
+
+    if let Some(bundle) = args.merge_bundle.as_ref() {
+        let output = args
+            .output
+            .clone()
+            .context("missing -o/--output for merge")
+            .kind(ErrorKind::Usage, "bad arguments")?;
+        let output = resolve_output_dir(args.output_dir.as_deref(), output)?;
+        ensure_output_writable(&output, args.force)?;
+        let subs = merge_mask_bundle(
+            bundle,
+            args.merge_text_json.as_deref(),
+            &output,
+            &merge_options(&args)?,
+        )
+        .kind(ErrorKind::Merge, "merge")?;
+        report_merge_substitutions(&output, &subs)?;
+        return Ok(());
+    }
+
+    if args.merge_mask_json.is_some()
         || args.merge_offsets_json.is_some()
         || args.merge_text_json.is_some()
+        || (workdir.is_some() && args.input.is_none())
     {
-        return Err(anyhow::anyhow!(
-            "merge mode requires: --merge-mask-json, --merge-offsets-json, --merge-text-json, and -o/--output"
-        ));
+        let (mask, offsets, text_json) = merge_inputs(&args, workdir.as_deref())?;
+        let output = args
+            .output
+            .clone()
+            .context("missing -o/--output for merge")
+            .kind(ErrorKind::Usage, "bad arguments")?;
+        let output = resolve_output_dir(args.output_dir.as_deref(), output)?;
+        ensure_output_writable(&output, args.force)?;
+        let subs = merge_mask_json_and_offsets_with(
+            &mask,
+            &offsets,
+            &text_json,
+            &output,
+            &merge_options(&args)?,
+        )
+        .kind(ErrorKind::Merge, "merge")?;
+        report_merge_substitutions(&output, &subs)?;
+        return Ok(());
+    }
+
+    if args.translate_text {
+        return translate_stdin_text(args, progress);
     }
 
     let input = match args.input {
@@ -199,6 +650,97 @@ fn main() -> anyhow::Result<()> {
             return Ok(());
         }
     };
+    let package = PackageOptions {
+        limits: configured_package_limits(
+            Some(&input),
+            args.config.as_deref(),
+            args.profile.as_deref(),
+        ),
+        password: docx_password(args.password.clone(), args.password_prompt)?,
+        lenient: args.lenient_parse,
+    };
+    if let Some(part_name) = args.dump_xml.as_deref() {
+        let dump = dump_xml(&input, part_name, &package)?;
+        match args.output.as_ref() {
+            Some(path) => {
+                std::fs::write(path, dump).with_context(|| format!("write {}", path.display()))?
+            }
+            None => print!("{dump}"),
+        }
+        return Ok(());
+    }
+
+    if args.clear_highlights {
+        let output = match args.output.clone() {
+            Some(p) => p,
+            None => {
+                let stem = input
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .unwrap_or("output");
+                input.with_file_name(format!("{stem}.clean.docx"))
+            }
+        };
+        let output = resolve_output_dir(args.output_dir.as_deref(), output)?;
+        ensure_output_writable(&output, args.force)?;
+        let color = configured_highlight_color(
+            Some(&input),
+            args.config.as_deref(),
+            args.profile.as_deref(),
+        )
+        .kind(ErrorKind::Config, "load config")?;
+        let (removed, fixes) = clear_highlights(&input, &output, &color, &package)
+            .kind(ErrorKind::InputDocx, "clear highlights")?;
+        report_parse_fixes(&fixes);
+        eprintln!(
+            "{}",
+            tr_args(
+                "main.highlights_cleared",
+                &[
+                    ("count", &removed),
+                    ("color", &color),
+                    ("path", &output.display())
+                ]
+            )
+        );
+        return Ok(());
+    }
+
+    if let Some(old) = args.compare_with.as_ref() {
+        let output = match args.output.clone() {
+            Some(p) => p,
+            None => {
+                let stem = input
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .unwrap_or("output");
+                input.with_file_name(format!("{stem}.compare.docx"))
+            }
+        };
+        let output = resolve_output_dir(args.output_dir.as_deref(), output)?;
+        ensure_output_writable(&output, args.force)?;
+        let opts = CompareOptions {
+            package: package.clone(),
+            ..CompareOptions::default()
+        };
+        let summary =
+            compare_docx(old, &input, &output, &opts).kind(ErrorKind::InputDocx, "compare")?;
+        report_parse_fixes(&summary.parse_fixes);
+        eprintln!(
+            "{}",
+            tr_args(
+                "main.compare_written",
+                &[
+                    ("path", &output.display()),
+                    ("changed", &summary.paragraphs_changed),
+                    ("inserted", &summary.paragraphs_inserted),
+                    ("deleted", &summary.paragraphs_deleted),
+                ]
+            )
+        );
+        return Ok(());
+    }
+
     let output = match args.output {
         Some(p) => p,
         None => {
@@ -207,8 +749,49 @@ fn main() -> anyhow::Result<()> {
                 .and_then(|s| s.to_str())
                 .unwrap_or("output")
                 .to_string();
-            input.with_file_name(format!("{stem}_翻译.docx"))
+            let ext = if TextDocKind::from_path(&input).is_some() || is_html_path(&input) {
+                input.extension().and_then(|s| s.to_str()).unwrap_or("txt")
+            } else {
+                "docx"
+            };
+            input.with_file_name(format!("{stem}{}.{ext}", output_suffix()))
+        }
+    };
+    let output = resolve_output_dir(args.output_dir.as_deref(), output)?;
+    let writes_output = !(args.experiment.is_some()
+        || args.emit_prompts_only
+        || args.extract_text_json.is_some()
+        || args.extract_structure_json.is_some()
+        || args.extract_mask_json.is_some()
+        || args.extract_offsets_json.is_some()
+        || args.extract_mask_blobs.is_some()
+        || args.bundle.is_some());
+    // These update a previous run's output in place (the same -o), so it is expected to exist.
+    let updates_output = args.range.is_some()
+        || !args.scope.is_empty()
+        || args.from_report.is_some()
+        || args.from_review.is_some();
+    if writes_output && !updates_output {
+        ensure_output_writable(&output, args.force)?;
+    }
+
+    // Extraction defaults (`<stem>.mask.json`, ...) go next to the input, or into the workdir.
+    let artifact_base = match workdir.as_deref() {
+        Some(dir) => {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("create workdir: {}", dir.display()))?;
+            dir.join(input.file_name().unwrap_or_default())
         }
+        None => input.clone(),
+    };
+
+    let mask_options = MaskOptions {
+        compression: if args.compress_mask_blobs {
+            BlobCompression::Zstd
+        } else {
+            BlobCompression::None
+        },
+        keep_originals: args.strict_roundtrip,
     };
 
     if args.filter_docx {
@@ -216,8 +799,10 @@ fn main() -> anyhow::Result<()> {
             .filter_rules
             .clone()
             .unwrap_or_else(|| PathBuf::from("docx-filter-rules.toml"));
-        let rules = DocxFilterRules::from_toml_path(&rules_path)?;
-        filter_docx_with_rules(&input, &output, &rules)?;
+        let rules = DocxFilterRules::from_toml_path(&rules_path)
+            .kind(ErrorKind::Config, "load filter rules")?;
+        let fixes = filter_docx_with_rules(&input, &output, &rules, &package)?;
+        report_parse_fixes(&fixes);
         return Ok(());
     }
 
@@ -226,6 +811,7 @@ fn main() -> anyhow::Result<()> {
         || args.extract_mask_json.is_some()
         || args.extract_offsets_json.is_some()
         || args.extract_mask_blobs.is_some()
+        || args.bundle.is_some()
     {
         if args.extract_mask_blobs.is_some()
             && args.extract_mask_json.is_none()
@@ -233,41 +819,81 @@ fn main() -> anyhow::Result<()> {
         {
             return Err(anyhow::anyhow!(
                 "--extract-mask-blobs requires --extract-mask-json and/or --extract-offsets-json"
-            ));
+            ))
+            .kind(ErrorKind::Usage, "bad arguments");
         }
-        if let Some(text_json) = args.extract_text_json.clone() {
-            extract_pure_text_json(&input, &text_json)?;
-        }
-        if let Some(structure_json) = args.extract_structure_json.clone() {
-            extract_structure_json(&input, &structure_json)?;
+        let session = DocumentSession::open_with(&input, &package, &prefix_mode)?;
+        report_parse_fixes(session.parse_fixes());
+        let place = |path: &PathBuf| match workdir.as_deref() {
+            Some(dir) => in_workdir(dir, path),
+            None => path.clone(),
+        };
+        let mut written: Vec<(&str, PathBuf)> = Vec::new();
+        if args.extract_text_json.is_some() || args.extract_structure_json.is_some() {
+            let pure = pure_text_from_session(&session)?;
+            if let Some(text_json) = args.extract_text_json.as_ref().map(place) {
+                write_pure_text_json(&pure, &text_json)?;
+                written.push((ARTIFACT_TEXT, text_json));
+            }
+            if let Some(structure_json) = args.extract_structure_json.as_ref().map(place) {
+                write_structure_json(&pure, &structure_json)?;
+                written.push((ARTIFACT_STRUCTURE, structure_json));
+            }
         }
         if args.extract_mask_json.is_some() || args.extract_offsets_json.is_some() {
-            let defaults = default_outputs_for(&input);
-            let mask_json = args.extract_mask_json.clone().unwrap_or(defaults.mask_json_path);
+            let defaults = default_outputs_for(&artifact_base);
+            let mask_json = args
+                .extract_mask_json
+                .as_ref()
+                .map(place)
+                .unwrap_or(defaults.mask_json_path);
             let offsets_json = args
                 .extract_offsets_json
-                .clone()
+                .as_ref()
+                .map(place)
                 .unwrap_or(defaults.offsets_json_path);
             let blobs_bin = args
                 .extract_mask_blobs
-                .clone()
+                .as_ref()
+                .map(place)
                 .unwrap_or(defaults.blobs_bin_path);
-            extract_mask_json_and_offsets(&input, &mask_json, &offsets_json, &blobs_bin)?;
+            extract_mask_json_and_offsets_from(
+                &session,
+                &mask_json,
+                &offsets_json,
+                &blobs_bin,
+                mask_options,
+            )?;
+            written.push((ARTIFACT_MASK, mask_json));
+            written.push((ARTIFACT_OFFSETS, offsets_json));
+            written.push((ARTIFACT_BLOBS, blobs_bin));
+        }
+        if let Some(bundle) = args.bundle.as_ref() {
+            write_mask_bundle(&session, bundle, mask_options)?;
+        }
+        if let Some(dir) = workdir.as_deref() {
+            let written: Vec<(&str, &Path)> =
+                written.iter().map(|(k, p)| (*k, p.as_path())).collect();
+            record_artifacts(dir, Some(&input), &written)?;
         }
         return Ok(());
     }
 
     if args.verify_extract_merge_json {
-        let mask_defaults = default_outputs_for(&input);
-        let text_defaults = default_text_output_for(&input);
-        let structure_defaults = default_structure_output_for(&input);
-        extract_pure_text_json(&input, &text_defaults.text_json_path)?;
-        extract_structure_json(&input, &structure_defaults.structure_json_path)?;
-        extract_mask_json_and_offsets(
-            &input,
+        let mask_defaults = default_outputs_for(&artifact_base);
+        let text_defaults = default_text_output_for(&artifact_base);
+        let structure_defaults = default_structure_output_for(&artifact_base);
+        let session = DocumentSession::open_with(&input, &package, &prefix_mode)?;
+        report_parse_fixes(session.parse_fixes());
+        let pure = pure_text_from_session(&session)?;
+        write_pure_text_json(&pure, &text_defaults.text_json_path)?;
+        write_structure_json(&pure, &structure_defaults.structure_json_path)?;
+        extract_mask_json_and_offsets_from(
+            &session,
             &mask_defaults.mask_json_path,
             &mask_defaults.offsets_json_path,
             &mask_defaults.blobs_bin_path,
+            mask_options,
         )?;
         merge_mask_json_and_offsets(
             &mask_defaults.mask_json_path,
@@ -275,32 +901,61 @@ fn main() -> anyhow::Result<()> {
             &text_defaults.text_json_path,
             &output,
         )?;
-        verify_docx_roundtrip(&input, &output)?;
+        if let Some(dir) = workdir.as_deref() {
+            record_artifacts(
+                dir,
+                Some(&input),
+                &[
+                    (ARTIFACT_TEXT, &text_defaults.text_json_path),
+                    (ARTIFACT_STRUCTURE, &structure_defaults.structure_json_path),
+                    (ARTIFACT_MASK, &mask_defaults.mask_json_path),
+                    (ARTIFACT_OFFSETS, &mask_defaults.offsets_json_path),
+                    (ARTIFACT_BLOBS, &mask_defaults.blobs_bin_path),
+                ],
+            )?;
+        }
+        verify_docx_roundtrip_with(&input, &output, args.strict_roundtrip, &package)?;
         return Ok(());
     }
 
     if args.roundtrip_only {
-        let pkg = DocxPackage::read(&input)?;
-        let mut replacements: std::collections::HashMap<String, Vec<u8>> =
-            std::collections::HashMap::new();
-        for ent in pkg.xml_entries() {
-            if ent.data.is_empty() {
-                continue;
-            }
-            let part = parse_xml_part(&ent.name, &ent.data)
-                .with_context(|| format!("parse xml: {}", ent.name))?;
-            let bytes =
-                write_xml_part(&part).with_context(|| format!("serialize xml: {}", ent.name))?;
-            replacements.insert(ent.name.clone(), bytes);
+        let pkg = DocxPackage::read_streaming_with(&input, &package)?;
+        let rewritten: Vec<(String, Vec<u8>, Vec<ParseFix>)> = pkg
+            .xml_entries()
+            .into_par_iter()
+            .filter(|ent| !ent.data.is_empty())
+            .map(|ent| {
+                let (part, fixes) = parse_xml_part_with(&ent.name, &ent.data, package.lenient)
+                    .with_context(|| format!("parse xml: {}", ent.name))?;
+                let bytes = write_xml_part(&part)
+                    .with_context(|| format!("serialize xml: {}", ent.name))?;
+                Ok((ent.name.clone(), bytes, fixes))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        let mut replacements = std::collections::HashMap::new();
+        for (name, bytes, fixes) in rewritten {
+            report_parse_fixes(&fixes);
+            replacements.insert(name, bytes);
         }
         pkg.write_with_replacements(&output, &replacements)?;
         return Ok(());
     }
 
-    let cfg = PipelineConfig::from_paths_and_args(
+    if !(args.experiment.is_some() || args.emit_prompts_only) {
+        ctx.translation = Some(TranslationRun {
+            input: input.clone(),
+            output: output.clone(),
+            config: args.config.clone(),
+            profile: args.profile.clone(),
+            started: Instant::now(),
+            stats: None,
+        });
+    }
+    let mut cfg = PipelineConfig::from_paths_and_args(
         &input,
         &output,
         args.config,
+        args.profile.as_deref(),
         args.translate_backend,
         args.alt_translate_backend,
         args.rewrite_backend,
@@ -318,9 +973,423 @@ fn main() -> anyhow::Result<()> {
         args.ctx_controller,
         args.max_tus,
     )
-    .context("build config")?;
+    .kind(ErrorKind::Config, "build config")?;
+    if let Some(dir) = args.project {
+        cfg.project_dir = Some(dir);
+    }
+    cfg.package.password = package.password;
+    cfg.package.lenient = package.lenient;
+    cfg.placeholder_prefix = prefix_mode;
+    cfg.resume = args.resume;
+    cfg.interactive = args.interactive;
+    if let Some(seed) = args.seed.as_deref() {
+        let seed = parse_seed(seed).kind(ErrorKind::Usage, "bad arguments")?;
+        cfg.seed_random = seed.is_none();
+        cfg.seed = seed.unwrap_or_else(random_seed);
+    }
+    if args.deterministic {
+        if cfg.seed_random {
+            return Err(anyhow::anyhow!(
+                "--deterministic needs a fixed seed (not \"random\")"
+            ))
+            .kind(ErrorKind::Usage, "bad arguments");
+        }
+        cfg.deterministic = true;
+        cfg.overlap_controller = false;
+    }
+    if args.proofread {
+        if let (Some(src), Some(tgt)) = (cfg.source_lang.as_deref(), cfg.target_lang.as_deref()) {
+            if !src.eq_ignore_ascii_case(tgt) {
+                return Err(anyhow::anyhow!(
+                    "--proofread keeps the source language; got {src} -> {tgt}"
+                ))
+                .kind(ErrorKind::Usage, "bad arguments");
+            }
+        }
+        cfg.proofread = true;
+        cfg.prompts.set_proofread();
+    }
+    if args.pseudo {
+        cfg.offline = Some(OfflineMode::Pseudo {
+            expansion: args.pseudo_expansion.unwrap_or(DEFAULT_PSEUDO_EXPANSION),
+        });
+    }
+    if args.copy_source {
+        cfg.offline = Some(OfflineMode::CopySource);
+    }
+    cfg.fail_on_fallback = args.fail_on_fallback;
+    if let Some(pct) = args.qa_sample.as_deref() {
+        cfg.qa_sample = Some(parse_percent(pct).kind(ErrorKind::Usage, "bad arguments")?);
+    }
+    if args.range.is_some() || !args.scope.is_empty() || args.from_report.is_some() {
+        let range = match args.range.as_deref() {
+            Some(range) => {
+                Some(UnitFilter::parse_range(range).kind(ErrorKind::Usage, "bad arguments")?)
+            }
+            None => None,
+        };
+        let units = match args.from_report.as_deref() {
+            Some(report) => {
+                let ids =
+                    flagged_unit_ids(report, &args.only).kind(ErrorKind::Usage, "bad arguments")?;
+                if ids.is_empty() {
+                    return Err(anyhow::anyhow!(
+                        "no paragraph in {} has a flag matching --only {:?}; nothing to retranslate",
+                        report.display(),
+                        args.only
+                    ))
+                    .kind(ErrorKind::Usage, "bad arguments");
+                }
+                Some(ids)
+            }
+            None => None,
+        };
+        cfg.unit_filter = Some(UnitFilter {
+            range,
+            units,
+            scopes: args.scope.clone(),
+        });
+    }
+    if let Some(reviewed) = args.from_review.as_ref() {
+        if cfg.mode != PipelineMode::Full {
+            return Err(anyhow::anyhow!(
+                "--from-review needs pipeline.mode = \"full\" (comments are applied by the patch stage)"
+            ))
+            .kind(ErrorKind::Usage, "bad arguments");
+        }
+        cfg.review_docx = Some(reviewed.clone());
+    }
+    if args.no_trace {
+        cfg.trace_prompts = false;
+    }
+    if let Some(dir) = workdir {
+        cfg.trace_dir = dir.clone();
+        cfg.artifact_dir = Some(dir);
+    }
+
+    let redact = args.redact || cfg.trace_bundle_redact;
+    let bundle_on_failure = cfg.trace_bundle_on_failure;
+    ctx.trace_dir = Some(cfg.trace_dir.clone());
+
+    if let Some(report) = args.experiment.as_ref() {
+        let spec = ExperimentSpec {
+            backends: variant_pair(args.experiment_backends.as_deref(), "--experiment-backends")?
+                .map(|v| v.map(str::to_string)),
+            prompts: variant_pair(args.experiment_prompts.as_deref(), "--experiment-prompts")?
+                .map(|v| v.map(PathBuf::from)),
+            sample: args.experiment_sample,
+        };
+        let mut pipeline = TranslatorPipeline::new(cfg, progress);
+        return pipeline.run_experiment(&input, report, &spec);
+    }
+
+    if args.emit_prompts_only {
+        let mut pipeline = TranslatorPipeline::new(cfg, progress);
+        let input = if args.from_pdf {
+            pipeline.convert_pdf_input(&input)?
+        } else {
+            input
+        };
+        return pipeline.emit_prompts_only(&input, &output);
+    }
 
     let mut pipeline = TranslatorPipeline::new(cfg, progress);
-    pipeline.translate_docx(&input, &output)?;
+    let result = if args.from_pdf {
+        pipeline.translate_pdf(&input, &output)
+    } else {
+        pipeline.translate_docx(&input, &output)
+    };
+    if let Some(run) = ctx.translation.as_mut() {
+        run.stats = pipeline.run_stats().cloned();
+    }
+    let bundle = args.trace_bundle.clone().or_else(|| {
+        let stem = output
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("output");
+        (result.is_err() && bundle_on_failure)
+            .then(|| output.with_file_name(format!("{stem}.trace-bundle.zip")))
+    });
+    if let Some(zip_path) = bundle {
+        ctx.trace_bundle = Some(zip_path.clone());
+        if let Err(err) = pipeline.write_trace_bundle(&zip_path, redact, result.as_ref().err()) {
+            eprintln!("[warn] write trace bundle failed: {err:#}");
+        }
+    }
+    result
+}
+
+/// `A,B` for the two experiment variants; blank entries keep the default.
+fn variant_pair<'a>(value: Option<&'a str>, flag: &str) -> anyhow::Result<[Option<&'a str>; 2]> {
+    let Some(value) = value else {
+        return Ok([None, None]);
+    };
+    let parts: Vec<&str> = value.split(',').map(str::trim).collect();
+    if parts.len() != 2 {
+        return Err(anyhow::anyhow!(
+            "{flag} expects two comma-separated values (A,B)"
+        ))
+        .kind(ErrorKind::Usage, "bad arguments");
+    }
+    let pick = |s: &'a str| (!s.is_empty()).then_some(s);
+    Ok([pick(parts[0]), pick(parts[1])])
+}
+
+/// `--translate-text`: stdin -> stdout through the plain-text pipeline. Progress stays on
+/// stderr and no trace files are written, so the command is safe inside shell pipelines.
+fn translate_stdin_text(args: Args, progress: ConsoleProgress) -> anyhow::Result<()> {
+    let mut source = String::new();
+    std::io::stdin()
+        .read_to_string(&mut source)
+        .context("read stdin (expected UTF-8 text)")
+        .kind(ErrorKind::Io, "read stdin")?;
+
+    let cwd = std::env::current_dir().context("current dir")?;
+    let mut cfg = PipelineConfig::from_paths_and_args(
+        &cwd.join("stdin.txt"),
+        &cwd.join("stdout.txt"),
+        args.config,
+        args.profile.as_deref(),
+        args.translate_backend,
+        args.alt_translate_backend,
+        args.rewrite_backend,
+        args.polish_backend,
+        args.controller_backend,
+        args.translate_model,
+        args.alt_translate_model,
+        args.rewrite_model,
+        args.controller_model,
+        args.source_lang,
+        args.target_lang,
+        args.threads,
+        args.gpu_layers,
+        args.ctx_translate,
+        args.ctx_controller,
+        args.max_tus,
+    )
+    .kind(ErrorKind::Config, "build config")?;
+    if let Some(dir) = args.project {
+        cfg.project_dir = Some(dir);
+    }
+    cfg.trace_prompts = false;
+
+    let mut pipeline = TranslatorPipeline::new(cfg, progress);
+    let translated = pipeline.translate_text(&source)?;
+    let mut stdout = std::io::stdout().lock();
+    stdout
+        .write_all(translated.as_bytes())
+        .and_then(|_| stdout.flush())
+        .context("write stdout")
+        .kind(ErrorKind::Io, "write stdout")?;
+    Ok(())
+}
+
+/// True when the process owns its console window alone, i.e. it was started from Explorer
+/// (double-click / drag-and-drop) and the window would vanish as soon as we exit.
+#[cfg(windows)]
+fn launched_without_terminal() -> bool {
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetConsoleProcessList(list: *mut u32, count: u32) -> u32;
+    }
+    let mut pids = [0u32; 4];
+    // SAFETY: the buffer is valid for `pids.len()` entries.
+    let n = unsafe { GetConsoleProcessList(pids.as_mut_ptr(), pids.len() as u32) };
+    n == 1
+}
+
+#[cfg(not(windows))]
+fn launched_without_terminal() -> bool {
+    false
+}
+
+/// Environment variable with the password of encrypted inputs (when no flag gives one).
+const PASSWORD_ENV: &str = "MUGGLE_TRANSLATOR_PASSWORD";
+
+/// `--password`, else `--password-prompt`, else `MUGGLE_TRANSLATOR_PASSWORD`.
+fn docx_password(password: Option<String>, prompt: bool) -> anyhow::Result<Option<String>> {
+    if password.is_some() {
+        return Ok(password);
+    }
+    if prompt {
+        return read_password(&tr("main.password_prompt")).map(Some);
+    }
+    Ok(std::env::var(PASSWORD_ENV).ok().filter(|p| !p.is_empty()))
+}
+
+/// One line from the terminal with echo switched off.
+fn read_password(prompt: &str) -> anyhow::Result<String> {
+    eprint!("{prompt}");
+    let _ = std::io::stderr().flush();
+    set_terminal_echo(false);
+    let mut line = String::new();
+    let read = std::io::stdin().read_line(&mut line);
+    set_terminal_echo(true);
+    eprintln!();
+    read.context("read password")
+        .kind(ErrorKind::Usage, "--password-prompt")?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+#[cfg(windows)]
+fn set_terminal_echo(on: bool) {
+    use std::ffi::c_void;
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetStdHandle(id: u32) -> *mut c_void;
+        fn GetConsoleMode(handle: *mut c_void, mode: *mut u32) -> i32;
+        fn SetConsoleMode(handle: *mut c_void, mode: u32) -> i32;
+    }
+    const STD_INPUT_HANDLE: u32 = -10i32 as u32;
+    const ENABLE_ECHO_INPUT: u32 = 0x0004;
+    // SAFETY: plain console API calls on the process's own stdin handle.
+    unsafe {
+        let handle = GetStdHandle(STD_INPUT_HANDLE);
+        let mut mode = 0u32;
+        if GetConsoleMode(handle, &mut mode) == 0 {
+            return;
+        }
+        let mode = if on {
+            mode | ENABLE_ECHO_INPUT
+        } else {
+            mode & !ENABLE_ECHO_INPUT
+        };
+        SetConsoleMode(handle, mode);
+    }
+}
+
+#[cfg(not(windows))]
+fn set_terminal_echo(on: bool) {
+    let _ = std::process::Command::new("stty")
+        .arg(if on { "echo" } else { "-echo" })
+        .stdin(std::process::Stdio::inherit())
+        .status();
+}
+
+/// `--output-dir`: relative outputs (and the default `<stem>_翻译.docx`) are placed inside DIR.
+fn resolve_output_dir(output_dir: Option<&Path>, output: PathBuf) -> anyhow::Result<PathBuf> {
+    let Some(dir) = output_dir else {
+        return Ok(output);
+    };
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("create output dir: {}", dir.display()))?;
+    if output.is_absolute() {
+        return Ok(output);
+    }
+    let name = output.file_name().map(PathBuf::from).unwrap_or(output);
+    Ok(dir.join(name))
+}
+
+/// Refuse to clobber an existing output (possibly a hand-edited deliverable) unless `--force`.
+fn ensure_output_writable(output: &Path, force: bool) -> anyhow::Result<()> {
+    if output.exists() && !force {
+        return Err(anyhow::anyhow!(tr_args(
+            "main.output_exists",
+            &[("path", &output.display())]
+        )))
+        .kind(ErrorKind::OutputExists, "refusing to overwrite output");
+    }
+    Ok(())
+}
+
+/// Mask, offsets and text JSON of a merge: the `--merge-*` paths, the missing ones from the
+/// `--workdir` manifest (text: the final translation, else the autosave, else the extraction).
+fn merge_inputs(
+    args: &Args,
+    workdir: Option<&Path>,
+) -> anyhow::Result<(PathBuf, PathBuf, PathBuf)> {
+    let complete = args.merge_mask_json.is_some()
+        && args.merge_offsets_json.is_some()
+        && args.merge_text_json.is_some();
+    let manifest = match workdir {
+        Some(dir) if !complete => {
+            Some(WorkdirManifest::load(dir).kind(ErrorKind::Usage, "load workdir manifest")?)
+        }
+        _ => None,
+    };
+    let pick = |given: &Option<PathBuf>, kinds: &[&str]| {
+        given.clone().or_else(|| {
+            let dir = workdir?;
+            manifest.as_ref()?.first_artifact(dir, kinds)
+        })
+    };
+    match (
+        pick(&args.merge_mask_json, &[ARTIFACT_MASK]),
+        pick(&args.merge_offsets_json, &[ARTIFACT_OFFSETS]),
+        pick(
+            &args.merge_text_json,
+            &[ARTIFACT_FINAL_TEXT, ARTIFACT_AUTOSAVE_TEXT, ARTIFACT_TEXT],
+        ),
+    ) {
+        (Some(mask), Some(offsets), Some(text)) => Ok((mask, offsets, text)),
+        _ => Err(anyhow::anyhow!(
+            "merge mode requires: --merge-mask-json, --merge-offsets-json, --merge-text-json (or a --workdir whose manifest lists them), and -o/--output"
+        ))
+        .kind(ErrorKind::Usage, "bad arguments"),
+    }
+}
+
+/// `--dump-xml`: the indented, indexed dump of one part of `input`.
+fn dump_xml(input: &Path, part_name: &str, package: &PackageOptions) -> anyhow::Result<String> {
+    let pkg =
+        DocxPackage::read_streaming_with(input, package).kind(ErrorKind::InputDocx, "read docx")?;
+    let Some(ent) = pkg
+        .entries
+        .iter()
+        .find(|e| e.name == part_name && !e.is_dir)
+    else {
+        let xml_parts: Vec<&str> = pkg
+            .entries
+            .iter()
+            .filter(|e| e.name.to_lowercase().ends_with(".xml"))
+            .map(|e| e.name.as_str())
+            .collect();
+        return Err(anyhow::anyhow!(
+            "no part {part_name:?} in {}; xml parts: {}",
+            input.display(),
+            xml_parts.join(", ")
+        ))
+        .kind(ErrorKind::Usage, "bad arguments");
+    };
+    let (part, fixes) = parse_xml_part_with(&ent.name, &ent.data, package.lenient)
+        .with_context(|| format!("parse xml: {}", ent.name))
+        .kind(ErrorKind::InputDocx, "parse xml")?;
+    report_parse_fixes(&fixes);
+    Ok(dump_xml_part(&part))
+}
+
+/// `--lenient-parse`: say which repairs were applied, one line each.
+fn report_parse_fixes(fixes: &[ParseFix]) {
+    for fix in fixes {
+        eprintln!("{fix}");
+    }
+}
+
+/// `--merge-lenient` and the configured `[[links.rewrite]]` rules for the standalone merges.
+fn merge_options(args: &Args) -> anyhow::Result<MergeOptions> {
+    Ok(MergeOptions {
+        lenient: args.merge_lenient,
+        link_rewrites: configured_link_rewrites(
+            args.input.as_deref(),
+            args.config.as_deref(),
+            args.profile.as_deref(),
+        )
+        .kind(ErrorKind::Config, "load config")?,
+    })
+}
+
+/// `--merge-lenient`: write the substitution report and say where it is.
+fn report_merge_substitutions(output: &Path, subs: &[SlotSubstitution]) -> anyhow::Result<()> {
+    if subs.is_empty() {
+        return Ok(());
+    }
+    let path = write_merge_report(output, subs).kind(ErrorKind::Merge, "merge")?;
+    eprintln!(
+        "{}",
+        tr_args(
+            "main.merge_substituted",
+            &[("count", &subs.len()), ("path", &path.display())]
+        )
+    );
     Ok(())
 }
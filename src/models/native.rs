@@ -11,9 +11,37 @@ use llama_cpp_2::model::params::LlamaModelParams;
 use llama_cpp_2::model::{AddBos, LlamaChatMessage, LlamaChatTemplate, LlamaModel, Special};
 use llama_cpp_2::sampling::LlamaSampler;
 use llama_cpp_2::DecodeError;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::cancel::CancellationToken;
 
 const JSON_GBNF: &str = include_str!("json.gbnf");
 
+static REASONING_BLOCK_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)<(?:think|thinking|reasoning)>.*?</(?:think|thinking|reasoning)>")
+        .expect("reasoning block regex")
+});
+static REASONING_OPEN_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)<(?:think|thinking|reasoning)>").expect("reasoning open regex"));
+static REASONING_CLOSE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)</(?:think|thinking|reasoning)>").expect("reasoning close regex")
+});
+
+/// Answer part of a thinking model's output: closed reasoning blocks are removed, an unclosed
+/// one (generation stopped mid-thought) is cut off, and text before a stray closing tag (the
+/// chat template opened the block in the prompt) is dropped.
+pub fn strip_reasoning(text: &str) -> String {
+    let mut out = REASONING_BLOCK_RE.replace_all(text, "").into_owned();
+    if let Some(m) = REASONING_CLOSE_RE.find_iter(&out).last() {
+        out = out[m.end()..].to_string();
+    }
+    if let Some(m) = REASONING_OPEN_RE.find(&out) {
+        out.truncate(m.start());
+    }
+    out.trim().to_string()
+}
+
 #[derive(Clone, Debug)]
 pub struct NativeModelConfig {
     pub name: String,
@@ -25,7 +53,32 @@ pub struct NativeModelConfig {
     pub batch_size: Option<u32>,
     pub ubatch_size: Option<u32>,
     pub offload_kqv: Option<bool>,
+    /// `None` keeps llama.cpp's defaults (mmap on, mlock off).
+    pub use_mmap: Option<bool>,
+    pub use_mlock: Option<bool>,
     pub seed: u32,
+    /// Greedy decoding for every call, whatever temperature the caller asks for.
+    pub greedy: bool,
+    /// Remove reasoning blocks (`<think>...</think>`) from every output.
+    pub strip_reasoning: bool,
+    /// Extra tokens per call for the reasoning block (also lifts the JSON grammar, which would
+    /// not let the model think first).
+    pub thinking_budget: u32,
+    /// Send prompts raw, without the chat template.
+    pub completion: Option<CompletionFormat>,
+    /// Checked before every call and generated token.
+    pub cancel: CancellationToken,
+}
+
+/// Prompt handling of a completion (non-chat) backend.
+#[derive(Clone, Debug, Default)]
+pub struct CompletionFormat {
+    /// Prepend BOS; `None` decides from the prompt (`decide_add_bos`).
+    pub add_bos: Option<bool>,
+    /// Append the EOS token after the prompt.
+    pub add_eos: bool,
+    /// Strings that end generation; the output is cut before them.
+    pub stop: Vec<String>,
 }
 
 pub struct NativeChatModel {
@@ -39,6 +92,11 @@ pub struct NativeChatModel {
     ctx: Option<LlamaContext<'static>>,
     template: LlamaChatTemplate,
     seed: u32,
+    greedy: bool,
+    strip_reasoning: bool,
+    thinking_budget: u32,
+    completion: Option<CompletionFormat>,
+    cancel: CancellationToken,
 }
 
 impl NativeChatModel {
@@ -58,6 +116,12 @@ impl NativeChatModel {
         } else if cfg.gpu_layers >= 0 {
             model_params = model_params.with_n_gpu_layers(cfg.gpu_layers as u32);
         }
+        if let Some(mmap) = cfg.use_mmap {
+            model_params = model_params.with_use_mmap(mmap);
+        }
+        if let Some(mlock) = cfg.use_mlock {
+            model_params = model_params.with_use_mlock(mlock);
+        }
 
         let model = Box::new(
             LlamaModel::load_from_file(backend, &cfg.model_path, &model_params)
@@ -83,8 +147,7 @@ impl NativeChatModel {
             ctx_size = 256;
         }
 
-        let mut ctx_params =
-            LlamaContextParams::default().with_n_ctx(NonZeroU32::new(ctx_size));
+        let mut ctx_params = LlamaContextParams::default().with_n_ctx(NonZeroU32::new(ctx_size));
         let n_batch: u32 = cfg.batch_size.unwrap_or(512).clamp(8, 65536);
         let mut n_ubatch: u32 = cfg.ubatch_size.unwrap_or(n_batch).clamp(1, 65536);
         if n_ubatch > n_batch {
@@ -125,8 +188,9 @@ impl NativeChatModel {
         } else {
             match model_ref.chat_template(None) {
                 Ok(t) => t,
-                Err(_) => LlamaChatTemplate::new("chatml")
-                    .context("build fallback chat template")?,
+                Err(_) => {
+                    LlamaChatTemplate::new("chatml").context("build fallback chat template")?
+                }
             }
         };
 
@@ -141,6 +205,11 @@ impl NativeChatModel {
             ctx: Some(ctx),
             template,
             seed: cfg.seed,
+            greedy: cfg.greedy,
+            strip_reasoning: cfg.strip_reasoning,
+            thinking_budget: cfg.thinking_budget,
+            completion: cfg.completion,
+            cancel: cfg.cancel,
         })
     }
 
@@ -156,6 +225,22 @@ impl NativeChatModel {
         repeat_penalty: Option<f32>,
         json_mode: bool,
     ) -> anyhow::Result<String> {
+        if self.completion.is_some() {
+            // No chat template: a system prompt simply precedes the user prompt.
+            let prompt = match system_prompt.map(str::trim).filter(|s| !s.is_empty()) {
+                Some(system) => format!("{system}\n\n{user_prompt}"),
+                None => user_prompt.to_string(),
+            };
+            return self.generate_from_prompt(
+                &prompt,
+                max_tokens,
+                temperature,
+                top_p,
+                top_k,
+                repeat_penalty,
+                json_mode,
+            );
+        }
         let mut chat: Vec<LlamaChatMessage> = Vec::new();
         if let Some(s) = system_prompt {
             if !s.trim().is_empty() {
@@ -196,13 +281,21 @@ impl NativeChatModel {
         repeat_penalty: Option<f32>,
         json_mode: bool,
     ) -> anyhow::Result<String> {
+        self.cancel.check()?;
         self.ctx_mut().clear_kv_cache();
 
-        let add_bos = decide_add_bos(prompt);
-        let prompt_tokens = self
+        let add_bos = match self.completion.as_ref().and_then(|c| c.add_bos) {
+            Some(true) => AddBos::Always,
+            Some(false) => AddBos::Never,
+            None => decide_add_bos(prompt),
+        };
+        let mut prompt_tokens = self
             .model_ref()
             .str_to_token(prompt, add_bos)
             .context("tokenize prompt")?;
+        if self.completion.as_ref().is_some_and(|c| c.add_eos) {
+            prompt_tokens.push(self.model_ref().token_eos());
+        }
         if prompt_tokens.is_empty() {
             return Err(anyhow!("empty prompt tokens"));
         }
@@ -216,7 +309,7 @@ impl NativeChatModel {
             ));
         }
 
-        let mut max_tokens = max_tokens as usize;
+        let mut max_tokens = max_tokens.saturating_add(self.thinking_budget) as usize;
         let available = n_ctx.saturating_sub(prompt_tokens.len() + 1);
         if available == 0 {
             return Err(anyhow!(
@@ -251,7 +344,9 @@ impl NativeChatModel {
             chunk_start = chunk_end;
         }
 
+        let temperature = if self.greedy { 0.0 } else { temperature };
         let mut samplers: Vec<LlamaSampler> = Vec::new();
+        let json_mode = json_mode && self.thinking_budget == 0;
         let mut use_json_grammar = json_mode;
         if json_mode {
             match LlamaSampler::grammar(self.model_ref(), JSON_GBNF, "root") {
@@ -288,7 +383,13 @@ impl NativeChatModel {
 
         let mut batch = LlamaBatch::new(512, 1);
         let mut n_cur: i32 = prompt_tokens.len() as i32;
+        let stops: Vec<String> = self
+            .completion
+            .as_ref()
+            .map(|c| c.stop.clone())
+            .unwrap_or_default();
         for _ in 0..max_tokens {
+            self.cancel.check()?;
             let token = sampler.sample(self.ctx_ref(), -1);
 
             if self.model_ref().is_eog_token(token) {
@@ -302,6 +403,10 @@ impl NativeChatModel {
             let mut piece = String::with_capacity(32);
             let _ = decoder.decode_to_string(&bytes, &mut piece, false);
             out.push_str(&piece);
+            if let Some(cut) = stop_position(&out, &stops) {
+                out.truncate(cut);
+                break;
+            }
 
             batch.clear();
             batch
@@ -316,6 +421,9 @@ impl NativeChatModel {
         let _ = decoder.decode_to_string(&[], &mut tail, true);
         out.push_str(&tail);
 
+        if self.strip_reasoning {
+            return Ok(strip_reasoning(&out));
+        }
         Ok(out.trim().to_string())
     }
 
@@ -357,6 +465,14 @@ impl Drop for NativeChatModel {
     }
 }
 
+/// Byte offset of the earliest stop string in `out`.
+fn stop_position(out: &str, stops: &[String]) -> Option<usize> {
+    stops
+        .iter()
+        .filter_map(|stop| out.find(stop.as_str()))
+        .min()
+}
+
 fn decide_add_bos(prompt: &str) -> AddBos {
     let p = prompt.trim_start();
     // Heuristic: if the template already starts with a BOS-like special token, don't add another.
@@ -0,0 +1,192 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::Context;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::sentinels::ANY_MT_TOKEN_RE;
+
+use super::PipelineConfig;
+
+/// JSON keys whose string values are identifiers (not document text) and survive redaction.
+const REDACT_KEEP_KEYS: [&str; 22] = [
+    "placeholder_prefix",
+    "part_name",
+    "attr_name",
+    "anchor",
+    "scope_key",
+    "p_style",
+    "para_style",
+    "container",
+    "schema",
+    "source_lang",
+    "target_lang",
+    "model_a",
+    "model_b",
+    "agent_model",
+    "tu_kind",
+    "kind",
+    "name",
+    "blobs_file",
+    "tool",
+    "tool_version",
+    "source_sha256",
+    "created",
+];
+
+/// Pack the trace dir + resolved config + validation report into one zip for bug reports.
+///
+/// - `trace/*`: trace files, mask/offsets/text JSON (DOCX copies and mask blobs are skipped)
+/// - `config/`: the config file as found on disk + the resolved `PipelineConfig`
+/// - `validation_report.txt`: summary of `*validate_fail*` traces
+/// - `error.txt`: the pipeline error, when the run failed
+///
+/// With `redact`, letters in document-derived text are replaced by `x` (MT sentinels, digits and
+/// JSON keys/identifiers are kept), so structure problems stay debuggable without the content.
+/// Offsets JSON is redacted like any other JSON (slot source text included); mask JSON is left
+/// out, since the package XML it embeds cannot be redacted without losing its structure.
+pub fn write_trace_bundle(
+    cfg: &PipelineConfig,
+    out_zip: &Path,
+    redact: bool,
+    error: Option<&anyhow::Error>,
+) -> anyhow::Result<usize> {
+    if let Some(parent) = out_zip.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("create bundle dir: {}", parent.display()))?;
+        }
+    }
+    let f = fs::File::create(out_zip)
+        .with_context(|| format!("create trace bundle: {}", out_zip.display()))?;
+    let mut zout = ZipWriter::new(f);
+    let opts = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut count = 0usize;
+
+    let mut add = |zout: &mut ZipWriter<fs::File>, name: &str, data: &[u8]| -> anyhow::Result<()> {
+        zout.start_file(name, opts)
+            .with_context(|| format!("zip start_file: {name}"))?;
+        zout.write_all(data)
+            .with_context(|| format!("zip write: {name}"))?;
+        count += 1;
+        Ok(())
+    };
+
+    let mut trace_files: Vec<_> = fs::read_dir(&cfg.trace_dir)
+        .map(|rd| rd.flatten().map(|e| e.path()).collect())
+        .unwrap_or_default();
+    trace_files.sort();
+
+    let mut validation_report = String::new();
+    for path in &trace_files {
+        if !path.is_file() {
+            continue;
+        }
+        let Some(name) = path.file_name().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        let lower = name.to_ascii_lowercase();
+        if lower.ends_with(".docx") || lower.ends_with(".bin") || lower.ends_with(".zip") {
+            continue;
+        }
+        if redact && lower.ends_with(".mask.json") {
+            continue;
+        }
+        let data =
+            fs::read(path).with_context(|| format!("read trace file: {}", path.display()))?;
+        let data = if redact {
+            redact_bytes(&lower, &data)
+        } else {
+            data
+        };
+        if lower.contains("validate_fail") {
+            let text = String::from_utf8_lossy(&data);
+            let first = text.lines().next().unwrap_or("").trim();
+            validation_report.push_str(&format!("{name}: {first}\n"));
+        }
+        add(&mut zout, &format!("trace/{name}"), &data)?;
+    }
+
+    if cfg.config_path.exists() {
+        let data = fs::read(&cfg.config_path)
+            .with_context(|| format!("read config: {}", cfg.config_path.display()))?;
+        add(&mut zout, "config/muggle-translator.toml", &data)?;
+    }
+    add(
+        &mut zout,
+        "config/resolved.txt",
+        format!("{cfg:#?}\n").as_bytes(),
+    )?;
+
+    if validation_report.is_empty() {
+        validation_report.push_str("no validation failures recorded\n");
+    }
+    add(
+        &mut zout,
+        "validation_report.txt",
+        validation_report.as_bytes(),
+    )?;
+
+    if let Some(err) = error {
+        let msg = format!("{err:#}\n");
+        let msg = if redact { redact_text(&msg) } else { msg };
+        add(&mut zout, "error.txt", msg.as_bytes())?;
+    }
+
+    zout.finish().context("finish trace bundle")?;
+    Ok(count)
+}
+
+fn redact_bytes(lower_name: &str, data: &[u8]) -> Vec<u8> {
+    let text = String::from_utf8_lossy(data);
+    if lower_name.ends_with(".json") {
+        let body = text.trim_start_matches('\u{FEFF}');
+        if let Ok(mut v) = serde_json::from_str::<serde_json::Value>(body) {
+            redact_json(&mut v);
+            if let Ok(out) = serde_json::to_vec_pretty(&v) {
+                return out;
+            }
+        }
+    }
+    redact_text(&text).into_bytes()
+}
+
+fn redact_json(v: &mut serde_json::Value) {
+    match v {
+        serde_json::Value::String(s) => *s = redact_text(s),
+        serde_json::Value::Array(items) => items.iter_mut().for_each(redact_json),
+        serde_json::Value::Object(map) => {
+            for (k, item) in map.iter_mut() {
+                if item.is_string() && REDACT_KEEP_KEYS.contains(&k.as_str()) {
+                    continue;
+                }
+                redact_json(item);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn redact_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut cursor = 0usize;
+    for m in ANY_MT_TOKEN_RE.find_iter(text) {
+        push_redacted(&mut out, &text[cursor..m.start()]);
+        out.push_str(m.as_str());
+        cursor = m.end();
+    }
+    push_redacted(&mut out, &text[cursor..]);
+    out
+}
+
+fn push_redacted(out: &mut String, s: &str) {
+    for ch in s.chars() {
+        if ch.is_alphabetic() {
+            out.push('x');
+        } else {
+            out.push(ch);
+        }
+    }
+}
@@ -0,0 +1,295 @@
+use std::collections::HashMap;
+
+use crate::docx::pure_text::{ParaContainer, PureParagraph, PureTextJson};
+use crate::docx::structure::heading_level;
+use crate::textutil::strip_sentinels;
+
+use super::config::{CaseStyle, CasingRules};
+
+/// Lower-case inside English title case (articles, short conjunctions and prepositions).
+const TITLE_SMALL_WORDS: &[&str] = &[
+    "a", "an", "and", "as", "at", "but", "by", "for", "from", "if", "in", "into", "nor", "of",
+    "off", "on", "onto", "or", "over", "per", "so", "than", "the", "to", "up", "upon", "via", "vs",
+    "with", "yet",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CaseOp {
+    Keep,
+    Upper,
+    Lower,
+}
+
+/// Apply `rules` to the final slot texts of `output`, one paragraph at a time (`units`: the
+/// paragraph id and its slot ids in reading order). The paragraph kind comes from the source
+/// paragraph: headings as in the structure extractor, table cells, or other body text; headers
+/// and footers are never changed. Returns the number of paragraphs changed.
+pub fn apply_casing(
+    rules: &CasingRules,
+    target_lang: &str,
+    source: &PureTextJson,
+    units: &[(usize, &[usize])],
+    output: &mut PureTextJson,
+) -> usize {
+    let paras: HashMap<usize, &PureParagraph> =
+        source.paragraphs.iter().map(|p| (p.para_id, p)).collect();
+    let english = target_lang.trim().to_ascii_lowercase().starts_with("en");
+    let mut changed = 0usize;
+    for &(para_id, slot_ids) in units {
+        let Some(p) = paras.get(&para_id) else {
+            continue;
+        };
+        let style = match p.container {
+            ParaContainer::Header | ParaContainer::Footer => continue,
+            ParaContainer::TableCell => rules.table_cells,
+            ParaContainer::DocumentBody if heading_level(p).is_some() => rules.headings,
+            ParaContainer::DocumentBody => rules.body,
+        };
+        let style = match style {
+            CaseStyle::Title if !english => CaseStyle::Keep,
+            style => style,
+        };
+        let all_caps = rules.preserve_all_caps && is_all_caps(&p.text);
+        if style == CaseStyle::Keep && !all_caps {
+            continue;
+        }
+        let rewritten = rewrite_paragraph(output, slot_ids, |chars| {
+            let ops = if all_caps {
+                upper_ops(chars)
+            } else {
+                case_ops(chars, style)
+            };
+            ops.iter()
+                .zip(chars)
+                .map(|(op, c)| match op {
+                    CaseOp::Keep => None,
+                    CaseOp::Upper => Some(c.to_uppercase().collect()),
+                    CaseOp::Lower => Some(c.to_lowercase().collect()),
+                })
+                .collect()
+        });
+        if !rewritten {
+            continue;
+        }
+        changed += 1;
+    }
+    changed
+}
+
+/// Rewrite the text of one paragraph spread over `slot_ids`: `rewrite` sees the paragraph's
+/// chars and returns a replacement for each (`None` keeps it), so changes that depend on the
+/// whole paragraph still land in the slot holding the char. Returns whether anything changed.
+pub(super) fn rewrite_paragraph(
+    output: &mut PureTextJson,
+    slot_ids: &[usize],
+    rewrite: impl FnOnce(&[char]) -> Vec<Option<String>>,
+) -> bool {
+    let slots: Vec<usize> = slot_ids
+        .iter()
+        .filter(|&&id| id != 0 && id <= output.slot_texts.len())
+        .map(|&id| id - 1)
+        .collect();
+    let chars: Vec<char> = slots
+        .iter()
+        .flat_map(|&i| output.slot_texts[i].chars())
+        .collect();
+    let replacements = rewrite(&chars);
+    let unchanged = replacements
+        .iter()
+        .zip(&chars)
+        .all(|(r, &c)| r.as_ref().is_none_or(|r| r.chars().eq([c])));
+    if unchanged {
+        return false;
+    }
+    let mut pos = 0usize;
+    for &i in &slots {
+        let n = output.slot_texts[i].chars().count();
+        let mut out = String::with_capacity(output.slot_texts[i].len());
+        for (&c, r) in chars[pos..pos + n].iter().zip(&replacements[pos..pos + n]) {
+            match r {
+                Some(r) => out.push_str(r),
+                None => out.push(c),
+            }
+        }
+        output.slot_texts[i] = out;
+        pos += n;
+    }
+    true
+}
+
+/// Source paragraphs written in capitals (at least two letters, none lower-case).
+fn is_all_caps(text: &str) -> bool {
+    let plain = strip_sentinels(text);
+    let cased: Vec<char> = plain
+        .chars()
+        .filter(|c| c.is_uppercase() || c.is_lowercase())
+        .collect();
+    cased.len() >= 2 && cased.iter().all(|c| c.is_uppercase())
+}
+
+#[derive(Debug)]
+struct Word {
+    start: usize,
+    end: usize,
+    kind: WordKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum WordKind {
+    Lower,
+    /// First letter upper-case, the rest lower-case.
+    Capitalized,
+    /// Acronyms, mixed case, digits: never changed.
+    Other,
+}
+
+/// Letter/digit runs (inner apostrophes included), without URL and e-mail tokens.
+fn words(chars: &[char]) -> Vec<Word> {
+    let protected = protected_mask(chars);
+    let is_word = |i: usize| {
+        let c = chars[i];
+        c.is_alphanumeric()
+            || (matches!(c, '\'' | '\u{2019}')
+                && i > 0
+                && chars[i - 1].is_alphanumeric()
+                && chars.get(i + 1).is_some_and(|n| n.is_alphanumeric()))
+    };
+    let mut out = Vec::new();
+    let mut i = 0usize;
+    while i < chars.len() {
+        if !is_word(i) {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < chars.len() && is_word(i) {
+            i += 1;
+        }
+        if protected[start] {
+            continue;
+        }
+        let word = &chars[start..i];
+        let letters: Vec<char> = word.iter().copied().filter(|c| c.is_alphabetic()).collect();
+        let kind = if word.iter().any(|c| c.is_numeric()) || letters.is_empty() {
+            WordKind::Other
+        } else if letters.iter().all(|c| !c.is_uppercase()) {
+            WordKind::Lower
+        } else if word[0].is_uppercase() && letters[1..].iter().all(|c| !c.is_uppercase()) {
+            WordKind::Capitalized
+        } else {
+            WordKind::Other
+        };
+        out.push(Word {
+            start,
+            end: i,
+            kind,
+        });
+    }
+    out
+}
+
+/// Chars inside whitespace-separated tokens that look like URLs or e-mail addresses.
+fn protected_mask(chars: &[char]) -> Vec<bool> {
+    let mut mask = vec![false; chars.len()];
+    let mut i = 0usize;
+    while i < chars.len() {
+        if chars[i].is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < chars.len() && !chars[i].is_whitespace() {
+            i += 1;
+        }
+        let token: String = chars[start..i].iter().collect::<String>().to_lowercase();
+        if token.contains("://") || token.contains('@') || token.starts_with("www.") {
+            mask[start..i].iter_mut().for_each(|m| *m = true);
+        }
+    }
+    mask
+}
+
+/// Last non-space char before `at`.
+fn previous_mark(chars: &[char], at: usize) -> Option<char> {
+    chars[..at]
+        .iter()
+        .rev()
+        .copied()
+        .find(|c| !c.is_whitespace())
+}
+
+fn upper_ops(chars: &[char]) -> Vec<CaseOp> {
+    let protected = protected_mask(chars);
+    chars
+        .iter()
+        .zip(protected)
+        .map(|(c, p)| {
+            if !p && c.is_lowercase() {
+                CaseOp::Upper
+            } else {
+                CaseOp::Keep
+            }
+        })
+        .collect()
+}
+
+fn case_ops(chars: &[char], style: CaseStyle) -> Vec<CaseOp> {
+    let mut ops = vec![CaseOp::Keep; chars.len()];
+    let words = words(chars);
+    let Some(first) = words.first() else {
+        return ops;
+    };
+    match style {
+        CaseStyle::Keep => {}
+        CaseStyle::Capitalize => {
+            if first.kind == WordKind::Lower {
+                ops[first.start] = CaseOp::Upper;
+            }
+        }
+        CaseStyle::Sentence => {
+            if first.kind == WordKind::Lower {
+                ops[first.start] = CaseOp::Upper;
+            }
+            // Only text the model wrote In Title Case: every longer word capitalized.
+            let long: Vec<&Word> = words[1..]
+                .iter()
+                .filter(|w| w.kind != WordKind::Other && w.end - w.start >= 4)
+                .collect();
+            let title_cased =
+                long.len() >= 2 && long.iter().all(|w| w.kind == WordKind::Capitalized);
+            if title_cased {
+                for w in &words[1..] {
+                    let after_stop =
+                        matches!(previous_mark(chars, w.start), Some('.' | '!' | '?' | ':'));
+                    let pronoun_i = w.end - w.start == 1 && chars[w.start] == 'I';
+                    if w.kind == WordKind::Capitalized && !after_stop && !pronoun_i {
+                        ops[w.start] = CaseOp::Lower;
+                    }
+                }
+            }
+        }
+        CaseStyle::Title => {
+            let last = words.len() - 1;
+            for (n, w) in words.iter().enumerate() {
+                if w.kind == WordKind::Other {
+                    continue;
+                }
+                let text: String = chars[w.start..w.end]
+                    .iter()
+                    .collect::<String>()
+                    .to_lowercase();
+                let after_colon = matches!(previous_mark(chars, w.start), Some(':' | '\u{2014}'));
+                let small = TITLE_SMALL_WORDS.contains(&text.as_str())
+                    && n != 0
+                    && n != last
+                    && !after_colon;
+                ops[w.start] = match (small, w.kind) {
+                    (true, WordKind::Capitalized) => CaseOp::Lower,
+                    (false, WordKind::Lower) => CaseOp::Upper,
+                    _ => CaseOp::Keep,
+                };
+            }
+        }
+    }
+    ops
+}
@@ -1,11 +1,22 @@
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 
-use anyhow::Context;
+use anyhow::{anyhow, Context};
 
+use crate::cancel::CancellationToken;
 use crate::config::{
-    find_default_config, load_config, resolve_backend, AppConfig, ResolvedBackend,
+    find_default_config, load_config, resolve_backend, AppConfig, BackendChain, HookTargets,
+    ResolvedBackend, SeedSetting, StageSetting,
 };
+use crate::docx::decompose::PlaceholderPrefix;
+use crate::docx::highlight::{DEFAULT_HIGHLIGHT, HIGHLIGHT_COLORS};
+use crate::docx::links::LinkRewrite;
+use crate::docx::package::{PackageLimits, PackageOptions};
+use crate::freezer::AcronymRules;
+use crate::i18n::tr_args;
+use crate::pipeline::hooks::Hooks;
 use crate::pipeline::prompts::{default_prompt_files, PromptCatalog, DEFAULT_PROMPTS_DIR};
+use crate::pipeline::trace::{TraceLimits, TRACE_STAGES};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum PipelineMode {
@@ -22,17 +33,377 @@ impl PipelineMode {
     }
 }
 
+/// DOCX runs that need no model (`TranslatorPipeline::translate_docx` then skips the mode).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OfflineMode {
+    /// `--pseudo`: accented text, each paragraph in brackets and `expansion` percent longer.
+    Pseudo { expansion: u32 },
+    /// `--copy-source`: the source text merged back unchanged; a filtered, run-merged DOCX.
+    CopySource,
+}
+
+/// `--pseudo-expansion` default, percent.
+pub const DEFAULT_PSEUDO_EXPANSION: u32 = 30;
+
+/// Full mode: how paragraph translations are projected onto their text slots
+/// (`pipeline.slot_projection`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SlotProjection {
+    /// A `<<MT_SLOT:n>>` marker per text node, placed by the model.
+    Markers,
+    /// Slots grouped by run style; ranges in other styles than the paragraph's dominant one are
+    /// marked with `<<MT_EM:nn>>` pairs and the text is split across each group's slots.
+    Spans,
+    /// The whole translation goes to the paragraph's dominant run; the other runs are dropped.
+    Paragraph,
+}
+
+impl SlotProjection {
+    pub fn parse(s: Option<&str>) -> anyhow::Result<Self> {
+        match s.unwrap_or("markers").trim().to_ascii_lowercase().as_str() {
+            "markers" => Ok(Self::Markers),
+            "spans" => Ok(Self::Spans),
+            "paragraph" => Ok(Self::Paragraph),
+            other => Err(anyhow!(
+                "pipeline.slot_projection: unknown value {other:?} (expected \"markers\", \"spans\" or \"paragraph\")"
+            )),
+        }
+    }
+}
+
+/// Units with nothing but numbers, dates, currency codes and magnitude suffixes
+/// (`pipeline.numeric_cells`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NumericCells {
+    /// Sent to the model like any other unit.
+    Translate,
+    /// Copied through without a model call.
+    Copy,
+    /// Copied through with the target language's decimal and group separators.
+    Locale,
+}
+
+impl NumericCells {
+    pub fn parse(s: Option<&str>) -> anyhow::Result<Self> {
+        match s.unwrap_or("copy").trim().to_ascii_lowercase().as_str() {
+            "translate" => Ok(Self::Translate),
+            "copy" => Ok(Self::Copy),
+            "locale" => Ok(Self::Locale),
+            other => Err(anyhow!(
+                "pipeline.numeric_cells: unknown value {other:?} (expected \"copy\", \"locale\" or \"translate\")"
+            )),
+        }
+    }
+}
+
+/// `--range` / `--scope` / `--from-report`: the units to retranslate; all others keep the
+/// previous output.
+#[derive(Clone, Debug, Default)]
+pub struct UnitFilter {
+    /// Paragraph (unit) ids `start..end`, end exclusive.
+    pub range: Option<(usize, usize)>,
+    /// Paragraph ids flagged in a previous run's validation report.
+    pub units: Option<HashSet<usize>>,
+    /// Scope-key patterns such as `word/document.xml#w:p@*`; `*` matches any text.
+    pub scopes: Vec<String>,
+}
+
+impl UnitFilter {
+    /// `120..240`, `120..=240`, `120..` or `..240`.
+    pub fn parse_range(value: &str) -> anyhow::Result<(usize, usize)> {
+        let invalid = || {
+            anyhow!(
+                "invalid range {value:?} (expected e.g. \"120..240\", \"120..=240\" or \"120..\")"
+            )
+        };
+        let (start, end) = value.trim().split_once("..").ok_or_else(invalid)?;
+        let (end, inclusive) = match end.strip_prefix('=') {
+            Some(end) => (end, true),
+            None => (end, false),
+        };
+        let bound = |s: &str| -> anyhow::Result<Option<usize>> {
+            let s = s.trim();
+            if s.is_empty() {
+                return Ok(None);
+            }
+            s.parse::<usize>().map(Some).map_err(|_| invalid())
+        };
+        let start = bound(start)?.unwrap_or(0);
+        let end = match bound(end)? {
+            Some(end) if inclusive => end.saturating_add(1),
+            Some(end) => end,
+            None if inclusive => return Err(invalid()),
+            None => usize::MAX,
+        };
+        if start >= end {
+            return Err(invalid());
+        }
+        Ok((start, end))
+    }
+
+    /// A unit is kept when it is inside the range and the report's units (if given) and matches
+    /// one of the scopes (if any).
+    pub fn keeps(&self, tu_id: usize, scope_key: &str) -> bool {
+        let in_range = self
+            .range
+            .is_none_or(|(start, end)| (start..end).contains(&tu_id));
+        let in_report = self.units.as_ref().is_none_or(|ids| ids.contains(&tu_id));
+        let in_scope =
+            self.scopes.is_empty() || self.scopes.iter().any(|p| scope_matches(p, scope_key));
+        in_range && in_report && in_scope
+    }
+}
+
+/// Glob match of a scope pattern: `*` matches any (possibly empty) text.
+fn scope_matches(pattern: &str, key: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = key.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// Quotation marks the final translation is converted to (`pipeline.quote_style`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuoteStyle {
+    Keep,
+    Curly,
+    Guillemets,
+    Corner,
+    /// By target language: corner brackets for Japanese and traditional Chinese, guillemets for
+    /// French, Russian and their neighbours, curly quotes otherwise.
+    Auto,
+}
+
+/// Opening and closing marks for double quotes and for quotes nested inside them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QuoteMarks {
+    pub double: (char, char),
+    pub single: (char, char),
+}
+
+impl QuoteStyle {
+    pub fn parse(s: Option<&str>) -> anyhow::Result<Self> {
+        match s.unwrap_or("keep").trim().to_ascii_lowercase().as_str() {
+            "keep" => Ok(Self::Keep),
+            "curly" => Ok(Self::Curly),
+            "guillemets" => Ok(Self::Guillemets),
+            "corner" => Ok(Self::Corner),
+            "auto" => Ok(Self::Auto),
+            other => Err(anyhow!(
+                "pipeline.quote_style: unknown value {other:?} (expected \"keep\", \"curly\", \"guillemets\", \"corner\" or \"auto\")"
+            )),
+        }
+    }
+
+    /// The marks for `target_lang`; `None` for `Keep`.
+    pub fn marks(self, target_lang: &str) -> Option<QuoteMarks> {
+        const CURLY: QuoteMarks = QuoteMarks {
+            double: ('\u{201C}', '\u{201D}'),
+            single: ('\u{2018}', '\u{2019}'),
+        };
+        const GUILLEMETS: QuoteMarks = QuoteMarks {
+            double: ('\u{AB}', '\u{BB}'),
+            single: ('\u{2039}', '\u{203A}'),
+        };
+        const CORNER: QuoteMarks = QuoteMarks {
+            double: ('\u{300C}', '\u{300D}'),
+            single: ('\u{300E}', '\u{300F}'),
+        };
+        match self {
+            Self::Keep => None,
+            Self::Curly => Some(CURLY),
+            Self::Guillemets => Some(GUILLEMETS),
+            Self::Corner => Some(CORNER),
+            Self::Auto => {
+                let lang = target_lang.trim().to_ascii_lowercase().replace('_', "-");
+                let base = lang.split('-').next().unwrap_or("");
+                let traditional = ["zh-tw", "zh-hk", "zh-mo", "zh-hant"]
+                    .iter()
+                    .any(|t| lang.starts_with(t));
+                Some(match base {
+                    "ja" => CORNER,
+                    "zh" if traditional => CORNER,
+                    "fr" | "ru" | "uk" | "be" | "es" | "it" | "pt" | "el" | "no" | "nb" | "ar"
+                    | "fa" => GUILLEMETS,
+                    _ => CURLY,
+                })
+            }
+        }
+    }
+}
+
+/// How one kind of paragraph is cased after translation (`[pipeline.casing]`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CaseStyle {
+    #[default]
+    Keep,
+    /// Upper-case the first letter.
+    Capitalize,
+    /// First letter upper-case; text in title case is lowered to sentence case (acronyms and
+    /// mixed-case words are kept).
+    Sentence,
+    /// English title case: every word capitalized except articles, short conjunctions and
+    /// prepositions inside the text. Applied to English targets only.
+    Title,
+}
+
+impl CaseStyle {
+    fn parse(key: &str, s: Option<&str>) -> anyhow::Result<Self> {
+        match s.unwrap_or("keep").trim().to_ascii_lowercase().as_str() {
+            "keep" => Ok(Self::Keep),
+            "capitalize" => Ok(Self::Capitalize),
+            "sentence" => Ok(Self::Sentence),
+            "title" => Ok(Self::Title),
+            other => Err(anyhow!(
+                "pipeline.casing.{key}: unknown value {other:?} (expected \"keep\", \"capitalize\", \"sentence\" or \"title\")"
+            )),
+        }
+    }
+}
+
+/// Casing rules (`[pipeline.casing]`).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CasingRules {
+    pub headings: CaseStyle,
+    pub body: CaseStyle,
+    pub table_cells: CaseStyle,
+    pub preserve_all_caps: bool,
+}
+
+impl CasingRules {
+    pub fn is_noop(&self) -> bool {
+        !self.preserve_all_caps
+            && [self.headings, self.body, self.table_cells]
+                .iter()
+                .all(|s| *s == CaseStyle::Keep)
+    }
+}
+
+/// Translate chunk limits (`[pipeline.chunking]`).
+#[derive(Clone, Debug)]
+pub struct Chunking {
+    /// Units per chunk for every mode (`None`: the mode's default).
+    pub max_items: Option<usize>,
+    pub char_per_token_ratio: f64,
+    pub reserve_tokens: u32,
+}
+
+impl Default for Chunking {
+    fn default() -> Self {
+        Self {
+            max_items: None,
+            char_per_token_ratio: 2.0,
+            reserve_tokens: 900,
+        }
+    }
+}
+
+impl Chunking {
+    /// Source chars per chunk for a backend with `ctx_size` tokens (at least 2000 tokens' worth).
+    pub fn char_budget(&self, ctx_size: u32) -> usize {
+        let tokens = ctx_size.saturating_sub(self.reserve_tokens).max(2000);
+        (f64::from(tokens) * self.char_per_token_ratio) as usize
+    }
+}
+
+/// A `[routing]` entry: the translate backend for documents in `source` -> `target` (lowercase
+/// codes; `*` matches any language).
+#[derive(Clone, Debug)]
+pub struct LangRoute {
+    pub source: String,
+    pub target: String,
+    pub backend: ResolvedBackend,
+}
+
+impl LangRoute {
+    pub fn matches(&self, source_lang: &str, target_lang: &str) -> bool {
+        lang_matches(&self.source, source_lang) && lang_matches(&self.target, target_lang)
+    }
+}
+
+/// A `[pivot]` entry: documents in `source` -> `target` are translated into `via` first, then
+/// from there into `target`. A missing backend is picked per hop at run time.
+#[derive(Clone, Debug)]
+pub struct Pivot {
+    pub source: String,
+    pub target: String,
+    pub via: String,
+    pub first: Option<ResolvedBackend>,
+    pub second: Option<ResolvedBackend>,
+}
+
+impl Pivot {
+    pub fn matches(&self, source_lang: &str, target_lang: &str) -> bool {
+        lang_matches(&self.source, source_lang) && lang_matches(&self.target, target_lang)
+    }
+}
+
+/// `pattern` is `*`, the code itself, or its primary subtag (`zh` matches `zh-CN`).
+fn lang_matches(pattern: &str, code: &str) -> bool {
+    let code = code.trim().to_ascii_lowercase();
+    pattern == "*" || pattern == code || code.split(['-', '_']).next() == Some(pattern)
+}
+
+/// Stages `[pipeline.stages]` can limit to flagged units (`"flagged"`).
+pub const FLAGGED_STAGES: [&str; 3] = ["fuse", "stitch_audit", "patch"];
+
+/// Sampling seed when `pipeline.seed` / `--seed` is not set.
+pub const DEFAULT_SEED: u32 = 42;
+
+/// `[pdf] to_docx` default: LibreOffice headless with its PDF import filter.
+pub const DEFAULT_PDF_TO_DOCX: &str =
+    "soffice --headless --infilter=writer_pdf_import --convert-to docx --outdir {outdir} {input}";
+
+/// `[pdf] to_pdf` default.
+pub const DEFAULT_DOCX_TO_PDF: &str =
+    "soffice --headless --convert-to pdf --outdir {outdir} {input}";
+
+/// Stage keys understood by `[pipeline.stages]` (full mode). `polish` is reserved: there is no
+/// polish pass yet.
+pub const PIPELINE_STAGES: [&str; 6] = [
+    "notes",
+    "alt_translate",
+    "fuse",
+    "stitch_audit",
+    "patch",
+    "polish",
+];
+
 #[derive(Clone, Debug)]
 pub struct PipelineConfig {
     pub workdir: PathBuf,
     pub config_path: PathBuf,
+    /// `--profile` applied to `config_path`.
+    pub config_profile: Option<String>,
 
     pub mode: PipelineMode,
 
     pub translate_backend: ResolvedBackend,
+    /// Remaining `translate_backend` chain entries whose model files exist, in fallback order.
+    pub translate_fallbacks: Vec<ResolvedBackend>,
+    /// `[routing]` language-pair entries, most specific first; empty when the translate backend
+    /// was chosen on the command line.
+    pub routes: Vec<LangRoute>,
+    /// `[pivot]` entries, most specific first.
+    pub pivots: Vec<Pivot>,
     pub alt_translate_backend: Option<ResolvedBackend>,
     pub rewrite_backend: Option<ResolvedBackend>,
     pub controller_backend: Option<ResolvedBackend>,
+    /// Full mode: para_notes for the whole document on a worker thread concurrently with the
+    /// whole of Translate A (which does not read the notes, so nothing is pipelined per chunk).
+    pub overlap_controller: bool,
 
     pub threads: i32,
     pub gpu_layers: i32,
@@ -41,22 +412,117 @@ pub struct PipelineConfig {
 
     pub autosave_every: usize,
     pub autosave_suffix: String,
+    pub autosave_keep: usize,
     pub trace_dir: PathBuf,
+    /// `--workdir`: also the trace dir; autosaves go here instead of next to the output and
+    /// written artifacts are recorded in its `manifest.json`.
+    pub artifact_dir: Option<PathBuf>,
     pub trace_prompts: bool,
+    pub trace_limits: TraceLimits,
+    pub trace_bundle_on_failure: bool,
+    pub trace_bundle_redact: bool,
     pub log_max_chars: usize,
     pub max_tus: Option<usize>,
+    /// Resume from a matching autosave text.json (`--continue`; basic mode).
+    pub resume: bool,
+    /// Review each Translate A chunk on the terminal before it is committed (`--interactive`;
+    /// basic mode).
+    pub interactive: bool,
+    /// Reproducible runs (`--deterministic`): greedy decoding, fixed seed, no concurrent
+    /// controller, no switching to a fallback backend.
+    pub deterministic: bool,
+    /// `--proofread`: same-language pass with the `proofread` prompt; the target language is
+    /// the source language.
+    pub proofread: bool,
+    /// `--pseudo` / `--copy-source`: no model; see `OfflineMode`.
+    pub offline: Option<OfflineMode>,
 
+    /// How input packages are opened: `[input]` caps and the `--password` of encrypted inputs.
+    pub package: PackageOptions,
+    /// `[[links.rewrite]]` rules applied when the output DOCX is merged.
+    pub link_rewrites: Vec<LinkRewrite>,
+    /// `input.placeholder_prefix` (or `--placeholder-prefix`) for the mask placeholders.
+    pub placeholder_prefix: PlaceholderPrefix,
     pub docx_filter_rules: Option<PathBuf>,
+    pub project_dir: Option<PathBuf>,
+    /// Translated pairs carried into the next chunk's prompt as read-only context (0 = off).
+    pub context_overlap: usize,
+    /// Size cap (chars) of that context.
+    pub context_overlap_max_chars: usize,
+    /// Translate embedded DOCX/XLSX objects (`word/embeddings/`) too.
+    pub translate_embedded: bool,
+    /// Shell command that OCRs one image (`{image}` placeholder); appends an OCR appendix.
+    pub ocr_command: Option<String>,
+    /// `--from-pdf` converter commands (`[pdf]`, LibreOffice by default).
+    pub pdf_to_docx_command: String,
+    pub docx_to_pdf_command: String,
+    /// Plain text (no `<<MT_...>>` tokens) in the aligned corpus JSONL.
+    pub corpus_strip_tokens: bool,
+    /// Highlight source-fallback slots in the output DOCX.
+    pub highlight_fallback: bool,
+    /// Highlight slots with hard quality flags too.
+    pub highlight_low_confidence: bool,
+    pub highlight_color: String,
+    /// Per-unit repair attempts (token errors get `max_repairs_token_errors`).
+    pub max_repairs: usize,
+    pub max_repairs_token_errors: usize,
+    /// Repair calls for the whole run (`None` = unlimited).
+    pub max_repairs_total: Option<usize>,
+    /// Stitch audit + patch rounds at most (full mode).
+    pub stitch_rounds: usize,
+    /// Patch calls over all stitch rounds (`None` = unlimited).
+    pub patch_budget: Option<usize>,
+    /// Patch models generating in parallel (at least 1).
+    pub patch_workers: usize,
+    pub chunking: Chunking,
+    /// Adapt translate chunk sizes to the parse failure rate per backend.
+    pub adaptive_chunks: bool,
+    pub slot_projection: SlotProjection,
+    pub numeric_cells: NumericCells,
+    pub casing: CasingRules,
+    pub acronyms: AcronymRules,
+    pub quote_style: QuoteStyle,
+    pub cross_refs: bool,
+    pub update_fields: bool,
+    /// Effective sampling seed of the run (drawn at startup for `seed = "random"`).
+    pub seed: u32,
+    pub seed_random: bool,
+    /// Abort when more than this percentage of units fell back to source (`--fail-on-fallback`).
+    pub fail_on_fallback: Option<f64>,
+    /// Percentage of translated units re-checked by the QA sample (`--qa-sample`).
+    pub qa_sample: Option<f64>,
+    /// Retranslate only these units onto the previous output (`--range` / `--scope` /
+    /// `--from-report`).
+    pub unit_filter: Option<UnitFilter>,
+    /// Reviewed DOCX whose Word comments become patch instructions (`--from-review`).
+    pub review_docx: Option<PathBuf>,
+    /// `[pipeline.stages]` switched off (full mode).
+    pub skipped_stages: Vec<String>,
+    /// `[pipeline.stages]` set to `"flagged"`: run only on units with quality flags.
+    pub flagged_stages: Vec<String>,
+    /// Set by `TranslatorPipeline::with_cancellation`; checked between chunks and model calls.
+    pub cancel: CancellationToken,
 
     pub prompts: PromptCatalog,
 }
 
 impl PipelineConfig {
+    /// Whether `[pipeline.stages]` leaves `stage` (a `PIPELINE_STAGES` key) on.
+    pub fn stage_enabled(&self, stage: &str) -> bool {
+        !self.skipped_stages.iter().any(|s| s == stage)
+    }
+
+    /// Whether `[pipeline.stages]` limits `stage` to units with quality flags.
+    pub fn stage_flagged_only(&self, stage: &str) -> bool {
+        self.flagged_stages.iter().any(|s| s == stage)
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub fn from_paths_and_args(
         input: &Path,
         output: &Path,
         config_path: Option<PathBuf>,
+        profile: Option<&str>,
         translate_backend: Option<String>,
         alt_translate_backend: Option<String>,
         rewrite_backend: Option<String>,
@@ -74,25 +540,13 @@ impl PipelineConfig {
         _ctx_controller: Option<u32>,
         max_tus: Option<usize>,
     ) -> anyhow::Result<Self> {
-        let workdir = input
-            .parent()
-            .map(|p| p.to_path_buf())
-            .unwrap_or_else(|| PathBuf::from("."));
-        let workdir = workdir.canonicalize().unwrap_or(workdir);
-
-        let cfg_file = config_path
-            .clone()
-            .or_else(|| {
-                std::env::var("MUGGLE_TRANSLATOR_CONFIG")
-                    .ok()
-                    .map(PathBuf::from)
-            })
-            .or_else(|| find_default_config(&workdir, "muggle-translator.toml"));
+        let workdir = input_workdir(input);
+        let cfg_file = locate_config_file(&workdir, config_path.clone());
 
         let mut file_cfg = AppConfig::default();
         if let Some(p) = cfg_file.as_ref() {
             if p.exists() {
-                file_cfg = load_config(p)?;
+                file_cfg = load_config(p, profile)?;
             }
         }
         let cfg_path = cfg_file
@@ -101,9 +555,34 @@ impl PipelineConfig {
 
         let mode = PipelineMode::parse(file_cfg.pipeline.mode.as_deref());
 
-        let translate_backend_name = translate_backend
-            .or_else(|| file_cfg.pipeline.translate_backend.clone())
-            .unwrap_or_else(|| "translategemma_4b".to_string());
+        let translate_backend_from_cli = translate_backend.is_some();
+        let mut translate_chain: Vec<String> = match translate_backend {
+            Some(names) => names
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            None => file_cfg
+                .pipeline
+                .translate_backend
+                .as_ref()
+                .map(BackendChain::names)
+                .unwrap_or_default(),
+        };
+        // `[routing]` only applies when the command line did not pick the translate backend.
+        let cli_backend = translate_backend_from_cli || translate_model.is_some();
+        let (route_default, route_specs) = if cli_backend {
+            (None, Vec::new())
+        } else {
+            configured_routes(&file_cfg)?
+        };
+        if let Some(name) = route_default {
+            translate_chain.retain(|n| *n != name);
+            translate_chain.insert(0, name);
+        }
+        if translate_chain.is_empty() {
+            translate_chain.push("translategemma_4b".to_string());
+        }
         let alt_translate_backend_name = if mode == PipelineMode::Full {
             alt_translate_backend
                 .or_else(|| file_cfg.pipeline.alt_translate_backend.clone())
@@ -144,7 +623,59 @@ impl PipelineConfig {
         } else {
             output_dir.join(trace_dir)
         };
-        let trace_prompts = file_cfg.pipeline.trace_prompts.unwrap_or(true);
+        let trace_prompts = file_cfg.pipeline.trace_prompts.unwrap_or(true)
+            && file_cfg.trace.enabled.unwrap_or(true);
+        let mut disabled_stages: Vec<String> = Vec::new();
+        for (stage, enabled) in &file_cfg.trace.stages {
+            if !TRACE_STAGES.contains(&stage.as_str()) {
+                eprintln!(
+                    "[warn] unknown [trace.stages] key {stage:?} (known: {})",
+                    TRACE_STAGES.join(", ")
+                );
+                continue;
+            }
+            if !enabled {
+                disabled_stages.push(stage.clone());
+            }
+        }
+        let mut skipped_stages: Vec<String> = Vec::new();
+        let mut flagged_stages: Vec<String> = Vec::new();
+        for (stage, setting) in &file_cfg.pipeline.stages {
+            if !PIPELINE_STAGES.contains(&stage.as_str()) {
+                eprintln!(
+                    "[warn] unknown [pipeline.stages] key {stage:?} (known: {})",
+                    PIPELINE_STAGES.join(", ")
+                );
+                continue;
+            }
+            match setting {
+                StageSetting::Enabled(true) => {}
+                StageSetting::Enabled(false) => skipped_stages.push(stage.clone()),
+                StageSetting::Named(value)
+                    if value.trim().eq_ignore_ascii_case("flagged")
+                        && FLAGGED_STAGES.contains(&stage.as_str()) =>
+                {
+                    flagged_stages.push(stage.clone());
+                }
+                StageSetting::Named(value) => eprintln!(
+                    "[warn] [pipeline.stages] {stage} = {value:?} not supported (true, false, or \"flagged\" for {}); the stage stays on",
+                    FLAGGED_STAGES.join(", ")
+                ),
+            }
+        }
+        skipped_stages.sort_by_key(|s| PIPELINE_STAGES.iter().position(|k| k == s));
+        flagged_stages.sort_by_key(|s| PIPELINE_STAGES.iter().position(|k| k == s));
+        let trace_limits = TraceLimits {
+            max_files: file_cfg.trace.max_files.filter(|n| *n > 0),
+            max_bytes: file_cfg
+                .trace
+                .max_size_mb
+                .filter(|n| *n > 0)
+                .map(|mb| mb.saturating_mul(1024 * 1024)),
+            disabled_stages,
+        };
+        let trace_bundle_on_failure = file_cfg.trace.bundle_on_failure.unwrap_or(true);
+        let trace_bundle_redact = file_cfg.trace.bundle_redact.unwrap_or(false);
         let log_max_chars = file_cfg.pipeline.log_max_chars.unwrap_or(240);
         let autosave_every = file_cfg.pipeline.autosave_every.unwrap_or(10).max(1);
         let autosave_suffix = file_cfg
@@ -152,8 +683,11 @@ impl PipelineConfig {
             .autosave_suffix
             .clone()
             .unwrap_or_else(|| "_进度.docx".to_string());
+        let autosave_keep = file_cfg.pipeline.autosave_keep.unwrap_or(0);
         let max_tus = max_tus.or(file_cfg.pipeline.max_tus).filter(|n| *n > 0);
 
+        let link_rewrites = link_rewrites(&file_cfg).context("links.rewrite")?;
+        let placeholder_prefix = placeholder_prefix(&file_cfg)?;
         let docx_filter_rules = file_cfg
             .pipeline
             .docx_filter_rules
@@ -169,6 +703,71 @@ impl PipelineConfig {
                 }
             });
 
+        let project_dir = file_cfg
+            .pipeline
+            .project_dir
+            .clone()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .map(PathBuf::from)
+            .map(|p| {
+                if p.is_relative() {
+                    cfg_path.parent().unwrap_or_else(|| Path::new(".")).join(p)
+                } else {
+                    p
+                }
+            });
+
+        let context_overlap = file_cfg.pipeline.context_overlap.unwrap_or(0);
+        let context_overlap_max_chars = file_cfg.pipeline.context_overlap_max_chars.unwrap_or(1200);
+        let translate_embedded = file_cfg.pipeline.translate_embedded.unwrap_or(false);
+        let ocr_command = file_cfg
+            .pipeline
+            .ocr_command
+            .clone()
+            .filter(|c| !c.trim().is_empty());
+        let pdf_to_docx_command = file_cfg
+            .pdf
+            .to_docx
+            .clone()
+            .unwrap_or_else(|| DEFAULT_PDF_TO_DOCX.to_string());
+        let docx_to_pdf_command = file_cfg
+            .pdf
+            .to_pdf
+            .clone()
+            .unwrap_or_else(|| DEFAULT_DOCX_TO_PDF.to_string());
+        let corpus_strip_tokens = file_cfg.pipeline.corpus_strip_tokens.unwrap_or(false);
+        let highlight_fallback = file_cfg.pipeline.highlight_fallback.unwrap_or(false);
+        let highlight_low_confidence = file_cfg.pipeline.highlight_low_confidence.unwrap_or(false);
+        let highlight_color = configured_color(&file_cfg)?;
+        let max_repairs = file_cfg.pipeline.max_repairs.unwrap_or(2);
+        let max_repairs_token_errors = file_cfg
+            .pipeline
+            .max_repairs_token_errors
+            .unwrap_or(6)
+            .max(max_repairs);
+        let max_repairs_total = file_cfg.pipeline.max_repairs_total;
+        let stitch_rounds = file_cfg.pipeline.stitch_rounds.unwrap_or(2).max(1);
+        let patch_budget = file_cfg.pipeline.patch_budget;
+        let patch_workers = file_cfg.pipeline.patch_workers.unwrap_or(1).max(1);
+        let chunking = configured_chunking(&file_cfg)?;
+        let adaptive_chunks = file_cfg.pipeline.adaptive_chunks.unwrap_or(true);
+        let slot_projection = SlotProjection::parse(file_cfg.pipeline.slot_projection.as_deref())?;
+        let numeric_cells = NumericCells::parse(file_cfg.pipeline.numeric_cells.as_deref())?;
+        let casing = configured_casing(&file_cfg)?;
+        let acronyms = configured_acronyms(&file_cfg)?;
+        let quote_style = QuoteStyle::parse(file_cfg.pipeline.quote_style.as_deref())?;
+        let cross_refs = file_cfg.pipeline.cross_refs.unwrap_or(true);
+        let update_fields = file_cfg.pipeline.update_fields.unwrap_or(true);
+        let seed = match &file_cfg.pipeline.seed {
+            None => Some(DEFAULT_SEED),
+            Some(SeedSetting::Fixed(n)) => Some(*n),
+            Some(SeedSetting::Named(s)) => {
+                parse_seed(s).map_err(|err| anyhow!("pipeline.seed: {err}"))?
+            }
+        };
+        let overlap_controller = file_cfg.pipeline.overlap_controller.unwrap_or(false);
+
         let threads = threads.or(file_cfg.pipeline.threads).unwrap_or(-1);
         let gpu_layers = gpu_layers.or(file_cfg.pipeline.gpu_layers).unwrap_or(-1);
 
@@ -190,6 +789,11 @@ impl PipelineConfig {
                     batch_size: None,
                     ubatch_size: None,
                     offload_kqv: None,
+                    use_mmap: None,
+                    use_mlock: None,
+                    strip_reasoning: true,
+                    thinking_budget: 0,
+                    completion: None,
                 });
             }
             resolve_backend(
@@ -203,8 +807,86 @@ impl PipelineConfig {
             )
         };
 
-        let translate_backend =
-            resolve_with_override(&translate_backend_name, translate_model, 8192)?;
+        let (translate_backend, translate_fallbacks) = if translate_model.is_some() {
+            (
+                resolve_with_override(&translate_chain[0], translate_model, 8192)?,
+                Vec::new(),
+            )
+        } else {
+            let mut resolved: Vec<ResolvedBackend> = Vec::new();
+            let mut first_err: Option<anyhow::Error> = None;
+            for name in &translate_chain {
+                match resolve_with_override(name, None, 8192) {
+                    Ok(b) => resolved.push(b),
+                    Err(err) => {
+                        if translate_chain.len() > 1 {
+                            eprintln!(
+                                "{}",
+                                tr_args(
+                                    "pipeline.backend_unavailable",
+                                    &[("name", name), ("err", &format!("{err:#}"))],
+                                )
+                            );
+                        }
+                        first_err.get_or_insert(err);
+                    }
+                }
+            }
+            if resolved.is_empty() {
+                return Err(first_err.unwrap_or_else(|| anyhow::anyhow!("no translate backend")));
+            }
+            let primary = resolved.remove(0);
+            (primary, resolved)
+        };
+        let mut routes: Vec<LangRoute> = Vec::new();
+        for (source, target, name) in route_specs {
+            match resolve_with_override(&name, None, 8192) {
+                Ok(backend) => routes.push(LangRoute {
+                    source,
+                    target,
+                    backend,
+                }),
+                Err(err) => eprintln!(
+                    "{}",
+                    tr_args(
+                        "pipeline.backend_unavailable",
+                        &[("name", &name), ("err", &format!("{err:#}"))],
+                    )
+                ),
+            }
+        }
+        let mut pivots: Vec<Pivot> = Vec::new();
+        for (key, section) in &file_cfg.pivot {
+            let Some((source, target)) = parse_lang_pair_key(key) else {
+                return Err(anyhow!(
+                    "pivot: bad key {key:?} (expected \"<source>-><target>\")"
+                ));
+            };
+            let via = section.via.trim().to_ascii_lowercase();
+            if via.is_empty() || via == "*" {
+                return Err(anyhow!("pivot.{key:?}: `via` must be a language code"));
+            }
+            let hop_backend = |name: Option<&String>| match name.map(|s| s.trim()) {
+                Some(n) if !n.is_empty() => resolve_with_override(n, None, 8192).map(Some),
+                _ => Ok(None),
+            };
+            let first = hop_backend(section.first.as_ref())
+                .with_context(|| format!("pivot.{key:?}.first"))?;
+            let second = hop_backend(section.second.as_ref())
+                .with_context(|| format!("pivot.{key:?}.second"))?;
+            pivots.push(Pivot {
+                source,
+                target,
+                via,
+                first,
+                second,
+            });
+        }
+        pivots.sort_by(|a, b| {
+            pair_wildcards(&a.source, &a.target)
+                .cmp(&pair_wildcards(&b.source, &b.target))
+                .then_with(|| (&a.source, &a.target).cmp(&(&b.source, &b.target)))
+        });
         let alt_translate_backend = match alt_translate_backend_name.as_deref() {
             Some(n) => Some(resolve_with_override(n, alt_translate_model, 4096)?),
             None => None,
@@ -220,6 +902,17 @@ impl PipelineConfig {
 
         let mut prompt_backends: Vec<String> = Vec::new();
         prompt_backends.push(translate_backend.name.clone());
+        prompt_backends.extend(translate_fallbacks.iter().map(|b| b.name.clone()));
+        prompt_backends.extend(routes.iter().map(|r| r.backend.name.clone()));
+        for pivot in &pivots {
+            prompt_backends.extend(
+                pivot
+                    .first
+                    .iter()
+                    .chain(&pivot.second)
+                    .map(|b| b.name.clone()),
+            );
+        }
         if let Some(b) = alt_translate_backend.as_ref() {
             prompt_backends.push(b.name.clone());
         }
@@ -237,27 +930,424 @@ impl PipelineConfig {
         Ok(Self {
             workdir,
             config_path: cfg_path,
+            config_profile: profile.map(str::to_string),
             mode,
             translate_backend,
+            translate_fallbacks,
+            routes,
+            pivots,
             alt_translate_backend,
             rewrite_backend,
             controller_backend,
+            overlap_controller,
             threads,
             gpu_layers,
             source_lang,
             target_lang,
             autosave_every,
             autosave_suffix,
+            autosave_keep,
             trace_dir,
+            artifact_dir: None,
             trace_prompts,
+            trace_limits,
+            trace_bundle_on_failure,
+            trace_bundle_redact,
             log_max_chars,
             max_tus,
+            resume: false,
+            interactive: false,
+            deterministic: false,
+            proofread: false,
+            offline: None,
+            package: PackageOptions {
+                limits: package_limits(&file_cfg),
+                password: None,
+                lenient: false,
+            },
+            link_rewrites,
+            placeholder_prefix,
             docx_filter_rules,
+            project_dir,
+            context_overlap,
+            context_overlap_max_chars,
+            translate_embedded,
+            ocr_command,
+            pdf_to_docx_command,
+            docx_to_pdf_command,
+            corpus_strip_tokens,
+            highlight_fallback,
+            highlight_low_confidence,
+            highlight_color,
+            max_repairs,
+            max_repairs_token_errors,
+            max_repairs_total,
+            stitch_rounds,
+            patch_budget,
+            patch_workers,
+            chunking,
+            adaptive_chunks,
+            slot_projection,
+            numeric_cells,
+            casing,
+            acronyms,
+            quote_style,
+            cross_refs,
+            update_fields,
+            seed: seed.unwrap_or_else(random_seed),
+            seed_random: seed.is_none(),
+            fail_on_fallback: None,
+            qa_sample: None,
+            unit_filter: None,
+            review_docx: None,
+            skipped_stages,
+            flagged_stages,
+            cancel: CancellationToken::new(),
             prompts,
         })
     }
 }
 
+impl PipelineConfig {
+    /// Resolve a configured backend outside the run's roles (e.g. for `--experiment`) and load
+    /// its prompt overrides into the catalog.
+    pub fn resolve_extra_backend(&mut self, name: &str) -> anyhow::Result<ResolvedBackend> {
+        let file_cfg = if self.config_path.exists() {
+            load_config(&self.config_path, self.config_profile.as_deref())?
+        } else {
+            AppConfig::default()
+        };
+        let model_dir = file_cfg
+            .models
+            .model_dir
+            .clone()
+            .unwrap_or_else(|| self.workdir.clone());
+        let backend = resolve_backend(
+            &file_cfg,
+            &self.config_path,
+            name,
+            &model_dir,
+            &[],
+            8192,
+            None,
+        )?;
+        self.prompts
+            .add_backend(&self.config_path, &file_cfg, name)
+            .context("load prompts")?;
+        Ok(backend)
+    }
+}
+
+pub(super) fn input_workdir(input: &Path) -> PathBuf {
+    let workdir = input
+        .parent()
+        .map(|p| p.to_path_buf())
+        .unwrap_or_else(|| PathBuf::from("."));
+    workdir.canonicalize().unwrap_or(workdir)
+}
+
+/// `--config`, then `MUGGLE_TRANSLATOR_CONFIG`, then `muggle-translator.toml` searched upwards.
+pub(super) fn locate_config_file(workdir: &Path, config_path: Option<PathBuf>) -> Option<PathBuf> {
+    config_path
+        .or_else(|| {
+            std::env::var("MUGGLE_TRANSLATOR_CONFIG")
+                .ok()
+                .map(PathBuf::from)
+        })
+        .or_else(|| find_default_config(workdir, "muggle-translator.toml"))
+}
+
+/// The config a run on `input` would use, read before the full config is built; config errors
+/// are left to `PipelineConfig::from_paths_and_args` to report.
+fn located_config(
+    input: Option<&Path>,
+    config_path: Option<&Path>,
+    profile: Option<&str>,
+) -> Option<AppConfig> {
+    let workdir = match input {
+        Some(p) => input_workdir(p),
+        None => PathBuf::from("."),
+    };
+    let path = locate_config_file(&workdir, config_path.map(Path::to_path_buf))?;
+    load_config(&path, profile).ok()
+}
+
+/// `pipeline.ui_lang` from the config that a run on `input` would use, so that early messages
+/// and the default output name are localized too.
+pub fn configured_ui_lang(
+    input: Option<&Path>,
+    config_path: Option<&Path>,
+    profile: Option<&str>,
+) -> Option<String> {
+    let cfg = located_config(input, config_path, profile)?;
+    cfg.pipeline
+        .ui_lang
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// `[input]` caps from the config that applies to `input` (defaults when absent).
+pub fn configured_package_limits(
+    input: Option<&Path>,
+    config_path: Option<&Path>,
+    profile: Option<&str>,
+) -> PackageLimits {
+    match located_config(input, config_path, profile) {
+        Some(cfg) => package_limits(&cfg),
+        None => PackageLimits::default(),
+    }
+}
+
+fn package_limits(cfg: &AppConfig) -> PackageLimits {
+    let mut limits = PackageLimits::default();
+    if let Some(n) = cfg.input.max_entries {
+        limits.max_entries = n;
+    }
+    if let Some(mb) = cfg.input.max_entry_mb {
+        limits.max_entry_bytes = mb.saturating_mul(1 << 20);
+    }
+    if let Some(mb) = cfg.input.max_total_mb {
+        limits.max_total_bytes = mb.saturating_mul(1 << 20);
+    }
+    limits
+}
+
+/// `input.placeholder_prefix` from the config that applies to `input` (file hash when absent);
+/// an invalid value is an error.
+pub fn configured_placeholder_prefix(
+    input: Option<&Path>,
+    config_path: Option<&Path>,
+    profile: Option<&str>,
+) -> anyhow::Result<PlaceholderPrefix> {
+    match located_config(input, config_path, profile) {
+        Some(cfg) => placeholder_prefix(&cfg),
+        None => Ok(PlaceholderPrefix::FileHash),
+    }
+}
+
+fn placeholder_prefix(cfg: &AppConfig) -> anyhow::Result<PlaceholderPrefix> {
+    match cfg.input.placeholder_prefix.as_deref() {
+        Some(value) => PlaceholderPrefix::parse(value).context("input.placeholder_prefix"),
+        None => Ok(PlaceholderPrefix::FileHash),
+    }
+}
+
+/// `[[links.rewrite]]` rules from the config that applies to `input`; a bad pattern is an error.
+pub fn configured_link_rewrites(
+    input: Option<&Path>,
+    config_path: Option<&Path>,
+    profile: Option<&str>,
+) -> anyhow::Result<Vec<LinkRewrite>> {
+    match located_config(input, config_path, profile) {
+        Some(cfg) => link_rewrites(&cfg),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn link_rewrites(cfg: &AppConfig) -> anyhow::Result<Vec<LinkRewrite>> {
+    cfg.links
+        .rewrite
+        .iter()
+        .map(|r| LinkRewrite::new(&r.pattern, &r.replacement))
+        .collect()
+}
+
+/// `[hooks]` from the config that applies to `input` (none when no config is found).
+pub fn configured_hooks(
+    input: Option<&Path>,
+    config_path: Option<&Path>,
+    profile: Option<&str>,
+) -> Hooks {
+    let Some(cfg) = located_config(input, config_path, profile) else {
+        return Hooks::default();
+    };
+    let targets = |t: Option<&HookTargets>| t.map(HookTargets::targets).unwrap_or_default();
+    Hooks {
+        on_success: targets(cfg.hooks.on_success.as_ref()),
+        on_failure: targets(cfg.hooks.on_failure.as_ref()),
+        timeout_secs: cfg.hooks.timeout_secs.unwrap_or(30),
+    }
+}
+
+/// `pipeline.highlight_color` from the config that applies to `input` (for `--clear-highlights`).
+pub fn configured_highlight_color(
+    input: Option<&Path>,
+    config_path: Option<&Path>,
+    profile: Option<&str>,
+) -> anyhow::Result<String> {
+    match located_config(input, config_path, profile) {
+        Some(cfg) => configured_color(&cfg),
+        None => Ok(DEFAULT_HIGHLIGHT.to_string()),
+    }
+}
+
+/// `N` or `random` (`None`: draw a fresh seed with `random_seed`).
+pub fn parse_seed(value: &str) -> anyhow::Result<Option<u32>> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("random") {
+        return Ok(None);
+    }
+    value
+        .parse::<u32>()
+        .map(Some)
+        .map_err(|_| anyhow!("invalid seed {value:?} (expected a number or \"random\")"))
+}
+
+/// `5%` or `5`: a percentage in (0, 100].
+pub fn parse_percent(value: &str) -> anyhow::Result<f64> {
+    let value = value.trim();
+    let number = value.strip_suffix('%').unwrap_or(value).trim();
+    match number.parse::<f64>() {
+        Ok(pct) if pct > 0.0 && pct <= 100.0 => Ok(pct),
+        _ => Err(anyhow!(
+            "invalid percentage {value:?} (expected e.g. \"5%\", above 0 and at most 100)"
+        )),
+    }
+}
+
+/// Seed for `random`: clock nanoseconds mixed with the process id (no RNG crate needed).
+pub fn random_seed() -> u32 {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0);
+    let mixed = (nanos ^ (u64::from(std::process::id()) << 32)).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    (mixed >> 32) as u32
+}
+
+/// `(source, target, backend name)` of a `[routing]` pair entry.
+type RouteSpec = (String, String, String);
+
+/// `[routing]`: the `default` backend name and the pair entries, entries without `*` first.
+fn configured_routes(cfg: &AppConfig) -> anyhow::Result<(Option<String>, Vec<RouteSpec>)> {
+    let mut default = None;
+    let mut routes = Vec::new();
+    for (key, name) in &cfg.routing {
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            continue;
+        }
+        let key = key.trim().to_ascii_lowercase();
+        if key == "default" {
+            default = Some(name);
+            continue;
+        }
+        let Some((source, target)) = parse_lang_pair_key(&key) else {
+            return Err(anyhow!(
+                "routing: bad key {key:?} (expected \"<source>-><target>\" or \"default\")"
+            ));
+        };
+        routes.push((source, target, name));
+    }
+    routes.sort_by(|a, b| {
+        pair_wildcards(&a.0, &a.1)
+            .cmp(&pair_wildcards(&b.0, &b.1))
+            .then_with(|| a.cmp(b))
+    });
+    Ok((default, routes))
+}
+
+/// `"<source>-><target>"` (lowercase, trimmed) as `(source, target)`.
+fn parse_lang_pair_key(key: &str) -> Option<(String, String)> {
+    let (source, target) = key.split_once("->")?;
+    let (source, target) = (source.trim(), target.trim());
+    if source.is_empty() || target.is_empty() {
+        return None;
+    }
+    Some((source.to_ascii_lowercase(), target.to_ascii_lowercase()))
+}
+
+fn pair_wildcards(source: &str, target: &str) -> u8 {
+    u8::from(source == "*") + u8::from(target == "*")
+}
+
+fn configured_chunking(cfg: &AppConfig) -> anyhow::Result<Chunking> {
+    let section = &cfg.pipeline.chunking;
+    let mut chunking = Chunking::default();
+    if let Some(n) = section.max_items {
+        if n == 0 {
+            return Err(anyhow!("pipeline.chunking.max_items must be at least 1"));
+        }
+        chunking.max_items = Some(n);
+    }
+    if let Some(ratio) = section.char_per_token_ratio {
+        if !(ratio.is_finite() && ratio > 0.0) {
+            return Err(anyhow!(
+                "pipeline.chunking.char_per_token_ratio must be a positive number (got {ratio})"
+            ));
+        }
+        chunking.char_per_token_ratio = ratio;
+    }
+    if let Some(n) = section.reserve_tokens {
+        chunking.reserve_tokens = n;
+    }
+    Ok(chunking)
+}
+
+fn configured_casing(cfg: &AppConfig) -> anyhow::Result<CasingRules> {
+    let section = &cfg.pipeline.casing;
+    Ok(CasingRules {
+        headings: CaseStyle::parse("headings", section.headings.as_deref())?,
+        body: CaseStyle::parse("body", section.body.as_deref())?,
+        table_cells: CaseStyle::parse("table_cells", section.table_cells.as_deref())?,
+        preserve_all_caps: section.preserve_all_caps.unwrap_or(false),
+    })
+}
+
+fn configured_acronyms(cfg: &AppConfig) -> anyhow::Result<AcronymRules> {
+    let section = &cfg.pipeline.acronyms;
+    AcronymRules::new(
+        section.enabled.unwrap_or(true),
+        &section.allow,
+        &section.deny,
+    )
+    .context("pipeline.acronyms.allow")
+}
+
+fn configured_color(cfg: &AppConfig) -> anyhow::Result<String> {
+    let Some(color) = cfg.pipeline.highlight_color.as_deref().map(str::trim) else {
+        return Ok(DEFAULT_HIGHLIGHT.to_string());
+    };
+    HIGHLIGHT_COLORS
+        .iter()
+        .find(|c| c.eq_ignore_ascii_case(color))
+        .map(|c| c.to_string())
+        .ok_or_else(|| {
+            anyhow!(
+                "pipeline.highlight_color: unknown color {color:?} (expected one of: {})",
+                HIGHLIGHT_COLORS.join(", ")
+            )
+        })
+}
+
+/// `pipeline.project_dir` from the config that applies to `input` (relative to the config file).
+pub fn configured_project_dir(
+    input: Option<&Path>,
+    config_path: Option<&Path>,
+    profile: Option<&str>,
+) -> Option<PathBuf> {
+    let workdir = match input {
+        Some(p) => input_workdir(p),
+        None => PathBuf::from("."),
+    };
+    let cfg_path = locate_config_file(&workdir, config_path.map(Path::to_path_buf))?;
+    let dir = load_config(&cfg_path, profile)
+        .ok()?
+        .pipeline
+        .project_dir
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .map(PathBuf::from)?;
+    Some(if dir.is_relative() {
+        cfg_path
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .join(dir)
+    } else {
+        dir
+    })
+}
+
 pub fn init_default_config(dir: &Path, force: bool) -> anyhow::Result<PathBuf> {
     std::fs::create_dir_all(dir)
         .with_context(|| format!("create config dir: {}", dir.display()))?;
@@ -319,32 +1409,164 @@ pub fn init_default_config(dir: &Path, force: bool) -> anyhow::Result<PathBuf> {
     let cfg_text = r#"[pipeline]
 mode = "basic"
  translate_backend = "hy_mt"
+# Fallback chain: the next backend is used when a model file is missing, fails to load,
+# or its chat calls keep failing.
+# translate_backend = ["hy_mt", "translategemma_4b"]
 # In "basic" mode, ONLY translate_backend is used.
 # Switch to mode="full" to enable these additional stages:
 # alt_translate_backend = "hy_mt"
 # rewrite_backend = "translategemma_12b"
 # controller_backend = "gemma3_4b"
+# overlap_controller = true
 
 threads = -1
 gpu_layers = -1
 
 autosave_every = 10
 autosave_suffix = "_进度.docx"
+autosave_keep = 0
 
 trace_dir = "_trace"
 trace_prompts = true
 log_max_chars = 240
 docx_filter_rules = "docx-filter-rules.toml"
 
+# ui_lang = "zh"
+
+# context_overlap = 3
+# context_overlap_max_chars = 1200
+
+# project_dir = "project"
+
+# translate_embedded = false
+
+# ocr_command = "tesseract {image} stdout"
+
+# corpus_strip_tokens = false
+
+# highlight_fallback = false
+# highlight_low_confidence = false
+# highlight_color = "yellow"
+
+# max_repairs = 2
+# max_repairs_token_errors = 6
+# max_repairs_total = 500
+
+# Full mode: stitch audit + patch rounds (stops early once no issue remains).
+# stitch_rounds = 2
+# patch_budget = 50
+# patch_workers = 1
+
+# adaptive_chunks = true
+
+# Full mode: "markers" (one marker per text node), "spans" (styled ranges only) or
+# "paragraph" (one run per paragraph).
+# slot_projection = "markers"
+
+# Number-only units (amounts, dates, currency codes): "copy", "locale" or "translate".
+# numeric_cells = "copy"
+
+# Quotation marks of the translation: "keep", "curly", "guillemets", "corner" or "auto".
+# quote_style = "keep"
+
+# REF field results, quoted heading titles and typed TOC entries follow the translated headings.
+# cross_refs = true
+
+# Let Word refresh a field-generated TOC on open.
+# update_fields = true
+
+# seed = 42
+
+# [pipeline.chunking]
+# max_items = 64
+# char_per_token_ratio = 2.0
+# reserve_tokens = 900
+
+# Casing of the translation: "keep", "capitalize", "sentence" or "title" (en targets).
+# [pipeline.casing]
+# headings = "keep"
+# body = "keep"
+# table_cells = "keep"
+# preserve_all_caps = false
+
+# All-caps words (GDPR, API) are frozen like numbers; allow adds mixed-case ones.
+# [pipeline.acronyms]
+# enabled = true
+# allow = ["SaaS", "iOS"]
+# deny = ["NATO"]
+
+# Full mode stages (all on by default; Translate A always runs).
+# fuse/stitch_audit/patch = "flagged": only units with quality flags.
+# [pipeline.stages]
+# notes = true
+# alt_translate = true
+# fuse = true
+# stitch_audit = true
+# patch = true
+
+# Translate backend per detected language pair (unless --translate-backend/--translate-model is given).
+# [routing]
+# "en->zh" = "hy_mt"
+# "zh->en" = "translategemma_12b"
+# default = "translategemma_4b"
+
+# Pairs without a direct model: translate through an intermediate language (basic mode).
+# [pivot."de->zh"]
+# via = "en"
+# first = "translategemma_4b"   # de->en
+# second = "hy_mt"              # en->zh
+
+# Overrides selected with --profile <name>.
+# [profile.fast.pipeline]
+# max_repairs = 0
+# [profile.quality.pipeline]
+# mode = "full"
+
+# Caps for input packages; inputs beyond them are rejected (zip bomb protection).
+[input]
+# max_entries = 20000
+# max_entry_mb = 1024
+# max_total_mb = 4096
+# placeholder_prefix = "hash"   # hash (of the file) | content (survives re-saves) | fixed prefix
+
+# Regex rewrites of external link targets (word/_rels/*.rels), applied in order at merge.
+# [[links.rewrite]]
+# pattern = "^(https?://)en\\.wikipedia\\.org/"
+# replacement = "${1}zh.wikipedia.org/"
+
+# Run when a translation ends: shell commands (JSON payload on stdin) or webhook URLs (POSTed via curl).
+# [hooks]
+# on_success = "notify-send 'translation done'"
+# on_failure = ["https://hooks.slack.com/services/...", "./on-failure.sh"]
+# timeout_secs = 30
+
+# --from-pdf converters ({input}, {outdir}, {output}); LibreOffice headless by default.
+# [pdf]
+# to_docx = "soffice --headless --infilter=writer_pdf_import --convert-to docx --outdir {outdir} {input}"
+# to_pdf = "soffice --headless --convert-to pdf --outdir {outdir} {input}"
+
+[trace]
+# enabled = true
+max_files = 20000
+max_size_mb = 2048
+bundle_on_failure = true
+bundle_redact = false
+
+# [trace.stages]
+# translate_b = false
+# stitch_audit = true
+
 [prompts]
 translate_a = "prompts/translate_a.txt"
 translate_b = "prompts/translate_b.txt"
 translate_repair = "prompts/translate_repair.txt"
 para_notes = "prompts/para_notes.json.txt"
+key_terms = "prompts/key_terms.txt"
 json_repair = "prompts/json_repair.txt"
 fuse_ab = "prompts/fuse_ab.txt"
 stitch_audit = "prompts/stitch_audit.json.txt"
 patch = "prompts/patch.txt"
+proofread = "prompts/proofread.txt"
 
 [models]
 model_dir = "."
@@ -379,6 +1601,14 @@ offload_kqv = true
 # translate_b = "prompts/backends/translategemma/translate_a.txt"
 # translate_repair = "prompts/backends/translategemma/translate_repair.txt"
 
+# Completion model (no chat template):
+# [models.backends.nllb]
+# path = "nllb-200-distilled-600M.Q8_0.gguf"
+# completion = true
+# add_bos = true
+# add_eos = false
+# stop = ["\n\n"]
+
 [models.backends.translategemma_12b]
 path = "translategemma-12b-it.i1-Q6_K.gguf"
 template_hint = "gemma"
@@ -387,6 +1617,8 @@ gpu_layers = -1
 batch_size = 512
 ubatch_size = 512
 offload_kqv = true
+# use_mmap = true
+# use_mlock = false
 
 [models.backends.gemma3_4b]
 path = "gemma-3-4b-it.Q6_K.gguf"
@@ -396,6 +1628,16 @@ gpu_layers = -1
 batch_size = 512
 ubatch_size = 512
 offload_kqv = true
+# strip_reasoning = true
+# thinking_budget = 0
+
+# Controller stages can be bound too:
+# [models.backends.gemma3_4b.prompts]
+# para_notes = "prompts/backends/gemma3/para_notes.json.txt"
+# fuse_ab = "prompts/backends/gemma3/fuse_ab.txt"
+# stitch_audit = "prompts/backends/gemma3/stitch_audit.json.txt"
+# json_repair = "prompts/backends/gemma3/json_repair.txt"
+# patch = "prompts/backends/gemma3/patch.txt"
 "#;
 
     std::fs::write(&cfg_path, cfg_text)
@@ -403,7 +1645,9 @@ offload_kqv = true
     Ok(cfg_path)
 }
 
-const DEFAULT_DOCX_FILTER_RULES_TOML: &str = r#"version = 1
+/// `docx-filter-rules.toml` of `--init-config`; `--copy-source` applies it when
+/// `pipeline.docx_filter_rules` is unset.
+pub(super) const DEFAULT_DOCX_FILTER_RULES_TOML: &str = r#"version = 1
 
 # ---------------------
 # Revision/meta cleanup
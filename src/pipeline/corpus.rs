@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::Context;
+use serde::Serialize;
+
+use crate::docx::pure_text::{ParaContainer, PureTextJson};
+use crate::freezer::unfreeze_text;
+use crate::ir::TranslationUnit;
+use crate::sentinels::{ANY_MT_TOKEN_RE, BR, NBH, SHY, TAB};
+
+/// One line of `<stem>.aligned.jsonl`.
+#[derive(Debug, Serialize)]
+struct AlignedRecord<'a> {
+    source: String,
+    target: String,
+    scope_key: &'a str,
+    container: Option<ParaContainer>,
+    qe_flags: &'a [String],
+}
+
+/// Write the translated units of a run as JSONL (source, target, scope_key, container,
+/// qe_flags), skipping units without a translation. `text` maps DOCX paragraph scope keys to
+/// their container; other inputs have none. Returns the number of lines written.
+pub fn write_aligned_corpus(
+    path: &Path,
+    tus: &[TranslationUnit],
+    text: Option<&PureTextJson>,
+    strip_tokens: bool,
+) -> anyhow::Result<usize> {
+    let containers: HashMap<&str, ParaContainer> = text
+        .map(|t| t.paragraphs.as_slice())
+        .unwrap_or_default()
+        .iter()
+        .map(|p| (p.scope_key.as_str(), p.container))
+        .collect();
+    let clean = |s: String| if strip_tokens { strip_mt_tokens(&s) } else { s };
+
+    let mut out = String::new();
+    let mut count = 0usize;
+    for tu in tus {
+        let Some(target) = tu
+            .final_translation
+            .as_deref()
+            .or(tu.draft_translation.as_deref())
+        else {
+            continue;
+        };
+        let source = clean(tu.source_surface.clone());
+        let target = clean(unfreeze_text(target, &tu.nt_map));
+        if source.trim().is_empty() || target.trim().is_empty() {
+            continue;
+        }
+        let rec = AlignedRecord {
+            source,
+            target,
+            scope_key: &tu.scope_key,
+            container: containers.get(tu.scope_key.as_str()).copied(),
+            qe_flags: &tu.qe_flags,
+        };
+        out.push_str(&serde_json::to_string(&rec).context("serialize aligned record")?);
+        out.push('\n');
+        count += 1;
+    }
+    fs::write(path, out).with_context(|| format!("write aligned corpus: {}", path.display()))?;
+    Ok(count)
+}
+
+/// Control tokens become their plain characters; any other `<<MT_...>>` token is dropped.
+fn strip_mt_tokens(text: &str) -> String {
+    let text = text
+        .replace(TAB, "\t")
+        .replace(BR, "\n")
+        .replace(NBH, "\u{2011}")
+        .replace(SHY, "");
+    ANY_MT_TOKEN_RE.replace_all(&text, "").into_owned()
+}
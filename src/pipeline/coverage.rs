@@ -0,0 +1,117 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use anyhow::Context;
+use serde::Serialize;
+
+use crate::docx::decompose::OffsetsJson;
+use crate::docx::pure_text::PureTextJson;
+use crate::docx::unsupported::UnsupportedContent;
+
+/// `<stem>.coverage.json`: per package part, how many text slots the output translates.
+#[derive(Debug, Default, Serialize)]
+pub struct CoverageReport {
+    pub slots: usize,
+    pub translated: usize,
+    pub parts: Vec<PartCoverage>,
+    /// SmartArt, charts, embedded objects and images, which have no slots to translate.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unsupported: Vec<UnsupportedContent>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PartCoverage {
+    pub part_name: String,
+    /// `body`, `header`, `footnotes`, `diagram`, ... (see `part_kind`).
+    pub kind: &'static str,
+    /// Slots with text to translate (letters, not just digits or punctuation).
+    pub slots: usize,
+    pub translated: usize,
+    /// Slots whose output text is still the source text.
+    pub unchanged: usize,
+}
+
+/// Coverage of `output` (the merged text.json) against the extracted `source`. A slot counts as
+/// translated when its output text differs from the source, or when slot projection collapsed
+/// it into a neighbour. Parts are listed in document order.
+pub fn slot_coverage(
+    offsets: &OffsetsJson,
+    source: &PureTextJson,
+    output: &PureTextJson,
+) -> CoverageReport {
+    let collapsed: HashSet<usize> = output.collapsed_slots.iter().copied().collect();
+    let mut report = CoverageReport::default();
+    // Slot ids are 1-based and `slot_texts` follows the offsets order.
+    for (i, slot) in offsets.slots.iter().enumerate() {
+        let Some(src) = source.slot_texts.get(i) else {
+            continue;
+        };
+        if !src.chars().any(char::is_alphabetic) {
+            continue;
+        }
+        let out = output.slot_texts.get(i).unwrap_or(src);
+        let translated = collapsed.contains(&slot.id) || out != src;
+
+        let part = match report
+            .parts
+            .iter_mut()
+            .position(|p| p.part_name == slot.part_name)
+        {
+            Some(i) => &mut report.parts[i],
+            None => {
+                report.parts.push(PartCoverage {
+                    part_name: slot.part_name.clone(),
+                    kind: part_kind(&slot.part_name),
+                    slots: 0,
+                    translated: 0,
+                    unchanged: 0,
+                });
+                report.parts.last_mut().expect("part just pushed")
+            }
+        };
+        part.slots += 1;
+        report.slots += 1;
+        if translated {
+            part.translated += 1;
+            report.translated += 1;
+        } else {
+            part.unchanged += 1;
+        }
+    }
+    report
+}
+
+/// What a package part holds, for the coverage report.
+pub fn part_kind(part_name: &str) -> &'static str {
+    let file = part_name.rsplit('/').next().unwrap_or(part_name);
+    if part_name == "word/document.xml" {
+        "body"
+    } else if file.starts_with("header") {
+        "header"
+    } else if file.starts_with("footer") {
+        "footer"
+    } else if file.starts_with("footnotes") {
+        "footnotes"
+    } else if file.starts_with("endnotes") {
+        "endnotes"
+    } else if file.starts_with("comments") {
+        "comments"
+    } else if part_name.contains("/diagrams/") {
+        "diagram"
+    } else if part_name.contains("/charts/") {
+        "chart"
+    } else if part_name.starts_with("docProps/") {
+        "properties"
+    } else {
+        "other"
+    }
+}
+
+pub fn write_coverage_report(path: &Path, report: &CoverageReport) -> anyhow::Result<()> {
+    fs::write(
+        path,
+        serde_json::to_vec_pretty(report).context("serialize coverage report")?,
+    )
+    .with_context(|| format!("write coverage report: {}", path.display()))
+}
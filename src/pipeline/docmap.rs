@@ -1,13 +1,14 @@
 use std::collections::HashMap;
-use std::path::Path;
 
-use anyhow::{anyhow, Context};
+use anyhow::anyhow;
 
 use crate::docx::decompose::{OffsetsJson, SlotKind};
-use crate::docx::package::DocxPackage;
 use crate::docx::pure_text::PureTextJson;
-use crate::docx::xml::{parse_xml_part, XmlEvent};
-use crate::sentinels::slot_token;
+use crate::docx::session::DocumentSession;
+use crate::docx::xml::{XmlEvent, XmlName};
+use crate::sentinels::{em_end, em_start, slot_token};
+
+use super::config::SlotProjection;
 
 #[derive(Clone, Debug)]
 pub struct ParaSlotUnit {
@@ -16,11 +17,231 @@ pub struct ParaSlotUnit {
     pub scope_key: String,
     pub para_style: Option<String>,
     pub slot_ids: Vec<usize>,
+    /// Run style of each slot, parallel to `slot_ids`: the run's `w:rPr` plus the hyperlink
+    /// around it.
+    pub slot_styles: Vec<String>,
+    /// Footnote/endnote reference marks with text slots on both sides: `(slot before, slot
+    /// after)`.
+    pub note_refs: Vec<(usize, usize)>,
+    /// Bookmarks starting in the paragraph (or right before it, between paragraphs).
+    pub bookmarks: Vec<String>,
+    /// `REF` fields whose cached result lies in the paragraph.
+    pub ref_fields: Vec<RefField>,
+    /// The paragraph's text lies in the result of a `TOC` field (a generated table of contents).
+    pub in_toc_field: bool,
     pub source_surface: String,
 }
 
+/// A `REF` field showing a bookmark's text (not its number or position).
+#[derive(Clone, Debug)]
+pub struct RefField {
+    pub bookmark: String,
+    /// Slots of the field's cached result.
+    pub slot_ids: Vec<usize>,
+}
+
+/// A field being read: its instruction and, once past `separate`, the result slots.
+struct OpenField {
+    instr: String,
+    para: Option<usize>,
+    in_result: bool,
+    slot_ids: Vec<usize>,
+}
+
+impl OpenField {
+    fn is_toc(&self) -> bool {
+        self.in_result
+            && self
+                .instr
+                .split_whitespace()
+                .next()
+                .is_some_and(|w| w.eq_ignore_ascii_case("TOC"))
+    }
+
+    /// The `REF` field this is, if it shows the bookmarked text (`\n`, `\r`, `\w` and `\p` show
+    /// numbers or positions instead).
+    fn into_ref_field(self) -> Option<(usize, RefField)> {
+        let mut words = self.instr.split_whitespace();
+        if !words.next()?.eq_ignore_ascii_case("REF") {
+            return None;
+        }
+        let bookmark = words.next()?.to_string();
+        let numbered = words.any(|w| {
+            ["\\n", "\\r", "\\w", "\\p"]
+                .iter()
+                .any(|s| w.eq_ignore_ascii_case(s))
+        });
+        if numbered || bookmark.starts_with('\\') || self.slot_ids.is_empty() {
+            return None;
+        }
+        Some((
+            self.para?,
+            RefField {
+                bookmark,
+                slot_ids: self.slot_ids,
+            },
+        ))
+    }
+}
+
+/// `pipeline.slot_projection = "spans"`: a paragraph's slots grouped into runs of one style.
+#[derive(Clone, Debug)]
+pub struct SpanLayout {
+    pub groups: Vec<SpanGroup>,
+    /// `"paragraph"`: slots dropped from the output; the whole text goes to the one group.
+    pub collapsed: Vec<usize>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SpanGroup {
+    /// Style id of the group's `<<MT_EM:nn>>` pair (`None`: the paragraph's base style, unmarked).
+    pub style_id: Option<usize>,
+    pub slot_ids: Vec<usize>,
+    /// Source chars of each slot; the group's translation is split across them in proportion.
+    pub weights: Vec<usize>,
+}
+
+/// Style ids that fit the two-digit `<<MT_EM:nn>>` markers.
+const MAX_EM_STYLES: usize = 99;
+
+impl ParaSlotUnit {
+    /// Source surface and layout for a non-marker `pipeline.slot_projection`; `None` keeps the
+    /// slot-marker surface.
+    pub fn projected_surface(
+        &self,
+        projection: SlotProjection,
+        slot_texts: &[String],
+    ) -> Option<(String, SpanLayout)> {
+        match projection {
+            SlotProjection::Markers => None,
+            SlotProjection::Spans => self.span_surface(slot_texts),
+            SlotProjection::Paragraph => self.paragraph_surface(slot_texts),
+        }
+    }
+
+    /// Source surface for span projection: the paragraph text without slot markers, with text in
+    /// other styles than the dominant one wrapped in `<<MT_EM:nn>>`...`<<MT_EM_END:nn>>` (one id
+    /// per style). `None` for paragraphs without slots or with more styles than ids.
+    pub fn span_surface(&self, slot_texts: &[String]) -> Option<(String, SpanLayout)> {
+        if self.slot_ids.is_empty() {
+            return None;
+        }
+        let slot_text = |slot_id: usize| slot_text(slot_texts, slot_id);
+        let chars_by_style = self.chars_by_style(slot_texts);
+        if chars_by_style.len() > MAX_EM_STYLES + 1 {
+            return None;
+        }
+        // Base style: most text; the others are numbered in order.
+        let base = dominant_style(&chars_by_style);
+        let style_ids: HashMap<&str, usize> = chars_by_style
+            .iter()
+            .map(|(style, _)| *style)
+            .filter(|style| *style != base)
+            .enumerate()
+            .map(|(i, style)| (style, i + 1))
+            .collect();
+
+        let mut groups: Vec<SpanGroup> = Vec::new();
+        for (slot_id, style) in self.slot_ids.iter().zip(&self.slot_styles) {
+            let style_id = style_ids.get(style.as_str()).copied();
+            let n = slot_text(*slot_id).chars().count();
+            match groups.last_mut() {
+                Some(g) if g.style_id == style_id => {
+                    g.slot_ids.push(*slot_id);
+                    g.weights.push(n);
+                }
+                _ => groups.push(SpanGroup {
+                    style_id,
+                    slot_ids: vec![*slot_id],
+                    weights: vec![n],
+                }),
+            }
+        }
+
+        let mut surface = String::new();
+        for g in &groups {
+            let text: String = g.slot_ids.iter().map(|id| slot_text(*id)).collect();
+            match g.style_id {
+                Some(id) => {
+                    surface.push_str(&em_start(id));
+                    surface.push_str(&text);
+                    surface.push_str(&em_end(id));
+                }
+                None => surface.push_str(&text),
+            }
+        }
+        Some((
+            surface,
+            SpanLayout {
+                groups,
+                collapsed: Vec::new(),
+            },
+        ))
+    }
+
+    /// Source surface for whole-paragraph replacement: the paragraph text without any markers.
+    /// The layout sends the translation to the first slot in the dominant style and collapses
+    /// the others. `None` for paragraphs without slots.
+    pub fn paragraph_surface(&self, slot_texts: &[String]) -> Option<(String, SpanLayout)> {
+        let base = dominant_style(&self.chars_by_style(slot_texts));
+        let keep = self
+            .slot_ids
+            .iter()
+            .zip(&self.slot_styles)
+            .find(|(_, style)| style.as_str() == base)
+            .map(|(slot_id, _)| *slot_id)?;
+        let surface: String = self
+            .slot_ids
+            .iter()
+            .map(|id| slot_text(slot_texts, *id))
+            .collect();
+        let collapsed = self
+            .slot_ids
+            .iter()
+            .copied()
+            .filter(|id| *id != keep)
+            .collect();
+        let groups = vec![SpanGroup {
+            style_id: None,
+            slot_ids: vec![keep],
+            weights: vec![1],
+        }];
+        Some((surface, SpanLayout { groups, collapsed }))
+    }
+
+    /// Source chars per run style, in order of first appearance.
+    fn chars_by_style(&self, slot_texts: &[String]) -> Vec<(&str, usize)> {
+        let mut chars_by_style: Vec<(&str, usize)> = Vec::new();
+        for (slot_id, style) in self.slot_ids.iter().zip(&self.slot_styles) {
+            let n = slot_text(slot_texts, *slot_id).chars().count();
+            match chars_by_style.iter_mut().find(|(s, _)| s == style) {
+                Some((_, total)) => *total += n,
+                None => chars_by_style.push((style, n)),
+            }
+        }
+        chars_by_style
+    }
+}
+
+fn slot_text(slot_texts: &[String], slot_id: usize) -> &str {
+    slot_texts
+        .get(slot_id.saturating_sub(1))
+        .map(String::as_str)
+        .unwrap_or("")
+}
+
+/// The style with the most text (the first one on ties).
+fn dominant_style<'a>(chars_by_style: &[(&'a str, usize)]) -> &'a str {
+    chars_by_style
+        .iter()
+        .enumerate()
+        .max_by(|(ia, a), (ib, b)| a.1.cmp(&b.1).then_with(|| ib.cmp(ia)))
+        .map(|(_, (style, _))| *style)
+        .unwrap_or("")
+}
+
 pub fn build_para_slot_units(
-    docx_path: &Path,
+    session: &DocumentSession,
     text: &PureTextJson,
     offsets: &OffsetsJson,
 ) -> anyhow::Result<Vec<ParaSlotUnit>> {
@@ -34,6 +255,11 @@ pub fn build_para_slot_units(
             scope_key: p.scope_key.clone(),
             para_style: p.p_style.clone(),
             slot_ids: Vec::new(),
+            slot_styles: Vec::new(),
+            note_refs: Vec::new(),
+            bookmarks: Vec::new(),
+            ref_fields: Vec::new(),
+            in_toc_field: false,
             source_surface: String::new(),
         });
     }
@@ -46,19 +272,114 @@ pub fn build_para_slot_units(
         );
     }
 
-    let pkg = DocxPackage::read(docx_path)?;
-    for ent in pkg.xml_entries() {
-        if ent.data.is_empty() {
-            continue;
-        }
-        let part = parse_xml_part(&ent.name, &ent.data)
-            .with_context(|| format!("parse xml: {}", ent.name))?;
-
-        let mut stack: Vec<String> = Vec::new();
+    for part in session.xml_parts() {
+        let mut stack: Vec<XmlName> = Vec::new();
         let mut cur_para_idx: Option<usize> = None;
         let mut nested_para_depth: usize = 0;
+        // Style of the current run: its `w:rPr` children, prefixed by the enclosing hyperlink.
+        let mut run_style = String::new();
+        let mut link = String::new();
+        // A note reference was seen since the paragraph's last text slot.
+        let mut note_ref = false;
+        let mut fields: Vec<OpenField> = Vec::new();
+        let mut pending_bookmarks: Vec<String> = Vec::new();
 
         for (idx, ev) in part.events.iter().enumerate() {
+            let in_rpr = stack.last().is_some_and(|n| n == "w:rPr")
+                && stack.iter().rev().nth(1).is_some_and(|n| n == "w:r");
+            match ev {
+                XmlEvent::Start { name, attrs } | XmlEvent::Empty { name, attrs } if in_rpr => {
+                    run_style.push_str(name);
+                    for (k, v) in attrs {
+                        run_style.push_str(&format!(" {k}={v}"));
+                    }
+                    run_style.push(';');
+                }
+                _ => {}
+            }
+            match ev {
+                XmlEvent::Start { name, attrs } if name == "w:hyperlink" => {
+                    let target: Vec<String> = attrs
+                        .iter()
+                        .filter(|(k, _)| k == "r:id" || k == "w:anchor")
+                        .map(|(k, v)| format!("{k}={v}"))
+                        .collect();
+                    link = format!("link({})|", target.join(" "));
+                }
+                XmlEvent::End { name } if name == "w:hyperlink" => link.clear(),
+                XmlEvent::Start { name, .. } if name == "w:r" => run_style.clear(),
+                XmlEvent::Start { name, .. } | XmlEvent::Empty { name, .. }
+                    if name == "w:footnoteReference" || name == "w:endnoteReference" =>
+                {
+                    note_ref = cur_para_idx.is_some() && nested_para_depth == 0;
+                }
+                XmlEvent::Start { name, attrs } | XmlEvent::Empty { name, attrs }
+                    if name == "w:bookmarkStart" =>
+                {
+                    if let Some((_, v)) = attrs.iter().find(|(k, _)| k == "w:name") {
+                        match cur_para_idx {
+                            Some(pi) if nested_para_depth == 0 => {
+                                units[pi].bookmarks.push(v.clone())
+                            }
+                            Some(_) => {}
+                            None => pending_bookmarks.push(v.clone()),
+                        }
+                    }
+                }
+                XmlEvent::Start { name, attrs } | XmlEvent::Empty { name, attrs }
+                    if name == "w:fldChar" =>
+                {
+                    let kind = attrs
+                        .iter()
+                        .find(|(k, _)| k == "w:fldCharType")
+                        .map(|(_, v)| v.as_str());
+                    match kind {
+                        Some("begin") => fields.push(OpenField {
+                            instr: String::new(),
+                            para: cur_para_idx,
+                            in_result: false,
+                            slot_ids: Vec::new(),
+                        }),
+                        Some("separate") => {
+                            if let Some(f) = fields.last_mut() {
+                                f.in_result = true;
+                            }
+                        }
+                        Some("end") => {
+                            if let Some((pi, field)) =
+                                fields.pop().and_then(OpenField::into_ref_field)
+                            {
+                                units[pi].ref_fields.push(field);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                XmlEvent::Start { name, attrs } if name == "w:fldSimple" => {
+                    let instr = attrs
+                        .iter()
+                        .find(|(k, _)| k == "w:instr")
+                        .map(|(_, v)| v.clone())
+                        .unwrap_or_default();
+                    fields.push(OpenField {
+                        instr,
+                        para: cur_para_idx,
+                        in_result: true,
+                        slot_ids: Vec::new(),
+                    });
+                }
+                XmlEvent::End { name } if name == "w:fldSimple" => {
+                    if let Some((pi, field)) = fields.pop().and_then(OpenField::into_ref_field) {
+                        units[pi].ref_fields.push(field);
+                    }
+                }
+                XmlEvent::Text { text } if stack.last().is_some_and(|n| n == "w:instrText") => {
+                    if let Some(f) = fields.last_mut().filter(|f| !f.in_result) {
+                        f.instr.push_str(text);
+                    }
+                }
+                _ => {}
+            }
             match ev {
                 XmlEvent::Start { name, .. } => {
                     if name == "w:p" {
@@ -67,6 +388,10 @@ pub fn build_para_slot_units(
                         } else {
                             cur_para_idx = para_index.get(&(part.name.clone(), idx)).copied();
                             nested_para_depth = 0;
+                            note_ref = false;
+                            if let Some(pi) = cur_para_idx {
+                                units[pi].bookmarks.append(&mut pending_bookmarks);
+                            }
                         }
                     }
                     stack.push(name.clone());
@@ -109,7 +434,24 @@ pub fn build_para_slot_units(
                         .slot_texts
                         .get(slot_id.saturating_sub(1))
                         .ok_or_else(|| anyhow!("missing slot_texts for slot_id={slot_id}"))?;
+                    if std::mem::take(&mut note_ref) {
+                        if let Some(&before) = units[pi].slot_ids.last() {
+                            units[pi].note_refs.push((before, slot_id));
+                        }
+                    }
+                    // Results spanning paragraphs are left alone.
+                    if let Some(f) = fields.last_mut().filter(|f| f.in_result) {
+                        if f.para == Some(pi) {
+                            f.slot_ids.push(slot_id);
+                        } else {
+                            f.para = None;
+                        }
+                    }
+                    if fields.iter().any(OpenField::is_toc) {
+                        units[pi].in_toc_field = true;
+                    }
                     units[pi].slot_ids.push(slot_id);
+                    units[pi].slot_styles.push(format!("{link}{run_style}"));
                     units[pi].source_surface.push_str(&slot_token(slot_id));
                     units[pi].source_surface.push_str(slot_text);
                 }
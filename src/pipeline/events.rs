@@ -0,0 +1,32 @@
+use std::path::PathBuf;
+
+/// What the pipeline reports while it runs, delivered to the `TranslatorPipeline::with_events`
+/// receiver. The console progress lines are rendered from the same events.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PipelineEvent {
+    /// A stage (`translate_a`, `para_notes`, `fuse`, `stitch_audit`, `patch`, `qa_sample`, a
+    /// custom stage's name, ...) starts.
+    StageStarted { stage: String },
+    /// Units of a translate stage were committed: a model chunk, or a unit passed through
+    /// without a model call. `done` of `total` units of the stage are committed.
+    ChunkDone {
+        stage: String,
+        ids: Vec<usize>,
+        done: usize,
+        total: usize,
+    },
+    /// A translated unit passed validation (after repairs), or fell back to its source.
+    TuValidated {
+        tu_id: usize,
+        source_fallback: bool,
+        qe_flags: Vec<String>,
+    },
+    /// A repair call for `tu_id`; `reason` is the validation error or the quality flags.
+    RepairAttempt { tu_id: usize, reason: String },
+    /// The progress DOCX was rewritten after `done` of `total` units.
+    Autosave {
+        path: PathBuf,
+        done: usize,
+        total: usize,
+    },
+}
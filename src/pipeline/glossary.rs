@@ -0,0 +1,122 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::Context;
+use serde::Serialize;
+
+use crate::terminology::TermDecision;
+use crate::textutil::strip_sentinels;
+
+/// `<stem>.glossary.json`: which project glossary terms the document uses and how often the
+/// translation follows them.
+#[derive(Debug, Default, Serialize)]
+pub struct GlossaryReport {
+    /// Terms in the project glossary for this language pair.
+    pub glossary_terms: usize,
+    /// Terms found at least once in the source.
+    pub terms_found: usize,
+    pub occurrences: usize,
+    pub compliant: usize,
+    /// Found terms, least followed first.
+    pub terms: Vec<TermHits>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TermHits {
+    pub src: String,
+    pub tgt: String,
+    /// Occurrences of `src` in the source segments.
+    pub occurrences: usize,
+    /// Occurrences matched by `tgt` in the translation of the same segment.
+    pub compliant: usize,
+    /// `compliant / occurrences`.
+    pub rate: f64,
+    /// Units whose translation misses at least one occurrence.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub missed_tu_ids: Vec<usize>,
+}
+
+impl GlossaryReport {
+    /// Share of term occurrences the translation follows (1.0 when no term occurs).
+    pub fn rate(&self) -> f64 {
+        if self.occurrences == 0 {
+            1.0
+        } else {
+            self.compliant as f64 / self.occurrences as f64
+        }
+    }
+}
+
+/// Count glossary hits over the final `(tu_id, source, translation)` pairs. Within a segment,
+/// each source occurrence is matched by at most one occurrence of the target term (compared
+/// case-insensitively, so sentence-initial capitals still count).
+pub fn glossary_hits<'a>(
+    terms: impl Iterator<Item = &'a TermDecision>,
+    pairs: &[(usize, String, String)],
+) -> GlossaryReport {
+    let pairs: Vec<(usize, String, String)> = pairs
+        .iter()
+        .map(|(id, src, tgt)| {
+            (
+                *id,
+                strip_sentinels(src),
+                strip_sentinels(tgt).to_lowercase(),
+            )
+        })
+        .collect();
+    let mut report = GlossaryReport::default();
+    for term in terms {
+        report.glossary_terms += 1;
+        if term.src.trim().is_empty() {
+            continue;
+        }
+        let tgt = term.tgt.to_lowercase();
+        let mut hits = TermHits {
+            src: term.src.clone(),
+            tgt: term.tgt.clone(),
+            occurrences: 0,
+            compliant: 0,
+            rate: 0.0,
+            missed_tu_ids: Vec::new(),
+        };
+        for (tu_id, source, translation) in &pairs {
+            let found = source.matches(term.src.as_str()).count();
+            if found == 0 {
+                continue;
+            }
+            let followed = if tgt.is_empty() {
+                0
+            } else {
+                translation.matches(tgt.as_str()).count().min(found)
+            };
+            hits.occurrences += found;
+            hits.compliant += followed;
+            if followed < found {
+                hits.missed_tu_ids.push(*tu_id);
+            }
+        }
+        if hits.occurrences == 0 {
+            continue;
+        }
+        hits.rate = hits.compliant as f64 / hits.occurrences as f64;
+        report.terms_found += 1;
+        report.occurrences += hits.occurrences;
+        report.compliant += hits.compliant;
+        report.terms.push(hits);
+    }
+    report.terms.sort_by(|a, b| {
+        a.rate
+            .total_cmp(&b.rate)
+            .then_with(|| b.occurrences.cmp(&a.occurrences))
+            .then_with(|| a.src.cmp(&b.src))
+    });
+    report
+}
+
+pub fn write_glossary_report(path: &Path, report: &GlossaryReport) -> anyhow::Result<()> {
+    fs::write(
+        path,
+        serde_json::to_vec_pretty(report).context("serialize glossary report")?,
+    )
+    .with_context(|| format!("write glossary report: {}", path.display()))
+}
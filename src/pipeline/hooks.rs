@@ -0,0 +1,175 @@
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use anyhow::{anyhow, Context};
+use serde::Serialize;
+
+use crate::errors::ErrorReport;
+use crate::ir::TranslationUnit;
+
+/// `[hooks]` of the config that applies to a run.
+#[derive(Clone, Debug, Default)]
+pub struct Hooks {
+    pub on_success: Vec<String>,
+    pub on_failure: Vec<String>,
+    pub timeout_secs: u64,
+}
+
+/// Unit counts and validation outcomes of a finished run (from the units of its aligned corpus).
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct RunStats {
+    pub units: usize,
+    pub translated: usize,
+    /// Units with any quality flag.
+    pub flagged: usize,
+    pub repaired: usize,
+    pub forced_retranslate: usize,
+    pub source_fallback: usize,
+    /// Units whose translation could not be projected onto their text slots at least once.
+    pub projection_failed: usize,
+    /// Repairs served from the run's repair cache instead of a model call.
+    pub repairs_reused: usize,
+}
+
+impl RunStats {
+    pub fn from_units(tus: &[TranslationUnit]) -> Self {
+        let has = |tu: &TranslationUnit, flag: &str| tu.qe_flags.iter().any(|f| f == flag);
+        Self {
+            units: tus.len(),
+            translated: tus
+                .iter()
+                .filter(|tu| tu.final_translation.is_some() || tu.draft_translation.is_some())
+                .count(),
+            flagged: tus.iter().filter(|tu| !tu.qe_flags.is_empty()).count(),
+            repaired: tus
+                .iter()
+                .filter(|tu| tu.qe_flags.iter().any(|f| f.starts_with("repairs:")))
+                .count(),
+            forced_retranslate: tus
+                .iter()
+                .filter(|tu| has(tu, "forced_retranslate"))
+                .count(),
+            source_fallback: tus.iter().filter(|tu| has(tu, "source_fallback")).count(),
+            projection_failed: tus
+                .iter()
+                .filter(|tu| has(tu, "slot_projection_failed"))
+                .count(),
+            repairs_reused: 0,
+        }
+    }
+}
+
+/// JSON sent to every hook (stdin of commands, body of webhooks).
+#[derive(Debug, Serialize)]
+pub struct HookPayload<'a> {
+    /// "success" or "failure".
+    pub event: &'static str,
+    pub input: &'a Path,
+    pub output: &'a Path,
+    pub duration_secs: f64,
+    pub stats: Option<&'a RunStats>,
+    pub error: Option<&'a ErrorReport>,
+}
+
+impl Hooks {
+    /// Run the hooks for `payload.event` in order. Failures do not stop the remaining hooks; they
+    /// are returned as (target, error) for the caller to report, webhook URLs cut to their host
+    /// (Slack-style URLs carry their secret in the path).
+    pub fn run(&self, payload: &HookPayload) -> Vec<(String, anyhow::Error)> {
+        let targets = match payload.event {
+            "success" => &self.on_success,
+            _ => &self.on_failure,
+        };
+        if targets.is_empty() {
+            return Vec::new();
+        }
+        let body = match serde_json::to_vec(payload).context("serialize hook payload") {
+            Ok(body) => body,
+            Err(err) => return vec![(String::from("(payload)"), err)],
+        };
+        targets
+            .iter()
+            .filter_map(|target| {
+                if is_webhook(target) {
+                    post_webhook(target, &body, self.timeout_secs)
+                        .err()
+                        .map(|err| (webhook_label(target), err))
+                } else {
+                    run_command(target, payload, &body)
+                        .err()
+                        .map(|err| (target.clone(), err))
+                }
+            })
+            .collect()
+    }
+}
+
+fn is_webhook(target: &str) -> bool {
+    let lower = target.to_ascii_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://")
+}
+
+fn webhook_label(url: &str) -> String {
+    let rest_at = url.find("://").map(|i| i + 3).unwrap_or(0);
+    match url[rest_at..].find('/') {
+        Some(i) => format!("{}/...", &url[..rest_at + i]),
+        None => url.to_string(),
+    }
+}
+
+/// `command` run through the platform shell (`sh -c` / `cmd /C`).
+pub(super) fn shell_command(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut c = Command::new("cmd");
+        c.arg("/C").arg(command);
+        c
+    } else {
+        let mut c = Command::new("sh");
+        c.arg("-c").arg(command);
+        c
+    }
+}
+
+/// Shell command; the payload arrives on stdin, event/input/output also as environment variables.
+fn run_command(command: &str, payload: &HookPayload, body: &[u8]) -> anyhow::Result<()> {
+    let mut child = shell_command(command)
+        .env("MUGGLE_HOOK_EVENT", payload.event)
+        .env("MUGGLE_HOOK_INPUT", payload.input)
+        .env("MUGGLE_HOOK_OUTPUT", payload.output)
+        .stdin(Stdio::piped())
+        .spawn()
+        .context("spawn hook command")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A command that ignores its stdin may exit first; that is not a failure.
+        let _ = stdin.write_all(body);
+    }
+    let status = child.wait().context("wait for hook command")?;
+    if !status.success() {
+        return Err(anyhow!("hook command exited with {status}"));
+    }
+    Ok(())
+}
+
+/// POST the payload with `curl` (no HTTP client is linked into the binary).
+fn post_webhook(url: &str, body: &[u8], timeout_secs: u64) -> anyhow::Result<()> {
+    let mut child = Command::new("curl")
+        .args(["-sS", "-f", "-o"])
+        .arg(if cfg!(windows) { "NUL" } else { "/dev/null" })
+        .arg("--max-time")
+        .arg(timeout_secs.max(1).to_string())
+        .args(["-X", "POST", "-H", "Content-Type: application/json"])
+        .args(["--data-binary", "@-"])
+        .arg(url)
+        .stdin(Stdio::piped())
+        .spawn()
+        .context("spawn curl (webhooks need curl on PATH)")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(body).context("send webhook payload")?;
+    }
+    let status = child.wait().context("wait for curl")?;
+    if !status.success() {
+        return Err(anyhow!("webhook request failed (curl {status})"));
+    }
+    Ok(())
+}
@@ -0,0 +1,228 @@
+//! `--list-backends` / `--list-prompts`: show what the active config would actually use.
+
+use std::path::{Path, PathBuf};
+
+use crate::config::{load_config, resolve_backend, AppConfig, BackendChain};
+use crate::pipeline::config::{input_workdir, locate_config_file, PipelineMode};
+use crate::pipeline::prompts::{default_prompt_paths, override_prompt_paths};
+
+struct ActiveConfig {
+    path: Option<PathBuf>,
+    workdir: PathBuf,
+    cfg: AppConfig,
+}
+
+impl ActiveConfig {
+    fn load(
+        input: Option<&Path>,
+        config_path: Option<&Path>,
+        profile: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        let workdir = match input {
+            Some(p) => input_workdir(p),
+            None => std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+        };
+        let path =
+            locate_config_file(&workdir, config_path.map(Path::to_path_buf)).filter(|p| p.exists());
+        let cfg = match path.as_ref() {
+            Some(p) => load_config(p, profile)?,
+            None => AppConfig::default(),
+        };
+        Ok(Self { path, workdir, cfg })
+    }
+
+    fn config_path(&self) -> PathBuf {
+        self.path
+            .clone()
+            .unwrap_or_else(|| self.workdir.join("muggle-translator.toml"))
+    }
+
+    fn config_dir(&self) -> PathBuf {
+        self.config_path()
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from("."))
+    }
+
+    fn header(&self) -> String {
+        match self.path.as_ref() {
+            Some(p) => format!("config: {}\n", p.display()),
+            None => "config: (none found; built-in defaults)\n".to_string(),
+        }
+    }
+
+    /// Pipeline roles each backend is selected for (same defaults as `PipelineConfig`).
+    fn roles(&self, name: &str) -> Vec<&'static str> {
+        let p = &self.cfg.pipeline;
+        let full = PipelineMode::parse(p.mode.as_deref()) == PipelineMode::Full;
+        let pick = |v: &Option<String>| {
+            v.as_deref()
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+        };
+        let mut roles = Vec::new();
+        let mut translate_chain = p
+            .translate_backend
+            .as_ref()
+            .map(BackendChain::names)
+            .unwrap_or_default();
+        if translate_chain.is_empty() {
+            translate_chain.push("translategemma_4b".to_string());
+        }
+        match translate_chain.iter().position(|n| n == name) {
+            Some(0) => roles.push("translate"),
+            Some(_) => roles.push("translate_fallback"),
+            None => {}
+        }
+        if full {
+            if pick(&p.alt_translate_backend).as_deref() == Some(name) {
+                roles.push("alt_translate");
+            }
+            if pick(&p.rewrite_backend)
+                .as_deref()
+                .unwrap_or("translategemma_12b")
+                == name
+            {
+                roles.push("rewrite");
+            }
+            if pick(&p.controller_backend).as_deref() == Some(name) {
+                roles.push("controller");
+            }
+        }
+        roles
+    }
+}
+
+/// One row per configured backend: roles, whether the model file resolves, ctx and template
+/// (`(completion)` for raw-prompt backends).
+pub fn list_backends(
+    input: Option<&Path>,
+    config_path: Option<&Path>,
+    profile: Option<&str>,
+) -> anyhow::Result<String> {
+    let active = ActiveConfig::load(input, config_path, profile)?;
+    let cfg_path = active.config_path();
+    let mut names: Vec<&String> = active.cfg.models.backends.keys().collect();
+    names.sort();
+
+    let mut rows: Vec<[String; 6]> = vec![[
+        "NAME".to_string(),
+        "ROLE".to_string(),
+        "EXISTS".to_string(),
+        "CTX".to_string(),
+        "TEMPLATE".to_string(),
+        "MODEL".to_string(),
+    ]];
+    for name in names {
+        let b = &active.cfg.models.backends[name];
+        let (exists, model) =
+            match resolve_backend(&active.cfg, &cfg_path, name, &active.workdir, &[], 0, None) {
+                Ok(r) => ("yes", r.model_path),
+                Err(_) => ("no", b.path.clone()),
+            };
+        let roles = active.roles(name);
+        rows.push([
+            name.clone(),
+            if roles.is_empty() {
+                "-".to_string()
+            } else {
+                roles.join(",")
+            },
+            exists.to_string(),
+            b.ctx_size
+                .map(|n| n.to_string())
+                .unwrap_or_else(|| "default".to_string()),
+            if b.completion.unwrap_or(false) {
+                "(completion)".to_string()
+            } else {
+                b.template_hint
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .unwrap_or("-")
+                    .to_string()
+            },
+            model.display().to_string(),
+        ]);
+    }
+
+    let mut out = active.header();
+    if rows.len() == 1 {
+        out.push_str("(no [models.backends.*] sections)\n");
+        return Ok(out);
+    }
+    out.push_str(&format_table(&rows));
+    Ok(out)
+}
+
+/// Stage -> resolved prompt file, then the per-backend overrides on top of it.
+pub fn list_prompts(
+    input: Option<&Path>,
+    config_path: Option<&Path>,
+    profile: Option<&str>,
+) -> anyhow::Result<String> {
+    let active = ActiveConfig::load(input, config_path, profile)?;
+    let config_dir = active.config_dir();
+
+    let mut out = active.header();
+    let mut rows: Vec<[String; 3]> = vec![[
+        "STAGE".to_string(),
+        "EXISTS".to_string(),
+        "FILE".to_string(),
+    ]];
+    for (stage, path) in default_prompt_paths(&config_dir, &active.cfg.prompts) {
+        rows.push(prompt_row(stage, &path));
+    }
+    out.push_str(&format_table(&rows));
+
+    let mut names: Vec<&String> = active.cfg.models.backends.keys().collect();
+    names.sort();
+    for name in names {
+        let overrides =
+            override_prompt_paths(&config_dir, &active.cfg.models.backends[name].prompts);
+        if overrides.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("\nbackend {name} overrides:\n"));
+        let mut rows: Vec<[String; 3]> = Vec::new();
+        for (stage, path) in overrides {
+            rows.push(prompt_row(stage, &path));
+        }
+        out.push_str(&format_table(&rows));
+    }
+    Ok(out)
+}
+
+fn prompt_row(stage: &str, path: &Path) -> [String; 3] {
+    [
+        stage.to_string(),
+        if path.exists() { "yes" } else { "no" }.to_string(),
+        path.display().to_string(),
+    ]
+}
+
+pub(super) fn format_table<const N: usize>(rows: &[[String; N]]) -> String {
+    let mut widths = [0usize; N];
+    for row in rows {
+        for (w, cell) in widths.iter_mut().zip(row) {
+            *w = (*w).max(cell.chars().count());
+        }
+    }
+    let mut out = String::new();
+    for row in rows {
+        let mut line = String::new();
+        for (i, cell) in row.iter().enumerate() {
+            if i + 1 == N {
+                line.push_str(cell);
+            } else {
+                let pad = widths[i] - cell.chars().count();
+                line.push_str(cell);
+                line.push_str(&" ".repeat(pad + 2));
+            }
+        }
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out
+}
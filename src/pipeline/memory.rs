@@ -14,6 +14,18 @@ pub struct ParaNotes {
     pub terms: Vec<String>,
 }
 
+impl ParaNotes {
+    /// Context line for the fuse/patch prompts: the controller's understanding, or the key terms
+    /// when only those are known (notes from the translate backend).
+    pub fn prompt_note(&self) -> Option<String> {
+        match self.understanding.as_deref().map(str::trim) {
+            Some(u) if !u.is_empty() => Some(u.to_string()),
+            _ if !self.terms.is_empty() => Some(format!("Key terms: {}", self.terms.join("; "))),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize)]
 pub struct ParagraphMemoryFile {
     #[serde(rename = "schema")]
@@ -28,6 +40,9 @@ pub struct ParagraphMemoryFile {
     pub model_b: Option<String>,
     #[serde(rename = "agent_model")]
     pub agent_model: Option<String>,
+    /// Sampling seed of the run.
+    #[serde(rename = "seed")]
+    pub seed: u32,
     #[serde(rename = "paragraphs")]
     pub paragraphs: Vec<ParagraphRecord>,
 }
@@ -69,12 +84,14 @@ pub struct ParagraphRecord {
     pub final_translation: Option<String>,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn build_memory(
     source_lang: &str,
     target_lang: &str,
     model_a: &str,
     model_b: Option<&str>,
     agent_model: Option<&str>,
+    seed: u32,
     tus: &[TranslationUnit],
     notes: &HashMap<usize, ParaNotes>,
 ) -> ParagraphMemoryFile {
@@ -127,6 +144,7 @@ pub fn build_memory(
         model_a: model_a.to_string(),
         model_b: model_b.map(|s| s.to_string()),
         agent_model: agent_model.map(|s| s.to_string()),
+        seed,
         paragraphs,
     }
 }
@@ -139,4 +157,3 @@ pub fn write_memory_file(path: &Path, mem: &ParagraphMemoryFile) -> anyhow::Resu
     std::fs::write(path, buf).with_context(|| format!("write memory: {}", path.display()))?;
     Ok(())
 }
-
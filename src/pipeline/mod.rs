@@ -1,9 +1,43 @@
+mod bundle;
+mod casing;
 mod config;
+mod corpus;
+mod coverage;
 mod docmap;
+mod events;
+mod glossary;
+mod hooks;
+mod inspect;
 mod memory;
+mod noterefs;
+mod project;
 mod prompts;
+mod quotes;
+mod selftest;
+mod stage;
 mod trace;
 mod translator;
+mod validation;
+mod workdir;
+mod worklist;
+mod xrefs;
 
-pub use config::{init_default_config, PipelineConfig};
-pub use translator::TranslatorPipeline;
+pub use config::{
+    configured_highlight_color, configured_hooks, configured_link_rewrites,
+    configured_package_limits, configured_placeholder_prefix, configured_project_dir,
+    configured_ui_lang, init_default_config, parse_percent, parse_seed, random_seed, OfflineMode,
+    PipelineConfig, PipelineMode, UnitFilter, DEFAULT_PSEUDO_EXPANSION,
+};
+pub use events::PipelineEvent;
+pub use hooks::{HookPayload, Hooks, RunStats};
+pub use inspect::{list_backends, list_prompts};
+pub use project::project_term_report;
+pub use selftest::{run_selftest, SelftestReport};
+pub use stage::{PipelineStage, StageContext};
+pub use translator::{ExperimentSpec, TranslatorPipeline};
+pub use validation::flagged_unit_ids;
+pub use workdir::{
+    default_workdir_for, in_workdir, record_artifacts, WorkdirManifest, ARTIFACT_AUTOSAVE_TEXT,
+    ARTIFACT_BLOBS, ARTIFACT_FINAL_TEXT, ARTIFACT_MASK, ARTIFACT_OFFSETS, ARTIFACT_OUTPUT,
+    ARTIFACT_SOURCE_TEXT, ARTIFACT_STRUCTURE, ARTIFACT_TEXT, MANIFEST_FILE,
+};
@@ -0,0 +1,109 @@
+use crate::docx::pure_text::PureTextJson;
+
+/// Punctuation that must not end up on both sides of a reference mark (`end.¹.`).
+const NOTE_REF_PUNCT: &[char] = &[
+    '.', ',', ';', ':', '!', '?', '\u{3002}', '\u{FF0C}', '\u{FF1B}', '\u{FF1A}', '\u{FF01}',
+    '\u{FF1F}', '\u{3001}',
+];
+
+/// Outcome of `protect_note_refs` for one paragraph.
+#[derive(Debug, Default)]
+pub struct NoteRefCheck {
+    /// Marks whose surrounding spaces or punctuation were repaired.
+    pub fixed: usize,
+    /// Marks whose translated text moved to the other side entirely (one side empty now).
+    pub suspicious: usize,
+}
+
+/// Projection rule for the text around footnote/endnote reference marks of one paragraph
+/// (`note_refs`: the slots before and after each mark, see `ParaSlotUnit::note_refs`), applied
+/// to the final slot texts in `output`: a space the source has at the mark and the translation
+/// swallowed is restored (Latin-script neighbours only), a space that slid in front of the mark
+/// moves behind it, and punctuation doubled around the mark is kept on the source's side only.
+pub fn protect_note_refs(
+    source: &PureTextJson,
+    note_refs: &[(usize, usize)],
+    output: &mut PureTextJson,
+) -> NoteRefCheck {
+    let mut check = NoteRefCheck::default();
+    for &(before, after) in note_refs {
+        let (Some(a), Some(b)) = (before.checked_sub(1), after.checked_sub(1)) else {
+            continue;
+        };
+        if a.max(b) >= output.slot_texts.len()
+            || output.collapsed_slots.contains(&before)
+            || output.collapsed_slots.contains(&after)
+        {
+            continue;
+        }
+        let src_a = source.slot_texts.get(a).map(String::as_str).unwrap_or("");
+        let src_b = source.slot_texts.get(b).map(String::as_str).unwrap_or("");
+        let mut tgt_a = output.slot_texts[a].clone();
+        let mut tgt_b = output.slot_texts[b].clone();
+
+        let has_text = |s: &str| s.chars().any(char::is_alphanumeric);
+        if (has_text(src_a) && !has_text(&tgt_a)) || (has_text(src_b) && !has_text(&tgt_b)) {
+            check.suspicious += 1;
+            continue;
+        }
+
+        let mut fixed = false;
+        let last_a = tgt_a.trim_end().chars().last();
+        let first_b = tgt_b.trim_start().chars().next();
+        if let (Some(p), Some(q)) = (last_a, first_b) {
+            let in_source = src_a.trim_end().ends_with(p) && src_b.trim_start().starts_with(p);
+            if p == q && NOTE_REF_PUNCT.contains(&p) && !in_source {
+                let source_after =
+                    src_b.trim_start().starts_with(p) && !src_a.trim_end().ends_with(p);
+                if source_after {
+                    let end = tgt_a.trim_end().len();
+                    tgt_a.replace_range(end - p.len_utf8()..end, "");
+                } else {
+                    let start = tgt_b.len() - tgt_b.trim_start().len();
+                    tgt_b.replace_range(start..start + p.len_utf8(), "");
+                }
+                fixed = true;
+            }
+        }
+
+        let src_space_a = src_a.ends_with(char::is_whitespace);
+        let src_space_b = src_b.starts_with(char::is_whitespace);
+        let tgt_space_a = tgt_a.ends_with(char::is_whitespace);
+        let tgt_space_b = tgt_b.starts_with(char::is_whitespace);
+        if tgt_space_a && !tgt_space_b && !src_space_a && src_space_b {
+            // "word ¹next" -> "word¹ next"
+            let trimmed = tgt_a.trim_end().len();
+            let space = tgt_a.split_off(trimmed);
+            tgt_b.insert_str(0, &space);
+            fixed = true;
+        } else if !tgt_space_a && !tgt_space_b && (src_space_a || src_space_b) {
+            let joined = tgt_a.chars().last().is_some_and(is_spaced_script)
+                && tgt_b
+                    .chars()
+                    .next()
+                    .is_some_and(|c| c.is_alphanumeric() && is_spaced_script(c));
+            if joined {
+                if src_space_b {
+                    tgt_b.insert(0, ' ');
+                } else {
+                    tgt_a.push(' ');
+                }
+                fixed = true;
+            }
+        }
+
+        if fixed {
+            output.slot_texts[a] = tgt_a;
+            output.slot_texts[b] = tgt_b;
+            check.fixed += 1;
+        }
+    }
+    check
+}
+
+/// Scripts that separate words with spaces (not Han, kana, Hangul or Thai).
+fn is_spaced_script(c: char) -> bool {
+    !matches!(c as u32,
+        0x0E00..=0x0E7F | 0x3000..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xAC00..=0xD7AF
+        | 0xF900..=0xFAFF | 0xFF00..=0xFFEF)
+}
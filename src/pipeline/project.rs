@@ -0,0 +1,396 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context};
+use rusqlite::{params, Connection};
+
+use crate::terminology::{TermDecision, TermMemory, TermUpdate};
+use crate::textutil::strip_sentinels;
+
+pub const PROJECT_DB_FILENAME: &str = "project_memory.sqlite";
+
+/// Short single-line segments (headings, table cells, defined terms) are learned as glossary
+/// entries; longer paragraphs are only kept as reference translations.
+const TERM_MAX_CHARS: usize = 40;
+const GLOSSARY_MAX_TERMS: usize = 48;
+const REFERENCE_MAX_ITEMS: usize = 8;
+
+/// A glossary-sized source segment translated differently across documents of a project.
+#[derive(Clone, Debug)]
+pub struct TermConflict {
+    pub source_lang: String,
+    pub target_lang: String,
+    pub source: String,
+    /// Each distinct translation with its `(file_name, tu_id)` occurrences.
+    pub variants: Vec<(String, Vec<(String, usize)>)>,
+}
+
+/// Persistent memory shared by all documents translated with the same `--project <dir>`.
+///
+/// Stored in `<dir>/project_memory.sqlite`:
+/// - `documents`: one row per input document (keyed by its placeholder prefix / content hash)
+/// - `paragraphs`: final paragraph translations per document
+/// - `terms`: glossary decisions per language pair (first decision wins; later ones only bump `seen`)
+pub struct ProjectMemory {
+    db_path: PathBuf,
+    conn: Connection,
+    source_lang: String,
+    target_lang: String,
+    terms: TermMemory,
+    references: HashMap<String, String>,
+}
+
+impl ProjectMemory {
+    pub fn open(dir: &Path) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("create project dir: {}", dir.display()))?;
+        let db_path = dir.join(PROJECT_DB_FILENAME);
+        let conn = Connection::open(&db_path)
+            .with_context(|| format!("open project memory: {}", db_path.display()))?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS documents (
+                doc_key TEXT PRIMARY KEY,
+                file_name TEXT NOT NULL,
+                source_lang TEXT NOT NULL,
+                target_lang TEXT NOT NULL,
+                updated_at INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS paragraphs (
+                doc_key TEXT NOT NULL,
+                tu_id INTEGER NOT NULL,
+                source TEXT NOT NULL,
+                target TEXT NOT NULL,
+                PRIMARY KEY (doc_key, tu_id)
+            );
+            CREATE TABLE IF NOT EXISTS terms (
+                source_lang TEXT NOT NULL,
+                target_lang TEXT NOT NULL,
+                src TEXT NOT NULL,
+                tgt TEXT NOT NULL,
+                kind TEXT,
+                note TEXT,
+                seen INTEGER NOT NULL DEFAULT 1,
+                PRIMARY KEY (source_lang, target_lang, src)
+            );",
+        )
+        .context("init project memory schema")?;
+        Ok(Self {
+            db_path,
+            conn,
+            source_lang: String::new(),
+            target_lang: String::new(),
+            terms: TermMemory::new(),
+            references: HashMap::new(),
+        })
+    }
+
+    pub fn db_path(&self) -> &Path {
+        &self.db_path
+    }
+
+    pub fn term_count(&self) -> usize {
+        self.terms.len()
+    }
+
+    /// Glossary entries loaded for the current language pair.
+    pub fn terms(&self) -> impl Iterator<Item = &TermDecision> {
+        self.terms.decisions()
+    }
+
+    pub fn reference_count(&self) -> usize {
+        self.references.len()
+    }
+
+    /// Load glossary + reference translations for one language pair.
+    pub fn load(&mut self, source_lang: &str, target_lang: &str) -> anyhow::Result<()> {
+        self.source_lang = source_lang.to_string();
+        self.target_lang = target_lang.to_string();
+        self.terms = TermMemory::new();
+        self.references.clear();
+
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT src, tgt, kind, note, seen FROM terms
+                 WHERE source_lang = ?1 AND target_lang = ?2",
+            )
+            .context("prepare load terms")?;
+        let rows = stmt
+            .query_map(params![source_lang, target_lang], |row| {
+                Ok(TermDecision {
+                    src: row.get(0)?,
+                    tgt: row.get(1)?,
+                    kind: row.get(2)?,
+                    note: row.get(3)?,
+                    seen: row.get::<_, i64>(4)?.max(0) as usize,
+                })
+            })
+            .context("query terms")?;
+        for row in rows {
+            self.terms.insert_decision(row.context("read term row")?);
+        }
+
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT p.source, p.target FROM paragraphs p
+                 JOIN documents d ON d.doc_key = p.doc_key
+                 WHERE d.source_lang = ?1 AND d.target_lang = ?2
+                 ORDER BY d.updated_at ASC",
+            )
+            .context("prepare load paragraphs")?;
+        let rows = stmt
+            .query_map(params![source_lang, target_lang], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .context("query paragraphs")?;
+        for row in rows {
+            let (src, tgt) = row.context("read paragraph row")?;
+            // Later documents win for identical sources.
+            self.references.insert(src, tgt);
+        }
+        Ok(())
+    }
+
+    /// Render the glossary/reference block for a chunk of source texts (empty when nothing applies).
+    pub fn glossary_for(&self, sources: &[&str]) -> String {
+        let joined = sources.join("\n");
+        let terms = self.terms.relevant_for_text(&joined, GLOSSARY_MAX_TERMS);
+        let mut out = TermMemory::render_for_prompt(&terms);
+
+        let mut refs: Vec<(&str, &str)> = Vec::new();
+        for src in sources {
+            if refs.len() >= REFERENCE_MAX_ITEMS {
+                break;
+            }
+            let key = memory_text(src);
+            if key.chars().count() <= TERM_MAX_CHARS {
+                continue;
+            }
+            if let Some((src, tgt)) = self.references.get_key_value(&key) {
+                if !refs.iter().any(|(s, _)| *s == src) {
+                    refs.push((src.as_str(), tgt.as_str()));
+                }
+            }
+        }
+        if !refs.is_empty() {
+            if !out.is_empty() {
+                out.push('\n');
+            }
+            out.push_str(
+                "REFERENCE TRANSLATIONS (approved earlier in this project; reuse when the source is identical):\n",
+            );
+            for (src, tgt) in refs {
+                out.push_str("- ");
+                out.push_str(src);
+                out.push_str("\n  => ");
+                out.push_str(tgt);
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    /// Store the final `(source, translation)` pairs of one document and learn glossary entries
+    /// from short segments. Returns the number of newly learned terms.
+    pub fn record_document(
+        &mut self,
+        doc_key: &str,
+        file_name: &str,
+        pairs: &[(usize, String, String)],
+    ) -> anyhow::Result<usize> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+
+        let pairs: Vec<(usize, String, String)> = pairs
+            .iter()
+            .map(|(id, src, tgt)| (*id, memory_text(src), memory_text(tgt)))
+            .filter(|(_, src, tgt)| !src.is_empty() && !tgt.is_empty())
+            .collect();
+
+        let mut updates: Vec<TermUpdate> = Vec::new();
+        for (_, src, tgt) in &pairs {
+            let n = src.chars().count();
+            if src == tgt || !(2..=TERM_MAX_CHARS).contains(&n) {
+                continue;
+            }
+            updates.push(TermUpdate {
+                src: src.clone(),
+                tgt: tgt.clone(),
+                kind: Some("segment".to_string()),
+                note: None,
+            });
+        }
+        let before = self.terms.len();
+        let _ = self.terms.apply_updates(updates);
+        let learned = self.terms.len().saturating_sub(before);
+
+        let tx = self
+            .conn
+            .transaction()
+            .context("begin project memory transaction")?;
+        tx.execute(
+            "INSERT OR REPLACE INTO documents (doc_key, file_name, source_lang, target_lang, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![doc_key, file_name, self.source_lang, self.target_lang, now],
+        )
+        .context("upsert document")?;
+        tx.execute(
+            "DELETE FROM paragraphs WHERE doc_key = ?1",
+            params![doc_key],
+        )
+        .context("clear document paragraphs")?;
+        for (tu_id, src, tgt) in &pairs {
+            tx.execute(
+                "INSERT OR REPLACE INTO paragraphs (doc_key, tu_id, source, target)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![doc_key, *tu_id as i64, src, tgt],
+            )
+            .context("insert paragraph")?;
+            self.references.insert(src.clone(), tgt.clone());
+        }
+        for t in self.terms.decisions() {
+            tx.execute(
+                "INSERT OR REPLACE INTO terms (source_lang, target_lang, src, tgt, kind, note, seen)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    self.source_lang,
+                    self.target_lang,
+                    t.src,
+                    t.tgt,
+                    t.kind,
+                    t.note,
+                    t.seen as i64
+                ],
+            )
+            .context("upsert term")?;
+        }
+        tx.commit().context("commit project memory")?;
+        Ok(learned)
+    }
+
+    pub fn document_count(&self) -> anyhow::Result<usize> {
+        let n: i64 = self
+            .conn
+            .query_row("SELECT COUNT(*) FROM documents", [], |row| row.get(0))
+            .context("count documents")?;
+        Ok(n.max(0) as usize)
+    }
+
+    /// Short segments (same size rule as learned glossary entries) whose stored translations
+    /// differ between documents, for every language pair in the project.
+    pub fn term_conflicts(&self) -> anyhow::Result<Vec<TermConflict>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT d.source_lang, d.target_lang, d.file_name, p.tu_id, p.source, p.target
+                 FROM paragraphs p
+                 JOIN documents d ON d.doc_key = p.doc_key
+                 ORDER BY d.updated_at ASC, p.tu_id ASC",
+            )
+            .context("prepare term conflicts")?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, i64>(3)?.max(0) as usize,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, String>(5)?,
+                ))
+            })
+            .context("query paragraphs")?;
+
+        type Variants = BTreeMap<String, Vec<(String, usize)>>;
+        let mut by_source: BTreeMap<(String, String, String), Variants> = BTreeMap::new();
+        for row in rows {
+            let (source_lang, target_lang, file_name, tu_id, src, tgt) =
+                row.context("read paragraph row")?;
+            let n = src.chars().count();
+            if src == tgt || !(2..=TERM_MAX_CHARS).contains(&n) {
+                continue;
+            }
+            by_source
+                .entry((source_lang, target_lang, src))
+                .or_default()
+                .entry(tgt)
+                .or_default()
+                .push((file_name, tu_id));
+        }
+
+        let mut out = Vec::new();
+        for ((source_lang, target_lang, source), variants) in by_source {
+            let files: BTreeSet<&str> = variants
+                .values()
+                .flatten()
+                .map(|(file, _)| file.as_str())
+                .collect();
+            if variants.len() < 2 || files.len() < 2 {
+                continue;
+            }
+            let mut variants: Vec<(String, Vec<(String, usize)>)> = variants.into_iter().collect();
+            variants.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(&b.0)));
+            out.push(TermConflict {
+                source_lang,
+                target_lang,
+                source,
+                variants,
+            });
+        }
+        Ok(out)
+    }
+}
+
+/// `--term-report`: cross-document consistency report for the project in `dir`.
+pub fn project_term_report(dir: &Path) -> anyhow::Result<String> {
+    let db_path = dir.join(PROJECT_DB_FILENAME);
+    if !db_path.is_file() {
+        return Err(anyhow!("no project memory: {}", db_path.display()));
+    }
+    let project = ProjectMemory::open(dir)?;
+    Ok(render_term_report(
+        &project.term_conflicts()?,
+        project.document_count()?,
+    ))
+}
+
+/// Plain-text report of `conflicts` (most frequent translation first per term).
+fn render_term_report(conflicts: &[TermConflict], documents: usize) -> String {
+    let mut out = format!(
+        "Term consistency across {documents} document(s): {} term(s) translated differently\n",
+        conflicts.len()
+    );
+    let mut pair = None;
+    for c in conflicts {
+        if pair != Some((&c.source_lang, &c.target_lang)) {
+            pair = Some((&c.source_lang, &c.target_lang));
+            out.push_str(&format!("\n== {} -> {} ==\n", c.source_lang, c.target_lang));
+        }
+        out.push_str(&format!("\n{:?}\n", c.source));
+        for (target, refs) in &c.variants {
+            let refs: Vec<String> = refs
+                .iter()
+                .map(|(file, tu_id)| format!("{file}#{tu_id}"))
+                .collect();
+            out.push_str(&format!(
+                "  {target:?}  ({}) {}\n",
+                refs.len(),
+                refs.join(", ")
+            ));
+        }
+    }
+    out
+}
+
+/// Memory keys/values are stored without MT sentinels and with collapsed whitespace, so that
+/// slot-level and paragraph-level runs share the same entries.
+fn memory_text(text: &str) -> String {
+    strip_sentinels(text)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
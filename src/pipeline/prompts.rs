@@ -2,6 +2,8 @@ use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, Context};
+use once_cell::sync::Lazy;
+use regex::Regex;
 
 use crate::config::{AppConfig, PromptsSection};
 
@@ -11,10 +13,15 @@ pub const DEFAULT_TRANSLATE_A: &str = "translate_a.txt";
 pub const DEFAULT_TRANSLATE_B: &str = "translate_b.txt";
 pub const DEFAULT_TRANSLATE_REPAIR: &str = "translate_repair.txt";
 pub const DEFAULT_PARA_NOTES: &str = "para_notes.json.txt";
+pub const DEFAULT_KEY_TERMS: &str = "key_terms.txt";
 pub const DEFAULT_JSON_REPAIR: &str = "json_repair.txt";
 pub const DEFAULT_FUSE_AB: &str = "fuse_ab.txt";
 pub const DEFAULT_STITCH_AUDIT: &str = "stitch_audit.json.txt";
 pub const DEFAULT_PATCH: &str = "patch.txt";
+/// First-pass template of `--proofread` (replaces `translate_a` / `translate_b`).
+pub const DEFAULT_PROOFREAD: &str = "proofread.txt";
+/// Shared fragment pulled into the translate prompts via `{{include:common_rules.txt}}`.
+pub const DEFAULT_COMMON_RULES: &str = "common_rules.txt";
 
 #[derive(Clone, Debug)]
 pub struct PromptSet {
@@ -22,10 +29,12 @@ pub struct PromptSet {
     pub translate_b: String,
     pub translate_repair: String,
     pub para_notes: String,
+    pub key_terms: String,
     pub json_repair: String,
     pub fuse_ab: String,
     pub stitch_audit: String,
     pub patch: String,
+    pub proofread: String,
 }
 
 impl PromptSet {
@@ -42,10 +51,18 @@ impl PromptSet {
                 DEFAULT_TRANSLATE_REPAIR,
             )?,
             para_notes: read_prompt(config_dir, &p, "para_notes", DEFAULT_PARA_NOTES)?,
+            key_terms: read_prompt(config_dir, &p, "key_terms", DEFAULT_KEY_TERMS)?,
             json_repair: read_prompt(config_dir, &p, "json_repair", DEFAULT_JSON_REPAIR)?,
             fuse_ab: read_prompt(config_dir, &p, "fuse_ab", DEFAULT_FUSE_AB)?,
             stitch_audit: read_prompt(config_dir, &p, "stitch_audit", DEFAULT_STITCH_AUDIT)?,
             patch: read_prompt(config_dir, &p, "patch", DEFAULT_PATCH)?,
+            proofread: read_optional_prompt(
+                config_dir,
+                &p,
+                "proofread",
+                DEFAULT_PROOFREAD,
+                DEFAULT_PROOFREAD_TEXT,
+            )?,
         })
     }
 }
@@ -54,6 +71,7 @@ impl PromptSet {
 pub struct PromptCatalog {
     default: PromptSet,
     by_backend: HashMap<String, PromptSet>,
+    proofread: bool,
 }
 
 impl PromptCatalog {
@@ -63,34 +81,133 @@ impl PromptCatalog {
         backend_names: &[String],
     ) -> anyhow::Result<Self> {
         let default = PromptSet::load(config_path, cfg).context("load default prompts")?;
-        let config_dir = config_path.parent().unwrap_or_else(|| Path::new("."));
-
-        let mut by_backend: HashMap<String, PromptSet> = HashMap::new();
+        let mut catalog = Self {
+            default,
+            by_backend: HashMap::new(),
+            proofread: false,
+        };
         for name in backend_names {
-            if by_backend.contains_key(name) {
-                continue;
-            }
-            let Some(backend) = cfg.models.backends.get(name) else {
-                continue;
-            };
-            let overrides = &backend.prompts;
-            if prompts_section_is_empty(overrides) {
-                continue;
-            }
-            let mut set = default.clone();
-            apply_prompt_overrides(config_dir, name, &mut set, overrides)?;
-            by_backend.insert(name.clone(), set);
+            catalog.add_backend(config_path, cfg, name)?;
         }
+        Ok(catalog)
+    }
 
-        Ok(Self {
-            default,
-            by_backend,
-        })
+    /// Load `[models.backends.<name>.prompts]` overrides (no-op when already loaded or empty).
+    pub fn add_backend(
+        &mut self,
+        config_path: &Path,
+        cfg: &AppConfig,
+        name: &str,
+    ) -> anyhow::Result<()> {
+        if self.by_backend.contains_key(name) {
+            return Ok(());
+        }
+        let Some(backend) = cfg.models.backends.get(name) else {
+            return Ok(());
+        };
+        let overrides = &backend.prompts;
+        if prompts_section_is_empty(overrides) {
+            return Ok(());
+        }
+        let config_dir = config_path.parent().unwrap_or_else(|| Path::new("."));
+        let mut set = self.default.clone();
+        apply_prompt_overrides(config_dir, name, &mut set, overrides)?;
+        if self.proofread {
+            use_proofread_prompt(&mut set);
+        }
+        self.by_backend.insert(name.to_string(), set);
+        Ok(())
     }
 
     pub fn for_backend(&self, name: &str) -> &PromptSet {
         self.by_backend.get(name).unwrap_or(&self.default)
     }
+
+    /// `--proofread`: every backend's first pass (`translate_a`, `translate_b`) uses its
+    /// `proofread` template; repair and the later stages are unchanged.
+    pub fn set_proofread(&mut self) {
+        self.proofread = true;
+        use_proofread_prompt(&mut self.default);
+        for set in self.by_backend.values_mut() {
+            use_proofread_prompt(set);
+        }
+    }
+}
+
+fn use_proofread_prompt(set: &mut PromptSet) {
+    set.translate_a = set.proofread.clone();
+    set.translate_b = set.proofread.clone();
+}
+
+/// Prompt stages in pipeline order, with their default file names under `prompts/`.
+pub(super) const PROMPT_STAGES: [(&str, &str); 10] = [
+    ("translate_a", DEFAULT_TRANSLATE_A),
+    ("translate_b", DEFAULT_TRANSLATE_B),
+    ("translate_repair", DEFAULT_TRANSLATE_REPAIR),
+    ("para_notes", DEFAULT_PARA_NOTES),
+    ("key_terms", DEFAULT_KEY_TERMS),
+    ("json_repair", DEFAULT_JSON_REPAIR),
+    ("fuse_ab", DEFAULT_FUSE_AB),
+    ("stitch_audit", DEFAULT_STITCH_AUDIT),
+    ("patch", DEFAULT_PATCH),
+    ("proofread", DEFAULT_PROOFREAD),
+];
+
+fn prompt_field<'a>(p: &'a PromptsSection, key: &str) -> Option<&'a Option<String>> {
+    match key {
+        "translate_a" => Some(&p.translate_a),
+        "translate_b" => Some(&p.translate_b),
+        "translate_repair" => Some(&p.translate_repair),
+        "para_notes" => Some(&p.para_notes),
+        "key_terms" => Some(&p.key_terms),
+        "json_repair" => Some(&p.json_repair),
+        "fuse_ab" => Some(&p.fuse_ab),
+        "stitch_audit" => Some(&p.stitch_audit),
+        "patch" => Some(&p.patch),
+        "proofread" => Some(&p.proofread),
+        _ => None,
+    }
+}
+
+fn resolve_prompt_file(config_dir: &Path, path: &str) -> PathBuf {
+    let p = PathBuf::from(path);
+    if p.is_relative() {
+        config_dir.join(p)
+    } else {
+        p
+    }
+}
+
+/// File each stage reads from the `[prompts]` section (or its default under `prompts/`).
+pub(super) fn default_prompt_paths(
+    config_dir: &Path,
+    p: &PromptsSection,
+) -> Vec<(&'static str, PathBuf)> {
+    PROMPT_STAGES
+        .iter()
+        .map(|(key, default_filename)| {
+            let rel = format!("{DEFAULT_PROMPTS_DIR}/{default_filename}");
+            let path = prompt_field(p, key).and_then(|v| v.clone()).unwrap_or(rel);
+            (*key, resolve_prompt_file(config_dir, &path))
+        })
+        .collect()
+}
+
+/// Stages overridden by a `[models.backends.<name>.prompts]` section, with their files.
+pub(super) fn override_prompt_paths(
+    config_dir: &Path,
+    overrides: &PromptsSection,
+) -> Vec<(&'static str, PathBuf)> {
+    PROMPT_STAGES
+        .iter()
+        .filter_map(|(key, _)| {
+            let path = prompt_field(overrides, key)?
+                .as_deref()
+                .map(|s| s.trim())
+                .filter(|s| !s.is_empty())?;
+            Some((*key, resolve_prompt_file(config_dir, path)))
+        })
+        .collect()
 }
 
 fn read_prompt(
@@ -100,22 +217,39 @@ fn read_prompt(
     default_filename: &str,
 ) -> anyhow::Result<String> {
     let rel = format!("{DEFAULT_PROMPTS_DIR}/{default_filename}");
-    let path = match key {
-        "translate_a" => p.translate_a.clone().unwrap_or(rel),
-        "translate_b" => p.translate_b.clone().unwrap_or(rel),
-        "translate_repair" => p.translate_repair.clone().unwrap_or(rel),
-        "para_notes" => p.para_notes.clone().unwrap_or(rel),
-        "json_repair" => p.json_repair.clone().unwrap_or(rel),
-        "fuse_ab" => p.fuse_ab.clone().unwrap_or(rel),
-        "stitch_audit" => p.stitch_audit.clone().unwrap_or(rel),
-        "patch" => p.patch.clone().unwrap_or(rel),
-        other => return Err(anyhow!("unknown prompt key: {other}")),
-    };
+    let path = prompt_field(p, key)
+        .ok_or_else(|| anyhow!("unknown prompt key: {key}"))?
+        .clone()
+        .unwrap_or(rel);
 
-    let mut p = PathBuf::from(path);
-    if p.is_relative() {
-        p = config_dir.join(&p);
+    read_prompt_file(config_dir, &resolve_prompt_file(config_dir, &path), key)
+}
+
+/// `read_prompt` for stages added after the prompt set was first released: a config that
+/// neither sets `key` nor has the default file (made by an older `--init-config`) gets the
+/// built-in `fallback`.
+fn read_optional_prompt(
+    config_dir: &Path,
+    p: &PromptsSection,
+    key: &str,
+    default_filename: &str,
+    fallback: &str,
+) -> anyhow::Result<String> {
+    let configured = prompt_field(p, key).is_some_and(|v| v.is_some());
+    let default_path = config_dir.join(DEFAULT_PROMPTS_DIR).join(default_filename);
+    if !configured && !default_path.exists() {
+        let prompts_dir = config_dir.join(DEFAULT_PROMPTS_DIR);
+        return expand_includes(fallback, &default_path, &prompts_dir, &mut Vec::new())
+            .with_context(|| format!("expand includes in built-in prompt for {key}"));
     }
+    read_prompt(config_dir, p, key, default_filename)
+}
+
+fn read_prompt_path(config_dir: &Path, path: &str, key: &str) -> anyhow::Result<String> {
+    read_prompt_file(config_dir, &resolve_prompt_file(config_dir, path), key)
+}
+
+pub(super) fn read_prompt_file(config_dir: &Path, p: &Path, key: &str) -> anyhow::Result<String> {
     if !p.exists() {
         return Err(anyhow!(
             "prompt file not found for {key}: {} (run: muggle-translator --init-config)",
@@ -123,24 +257,75 @@ fn read_prompt(
         ));
     }
     let text =
-        std::fs::read_to_string(&p).with_context(|| format!("read prompt: {}", p.display()))?;
-    Ok(text)
+        std::fs::read_to_string(p).with_context(|| format!("read prompt: {}", p.display()))?;
+    let mut stack = vec![p.canonicalize().unwrap_or_else(|_| p.to_path_buf())];
+    expand_includes(&text, p, &config_dir.join(DEFAULT_PROMPTS_DIR), &mut stack)
+        .with_context(|| format!("expand includes in prompt for {key}: {}", p.display()))
 }
 
-fn read_prompt_path(config_dir: &Path, path: &str, key: &str) -> anyhow::Result<String> {
-    let mut p = PathBuf::from(path);
-    if p.is_relative() {
-        p = config_dir.join(&p);
+static INCLUDE_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\{\{\s*include:\s*([^}]+?)\s*\}\}").expect("include regex"));
+
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// Replace `{{include:<file>}}` with the fragment's text (one trailing newline dropped, so the
+/// directive can sit on its own line). Fragments resolve next to the including file, then in
+/// the `prompts/` dir; they may include other fragments.
+fn expand_includes(
+    text: &str,
+    file: &Path,
+    prompts_dir: &Path,
+    stack: &mut Vec<PathBuf>,
+) -> anyhow::Result<String> {
+    if !INCLUDE_RE.is_match(text) {
+        return Ok(text.to_string());
     }
-    if !p.exists() {
+    if stack.len() > MAX_INCLUDE_DEPTH {
         return Err(anyhow!(
-            "prompt file not found for {key}: {} (run: muggle-translator --init-config)",
-            p.display()
+            "prompt includes nested deeper than {MAX_INCLUDE_DEPTH}"
         ));
     }
-    let text =
-        std::fs::read_to_string(&p).with_context(|| format!("read prompt: {}", p.display()))?;
-    Ok(text)
+    let base_dir = file.parent().unwrap_or_else(|| Path::new("."));
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0usize;
+    for cap in INCLUDE_RE.captures_iter(text) {
+        let m = cap.get(0).expect("match");
+        out.push_str(&text[last..m.start()]);
+        last = m.end();
+
+        let name = cap[1].trim();
+        let rel = PathBuf::from(name);
+        let path = if rel.is_absolute() {
+            rel
+        } else {
+            [base_dir.join(&rel), prompts_dir.join(&rel)]
+                .into_iter()
+                .find(|p| p.exists())
+                .ok_or_else(|| {
+                    anyhow!(
+                        "prompt include not found: {name} (searched: {}; {})",
+                        base_dir.display(),
+                        prompts_dir.display()
+                    )
+                })?
+        };
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
+        if stack.contains(&canonical) {
+            return Err(anyhow!("prompt include cycle: {}", path.display()));
+        }
+        let fragment = std::fs::read_to_string(&path)
+            .with_context(|| format!("read prompt include: {}", path.display()))?;
+        stack.push(canonical);
+        let fragment = expand_includes(&fragment, &path, prompts_dir, stack)?;
+        stack.pop();
+        let fragment = fragment
+            .strip_suffix("\r\n")
+            .or_else(|| fragment.strip_suffix('\n'))
+            .unwrap_or(&fragment);
+        out.push_str(fragment);
+    }
+    out.push_str(&text[last..]);
+    Ok(out)
 }
 
 fn apply_prompt_overrides(
@@ -166,6 +351,7 @@ fn apply_prompt_overrides(
         &mut out.translate_repair,
     )?;
     apply("para_notes", &overrides.para_notes, &mut out.para_notes)?;
+    apply("key_terms", &overrides.key_terms, &mut out.key_terms)?;
     apply("json_repair", &overrides.json_repair, &mut out.json_repair)?;
     apply("fuse_ab", &overrides.fuse_ab, &mut out.fuse_ab)?;
     apply(
@@ -174,6 +360,7 @@ fn apply_prompt_overrides(
         &mut out.stitch_audit,
     )?;
     apply("patch", &overrides.patch, &mut out.patch)?;
+    apply("proofread", &overrides.proofread, &mut out.proofread)?;
 
     Ok(())
 }
@@ -187,10 +374,12 @@ fn prompts_section_is_empty(p: &PromptsSection) -> bool {
             .trim()
             .is_empty()
         && p.para_notes.as_deref().unwrap_or("").trim().is_empty()
+        && p.key_terms.as_deref().unwrap_or("").trim().is_empty()
         && p.json_repair.as_deref().unwrap_or("").trim().is_empty()
         && p.fuse_ab.as_deref().unwrap_or("").trim().is_empty()
         && p.stitch_audit.as_deref().unwrap_or("").trim().is_empty()
         && p.patch.as_deref().unwrap_or("").trim().is_empty()
+        && p.proofread.as_deref().unwrap_or("").trim().is_empty()
 }
 
 pub fn render_template(template: &str, vars: &[(&str, &str)]) -> String {
@@ -208,19 +397,20 @@ pub fn default_prompt_files() -> Vec<(&'static str, &'static str)> {
         (DEFAULT_TRANSLATE_B, DEFAULT_TRANSLATE_B_TEXT),
         (DEFAULT_TRANSLATE_REPAIR, DEFAULT_TRANSLATE_REPAIR_TEXT),
         (DEFAULT_PARA_NOTES, DEFAULT_PARA_NOTES_TEXT),
+        (DEFAULT_KEY_TERMS, DEFAULT_KEY_TERMS_TEXT),
         (DEFAULT_JSON_REPAIR, DEFAULT_JSON_REPAIR_TEXT),
         (DEFAULT_FUSE_AB, DEFAULT_FUSE_AB_TEXT),
         (DEFAULT_STITCH_AUDIT, DEFAULT_STITCH_AUDIT_TEXT),
         (DEFAULT_PATCH, DEFAULT_PATCH_TEXT),
+        (DEFAULT_PROOFREAD, DEFAULT_PROOFREAD_TEXT),
+        (DEFAULT_COMMON_RULES, DEFAULT_COMMON_RULES_TEXT),
     ]
 }
 
-pub const DEFAULT_TRANSLATE_A_TEXT: &str = r#"Translate from {{source_lang}} to {{target_lang}}.
-
-Rules:
-- Do NOT omit content; do NOT summarize.
+pub const DEFAULT_COMMON_RULES_TEXT: &str = r#"- Do NOT omit content; do NOT summarize.
 - Do NOT use ellipsis placeholders like … or ... to skip content.
 - Keep ALL tokens like <<MT_...>> unchanged.
+- Keep each <<MT_EM:nn>> ... <<MT_EM_END:nn>> pair around the words that translate the words it marks.
 - Preserve all digits (0-9) exactly.
 - Output ONLY the translated segments, in the same order.
 - For each TU id, output EXACTLY:
@@ -228,6 +418,12 @@ Rules:
   ...translation...
   <<MT_END:000123>>
 - Do NOT add any other text.
+"#;
+
+pub const DEFAULT_TRANSLATE_A_TEXT: &str = r#"Translate from {{source_lang}} to {{target_lang}}.
+
+Rules:
+{{include:common_rules.txt}}
 
 INPUT:
 {{tu_block}}"#;
@@ -235,16 +431,7 @@ INPUT:
 pub const DEFAULT_TRANSLATE_B_TEXT: &str = r#"Translate from {{source_lang}} to {{target_lang}}.
 
 Rules:
-- Do NOT omit content; do NOT summarize.
-- Do NOT use ellipsis placeholders like … or ... to skip content.
-- Keep ALL tokens like <<MT_...>> unchanged.
-- Preserve all digits (0-9) exactly.
-- Output ONLY the translated segments, in the same order.
-- For each TU id, output EXACTLY:
-  <<MT_SEG:000123>>
-  ...translation...
-  <<MT_END:000123>>
-- Do NOT add any other text.
+{{include:common_rules.txt}}
 
 INPUT:
 {{tu_block}}"#;
@@ -290,6 +477,14 @@ Schema:
 PARAGRAPHS:
 {{tu_block}}"#;
 
+pub const DEFAULT_KEY_TERMS_TEXT: &str = r#"List the key terms of each paragraph below (names, technical terms, recurring phrases), at most 5 per paragraph, each with its {{target_lang}} rendering.
+Output one line per paragraph, EXACTLY:
+TU#123: term = rendering; term = rendering
+Skip paragraphs without key terms. Do NOT add any other text.
+
+PARAGRAPHS:
+{{tu_block}}"#;
+
 pub const DEFAULT_JSON_REPAIR_TEXT: &str = r#"You are a JSON repair tool.
 Return STRICT JSON only (one JSON object). No markdown. No extra text.
 Do not add new facts.
@@ -324,6 +519,15 @@ Schema:
 INPUT:
 {{tu_block}}"#;
 
+pub const DEFAULT_PROOFREAD_TEXT: &str = r#"Proofread the following {{source_lang}} text. Fix spelling, grammar, punctuation and clumsy wording; keep the meaning, tone, terminology and language. Leave correct text unchanged. Do NOT translate.
+In the rules below, "translation" means the corrected text.
+
+Rules:
+{{include:common_rules.txt}}
+
+INPUT:
+{{tu_block}}"#;
+
 pub const DEFAULT_PATCH_TEXT: &str = r#"Rewrite the translation of CURRENT paragraph to be natural and consistent.
 Keep ALL tokens like <<MT_...>> unchanged.
 Return ONLY the rewritten translation for CURRENT.
@@ -0,0 +1,68 @@
+use crate::docx::pure_text::PureTextJson;
+
+use super::casing::rewrite_paragraph;
+use super::config::QuoteMarks;
+
+/// Convert the quotation mark pairs of every paragraph (`units`: paragraph id and slot ids) to
+/// `marks`: double quotes (straight, curly, low-9, guillemets, corner brackets) to
+/// `marks.double`, single quotes opened by `‘`, `‹` or `『` to `marks.single`. Marks without a
+/// partner in the paragraph and apostrophes are left alone. Returns the number of paragraphs
+/// changed.
+pub fn convert_quotes(
+    marks: &QuoteMarks,
+    units: &[(usize, &[usize])],
+    output: &mut PureTextJson,
+) -> usize {
+    let mut changed = 0usize;
+    for &(_, slot_ids) in units {
+        let rewritten = rewrite_paragraph(output, slot_ids, |chars| {
+            let mut out = vec![None; chars.len()];
+            for (open, close, double) in quote_pairs(chars) {
+                let (o, c) = if double { marks.double } else { marks.single };
+                out[open] = Some(o.to_string());
+                out[close] = Some(c.to_string());
+            }
+            out
+        });
+        if rewritten {
+            changed += 1;
+        }
+    }
+    changed
+}
+
+/// `(open, close, is_double)` char positions of the quote pairs in `chars`.
+pub(super) fn quote_pairs(chars: &[char]) -> Vec<(usize, usize, bool)> {
+    let mut pairs = Vec::new();
+    let mut double: Option<usize> = None;
+    let mut single: Option<usize> = None;
+    for (i, &c) in chars.iter().enumerate() {
+        match c {
+            // Straight quotes and `“` toggle: `“` also closes German „...“ and “...“ typos.
+            '"' | '\u{FF02}' | '\u{201C}' => match double.take() {
+                Some(open) => pairs.push((open, i, true)),
+                None => double = Some(i),
+            },
+            '\u{201E}' | '\u{AB}' | '\u{300C}' if double.is_none() => double = Some(i),
+            '\u{201D}' | '\u{BB}' | '\u{300D}' => {
+                if let Some(open) = double.take() {
+                    pairs.push((open, i, true));
+                }
+            }
+            '\u{2018}' | '\u{2039}' | '\u{300E}' if single.is_none() => single = Some(i),
+            // `’` between letters is an apostrophe (`it’s`), never a closing quote.
+            '\u{2019}' if is_apostrophe(chars, i) => {}
+            '\u{2019}' | '\u{203A}' | '\u{300F}' => {
+                if let Some(open) = single.take() {
+                    pairs.push((open, i, false));
+                }
+            }
+            _ => {}
+        }
+    }
+    pairs
+}
+
+fn is_apostrophe(chars: &[char], i: usize) -> bool {
+    i > 0 && chars[i - 1].is_alphanumeric() && chars.get(i + 1).is_some_and(|c| c.is_alphabetic())
+}
@@ -0,0 +1,176 @@
+//! `--selftest <DIR>`: run the model-free round trips over a folder of DOCX files and print a
+//! pass/fail matrix, so a document collection can be checked before trusting a translation run.
+
+use std::fs;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context};
+use rayon::prelude::*;
+
+use crate::docx::decompose::{
+    extract_mask_json_and_offsets_from, merge_mask_json_and_offsets, verify_docx_roundtrip,
+    MaskOptions,
+};
+use crate::docx::filter::{filter_docx_with_rules, DocxFilterRules};
+use crate::docx::package::PackageOptions;
+use crate::docx::pure_text::{pure_text_from_session, write_pure_text_json};
+use crate::docx::session::DocumentSession;
+use crate::docx::structure::build_structure;
+
+use super::config::DEFAULT_DOCX_FILTER_RULES_TOML;
+use super::inspect::format_table;
+
+/// Columns of the matrix, in the order they run.
+const CHECKS: [&str; 3] = ["extract_merge", "filter_roundtrip", "structure"];
+
+/// Outcome of `run_selftest`: the rendered matrix and how many files failed a check.
+pub struct SelftestReport {
+    pub files: usize,
+    pub failed: usize,
+    pub text: String,
+}
+
+/// Check every `.docx` directly in `dir` (files in parallel, each in its own scratch dir):
+/// - extract_merge: extract text + mask, merge them back, compare with the input
+///   (`--verify-extract-merge-json`);
+/// - filter_roundtrip: the same on the filtered document (`rules_path`, or the built-in rules
+///   that merge adjacent runs);
+/// - structure: heading/table structure extraction (`--extract-structure-json`).
+///
+/// A check that panics counts as failed; the other files and checks still run.
+pub fn run_selftest(dir: &Path, rules_path: Option<&Path>) -> anyhow::Result<SelftestReport> {
+    let rules = match rules_path {
+        Some(path) => DocxFilterRules::from_toml_path(path)?,
+        None => DocxFilterRules::from_toml_str(DEFAULT_DOCX_FILTER_RULES_TOML)
+            .context("built-in filter rules")?,
+    };
+    let files = docx_files(dir)?;
+    if files.is_empty() {
+        return Err(anyhow!("no .docx files in {}", dir.display()));
+    }
+    let scratch = std::env::temp_dir().join(format!("muggle-selftest-{}", std::process::id()));
+    let results: Vec<[Result<(), String>; 3]> = files
+        .par_iter()
+        .enumerate()
+        .map(|(i, file)| check_file(file, &scratch.join(i.to_string()), &rules))
+        .collect();
+    let _ = fs::remove_dir_all(&scratch);
+
+    let mut rows: Vec<[String; 4]> = vec![[
+        "FILE".to_string(),
+        CHECKS[0].to_uppercase(),
+        CHECKS[1].to_uppercase(),
+        CHECKS[2].to_uppercase(),
+    ]];
+    let mut failures = String::new();
+    let mut failed = 0usize;
+    for (file, checks) in files.iter().zip(&results) {
+        let name = file
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let cell = |r: &Result<(), String>| if r.is_ok() { "ok" } else { "FAIL" }.to_string();
+        rows.push([
+            name.clone(),
+            cell(&checks[0]),
+            cell(&checks[1]),
+            cell(&checks[2]),
+        ]);
+        if checks.iter().any(Result::is_err) {
+            failed += 1;
+        }
+        for (check, result) in CHECKS.iter().zip(checks) {
+            if let Err(err) = result {
+                failures.push_str(&format!("{name} {check}: {err}\n"));
+            }
+        }
+    }
+
+    let mut text = format_table(&rows);
+    if !failures.is_empty() {
+        text.push('\n');
+        text.push_str(&failures);
+    }
+    text.push_str(&format!(
+        "\n{} of {} file(s) passed\n",
+        files.len() - failed,
+        files.len()
+    ));
+    Ok(SelftestReport {
+        files: files.len(),
+        failed,
+        text,
+    })
+}
+
+/// `.docx` files directly in `dir`, by name (Word's `~$` lock files skipped).
+fn docx_files(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .with_context(|| format!("read dir: {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.is_file())
+        .filter(|p| {
+            p.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("docx"))
+        })
+        .filter(|p| {
+            !p.file_name()
+                .is_some_and(|n| n.to_string_lossy().starts_with("~$"))
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+fn check_file(input: &Path, scratch: &Path, rules: &DocxFilterRules) -> [Result<(), String>; 3] {
+    if let Err(err) = fs::create_dir_all(scratch) {
+        let msg = format!("create scratch dir {}: {err}", scratch.display());
+        return [Err(msg.clone()), Err(msg.clone()), Err(msg)];
+    }
+    let filtered = scratch.join("filtered.docx");
+    [
+        guarded(|| extract_merge_roundtrip(input, &scratch.join("plain"))),
+        guarded(|| {
+            filter_docx_with_rules(input, &filtered, rules, &PackageOptions::default())?;
+            extract_merge_roundtrip(&filtered, &scratch.join("filtered"))
+        }),
+        guarded(|| {
+            let session = DocumentSession::open(input)?;
+            let pure = pure_text_from_session(&session)?;
+            serde_json::to_vec(&build_structure(&pure)).context("serialize structure json")?;
+            Ok(())
+        }),
+    ]
+}
+
+/// Extract `input` into `dir`, merge the unchanged text back and compare the result with
+/// `input`, as `--verify-extract-merge-json` does.
+fn extract_merge_roundtrip(input: &Path, dir: &Path) -> anyhow::Result<()> {
+    fs::create_dir_all(dir).with_context(|| format!("create dir: {}", dir.display()))?;
+    let (mask, offsets, blobs) = (
+        dir.join("mask.json"),
+        dir.join("offsets.json"),
+        dir.join("mask.blobs.bin"),
+    );
+    let (text, merged) = (dir.join("text.json"), dir.join("merged.docx"));
+    let session = DocumentSession::open(input)?;
+    write_pure_text_json(&pure_text_from_session(&session)?, &text)?;
+    extract_mask_json_and_offsets_from(&session, &mask, &offsets, &blobs, MaskOptions::default())?;
+    merge_mask_json_and_offsets(&mask, &offsets, &text, &merged)?;
+    verify_docx_roundtrip(input, &merged)
+}
+
+/// Run one check, turning an error or a panic into its message.
+fn guarded(check: impl FnOnce() -> anyhow::Result<()>) -> Result<(), String> {
+    match catch_unwind(AssertUnwindSafe(check)) {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(err)) => Err(format!("{err:#}")),
+        Err(panic) => Err(panic
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .map(|s| format!("panicked: {s}"))
+            .unwrap_or_else(|| "panicked".to_string())),
+    }
+}
@@ -0,0 +1,27 @@
+use std::path::Path;
+
+use crate::ir::TranslationUnit;
+
+/// What a custom stage gets to know about the run besides its units.
+#[derive(Clone, Copy, Debug)]
+pub struct StageContext<'a> {
+    /// Output file stem; trace artifacts of the run are named after it.
+    pub stem: &'a str,
+    pub source_lang: &'a str,
+    pub target_lang: &'a str,
+    pub trace_dir: &'a Path,
+}
+
+/// A custom pipeline stage (company QA, watermarking, legal clause checks, ...), registered with
+/// `TranslatorPipeline::with_stage`.
+///
+/// Stages run in registration order once the model stages are done and before the output is
+/// written. Translations are frozen text (`final_translation`, else `draft_translation`); units
+/// whose translation a stage changes are written back into the output, and flags pushed to
+/// `qe_flags` show up in the reports like the pipeline's own. An error aborts the run.
+pub trait PipelineStage: Send {
+    /// Name for the console and the error's `stage` field.
+    fn name(&self) -> &str;
+
+    fn run(&mut self, ctx: &StageContext<'_>, tus: &mut [TranslationUnit]) -> anyhow::Result<()>;
+}
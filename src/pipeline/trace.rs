@@ -1,10 +1,35 @@
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::SystemTime;
 
 use anyhow::Context;
 
+/// Stage keys understood by `[trace.stages]`. Trace file names start with one of these.
+pub const TRACE_STAGES: [&str; 7] = [
+    "translate_a",
+    "translate_b",
+    "para_notes",
+    "fuse",
+    "stitch_audit",
+    "patch",
+    "validate",
+];
+
+#[derive(Clone, Debug, Default)]
+pub struct TraceLimits {
+    pub max_files: Option<usize>,
+    pub max_bytes: Option<u64>,
+    pub disabled_stages: Vec<String>,
+}
+
 pub struct TraceWriter {
     dir: PathBuf,
     enabled: bool,
+    limits: TraceLimits,
+    /// `(files, bytes)` counted against `limits`; shared by the threads of one run.
+    used: Mutex<(usize, u64)>,
+    limit_hit: AtomicBool,
 }
 
 impl TraceWriter {
@@ -13,15 +38,72 @@ impl TraceWriter {
             std::fs::create_dir_all(&dir)
                 .with_context(|| format!("create trace dir: {}", dir.display()))?;
         }
-        Ok(Self { dir, enabled })
+        Ok(Self {
+            dir,
+            enabled,
+            limits: TraceLimits::default(),
+            used: Mutex::new((0, 0)),
+            limit_hit: AtomicBool::new(false),
+        })
+    }
+
+    pub fn with_limits(mut self, limits: TraceLimits) -> Self {
+        self.limits = limits;
+        self
     }
 
     pub fn dir(&self) -> &Path {
         &self.dir
     }
 
+    /// Delete the oldest trace files in the trace dir until it fits `max_files`/`max_bytes`, then
+    /// seed the in-run budget with what is left. Only names this writer produces are counted or
+    /// removed (see `is_trace_file`): the dir is configurable and may hold the user's own files.
+    /// Returns `(removed_files, removed_bytes)`.
+    pub fn prune(&self) -> anyhow::Result<(usize, u64)> {
+        if self.limits.max_files.is_none() && self.limits.max_bytes.is_none() {
+            return Ok((0, 0));
+        }
+        let mut entries: Vec<(SystemTime, u64, PathBuf)> = Vec::new();
+        if let Ok(rd) = std::fs::read_dir(&self.dir) {
+            for ent in rd.flatten() {
+                let Ok(meta) = ent.metadata() else {
+                    continue;
+                };
+                if !meta.is_file() || !ent.file_name().to_str().is_some_and(is_trace_file) {
+                    continue;
+                }
+                let mtime = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                entries.push((mtime, meta.len(), ent.path()));
+            }
+        }
+        entries.sort_by_key(|e| e.0);
+
+        let mut files = entries.len();
+        let mut bytes: u64 = entries.iter().map(|e| e.1).sum();
+        let max_files = self.limits.max_files.unwrap_or(usize::MAX);
+        let max_bytes = self.limits.max_bytes.unwrap_or(u64::MAX);
+        let mut removed = (0usize, 0u64);
+        for (_, len, path) in entries {
+            if files <= max_files && bytes <= max_bytes {
+                break;
+            }
+            std::fs::remove_file(&path)
+                .with_context(|| format!("prune trace file: {}", path.display()))?;
+            files -= 1;
+            bytes = bytes.saturating_sub(len);
+            removed.0 += 1;
+            removed.1 += len;
+        }
+        *self.used.lock().unwrap_or_else(|e| e.into_inner()) = (files, bytes);
+        Ok(removed)
+    }
+
     pub fn write_named_text(&self, name: &str, text: &str) -> anyhow::Result<()> {
-        if !self.enabled {
+        if !self.enabled || !self.stage_enabled(name) {
+            return Ok(());
+        }
+        if !self.reserve(text.len() as u64) {
             return Ok(());
         }
         let path = self.dir.join(sanitize_filename(name));
@@ -42,6 +124,72 @@ impl TraceWriter {
         let name = format!("tu_{tu_id:06}.{stage}.{kind}.txt");
         self.write_named_text(&name, text)
     }
+
+    fn stage_enabled(&self, name: &str) -> bool {
+        match trace_stage_of(name) {
+            Some(stage) => !self.limits.disabled_stages.iter().any(|s| s == stage),
+            None => true,
+        }
+    }
+
+    fn reserve(&self, len: u64) -> bool {
+        let mut used = self.used.lock().unwrap_or_else(|e| e.into_inner());
+        let files = used.0 + 1;
+        let bytes = used.1 + len;
+        let over_files = self.limits.max_files.is_some_and(|m| files > m);
+        let over_bytes = self.limits.max_bytes.is_some_and(|m| bytes > m);
+        if over_files || over_bytes {
+            if !self.limit_hit.swap(true, Ordering::Relaxed) {
+                eprintln!(
+                    "[warn] trace limit reached ({} files, {} bytes in {}); skipping further trace files",
+                    used.0,
+                    used.1,
+                    self.dir.display()
+                );
+            }
+            return false;
+        }
+        *used = (files, bytes);
+        true
+    }
+}
+
+fn trace_stage_of(name: &str) -> Option<&'static str> {
+    if name.contains("validate_fail") || name.contains("projection_fail") {
+        return Some("validate");
+    }
+    // `tu_000123.<stage>.<kind>.txt`
+    let name = match name.strip_prefix("tu_") {
+        Some(rest) => rest.split_once('.').map(|(_, r)| r).unwrap_or(rest),
+        None => name,
+    };
+    TRACE_STAGES.iter().copied().find(|s| name.starts_with(s))
+}
+
+/// Whether `name` is one of the writer's own files: `run.seed.txt`, per-unit traces
+/// (`tu_000123.*`, `para_tu_000123.*`) and stage traces (`<stage>.*`), all `.txt` or `.json`.
+fn is_trace_file(name: &str) -> bool {
+    if !(name.ends_with(".txt") || name.ends_with(".json")) {
+        return false;
+    }
+    if name == "run.seed.txt" {
+        return true;
+    }
+    let tu_id_follows = |rest: &str| {
+        let b = rest.as_bytes();
+        b.len() > 7 && b[..6].iter().all(u8::is_ascii_digit) && b[6] == b'.'
+    };
+    if name.strip_prefix("tu_").is_some_and(tu_id_follows)
+        || name
+            .split_once("_tu_")
+            .is_some_and(|(_, rest)| tu_id_follows(rest))
+    {
+        return true;
+    }
+    TRACE_STAGES.iter().any(|s| {
+        name.strip_prefix(s)
+            .is_some_and(|rest| rest.starts_with('.'))
+    })
 }
 
 fn sanitize_filename(name: &str) -> String {
@@ -54,4 +202,3 @@ fn sanitize_filename(name: &str) -> String {
     }
     out
 }
-
@@ -1,36 +1,86 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use anyhow::{anyhow, Context};
 use once_cell::sync::Lazy;
+use regex::Regex;
 
+use crate::async_api::EventSender;
+use crate::cancel::{is_cancelled, CancellationToken};
+use crate::docx::artifact::utc_from_secs;
 use crate::docx::decompose::{
-    extract_mask_json_and_offsets, merge_mask_json_and_offsets, OffsetsJson,
+    extract_mask_json_and_offsets_from, merge_mask_json_and_offsets_with, MaskOptions,
+    MergeOptions, OffsetsJson,
 };
 use crate::docx::filter::{filter_docx_with_rules, DocxFilterRules};
-use crate::docx::pure_text::{extract_pure_text, PureTextJson};
-use crate::docx::structure::extract_structure_json;
-use crate::freezer::{freeze_text, unfreeze_text};
+use crate::docx::project::split_text_by_weights;
+use crate::docx::pure_text::{pure_text_from_session, PureTextJson};
+use crate::docx::session::DocumentSession;
+use crate::docx::structure::write_structure_json;
+use crate::docx::unsupported::{find_unsupported_content, UnsupportedContent};
+use crate::docx::xml::ParseFix;
+use crate::errors::{ErrorKind, ErrorReport, ResultExt};
+use crate::freezer::{freeze_text_with, unfreeze_text};
+use crate::htmldoc::is_html_path;
+use crate::i18n::{tr, tr_args};
 use crate::ir::TranslationUnit;
 use crate::models::native::{NativeChatModel, NativeModelConfig};
 use crate::progress::ConsoleProgress;
-use crate::quality::must_extract_json_obj;
-use crate::sentinels::parse_slot_output;
-use crate::textutil::{auto_language_pair, is_trivial_sentinel_text, lang_label};
+use crate::quality::{must_extract_json_obj, validate_translation};
+use crate::sentinels::{parse_em_output, parse_slot_output};
+use crate::textdoc::TextDocKind;
+use crate::textutil::{
+    auto_language_pair, is_numeric_only_text, is_trivial_sentinel_text, lang_label,
+    localize_numbers, strip_sentinels,
+};
 use llama_cpp_2::llama_backend::LlamaBackend;
 
-use super::config::PipelineMode;
-use super::docmap::build_para_slot_units;
+use super::bundle::write_trace_bundle;
+use super::casing::apply_casing;
+use super::config::{NumericCells, PipelineMode, SlotProjection};
+use super::corpus::write_aligned_corpus;
+use super::coverage::{slot_coverage, write_coverage_report, CoverageReport};
+use super::docmap::{build_para_slot_units, SpanLayout};
+use super::events::PipelineEvent;
+use super::glossary::{glossary_hits, write_glossary_report};
+use super::hooks::RunStats;
 use super::memory::{build_memory, write_memory_file, ParaNotes};
+use super::noterefs::protect_note_refs;
+use super::project::ProjectMemory;
 use super::prompts::render_template;
+use super::quotes::convert_quotes;
+use super::stage::{PipelineStage, StageContext};
 use super::trace::TraceWriter;
+use super::validation::{write_validation_report, ValidationUnit};
+use super::workdir::{
+    record_artifacts, ARTIFACT_AUTOSAVE_TEXT, ARTIFACT_BLOBS, ARTIFACT_FINAL_TEXT, ARTIFACT_MASK,
+    ARTIFACT_OFFSETS, ARTIFACT_OUTPUT, ARTIFACT_SOURCE_TEXT, ARTIFACT_STRUCTURE,
+};
+use super::xrefs::CrossRefs;
 use super::PipelineConfig;
 
 mod basic;
+mod dryrun;
+mod embedded;
+mod experiment;
+mod htmlfile;
 mod notes;
+mod ocr;
+mod offline;
+mod pdf;
+mod pivot;
+mod projection;
+mod qa;
+mod review;
+mod scope;
 mod segmented;
 mod stitch;
+mod textfile;
+
+pub use experiment::ExperimentSpec;
 
 static LLAMA_BACKEND: Lazy<LlamaBackend> =
     Lazy::new(|| LlamaBackend::init().expect("init llama backend"));
@@ -38,30 +88,247 @@ static LLAMA_BACKEND: Lazy<LlamaBackend> =
 pub struct TranslatorPipeline {
     cfg: PipelineConfig,
     progress: ConsoleProgress,
-    trace: TraceWriter,
+    /// Shared with `overlap_worker` pipelines, so the trace limits hold for the whole run.
+    trace: Arc<TraceWriter>,
+    project: Option<ProjectMemory>,
+    /// Last translated (source, target) pairs for `context_overlap`.
+    chunk_context: VecDeque<(String, String)>,
+    /// Counts of the finished run, for `[hooks]` payloads.
+    run_stats: Option<RunStats>,
+    /// Repair calls made so far, against `pipeline.max_repairs_total`.
+    repairs_used: usize,
+    repair_budget_reported: bool,
+    /// Valid repairs by (frozen source, failure reason), reused for the rest of the run.
+    repair_cache: HashMap<(String, String), String>,
+    repair_cache_hits: usize,
+    /// Translate chunk sizing learned per backend during the run.
+    chunk_sizing: HashMap<String, ChunkSizing>,
+    /// Full mode with `slot_projection = "spans"` / `"paragraph"`: layout of each paragraph by
+    /// tu_id.
+    span_layouts: HashMap<usize, SpanLayout>,
+    /// Stages registered with `with_stage`, in order.
+    custom_stages: Vec<Box<dyn PipelineStage>>,
+    /// Receiver of `PipelineEvent`s set with `with_events`.
+    events: Option<EventSender<PipelineEvent>>,
+}
+
+/// Recent segmented-parse outcomes of translate chunks.
+const CHUNK_OUTCOME_WINDOW: usize = 8;
+
+/// Quality flag: the translation left one side of a footnote/endnote reference mark empty.
+const NOTE_REF_TEXT_MOVED: &str = "note_ref_text_moved";
+
+/// Translate chunk sizing of one backend: `overflow_factor` scales the char budget after context
+/// overflows; `scale` follows the segmented-parse failure rate (items and, up to 1.0, chars).
+#[derive(Debug)]
+struct ChunkSizing {
+    overflow_factor: f64,
+    scale: f64,
+    recent: VecDeque<bool>,
+}
+
+impl Default for ChunkSizing {
+    fn default() -> Self {
+        Self {
+            overflow_factor: 1.0,
+            scale: 1.0,
+            recent: VecDeque::new(),
+        }
+    }
 }
 
 impl TranslatorPipeline {
     pub fn new(cfg: PipelineConfig, progress: ConsoleProgress) -> Self {
         let trace = TraceWriter::new(cfg.trace_dir.clone(), cfg.trace_prompts)
-            .unwrap_or_else(|_| TraceWriter::new(cfg.trace_dir.clone(), false).expect("trace"));
+            .unwrap_or_else(|_| TraceWriter::new(cfg.trace_dir.clone(), false).expect("trace"))
+            .with_limits(cfg.trace_limits.clone());
+        Self::with_trace(cfg, progress, Arc::new(trace))
+    }
+
+    fn with_trace(cfg: PipelineConfig, progress: ConsoleProgress, trace: Arc<TraceWriter>) -> Self {
         Self {
             cfg,
             progress,
             trace,
+            project: None,
+            chunk_context: VecDeque::new(),
+            run_stats: None,
+            repairs_used: 0,
+            repair_budget_reported: false,
+            repair_cache: HashMap::new(),
+            repair_cache_hits: 0,
+            chunk_sizing: HashMap::new(),
+            span_layouts: HashMap::new(),
+            custom_stages: Vec::new(),
+            events: None,
         }
     }
 
+    /// Add a custom stage, run after the model stages of every translation (see
+    /// `PipelineStage`).
+    pub fn with_stage(mut self, stage: impl PipelineStage + 'static) -> Self {
+        self.custom_stages.push(Box::new(stage));
+        self
+    }
+
+    /// Make the run stop at the next chunk boundary or generated token once `token` is cancelled;
+    /// it then fails with `cancel::Cancelled` and keeps the trace and autosave files written so
+    /// far.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cfg.cancel = token;
+        self
+    }
+
+    /// Deliver `PipelineEvent`s to `events` (see `async_api::event_channel`).
+    pub fn with_events(mut self, events: EventSender<PipelineEvent>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// A pipeline for a stage run on another thread alongside this one (`overlap_controller`):
+    /// the same config, console progress (clones share the clock and sink), event receiver and
+    /// trace writer.
+    fn overlap_worker(&self) -> Self {
+        let mut worker =
+            Self::with_trace(self.cfg.clone(), self.progress.clone(), self.trace.clone());
+        worker.events = self.events.clone();
+        worker
+    }
+
+    /// The token `with_cancellation` set (a fresh one otherwise).
+    pub fn cancellation(&self) -> CancellationToken {
+        self.cfg.cancel.clone()
+    }
+
+    /// Report `event` on the console and to the `with_events` receiver.
+    fn emit(&self, event: PipelineEvent) {
+        match &event {
+            PipelineEvent::ChunkDone {
+                stage, done, total, ..
+            } => self.progress.progress(stage, *done, *total),
+            PipelineEvent::Autosave { path, done, total } => self.progress.info(tr_args(
+                "pipeline.autosave",
+                &[("done", done), ("total", total), ("path", &path.display())],
+            )),
+            _ => {}
+        }
+        if let Some(events) = &self.events {
+            events.send(event);
+        }
+    }
+
+    /// `ChunkDone` for the units at `indices` of `tus`.
+    fn emit_chunk_done(
+        &self,
+        stage: &str,
+        tus: &[TranslationUnit],
+        indices: &[usize],
+        done: usize,
+        total: usize,
+    ) {
+        self.emit(PipelineEvent::ChunkDone {
+            stage: stage.to_string(),
+            ids: indices.iter().map(|&i| tus[i].tu_id).collect(),
+            done,
+            total,
+        });
+    }
+
+    fn emit_stage_started(&self, stage: &str) {
+        self.emit(PipelineEvent::StageStarted {
+            stage: stage.to_string(),
+        });
+    }
+
+    /// Unit counts and validation outcomes of the last completed translation.
+    pub fn run_stats(&self) -> Option<&RunStats> {
+        self.run_stats.as_ref()
+    }
+
     pub fn translate_docx(&mut self, input: &Path, output: &Path) -> anyhow::Result<()> {
+        match self.trace.prune() {
+            Ok((0, _)) => {}
+            Ok((files, bytes)) => self.progress.info(tr_args(
+                "pipeline.pruned_trace",
+                &[
+                    ("files", &files),
+                    ("mib", &format!("{:.1}", bytes as f64 / (1024.0 * 1024.0))),
+                ],
+            )),
+            Err(err) => self.progress.info(tr_args(
+                "pipeline.prune_trace_failed",
+                &[("err", &format!("{err:#}"))],
+            )),
+        }
+        if self.cfg.deterministic {
+            self.progress.info(tr("pipeline.deterministic"));
+        }
+        if self.cfg.proofread {
+            self.progress.info(tr("pipeline.proofread"));
+        }
+        if self.cfg.seed_random {
+            self.progress
+                .info(tr_args("pipeline.seed", &[("seed", &self.cfg.seed)]));
+        }
+        let _ = self
+            .trace
+            .write_named_text("run.seed.txt", &format!("{}\n", self.cfg.seed));
+        if let Some(mode) = self.cfg.offline {
+            return self.translate_docx_offline(mode, input, output);
+        }
+        if let Some(kind) = TextDocKind::from_path(input) {
+            return self.translate_text_file(input, output, kind);
+        }
+        if is_html_path(input) {
+            return self.translate_html_file(input, output);
+        }
+        let embedded_work = if self.cfg.translate_embedded {
+            self.translate_embedded_objects(input, output)?
+        } else {
+            None
+        };
+        let input = embedded_work.as_deref().unwrap_or(input);
+        let ocr_work = match self.cfg.ocr_command.clone() {
+            Some(command) => self.append_ocr_text(&command, input, output)?,
+            None => None,
+        };
+        let input = ocr_work.as_deref().unwrap_or(input);
         match self.cfg.mode {
             PipelineMode::Basic => self.translate_docx_basic(input, output),
             PipelineMode::Full => self.translate_docx_full(input, output),
         }
     }
 
+    /// Pack trace files + resolved config into `out_zip` (see `bundle::write_trace_bundle`).
+    pub fn write_trace_bundle(
+        &self,
+        out_zip: &Path,
+        redact: bool,
+        error: Option<&anyhow::Error>,
+    ) -> anyhow::Result<()> {
+        let n = write_trace_bundle(&self.cfg, out_zip, redact, error)?;
+        let redacted = if redact {
+            tr("pipeline.trace_bundle_redacted")
+        } else {
+            String::new()
+        };
+        self.progress.info(tr_args(
+            "pipeline.trace_bundle",
+            &[
+                ("count", &n),
+                ("redacted", &redacted),
+                ("path", &out_zip.display()),
+            ],
+        ));
+        Ok(())
+    }
+
     fn translate_docx_full(&mut self, input: &Path, output: &Path) -> anyhow::Result<()> {
+        if self.cfg.resume {
+            self.progress.info(tr("pipeline.continue_full_mode"));
+        }
         self.progress
-            .info(format!("Read DOCX: {}", input.display()));
+            .info(tr_args("pipeline.read_docx", &[("path", &input.display())]));
         fs::create_dir_all(self.trace.dir())
             .with_context(|| format!("create trace dir: {}", self.trace.dir().display()))?;
 
@@ -72,11 +339,14 @@ impl TranslatorPipeline {
 
         let mut work_docx = input.to_path_buf();
         if let Some(rules_path) = self.cfg.docx_filter_rules.clone() {
-            self.progress
-                .info(format!("DOCX filter rules: {}", rules_path.display()));
+            self.progress.info(tr_args(
+                "pipeline.filter_rules",
+                &[("path", &rules_path.display())],
+            ));
             let rules = DocxFilterRules::from_toml_path(&rules_path)?;
             let filtered = self.trace.dir().join(format!("{stem}.filtered.docx"));
-            filter_docx_with_rules(input, &filtered, &rules)?;
+            let fixes = filter_docx_with_rules(input, &filtered, &rules, &self.cfg.package)?;
+            self.report_parse_fixes(&fixes);
             work_docx = filtered;
         }
 
@@ -87,27 +357,60 @@ impl TranslatorPipeline {
         let structure_json = self.trace.dir().join(format!("{stem}.structure.json"));
         let autosave_text_json = self.trace.dir().join(format!("{stem}.autosave.text.json"));
 
-        let source_text = extract_pure_text(&work_docx)?;
+        // Parsed once; text, structure, mask and docmap all read from this session.
+        let session =
+            DocumentSession::open_with(&work_docx, &self.cfg.package, &self.cfg.placeholder_prefix)
+                .kind(ErrorKind::InputDocx, "read input docx")?;
+        self.report_parse_fixes(session.parse_fixes());
+        let unsupported = self.warn_unsupported_content(&session);
+        let source_text =
+            pure_text_from_session(&session).kind(ErrorKind::InputDocx, "read input docx")?;
         fs::write(
             &text_source_json,
             serde_json::to_vec_pretty(&source_text).context("serialize source text json")?,
         )
         .with_context(|| format!("write source text json: {}", text_source_json.display()))?;
-        let _ = extract_structure_json(&work_docx, &structure_json);
-        extract_mask_json_and_offsets(&work_docx, &mask_json, &offsets_json, &blobs_bin)?;
-
-        let offsets: OffsetsJson = serde_json::from_slice(
-            &fs::read(&offsets_json)
-                .with_context(|| format!("read offsets json: {}", offsets_json.display()))?,
+        let review = self.load_review_worklist(stem, &source_text)?;
+        let _ = write_structure_json(&source_text, &structure_json);
+        let offsets = extract_mask_json_and_offsets_from(
+            &session,
+            &mask_json,
+            &offsets_json,
+            &blobs_bin,
+            MaskOptions::default(),
         )
-        .context("parse offsets json")?;
+        .kind(ErrorKind::InputDocx, "decompose input docx")?;
+
+        self.record_workdir(
+            input,
+            &[
+                (ARTIFACT_MASK, &mask_json),
+                (ARTIFACT_OFFSETS, &offsets_json),
+                (ARTIFACT_BLOBS, &blobs_bin),
+                (ARTIFACT_SOURCE_TEXT, &text_source_json),
+                (ARTIFACT_STRUCTURE, &structure_json),
+                (ARTIFACT_AUTOSAVE_TEXT, &autosave_text_json),
+            ],
+        )?;
 
-        let para_units = build_para_slot_units(&work_docx, &source_text, &offsets)?;
+        let para_units = build_para_slot_units(&session, &source_text, &offsets)?;
         let mut tus: Vec<TranslationUnit> = Vec::with_capacity(para_units.len());
         let mut slots_by_tu: HashMap<usize, Vec<usize>> = HashMap::new();
-        for p in para_units {
+        let mut note_refs_by_tu: HashMap<usize, Vec<(usize, usize)>> = HashMap::new();
+        let cross_refs = CrossRefs::collect(&para_units);
+        self.span_layouts.clear();
+        for mut p in para_units {
             slots_by_tu.insert(p.tu_id, p.slot_ids.clone());
-            let fr = freeze_text(&p.source_surface);
+            if !p.note_refs.is_empty() {
+                note_refs_by_tu.insert(p.tu_id, std::mem::take(&mut p.note_refs));
+            }
+            if let Some((surface, layout)) =
+                p.projected_surface(self.cfg.slot_projection, &source_text.slot_texts)
+            {
+                p.source_surface = surface;
+                self.span_layouts.insert(p.tu_id, layout);
+            }
+            let fr = freeze_text_with(&p.source_surface, &self.cfg.acronyms);
             tus.push(TranslationUnit {
                 tu_id: p.tu_id,
                 part_name: p.part_name,
@@ -129,65 +432,149 @@ impl TranslatorPipeline {
             });
         }
 
-        self.progress
-            .info(format!("Extracted {} paragraphs", tus.len()));
+        self.progress.info(tr_args(
+            "pipeline.extracted_paragraphs",
+            &[("count", &tus.len())],
+        ));
         if let Some(max_tus) = self.cfg.max_tus {
             let keep = max_tus.max(1).min(tus.len());
             tus.truncate(keep);
             let max_id = tus.last().map(|t| t.tu_id).unwrap_or(0);
             slots_by_tu.retain(|id, _| *id <= max_id);
-            self.progress.info(format!("Max TUs: {keep}"));
+            self.progress
+                .info(tr_args("pipeline.max_tus", &[("count", &keep)]));
+        }
+        if self.cfg.unit_filter.is_some() {
+            self.apply_unit_filter(&mut tus, |tu| (tu.tu_id, tu.scope_key.as_str()));
+            let kept: HashSet<usize> = tus.iter().map(|tu| tu.tu_id).collect();
+            slots_by_tu.retain(|id, _| kept.contains(id));
         }
 
         let (source_lang, target_lang) = self.resolve_lang_pair(&tus);
-        self.progress
-            .info(format!("Language: {source_lang} -> {target_lang}"));
+        self.progress.info(tr_args(
+            "pipeline.language",
+            &[("src", &source_lang), ("tgt", &target_lang)],
+        ));
+        self.route_translate_backend(&source_lang, &target_lang);
+        self.open_project_memory(&source_lang, &target_lang)?;
 
+        if !self.cfg.skipped_stages.is_empty() {
+            self.progress.info(tr_args(
+                "pipeline.stages_skipped",
+                &[("stages", &self.cfg.skipped_stages.join(", "))],
+            ));
+        }
+        if self.cfg.interactive {
+            self.progress.info(tr("pipeline.interactive_basic_only"));
+        }
+        if self.pivot_for(&source_lang, &target_lang).is_some() {
+            self.progress.info(tr("pipeline.pivot_basic_only"));
+        }
         let mut notes: HashMap<usize, ParaNotes> = HashMap::new();
-        if let Some(agent) = self.cfg.controller_backend.clone() {
-            self.progress.info(format!("Notes model: {}", agent.name));
-            self.run_para_notes(&agent, &target_lang, &tus, &mut notes)?;
+        let mut notes_worker = None;
+        let notes_backend = self
+            .cfg
+            .controller_backend
+            .clone()
+            .filter(|_| self.cfg.stage_enabled("notes"));
+        if let Some(agent) = notes_backend {
+            self.progress
+                .info(tr_args("pipeline.notes_model", &[("name", &agent.name)]));
+            if self.cfg.overlap_controller {
+                // Translate A does not read the notes, so the controller runs over the whole
+                // document alongside the whole of Translate A rather than one chunk ahead of it:
+                // there is no per-chunk dependency to pipeline. The notes are only needed from
+                // the memory snapshots / fuse onwards.
+                self.progress.info(tr("pipeline.notes_overlap"));
+                let mut worker = self.overlap_worker();
+                let (notes_tus, notes_lang) = (tus.clone(), target_lang.clone());
+                notes_worker = Some(std::thread::spawn(move || {
+                    let mut notes = HashMap::new();
+                    worker
+                        .run_para_notes(&agent, &notes_lang, &notes_tus, &mut notes)
+                        .map(|()| notes)
+                }));
+            } else {
+                self.run_para_notes(&agent, &target_lang, &tus, &mut notes)
+                    .stage("para_notes")?;
+            }
+        } else if self.cfg.stage_enabled("notes") {
+            // No controller: a cheap key-terms pass on the translate backend still gives the
+            // fuse/patch prompts and the memory snapshots some paragraph context.
+            let backend = self.cfg.translate_backend.clone();
+            self.progress.info(tr_args(
+                "pipeline.notes_key_terms",
+                &[("name", &backend.name)],
+            ));
+            self.run_key_terms_notes(&backend, &target_lang, &tus, &mut notes)
+                .stage("para_notes")?;
+        }
+        if notes_worker.is_none() {
+            self.write_memory_snapshot("stage0", &source_lang, &target_lang, &tus, &notes);
         }
-        self.write_memory_snapshot("stage0", &source_lang, &target_lang, &tus, &notes);
 
-        // Translate A
-        let translate_backend = self.cfg.translate_backend.clone();
-        let translate_prompts = self.cfg.prompts.for_backend(&translate_backend.name);
-        let prompt_translate_a = translate_prompts.translate_a.clone();
-        let prompt_translate_repair = translate_prompts.translate_repair.clone();
-        self.progress
-            .info(format!("Translate A: {}", translate_backend.name));
-        let mut text_a: PureTextJson = source_text.clone();
-        self.translate_stage(
-            &translate_backend,
-            &source_lang,
-            &target_lang,
-            &prompt_translate_a,
-            &prompt_translate_repair,
-            &mut tus,
-            TranslationSlot::A,
-            &mut text_a,
-            &slots_by_tu,
-            &mask_json,
-            &offsets_json,
-            &autosave_text_json,
-            output,
-        )?;
+        // Translate A (with a unit filter: onto the previous output)
+        let final_text_json = self.trace.dir().join(format!("{stem}.final.text.json"));
+        let base_text = self.unit_filter_base(&[&final_text_json], &source_text)?;
+        let mut text_a: PureTextJson = base_text.clone();
+        loop {
+            let translate_backend = self.cfg.translate_backend.clone();
+            let translate_prompts = self.cfg.prompts.for_backend(&translate_backend.name);
+            let prompt_translate_a = translate_prompts.translate_a.clone();
+            let prompt_translate_repair = translate_prompts.translate_repair.clone();
+            self.progress.info(tr_args(
+                "pipeline.translate_a",
+                &[("name", &translate_backend.name)],
+            ));
+            let result = self.translate_stage(
+                &translate_backend,
+                &source_lang,
+                &target_lang,
+                &prompt_translate_a,
+                &prompt_translate_repair,
+                &mut tus,
+                TranslationSlot::A,
+                &mut text_a,
+                &slots_by_tu,
+                &mask_json,
+                &offsets_json,
+                &autosave_text_json,
+                output,
+            );
+            match result {
+                Ok(()) => break,
+                Err(err) => self.fall_back_translate_backend(err).stage("translate_a")?,
+            }
+        }
         let a_text_json = self.trace.dir().join(format!("{stem}.A.text.json"));
         fs::write(
             &a_text_json,
             serde_json::to_vec_pretty(&text_a).context("serialize A text json")?,
         )
         .with_context(|| format!("write A text json: {}", a_text_json.display()))?;
-        let _ = write_variant_docx(&mask_json, &offsets_json, &a_text_json, output, "A");
+        let _ = self.write_variant_docx(&mask_json, &offsets_json, &a_text_json, output, "A");
+        if let Some(worker) = notes_worker {
+            notes = worker
+                .join()
+                .map_err(|_| anyhow::anyhow!("para_notes worker panicked"))
+                .and_then(|r| r)
+                .stage("para_notes")?;
+            self.write_memory_snapshot("stage0", &source_lang, &target_lang, &tus, &notes);
+        }
         self.write_memory_snapshot("afterA", &source_lang, &target_lang, &tus, &notes);
 
         // Translate B
-        if let Some(alt) = self.cfg.alt_translate_backend.clone() {
+        let alt_backend = self
+            .cfg
+            .alt_translate_backend
+            .clone()
+            .filter(|_| self.cfg.stage_enabled("alt_translate"));
+        if let Some(alt) = alt_backend {
             let alt_prompts = self.cfg.prompts.for_backend(&alt.name);
             let prompt_translate_b = alt_prompts.translate_b.clone();
             let prompt_translate_repair = alt_prompts.translate_repair.clone();
-            self.progress.info(format!("Translate B: {}", alt.name));
+            self.progress
+                .info(tr_args("pipeline.translate_b", &[("name", &alt.name)]));
             let mut text_b: PureTextJson = source_text.clone();
             self.translate_stage(
                 &alt,
@@ -203,21 +590,29 @@ impl TranslatorPipeline {
                 &offsets_json,
                 &autosave_text_json,
                 output,
-            )?;
+            )
+            .stage("translate_b")?;
             let b_text_json = self.trace.dir().join(format!("{stem}.B.text.json"));
             fs::write(
                 &b_text_json,
                 serde_json::to_vec_pretty(&text_b).context("serialize B text json")?,
             )
             .with_context(|| format!("write B text json: {}", b_text_json.display()))?;
-            let _ = write_variant_docx(&mask_json, &offsets_json, &b_text_json, output, "B");
+            let _ = self.write_variant_docx(&mask_json, &offsets_json, &b_text_json, output, "B");
             self.write_memory_snapshot("afterB", &source_lang, &target_lang, &tus, &notes);
         }
 
         // Fuse AB via agent (paragraphs only). Others default to A.
-        if let Some(agent) = self.cfg.controller_backend.clone() {
-            self.progress.info(format!("Fuse AB via: {}", agent.name));
-            self.run_fuse_stage(&agent, &source_lang, &target_lang, &mut tus, &notes)?;
+        let fuse_backend = self
+            .cfg
+            .controller_backend
+            .clone()
+            .filter(|_| self.cfg.stage_enabled("fuse"));
+        if let Some(agent) = fuse_backend {
+            self.progress
+                .info(tr_args("pipeline.fuse_via", &[("name", &agent.name)]));
+            self.run_fuse_stage(&agent, &source_lang, &target_lang, &mut tus, &notes)
+                .stage("fuse")?;
         } else {
             for tu in &mut tus {
                 if tu.final_translation.is_none() {
@@ -228,7 +623,7 @@ impl TranslatorPipeline {
         self.write_memory_snapshot("afterFuse", &source_lang, &target_lang, &tus, &notes);
 
         // Apply final into slot_texts.
-        let mut text_final: PureTextJson = source_text;
+        let mut text_final: PureTextJson = base_text;
         for tu in &tus {
             let slots = slots_by_tu.get(&tu.tu_id).cloned().unwrap_or_default();
             if slots.is_empty() {
@@ -240,7 +635,8 @@ impl TranslatorPipeline {
                 .or(tu.draft_translation.as_deref())
                 .unwrap_or(&tu.frozen_surface);
             self.apply_slot_translation(&mut text_final, &slots, tu, t)
-                .with_context(|| format!("apply final tu_id={}", tu.tu_id))?;
+                .with_context(|| format!("apply final tu_id={}", tu.tu_id))
+                .kind(ErrorKind::Validation, "final translation rejected")?;
         }
         self.write_progress_docx(
             &mask_json,
@@ -253,42 +649,164 @@ impl TranslatorPipeline {
         )?;
 
         // Global stitch audit + patch (2 rounds max)
-        if let (Some(agent), Some(rewrite_backend)) = (
-            self.cfg.controller_backend.clone(),
-            self.cfg.rewrite_backend.clone(),
-        ) {
-            self.run_stitch_audit_and_patch(
-                &agent,
-                &rewrite_backend,
-                &source_lang,
-                &target_lang,
-                &mut tus,
-                &notes,
-                &mut text_final,
-                &slots_by_tu,
-                &mask_json,
-                &offsets_json,
-                &autosave_text_json,
-                output,
-            )?;
+        let audit_backend = self
+            .cfg
+            .controller_backend
+            .clone()
+            .filter(|_| self.cfg.stage_enabled("stitch_audit"));
+        let patch_backend = self
+            .cfg
+            .rewrite_backend
+            .clone()
+            .filter(|_| self.cfg.stage_enabled("patch"));
+        // Without a patch backend the audit only runs when `patch = false` asks for a report.
+        let audit_backend =
+            audit_backend.filter(|_| patch_backend.is_some() || !self.cfg.stage_enabled("patch"));
+        if !review.is_empty() {
+            match patch_backend.as_ref() {
+                Some(backend) => self
+                    .run_review_patch(
+                        backend,
+                        &source_lang,
+                        &target_lang,
+                        &mut tus,
+                        &notes,
+                        &mut text_final,
+                        &slots_by_tu,
+                        &review,
+                    )
+                    .stage("patch")?,
+                None => self.progress.info(tr("pipeline.review_patch_skipped")),
+            }
+        }
+        let mut stitch_unresolved = HashMap::new();
+        if let Some(agent) = audit_backend {
+            stitch_unresolved = self
+                .run_stitch_audit_and_patch(
+                    &agent,
+                    patch_backend.as_ref(),
+                    &source_lang,
+                    &target_lang,
+                    &mut tus,
+                    &notes,
+                    &mut text_final,
+                    &slots_by_tu,
+                    &mask_json,
+                    &offsets_json,
+                    &autosave_text_json,
+                    output,
+                )
+                .stage("stitch_audit")?;
         }
 
+        for idx in self.run_custom_stages(stem, &source_lang, &target_lang, &mut tus)? {
+            let tu = &tus[idx];
+            let slots = slots_by_tu.get(&tu.tu_id).cloned().unwrap_or_default();
+            let Some(t) = current_translation(tu) else {
+                continue;
+            };
+            if !slots.is_empty() {
+                self.apply_slot_translation(&mut text_final, &slots, tu, t)
+                    .with_context(|| format!("apply custom stage output tu_id={}", tu.tu_id))
+                    .kind(ErrorKind::Validation, "custom stage translation rejected")?;
+            }
+        }
+        let para_slots: Vec<(usize, &[usize])> = slots_by_tu
+            .iter()
+            .map(|(&id, slots)| (id, slots.as_slice()))
+            .collect();
+        self.apply_target_style(&source_text, &para_slots, &target_lang, &mut text_final);
+        let fields = self.cfg.slot_projection == SlotProjection::Markers;
+        self.harmonize_cross_refs(&cross_refs, &source_text, &mut text_final, fields);
+        let moved = self.protect_note_ref_context(&source_text, &note_refs_by_tu, &mut text_final);
+        for tu in tus.iter_mut().filter(|tu| moved.contains(&tu.tu_id)) {
+            tu.qe_flags.push(NOTE_REF_TEXT_MOVED.to_string());
+        }
+        self.report_projection_failures(&tus);
+        let flagged = tus
+            .iter()
+            .filter_map(|tu| ValidationUnit::from_units(tu.tu_id, &tu.scope_key, [tu]))
+            .map(|mut unit| {
+                unit.issues = stitch_unresolved.remove(&unit.tu_id).unwrap_or_default();
+                unit
+            })
+            .collect();
+        self.write_validation_report(stem, flagged);
+
         // Write final output
-        self.progress
-            .info(format!("Write output: {}", output.display()));
-        let final_text_json = self.trace.dir().join(format!("{stem}.final.text.json"));
+        self.progress.info(tr_args(
+            "pipeline.write_output",
+            &[("path", &output.display())],
+        ));
         fs::write(
             &final_text_json,
             serde_json::to_vec_pretty(&text_final).context("serialize final text json")?,
         )
         .with_context(|| format!("write final text json: {}", final_text_json.display()))?;
-        merge_mask_json_and_offsets(&mask_json, &offsets_json, &final_text_json, output)?;
+        self.merge_docx(&mask_json, &offsets_json, &final_text_json, output)
+            .kind(ErrorKind::Merge, "write output docx")?;
+        self.record_workdir(
+            input,
+            &[
+                (ARTIFACT_FINAL_TEXT, &final_text_json),
+                (ARTIFACT_OUTPUT, output),
+            ],
+        )?;
+        self.report_coverage(stem, &offsets, &source_text, &text_final, &unsupported);
 
         self.write_memory_snapshot("final", &source_lang, &target_lang, &tus, &notes);
-        self.progress.info("Done.".to_string());
+        self.run_qa_sample(stem, &source_lang, &target_lang, &mut tus)?;
+        self.write_aligned_corpus(stem, &tus, Some(&text_final));
+        let pairs: Vec<(usize, String, String)> = tus
+            .iter()
+            .filter_map(|tu| {
+                let t = tu.final_translation.as_deref()?;
+                Some((
+                    tu.tu_id,
+                    tu.source_surface.clone(),
+                    unfreeze_text(t, &tu.nt_map),
+                ))
+            })
+            .collect();
+        self.save_project_memory(stem, &text_final.placeholder_prefix, input, &pairs)?;
+        self.progress.info(tr("pipeline.done"));
         Ok(())
     }
 
+    /// `pipeline.numeric_cells`: a unit of only numbers, dates and currency codes is copied
+    /// through like a letterless one instead of going to the model.
+    fn is_numeric_cell(&self, source: &str) -> bool {
+        self.cfg.numeric_cells != NumericCells::Translate && is_numeric_only_text(source)
+    }
+
+    /// The source text a copied-through unit keeps: with the target language's number
+    /// separators for `numeric_cells = "locale"`.
+    fn passthrough_text(&self, source: &str, source_lang: &str, target_lang: &str) -> String {
+        if self.cfg.numeric_cells == NumericCells::Locale && is_numeric_only_text(source) {
+            localize_numbers(source, source_lang, target_lang)
+        } else {
+            source.to_string()
+        }
+    }
+
+    /// Full mode counterpart of `passthrough_text`: the unit's numbers are frozen, so their
+    /// separators change in its token map.
+    fn localize_numeric_unit(
+        &self,
+        tu: &mut TranslationUnit,
+        source_lang: &str,
+        target_lang: &str,
+    ) {
+        if self.cfg.numeric_cells != NumericCells::Locale
+            || !is_numeric_only_text(&tu.source_surface)
+        {
+            return;
+        }
+        for original in tu.nt_map.values_mut() {
+            *original = localize_numbers(original, source_lang, target_lang);
+        }
+    }
+
     fn resolve_lang_pair(&self, tus: &[TranslationUnit]) -> (String, String) {
         match (self.cfg.source_lang.clone(), self.cfg.target_lang.clone()) {
             (Some(s), Some(t)) => (s, t),
@@ -303,9 +821,674 @@ impl TranslatorPipeline {
                         break;
                     }
                 }
-                auto_language_pair(&excerpts)
+                self.auto_lang_pair(&excerpts)
+            }
+        }
+    }
+
+    /// Language pair detected from `excerpts` when `--source-lang` / `--target-lang` do not
+    /// give both; `--proofread` keeps the (given or detected) source language as the target.
+    pub(super) fn auto_lang_pair(&self, excerpts: &[String]) -> (String, String) {
+        let (source, target) = auto_language_pair(excerpts);
+        if !self.cfg.proofread {
+            return (source, target);
+        }
+        let lang = self
+            .cfg
+            .source_lang
+            .clone()
+            .or_else(|| self.cfg.target_lang.clone())
+            .unwrap_or(source);
+        (lang.clone(), lang)
+    }
+
+    /// `[routing]`: switch to the translate backend configured for the document's language pair.
+    fn route_translate_backend(&mut self, source_lang: &str, target_lang: &str) {
+        let Some(route) = self
+            .cfg
+            .routes
+            .iter()
+            .find(|r| r.matches(source_lang, target_lang))
+            .cloned()
+        else {
+            return;
+        };
+        self.use_translate_backend(route.backend);
+        self.progress.info(tr_args(
+            "pipeline.routed",
+            &[
+                ("src", &source_lang),
+                ("tgt", &target_lang),
+                ("name", &self.cfg.translate_backend.name),
+            ],
+        ));
+    }
+
+    /// Make `backend` the translate backend; the one it replaces heads the fallback chain.
+    fn use_translate_backend(&mut self, backend: crate::config::ResolvedBackend) {
+        if backend.name == self.cfg.translate_backend.name {
+            return;
+        }
+        let previous = std::mem::replace(&mut self.cfg.translate_backend, backend);
+        self.cfg.translate_fallbacks.insert(0, previous);
+        let name = self.cfg.translate_backend.name.clone();
+        self.cfg.translate_fallbacks.retain(|b| b.name != name);
+    }
+
+    /// Load the current translate backend; on failure fall back along the chain.
+    fn load_translate_model(
+        &mut self,
+    ) -> anyhow::Result<(NativeChatModel, crate::config::ResolvedBackend)> {
+        loop {
+            let backend = self.cfg.translate_backend.clone();
+            match load_model(&self.cfg, &backend) {
+                Ok(model) => return Ok((model, backend)),
+                Err(err) => self.fall_back_translate_backend(err)?,
+            }
+        }
+    }
+
+    /// Switch to the next `translate_backend` chain entry after a model failure (load error,
+    /// OOM, context overflow, repeated chat errors). Other errors, or an exhausted chain,
+    /// are returned unchanged.
+    fn fall_back_translate_backend(&mut self, err: anyhow::Error) -> anyhow::Result<()> {
+        let kind = ErrorReport::from_error(&err).kind;
+        let model_failure = matches!(
+            kind,
+            ErrorKind::Model | ErrorKind::ModelOom | ErrorKind::ContextOverflow
+        );
+        // A deterministic run fails rather than continue on a backend with other chunk limits.
+        if !model_failure || self.cfg.deterministic || self.cfg.translate_fallbacks.is_empty() {
+            return Err(err);
+        }
+        let next = self.cfg.translate_fallbacks.remove(0);
+        self.progress.info(tr_args(
+            "pipeline.backend_fallback",
+            &[
+                ("name", &self.cfg.translate_backend.name),
+                ("err", &format!("{err:#}")),
+                ("next", &next.name),
+            ],
+        ));
+        self.cfg.translate_backend = next;
+        Ok(())
+    }
+
+    /// Char budget of a translate chunk for `backend` (chars are a rough stand-in for tokens),
+    /// less the chunk context the next prompt carries.
+    fn chunk_char_budget(&self, backend: &crate::config::ResolvedBackend) -> usize {
+        let base = self.cfg.chunking.char_budget(backend.ctx_size);
+        let factor = self
+            .chunk_sizing
+            .get(&backend.name)
+            .map_or(1.0, |s| s.overflow_factor * s.scale.min(1.0));
+        ((base as f64 * factor) as usize).saturating_sub(self.render_chunk_context().len())
+    }
+
+    /// Item limit of a translate chunk for `backend`: `[pipeline.chunking] max_items`, else the
+    /// stage's `default`.
+    fn chunk_max_items(&self, backend: &crate::config::ResolvedBackend, default: usize) -> usize {
+        let max_items = self.cfg.chunking.max_items.unwrap_or(default);
+        let scale = self
+            .chunk_sizing
+            .get(&backend.name)
+            .map_or(1.0, |s| s.scale);
+        ((max_items as f64 * scale).round() as usize).max(1)
+    }
+
+    /// `pipeline.adaptive_chunks`: record whether a chunk's segmented output parsed. A failure
+    /// rate above a quarter of the recent chunks shrinks later chunks of this backend; a full
+    /// window without failures grows them again (up to twice the stage default).
+    fn record_chunk_outcome(
+        &mut self,
+        backend: &crate::config::ResolvedBackend,
+        items: usize,
+        parsed: bool,
+    ) {
+        if !self.cfg.adaptive_chunks || items < 2 {
+            return;
+        }
+        let sizing = self.chunk_sizing.entry(backend.name.clone()).or_default();
+        sizing.recent.push_back(parsed);
+        if sizing.recent.len() > CHUNK_OUTCOME_WINDOW {
+            sizing.recent.pop_front();
+        }
+        let failed = sizing.recent.iter().filter(|ok| !**ok).count();
+        let attempts = sizing.recent.len();
+        let scale = if !parsed && attempts >= 2 && failed * 4 > attempts {
+            (sizing.scale * 0.75).max(0.05)
+        } else if failed == 0 && attempts == CHUNK_OUTCOME_WINDOW && sizing.scale < 2.0 {
+            (sizing.scale * 1.25).min(2.0)
+        } else {
+            return;
+        };
+        sizing.scale = scale;
+        sizing.recent.clear();
+        self.progress.info(tr_args(
+            "pipeline.chunk_size_adjusted",
+            &[
+                ("name", &backend.name),
+                ("scale", &format!("{scale:.2}")),
+                ("failed", &failed),
+                ("attempts", &attempts),
+            ],
+        ));
+    }
+
+    /// A chunk prompt did not fit the context: shrink the budget of later chunks by the overshoot
+    /// llama reported (at least by a quarter), and log the new factor.
+    fn note_context_overflow(
+        &mut self,
+        backend: &crate::config::ResolvedBackend,
+        stage: &str,
+        first: usize,
+        last: usize,
+        err: &anyhow::Error,
+    ) {
+        let overshoot = PROMPT_TOO_LONG_RE
+            .captures(&format!("{err:#}"))
+            .and_then(|c| Some((c[1].parse::<f64>().ok()?, c[2].parse::<f64>().ok()?)))
+            .map(|(prompt, n_ctx)| n_ctx * 0.9 / prompt.max(1.0))
+            .unwrap_or(0.75)
+            .min(0.75);
+        let sizing = self.chunk_sizing.entry(backend.name.clone()).or_default();
+        sizing.overflow_factor = (sizing.overflow_factor * overshoot).max(0.1);
+        let factor = sizing.overflow_factor;
+        self.progress.info(tr_args(
+            "pipeline.context_overflow_split",
+            &[
+                ("stage", &stage),
+                ("first", &first),
+                ("last", &last),
+                ("factor", &format!("{factor:.2}")),
+            ],
+        ));
+    }
+
+    fn open_project_memory(&mut self, source_lang: &str, target_lang: &str) -> anyhow::Result<()> {
+        let Some(dir) = self.cfg.project_dir.clone() else {
+            return Ok(());
+        };
+        let mut project = ProjectMemory::open(&dir)?;
+        project.load(source_lang, target_lang)?;
+        self.progress.info(tr_args(
+            "pipeline.project_memory",
+            &[
+                ("path", &project.db_path().display()),
+                ("terms", &project.term_count()),
+                ("paragraphs", &project.reference_count()),
+            ],
+        ));
+        self.project = Some(project);
+        Ok(())
+    }
+
+    fn save_project_memory(
+        &mut self,
+        stem: &str,
+        doc_key: &str,
+        input: &Path,
+        pairs: &[(usize, String, String)],
+    ) -> anyhow::Result<()> {
+        // Against the glossary the run was prompted with, before this document adds terms.
+        self.report_glossary_hits(stem, pairs);
+        let Some(project) = self.project.as_mut() else {
+            return Ok(());
+        };
+        let file_name = input
+            .file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or("input.docx");
+        let learned = project
+            .record_document(doc_key, file_name, pairs)
+            .context("save project memory")?;
+        self.progress.info(tr_args(
+            "pipeline.project_memory_updated",
+            &[("paragraphs", &pairs.len()), ("terms", &learned)],
+        ));
+        Ok(())
+    }
+
+    /// Footnote/endnote reference marks in the final slot texts (`protect_note_refs`, by
+    /// paragraph id): repairs the spaces and punctuation around them and returns the paragraphs
+    /// whose text moved across a mark entirely.
+    fn protect_note_ref_context(
+        &self,
+        source: &PureTextJson,
+        note_refs: &HashMap<usize, Vec<(usize, usize)>>,
+        output: &mut PureTextJson,
+    ) -> HashSet<usize> {
+        let mut fixed = 0usize;
+        let mut moved = HashSet::new();
+        for (&tu_id, refs) in note_refs {
+            let check = protect_note_refs(source, refs, output);
+            fixed += check.fixed;
+            if check.suspicious > 0 {
+                moved.insert(tu_id);
             }
         }
+        if fixed > 0 || !moved.is_empty() {
+            self.progress.info(tr_args(
+                "pipeline.note_refs",
+                &[("fixed", &fixed), ("flagged", &moved.len())],
+            ));
+        }
+        moved
+    }
+
+    /// `pipeline.cross_refs`: `REF` field results, quoted heading titles and literal TOC entries
+    /// follow the final translated headings (`CrossRefs::harmonize` / `check_toc`); TOC entries
+    /// that still differ are reported. `pipeline.update_fields` marks documents with a TOC field
+    /// for a field update on open.
+    fn harmonize_cross_refs(
+        &self,
+        refs: &CrossRefs,
+        source: &PureTextJson,
+        output: &mut PureTextJson,
+        fields: bool,
+    ) {
+        const MAX_LISTED: usize = 10;
+        if self.cfg.update_fields && refs.has_toc_field() {
+            output.update_fields = true;
+            self.progress.info(tr("pipeline.toc_update_fields"));
+        }
+        if !self.cfg.cross_refs {
+            return;
+        }
+        let check = refs.harmonize(source, output, fields);
+        if check.fields > 0 || check.quoted > 0 {
+            self.progress.info(tr_args(
+                "pipeline.cross_refs",
+                &[("fields", &check.fields), ("quoted", &check.quoted)],
+            ));
+        }
+        let toc = refs.check_toc(source, output, fields);
+        if toc.fixed > 0 {
+            self.progress
+                .info(tr_args("pipeline.toc_fixed", &[("count", &toc.fixed)]));
+        }
+        for m in toc.mismatches.iter().take(MAX_LISTED) {
+            self.progress.info(tr_args(
+                "pipeline.toc_mismatch",
+                &[
+                    ("tu", &m.tu_id),
+                    ("entry", &m.entry),
+                    ("heading", &m.heading),
+                ],
+            ));
+        }
+    }
+
+    /// Post-pass on the final slot texts of `output`, paragraph by paragraph (`units`: paragraph
+    /// id and slot ids): `[pipeline.casing]`, then `pipeline.quote_style`.
+    fn apply_target_style(
+        &self,
+        source: &PureTextJson,
+        units: &[(usize, &[usize])],
+        target_lang: &str,
+        output: &mut PureTextJson,
+    ) {
+        if !self.cfg.casing.is_noop() {
+            let changed = apply_casing(&self.cfg.casing, target_lang, source, units, output);
+            if changed > 0 {
+                self.progress
+                    .info(tr_args("pipeline.casing_applied", &[("count", &changed)]));
+            }
+        }
+        if let Some(marks) = self.cfg.quote_style.marks(target_lang) {
+            let changed = convert_quotes(&marks, units, output);
+            if changed > 0 {
+                self.progress
+                    .info(tr_args("pipeline.quotes_converted", &[("count", &changed)]));
+            }
+        }
+    }
+
+    /// `<stem>.glossary.json` in the trace dir: per project glossary term found in the source,
+    /// how many occurrences the translation follows; the least followed terms go to the console.
+    fn report_glossary_hits(&self, stem: &str, pairs: &[(usize, String, String)]) {
+        const MAX_LISTED: usize = 10;
+        let Some(project) = self.project.as_ref() else {
+            return;
+        };
+        if project.term_count() == 0 {
+            return;
+        }
+        let report = glossary_hits(project.terms(), pairs);
+        let path = self.trace.dir().join(format!("{stem}.glossary.json"));
+        if let Err(err) = write_glossary_report(&path, &report) {
+            self.progress.info(tr_args(
+                "pipeline.glossary_report_failed",
+                &[("err", &format!("{err:#}"))],
+            ));
+            return;
+        }
+        self.progress.info(tr_args(
+            "pipeline.glossary_report",
+            &[
+                ("found", &report.terms_found),
+                ("total", &report.glossary_terms),
+                ("compliant", &report.compliant),
+                ("occurrences", &report.occurrences),
+                ("pct", &format!("{:.0}", report.rate() * 100.0)),
+                ("path", &path.display()),
+            ],
+        ));
+        for term in report
+            .terms
+            .iter()
+            .filter(|t| t.compliant < t.occurrences)
+            .take(MAX_LISTED)
+        {
+            self.progress.info(tr_args(
+                "pipeline.glossary_term",
+                &[
+                    ("src", &term.src),
+                    ("tgt", &term.tgt),
+                    ("compliant", &term.compliant),
+                    ("occurrences", &term.occurrences),
+                ],
+            ));
+        }
+    }
+
+    /// Start a new translate stage without context from the previous one.
+    fn reset_chunk_context(&mut self) {
+        self.chunk_context.clear();
+    }
+
+    /// Remember a finished unit for the next chunk's read-only context (`context_overlap`).
+    fn remember_chunk_context(&mut self, source: &str, translation: &str) {
+        let keep = self.cfg.context_overlap;
+        if keep == 0 {
+            return;
+        }
+        let source = strip_sentinels(source).trim().to_string();
+        let translation = strip_sentinels(translation).trim().to_string();
+        if source.is_empty() || translation.is_empty() {
+            return;
+        }
+        self.chunk_context.push_back((source, translation));
+        let max_chars = self.cfg.context_overlap_max_chars;
+        let mut chars: usize = self
+            .chunk_context
+            .iter()
+            .map(|(s, t)| s.chars().count() + t.chars().count())
+            .sum();
+        while self.chunk_context.len() > keep || chars > max_chars {
+            let Some((s, t)) = self.chunk_context.pop_front() else {
+                break;
+            };
+            chars -= s.chars().count() + t.chars().count();
+        }
+    }
+
+    fn render_chunk_context(&self) -> String {
+        if self.chunk_context.is_empty() {
+            return String::new();
+        }
+        let mut out = String::from(
+            "CONTEXT (previous segments, already translated; read-only: do NOT translate or output them):\n",
+        );
+        for (source, translation) in &self.chunk_context {
+            out.push_str(&format!(
+                "- SOURCE: {source}\n  TRANSLATION: {translation}\n"
+            ));
+        }
+        out
+    }
+
+    /// Render a segmented translation prompt, prepending the project glossary and the previous
+    /// chunk's context when present. Templates may place them with `{{glossary}}` / `{{context}}`.
+    fn render_translate_prompt(
+        &self,
+        prompt_tmpl: &str,
+        source_lang: &str,
+        target_lang: &str,
+        tu_block: &str,
+        sources: &[&str],
+    ) -> String {
+        let glossary = self
+            .project
+            .as_ref()
+            .map(|p| p.glossary_for(sources))
+            .unwrap_or_default();
+        let context = self.render_chunk_context();
+        let source_lang_label = lang_label(source_lang);
+        let target_lang_label = lang_label(target_lang);
+        let mut prompt = render_template(
+            prompt_tmpl,
+            &[
+                ("source_lang", &source_lang_label),
+                ("target_lang", &target_lang_label),
+                ("tu_block", tu_block),
+                ("glossary", &glossary),
+                ("context", &context),
+            ],
+        );
+        if !context.is_empty() && !prompt_tmpl.contains("{{context}}") {
+            prompt = format!("{context}\n{prompt}");
+        }
+        if !glossary.is_empty() && !prompt_tmpl.contains("{{glossary}}") {
+            prompt = format!("{glossary}\n{prompt}");
+        }
+        prompt
+    }
+
+    /// Report how many of the translated `tus` fell back to their source text; above
+    /// `--fail-on-fallback` percent that is a validation error. Returns the fallback tu_ids.
+    fn check_source_fallbacks(&self, tus: &[TranslationUnit]) -> anyhow::Result<Vec<usize>> {
+        let translated = tus
+            .iter()
+            .filter(|tu| tu.draft_translation.is_some())
+            .count();
+        let fallbacks: Vec<usize> = tus
+            .iter()
+            .filter(|tu| tu.qe_flags.iter().any(|f| f == "source_fallback"))
+            .map(|tu| tu.tu_id)
+            .collect();
+        if translated == 0 {
+            return Ok(fallbacks);
+        }
+        let pct = fallbacks.len() as f64 * 100.0 / translated as f64;
+        let pct_text = format!("{pct:.1}");
+        if !fallbacks.is_empty() || self.cfg.fail_on_fallback.is_some() {
+            self.progress.info(tr_args(
+                "pipeline.fallback_summary",
+                &[
+                    ("count", &fallbacks.len()),
+                    ("total", &translated),
+                    ("pct", &pct_text),
+                ],
+            ));
+        }
+        if let Some(limit) = self.cfg.fail_on_fallback.filter(|&limit| pct > limit) {
+            return Err(anyhow!(
+                "{} of {translated} units fell back to source text ({pct_text}% > --fail-on-fallback {limit}%)",
+                fallbacks.len()
+            ))
+            .kind(ErrorKind::Validation, "source fallback threshold");
+        }
+        Ok(fallbacks)
+    }
+
+    /// `<stem>.aligned.jsonl` in the trace dir (a failure is reported, not fatal); also records
+    /// the run's `RunStats` from the same units.
+    fn write_aligned_corpus(
+        &mut self,
+        stem: &str,
+        tus: &[TranslationUnit],
+        text: Option<&PureTextJson>,
+    ) {
+        self.run_stats = Some(RunStats {
+            repairs_reused: self.repair_cache_hits,
+            ..RunStats::from_units(tus)
+        });
+        if self.repair_cache_hits > 0 {
+            self.progress.info(tr_args(
+                "pipeline.repairs_reused",
+                &[("count", &self.repair_cache_hits)],
+            ));
+        }
+        let path = self.trace.dir().join(format!("{stem}.aligned.jsonl"));
+        match write_aligned_corpus(&path, tus, text, self.cfg.corpus_strip_tokens) {
+            Ok(count) => self.progress.info(tr_args(
+                "pipeline.aligned_corpus",
+                &[("count", &count), ("path", &path.display())],
+            )),
+            Err(err) => self.progress.info(tr_args(
+                "pipeline.aligned_corpus_failed",
+                &[("err", &format!("{err:#}"))],
+            )),
+        }
+    }
+
+    /// Run the `with_stage` stages over `tus`; returns the indices of the units whose translation
+    /// a stage changed, to be written back into the output.
+    fn run_custom_stages(
+        &mut self,
+        stem: &str,
+        source_lang: &str,
+        target_lang: &str,
+        tus: &mut [TranslationUnit],
+    ) -> anyhow::Result<Vec<usize>> {
+        // Last point before the output is written in every mode.
+        self.cfg.cancel.check()?;
+        if self.custom_stages.is_empty() {
+            return Ok(Vec::new());
+        }
+        let before: Vec<Option<String>> = tus
+            .iter()
+            .map(|tu| current_translation(tu).map(str::to_string))
+            .collect();
+        let ctx = StageContext {
+            stem,
+            source_lang,
+            target_lang,
+            trace_dir: self.trace.dir(),
+        };
+        for stage in &mut self.custom_stages {
+            self.progress
+                .info(tr_args("pipeline.custom_stage", &[("name", &stage.name())]));
+            let name = stage.name().to_string();
+            // `emit` would borrow all of `self` while `custom_stages` is borrowed.
+            if let Some(events) = &self.events {
+                events.send(PipelineEvent::StageStarted {
+                    stage: name.clone(),
+                });
+            }
+            stage.run(&ctx, tus).stage(&name)?;
+        }
+        Ok((0..tus.len())
+            .filter(|&i| current_translation(&tus[i]) != before[i].as_deref())
+            .collect())
+    }
+
+    /// `[pipeline.stages] <stage> = "flagged"`: whether `tu` is in the stage's scope (units with
+    /// quality flags); every unit otherwise.
+    fn in_stage_scope(&self, stage: &str, tu: &TranslationUnit) -> bool {
+        !self.cfg.stage_flagged_only(stage) || !tu.qe_flags.is_empty()
+    }
+
+    /// How many of `total` units a `"flagged"` stage runs on, on the console.
+    fn report_stage_scope(&self, stage: &str, count: usize, total: usize) {
+        if self.cfg.stage_flagged_only(stage) {
+            self.progress.info(tr_args(
+                "pipeline.stage_flagged",
+                &[("stage", &stage), ("count", &count), ("total", &total)],
+            ));
+        }
+    }
+
+    /// `<stem>.validation.json` in the trace dir, input of `--from-report` (a failure is reported,
+    /// not fatal). Under `--range` / `--scope` / `--from-report` the entries of paragraphs this run
+    /// left alone are carried over from the existing report.
+    fn write_validation_report(&self, stem: &str, units: Vec<ValidationUnit>) {
+        let path = self.trace.dir().join(format!("{stem}.validation.json"));
+        let filter = self.cfg.unit_filter.as_ref();
+        let keep = |u: &ValidationUnit| filter.is_some_and(|f| !f.keeps(u.tu_id, &u.scope_key));
+        match write_validation_report(&path, units, keep) {
+            Ok(count) => self.progress.info(tr_args(
+                "pipeline.validation_report",
+                &[("count", &count), ("path", &path.display())],
+            )),
+            Err(err) => self.progress.info(tr_args(
+                "pipeline.validation_report_failed",
+                &[("err", &format!("{err:#}"))],
+            )),
+        }
+    }
+
+    /// `<stem>.coverage.json` in the trace dir for the merged `output` text, with a console line
+    /// per part that kept source text (a failure is reported, not fatal).
+    fn report_coverage(
+        &self,
+        stem: &str,
+        offsets: &OffsetsJson,
+        source: &PureTextJson,
+        output: &PureTextJson,
+        unsupported: &[UnsupportedContent],
+    ) {
+        let report = CoverageReport {
+            unsupported: unsupported.to_vec(),
+            ..slot_coverage(offsets, source, output)
+        };
+        let path = self.trace.dir().join(format!("{stem}.coverage.json"));
+        if let Err(err) = write_coverage_report(&path, &report) {
+            self.progress.info(tr_args(
+                "pipeline.coverage_failed",
+                &[("err", &format!("{err:#}"))],
+            ));
+            return;
+        }
+        self.progress.info(tr_args(
+            "pipeline.coverage",
+            &[
+                ("translated", &report.translated),
+                ("total", &report.slots),
+                ("path", &path.display()),
+            ],
+        ));
+        for part in report.parts.iter().filter(|p| p.unchanged > 0) {
+            self.progress.info(tr_args(
+                "pipeline.coverage_part",
+                &[
+                    ("part", &part.part_name),
+                    ("kind", &part.kind),
+                    ("unchanged", &part.unchanged),
+                    ("total", &part.slots),
+                ],
+            ));
+        }
+        if !unsupported.is_empty() {
+            self.progress.info(tr_args(
+                "pipeline.unsupported_content",
+                &[("list", &unsupported_list(unsupported))],
+            ));
+        }
+    }
+
+    /// Announce the content of the input the run leaves untranslated (see
+    /// `docx::unsupported`); embedded Word/Excel documents count unless `translate_embedded`.
+    fn warn_unsupported_content(&self, session: &DocumentSession) -> Vec<UnsupportedContent> {
+        let mut found = find_unsupported_content(session.package());
+        if self.cfg.translate_embedded {
+            found.retain(|c| c.kind != "embedded_document");
+        }
+        if !found.is_empty() {
+            self.progress.info(tr_args(
+                "pipeline.unsupported_content",
+                &[("list", &unsupported_list(&found))],
+            ));
+        }
+        found
+    }
+
+    /// Announce the repairs `--lenient-parse` applied to the parts of an input.
+    fn report_parse_fixes(&self, fixes: &[ParseFix]) {
+        for fix in fixes {
+            self.progress.info(fix.to_string());
+        }
     }
 
     fn write_memory_snapshot(
@@ -328,6 +1511,7 @@ impl TranslatorPipeline {
                 .controller_backend
                 .as_ref()
                 .map(|b| b.name.as_str()),
+            self.cfg.seed,
             tus,
             notes,
         );
@@ -355,35 +1539,36 @@ impl TranslatorPipeline {
         autosave_text_json: &Path,
         output: &Path,
     ) -> anyhow::Result<()> {
+        self.reset_chunk_context();
+        self.emit_stage_started(slot.stage_name());
         let mut model = load_model(&self.cfg, backend)?;
         let total = tus.len().max(1);
-        let max_chars = (backend.ctx_size as usize)
-            .saturating_mul(2)
-            .saturating_sub(1800)
-            .max(4000);
-        let max_items = 32usize;
 
         let mut chunk_indices: Vec<usize> = Vec::new();
         let mut used = 0usize;
         let mut processed = 0usize;
 
         for idx in 0..tus.len() {
-            self.progress.progress(slot.stage_name(), idx + 1, total);
             let is_skip = {
                 let tu = &tus[idx];
-                tu.frozen_surface.trim().is_empty() || is_trivial_sentinel_text(&tu.source_surface)
+                tu.frozen_surface.trim().is_empty()
+                    || is_trivial_sentinel_text(&tu.source_surface)
+                    || self.is_numeric_cell(&tu.source_surface)
             };
 
             let tu_id = tus[idx].tu_id;
             let slots = slots_by_tu.get(&tu_id).cloned().unwrap_or_default();
             if is_skip {
+                self.localize_numeric_unit(&mut tus[idx], source_lang, target_lang);
                 let txt = tus[idx].frozen_surface.clone();
                 set_translation_slot(&mut tus[idx], slot, txt.clone(), &backend.name);
                 if !slots.is_empty() {
                     self.apply_slot_translation(text_variant, &slots, &tus[idx], &txt)
-                        .with_context(|| format!("apply {} tu_id={}", slot.stage_name(), tu_id))?;
+                        .with_context(|| format!("apply {} tu_id={}", slot.stage_name(), tu_id))
+                        .kind(ErrorKind::Validation, "translation rejected")?;
                 }
                 processed += 1;
+                self.emit_chunk_done(slot.stage_name(), tus, &[idx], processed, total);
                 if processed % self.cfg.autosave_every == 0 {
                     let _ = self.write_progress_docx(
                         mask_json,
@@ -400,7 +1585,8 @@ impl TranslatorPipeline {
 
             let add = tus[idx].frozen_surface.len() + 96;
             if !chunk_indices.is_empty()
-                && (used + add > max_chars || chunk_indices.len() >= max_items)
+                && (used + add > self.chunk_char_budget(backend)
+                    || chunk_indices.len() >= self.chunk_max_items(backend, 32))
             {
                 self.translate_chunk_recursive(
                     &mut model,
@@ -420,6 +1606,7 @@ impl TranslatorPipeline {
                     &chunk_indices,
                     &mut processed,
                 )?;
+                self.emit_chunk_done(slot.stage_name(), tus, &chunk_indices, processed, total);
                 chunk_indices.clear();
                 used = 0;
             }
@@ -446,6 +1633,7 @@ impl TranslatorPipeline {
                 &chunk_indices,
                 &mut processed,
             )?;
+            self.emit_chunk_done(slot.stage_name(), tus, &chunk_indices, processed, total);
         }
         Ok(())
     }
@@ -460,6 +1648,9 @@ impl TranslatorPipeline {
         if slot_ids.is_empty() {
             return Ok(());
         }
+        if let Some(layout) = self.span_layouts.get(&tu.tu_id) {
+            return apply_span_translation(text_json, layout, tu, translated);
+        }
 
         let mut expected: Vec<usize> = Vec::with_capacity(slot_ids.len() + 1);
         expected.extend_from_slice(slot_ids);
@@ -498,18 +1689,59 @@ impl TranslatorPipeline {
         Ok(())
     }
 
+    /// Count one repair call against `pipeline.max_repairs_total`. Once the run budget is
+    /// spent this returns false (reported once) and callers keep what they have.
+    fn take_repair_budget(&mut self) -> bool {
+        if self
+            .cfg
+            .max_repairs_total
+            .is_some_and(|limit| self.repairs_used >= limit)
+        {
+            if !self.repair_budget_reported {
+                self.repair_budget_reported = true;
+                self.progress.info(tr_args(
+                    "pipeline.repair_budget_exhausted",
+                    &[("count", &self.repairs_used)],
+                ));
+            }
+            return false;
+        }
+        self.repairs_used += 1;
+        true
+    }
+
+    /// Whether the repair cache already holds a valid repair of `tu` for `reason`; callers skip
+    /// the repair budget for those.
+    fn has_cached_repair(&self, tu: &TranslationUnit, reason: &str) -> bool {
+        self.repair_cache
+            .contains_key(&(tu.frozen_surface.clone(), reason.to_string()))
+    }
+
+    /// Ask the model to fix `bad` for `validation_error`. A repair that validates is cached by
+    /// (frozen source, reason) for the rest of the run, so the same failure on repeated text
+    /// (table headers, boilerplate) is fixed once.
     fn repair_translation(
         &mut self,
         model: &mut NativeChatModel,
         repair_tmpl: &str,
         source_lang: &str,
         target_lang: &str,
-        source_frozen: &str,
+        tu: &TranslationUnit,
         bad: &str,
-        must_keep_tokens: &str,
         validation_error: &str,
-        nt_map: &str,
     ) -> anyhow::Result<String> {
+        let key = (tu.frozen_surface.clone(), validation_error.to_string());
+        if let Some(hit) = self.repair_cache.get(&key) {
+            self.repair_cache_hits += 1;
+            return Ok(hit.clone());
+        }
+        self.emit(PipelineEvent::RepairAttempt {
+            tu_id: tu.tu_id,
+            reason: validation_error.to_string(),
+        });
+        let source_frozen = tu.frozen_surface.as_str();
+        let must_keep_tokens = crate::sentinels::must_keep_tokens(source_frozen);
+        let nt_map = crate::freezer::render_nt_map_for_prompt(&tu.nt_map);
         let source_lang_label = lang_label(source_lang);
         let target_lang_label = lang_label(target_lang);
         let prompt = render_template(
@@ -519,9 +1751,9 @@ impl TranslatorPipeline {
                 ("target_lang", &target_lang_label),
                 ("source", source_frozen),
                 ("bad", bad),
-                ("must_keep_tokens", must_keep_tokens),
+                ("must_keep_tokens", &must_keep_tokens),
                 ("validation_error", validation_error),
-                ("nt_map", nt_map),
+                ("nt_map", &nt_map),
             ],
         );
         let max_tokens = ((source_frozen.len() as u32) / 2).clamp(512, 4096);
@@ -535,7 +1767,11 @@ impl TranslatorPipeline {
             Some(1.05),
             false,
         )?;
-        Ok(cleanup_model_text(&out))
+        let out = cleanup_model_text(&out);
+        if validate_translation(tu, &out).is_ok() {
+            self.repair_cache.insert(key, out.clone());
+        }
+        Ok(out)
     }
 
     fn run_fuse_stage(
@@ -546,14 +1782,11 @@ impl TranslatorPipeline {
         tus: &mut [TranslationUnit],
         notes: &HashMap<usize, ParaNotes>,
     ) -> anyhow::Result<()> {
-        let mut model = load_model(&self.cfg, agent_backend)?;
-        let agent_prompts = self.cfg.prompts.for_backend(&agent_backend.name);
-        let fuse_tmpl = agent_prompts.fuse_ab.clone();
-        let repair_tmpl = agent_prompts.translate_repair.clone();
-
-        // Default non-paragraph to A.
+        // Default non-paragraph (and, with `fuse = "flagged"`, unflagged paragraphs) to A.
+        let is_para =
+            |tu: &TranslationUnit| tu.scope_key.contains("#w:p") || tu.scope_key.contains("#a:p");
         for tu in tus.iter_mut() {
-            if !(tu.scope_key.contains("#w:p") || tu.scope_key.contains("#a:p")) {
+            if !is_para(tu) || !self.in_stage_scope("fuse", tu) {
                 tu.final_translation = tu.draft_translation.clone();
             }
         }
@@ -561,13 +1794,21 @@ impl TranslatorPipeline {
         let para_indices: Vec<usize> = tus
             .iter()
             .enumerate()
-            .filter(|(_, tu)| tu.scope_key.contains("#w:p") || tu.scope_key.contains("#a:p"))
+            .filter(|(_, tu)| is_para(tu) && self.in_stage_scope("fuse", tu))
             .map(|(i, _)| i)
             .collect();
+        let total = tus.iter().filter(|tu| is_para(tu)).count();
+        self.report_stage_scope("fuse", para_indices.len(), total);
         if para_indices.is_empty() {
             return Ok(());
         }
 
+        self.emit_stage_started("fuse");
+        let mut model = load_controller_model(&self.cfg, agent_backend)?;
+        let agent_prompts = self.cfg.prompts.for_backend(&agent_backend.name);
+        let fuse_tmpl = agent_prompts.fuse_ab.clone();
+        let repair_tmpl = agent_prompts.translate_repair.clone();
+
         let max_chars = (agent_backend.ctx_size as usize)
             .saturating_mul(2)
             .saturating_sub(2200)
@@ -584,12 +1825,11 @@ impl TranslatorPipeline {
                 .as_deref()
                 .unwrap_or(&tu.frozen_surface);
             let b = tu.alt_translation.as_deref().unwrap_or(a);
-            let note = notes
+            let note_len = notes
                 .get(&tu.tu_id)
-                .and_then(|n| n.understanding.as_ref())
-                .map(|s| s.as_str())
-                .unwrap_or("");
-            let add = tu.frozen_surface.len() + a.len() + b.len() + note.len() + 160;
+                .and_then(|n| n.prompt_note())
+                .map_or(0, |s| s.len());
+            let add = tu.frozen_surface.len() + a.len() + b.len() + note_len + 160;
             if !chunk.is_empty() && (used + add > max_chars || chunk.len() >= max_items) {
                 self.fuse_chunk_recursive(
                     &mut model,
@@ -634,6 +1874,73 @@ impl TranslatorPipeline {
         done: usize,
         total: usize,
     ) -> anyhow::Result<()> {
+        let progress_path = self.autosave_path_for(output);
+        self.emit(PipelineEvent::Autosave {
+            path: progress_path.clone(),
+            done,
+            total,
+        });
+        let progress_text_json = progress_path.with_extension("text.json");
+        let json = serde_json::to_vec_pretty(text).context("serialize autosave text json")?;
+        write_file_atomic(autosave_text_json, &json)?;
+        let _ = write_file_atomic(&progress_text_json, &json);
+
+        // Merge into a temp file first: a crash mid-write must never leave a corrupt autosave.
+        let tmp_docx = tmp_path_for(&progress_path);
+        self.merge_docx(mask_json, offsets_json, autosave_text_json, &tmp_docx)?;
+        fs::rename(&tmp_docx, &progress_path)
+            .with_context(|| format!("replace autosave: {}", progress_path.display()))?;
+
+        if self.cfg.autosave_keep > 0 {
+            if let Err(err) = self.keep_autosave_history(&progress_path) {
+                self.progress.info(tr_args(
+                    "pipeline.autosave_history_failed",
+                    &[("err", &format!("{err:#}"))],
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn write_variant_docx(
+        &self,
+        mask_json: &Path,
+        offsets_json: &Path,
+        text_json: &Path,
+        output: &Path,
+        variant: &str,
+    ) -> anyhow::Result<PathBuf> {
+        let stem = output
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("output");
+        let suffix = match variant {
+            "A" => "_A.docx",
+            "B" => "_B.docx",
+            other => return Err(anyhow!("unknown variant: {other}")),
+        };
+        let out_path = output.with_file_name(format!("{stem}{suffix}"));
+        self.merge_docx(mask_json, offsets_json, text_json, &out_path)?;
+        Ok(out_path)
+    }
+
+    /// Merges the artifacts into `output` with this job's link rewrite rules.
+    fn merge_docx(
+        &self,
+        mask_json: &Path,
+        offsets_json: &Path,
+        text_json: &Path,
+        output: &Path,
+    ) -> anyhow::Result<()> {
+        let opts = MergeOptions {
+            lenient: false,
+            link_rewrites: self.cfg.link_rewrites.clone(),
+        };
+        merge_mask_json_and_offsets_with(mask_json, offsets_json, text_json, output, &opts)?;
+        Ok(())
+    }
+
+    fn autosave_path_for(&self, output: &Path) -> PathBuf {
         let stem = output
             .file_stem()
             .and_then(|s| s.to_str())
@@ -642,23 +1949,73 @@ impl TranslatorPipeline {
         if !suffix.to_ascii_lowercase().ends_with(".docx") {
             suffix.push_str(".docx");
         }
-        let progress_path = output.with_file_name(format!("{stem}{suffix}"));
-        self.progress.info(format!(
-            "Autosave {done}/{total}: {}",
-            progress_path.display()
-        ));
-        let progress_text_json = progress_path.with_extension("text.json");
-        fs::write(
-            autosave_text_json,
-            serde_json::to_vec_pretty(text).context("serialize autosave text json")?,
-        )
-        .with_context(|| format!("write autosave text json: {}", autosave_text_json.display()))?;
-        let _ = fs::write(
-            &progress_text_json,
-            serde_json::to_vec_pretty(text).context("serialize progress text json")?,
-        );
-        merge_mask_json_and_offsets(mask_json, offsets_json, autosave_text_json, &progress_path)
+        self.beside_output(&output.with_file_name(format!("{stem}{suffix}")))
+    }
+
+    /// `path` (a file written next to the output) moved into the workdir when there is one.
+    fn beside_output(&self, path: &Path) -> PathBuf {
+        match (self.cfg.artifact_dir.as_deref(), path.file_name()) {
+            (Some(dir), Some(name)) => dir.join(name),
+            _ => path.to_path_buf(),
+        }
+    }
+
+    /// `--workdir`: note artifacts written for `input` in the workdir manifest.
+    fn record_workdir(&self, input: &Path, artifacts: &[(&str, &Path)]) -> anyhow::Result<()> {
+        match self.cfg.artifact_dir.as_deref() {
+            Some(dir) => record_artifacts(dir, Some(input), artifacts),
+            None => Ok(()),
+        }
+    }
+
+    /// Copy the current autosave to `<autosave_stem>.<YYYYMMDD-HHMMSS-mmm>.docx` and keep only
+    /// the newest `autosave_keep` copies.
+    fn keep_autosave_history(&self, progress_path: &Path) -> anyhow::Result<()> {
+        snapshot_autosave(progress_path, self.cfg.autosave_keep)
+    }
+}
+
+fn snapshot_autosave(progress_path: &Path, keep: usize) -> anyhow::Result<()> {
+    let base = progress_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("autosave")
+        .to_string();
+    // Two snapshots within one millisecond: take the next free one, so names still sort by age.
+    let mut time = SystemTime::now();
+    let snapshot = loop {
+        let path = progress_path.with_file_name(format!("{base}.{}.docx", timestamp_utc(time)));
+        if !path.exists() {
+            break path;
+        }
+        time += Duration::from_millis(1);
+    };
+    let tmp = tmp_path_for(&snapshot);
+    fs::copy(progress_path, &tmp).with_context(|| format!("copy autosave: {}", tmp.display()))?;
+    fs::rename(&tmp, &snapshot)
+        .with_context(|| format!("write autosave snapshot: {}", snapshot.display()))?;
+
+    let dir = progress_path.parent().unwrap_or_else(|| Path::new("."));
+    let prefix = format!("{base}.");
+    let mut snapshots: Vec<PathBuf> = fs::read_dir(dir)
+        .with_context(|| format!("list autosaves: {}", dir.display()))?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| {
+            p.file_name()
+                .and_then(|s| s.to_str())
+                .and_then(|n| n.strip_prefix(&prefix)?.strip_suffix(".docx"))
+                .is_some_and(|ts| {
+                    !ts.is_empty() && ts.chars().all(|c| c.is_ascii_digit() || c == '-')
+                })
+        })
+        .collect();
+    snapshots.sort();
+    let excess = snapshots.len().saturating_sub(keep);
+    for old in snapshots.into_iter().take(excess) {
+        let _ = fs::remove_file(old);
     }
+    Ok(())
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -676,6 +2033,90 @@ impl TranslationSlot {
     }
 }
 
+/// Span projection: the text between `<<MT_EM:nn>>` pairs goes to the marked groups in order and
+/// each unmarked gap to the source's base-style group at the same position (or, when the source
+/// has none there, to the neighbouring marked group). Each group's text is then split across its
+/// slots in proportion to their source lengths. Collapsed slots are emptied and their runs dropped
+/// on merge.
+fn apply_span_translation(
+    text_json: &mut PureTextJson,
+    layout: &SpanLayout,
+    tu: &TranslationUnit,
+    translated: &str,
+) -> anyhow::Result<()> {
+    let pieces = parse_em_output(translated)
+        .with_context(|| format!("tu_id={} parse em output", tu.tu_id))?;
+    let got: Vec<usize> = pieces.iter().filter_map(|(id, _)| *id).collect();
+    let want: Vec<usize> = layout.groups.iter().filter_map(|g| g.style_id).collect();
+    if got != want {
+        return Err(anyhow!(
+            "em_marker_order_mismatch tu_id={} expected={want:?} got={got:?}",
+            tu.tu_id
+        ));
+    }
+
+    let mut texts: Vec<String> = vec![String::new(); layout.groups.len()];
+    let marked: Vec<usize> = (0..layout.groups.len())
+        .filter(|&i| layout.groups[i].style_id.is_some())
+        .collect();
+    let mut base_at_gap: HashMap<usize, usize> = HashMap::new();
+    let mut gap = 0usize;
+    for (i, group) in layout.groups.iter().enumerate() {
+        if group.style_id.is_some() {
+            gap += 1;
+        } else {
+            base_at_gap.insert(gap, i);
+        }
+    }
+    let mut gap = 0usize;
+    for (id, text) in pieces {
+        if id.is_some() {
+            texts[marked[gap]].push_str(&text);
+            gap += 1;
+            continue;
+        }
+        if text.is_empty() {
+            continue;
+        }
+        // Without a source base group here: append to the previous marked group, or lead the
+        // first one.
+        let group = base_at_gap
+            .get(&gap)
+            .copied()
+            .or_else(|| gap.checked_sub(1).map(|k| marked[k]))
+            .or_else(|| marked.first().copied());
+        match group {
+            Some(i) => texts[i].push_str(&text),
+            None => return Err(anyhow!("span_layout_empty tu_id={}", tu.tu_id)),
+        }
+    }
+    for (group, text) in layout.groups.iter().zip(texts) {
+        let text = unfreeze_text(&text, &tu.nt_map);
+        let parts = split_text_by_weights(&text, &group.weights);
+        for (&slot_id, part) in group.slot_ids.iter().zip(parts) {
+            let idx = slot_id.saturating_sub(1);
+            if idx >= text_json.slot_texts.len() {
+                return Err(anyhow!(
+                    "slot_id_out_of_range tu_id={} slot_id={} slot_texts_len={}",
+                    tu.tu_id,
+                    slot_id,
+                    text_json.slot_texts.len()
+                ));
+            }
+            text_json.slot_texts[idx] = part;
+        }
+    }
+    for &slot_id in &layout.collapsed {
+        if let Some(text) = text_json.slot_texts.get_mut(slot_id.saturating_sub(1)) {
+            text.clear();
+        }
+        if let Err(pos) = text_json.collapsed_slots.binary_search(&slot_id) {
+            text_json.collapsed_slots.insert(pos, slot_id);
+        }
+    }
+    Ok(())
+}
+
 fn set_translation_slot(
     tu: &mut TranslationUnit,
     slot: TranslationSlot,
@@ -697,6 +2138,22 @@ fn set_translation_slot(
 fn load_model(
     cfg: &PipelineConfig,
     backend: &crate::config::ResolvedBackend,
+) -> anyhow::Result<NativeChatModel> {
+    load_model_with_budget(cfg, backend, 0)
+}
+
+/// Model for a controller stage (notes, fuse, stitch audit): gets the backend's thinking budget.
+fn load_controller_model(
+    cfg: &PipelineConfig,
+    backend: &crate::config::ResolvedBackend,
+) -> anyhow::Result<NativeChatModel> {
+    load_model_with_budget(cfg, backend, backend.thinking_budget)
+}
+
+fn load_model_with_budget(
+    cfg: &PipelineConfig,
+    backend: &crate::config::ResolvedBackend,
+    thinking_budget: u32,
 ) -> anyhow::Result<NativeChatModel> {
     let threads = backend.threads.unwrap_or(cfg.threads);
     let gpu_layers = backend.gpu_layers.unwrap_or(cfg.gpu_layers);
@@ -712,9 +2169,77 @@ fn load_model(
             batch_size: backend.batch_size,
             ubatch_size: backend.ubatch_size,
             offload_kqv: backend.offload_kqv,
-            seed: 42,
+            use_mmap: backend.use_mmap,
+            use_mlock: backend.use_mlock,
+            seed: cfg.seed,
+            greedy: cfg.deterministic,
+            strip_reasoning: backend.strip_reasoning,
+            thinking_budget,
+            completion: backend.completion.clone(),
+            cancel: cfg.cancel.clone(),
         },
     )
+    .kind(ErrorKind::Model, "load model")
+}
+
+/// Chat attempts per translate chunk before the backend counts as failing.
+const CHAT_ATTEMPTS: usize = 3;
+
+static PROMPT_TOO_LONG_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"prompt_too_long: prompt_tokens=(\d+) n_ctx=(\d+)").expect("prompt_too_long regex")
+});
+
+fn is_context_overflow(err: &anyhow::Error) -> bool {
+    ErrorReport::from_error(err).kind == ErrorKind::ContextOverflow
+}
+
+fn chat_with_retries(mut chat: impl FnMut() -> anyhow::Result<String>) -> anyhow::Result<String> {
+    let mut attempt = 1;
+    loop {
+        match chat() {
+            Ok(out) => return Ok(out),
+            Err(err) if is_cancelled(&err) => return Err(err),
+            // The same prompt overflows again; the caller has to shrink it.
+            Err(err) if is_context_overflow(&err) => return Err(err),
+            Err(err) if attempt >= CHAT_ATTEMPTS => {
+                return Err(err).kind(
+                    ErrorKind::Model,
+                    &format!("chat failed {CHAT_ATTEMPTS} times"),
+                )
+            }
+            Err(_) => attempt += 1,
+        }
+    }
+}
+
+/// "2 chart(s), 5 image(s) ..." for `pipeline.unsupported_content`.
+fn unsupported_list(found: &[UnsupportedContent]) -> String {
+    found
+        .iter()
+        .map(|c| {
+            tr_args(
+                &format!("pipeline.unsupported_{}", c.kind),
+                &[("count", &c.count)],
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// `s` as one shell word, for substituting paths into configured commands.
+fn shell_quote(s: &str) -> String {
+    if cfg!(windows) {
+        format!("\"{s}\"")
+    } else {
+        format!("'{}'", s.replace('\'', r"'\''"))
+    }
+}
+
+/// The unit's current (frozen) translation: the final one, else the draft.
+fn current_translation(tu: &TranslationUnit) -> Option<&str> {
+    tu.final_translation
+        .as_deref()
+        .or(tu.draft_translation.as_deref())
 }
 
 fn cleanup_model_text(text: &str) -> String {
@@ -763,23 +2288,59 @@ fn parse_json_with_repair(
     must_extract_json_obj(raw)
 }
 
-fn write_variant_docx(
-    mask_json: &Path,
-    offsets_json: &Path,
-    text_json: &Path,
-    output: &Path,
-    variant: &str,
-) -> anyhow::Result<PathBuf> {
-    let stem = output
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("output");
-    let suffix = match variant {
-        "A" => "_A.docx",
-        "B" => "_B.docx",
-        other => return Err(anyhow!("unknown variant: {other}")),
-    };
-    let out_path = output.with_file_name(format!("{stem}{suffix}"));
-    merge_mask_json_and_offsets(mask_json, offsets_json, text_json, &out_path)?;
-    Ok(out_path)
+/// Write via `<path>.tmp` + rename, so readers never observe a half-written file.
+fn write_file_atomic(path: &Path, data: &[u8]) -> anyhow::Result<()> {
+    let tmp = tmp_path_for(path);
+    fs::write(&tmp, data).with_context(|| format!("write {}", tmp.display()))?;
+    fs::rename(&tmp, path).with_context(|| format!("replace {}", path.display()))?;
+    Ok(())
+}
+
+fn tmp_path_for(path: &Path) -> PathBuf {
+    let mut name = path
+        .file_name()
+        .map(|s| s.to_os_string())
+        .unwrap_or_default();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+/// `YYYYMMDD-HHMMSS-mmm` (UTC) of `time`, sortable by name.
+fn timestamp_utc(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let (year, month, day, hour, minute, second) = utc_from_secs(since_epoch.as_secs());
+    let millis = since_epoch.subsec_millis();
+    format!("{year:04}{month:02}{day:02}-{hour:02}{minute:02}{second:02}-{millis:03}")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::snapshot_autosave;
+
+    #[test]
+    fn back_to_back_autosave_snapshots_are_kept_apart() {
+        let dir = std::env::temp_dir().join(format!("mt-autosave-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("temp dir");
+        let progress = dir.join("out.autosave.docx");
+        fs::write(&progress, "first").expect("write autosave");
+        snapshot_autosave(&progress, 2).expect("first snapshot");
+        fs::write(&progress, "second").expect("write autosave");
+        snapshot_autosave(&progress, 2).expect("second snapshot");
+
+        let mut snapshots: Vec<_> = fs::read_dir(&dir)
+            .expect("list dir")
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p != &progress)
+            .collect();
+        snapshots.sort();
+        let contents: Vec<String> = snapshots
+            .iter()
+            .map(|p| fs::read_to_string(p).expect("read snapshot"))
+            .collect();
+        assert_eq!(contents, ["first", "second"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
 }
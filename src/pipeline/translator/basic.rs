@@ -4,23 +4,37 @@ use std::path::Path;
 
 use anyhow::{anyhow, Context};
 
-use crate::docx::decompose::{
-    extract_mask_json_and_offsets, merge_mask_json_and_offsets, OffsetsJson,
-};
+use crate::docx::artifact::{check_schema, ArtifactHead};
+use crate::docx::decompose::{extract_mask_json_and_offsets_from, MaskOptions};
 use crate::docx::filter::{filter_docx_with_rules, DocxFilterRules};
-use crate::docx::pure_text::{extract_pure_text, PureTextJson};
-use crate::docx::structure::extract_structure_json;
-use crate::freezer::{freeze_text, normalize_nt_tokens, render_nt_map_for_prompt, unfreeze_text};
+use crate::docx::pure_text::{
+    pure_text_from_session, PureTextJson, SlotProvenance, PURE_TEXT_VERSION,
+};
+use crate::docx::session::DocumentSession;
+use crate::docx::structure::write_structure_json;
+use crate::errors::{ErrorKind, ResultExt};
+use crate::freezer::{freeze_text_with, normalize_nt_tokens, unfreeze_text};
+use crate::i18n::{tr, tr_args};
 use crate::ir::TranslationUnit;
 use crate::models::native::NativeChatModel;
-use crate::quality::{quality_heuristics, validate_translation};
+use crate::quality::{is_hard_quality_flag, quality_heuristics, validate_translation};
 use crate::sentinels::{parse_segmented_output, seg_end, seg_start, ANY_SENTINEL_RE};
-use crate::textutil::{auto_language_pair, is_trivial_sentinel_text, lang_label};
+use crate::textutil::{is_trivial_sentinel_text, lang_label};
 
 use super::super::docmap::build_para_slot_units;
+use super::super::events::PipelineEvent;
 use super::super::memory::{build_memory, write_memory_file, ParaNotes};
+use super::super::validation::ValidationUnit;
+use super::super::workdir::{
+    ARTIFACT_AUTOSAVE_TEXT, ARTIFACT_BLOBS, ARTIFACT_FINAL_TEXT, ARTIFACT_MASK, ARTIFACT_OFFSETS,
+    ARTIFACT_OUTPUT, ARTIFACT_SOURCE_TEXT, ARTIFACT_STRUCTURE,
+};
+use super::super::xrefs::CrossRefs;
 
-use super::{cleanup_model_text, load_model, render_template, TranslatorPipeline};
+use super::{
+    chat_with_retries, cleanup_model_text, current_translation, is_context_overflow,
+    TranslatorPipeline, NOTE_REF_TEXT_MOVED,
+};
 
 impl TranslatorPipeline {
     pub(super) fn translate_docx_basic(
@@ -28,10 +42,9 @@ impl TranslatorPipeline {
         input: &Path,
         output: &Path,
     ) -> anyhow::Result<()> {
+        self.progress.info(tr("pipeline.mode_basic"));
         self.progress
-            .info(format!("Pipeline mode: basic (translate_backend only)"));
-        self.progress
-            .info(format!("Read DOCX: {}", input.display()));
+            .info(tr_args("pipeline.read_docx", &[("path", &input.display())]));
         fs::create_dir_all(self.trace.dir())
             .with_context(|| format!("create trace dir: {}", self.trace.dir().display()))?;
 
@@ -42,11 +55,14 @@ impl TranslatorPipeline {
 
         let mut work_docx = input.to_path_buf();
         if let Some(rules_path) = self.cfg.docx_filter_rules.clone() {
-            self.progress
-                .info(format!("DOCX filter rules: {}", rules_path.display()));
+            self.progress.info(tr_args(
+                "pipeline.filter_rules",
+                &[("path", &rules_path.display())],
+            ));
             let rules = DocxFilterRules::from_toml_path(&rules_path)?;
             let filtered = self.trace.dir().join(format!("{stem}.filtered.docx"));
-            filter_docx_with_rules(input, &filtered, &rules)?;
+            let fixes = filter_docx_with_rules(input, &filtered, &rules, &self.cfg.package)?;
+            self.report_parse_fixes(&fixes);
             work_docx = filtered;
         }
 
@@ -57,40 +73,57 @@ impl TranslatorPipeline {
         let structure_json = self.trace.dir().join(format!("{stem}.structure.json"));
         let autosave_text_json = self.trace.dir().join(format!("{stem}.autosave.text.json"));
 
-        let source_text = extract_pure_text(&work_docx)?;
+        // Parsed once; text, structure, mask and docmap all read from this session.
+        let session =
+            DocumentSession::open_with(&work_docx, &self.cfg.package, &self.cfg.placeholder_prefix)
+                .kind(ErrorKind::InputDocx, "read input docx")?;
+        self.report_parse_fixes(session.parse_fixes());
+        let unsupported = self.warn_unsupported_content(&session);
+        let source_text =
+            pure_text_from_session(&session).kind(ErrorKind::InputDocx, "read input docx")?;
         fs::write(
             &text_source_json,
             serde_json::to_vec_pretty(&source_text).context("serialize source text json")?,
         )
         .with_context(|| format!("write source text json: {}", text_source_json.display()))?;
-        let _ = extract_structure_json(&work_docx, &structure_json);
-        extract_mask_json_and_offsets(&work_docx, &mask_json, &offsets_json, &blobs_bin)?;
-
-        let offsets: OffsetsJson = serde_json::from_slice(
-            &fs::read(&offsets_json)
-                .with_context(|| format!("read offsets json: {}", offsets_json.display()))?,
+        let _ = write_structure_json(&source_text, &structure_json);
+        let offsets = extract_mask_json_and_offsets_from(
+            &session,
+            &mask_json,
+            &offsets_json,
+            &blobs_bin,
+            MaskOptions::default(),
         )
-        .context("parse offsets json")?;
+        .kind(ErrorKind::InputDocx, "decompose input docx")?;
 
-        let mut para_units = build_para_slot_units(&work_docx, &source_text, &offsets)?;
+        self.record_workdir(
+            input,
+            &[
+                (ARTIFACT_MASK, &mask_json),
+                (ARTIFACT_OFFSETS, &offsets_json),
+                (ARTIFACT_BLOBS, &blobs_bin),
+                (ARTIFACT_SOURCE_TEXT, &text_source_json),
+                (ARTIFACT_STRUCTURE, &structure_json),
+                (ARTIFACT_AUTOSAVE_TEXT, &autosave_text_json),
+            ],
+        )?;
+
+        let mut para_units = build_para_slot_units(&session, &source_text, &offsets)?;
         if let Some(max_tus) = self.cfg.max_tus {
             let keep = max_tus.max(1).min(para_units.len());
             para_units.truncate(keep);
-            self.progress.info(format!("Max TUs: {keep}"));
+            self.progress
+                .info(tr_args("pipeline.max_tus", &[("count", &keep)]));
         }
+        self.apply_unit_filter(&mut para_units, |u| (u.tu_id, u.scope_key.as_str()));
 
         let (source_lang, target_lang) = self.resolve_lang_pair_from_pure_text(&source_text);
-        self.progress
-            .info(format!("Language: {source_lang} -> {target_lang}"));
-
-        let translate_backend = self.cfg.translate_backend.clone();
-        self.progress
-            .info(format!("Translate backend: {}", translate_backend.name));
-        let mut model = load_model(&self.cfg, &translate_backend)?;
-        let translate_prompts = self.cfg.prompts.for_backend(&translate_backend.name);
-        let prompt_translate_a = translate_prompts.translate_a.clone();
-        let prompt_translate_b = translate_prompts.translate_b.clone();
-        let prompt_translate_repair = translate_prompts.translate_repair.clone();
+        self.progress.info(tr_args(
+            "pipeline.language",
+            &[("src", &source_lang), ("tgt", &target_lang)],
+        ));
+        self.route_translate_backend(&source_lang, &target_lang);
+        self.open_project_memory(&source_lang, &target_lang)?;
 
         // A: translate slot_texts (used to render the output DOCX)
         let mut ordered_slot_ids: Vec<usize> = Vec::new();
@@ -105,8 +138,10 @@ impl TranslatorPipeline {
                 }
             }
         }
-        self.progress
-            .info(format!("Translatable slots: {}", ordered_slot_ids.len()));
+        self.progress.info(tr_args(
+            "pipeline.translatable_slots",
+            &[("count", &ordered_slot_ids.len())],
+        ));
 
         let mut tus_slots: Vec<TranslationUnit> = Vec::with_capacity(ordered_slot_ids.len());
         for &slot_id in &ordered_slot_ids {
@@ -116,7 +151,7 @@ impl TranslatorPipeline {
                 .get(idx)
                 .cloned()
                 .ok_or_else(|| anyhow!("slot_id_out_of_range: {slot_id}"))?;
-            let fr = freeze_text(&src);
+            let fr = freeze_text_with(&src, &self.cfg.acronyms);
             tus_slots.push(TranslationUnit {
                 tu_id: slot_id,
                 part_name: String::new(),
@@ -138,46 +173,41 @@ impl TranslatorPipeline {
             });
         }
 
-        let mut text_a: PureTextJson = source_text.clone();
-        self.translate_slot_texts_segmented_basic(
-            &mut model,
-            &translate_backend,
-            &source_lang,
-            &target_lang,
-            "translate_a(slot_texts)",
-            &prompt_translate_a,
-            &prompt_translate_repair,
-            &mut tus_slots,
-            &mut text_a,
-            &mask_json,
-            &offsets_json,
-            &autosave_text_json,
-            output,
-        )?;
-
-        let a_text_json_trace = self.trace.dir().join(format!("{stem}.A.text.json"));
-        fs::write(
-            &a_text_json_trace,
-            serde_json::to_vec_pretty(&text_a).context("serialize A text json")?,
-        )
-        .with_context(|| format!("write A text json: {}", a_text_json_trace.display()))?;
-        let a_text_json = output.with_extension("text.json");
-        fs::write(
-            &a_text_json,
-            serde_json::to_vec_pretty(&text_a).context("serialize output text json")?,
-        )
-        .with_context(|| format!("write output text json: {}", a_text_json.display()))?;
-
-        self.progress
-            .info(format!("Write output: {}", output.display()));
-        merge_mask_json_and_offsets(&mask_json, &offsets_json, &a_text_json, output)?;
+        let previous_text_json = self.beside_output(&output.with_extension("text.json"));
+        let mut text_a: PureTextJson =
+            self.unit_filter_base(&[&previous_text_json], &source_text)?;
+        if self.cfg.resume {
+            let progress_text_json = self.autosave_path_for(output).with_extension("text.json");
+            match load_resume_text(&[&autosave_text_json, &progress_text_json], &source_text)? {
+                Some((path, resumed)) => {
+                    let before = tus_slots.len();
+                    tus_slots.retain(|tu| {
+                        let idx = tu.tu_id.saturating_sub(1);
+                        let done = resumed.slot_texts[idx] != source_text.slot_texts[idx];
+                        if done {
+                            text_a.slot_texts[idx] = resumed.slot_texts[idx].clone();
+                        }
+                        !done
+                    });
+                    self.progress.info(tr_args(
+                        "pipeline.continue_from",
+                        &[
+                            ("path", &path.display()),
+                            ("done", &(before - tus_slots.len())),
+                            ("remaining", &tus_slots.len()),
+                        ],
+                    ));
+                }
+                None => self.progress.info(tr("pipeline.continue_no_match")),
+            }
+        }
 
-        // B: translate paragraphs for review (not used for DOCX merge)
+        // Units of B (paragraphs for review), built up front so a pivot hop covers them too.
         let mut para_idx_by_id: HashMap<usize, usize> = HashMap::new();
         let mut tus_paras: Vec<TranslationUnit> = Vec::with_capacity(source_text.paragraphs.len());
         for (idx, p) in source_text.paragraphs.iter().enumerate() {
             para_idx_by_id.insert(p.para_id, idx);
-            let fr = freeze_text(&p.text);
+            let fr = freeze_text_with(&p.text, &self.cfg.acronyms);
             tus_paras.push(TranslationUnit {
                 tu_id: p.para_id,
                 part_name: p.part_name.clone(),
@@ -202,11 +232,158 @@ impl TranslatorPipeline {
             let keep = max_tus.max(1).min(tus_paras.len());
             tus_paras.truncate(keep);
         }
+        if let Some(filter) = self.cfg.unit_filter.as_ref() {
+            tus_paras.retain(|tu| filter.keeps(tu.tu_id, &tu.scope_key));
+        }
+        // `[pivot]`: translate both into the intermediate language first; A and B then translate
+        // from there.
+        let pivot = self.pivot_for(&source_lang, &target_lang);
+        let mut pivot_originals: Vec<Vec<TranslationUnit>> = Vec::new();
+        if let Some(pivot) = pivot.as_ref() {
+            pivot_originals = self.run_pivot_hop(
+                pivot,
+                &source_lang,
+                &target_lang,
+                &mut [
+                    ("pivot(slot_texts)", &mut tus_slots),
+                    ("pivot(paragraphs)", &mut tus_paras),
+                ],
+            )?;
+        }
+        let hop_source_lang = pivot.map_or_else(|| source_lang.clone(), |p| p.via);
+
+        self.progress.info(tr_args(
+            "pipeline.translate_backend",
+            &[("name", &self.cfg.translate_backend.name)],
+        ));
+        let (mut model, mut translate_backend) = self.load_translate_model()?;
+        loop {
+            let translate_prompts = self.cfg.prompts.for_backend(&translate_backend.name);
+            let prompt_translate_a = translate_prompts.translate_a.clone();
+            let prompt_translate_repair = translate_prompts.translate_repair.clone();
+            let result = self.translate_slot_texts_segmented_basic(
+                &mut model,
+                &translate_backend,
+                &hop_source_lang,
+                &target_lang,
+                "translate_a(slot_texts)",
+                &prompt_translate_a,
+                &prompt_translate_repair,
+                &mut tus_slots,
+                &mut text_a,
+                &mask_json,
+                &offsets_json,
+                &autosave_text_json,
+                output,
+            );
+            match result {
+                Ok(()) => break,
+                Err(err) => {
+                    self.fall_back_translate_backend(err).stage("translate_a")?;
+                    (model, translate_backend) = self.load_translate_model()?;
+                }
+            }
+        }
+        flag_hard_quality(&mut tus_slots, &hop_source_lang, &target_lang);
+        let mut pivot_originals = pivot_originals.into_iter();
+        if let Some(originals) = pivot_originals.next() {
+            for (slot_id, text) in self.finish_pivot(originals, &mut tus_slots) {
+                apply_slot_text(&mut text_a, slot_id, &text)?;
+            }
+        }
+        for idx in self.run_custom_stages(stem, &source_lang, &target_lang, &mut tus_slots)? {
+            let tu = &tus_slots[idx];
+            if let Some(t) = current_translation(tu) {
+                apply_slot_text(&mut text_a, tu.tu_id, &unfreeze_text(t, &tu.nt_map))?;
+            }
+        }
+        let para_slots: Vec<(usize, &[usize])> = para_units
+            .iter()
+            .map(|p| (p.tu_id, p.slot_ids.as_slice()))
+            .collect();
+        self.apply_target_style(&source_text, &para_slots, &target_lang, &mut text_a);
+        let cross_refs = CrossRefs::collect(&para_units);
+        self.harmonize_cross_refs(&cross_refs, &source_text, &mut text_a, true);
+        let note_refs: HashMap<usize, Vec<(usize, usize)>> = para_units
+            .iter()
+            .filter(|p| !p.note_refs.is_empty())
+            .map(|p| (p.tu_id, p.note_refs.clone()))
+            .collect();
+        let moved = self.protect_note_ref_context(&source_text, &note_refs, &mut text_a);
+        // Slot units: the flag goes to the slot after each mark of a flagged paragraph.
+        let after_slots: HashSet<usize> = moved
+            .iter()
+            .flat_map(|id| note_refs[id].iter().map(|&(_, after)| after))
+            .collect();
+        for tu in tus_slots
+            .iter_mut()
+            .filter(|tu| after_slots.contains(&tu.tu_id))
+        {
+            tu.qe_flags.push(NOTE_REF_TEXT_MOVED.to_string());
+        }
+        let translate_prompts = self.cfg.prompts.for_backend(&translate_backend.name);
+        let prompt_translate_b = translate_prompts.translate_b.clone();
+        let prompt_translate_repair = translate_prompts.translate_repair.clone();
+
+        text_a.provenance = slot_provenance(&tus_slots);
+        let a_text_json_trace = self.trace.dir().join(format!("{stem}.A.text.json"));
+        fs::write(
+            &a_text_json_trace,
+            serde_json::to_vec_pretty(&text_a).context("serialize A text json")?,
+        )
+        .with_context(|| format!("write A text json: {}", a_text_json_trace.display()))?;
+        let fallback_slots = self
+            .check_source_fallbacks(&tus_slots)
+            .stage("translate_a")?;
+        let slot_units: HashMap<usize, &TranslationUnit> =
+            tus_slots.iter().map(|tu| (tu.tu_id, tu)).collect();
+        let flagged = para_units
+            .iter()
+            .filter_map(|p| {
+                let units = p
+                    .slot_ids
+                    .iter()
+                    .filter_map(|id| slot_units.get(id).copied());
+                ValidationUnit::from_units(p.tu_id, &p.scope_key, units)
+            })
+            .collect();
+        self.write_validation_report(stem, flagged);
+        if self.cfg.highlight_low_confidence {
+            text_a.highlight_slots = low_confidence_slots(&tus_slots);
+        } else if self.cfg.highlight_fallback {
+            text_a.highlight_slots = fallback_slots;
+        }
+        if !text_a.highlight_slots.is_empty() {
+            text_a.highlight_color = Some(self.cfg.highlight_color.clone());
+        }
+        let a_text_json = self.beside_output(&output.with_extension("text.json"));
+        fs::write(
+            &a_text_json,
+            serde_json::to_vec_pretty(&text_a).context("serialize output text json")?,
+        )
+        .with_context(|| format!("write output text json: {}", a_text_json.display()))?;
+
+        self.progress.info(tr_args(
+            "pipeline.write_output",
+            &[("path", &output.display())],
+        ));
+        self.merge_docx(&mask_json, &offsets_json, &a_text_json, output)
+            .kind(ErrorKind::Merge, "write output docx")?;
+        self.record_workdir(
+            input,
+            &[
+                (ARTIFACT_FINAL_TEXT, &a_text_json),
+                (ARTIFACT_OUTPUT, output),
+            ],
+        )?;
+        self.report_coverage(stem, &offsets, &source_text, &text_a, &unsupported);
+
+        // B: translate paragraphs for review (not used for DOCX merge)
         let mut text_b: PureTextJson = source_text.clone();
         self.translate_units_segmented_basic(
             &mut model,
             &translate_backend,
-            &source_lang,
+            &hop_source_lang,
             &target_lang,
             "translate_b(paragraphs)",
             &prompt_translate_b,
@@ -219,7 +396,15 @@ impl TranslatorPipeline {
                 text_b.paragraphs[pi].text = out_unfrozen.to_string();
                 Ok(())
             },
-        )?;
+        )
+        .stage("translate_b")?;
+        if let Some(originals) = pivot_originals.next() {
+            for (para_id, text) in self.finish_pivot(originals, &mut tus_paras) {
+                if let Some(&pi) = para_idx_by_id.get(&para_id) {
+                    text_b.paragraphs[pi].text = text;
+                }
+            }
+        }
 
         let b_text_json_trace = self.trace.dir().join(format!("{stem}.B.text.json"));
         fs::write(
@@ -234,17 +419,29 @@ impl TranslatorPipeline {
             &translate_backend.name,
             None,
             None,
+            self.cfg.seed,
             &tus_paras,
             &HashMap::<usize, ParaNotes>::new(),
         );
         let mem_path = self.trace.dir().join("paragraph_memory.basic.json");
         let _ = write_memory_file(&mem_path, &mem);
+        self.run_qa_sample(stem, &source_lang, &target_lang, &mut tus_paras)?;
+        self.write_aligned_corpus(stem, &tus_paras, Some(&source_text));
+
+        let pairs: Vec<(usize, String, String)> = tus_paras
+            .iter()
+            .filter_map(|tu| {
+                let t = tu.draft_translation.clone()?;
+                Some((tu.tu_id, tu.source_surface.clone(), t))
+            })
+            .collect();
+        self.save_project_memory(stem, &source_text.placeholder_prefix, input, &pairs)?;
 
-        self.progress.info("Done.".to_string());
+        self.progress.info(tr("pipeline.done"));
         Ok(())
     }
 
-    fn resolve_lang_pair_from_pure_text(&self, text: &PureTextJson) -> (String, String) {
+    pub(super) fn resolve_lang_pair_from_pure_text(&self, text: &PureTextJson) -> (String, String) {
         match (self.cfg.source_lang.clone(), self.cfg.target_lang.clone()) {
             (Some(s), Some(t)) => (s, t),
             _ => {
@@ -258,13 +455,13 @@ impl TranslatorPipeline {
                         break;
                     }
                 }
-                auto_language_pair(&excerpts)
+                self.auto_lang_pair(&excerpts)
             }
         }
     }
 
     #[allow(clippy::too_many_arguments)]
-    fn translate_units_segmented_basic(
+    pub(super) fn translate_units_segmented_basic(
         &mut self,
         model: &mut NativeChatModel,
         backend: &crate::config::ResolvedBackend,
@@ -276,31 +473,31 @@ impl TranslatorPipeline {
         tus: &mut [TranslationUnit],
         on_unit: &mut dyn FnMut(&TranslationUnit, &str, usize, usize) -> anyhow::Result<()>,
     ) -> anyhow::Result<()> {
+        self.reset_chunk_context();
+        self.emit_stage_started(stage);
         let total = tus.len().max(1);
-        let max_chars = (backend.ctx_size as usize)
-            .saturating_mul(2)
-            .saturating_sub(1800)
-            .max(4000);
-        let max_items = 64usize;
 
         let mut processed = 0usize;
         let mut chunk_indices: Vec<usize> = Vec::new();
         let mut used = 0usize;
 
         for idx in 0..tus.len() {
-            self.progress.progress(stage, idx + 1, total);
-            if is_trivial_sentinel_text(&tus[idx].frozen_surface) {
-                let src = tus[idx].source_surface.clone();
+            if is_trivial_sentinel_text(&tus[idx].frozen_surface)
+                || self.is_numeric_cell(&tus[idx].source_surface)
+            {
+                let src = self.passthrough_text(&tus[idx].source_surface, source_lang, target_lang);
                 tus[idx].draft_translation = Some(src.clone());
                 tus[idx].draft_translation_model = Some(backend.name.clone());
                 processed += 1;
+                self.emit_chunk_done(stage, tus, &[idx], processed, total);
                 on_unit(&tus[idx], &src, processed, total)?;
                 continue;
             }
 
             let add = tus[idx].frozen_surface.len() + 64;
             if !chunk_indices.is_empty()
-                && (used + add > max_chars || chunk_indices.len() >= max_items)
+                && (used + add > self.chunk_char_budget(backend)
+                    || chunk_indices.len() >= self.chunk_max_items(backend, 64))
             {
                 self.translate_chunk_recursive_basic(
                     model,
@@ -316,6 +513,7 @@ impl TranslatorPipeline {
                     total,
                     on_unit,
                 )?;
+                self.emit_chunk_done(stage, tus, &chunk_indices, processed, total);
                 chunk_indices.clear();
                 used = 0;
             }
@@ -337,6 +535,7 @@ impl TranslatorPipeline {
                 total,
                 on_unit,
             )?;
+            self.emit_chunk_done(stage, tus, &chunk_indices, processed, total);
         }
         Ok(())
     }
@@ -358,21 +557,19 @@ impl TranslatorPipeline {
         autosave_text_json: &Path,
         output: &Path,
     ) -> anyhow::Result<()> {
+        self.reset_chunk_context();
+        self.emit_stage_started(stage);
         let total = tus.len().max(1);
-        let max_chars = (backend.ctx_size as usize)
-            .saturating_mul(2)
-            .saturating_sub(1800)
-            .max(4000);
-        let max_items = 64usize;
 
         let mut processed = 0usize;
         let mut chunk_indices: Vec<usize> = Vec::new();
         let mut used = 0usize;
 
         for idx in 0..tus.len() {
-            self.progress.progress(stage, idx + 1, total);
-            if is_trivial_sentinel_text(&tus[idx].frozen_surface) {
-                let src = tus[idx].source_surface.clone();
+            if is_trivial_sentinel_text(&tus[idx].frozen_surface)
+                || self.is_numeric_cell(&tus[idx].source_surface)
+            {
+                let src = self.passthrough_text(&tus[idx].source_surface, source_lang, target_lang);
                 tus[idx].draft_translation = Some(src.clone());
                 tus[idx].draft_translation_model = Some(backend.name.clone());
                 let slot_id = tus[idx].tu_id;
@@ -383,6 +580,7 @@ impl TranslatorPipeline {
                     }
                 }
                 processed += 1;
+                self.emit_chunk_done(stage, tus, &[idx], processed, total);
                 if processed % self.cfg.autosave_every == 0 {
                     let _ = self.write_progress_docx(
                         mask_json,
@@ -399,7 +597,8 @@ impl TranslatorPipeline {
 
             let add = tus[idx].frozen_surface.len() + 64;
             if !chunk_indices.is_empty()
-                && (used + add > max_chars || chunk_indices.len() >= max_items)
+                && (used + add > self.chunk_char_budget(backend)
+                    || chunk_indices.len() >= self.chunk_max_items(backend, 64))
             {
                 self.translate_slot_chunk_recursive_basic(
                     model,
@@ -419,6 +618,7 @@ impl TranslatorPipeline {
                     &mut processed,
                     total,
                 )?;
+                self.emit_chunk_done(stage, tus, &chunk_indices, processed, total);
                 chunk_indices.clear();
                 used = 0;
             }
@@ -444,6 +644,7 @@ impl TranslatorPipeline {
                 &mut processed,
                 total,
             )?;
+            self.emit_chunk_done(stage, tus, &chunk_indices, processed, total);
         }
         Ok(())
     }
@@ -471,6 +672,7 @@ impl TranslatorPipeline {
         if indices.is_empty() {
             return Ok(());
         }
+        self.cfg.cancel.check()?;
 
         let first = tus[indices[0]].tu_id;
         let last = tus[*indices.last().unwrap_or(&indices[0])].tu_id;
@@ -488,15 +690,16 @@ impl TranslatorPipeline {
             tu_block.push_str("\n\n");
         }
 
-        let source_lang_label = lang_label(source_lang);
-        let target_lang_label = lang_label(target_lang);
-        let prompt = render_template(
+        let sources: Vec<&str> = indices
+            .iter()
+            .map(|&i| tus[i].source_surface.as_str())
+            .collect();
+        let prompt = self.render_translate_prompt(
             prompt_tmpl,
-            &[
-                ("source_lang", &source_lang_label),
-                ("target_lang", &target_lang_label),
-                ("tu_block", &tu_block),
-            ],
+            source_lang,
+            target_lang,
+            &tu_block,
+            &sources,
         );
         let _ = self.trace.write_named_text(
             &format!("{stage}.chunk.{first:06}-{last:06}.prompt.txt"),
@@ -504,24 +707,57 @@ impl TranslatorPipeline {
         );
 
         let max_tokens = backend.ctx_size.saturating_sub(256).clamp(512, 4096);
-        let raw = model.chat(
-            None,
-            &prompt,
-            max_tokens,
-            0.12,
-            0.9,
-            Some(40),
-            Some(1.05),
-            false,
-        )?;
+        let mut overflowed = false;
+        let raw = match chat_with_retries(|| {
+            model.chat(
+                None,
+                &prompt,
+                max_tokens,
+                0.12,
+                0.9,
+                Some(40),
+                Some(1.05),
+                false,
+            )
+        }) {
+            Ok(raw) => raw,
+            // Chunk sizing underestimated the tokens: no output, so the chunk is split below.
+            Err(err) if indices.len() > 1 && is_context_overflow(&err) => {
+                self.note_context_overflow(backend, stage, first, last, &err);
+                overflowed = true;
+                String::new()
+            }
+            Err(err) => return Err(err),
+        };
         let cleaned = cleanup_model_text(&raw);
         let _ = self.trace.write_named_text(
             &format!("{stage}.chunk.{first:06}-{last:06}.output.raw.txt"),
             &cleaned,
         );
 
-        let segs = match parse_segmented_output(&cleaned, &expected_ids) {
-            Ok(v) => v,
+        let parsed = parse_segmented_output(&cleaned, &expected_ids);
+        if !overflowed {
+            self.record_chunk_outcome(backend, indices.len(), parsed.is_ok());
+        }
+        let mut outs: Vec<(usize, String)> = match parsed {
+            Ok(segs) => {
+                let mut outs = Vec::with_capacity(indices.len());
+                for &idx in indices {
+                    let tu_id = tus[idx].tu_id;
+                    let out = segs.get(&tu_id).cloned().unwrap_or_default();
+                    let out_unfrozen = self.finalize_basic_output(
+                        model,
+                        backend,
+                        source_lang,
+                        target_lang,
+                        repair_tmpl,
+                        &mut tus[idx],
+                        cleanup_model_text(&out),
+                    )?;
+                    outs.push((idx, out_unfrozen));
+                }
+                outs
+            }
             Err(_err) => {
                 if indices.len() > 1 {
                     let mid = indices.len() / 2;
@@ -584,36 +820,21 @@ impl TranslatorPipeline {
                     &mut tus[idx],
                     out,
                 )?;
-                apply_slot_text(text_variant, tu_id, &out_unfrozen)?;
-                *processed += 1;
-                if *processed % self.cfg.autosave_every == 0 {
-                    let _ = self.write_progress_docx(
-                        mask_json,
-                        offsets_json,
-                        autosave_text_json,
-                        output,
-                        text_variant,
-                        *processed,
-                        total,
-                    );
-                }
-                return Ok(());
+                vec![(idx, out_unfrozen)]
             }
         };
 
-        for &idx in indices {
-            let tu_id = tus[idx].tu_id;
-            let out = segs.get(&tu_id).cloned().unwrap_or_default();
-            let out_unfrozen = self.finalize_basic_output(
-                model,
-                backend,
-                source_lang,
-                target_lang,
-                repair_tmpl,
-                &mut tus[idx],
-                cleanup_model_text(&out),
-            )?;
-            apply_slot_text(text_variant, tu_id, &out_unfrozen)?;
+        // `--interactive`: the chunk is only committed after review; units sent back for
+        // retranslation go through this function again (and are reviewed again).
+        let mut retranslate: Vec<usize> = Vec::new();
+        if self.cfg.interactive {
+            retranslate = self.review_chunk(stage, tus, &mut outs)?;
+        }
+        for (idx, out_unfrozen) in outs {
+            if retranslate.contains(&idx) {
+                continue;
+            }
+            apply_slot_text(text_variant, tus[idx].tu_id, &out_unfrozen)?;
             *processed += 1;
             if *processed % self.cfg.autosave_every == 0 {
                 let _ = self.write_progress_docx(
@@ -627,6 +848,26 @@ impl TranslatorPipeline {
                 );
             }
         }
+        if !retranslate.is_empty() {
+            self.translate_slot_chunk_recursive_basic(
+                model,
+                backend,
+                source_lang,
+                target_lang,
+                stage,
+                prompt_tmpl,
+                repair_tmpl,
+                tus,
+                text_variant,
+                mask_json,
+                offsets_json,
+                autosave_text_json,
+                output,
+                &retranslate,
+                processed,
+                total,
+            )?;
+        }
         Ok(())
     }
 
@@ -641,10 +882,10 @@ impl TranslatorPipeline {
         mut out: String,
     ) -> anyhow::Result<String> {
         let source = tu.frozen_surface.clone();
-        let must_keep_tokens = crate::sentinels::must_keep_tokens(&source);
-        let nt_map = render_nt_map_for_prompt(&tu.nt_map);
         let mut repairs_done = 0usize;
-        let mut max_repairs = 2usize;
+        let mut max_repairs = self.cfg.max_repairs;
+        // Best valid candidate so far, ranked by (hard, soft) heuristic flag counts.
+        let mut best: Option<((usize, usize), String)> = None;
         loop {
             out = normalize_nt_tokens(&source, &tu.nt_map, &out);
             let validation_error = validate_translation(tu, &out)
@@ -652,6 +893,12 @@ impl TranslatorPipeline {
                 .map(|e| e.to_string())
                 .unwrap_or_default();
             let heur = quality_heuristics(tu, &out, source_lang, target_lang);
+            if validation_error.is_empty() {
+                let rank = (heur.hard_flags.len(), heur.soft_flags.len());
+                if best.as_ref().is_none_or(|(r, _)| rank < *r) {
+                    best = Some((rank, out.clone()));
+                }
+            }
             let needs_repair = !validation_error.is_empty() || heur.wants_force_retranslate();
             if !needs_repair {
                 break;
@@ -661,7 +908,7 @@ impl TranslatorPipeline {
                 || validation_error.contains("nt_token_")
                 || validation_error.contains("unexpected_mt_token")
             {
-                max_repairs = max_repairs.max(6);
+                max_repairs = max_repairs.max(self.cfg.max_repairs_token_errors);
             }
             if repairs_done >= max_repairs {
                 break;
@@ -676,19 +923,31 @@ impl TranslatorPipeline {
             if reason.is_empty() {
                 reason = "needs_repair".to_string();
             }
+            if !self.has_cached_repair(tu, &reason) && !self.take_repair_budget() {
+                tu.qe_flags.push("repair_budget_exhausted".to_string());
+                break;
+            }
             out = self.repair_translation(
                 model,
                 repair_tmpl,
                 source_lang,
                 target_lang,
-                &source,
+                tu,
                 &out,
-                &must_keep_tokens,
                 &reason,
-                &nt_map,
             )?;
             repairs_done += 1;
         }
+        // A later repair may be worse than an earlier candidate: keep the best one.
+        if let Some((rank, cand)) = best {
+            let current = validate_translation(tu, &out).is_ok().then(|| {
+                let heur = quality_heuristics(tu, &out, source_lang, target_lang);
+                (heur.hard_flags.len(), heur.soft_flags.len())
+            });
+            if current.is_none_or(|r| rank < r) {
+                out = cand;
+            }
+        }
         if let Err(err) = validate_translation(tu, &out) {
             let scope_tag = if tu.scope_key.starts_with("slot#") {
                 "slot"
@@ -705,15 +964,27 @@ impl TranslatorPipeline {
                 &report,
             );
 
-            if let Ok(forced) = self.force_translate_preserving_tokens(
-                model,
-                backend,
-                source_lang,
-                target_lang,
-                &source,
-            ) {
+            let forced = if self.take_repair_budget() {
+                self.force_translate_preserving_tokens(
+                    model,
+                    backend,
+                    source_lang,
+                    target_lang,
+                    &source,
+                )
+                .ok()
+            } else {
+                if !tu.qe_flags.iter().any(|f| f == "repair_budget_exhausted") {
+                    tu.qe_flags.push("repair_budget_exhausted".to_string());
+                }
+                None
+            };
+            if let Some(forced) = forced {
                 match validate_translation(tu, &forced) {
-                    Ok(()) => out = forced,
+                    Ok(()) => {
+                        out = forced;
+                        tu.qe_flags.push("forced_retranslate".to_string());
+                    }
                     Err(err2) => {
                         let report = format!(
                             "validate_error: {err2}\n\nSOURCE_FROZEN:\n{source}\n\nFORCED_OUTPUT_FROZEN:\n{forced}\n"
@@ -726,13 +997,25 @@ impl TranslatorPipeline {
                             &report,
                         );
                         out = source;
+                        tu.qe_flags.push("source_fallback".to_string());
                     }
                 }
             } else {
                 out = source;
+                tu.qe_flags.push("source_fallback".to_string());
             }
         }
+        // Outcome flags (`repairs:N`, forced/fallback) for reports.
+        if repairs_done > 0 {
+            tu.qe_flags.insert(0, format!("repairs:{repairs_done}"));
+        }
+        self.emit(PipelineEvent::TuValidated {
+            tu_id: tu.tu_id,
+            source_fallback: tu.qe_flags.iter().any(|f| f == "source_fallback"),
+            qe_flags: tu.qe_flags.clone(),
+        });
         let out_unfrozen = unfreeze_text(&out, &tu.nt_map);
+        self.remember_chunk_context(&tu.source_surface, &out_unfrozen);
         tu.draft_translation = Some(out_unfrozen.clone());
         tu.draft_translation_model = Some(backend.name.clone());
         Ok(out_unfrozen)
@@ -873,6 +1156,7 @@ impl TranslatorPipeline {
         if indices.is_empty() {
             return Ok(());
         }
+        self.cfg.cancel.check()?;
 
         let first = tus[indices[0]].tu_id;
         let last = tus[*indices.last().unwrap_or(&indices[0])].tu_id;
@@ -890,15 +1174,16 @@ impl TranslatorPipeline {
             tu_block.push_str("\n\n");
         }
 
-        let source_lang_label = lang_label(source_lang);
-        let target_lang_label = lang_label(target_lang);
-        let prompt = render_template(
+        let sources: Vec<&str> = indices
+            .iter()
+            .map(|&i| tus[i].source_surface.as_str())
+            .collect();
+        let prompt = self.render_translate_prompt(
             prompt_tmpl,
-            &[
-                ("source_lang", &source_lang_label),
-                ("target_lang", &target_lang_label),
-                ("tu_block", &tu_block),
-            ],
+            source_lang,
+            target_lang,
+            &tu_block,
+            &sources,
         );
         let _ = self.trace.write_named_text(
             &format!("{stage}.chunk.{first:06}-{last:06}.prompt.txt"),
@@ -906,23 +1191,39 @@ impl TranslatorPipeline {
         );
 
         let max_tokens = backend.ctx_size.saturating_sub(256).clamp(512, 4096);
-        let raw = model.chat(
-            None,
-            &prompt,
-            max_tokens,
-            0.12,
-            0.9,
-            Some(40),
-            Some(1.05),
-            false,
-        )?;
+        let mut overflowed = false;
+        let raw = match chat_with_retries(|| {
+            model.chat(
+                None,
+                &prompt,
+                max_tokens,
+                0.12,
+                0.9,
+                Some(40),
+                Some(1.05),
+                false,
+            )
+        }) {
+            Ok(raw) => raw,
+            // Chunk sizing underestimated the tokens: no output, so the chunk is split below.
+            Err(err) if indices.len() > 1 && is_context_overflow(&err) => {
+                self.note_context_overflow(backend, stage, first, last, &err);
+                overflowed = true;
+                String::new()
+            }
+            Err(err) => return Err(err),
+        };
         let cleaned = cleanup_model_text(&raw);
         let _ = self.trace.write_named_text(
             &format!("{stage}.chunk.{first:06}-{last:06}.output.raw.txt"),
             &cleaned,
         );
 
-        let segs = match parse_segmented_output(&cleaned, &expected_ids) {
+        let parsed = parse_segmented_output(&cleaned, &expected_ids);
+        if !overflowed {
+            self.record_chunk_outcome(backend, indices.len(), parsed.is_ok());
+        }
+        let segs = match parsed {
             Ok(v) => v,
             Err(err) => {
                 if indices.len() > 1 {
@@ -1034,6 +1335,36 @@ impl TranslatorPipeline {
     }
 }
 
+/// First autosave text.json that belongs to the same source (same placeholder prefix and slot count).
+/// One written in a text.json schema this build does not read is an error, not a miss.
+pub(super) fn load_resume_text(
+    candidates: &[&Path],
+    source: &PureTextJson,
+) -> anyhow::Result<Option<(std::path::PathBuf, PureTextJson)>> {
+    for path in candidates {
+        let Ok(bytes) = fs::read(path) else {
+            continue;
+        };
+        if let Ok(head) = serde_json::from_slice::<ArtifactHead>(&bytes) {
+            check_schema(
+                &path.display().to_string(),
+                head.version,
+                (1, PURE_TEXT_VERSION),
+                head.producer.as_ref(),
+            )?;
+        }
+        let Ok(text) = serde_json::from_slice::<PureTextJson>(&bytes) else {
+            continue;
+        };
+        if text.placeholder_prefix == source.placeholder_prefix
+            && text.slot_texts.len() == source.slot_texts.len()
+        {
+            return Ok(Some((path.to_path_buf(), text)));
+        }
+    }
+    Ok(None)
+}
+
 fn apply_slot_text(
     text_json: &mut PureTextJson,
     slot_id: usize,
@@ -1053,3 +1384,58 @@ fn apply_slot_text(
     text_json.slot_texts[idx] = translated.to_string();
     Ok(())
 }
+
+/// Provenance of each slot the model translated, read off the units' outcome flags.
+fn slot_provenance(tus_slots: &[TranslationUnit]) -> Vec<SlotProvenance> {
+    let mut out: Vec<SlotProvenance> = tus_slots
+        .iter()
+        .filter(|tu| tu.draft_translation.is_some())
+        .map(|tu| {
+            let has = |flag: &str| tu.qe_flags.iter().any(|f| f == flag);
+            SlotProvenance {
+                slot_id: tu.tu_id,
+                backend: tu.draft_translation_model.clone(),
+                repairs: tu
+                    .qe_flags
+                    .iter()
+                    .find_map(|f| f.strip_prefix("repairs:"))
+                    .and_then(|n| n.parse().ok())
+                    .unwrap_or(0),
+                forced_retranslate: has("forced_retranslate"),
+                source_fallback: has("source_fallback"),
+                qe_score: tu.qe_score,
+                qe_flags: tu.qe_flags.clone(),
+            }
+        })
+        .collect();
+    out.sort_by_key(|p| p.slot_id);
+    out
+}
+
+/// Record the hard quality flags of the translated slots (for `highlight_low_confidence` and
+/// `--from-report`).
+fn flag_hard_quality(tus_slots: &mut [TranslationUnit], source_lang: &str, target_lang: &str) {
+    for tu in tus_slots.iter_mut() {
+        let Some(out) = tu.draft_translation.as_deref() else {
+            continue;
+        };
+        let heur = quality_heuristics(tu, out, source_lang, target_lang);
+        tu.qe_flags.extend(heur.hard_flags);
+    }
+}
+
+/// Slots with a source fallback or a hard quality flag, unless a reviewer edited them.
+fn low_confidence_slots(tus_slots: &[TranslationUnit]) -> Vec<usize> {
+    let mut out: Vec<usize> = tus_slots
+        .iter()
+        .filter(|tu| !tu.qe_flags.iter().any(|f| f == "human_edit"))
+        .filter(|tu| {
+            tu.qe_flags
+                .iter()
+                .any(|f| f == "source_fallback" || is_hard_quality_flag(f))
+        })
+        .map(|tu| tu.tu_id)
+        .collect();
+    out.sort_unstable();
+    out
+}
@@ -0,0 +1,237 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+
+use crate::docx::decompose::{extract_mask_json_and_offsets_from, MaskOptions};
+use crate::docx::filter::{filter_docx_with_rules, DocxFilterRules};
+use crate::docx::pure_text::pure_text_from_session;
+use crate::docx::session::DocumentSession;
+use crate::errors::{ErrorKind, ResultExt};
+use crate::freezer::{freeze_text_with, AcronymRules};
+use crate::htmldoc::is_html_path;
+use crate::i18n::tr_args;
+use crate::ir::TranslationUnit;
+use crate::sentinels::{seg_end, seg_start};
+use crate::textdoc::TextDocKind;
+use crate::textutil::is_trivial_sentinel_text;
+
+use super::super::config::PipelineMode;
+use super::super::docmap::build_para_slot_units;
+use super::TranslatorPipeline;
+
+impl TranslatorPipeline {
+    /// `--emit-prompts-only`: extract the DOCX, chunk it the way Translate A of the configured
+    /// mode would, and write every rendered prompt to the trace dir. No model is loaded, and
+    /// chunk context from earlier translations (`context_overlap`) is necessarily empty.
+    pub fn emit_prompts_only(&mut self, input: &Path, output: &Path) -> anyhow::Result<()> {
+        if TextDocKind::from_path(input).is_some() || is_html_path(input) {
+            return Err(anyhow!("--emit-prompts-only supports DOCX inputs only"))
+                .kind(ErrorKind::Usage, "bad arguments");
+        }
+        fs::create_dir_all(self.trace.dir())
+            .with_context(|| format!("create trace dir: {}", self.trace.dir().display()))?;
+        let stem = output
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("output");
+
+        // OCR needs no model, so the prompts include the appended OCR text.
+        let ocr_work = match self.cfg.ocr_command.clone() {
+            Some(command) => self.append_ocr_text(&command, input, output)?,
+            None => None,
+        };
+        let input = ocr_work.as_deref().unwrap_or(input);
+        let mut work_docx = input.to_path_buf();
+        if let Some(rules_path) = self.cfg.docx_filter_rules.clone() {
+            self.progress.info(tr_args(
+                "pipeline.filter_rules",
+                &[("path", &rules_path.display())],
+            ));
+            let rules = DocxFilterRules::from_toml_path(&rules_path)?;
+            let filtered = self.trace.dir().join(format!("{stem}.filtered.docx"));
+            let fixes = filter_docx_with_rules(input, &filtered, &rules, &self.cfg.package)?;
+            self.report_parse_fixes(&fixes);
+            work_docx = filtered;
+        }
+        let session =
+            DocumentSession::open_with(&work_docx, &self.cfg.package, &self.cfg.placeholder_prefix)
+                .kind(ErrorKind::InputDocx, "read input docx")?;
+        self.report_parse_fixes(session.parse_fixes());
+        self.warn_unsupported_content(&session);
+        let source_text =
+            pure_text_from_session(&session).kind(ErrorKind::InputDocx, "read input docx")?;
+        let offsets = extract_mask_json_and_offsets_from(
+            &session,
+            &self.trace.dir().join(format!("{stem}.mask.json")),
+            &self.trace.dir().join(format!("{stem}.offsets.json")),
+            &self.trace.dir().join(format!("{stem}.mask.blobs.bin")),
+            MaskOptions::default(),
+        )
+        .kind(ErrorKind::InputDocx, "decompose input docx")?;
+        let mut para_units = build_para_slot_units(&session, &source_text, &offsets)?;
+        if let Some(max_tus) = self.cfg.max_tus {
+            let keep = max_tus.max(1).min(para_units.len());
+            para_units.truncate(keep);
+            self.progress
+                .info(tr_args("pipeline.max_tus", &[("count", &keep)]));
+        }
+        self.apply_unit_filter(&mut para_units, |u| (u.tu_id, u.scope_key.as_str()));
+
+        // Same units, chunk limits and skip rules as the Translate A stage of each mode.
+        let (stage, tus, max_items, overhead) = match self.cfg.mode {
+            PipelineMode::Basic => {
+                let mut seen: HashSet<usize> = HashSet::new();
+                let mut tus = Vec::new();
+                for slot_id in para_units.iter().flat_map(|u| u.slot_ids.iter().copied()) {
+                    if slot_id == 0 || !seen.insert(slot_id) {
+                        continue;
+                    }
+                    let src = source_text
+                        .slot_texts
+                        .get(slot_id - 1)
+                        .cloned()
+                        .ok_or_else(|| anyhow!("slot_id_out_of_range: {slot_id}"))?;
+                    tus.push(prompt_unit(
+                        slot_id,
+                        format!("slot#{slot_id}"),
+                        src,
+                        &self.cfg.acronyms,
+                    ));
+                }
+                ("translate_a(slot_texts)", tus, 64usize, 64usize)
+            }
+            PipelineMode::Full => {
+                let projection = self.cfg.slot_projection;
+                let tus = para_units
+                    .into_iter()
+                    .map(|p| {
+                        let surface = p
+                            .projected_surface(projection, &source_text.slot_texts)
+                            .map(|(surface, _)| surface)
+                            .unwrap_or(p.source_surface);
+                        prompt_unit(p.tu_id, p.scope_key, surface, &self.cfg.acronyms)
+                    })
+                    .collect();
+                ("translate_a", tus, 32usize, 96usize)
+            }
+        };
+        let tus: Vec<TranslationUnit> = tus
+            .into_iter()
+            .filter(|tu| match self.cfg.mode {
+                PipelineMode::Basic => {
+                    !(is_trivial_sentinel_text(&tu.frozen_surface)
+                        || self.is_numeric_cell(&tu.source_surface))
+                }
+                PipelineMode::Full => {
+                    !(tu.frozen_surface.trim().is_empty()
+                        || is_trivial_sentinel_text(&tu.source_surface)
+                        || self.is_numeric_cell(&tu.source_surface))
+                }
+            })
+            .collect();
+
+        let (source_lang, target_lang) = match self.cfg.mode {
+            PipelineMode::Basic => self.resolve_lang_pair_from_pure_text(&source_text),
+            PipelineMode::Full => self.resolve_lang_pair(&tus),
+        };
+        self.progress.info(tr_args(
+            "pipeline.language",
+            &[("src", &source_lang), ("tgt", &target_lang)],
+        ));
+        self.route_translate_backend(&source_lang, &target_lang);
+        self.open_project_memory(&source_lang, &target_lang)?;
+
+        let backend = self.cfg.translate_backend.clone();
+        let prompt_tmpl = self
+            .cfg
+            .prompts
+            .for_backend(&backend.name)
+            .translate_a
+            .clone();
+        let max_chars = self.chunk_char_budget(&backend);
+        let max_items = self.chunk_max_items(&backend, max_items);
+
+        let mut chunks: Vec<&[TranslationUnit]> = Vec::new();
+        let (mut start, mut used) = (0usize, 0usize);
+        for (idx, tu) in tus.iter().enumerate() {
+            let add = tu.frozen_surface.len() + overhead;
+            if idx > start && (used + add > max_chars || idx - start >= max_items) {
+                chunks.push(&tus[start..idx]);
+                start = idx;
+                used = 0;
+            }
+            used += add;
+        }
+        if start < tus.len() {
+            chunks.push(&tus[start..]);
+        }
+
+        self.reset_chunk_context();
+        for chunk in &chunks {
+            let mut tu_block = String::new();
+            for tu in *chunk {
+                tu_block.push_str(&seg_start(tu.tu_id));
+                tu_block.push('\n');
+                tu_block.push_str(&tu.frozen_surface);
+                tu_block.push('\n');
+                tu_block.push_str(&seg_end(tu.tu_id));
+                tu_block.push_str("\n\n");
+            }
+            let sources: Vec<&str> = chunk.iter().map(|tu| tu.source_surface.as_str()).collect();
+            let prompt = self.render_translate_prompt(
+                &prompt_tmpl,
+                &source_lang,
+                &target_lang,
+                &tu_block,
+                &sources,
+            );
+            let (first, last) = (chunk[0].tu_id, chunk[chunk.len() - 1].tu_id);
+            // Written directly: the dry run's output must not depend on trace switches/limits.
+            let path = self
+                .trace
+                .dir()
+                .join(format!("{stage}.chunk.{first:06}-{last:06}.prompt.txt"));
+            fs::write(&path, prompt).with_context(|| format!("write {}", path.display()))?;
+        }
+        self.progress.info(tr_args(
+            "pipeline.prompts_emitted",
+            &[
+                ("count", &chunks.len()),
+                ("units", &tus.len()),
+                ("backend", &backend.name),
+                ("path", &self.trace.dir().display()),
+            ],
+        ));
+        Ok(())
+    }
+}
+
+fn prompt_unit(
+    tu_id: usize,
+    scope_key: String,
+    source: String,
+    acronyms: &AcronymRules,
+) -> TranslationUnit {
+    let fr = freeze_text_with(&source, acronyms);
+    TranslationUnit {
+        tu_id,
+        part_name: String::new(),
+        scope_key,
+        para_style: None,
+        atoms: Vec::new(),
+        spans: Vec::new(),
+        source_surface: source,
+        frozen_surface: fr.text,
+        nt_map: fr.nt_map,
+        nt_mask: fr.mask,
+        draft_translation: None,
+        final_translation: None,
+        alt_translation: None,
+        draft_translation_model: None,
+        alt_translation_model: None,
+        qe_score: None,
+        qe_flags: Vec::new(),
+    }
+}
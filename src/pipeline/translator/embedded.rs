@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context};
+
+use crate::docx::package::DocxPackage;
+use crate::docx::xml::{parse_xml_part_with, write_xml_part, XmlEvent};
+use crate::errors::ResultExt;
+use crate::freezer::freeze_text_with;
+use crate::i18n::tr_args;
+use crate::ir::TranslationUnit;
+
+use super::super::config::PipelineMode;
+use super::TranslatorPipeline;
+
+const SHARED_STRINGS: &str = "xl/sharedStrings.xml";
+
+#[derive(Clone, Copy)]
+enum EmbeddedKind {
+    Word,
+    Excel,
+}
+
+/// OOXML packages under `word/embeddings/` we know how to translate.
+fn embedded_kind(name: &str) -> Option<EmbeddedKind> {
+    let lower = name.to_lowercase();
+    if !lower.starts_with("word/embeddings/") {
+        return None;
+    }
+    match Path::new(&lower).extension().and_then(|e| e.to_str()) {
+        Some("docx" | "docm") => Some(EmbeddedKind::Word),
+        Some("xlsx" | "xlsm") => Some(EmbeddedKind::Excel),
+        _ => None,
+    }
+}
+
+impl TranslatorPipeline {
+    /// `pipeline.translate_embedded`: translates the embedded Word/Excel packages of `input` and
+    /// returns a copy of `input` (in the trace dir) that embeds the translations, or `None`
+    /// when there is nothing to translate. Embedded Word documents go through the configured
+    /// pipeline mode (one level deep); Excel workbooks get their shared strings translated.
+    /// A failing object is reported and kept as-is.
+    pub(super) fn translate_embedded_objects(
+        &mut self,
+        input: &Path,
+        output: &Path,
+    ) -> anyhow::Result<Option<PathBuf>> {
+        let pkg = DocxPackage::read_streaming_with(input, &self.cfg.package)?;
+        let embedded: Vec<(usize, EmbeddedKind)> = pkg
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| !e.is_dir)
+            .filter_map(|(i, e)| embedded_kind(&e.name).map(|k| (i, k)))
+            .collect();
+        if embedded.is_empty() {
+            return Ok(None);
+        }
+        fs::create_dir_all(self.trace.dir())
+            .with_context(|| format!("create trace dir: {}", self.trace.dir().display()))?;
+        let stem = output
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("output")
+            .to_string();
+
+        let mut zip = pkg
+            .open_source()?
+            .ok_or_else(|| anyhow!("embedded objects: package source unavailable"))?;
+        let mut replacements: HashMap<String, Vec<u8>> = HashMap::new();
+        for (n, (index, kind)) in embedded.into_iter().enumerate() {
+            let name = pkg.entries[index].name.clone();
+            let ext = Path::new(&name)
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or("bin")
+                .to_lowercase();
+            let src = self
+                .trace
+                .dir()
+                .join(format!("{stem}.embedded{}.{ext}", n + 1));
+            let dst = self
+                .trace
+                .dir()
+                .join(format!("{stem}.embedded{}.translated.{ext}", n + 1));
+            let mut data = Vec::new();
+            zip.by_index(index)
+                .context("zip entry")?
+                .read_to_end(&mut data)
+                .with_context(|| format!("read embedded object: {name}"))?;
+            fs::write(&src, &data).with_context(|| format!("write {}", src.display()))?;
+
+            self.progress
+                .info(tr_args("pipeline.embedded_translate", &[("name", &name)]));
+            let result = match kind {
+                EmbeddedKind::Word => match self.cfg.mode {
+                    PipelineMode::Basic => self.translate_docx_basic(&src, &dst),
+                    PipelineMode::Full => self.translate_docx_full(&src, &dst),
+                },
+                EmbeddedKind::Excel => self.translate_xlsx_shared_strings(&src, &dst),
+            };
+            match result
+                .and_then(|()| fs::read(&dst).with_context(|| format!("read {}", dst.display())))
+            {
+                Ok(bytes) => {
+                    replacements.insert(name, bytes);
+                }
+                Err(err) => self.progress.info(tr_args(
+                    "pipeline.embedded_failed",
+                    &[("name", &name), ("err", &format!("{err:#}"))],
+                )),
+            }
+        }
+        drop(zip);
+        if replacements.is_empty() {
+            return Ok(None);
+        }
+        let work = self.trace.dir().join(format!("{stem}.embedded.docx"));
+        pkg.write_with_replacements(&work, &replacements)
+            .with_context(|| format!("write {}", work.display()))?;
+        Ok(Some(work))
+    }
+
+    /// Translate every `<t>` of `xl/sharedStrings.xml` (phonetic `<rPh>` runs excluded).
+    fn translate_xlsx_shared_strings(&mut self, input: &Path, output: &Path) -> anyhow::Result<()> {
+        let pkg = DocxPackage::read_streaming_with(input, &self.cfg.package)?;
+        let Some(ent) = pkg.entries.iter().find(|e| e.name == SHARED_STRINGS) else {
+            fs::copy(input, output).with_context(|| format!("write {}", output.display()))?;
+            return Ok(());
+        };
+        let (mut part, fixes) = parse_xml_part_with(&ent.name, &ent.data, self.cfg.package.lenient)
+            .with_context(|| format!("parse xml: {}", ent.name))?;
+        self.report_parse_fixes(&fixes);
+
+        let mut text_events: Vec<usize> = Vec::new();
+        let mut stack: Vec<&str> = Vec::new();
+        for (i, ev) in part.events.iter().enumerate() {
+            match ev {
+                XmlEvent::Start { name, .. } => stack.push(name),
+                XmlEvent::End { .. } => {
+                    let _ = stack.pop();
+                }
+                XmlEvent::Text { text }
+                    if stack.last() == Some(&"t")
+                        && !stack.contains(&"rPh")
+                        && !text.trim().is_empty() =>
+                {
+                    text_events.push(i);
+                }
+                _ => {}
+            }
+        }
+
+        let mut tus: Vec<TranslationUnit> = Vec::with_capacity(text_events.len());
+        for (k, &ev_idx) in text_events.iter().enumerate() {
+            let XmlEvent::Text { text } = &part.events[ev_idx] else {
+                continue;
+            };
+            let fr = freeze_text_with(text, &self.cfg.acronyms);
+            tus.push(TranslationUnit {
+                tu_id: k + 1,
+                part_name: SHARED_STRINGS.to_string(),
+                scope_key: format!("{SHARED_STRINGS}#t@{ev_idx}"),
+                para_style: None,
+                atoms: Vec::new(),
+                spans: Vec::new(),
+                source_surface: text.clone(),
+                frozen_surface: fr.text,
+                nt_map: fr.nt_map,
+                nt_mask: fr.mask,
+                draft_translation: None,
+                final_translation: None,
+                alt_translation: None,
+                draft_translation_model: None,
+                alt_translation_model: None,
+                qe_score: None,
+                qe_flags: Vec::new(),
+            });
+        }
+        self.progress.info(tr_args(
+            "pipeline.translatable_slots",
+            &[("count", &tus.len())],
+        ));
+
+        let (source_lang, target_lang) = self.resolve_lang_pair(&tus);
+        let (mut model, mut translate_backend) = self.load_translate_model()?;
+        let mut translated: Vec<Option<String>> = vec![None; tus.len()];
+        loop {
+            let translate_prompts = self.cfg.prompts.for_backend(&translate_backend.name);
+            let prompt_translate_a = translate_prompts.translate_a.clone();
+            let prompt_translate_repair = translate_prompts.translate_repair.clone();
+            let result = self.translate_units_segmented_basic(
+                &mut model,
+                &translate_backend,
+                &source_lang,
+                &target_lang,
+                "translate_a(xlsx)",
+                &prompt_translate_a,
+                &prompt_translate_repair,
+                &mut tus,
+                &mut |tu, out_unfrozen, _processed, _total| {
+                    if let Some(slot) = translated.get_mut(tu.tu_id - 1) {
+                        *slot = Some(out_unfrozen.to_string());
+                    }
+                    Ok(())
+                },
+            );
+            match result {
+                Ok(()) => break,
+                Err(err) => {
+                    self.fall_back_translate_backend(err).stage("translate_a")?;
+                    (model, translate_backend) = self.load_translate_model()?;
+                }
+            }
+        }
+
+        for (&ev_idx, t) in text_events.iter().zip(translated) {
+            if let (Some(t), XmlEvent::Text { text }) = (t, &mut part.events[ev_idx]) {
+                *text = t;
+            }
+        }
+        let bytes =
+            write_xml_part(&part).with_context(|| format!("serialize xml: {}", part.name))?;
+        let mut replacements = HashMap::new();
+        replacements.insert(SHARED_STRINGS.to_string(), bytes);
+        pkg.write_with_replacements(output, &replacements)
+    }
+}
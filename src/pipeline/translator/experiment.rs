@@ -0,0 +1,361 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use anyhow::Context;
+use serde::Serialize;
+
+use crate::config::ResolvedBackend;
+use crate::docx::decompose::PlaceholderPrefix;
+use crate::docx::package::PackageOptions;
+use crate::docx::pure_text::pure_text_from_session;
+use crate::docx::session::DocumentSession;
+use crate::errors::{ErrorKind, ResultExt};
+use crate::freezer::{freeze_text_with, AcronymRules, FreezeResult};
+use crate::htmldoc::{extract_html, is_html_path};
+use crate::i18n::tr_args;
+use crate::ir::TranslationUnit;
+use crate::quality::quality_heuristics;
+use crate::textdoc::{TextDocKind, TextDocument};
+use crate::textutil::{is_trivial_sentinel_text, strip_sentinels};
+
+use super::super::prompts::read_prompt_file;
+use super::{load_model, TranslatorPipeline};
+
+/// `--experiment`: two variants (backend and/or translate prompt) run over the same TU sample.
+#[derive(Clone, Debug, Default)]
+pub struct ExperimentSpec {
+    /// Backend per variant (default: `translate_backend`).
+    pub backends: [Option<String>; 2],
+    /// Translate prompt file per variant (default: the backend's `translate_a` prompt).
+    pub prompts: [Option<PathBuf>; 2],
+    /// TUs taken evenly across the document.
+    pub sample: usize,
+}
+
+#[derive(Serialize)]
+struct ExperimentReport {
+    input: String,
+    source_lang: String,
+    target_lang: String,
+    sample: usize,
+    total_tus: usize,
+    variants: Vec<VariantStats>,
+    units: Vec<UnitComparison>,
+}
+
+#[derive(Serialize, Default)]
+struct VariantStats {
+    label: String,
+    backend: String,
+    prompt: String,
+    elapsed_secs: f64,
+    error: Option<String>,
+    translated: usize,
+    clean: usize,
+    repaired: usize,
+    repairs_total: usize,
+    forced_retranslate: usize,
+    source_fallback: usize,
+    flagged: usize,
+    mean_len_ratio: f64,
+    flag_counts: BTreeMap<String, usize>,
+}
+
+#[derive(Serialize)]
+struct UnitComparison {
+    tu_id: usize,
+    source: String,
+    a: Option<String>,
+    b: Option<String>,
+    a_flags: Vec<String>,
+    b_flags: Vec<String>,
+}
+
+impl TranslatorPipeline {
+    pub fn run_experiment(
+        &mut self,
+        input: &Path,
+        report_path: &Path,
+        spec: &ExperimentSpec,
+    ) -> anyhow::Result<()> {
+        let all = experiment_units(
+            input,
+            &self.cfg.package,
+            &self.cfg.placeholder_prefix,
+            &self.cfg.acronyms,
+        )
+        .kind(ErrorKind::InputDocx, "read input")?;
+        let total_tus = all.len();
+        let tus = sample_evenly(all, spec.sample.max(1));
+        self.progress.info(tr_args(
+            "pipeline.experiment_sample",
+            &[("count", &tus.len()), ("total", &total_tus)],
+        ));
+
+        let (source_lang, target_lang) =
+            match (self.cfg.source_lang.clone(), self.cfg.target_lang.clone()) {
+                (Some(s), Some(t)) => (s, t),
+                _ => {
+                    let excerpts: Vec<String> = tus
+                        .iter()
+                        .map(|tu| strip_sentinels(&tu.source_surface))
+                        .filter(|s| s.chars().count() >= 8)
+                        .take(20)
+                        .collect();
+                    self.auto_lang_pair(&excerpts)
+                }
+            };
+        self.progress.info(tr_args(
+            "pipeline.language",
+            &[("src", &source_lang), ("tgt", &target_lang)],
+        ));
+
+        let mut variants: Vec<VariantStats> = Vec::with_capacity(2);
+        let mut outputs: Vec<Vec<TranslationUnit>> = Vec::with_capacity(2);
+        for (i, label) in ["A", "B"].into_iter().enumerate() {
+            let backend = match spec.backends[i].as_deref() {
+                Some(name) => self.cfg.resolve_extra_backend(name)?,
+                None => self.cfg.translate_backend.clone(),
+            };
+            let prompts = self.cfg.prompts.for_backend(&backend.name).clone();
+            let (prompt_label, prompt_tmpl) = match spec.prompts[i].as_deref() {
+                Some(path) => {
+                    let config_dir = self
+                        .cfg
+                        .config_path
+                        .parent()
+                        .map(Path::to_path_buf)
+                        .unwrap_or_else(|| PathBuf::from("."));
+                    let text = read_prompt_file(&config_dir, path, "experiment")
+                        .kind(ErrorKind::Config, "load experiment prompt")?;
+                    (path.display().to_string(), text)
+                }
+                None => ("translate_a".to_string(), prompts.translate_a.clone()),
+            };
+            self.progress.info(tr_args(
+                "pipeline.experiment_variant",
+                &[
+                    ("label", &label),
+                    ("backend", &backend.name),
+                    ("prompt", &prompt_label),
+                ],
+            ));
+
+            let mut variant_tus = tus.clone();
+            let started = Instant::now();
+            let result = self.run_experiment_variant(
+                &backend,
+                &source_lang,
+                &target_lang,
+                label,
+                &prompt_tmpl,
+                &prompts.translate_repair,
+                &mut variant_tus,
+            );
+            record_quality_flags(&mut variant_tus, &source_lang, &target_lang);
+            let mut stats = variant_stats(&variant_tus);
+            stats.label = label.to_string();
+            stats.backend = backend.name.clone();
+            stats.prompt = prompt_label;
+            stats.elapsed_secs = started.elapsed().as_secs_f64();
+            stats.error = result.err().map(|err| format!("{err:#}"));
+            variants.push(stats);
+            outputs.push(variant_tus);
+        }
+
+        let units = tus
+            .iter()
+            .enumerate()
+            .map(|(i, tu)| UnitComparison {
+                tu_id: tu.tu_id,
+                source: tu.source_surface.clone(),
+                a: outputs[0][i].draft_translation.clone(),
+                b: outputs[1][i].draft_translation.clone(),
+                a_flags: outputs[0][i].qe_flags.clone(),
+                b_flags: outputs[1][i].qe_flags.clone(),
+            })
+            .collect();
+        let report = ExperimentReport {
+            input: input.display().to_string(),
+            source_lang,
+            target_lang,
+            sample: tus.len(),
+            total_tus,
+            variants,
+            units,
+        };
+        for v in &report.variants {
+            self.progress.info(tr_args(
+                "pipeline.experiment_result",
+                &[
+                    ("label", &v.label),
+                    ("clean", &v.clean),
+                    ("repaired", &v.repaired),
+                    ("fallback", &v.source_fallback),
+                    ("flagged", &v.flagged),
+                    ("total", &report.sample),
+                    ("secs", &format!("{:.1}", v.elapsed_secs)),
+                ],
+            ));
+        }
+        fs::write(
+            report_path,
+            serde_json::to_vec_pretty(&report).context("serialize experiment report")?,
+        )
+        .with_context(|| format!("write experiment report: {}", report_path.display()))?;
+        self.progress.info(tr_args(
+            "pipeline.experiment_report",
+            &[("path", &report_path.display())],
+        ));
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn run_experiment_variant(
+        &mut self,
+        backend: &ResolvedBackend,
+        source_lang: &str,
+        target_lang: &str,
+        label: &str,
+        prompt_tmpl: &str,
+        repair_tmpl: &str,
+        tus: &mut [TranslationUnit],
+    ) -> anyhow::Result<()> {
+        let mut model = load_model(&self.cfg, backend)?;
+        self.translate_units_segmented_basic(
+            &mut model,
+            backend,
+            source_lang,
+            target_lang,
+            &format!("experiment_{}", label.to_ascii_lowercase()),
+            prompt_tmpl,
+            repair_tmpl,
+            tus,
+            &mut |_, _, _, _| Ok(()),
+        )
+    }
+}
+
+/// Translatable units of any supported input (DOCX paragraphs, text units, HTML slots).
+fn experiment_units(
+    input: &Path,
+    package: &PackageOptions,
+    prefix: &PlaceholderPrefix,
+    acronyms: &AcronymRules,
+) -> anyhow::Result<Vec<TranslationUnit>> {
+    let mut sources: Vec<(String, String, String, FreezeResult)> = Vec::new();
+    if let Some(kind) = TextDocKind::from_path(input) {
+        let bytes = fs::read(input).with_context(|| format!("read input: {}", input.display()))?;
+        let text = String::from_utf8(bytes)
+            .map_err(|_| anyhow::anyhow!("input is not UTF-8: {}", input.display()))?;
+        let doc = TextDocument::parse(&text, kind);
+        for unit in doc.units() {
+            let fr = doc.freeze_unit(unit, acronyms);
+            sources.push((String::new(), "text".to_string(), unit.text.clone(), fr));
+        }
+    } else if is_html_path(input) {
+        let (_, offsets, slots) = extract_html(input, prefix)?;
+        for slot in &offsets.slots {
+            let src = slots[slot.id - 1].clone();
+            let fr = freeze_text_with(&src, acronyms);
+            sources.push((slot.part_name.clone(), format!("slot#{}", slot.id), src, fr));
+        }
+    } else {
+        let text = pure_text_from_session(&DocumentSession::open_with(input, package, prefix)?)?;
+        for p in text.paragraphs {
+            let fr = freeze_text_with(&p.text, acronyms);
+            sources.push((p.part_name, p.scope_key, p.text, fr));
+        }
+    }
+
+    Ok(sources
+        .into_iter()
+        .filter(|(_, _, _, fr)| !fr.text.trim().is_empty() && !is_trivial_sentinel_text(&fr.text))
+        .enumerate()
+        .map(|(i, (part_name, scope_key, src, fr))| TranslationUnit {
+            tu_id: i + 1,
+            part_name,
+            scope_key,
+            para_style: None,
+            atoms: Vec::new(),
+            spans: Vec::new(),
+            source_surface: src,
+            frozen_surface: fr.text,
+            nt_map: fr.nt_map,
+            nt_mask: fr.mask,
+            draft_translation: None,
+            final_translation: None,
+            alt_translation: None,
+            draft_translation_model: None,
+            alt_translation_model: None,
+            qe_score: None,
+            qe_flags: Vec::new(),
+        })
+        .collect())
+}
+
+/// Deterministic sample: `n` units spread evenly over the document.
+fn sample_evenly(all: Vec<TranslationUnit>, n: usize) -> Vec<TranslationUnit> {
+    if all.len() <= n {
+        return all;
+    }
+    let len = all.len();
+    let keep: Vec<usize> = (0..n).map(|i| i * len / n).collect();
+    all.into_iter()
+        .enumerate()
+        .filter(|(i, _)| keep.binary_search(i).is_ok())
+        .map(|(_, tu)| tu)
+        .collect()
+}
+
+/// Append the hard and soft heuristic flags of each translated unit to its outcome flags.
+fn record_quality_flags(tus: &mut [TranslationUnit], source_lang: &str, target_lang: &str) {
+    for tu in tus.iter_mut() {
+        let Some(out) = tu.draft_translation.as_deref() else {
+            continue;
+        };
+        let heur = quality_heuristics(tu, out, source_lang, target_lang);
+        tu.qe_flags.extend(heur.hard_flags);
+        tu.qe_flags.extend(heur.soft_flags);
+    }
+}
+
+fn variant_stats(tus: &[TranslationUnit]) -> VariantStats {
+    let mut stats = VariantStats::default();
+    let mut ratio_sum = 0f64;
+    for tu in tus {
+        let Some(out) = tu.draft_translation.as_deref() else {
+            continue;
+        };
+        stats.translated += 1;
+        let src_chars = tu.source_surface.chars().count().max(1);
+        ratio_sum += out.chars().count() as f64 / src_chars as f64;
+
+        let mut heuristic = false;
+        for flag in &tu.qe_flags {
+            if let Some(n) = flag.strip_prefix("repairs:") {
+                stats.repaired += 1;
+                stats.repairs_total += n.parse::<usize>().unwrap_or(0);
+                continue;
+            }
+            match flag.as_str() {
+                "forced_retranslate" => stats.forced_retranslate += 1,
+                "source_fallback" => stats.source_fallback += 1,
+                _ => heuristic = true,
+            }
+            *stats.flag_counts.entry(flag.clone()).or_default() += 1;
+        }
+        if heuristic {
+            stats.flagged += 1;
+        }
+        if tu.qe_flags.is_empty() {
+            stats.clean += 1;
+        }
+    }
+    if stats.translated > 0 {
+        stats.mean_len_ratio = ratio_sum / stats.translated as f64;
+    }
+    stats
+}
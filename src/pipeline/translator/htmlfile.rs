@@ -0,0 +1,197 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::Context;
+
+use crate::errors::{ErrorKind, ResultExt};
+use crate::freezer::{freeze_text_with, unfreeze_text};
+use crate::htmldoc::{extract_html, html_blocks, merge_html};
+use crate::i18n::{tr, tr_args};
+use crate::ir::TranslationUnit;
+
+use super::{current_translation, write_file_atomic, TranslatorPipeline};
+
+impl TranslatorPipeline {
+    /// `.html` inputs: markup is masked with placeholders, the text of each block element is one
+    /// TU with slot markers where inline tags were (each text attribute is a TU of its own), and
+    /// the translated slots are merged into the mask.
+    pub(super) fn translate_html_file(
+        &mut self,
+        input: &Path,
+        output: &Path,
+    ) -> anyhow::Result<()> {
+        self.progress
+            .info(tr_args("pipeline.read_html", &[("path", &input.display())]));
+        if self.cfg.resume {
+            self.progress.info(tr("pipeline.continue_text_mode"));
+        }
+        fs::create_dir_all(self.trace.dir())
+            .with_context(|| format!("create trace dir: {}", self.trace.dir().display()))?;
+
+        let stem = output
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("output");
+        let (mask, offsets, source_slots) = extract_html(input, &self.cfg.placeholder_prefix)
+            .kind(ErrorKind::InputFile, "read input html")?;
+        let blocks = html_blocks(&mask).kind(ErrorKind::InputFile, "read input html")?;
+        let mask_json = self.trace.dir().join(format!("{stem}.html.mask.json"));
+        let offsets_json = self.trace.dir().join(format!("{stem}.offsets.json"));
+        fs::write(
+            &mask_json,
+            serde_json::to_vec_pretty(&mask).context("serialize html mask json")?,
+        )
+        .with_context(|| format!("write html mask json: {}", mask_json.display()))?;
+        fs::write(
+            &offsets_json,
+            serde_json::to_vec_pretty(&offsets).context("serialize offsets json")?,
+        )
+        .with_context(|| format!("write offsets json: {}", offsets_json.display()))?;
+
+        let mut tus: Vec<TranslationUnit> = Vec::with_capacity(blocks.len());
+        for (i, block) in blocks.iter().enumerate() {
+            let src = block.surface(&source_slots);
+            let fr = freeze_text_with(&src, &self.cfg.acronyms);
+            tus.push(TranslationUnit {
+                tu_id: i + 1,
+                part_name: offsets.slots[block.slot_ids[0] - 1].part_name.clone(),
+                scope_key: format!("block#{}", i + 1),
+                para_style: None,
+                atoms: Vec::new(),
+                spans: Vec::new(),
+                source_surface: src,
+                frozen_surface: fr.text,
+                nt_map: fr.nt_map,
+                nt_mask: fr.mask,
+                draft_translation: None,
+                final_translation: None,
+                alt_translation: None,
+                draft_translation_model: None,
+                alt_translation_model: None,
+                qe_score: None,
+                qe_flags: Vec::new(),
+            });
+        }
+        self.progress.info(tr_args(
+            "pipeline.translatable_slots",
+            &[("count", &source_slots.len())],
+        ));
+        if let Some(max_tus) = self.cfg.max_tus {
+            let keep = max_tus.max(1).min(tus.len());
+            tus.truncate(keep);
+            self.progress
+                .info(tr_args("pipeline.max_tus", &[("count", &keep)]));
+        }
+
+        let (source_lang, target_lang) =
+            match (self.cfg.source_lang.clone(), self.cfg.target_lang.clone()) {
+                (Some(s), Some(t)) => (s, t),
+                _ => {
+                    let excerpts: Vec<String> = source_slots
+                        .iter()
+                        .filter(|s| s.chars().count() >= 8)
+                        .take(20)
+                        .cloned()
+                        .collect();
+                    self.auto_lang_pair(&excerpts)
+                }
+            };
+        self.progress.info(tr_args(
+            "pipeline.language",
+            &[("src", &source_lang), ("tgt", &target_lang)],
+        ));
+        self.route_translate_backend(&source_lang, &target_lang);
+        self.open_project_memory(&source_lang, &target_lang)?;
+        let pivot = self.pivot_for(&source_lang, &target_lang);
+        let mut pivot_originals: Vec<Vec<TranslationUnit>> = Vec::new();
+        if let Some(pivot) = pivot.as_ref() {
+            pivot_originals = self.run_pivot_hop(
+                pivot,
+                &source_lang,
+                &target_lang,
+                &mut [("pivot(html)", &mut tus)],
+            )?;
+        }
+        let hop_source_lang = pivot.map_or_else(|| source_lang.clone(), |p| p.via);
+
+        self.progress.info(tr_args(
+            "pipeline.translate_backend",
+            &[("name", &self.cfg.translate_backend.name)],
+        ));
+        let (mut model, mut translate_backend) = self.load_translate_model()?;
+
+        let mut slot_texts = source_slots.clone();
+        loop {
+            let translate_prompts = self.cfg.prompts.for_backend(&translate_backend.name);
+            let prompt_translate_a = translate_prompts.translate_a.clone();
+            let prompt_translate_repair = translate_prompts.translate_repair.clone();
+            let result = self.translate_units_segmented_basic(
+                &mut model,
+                &translate_backend,
+                &hop_source_lang,
+                &target_lang,
+                "translate_a(html)",
+                &prompt_translate_a,
+                &prompt_translate_repair,
+                &mut tus,
+                &mut |tu, out_unfrozen, _processed, _total| {
+                    blocks[tu.tu_id - 1]
+                        .project(out_unfrozen, &mut slot_texts)
+                        .with_context(|| format!("tu_id={} parse slot output", tu.tu_id))
+                },
+            );
+            match result {
+                Ok(()) => break,
+                Err(err) => {
+                    self.fall_back_translate_backend(err).stage("translate_a")?;
+                    (model, translate_backend) = self.load_translate_model()?;
+                }
+            }
+        }
+        if let Some(originals) = pivot_originals.pop() {
+            for (tu_id, text) in self.finish_pivot(originals, &mut tus) {
+                blocks[tu_id - 1]
+                    .project(&text, &mut slot_texts)
+                    .with_context(|| format!("tu_id={tu_id} parse slot output"))?;
+            }
+        }
+
+        for idx in self.run_custom_stages(stem, &source_lang, &target_lang, &mut tus)? {
+            let tu = &tus[idx];
+            if let Some(t) = current_translation(tu) {
+                blocks[tu.tu_id - 1]
+                    .project(&unfreeze_text(t, &tu.nt_map), &mut slot_texts)
+                    .with_context(|| format!("tu_id={} parse slot output", tu.tu_id))?;
+            }
+        }
+        self.check_source_fallbacks(&tus).stage("translate_a")?;
+        self.run_qa_sample(stem, &source_lang, &target_lang, &mut tus)?;
+
+        let final_json = self.trace.dir().join(format!("{stem}.final.slots.json"));
+        fs::write(
+            &final_json,
+            serde_json::to_vec_pretty(&slot_texts).context("serialize final slots json")?,
+        )
+        .with_context(|| format!("write final slots json: {}", final_json.display()))?;
+
+        self.progress.info(tr_args(
+            "pipeline.write_output",
+            &[("path", &output.display())],
+        ));
+        let html = merge_html(&mask, &offsets, &slot_texts).kind(ErrorKind::Merge, "merge html")?;
+        write_file_atomic(output, html.as_bytes()).kind(ErrorKind::Merge, "write output html")?;
+
+        let pairs: Vec<(usize, String, String)> = tus
+            .iter()
+            .filter_map(|tu| {
+                let t = tu.draft_translation.as_ref()?;
+                Some((tu.tu_id, tu.source_surface.clone(), t.clone()))
+            })
+            .collect();
+        self.save_project_memory(stem, &mask.placeholder_prefix, input, &pairs)?;
+        self.write_aligned_corpus(stem, &tus, None);
+
+        self.progress.info(tr("pipeline.done"));
+        Ok(())
+    }
+}
@@ -1,13 +1,19 @@
 use std::collections::HashMap;
 
 use anyhow::Context;
+use once_cell::sync::Lazy;
+use regex::Regex;
 use serde::Deserialize;
 
 use crate::config::ResolvedBackend;
+use crate::i18n::tr_args;
 use crate::ir::TranslationUnit;
 use crate::models::native::NativeChatModel;
 
-use super::{load_model, parse_json_with_repair, render_template, ParaNotes, TranslatorPipeline};
+use super::{
+    load_controller_model, load_model, parse_json_with_repair, render_template, ParaNotes,
+    TranslatorPipeline,
+};
 
 #[derive(Clone, Debug, Deserialize)]
 struct ParaNotesChunkResponse {
@@ -34,43 +40,15 @@ impl TranslatorPipeline {
         tus: &[TranslationUnit],
         notes: &mut HashMap<usize, ParaNotes>,
     ) -> anyhow::Result<()> {
-        let mut model = load_model(&self.cfg, agent_backend)?;
+        self.emit_stage_started("para_notes");
+        let mut model = load_controller_model(&self.cfg, agent_backend)?;
         let (para_notes_tmpl, json_repair_tmpl) = {
             let prompts = self.cfg.prompts.for_backend(&agent_backend.name);
             (prompts.para_notes.clone(), prompts.json_repair.clone())
         };
 
-        let paras: Vec<&TranslationUnit> = tus
-            .iter()
-            .filter(|tu| tu.scope_key.contains("#w:p") || tu.scope_key.contains("#a:p"))
-            .collect();
-        if paras.is_empty() {
-            return Ok(());
-        }
-
-        let max_chars = agent_backend.ctx_size.saturating_sub(1400) as usize;
-        let max_items = 24usize;
-        let mut chunk: Vec<&TranslationUnit> = Vec::new();
-        let mut used = 0usize;
-
-        for tu in paras {
-            let add = tu.frozen_surface.len() + 64;
-            if !chunk.is_empty() && (used + add > max_chars || chunk.len() >= max_items) {
-                self.run_para_notes_chunk(
-                    &mut model,
-                    &para_notes_tmpl,
-                    &json_repair_tmpl,
-                    target_lang,
-                    &chunk,
-                    notes,
-                )?;
-                chunk.clear();
-                used = 0;
-            }
-            used += add;
-            chunk.push(tu);
-        }
-        if !chunk.is_empty() {
+        for chunk in paragraph_chunks(tus, agent_backend.ctx_size.saturating_sub(1400) as usize) {
+            self.cfg.cancel.check()?;
             self.run_para_notes_chunk(
                 &mut model,
                 &para_notes_tmpl,
@@ -83,6 +61,66 @@ impl TranslatorPipeline {
         Ok(())
     }
 
+    /// Notes without a controller: ask the translate backend for a terse key-terms list per
+    /// paragraph (plain lines, no JSON round-trip). Fills `terms` only.
+    pub(super) fn run_key_terms_notes(
+        &mut self,
+        backend: &ResolvedBackend,
+        target_lang: &str,
+        tus: &[TranslationUnit],
+        notes: &mut HashMap<usize, ParaNotes>,
+    ) -> anyhow::Result<()> {
+        let chunks = paragraph_chunks(tus, backend.ctx_size.saturating_sub(1000) as usize);
+        if chunks.is_empty() {
+            return Ok(());
+        }
+        self.emit_stage_started("para_notes");
+        let mut model = load_model(&self.cfg, backend)?;
+        let tmpl = self
+            .cfg
+            .prompts
+            .for_backend(&backend.name)
+            .key_terms
+            .clone();
+        for chunk in chunks {
+            self.cfg.cancel.check()?;
+            let first = chunk.first().map(|t| t.tu_id).unwrap_or(0);
+            let last = chunk.last().map(|t| t.tu_id).unwrap_or(0);
+            let prompt = render_template(
+                &tmpl,
+                &[
+                    ("target_lang", target_lang),
+                    ("tu_block", &notes_tu_block(&chunk)),
+                ],
+            );
+            let _ = self.trace.write_named_text(
+                &format!("para_notes.key_terms.{first:06}-{last:06}.prompt.txt"),
+                &prompt,
+            );
+            let max_tokens = ((chunk.len() as u32) * 48).clamp(256, 1200);
+            let raw = model.chat(
+                None,
+                &prompt,
+                max_tokens,
+                0.1,
+                0.9,
+                Some(40),
+                Some(1.05),
+                false,
+            )?;
+            let _ = self.trace.write_named_text(
+                &format!("para_notes.key_terms.{first:06}-{last:06}.output.raw.txt"),
+                &raw,
+            );
+            for (tu_id, terms) in parse_key_terms(&raw) {
+                if chunk.iter().any(|tu| tu.tu_id == tu_id) {
+                    notes.entry(tu_id).or_default().terms = terms;
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn run_para_notes_chunk(
         &mut self,
         model: &mut NativeChatModel,
@@ -95,15 +133,12 @@ impl TranslatorPipeline {
         let first = chunk.first().map(|t| t.tu_id).unwrap_or(0);
         let last = chunk.last().map(|t| t.tu_id).unwrap_or(0);
 
-        let tu_block = chunk
-            .iter()
-            .map(|tu| format!("TU#{}:\n{}\n", tu.tu_id, tu.frozen_surface))
-            .collect::<Vec<_>>()
-            .join("\n");
-
         let prompt = render_template(
             para_notes_tmpl,
-            &[("target_lang", target_lang), ("tu_block", &tu_block)],
+            &[
+                ("target_lang", target_lang),
+                ("tu_block", &notes_tu_block(chunk)),
+            ],
         );
         let _ = self.trace.write_named_text(
             &format!("para_notes.{first:06}-{last:06}.prompt.txt"),
@@ -133,8 +168,9 @@ impl TranslatorPipeline {
                     &format!("para_notes.{first:06}-{last:06}.error.txt"),
                     &format!("{err:#}"),
                 );
-                self.progress.info(format!(
-                    "[warn] para_notes parse failed (TU#{first}-{last}): {err}"
+                self.progress.info(tr_args(
+                    "pipeline.para_notes_parse_failed",
+                    &[("first", &first), ("last", &last), ("err", &err)],
                 ));
                 return Ok(());
             }
@@ -155,3 +191,56 @@ impl TranslatorPipeline {
         Ok(())
     }
 }
+
+/// Paragraph units (`#w:p` / `#a:p`) in chunks of at most 24 items and ~`max_chars` of text.
+fn paragraph_chunks(tus: &[TranslationUnit], max_chars: usize) -> Vec<Vec<&TranslationUnit>> {
+    let max_items = 24usize;
+    let mut chunks = Vec::new();
+    let mut chunk: Vec<&TranslationUnit> = Vec::new();
+    let mut used = 0usize;
+    for tu in tus
+        .iter()
+        .filter(|tu| tu.scope_key.contains("#w:p") || tu.scope_key.contains("#a:p"))
+    {
+        let add = tu.frozen_surface.len() + 64;
+        if !chunk.is_empty() && (used + add > max_chars || chunk.len() >= max_items) {
+            chunks.push(std::mem::take(&mut chunk));
+            used = 0;
+        }
+        used += add;
+        chunk.push(tu);
+    }
+    if !chunk.is_empty() {
+        chunks.push(chunk);
+    }
+    chunks
+}
+
+fn notes_tu_block(chunk: &[&TranslationUnit]) -> String {
+    chunk
+        .iter()
+        .map(|tu| format!("TU#{}:\n{}\n", tu.tu_id, tu.frozen_surface))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+static KEY_TERMS_LINE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^\s*[-*]?\s*TU\s*#\s*(\d+)\s*[:：]\s*(.*)$").expect("key terms line regex")
+});
+
+/// `TU#12: term = rendering; term = rendering` lines; anything else is ignored.
+fn parse_key_terms(raw: &str) -> Vec<(usize, Vec<String>)> {
+    raw.lines()
+        .filter_map(|line| {
+            let caps = KEY_TERMS_LINE_RE.captures(line)?;
+            let tu_id = caps[1].parse::<usize>().ok()?;
+            let terms: Vec<String> = caps[2]
+                .split([';', '；'])
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty() && !t.eq_ignore_ascii_case("none"))
+                .take(8)
+                .collect();
+            (!terms.is_empty()).then_some((tu_id, terms))
+        })
+        .collect()
+}
@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context};
+
+use crate::docx::package::DocxPackage;
+use crate::docx::unsupported::is_media_image;
+use crate::docx::xml::{parse_xml_part_with, write_xml_part, XmlAttr, XmlEvent, XmlName};
+use crate::i18n::tr_args;
+
+use super::super::hooks::shell_command;
+use super::{shell_quote, TranslatorPipeline};
+
+const DOCUMENT_PART: &str = "word/document.xml";
+
+impl TranslatorPipeline {
+    /// `pipeline.ocr_command`: runs the command on every image under `word/media/` of `input`
+    /// and returns a copy of `input` (in the trace dir) whose body ends with the recognized
+    /// text, one `[OCR: <image>]` paragraph per image followed by its text blocks; `None` when
+    /// no image yielded text. A failing image is reported and skipped.
+    pub(super) fn append_ocr_text(
+        &mut self,
+        command: &str,
+        input: &Path,
+        output: &Path,
+    ) -> anyhow::Result<Option<PathBuf>> {
+        let pkg = DocxPackage::read_streaming_with(input, &self.cfg.package)?;
+        let images: Vec<usize> = pkg
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| !e.is_dir && is_media_image(&e.name))
+            .map(|(i, _)| i)
+            .collect();
+        if images.is_empty() {
+            return Ok(None);
+        }
+        fs::create_dir_all(self.trace.dir())
+            .with_context(|| format!("create trace dir: {}", self.trace.dir().display()))?;
+        let stem = output
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("output")
+            .to_string();
+
+        let mut zip = pkg
+            .open_source()?
+            .ok_or_else(|| anyhow!("ocr: package source unavailable"))?;
+        let mut appendix: Vec<XmlEvent> = Vec::new();
+        for (n, index) in images.into_iter().enumerate() {
+            self.cfg.cancel.check()?;
+            let name = pkg.entries[index].name.clone();
+            let file_name = name.rsplit('/').next().unwrap_or(&name).to_string();
+            let ext = Path::new(&name)
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or("bin")
+                .to_lowercase();
+            let image = self.trace.dir().join(format!("{stem}.ocr{}.{ext}", n + 1));
+            let mut data = Vec::new();
+            zip.by_index(index)
+                .context("zip entry")?
+                .read_to_end(&mut data)
+                .with_context(|| format!("read image: {name}"))?;
+            fs::write(&image, &data).with_context(|| format!("write {}", image.display()))?;
+
+            let blocks = match run_ocr_command(command, &image) {
+                Ok(text) => text_blocks(&text),
+                Err(err) => {
+                    self.progress.info(tr_args(
+                        "pipeline.ocr_failed",
+                        &[("name", &name), ("err", &format!("{err:#}"))],
+                    ));
+                    continue;
+                }
+            };
+            if blocks.is_empty() {
+                continue;
+            }
+            self.progress.info(tr_args(
+                "pipeline.ocr_image",
+                &[("name", &name), ("count", &blocks.len())],
+            ));
+            push_paragraph(&mut appendix, &format!("[OCR: {file_name}]"));
+            for block in &blocks {
+                push_paragraph(&mut appendix, block);
+            }
+        }
+        drop(zip);
+        if appendix.is_empty() {
+            return Ok(None);
+        }
+
+        let ent = pkg
+            .entries
+            .iter()
+            .find(|e| e.name == DOCUMENT_PART)
+            .ok_or_else(|| anyhow!("ocr: {DOCUMENT_PART} missing"))?;
+        let (mut part, fixes) = parse_xml_part_with(&ent.name, &ent.data, self.cfg.package.lenient)
+            .with_context(|| format!("parse xml: {}", ent.name))?;
+        self.report_parse_fixes(&fixes);
+        let at = appendix_position(&part.events)
+            .ok_or_else(|| anyhow!("ocr: no <w:body> in {DOCUMENT_PART}"))?;
+        part.events.splice(at..at, appendix);
+
+        let mut replacements: HashMap<String, Vec<u8>> = HashMap::new();
+        replacements.insert(DOCUMENT_PART.to_string(), write_xml_part(&part)?);
+        let work = self.trace.dir().join(format!("{stem}.ocr.docx"));
+        pkg.write_with_replacements(&work, &replacements)
+            .with_context(|| format!("write {}", work.display()))?;
+        Ok(Some(work))
+    }
+}
+
+/// Run `command` through the shell with `{image}` replaced by the quoted image path (also in
+/// `MUGGLE_OCR_IMAGE`); its stdout is the recognized text.
+fn run_ocr_command(command: &str, image: &Path) -> anyhow::Result<String> {
+    let path = image.display().to_string();
+    let command = command.replace("{image}", &shell_quote(&path));
+    let out = shell_command(&command)
+        .env("MUGGLE_OCR_IMAGE", &path)
+        .output()
+        .context("spawn ocr command")?;
+    if !out.status.success() {
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        return Err(anyhow!(
+            "ocr command exited with {}: {}",
+            out.status,
+            stderr.trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+/// Blank-line separated blocks of OCR output; the wrapped lines of a block are joined.
+fn text_blocks(text: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut cur: Vec<&str> = Vec::new();
+    for line in text.lines().map(str::trim).chain(std::iter::once("")) {
+        if !line.is_empty() {
+            cur.push(line);
+        } else if !cur.is_empty() {
+            blocks.push(cur.join(" "));
+            cur.clear();
+        }
+    }
+    blocks.retain(|b| b.chars().any(char::is_alphanumeric));
+    blocks
+}
+
+fn push_paragraph(events: &mut Vec<XmlEvent>, text: &str) {
+    let start = |name: &str, attrs: Vec<XmlAttr>| XmlEvent::Start {
+        name: XmlName::from(name),
+        attrs,
+    };
+    let end = |name: &str| XmlEvent::End {
+        name: XmlName::from(name),
+    };
+    events.push(start("w:p", Vec::new()));
+    events.push(start("w:r", Vec::new()));
+    events.push(start(
+        "w:t",
+        vec![(XmlName::from("xml:space"), "preserve".to_string())],
+    ));
+    events.push(XmlEvent::Text {
+        text: text.to_string(),
+    });
+    events.push(end("w:t"));
+    events.push(end("w:r"));
+    events.push(end("w:p"));
+}
+
+/// Before the body-level `<w:sectPr>` (which must stay last), else before `</w:body>`.
+fn appendix_position(events: &[XmlEvent]) -> Option<usize> {
+    let mut stack: Vec<&str> = Vec::new();
+    for (i, ev) in events.iter().enumerate() {
+        match ev {
+            XmlEvent::Start { name, .. } | XmlEvent::Empty { name, .. }
+                if name == "w:sectPr" && stack.last() == Some(&"w:body") =>
+            {
+                return Some(i);
+            }
+            XmlEvent::Start { name, .. } => stack.push(name),
+            XmlEvent::End { name } if name == "w:body" => return Some(i),
+            XmlEvent::End { .. } => {
+                let _ = stack.pop();
+            }
+            _ => {}
+        }
+    }
+    None
+}
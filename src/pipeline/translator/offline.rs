@@ -0,0 +1,221 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+
+use crate::docx::decompose::{extract_mask_json_and_offsets_from, MaskOptions};
+use crate::docx::filter::{filter_docx_with_rules, DocxFilterRules};
+use crate::docx::pure_text::{pure_text_from_session, PureTextJson};
+use crate::docx::session::DocumentSession;
+use crate::errors::{ErrorKind, ResultExt};
+use crate::freezer::{freeze_text_with, unfreeze_text, AcronymRules};
+use crate::htmldoc::is_html_path;
+use crate::i18n::{tr, tr_args};
+use crate::sentinels::ANY_MT_TOKEN_RE;
+use crate::textdoc::TextDocKind;
+
+use super::super::config::{OfflineMode, DEFAULT_DOCX_FILTER_RULES_TOML};
+use super::super::docmap::{build_para_slot_units, ParaSlotUnit};
+use super::super::workdir::{
+    ARTIFACT_BLOBS, ARTIFACT_FINAL_TEXT, ARTIFACT_MASK, ARTIFACT_OFFSETS, ARTIFACT_OUTPUT,
+};
+use super::TranslatorPipeline;
+
+impl TranslatorPipeline {
+    /// `OfflineMode` runs: extract the DOCX (after the filter rules), rewrite the text of the
+    /// slots the model would translate without a model (`--copy-source`: keep it), and merge.
+    /// No trace of prompts, no aligned corpus; pseudo runs write the coverage report as usual.
+    pub(super) fn translate_docx_offline(
+        &mut self,
+        mode: OfflineMode,
+        input: &Path,
+        output: &Path,
+    ) -> anyhow::Result<()> {
+        let flag = match mode {
+            OfflineMode::Pseudo { .. } => "--pseudo",
+            OfflineMode::CopySource => "--copy-source",
+        };
+        if TextDocKind::from_path(input).is_some() || is_html_path(input) {
+            return Err(anyhow!("{flag} supports DOCX inputs only"))
+                .kind(ErrorKind::Usage, "bad arguments");
+        }
+        match mode {
+            OfflineMode::Pseudo { expansion } => self
+                .progress
+                .info(tr_args("pipeline.mode_pseudo", &[("pct", &expansion)])),
+            OfflineMode::CopySource => self.progress.info(tr("pipeline.mode_copy_source")),
+        }
+        self.progress
+            .info(tr_args("pipeline.read_docx", &[("path", &input.display())]));
+        fs::create_dir_all(self.trace.dir())
+            .with_context(|| format!("create trace dir: {}", self.trace.dir().display()))?;
+        let stem = output
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("output");
+
+        let rules = match self.cfg.docx_filter_rules.clone() {
+            Some(rules_path) => {
+                self.progress.info(tr_args(
+                    "pipeline.filter_rules",
+                    &[("path", &rules_path.display())],
+                ));
+                Some(DocxFilterRules::from_toml_path(&rules_path)?)
+            }
+            // The skeleton is always normalized: built-in rules (run merging included).
+            None if mode == OfflineMode::CopySource => Some(
+                DocxFilterRules::from_toml_str(DEFAULT_DOCX_FILTER_RULES_TOML)
+                    .context("built-in filter rules")?,
+            ),
+            None => None,
+        };
+        let mut work_docx = input.to_path_buf();
+        if let Some(rules) = rules {
+            let filtered = self.trace.dir().join(format!("{stem}.filtered.docx"));
+            let fixes = filter_docx_with_rules(input, &filtered, &rules, &self.cfg.package)?;
+            self.report_parse_fixes(&fixes);
+            work_docx = filtered;
+        }
+        let mask_json = self.trace.dir().join(format!("{stem}.mask.json"));
+        let offsets_json = self.trace.dir().join(format!("{stem}.offsets.json"));
+        let blobs_bin = self.trace.dir().join(format!("{stem}.mask.blobs.bin"));
+
+        let session =
+            DocumentSession::open_with(&work_docx, &self.cfg.package, &self.cfg.placeholder_prefix)
+                .kind(ErrorKind::InputDocx, "read input docx")?;
+        self.report_parse_fixes(session.parse_fixes());
+        let unsupported = match mode {
+            OfflineMode::Pseudo { .. } => self.warn_unsupported_content(&session),
+            OfflineMode::CopySource => Vec::new(),
+        };
+        let source_text =
+            pure_text_from_session(&session).kind(ErrorKind::InputDocx, "read input docx")?;
+        let offsets = extract_mask_json_and_offsets_from(
+            &session,
+            &mask_json,
+            &offsets_json,
+            &blobs_bin,
+            MaskOptions::default(),
+        )
+        .kind(ErrorKind::InputDocx, "decompose input docx")?;
+        self.record_workdir(
+            input,
+            &[
+                (ARTIFACT_MASK, &mask_json),
+                (ARTIFACT_OFFSETS, &offsets_json),
+                (ARTIFACT_BLOBS, &blobs_bin),
+            ],
+        )?;
+        let mut para_units = build_para_slot_units(&session, &source_text, &offsets)?;
+        self.apply_unit_filter(&mut para_units, |u| (u.tu_id, u.scope_key.as_str()));
+
+        let mut text_out = source_text.clone();
+        match mode {
+            OfflineMode::Pseudo { expansion } => {
+                for unit in &para_units {
+                    pseudo_translate_paragraph(&mut text_out, unit, expansion, &self.cfg.acronyms);
+                }
+            }
+            OfflineMode::CopySource => {}
+        }
+
+        let out_text_json = self.beside_output(&output.with_extension("text.json"));
+        fs::write(
+            &out_text_json,
+            serde_json::to_vec_pretty(&text_out).context("serialize output text json")?,
+        )
+        .with_context(|| format!("write output text json: {}", out_text_json.display()))?;
+        self.progress.info(tr_args(
+            "pipeline.write_output",
+            &[("path", &output.display())],
+        ));
+        self.merge_docx(&mask_json, &offsets_json, &out_text_json, output)
+            .kind(ErrorKind::Merge, "write output docx")?;
+        self.record_workdir(
+            input,
+            &[
+                (ARTIFACT_FINAL_TEXT, &out_text_json),
+                (ARTIFACT_OUTPUT, output),
+            ],
+        )?;
+        if mode != OfflineMode::CopySource {
+            self.report_coverage(stem, &offsets, &source_text, &text_out, &unsupported);
+        }
+        self.progress.info(tr("pipeline.done"));
+        Ok(())
+    }
+}
+
+/// Pseudo-translate the slots of one paragraph: letters get accents, `[` opens the first slot
+/// with text and `~` padding (`expansion` percent of the text) plus `]` closes the last one.
+/// `<<MT_...>>` control tokens and protected spans (numbers, URLs, ...) are kept as they are.
+fn pseudo_translate_paragraph(
+    text: &mut PureTextJson,
+    unit: &ParaSlotUnit,
+    expansion: u32,
+    acronyms: &AcronymRules,
+) {
+    let slots: Vec<usize> = unit
+        .slot_ids
+        .iter()
+        .filter(|&&id| id != 0)
+        .map(|&id| id - 1)
+        .filter(|&i| {
+            text.slot_texts
+                .get(i)
+                .is_some_and(|t| t.chars().any(char::is_alphanumeric))
+        })
+        .collect();
+    let (Some(&first), Some(&last)) = (slots.first(), slots.last()) else {
+        return;
+    };
+    let mut chars = 0usize;
+    for &i in &slots {
+        let (pseudo, n) = pseudo_text(&text.slot_texts[i], acronyms);
+        text.slot_texts[i] = pseudo;
+        chars += n;
+    }
+    let pad = "~".repeat((chars * expansion as usize).div_ceil(100));
+
+    let slot = &text.slot_texts[first];
+    let body = slot.trim_start();
+    text.slot_texts[first] = format!("{}[{body}", &slot[..slot.len() - body.len()]);
+    let slot = &text.slot_texts[last];
+    let body = slot.trim_end();
+    text.slot_texts[last] = format!("{body}{pad}]{}", &slot[body.len()..]);
+}
+
+/// `text` with accented letters, and the number of characters transformed.
+fn pseudo_text(text: &str, acronyms: &AcronymRules) -> (String, usize) {
+    let fr = freeze_text_with(text, acronyms);
+    let mut out = String::with_capacity(fr.text.len() * 2);
+    let mut chars = 0usize;
+    let mut last = 0usize;
+    let mut push_plain = |out: &mut String, plain: &str| {
+        for c in plain.chars() {
+            if !c.is_whitespace() {
+                chars += 1;
+            }
+            out.push(accented(c));
+        }
+    };
+    for m in ANY_MT_TOKEN_RE.find_iter(&fr.text) {
+        push_plain(&mut out, &fr.text[last..m.start()]);
+        out.push_str(m.as_str());
+        last = m.end();
+    }
+    push_plain(&mut out, &fr.text[last..]);
+    (unfreeze_text(&out, &fr.nt_map), chars)
+}
+
+fn accented(c: char) -> char {
+    const UPPER: &str = "ÅƁÇÐÉƑĜĤÎĴĶĻṀÑÖÞǪŔŠŢÛṼŴẊÝŽ";
+    const LOWER: &str = "åƀçðéƒĝĥîĵķļṁñöþǫŕšţûṽŵẋýž";
+    let table = match c {
+        'A'..='Z' => UPPER,
+        'a'..='z' => LOWER,
+        _ => return c,
+    };
+    let idx = (c.to_ascii_lowercase() as u8 - b'a') as usize;
+    table.chars().nth(idx).unwrap_or(c)
+}
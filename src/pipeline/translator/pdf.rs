@@ -0,0 +1,107 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context};
+
+use crate::errors::{ErrorKind, ResultExt};
+use crate::i18n::tr_args;
+
+use super::super::hooks::shell_command;
+use super::{shell_quote, TranslatorPipeline};
+
+impl TranslatorPipeline {
+    /// `--from-pdf`: convert `input` to DOCX (`[pdf] to_docx`), translate it, and convert the
+    /// result back (`[pdf] to_pdf`) when `output` ends in `.pdf`; the translated DOCX then stays
+    /// in the trace dir. Any other `output` receives the translated DOCX.
+    pub fn translate_pdf(&mut self, input: &Path, output: &Path) -> anyhow::Result<()> {
+        let docx = self.convert_pdf_input(input)?;
+        if !has_extension(output, "pdf") {
+            return self.translate_docx(&docx, output);
+        }
+        let stem = output
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("output");
+        let out_dir = self.trace.dir().join("pdf_out");
+        fs::create_dir_all(&out_dir)
+            .with_context(|| format!("create dir: {}", out_dir.display()))?;
+        let translated = out_dir.join(format!("{stem}.docx"));
+        self.translate_docx(&docx, &translated)?;
+
+        self.progress.info(tr_args(
+            "pipeline.pdf_convert_back",
+            &[("path", &output.display())],
+        ));
+        let pdf = run_converter(&self.cfg.docx_to_pdf_command, &translated, &out_dir, "pdf")
+            .kind(ErrorKind::Io, "convert output to pdf")?;
+        fs::copy(&pdf, output)
+            .with_context(|| format!("write {}", output.display()))
+            .kind(ErrorKind::Io, "write output")?;
+        Ok(())
+    }
+
+    /// Convert the PDF `input` to a DOCX in the trace dir (`[pdf] to_docx`) and return its path;
+    /// `--emit-prompts-only` uses it directly.
+    pub fn convert_pdf_input(&mut self, input: &Path) -> anyhow::Result<PathBuf> {
+        if !has_extension(input, "pdf") {
+            return Err(anyhow!(
+                "--from-pdf expects a .pdf input: {}",
+                input.display()
+            ))
+            .kind(ErrorKind::Usage, "bad arguments");
+        }
+        let in_dir = self.trace.dir().join("pdf_in");
+        fs::create_dir_all(&in_dir).with_context(|| format!("create dir: {}", in_dir.display()))?;
+        self.progress.info(tr_args(
+            "pipeline.pdf_convert",
+            &[("path", &input.display())],
+        ));
+        run_converter(&self.cfg.pdf_to_docx_command, input, &in_dir, "docx")
+            .kind(ErrorKind::InputDocx, "convert pdf input")
+    }
+}
+
+fn has_extension(path: &Path, ext: &str) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case(ext))
+}
+
+/// Run a `[pdf]` converter on `input` and return the file it must produce:
+/// `<out_dir>/<input stem>.<ext>` (the `{output}` placeholder).
+fn run_converter(
+    command: &str,
+    input: &Path,
+    out_dir: &Path,
+    ext: &str,
+) -> anyhow::Result<PathBuf> {
+    let stem = input
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("document");
+    let expected = out_dir.join(format!("{stem}.{ext}"));
+    // A leftover from an earlier run must not pass for this run's result.
+    let _ = fs::remove_file(&expected);
+    let command = command
+        .replace("{input}", &shell_quote(&input.display().to_string()))
+        .replace("{outdir}", &shell_quote(&out_dir.display().to_string()))
+        .replace("{output}", &shell_quote(&expected.display().to_string()));
+    let out = shell_command(&command)
+        .output()
+        .with_context(|| format!("spawn converter: {command}"))?;
+    if !out.status.success() {
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        return Err(anyhow!(
+            "converter exited with {}: {} (command: {command}; see [pdf] in the config)",
+            out.status,
+            stderr.trim()
+        ));
+    }
+    if !expected.is_file() {
+        return Err(anyhow!(
+            "converter did not produce {} (command: {command}; see [pdf] in the config)",
+            expected.display()
+        ));
+    }
+    Ok(expected)
+}
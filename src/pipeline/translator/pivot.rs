@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+
+use crate::config::ResolvedBackend;
+use crate::errors::ResultExt;
+use crate::freezer::{normalize_nt_tokens, refreeze_text};
+use crate::i18n::tr_args;
+use crate::ir::TranslationUnit;
+use crate::quality::validate_translation;
+
+use super::super::config::Pivot;
+use super::{load_model, TranslatorPipeline};
+
+impl TranslatorPipeline {
+    /// `[pivot]` entry for the document's language pair, if any.
+    pub(super) fn pivot_for(&self, source_lang: &str, target_lang: &str) -> Option<Pivot> {
+        self.cfg
+            .pivots
+            .iter()
+            .find(|p| p.matches(source_lang, target_lang))
+            .cloned()
+    }
+
+    /// Backend of a pivot hop without a configured one: the `[routing]` entry for the hop's
+    /// pair, else the translate backend.
+    fn pivot_hop_backend(&self, source_lang: &str, target_lang: &str) -> ResolvedBackend {
+        self.cfg
+            .routes
+            .iter()
+            .find(|r| r.matches(source_lang, target_lang))
+            .map_or_else(|| self.cfg.translate_backend.clone(), |r| r.backend.clone())
+    }
+
+    /// First hop of a pivot translation: translate the units of every `(stage, units)` set into
+    /// `pivot.via` and make that text their source, re-frozen with each unit's own NT map so the
+    /// second hop keeps the original placeholders. The second hop's backend becomes the translate
+    /// backend. Returns the original units of each set for `finish_pivot`.
+    pub(super) fn run_pivot_hop(
+        &mut self,
+        pivot: &Pivot,
+        source_lang: &str,
+        target_lang: &str,
+        sets: &mut [(&str, &mut [TranslationUnit])],
+    ) -> anyhow::Result<Vec<Vec<TranslationUnit>>> {
+        let first = pivot
+            .first
+            .clone()
+            .unwrap_or_else(|| self.pivot_hop_backend(source_lang, &pivot.via));
+        let second = pivot
+            .second
+            .clone()
+            .unwrap_or_else(|| self.pivot_hop_backend(&pivot.via, target_lang));
+        self.progress.info(tr_args(
+            "pipeline.pivot",
+            &[
+                ("src", &source_lang),
+                ("via", &pivot.via),
+                ("tgt", &target_lang),
+                ("first", &first.name),
+                ("second", &second.name),
+            ],
+        ));
+        let originals: Vec<Vec<TranslationUnit>> =
+            sets.iter().map(|(_, tus)| tus.to_vec()).collect();
+
+        let mut model = load_model(&self.cfg, &first).stage("pivot")?;
+        let prompts = self.cfg.prompts.for_backend(&first.name);
+        let prompt_tmpl = prompts.translate_a.clone();
+        let repair_tmpl = prompts.translate_repair.clone();
+        // The project glossary renders source terms in the target language, not in `via`.
+        let project = self.project.take();
+        let mut result = Ok(());
+        for (stage, tus) in sets.iter_mut() {
+            result = self.translate_units_segmented_basic(
+                &mut model,
+                &first,
+                source_lang,
+                &pivot.via,
+                stage,
+                &prompt_tmpl,
+                &repair_tmpl,
+                tus,
+                &mut |_, _, _, _| Ok(()),
+            );
+            if result.is_err() {
+                break;
+            }
+        }
+        self.project = project;
+        drop(model);
+        result.stage("pivot")?;
+
+        for (_, tus) in sets.iter_mut() {
+            for tu in tus.iter_mut() {
+                let Some(text) = tu.draft_translation.take() else {
+                    continue;
+                };
+                tu.frozen_surface = refreeze_nt(&tu.frozen_surface, &tu.nt_map, &text);
+                tu.source_surface = text;
+                tu.draft_translation_model = None;
+                tu.qe_flags.clear();
+            }
+        }
+        self.use_translate_backend(second);
+        Ok(originals)
+    }
+
+    /// After the second hop: restore the original sources of `tus` and check every translation
+    /// against the original source's token inventory (placeholders, control tokens, digits). A
+    /// translation that fails falls back to the source text, flagged `pivot_token_mismatch`.
+    /// Returns those `(tu_id, source)` fallbacks for the caller's output.
+    pub(super) fn finish_pivot(
+        &mut self,
+        originals: Vec<TranslationUnit>,
+        tus: &mut [TranslationUnit],
+    ) -> Vec<(usize, String)> {
+        let mut fallbacks: Vec<(usize, String)> = Vec::new();
+        for (tu, original) in tus.iter_mut().zip(originals) {
+            let pivot_text = std::mem::replace(&mut tu.source_surface, original.source_surface);
+            tu.frozen_surface = original.frozen_surface;
+            let Some(out) = tu.draft_translation.clone() else {
+                continue;
+            };
+            let frozen = refreeze_nt(&tu.frozen_surface, &tu.nt_map, &out);
+            let Err(err) = validate_translation(tu, &frozen) else {
+                continue;
+            };
+            let report = format!(
+                "validate_error: {err}\n\nSOURCE_FROZEN:\n{}\n\nPIVOT:\n{pivot_text}\n\nOUTPUT:\n{out}\n",
+                tu.frozen_surface
+            );
+            let _ = self.trace.write_named_text(
+                &format!("tu_{:06}.pivot.validate_fail.txt", tu.tu_id),
+                &report,
+            );
+            tu.draft_translation = Some(tu.source_surface.clone());
+            tu.qe_flags.push("pivot_token_mismatch".to_string());
+            if !tu.qe_flags.iter().any(|f| f == "source_fallback") {
+                tu.qe_flags.push("source_fallback".to_string());
+            }
+            fallbacks.push((tu.tu_id, tu.source_surface.clone()));
+        }
+        if !fallbacks.is_empty() {
+            self.progress.info(tr_args(
+                "pipeline.pivot_mismatch",
+                &[("count", &fallbacks.len())],
+            ));
+        }
+        fallbacks
+    }
+}
+
+/// `text` (a translation of `source_frozen`) with the NT tokens of `source_frozen` back in place;
+/// spans the freeze rules do not find (e.g. Markdown inline code) are matched literally.
+fn refreeze_nt(source_frozen: &str, nt_map: &HashMap<String, String>, text: &str) -> String {
+    normalize_nt_tokens(source_frozen, nt_map, &refreeze_text(text, nt_map))
+}
@@ -0,0 +1,193 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::{json, Value};
+
+use crate::i18n::tr_args;
+use crate::ir::TranslationUnit;
+use crate::sentinels::{parse_em_output, sentinel_sequence, slot_token};
+
+use super::super::docmap::SpanLayout;
+use super::TranslatorPipeline;
+
+/// Slot and span markers a paragraph surface is split at.
+static PROJECTION_MARKER_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"<<MT_(?:SLOT:\d{6}|EM:\d{2}|EM_END:\d{2})>>").expect("projection marker regex")
+});
+
+impl TranslatorPipeline {
+    /// Record a failed slot projection: flag the unit (`slot_projection_failed`, once) and write
+    /// `tu_<id>.<stage>.projection_fail.json` with both surfaces split at their markers, the
+    /// control tokens and the text each slot would have received.
+    pub(super) fn trace_projection_failure(
+        &self,
+        stage: &str,
+        slot_ids: &[usize],
+        tu: &mut TranslationUnit,
+        translated: &str,
+        err: &anyhow::Error,
+    ) {
+        if !tu.qe_flags.iter().any(|f| f == "slot_projection_failed") {
+            tu.qe_flags.push("slot_projection_failed".to_string());
+        }
+        let layout = self.span_layouts.get(&tu.tu_id);
+        let report = projection_report(layout, slot_ids, tu, translated, err);
+        let name = format!("tu_{:06}.{stage}.projection_fail.json", tu.tu_id);
+        let text = serde_json::to_string_pretty(&report).unwrap_or_default();
+        let _ = self.trace.write_named_text(&name, &text);
+    }
+
+    /// Count of units whose slot projection failed at least once, on the console.
+    pub(super) fn report_projection_failures(&self, tus: &[TranslationUnit]) {
+        let failed = tus
+            .iter()
+            .filter(|tu| tu.qe_flags.iter().any(|f| f == "slot_projection_failed"))
+            .count();
+        if failed > 0 {
+            self.progress.info(tr_args(
+                "pipeline.projection_failures",
+                &[("count", &failed), ("total", &tus.len())],
+            ));
+        }
+    }
+}
+
+fn projection_report(
+    layout: Option<&SpanLayout>,
+    slot_ids: &[usize],
+    tu: &TranslationUnit,
+    translated: &str,
+    err: &anyhow::Error,
+) -> Value {
+    let source_tokens = sentinel_sequence(&tu.frozen_surface);
+    let target_tokens = sentinel_sequence(translated);
+    let first_token_mismatch = source_tokens
+        .iter()
+        .zip(&target_tokens)
+        .position(|(a, b)| a != b)
+        .or_else(|| {
+            (source_tokens.len() != target_tokens.len())
+                .then(|| source_tokens.len().min(target_tokens.len()))
+        });
+    let assignment = match layout {
+        Some(layout) => span_assignment(layout, &tu.frozen_surface, translated),
+        None => slot_assignment(slot_ids, &tu.frozen_surface, translated),
+    };
+    json!({
+        "tu_id": tu.tu_id,
+        "error": format!("{err:#}"),
+        "projection": match layout {
+            None => "markers",
+            Some(l) if l.collapsed.is_empty() => "spans",
+            Some(_) => "paragraph",
+        },
+        "source_parts": marker_parts(&tu.frozen_surface),
+        "target_parts": marker_parts(translated),
+        "control_tokens": {
+            "source": source_tokens,
+            "target": target_tokens,
+            "first_mismatch": first_token_mismatch,
+        },
+        "assignment": assignment,
+    })
+}
+
+/// `text` split at its slot/span markers: `{"marker", "text"}` per part, the leading part
+/// without a marker.
+fn marker_parts(text: &str) -> Vec<Value> {
+    let mut parts = Vec::new();
+    let mut marker: Option<&str> = None;
+    let mut pos = 0usize;
+    for m in PROJECTION_MARKER_RE.find_iter(text) {
+        if marker.is_some() || m.start() > pos {
+            parts.push(json!({ "marker": marker, "text": &text[pos..m.start()] }));
+        }
+        marker = Some(m.as_str());
+        pos = m.end();
+    }
+    if marker.is_some() || pos < text.len() {
+        parts.push(json!({ "marker": marker, "text": &text[pos..] }));
+    }
+    parts
+}
+
+/// Text after the first occurrence of `marker`, up to the next marker.
+fn text_after(text: &str, marker: &str) -> Option<String> {
+    let start = text.find(marker)? + marker.len();
+    let end = PROJECTION_MARKER_RE
+        .find(&text[start..])
+        .map_or(text.len(), |m| start + m.start());
+    Some(text[start..end].to_string())
+}
+
+/// Marker mode: per slot (and the `000000` terminator), its source text, the target text after
+/// its marker and what is wrong with it.
+fn slot_assignment(slot_ids: &[usize], source: &str, translated: &str) -> Vec<Value> {
+    let mut last_pos: Option<usize> = None;
+    slot_ids
+        .iter()
+        .copied()
+        .chain(std::iter::once(0))
+        .map(|slot_id| {
+            let marker = slot_token(slot_id);
+            let occurrences = translated.matches(&marker).count();
+            let pos = translated.find(&marker);
+            let target = text_after(translated, &marker);
+            let status = match (occurrences, pos) {
+                (0, _) => "missing",
+                (1, Some(p)) if last_pos.is_some_and(|last| p < last) => "out_of_order",
+                (1, _)
+                    if slot_id == 0 && target.as_deref().is_some_and(|t| !t.trim().is_empty()) =>
+                {
+                    "terminator_has_content"
+                }
+                (1, _) => "ok",
+                _ => "duplicate",
+            };
+            if pos.is_some() {
+                last_pos = pos;
+            }
+            json!({
+                "slot_id": slot_id,
+                "source": text_after(source, &marker),
+                "target": target,
+                "occurrences": occurrences,
+                "status": status,
+            })
+        })
+        .collect()
+}
+
+/// Span mode: per style group, its slots, source text and the target pieces marked with its
+/// style id (or, for base-style groups, the unmarked ones).
+fn span_assignment(layout: &SpanLayout, source: &str, translated: &str) -> Vec<Value> {
+    let source_pieces = parse_em_output(source).unwrap_or_default();
+    let target_pieces = parse_em_output(translated);
+    let target_error = target_pieces.as_ref().err().map(|e| e.to_string());
+    let target_pieces = target_pieces.unwrap_or_default();
+    let pieces_of = |pieces: &[(Option<usize>, String)], style_id: Option<usize>| -> Vec<String> {
+        pieces
+            .iter()
+            .filter(|(id, text)| *id == style_id && !text.is_empty())
+            .map(|(_, text)| text.clone())
+            .collect()
+    };
+    let mut groups: Vec<Value> = layout
+        .groups
+        .iter()
+        .map(|g| {
+            json!({
+                "style_id": g.style_id,
+                "slot_ids": g.slot_ids,
+                "source": pieces_of(&source_pieces, g.style_id),
+                "target": pieces_of(&target_pieces, g.style_id),
+            })
+        })
+        .collect();
+    if let Some(error) = target_error {
+        groups.push(json!({ "target_error": error }));
+    }
+    if !layout.collapsed.is_empty() {
+        groups.push(json!({ "collapsed": layout.collapsed }));
+    }
+    groups
+}
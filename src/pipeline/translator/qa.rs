@@ -0,0 +1,270 @@
+use anyhow::Context;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::errors::ResultExt;
+use crate::freezer::unfreeze_text;
+use crate::i18n::tr_args;
+use crate::ir::TranslationUnit;
+use crate::models::native::NativeChatModel;
+use crate::quality::text_similarity;
+use crate::sentinels::{parse_segmented_output, seg_end, seg_start};
+use crate::textutil::{is_trivial_sentinel_text, lang_label, strip_sentinels};
+
+use super::super::prompts::render_template;
+use super::{cleanup_model_text, current_translation, load_model, TranslatorPipeline};
+
+/// Sampling temperatures of the alternative translations of a QA-sampled unit.
+const QA_NBEST_TEMPERATURES: [f32; 3] = [0.5, 0.8, 1.0];
+
+/// Below this similarity the alternatives (or the backtranslation) disagree with the kept
+/// translation (or the source) and the unit is flagged.
+const QA_DIVERGENCE_THRESHOLD: f64 = 0.3;
+
+#[derive(Serialize)]
+struct QaSampleReport {
+    seed: u32,
+    percent: f64,
+    backend: String,
+    source_lang: String,
+    target_lang: String,
+    total: usize,
+    sampled: usize,
+    flagged: usize,
+    units: Vec<QaUnit>,
+}
+
+#[derive(Serialize)]
+struct QaUnit {
+    tu_id: usize,
+    source: String,
+    translation: String,
+    n_best: Vec<String>,
+    /// Mean similarity of the alternatives to the kept translation.
+    n_best_agreement: Option<f64>,
+    backtranslation: Option<String>,
+    /// Similarity of the backtranslation to the source.
+    backtranslation_similarity: Option<f64>,
+    flags: Vec<String>,
+    errors: Vec<String>,
+}
+
+impl TranslatorPipeline {
+    /// `--qa-sample`: re-check a seeded random sample of the translated `tus` with the translate
+    /// backend — alternative translations at higher temperatures and a backtranslation — and
+    /// write `<stem>.qa-sample.json` to the trace dir. Units whose alternatives or backtranslation
+    /// diverge get `qa_nbest_divergent` / `qa_backtranslation_divergent`.
+    pub(super) fn run_qa_sample(
+        &mut self,
+        stem: &str,
+        source_lang: &str,
+        target_lang: &str,
+        tus: &mut [TranslationUnit],
+    ) -> anyhow::Result<()> {
+        let Some(percent) = self.cfg.qa_sample else {
+            return Ok(());
+        };
+        let candidates: Vec<usize> = (0..tus.len())
+            .filter(|&i| current_translation(&tus[i]).is_some())
+            .filter(|&i| !is_trivial_sentinel_text(&tus[i].frozen_surface))
+            .collect();
+        let picked = sample_indices(&candidates, tus, percent, self.cfg.seed);
+        if picked.is_empty() {
+            return Ok(());
+        }
+
+        let backend = self.cfg.translate_backend.clone();
+        self.progress.info(tr_args(
+            "pipeline.qa_sample",
+            &[
+                ("count", &picked.len()),
+                ("total", &candidates.len()),
+                ("backend", &backend.name),
+            ],
+        ));
+        self.emit_stage_started("qa_sample");
+        let mut model = load_model(&self.cfg, &backend).stage("qa_sample")?;
+        let tmpl = self
+            .cfg
+            .prompts
+            .for_backend(&backend.name)
+            .translate_a
+            .clone();
+
+        let mut units = Vec::with_capacity(picked.len());
+        for idx in picked {
+            self.cfg.cancel.check()?;
+            let tu = &mut tus[idx];
+            let translation = current_translation(tu).unwrap_or_default().to_string();
+            let kept = unfreeze_text(&translation, &tu.nt_map);
+            let mut unit = QaUnit {
+                tu_id: tu.tu_id,
+                source: tu.source_surface.clone(),
+                translation: kept.clone(),
+                n_best: Vec::new(),
+                n_best_agreement: None,
+                backtranslation: None,
+                backtranslation_similarity: None,
+                flags: Vec::new(),
+                errors: Vec::new(),
+            };
+
+            for temperature in QA_NBEST_TEMPERATURES {
+                match qa_translate(
+                    &mut model,
+                    &tmpl,
+                    source_lang,
+                    target_lang,
+                    tu.tu_id,
+                    &tu.frozen_surface,
+                    temperature,
+                ) {
+                    Ok(alt) => unit.n_best.push(unfreeze_text(&alt, &tu.nt_map)),
+                    Err(err) => unit.errors.push(format!("n_best@{temperature}: {err:#}")),
+                }
+            }
+            if !unit.n_best.is_empty() {
+                let kept_plain = strip_sentinels(&kept);
+                let sum: f64 = unit
+                    .n_best
+                    .iter()
+                    .map(|alt| text_similarity(&kept_plain, &strip_sentinels(alt)))
+                    .sum();
+                unit.n_best_agreement = Some(sum / unit.n_best.len() as f64);
+            }
+
+            match qa_translate(
+                &mut model,
+                &tmpl,
+                target_lang,
+                source_lang,
+                tu.tu_id,
+                &translation,
+                0.0,
+            ) {
+                Ok(back) => {
+                    let back = unfreeze_text(&back, &tu.nt_map);
+                    unit.backtranslation_similarity = Some(text_similarity(
+                        &strip_sentinels(&tu.source_surface),
+                        &strip_sentinels(&back),
+                    ));
+                    unit.backtranslation = Some(back);
+                }
+                Err(err) => unit.errors.push(format!("backtranslation: {err:#}")),
+            }
+
+            if unit
+                .n_best_agreement
+                .is_some_and(|s| s < QA_DIVERGENCE_THRESHOLD)
+            {
+                unit.flags.push("qa_nbest_divergent".to_string());
+            }
+            if unit
+                .backtranslation_similarity
+                .is_some_and(|s| s < QA_DIVERGENCE_THRESHOLD)
+            {
+                unit.flags.push("qa_backtranslation_divergent".to_string());
+            }
+            tu.qe_flags.extend(unit.flags.iter().cloned());
+            units.push(unit);
+        }
+
+        let flagged = units.iter().filter(|u| !u.flags.is_empty()).count();
+        let report = QaSampleReport {
+            seed: self.cfg.seed,
+            percent,
+            backend: backend.name.clone(),
+            source_lang: source_lang.to_string(),
+            target_lang: target_lang.to_string(),
+            total: candidates.len(),
+            sampled: units.len(),
+            flagged,
+            units,
+        };
+        let path = self.trace.dir().join(format!("{stem}.qa-sample.json"));
+        std::fs::write(
+            &path,
+            serde_json::to_vec_pretty(&report).context("serialize qa sample report")?,
+        )
+        .with_context(|| format!("write qa sample report: {}", path.display()))?;
+        self.progress.info(tr_args(
+            "pipeline.qa_sample_report",
+            &[
+                ("flagged", &flagged),
+                ("count", &report.sampled),
+                ("path", &path.display()),
+            ],
+        ));
+        Ok(())
+    }
+}
+
+/// `percent` of `candidates` (at least one), ranked by a hash of the seed and tu_id so the same
+/// seed picks the same units. Returned in document order.
+fn sample_indices(
+    candidates: &[usize],
+    tus: &[TranslationUnit],
+    percent: f64,
+    seed: u32,
+) -> Vec<usize> {
+    if candidates.is_empty() {
+        return Vec::new();
+    }
+    let count =
+        ((candidates.len() as f64 * percent / 100.0).ceil() as usize).clamp(1, candidates.len());
+    let mut ranked: Vec<(u64, usize)> = candidates
+        .iter()
+        .map(|&i| {
+            let mut hasher = Sha256::new();
+            hasher.update(seed.to_le_bytes());
+            hasher.update((tus[i].tu_id as u64).to_le_bytes());
+            let digest = hasher.finalize();
+            let mut rank = [0u8; 8];
+            rank.copy_from_slice(&digest[..8]);
+            (u64::from_le_bytes(rank), i)
+        })
+        .collect();
+    ranked.sort_unstable();
+    let mut picked: Vec<usize> = ranked.into_iter().take(count).map(|(_, i)| i).collect();
+    picked.sort_unstable();
+    picked
+}
+
+/// One unit through the translate prompt (no glossary or chunk context); returns the frozen
+/// translation.
+fn qa_translate(
+    model: &mut NativeChatModel,
+    tmpl: &str,
+    source_lang: &str,
+    target_lang: &str,
+    tu_id: usize,
+    frozen: &str,
+    temperature: f32,
+) -> anyhow::Result<String> {
+    let tu_block = format!("{}\n{frozen}\n{}\n\n", seg_start(tu_id), seg_end(tu_id));
+    let prompt = render_template(
+        tmpl,
+        &[
+            ("source_lang", &lang_label(source_lang)),
+            ("target_lang", &lang_label(target_lang)),
+            ("tu_block", &tu_block),
+            ("glossary", ""),
+            ("context", ""),
+        ],
+    );
+    let max_tokens = ((frozen.len() as u32) * 2).clamp(256, 4096);
+    let raw = model.chat(
+        None,
+        &prompt,
+        max_tokens,
+        temperature,
+        0.9,
+        Some(40),
+        Some(1.05),
+        false,
+    )?;
+    let mut segs = parse_segmented_output(&cleanup_model_text(&raw), &[tu_id])?;
+    segs.remove(&tu_id)
+        .map(|s| s.trim().to_string())
+        .context("missing segment")
+}
@@ -0,0 +1,145 @@
+use std::io::{self, BufRead, Write};
+
+use anyhow::Context;
+
+use crate::i18n::{tr, tr_args};
+use crate::ir::TranslationUnit;
+use crate::sentinels::control_tokens_from_text;
+
+use super::TranslatorPipeline;
+
+/// One reviewer command, read from a line of stdin.
+enum ReviewCommand {
+    Accept,
+    Quit,
+    Edit(usize),
+    Retranslate(Option<usize>),
+    Help,
+}
+
+fn parse_review_command(line: &str) -> ReviewCommand {
+    let mut parts = line.split_whitespace();
+    let cmd = parts.next().unwrap_or("");
+    let n = parts.next().and_then(|s| s.parse::<usize>().ok());
+    match (cmd, n) {
+        ("" | "a", _) => ReviewCommand::Accept,
+        ("q", _) => ReviewCommand::Quit,
+        ("e", Some(n)) => ReviewCommand::Edit(n),
+        ("r", n) => ReviewCommand::Retranslate(n),
+        _ => ReviewCommand::Help,
+    }
+}
+
+fn read_review_line(prompt: &str) -> anyhow::Result<Option<String>> {
+    let mut stderr = io::stderr().lock();
+    let _ = write!(stderr, "{prompt}");
+    let _ = stderr.flush();
+    let mut line = String::new();
+    let n = io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .context("read review input")?;
+    if n == 0 {
+        return Ok(None);
+    }
+    Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()))
+}
+
+impl TranslatorPipeline {
+    /// `--interactive`: show the validated pairs of a chunk and let the user accept them, edit a
+    /// translation inline, or send units back for retranslation. Edits must keep the source's
+    /// control tokens. Returns the `tus` indices to retranslate; `outs` holds the (possibly
+    /// edited) translations to commit. `q` or end of input accepts and ends the review for the
+    /// rest of the run.
+    pub(super) fn review_chunk(
+        &mut self,
+        stage: &str,
+        tus: &mut [TranslationUnit],
+        outs: &mut [(usize, String)],
+    ) -> anyhow::Result<Vec<usize>> {
+        let show = |n: usize, tu: &TranslationUnit, out: &str| {
+            eprintln!("  [{n}] SRC: {}", tu.source_surface);
+            eprintln!(
+                "  {:width$} TGT: {out}",
+                "",
+                width = n.to_string().len() + 2
+            );
+        };
+        eprintln!();
+        eprintln!(
+            "{}",
+            tr_args(
+                "pipeline.review_header",
+                &[("stage", &stage), ("count", &outs.len())],
+            )
+        );
+        for (n, (idx, out)) in outs.iter().enumerate() {
+            show(n + 1, &tus[*idx], out);
+        }
+        eprintln!("{}", tr("pipeline.review_help"));
+
+        let mut retranslate: Vec<usize> = Vec::new();
+        loop {
+            let Some(line) = read_review_line("> ")? else {
+                self.stop_review();
+                break;
+            };
+            match parse_review_command(&line) {
+                ReviewCommand::Accept => break,
+                ReviewCommand::Quit => {
+                    self.stop_review();
+                    break;
+                }
+                ReviewCommand::Help => eprintln!("{}", tr("pipeline.review_help")),
+                ReviewCommand::Retranslate(None) => {
+                    retranslate = outs.iter().map(|(idx, _)| *idx).collect();
+                    break;
+                }
+                ReviewCommand::Retranslate(Some(n)) | ReviewCommand::Edit(n)
+                    if n == 0 || n > outs.len() =>
+                {
+                    eprintln!("{}", tr_args("pipeline.review_bad_index", &[("n", &n)]));
+                }
+                ReviewCommand::Retranslate(Some(n)) => {
+                    let idx = outs[n - 1].0;
+                    if !retranslate.contains(&idx) {
+                        retranslate.push(idx);
+                    }
+                }
+                ReviewCommand::Edit(n) => {
+                    let prompt = tr_args("pipeline.review_edit", &[("n", &n)]);
+                    let Some(edit) = read_review_line(&prompt)? else {
+                        self.stop_review();
+                        break;
+                    };
+                    let (idx, out) = &mut outs[n - 1];
+                    let tu = &mut tus[*idx];
+                    let expected = control_tokens_from_text(&tu.source_surface);
+                    if edit.trim().is_empty() || control_tokens_from_text(&edit) != expected {
+                        eprintln!(
+                            "{}",
+                            tr_args(
+                                "pipeline.review_bad_edit",
+                                &[("tokens", &expected.join(" "))],
+                            )
+                        );
+                        continue;
+                    }
+                    *out = edit.clone();
+                    tu.draft_translation = Some(edit);
+                    if !tu.qe_flags.iter().any(|f| f == "human_edit") {
+                        tu.qe_flags.push("human_edit".to_string());
+                    }
+                    retranslate.retain(|&i| i != *idx);
+                    show(n, tu, out);
+                }
+            }
+        }
+        Ok(retranslate)
+    }
+
+    fn stop_review(&mut self) {
+        self.cfg.interactive = false;
+        self.progress.info(tr("pipeline.review_stopped"));
+    }
+}
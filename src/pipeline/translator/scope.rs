@@ -0,0 +1,56 @@
+use std::path::Path;
+
+use crate::docx::pure_text::PureTextJson;
+use crate::i18n::{tr, tr_args};
+
+use super::basic::load_resume_text;
+use super::TranslatorPipeline;
+
+impl TranslatorPipeline {
+    /// `--range` / `--scope` / `--from-report`: drop the units outside the filter. `id_and_scope`
+    /// gives a unit's paragraph id and scope key.
+    pub(super) fn apply_unit_filter<T>(
+        &self,
+        units: &mut Vec<T>,
+        id_and_scope: impl Fn(&T) -> (usize, &str),
+    ) {
+        let Some(filter) = self.cfg.unit_filter.as_ref() else {
+            return;
+        };
+        let total = units.len();
+        units.retain(|u| {
+            let (id, scope_key) = id_and_scope(u);
+            filter.keeps(id, scope_key)
+        });
+        self.progress.info(tr_args(
+            "pipeline.unit_filter",
+            &[("count", &units.len()), ("total", &total)],
+        ));
+    }
+
+    /// `--range` / `--scope` / `--from-report`: the text the selected units are translated onto
+    /// — the slot texts of the first `candidates` file written for this document by a previous
+    /// run, else the source.
+    pub(super) fn unit_filter_base(
+        &self,
+        candidates: &[&Path],
+        source_text: &PureTextJson,
+    ) -> anyhow::Result<PureTextJson> {
+        let mut base = source_text.clone();
+        if self.cfg.unit_filter.is_none() {
+            return Ok(base);
+        }
+        match load_resume_text(candidates, source_text)? {
+            Some((path, previous)) => {
+                base.slot_texts = previous.slot_texts;
+                base.collapsed_slots = previous.collapsed_slots;
+                self.progress.info(tr_args(
+                    "pipeline.unit_filter_base",
+                    &[("path", &path.display())],
+                ));
+            }
+            None => self.progress.info(tr("pipeline.unit_filter_no_base")),
+        }
+        Ok(base)
+    }
+}
@@ -11,9 +11,10 @@ use crate::quality::{quality_heuristics, validate_translation};
 use crate::sentinels::{parse_segmented_output, seg_end, seg_start};
 use crate::textutil::lang_label;
 
+use super::super::events::PipelineEvent;
 use super::{
-    cleanup_model_text, render_template, set_translation_slot, ParaNotes, TranslationSlot,
-    TranslatorPipeline,
+    chat_with_retries, cleanup_model_text, is_context_overflow, render_template,
+    set_translation_slot, ParaNotes, TranslationSlot, TranslatorPipeline,
 };
 
 impl TranslatorPipeline {
@@ -40,6 +41,7 @@ impl TranslatorPipeline {
         if indices.is_empty() {
             return Ok(());
         }
+        self.cfg.cancel.check()?;
 
         let first = tus[indices[0]].tu_id;
         let last = tus[*indices.last().unwrap_or(&indices[0])].tu_id;
@@ -57,15 +59,16 @@ impl TranslatorPipeline {
             tu_block.push_str("\n\n");
         }
 
-        let source_lang_label = lang_label(source_lang);
-        let target_lang_label = lang_label(target_lang);
-        let prompt = render_template(
+        let sources: Vec<&str> = indices
+            .iter()
+            .map(|&i| tus[i].source_surface.as_str())
+            .collect();
+        let prompt = self.render_translate_prompt(
             prompt_tmpl,
-            &[
-                ("source_lang", &source_lang_label),
-                ("target_lang", &target_lang_label),
-                ("tu_block", &tu_block),
-            ],
+            source_lang,
+            target_lang,
+            &tu_block,
+            &sources,
         );
         let _ = self.trace.write_named_text(
             &format!(
@@ -76,16 +79,28 @@ impl TranslatorPipeline {
         );
 
         let max_tokens = backend.ctx_size.saturating_sub(256).max(512);
-        let raw = model.chat(
-            None,
-            &prompt,
-            max_tokens,
-            0.12,
-            0.9,
-            Some(40),
-            Some(1.05),
-            false,
-        )?;
+        let mut overflowed = false;
+        let raw = match chat_with_retries(|| {
+            model.chat(
+                None,
+                &prompt,
+                max_tokens,
+                0.12,
+                0.9,
+                Some(40),
+                Some(1.05),
+                false,
+            )
+        }) {
+            Ok(raw) => raw,
+            // Chunk sizing underestimated the tokens: no output, so the chunk is split below.
+            Err(err) if indices.len() > 1 && is_context_overflow(&err) => {
+                self.note_context_overflow(backend, slot.stage_name(), first, last, &err);
+                overflowed = true;
+                String::new()
+            }
+            Err(err) => return Err(err),
+        };
         let cleaned = cleanup_model_text(&raw);
         let _ = self.trace.write_named_text(
             &format!(
@@ -95,7 +110,11 @@ impl TranslatorPipeline {
             &cleaned,
         );
 
-        let segs = match parse_segmented_output(&cleaned, &expected_ids) {
+        let parsed = parse_segmented_output(&cleaned, &expected_ids);
+        if !overflowed {
+            self.record_chunk_outcome(backend, indices.len(), parsed.is_ok());
+        }
+        let segs = match parsed {
             Ok(v) => v,
             Err(err) => {
                 if indices.len() > 1 {
@@ -221,8 +240,6 @@ impl TranslatorPipeline {
     ) -> anyhow::Result<()> {
         let tu_id = tus[idx].tu_id;
         let source = tus[idx].frozen_surface.clone();
-        let must_keep_tokens = crate::sentinels::must_keep_tokens(&source);
-        let nt_map = crate::freezer::render_nt_map_for_prompt(&tus[idx].nt_map);
         let mut validation_error = validate_translation(&tus[idx], &out)
             .err()
             .map(|e| e.to_string())
@@ -234,54 +251,79 @@ impl TranslatorPipeline {
             if validation_error.is_empty() {
                 validation_error = "quality_force_retranslate".to_string();
             }
-            let repaired = self.repair_translation(
-                model,
-                repair_tmpl,
-                source_lang,
-                target_lang,
-                &source,
-                &out,
-                &must_keep_tokens,
-                &validation_error,
-                &nt_map,
-            )?;
-            out = repaired;
+            if self.has_cached_repair(&tus[idx], &validation_error) || self.take_repair_budget() {
+                out = self.repair_translation(
+                    model,
+                    repair_tmpl,
+                    source_lang,
+                    target_lang,
+                    &tus[idx],
+                    &out,
+                    &validation_error,
+                )?;
+            } else {
+                tus[idx]
+                    .qe_flags
+                    .push("repair_budget_exhausted".to_string());
+            }
         }
+        let mut source_fallback = false;
         if validate_translation(&tus[idx], &out).is_err() {
             out = source.clone();
+            source_fallback = true;
         }
 
         let slots = slots_by_tu.get(&tu_id).cloned().unwrap_or_default();
         if !slots.is_empty() {
-            if self
-                .apply_slot_translation(text_variant, &slots, &tus[idx], &out)
-                .is_err()
-            {
-                let reason = "slot_projection_failed".to_string();
-                let repaired = self.repair_translation(
-                    model,
-                    repair_tmpl,
-                    source_lang,
-                    target_lang,
-                    &source,
-                    &out,
-                    &must_keep_tokens,
-                    &reason,
-                    &nt_map,
-                )?;
-                out = repaired;
-                if validate_translation(&tus[idx], &out).is_err()
-                    || self
-                        .apply_slot_translation(text_variant, &slots, &tus[idx], &out)
-                        .is_err()
-                {
+            if let Err(err) = self.apply_slot_translation(text_variant, &slots, &tus[idx], &out) {
+                self.trace_projection_failure(slot.stage_name(), &slots, &mut tus[idx], &out, &err);
+                let reason = format!("slot_projection_failed: {err:#}");
+                if self.has_cached_repair(&tus[idx], &reason) || self.take_repair_budget() {
+                    out = self.repair_translation(
+                        model,
+                        repair_tmpl,
+                        source_lang,
+                        target_lang,
+                        &tus[idx],
+                        &out,
+                        &reason,
+                    )?;
+                } else {
+                    tus[idx]
+                        .qe_flags
+                        .push("repair_budget_exhausted".to_string());
+                }
+                let applied = validate_translation(&tus[idx], &out).is_ok()
+                    && match self.apply_slot_translation(text_variant, &slots, &tus[idx], &out) {
+                        Ok(()) => true,
+                        Err(err) => {
+                            let stage = format!("{}.repaired", slot.stage_name());
+                            self.trace_projection_failure(
+                                &stage,
+                                &slots,
+                                &mut tus[idx],
+                                &out,
+                                &err,
+                            );
+                            false
+                        }
+                    };
+                if !applied {
                     out = source.clone();
+                    source_fallback = true;
                     let _ = self.apply_slot_translation(text_variant, &slots, &tus[idx], &out);
                 }
             }
         }
 
+        let out_unfrozen = crate::freezer::unfreeze_text(&out, &tus[idx].nt_map);
+        self.remember_chunk_context(&tus[idx].source_surface, &out_unfrozen);
         set_translation_slot(&mut tus[idx], slot, out.clone(), &backend.name);
+        self.emit(PipelineEvent::TuValidated {
+            tu_id,
+            source_fallback,
+            qe_flags: tus[idx].qe_flags.clone(),
+        });
 
         *processed += 1;
         if *processed % self.cfg.autosave_every == 0 {
@@ -313,6 +355,7 @@ impl TranslatorPipeline {
         if indices.is_empty() {
             return Ok(());
         }
+        self.cfg.cancel.check()?;
 
         let first = tus[indices[0]].tu_id;
         let last = tus[*indices.last().unwrap_or(&indices[0])].tu_id;
@@ -328,9 +371,8 @@ impl TranslatorPipeline {
             let b = tu.alt_translation.as_deref().unwrap_or(a);
             let note = notes
                 .get(&tu.tu_id)
-                .and_then(|n| n.understanding.as_ref())
-                .map(|s| s.as_str())
-                .unwrap_or("");
+                .and_then(|n| n.prompt_note())
+                .unwrap_or_default();
 
             expected_ids.push(tu.tu_id);
             tu_block.push_str(&seg_start(tu.tu_id));
@@ -343,7 +385,7 @@ impl TranslatorPipeline {
             tu_block.push_str(b);
             if !note.trim().is_empty() {
                 tu_block.push_str("\n\nNOTE:\n");
-                tu_block.push_str(note.trim());
+                tu_block.push_str(&note);
             }
             tu_block.push('\n');
             tu_block.push_str(&seg_end(tu.tu_id));
@@ -440,8 +482,6 @@ impl TranslatorPipeline {
         mut out: String,
     ) -> anyhow::Result<()> {
         let source = tus[idx].frozen_surface.clone();
-        let must_keep_tokens = crate::sentinels::must_keep_tokens(&source);
-        let nt_map = crate::freezer::render_nt_map_for_prompt(&tus[idx].nt_map);
         let mut validation_error = validate_translation(&tus[idx], &out)
             .err()
             .map(|e| e.to_string())
@@ -458,18 +498,21 @@ impl TranslatorPipeline {
             if validation_error.is_empty() {
                 validation_error = "quality_force_retranslate".to_string();
             }
-            let repaired = self.repair_translation(
-                model,
-                repair_tmpl,
-                source_lang,
-                target_lang,
-                &source,
-                &out,
-                &must_keep_tokens,
-                &validation_error,
-                &nt_map,
-            )?;
-            out = repaired;
+            if self.has_cached_repair(&tus[idx], &validation_error) || self.take_repair_budget() {
+                out = self.repair_translation(
+                    model,
+                    repair_tmpl,
+                    source_lang,
+                    target_lang,
+                    &tus[idx],
+                    &out,
+                    &validation_error,
+                )?;
+            } else {
+                tus[idx]
+                    .qe_flags
+                    .push("repair_budget_exhausted".to_string());
+            }
         }
         if validate_translation(&tus[idx], &out).is_err() {
             out = a;
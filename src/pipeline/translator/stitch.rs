@@ -1,19 +1,30 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
+use std::sync::mpsc;
 
-use anyhow::Context;
+use anyhow::{anyhow, Context};
 use serde::Deserialize;
 
 use crate::config::ResolvedBackend;
-use crate::docx::pure_text::PureTextJson;
+use crate::docx::comments::read_comments;
+use crate::docx::pure_text::{pure_text_from_session, PureTextJson};
+use crate::docx::session::DocumentSession;
+use crate::errors::{ErrorKind, ResultExt};
+use crate::i18n::tr_args;
 use crate::ir::TranslationUnit;
+use crate::models::native::NativeChatModel;
 use crate::quality::validate_translation;
 
+use super::super::config::UnitFilter;
+use super::super::worklist::{review_worklist, write_review_worklist, ReviewItem};
 use super::{
-    cleanup_model_text, load_model, parse_json_with_repair, render_template, ParaNotes,
-    TranslatorPipeline,
+    cleanup_model_text, load_controller_model, load_model, parse_json_with_repair, render_template,
+    ParaNotes, TranslatorPipeline,
 };
 
+/// Quality flag: the stitch audit still reports an issue after the last patch round.
+const STITCH_UNRESOLVED: &str = "stitch_unresolved";
+
 #[derive(Clone, Debug, Deserialize)]
 struct StitchAuditResponse {
     #[serde(default)]
@@ -29,12 +40,113 @@ struct StitchIssue {
     rewrite_instructions: String,
 }
 
+/// Patch priority of an audit issue, from the words of its description.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Severity {
+    High,
+    Medium,
+    Low,
+}
+
+/// Meaning errors: mistranslations, omissions, wrong numbers, terminology breaks.
+const HIGH_SEVERITY_WORDS: &[&str] = &[
+    "mistranslat",
+    "meaning",
+    "omit",
+    "omission",
+    "missing",
+    "untranslated",
+    "wrong",
+    "incorrect",
+    "contradict",
+    "inconsisten",
+    "terminolog",
+    "number",
+    "negation",
+    "误译",
+    "漏译",
+    "遗漏",
+    "错误",
+    "不一致",
+    "矛盾",
+    "未翻译",
+    "术语",
+];
+
+/// Surface trivia: punctuation, spacing, casing, slight wording preferences.
+const LOW_SEVERITY_WORDS: &[&str] = &[
+    "punctuation",
+    "spacing",
+    "whitespace",
+    "capitaliz",
+    "typo",
+    "minor",
+    "slight",
+    "stylistic",
+    "wording",
+    "标点",
+    "空格",
+    "大小写",
+    "措辞",
+    "轻微",
+    "风格",
+];
+
+impl StitchIssue {
+    fn severity(&self) -> Severity {
+        let text = format!("{} {}", self.problem, self.rewrite_instructions).to_lowercase();
+        if HIGH_SEVERITY_WORDS.iter().any(|w| text.contains(w)) {
+            Severity::High
+        } else if LOW_SEVERITY_WORDS.iter().any(|w| text.contains(w)) {
+            Severity::Low
+        } else {
+            Severity::Medium
+        }
+    }
+}
+
+/// One issue per unit (chunks overlap in what they report): problems and instructions of the
+/// duplicates are joined. Issues with neither are dropped.
+fn merge_issues(issues: Vec<StitchIssue>) -> Vec<StitchIssue> {
+    let mut merged: Vec<StitchIssue> = Vec::new();
+    let mut by_tu: HashMap<usize, usize> = HashMap::new();
+    for issue in issues {
+        if issue.problem.trim().is_empty() && issue.rewrite_instructions.trim().is_empty() {
+            continue;
+        }
+        let Some(&i) = by_tu.get(&issue.tu_id) else {
+            by_tu.insert(issue.tu_id, merged.len());
+            merged.push(issue);
+            continue;
+        };
+        let target = &mut merged[i];
+        for (into, add, sep) in [
+            (&mut target.problem, issue.problem, "; "),
+            (
+                &mut target.rewrite_instructions,
+                issue.rewrite_instructions,
+                "\n",
+            ),
+        ] {
+            let add = add.trim();
+            if add.is_empty() || into.contains(add) {
+                continue;
+            }
+            if !into.is_empty() {
+                into.push_str(sep);
+            }
+            into.push_str(add);
+        }
+    }
+    merged
+}
+
 impl TranslatorPipeline {
     #[allow(clippy::too_many_arguments)]
     pub(super) fn run_stitch_audit_and_patch(
         &mut self,
         agent_backend: &ResolvedBackend,
-        patch_backend: &ResolvedBackend,
+        patch_backend: Option<&ResolvedBackend>,
         source_lang: &str,
         target_lang: &str,
         tus: &mut [TranslationUnit],
@@ -45,17 +157,75 @@ impl TranslatorPipeline {
         offsets_json: &Path,
         autosave_text_json: &Path,
         output: &Path,
-    ) -> anyhow::Result<()> {
-        for round in 1..=2 {
-            self.progress.info(format!("Stitch audit round {round}/2"));
-            let issues = self.run_stitch_audit_round(agent_backend, target_lang, tus, round)?;
+    ) -> anyhow::Result<HashMap<usize, Vec<String>>> {
+        let rounds = self.cfg.stitch_rounds;
+        // Issues of the last audit that no patch resolved.
+        let mut unresolved: Vec<StitchIssue> = Vec::new();
+        // Patch calls so far (`pipeline.patch_budget`).
+        let mut spent = 0usize;
+        for round in 1..=rounds {
+            self.progress.info(tr_args(
+                "pipeline.stitch_round",
+                &[("round", &round), ("rounds", &rounds)],
+            ));
+            let mut issues = self.run_stitch_audit_round(agent_backend, target_lang, tus, round)?;
+            if patch_backend.is_some() && self.cfg.stage_flagged_only("patch") {
+                let total = issues.len();
+                issues.retain(|issue| {
+                    tus.iter()
+                        .any(|tu| tu.tu_id == issue.tu_id && self.in_stage_scope("patch", tu))
+                });
+                self.report_stage_scope("patch", issues.len(), total);
+            }
+            issues.retain(|issue| tus.iter().any(|tu| tu.tu_id == issue.tu_id));
+            // Most severe first; the budget cuts from the end.
+            issues.sort_by_key(StitchIssue::severity);
             if issues.is_empty() {
+                unresolved.clear();
+                if round > 1 {
+                    self.progress
+                        .info(tr_args("pipeline.stitch_converged", &[("round", &round)]));
+                }
                 break;
             }
+            // `[pipeline.stages] patch = false`: the audit only reports.
+            let Some(patch_backend) = patch_backend else {
+                self.progress.info(tr_args(
+                    "pipeline.patch_skipped",
+                    &[("count", &issues.len())],
+                ));
+                unresolved = issues;
+                break;
+            };
 
-            self.progress
-                .info(format!("Patch issues: {}", issues.len()));
-            self.run_patch_round(
+            let deferred = match self.cfg.patch_budget {
+                Some(budget) if issues.len() > budget.saturating_sub(spent) => {
+                    issues.split_off(budget.saturating_sub(spent))
+                }
+                _ => Vec::new(),
+            };
+            if issues.is_empty() {
+                self.progress.info(tr_args(
+                    "pipeline.patch_budget_spent",
+                    &[("count", &deferred.len())],
+                ));
+                unresolved = deferred;
+                break;
+            }
+            let count =
+                |severity: Severity| issues.iter().filter(|i| i.severity() == severity).count();
+            self.progress.info(tr_args(
+                "pipeline.patch_issues",
+                &[
+                    ("count", &issues.len()),
+                    ("high", &count(Severity::High)),
+                    ("medium", &count(Severity::Medium)),
+                    ("low", &count(Severity::Low)),
+                    ("deferred", &deferred.len()),
+                ],
+            ));
+            spent += issues.len();
+            let patched = self.run_patch_round(
                 patch_backend,
                 source_lang,
                 target_lang,
@@ -66,6 +236,9 @@ impl TranslatorPipeline {
                 &issues,
                 round,
             )?;
+            issues.retain(|issue| !patched.contains(&issue.tu_id));
+            issues.extend(deferred);
+            unresolved = issues;
             self.write_memory_snapshot(
                 &format!("afterPatch{round}"),
                 source_lang,
@@ -80,10 +253,122 @@ impl TranslatorPipeline {
                 output,
                 text_final,
                 round,
-                2,
+                rounds,
             );
         }
-        Ok(())
+
+        let mut problems: HashMap<usize, Vec<String>> = HashMap::new();
+        for issue in unresolved {
+            let problem = if issue.problem.trim().is_empty() {
+                issue.rewrite_instructions
+            } else {
+                issue.problem
+            };
+            problems.entry(issue.tu_id).or_default().push(problem);
+        }
+        for tu in tus.iter_mut().filter(|tu| problems.contains_key(&tu.tu_id)) {
+            if !tu.qe_flags.iter().any(|f| f == STITCH_UNRESOLVED) {
+                tu.qe_flags.push(STITCH_UNRESOLVED.to_string());
+            }
+        }
+        if !problems.is_empty() {
+            self.progress.info(tr_args(
+                "pipeline.stitch_unresolved",
+                &[("count", &problems.len())],
+            ));
+        }
+        Ok(problems)
+    }
+
+    /// `--from-review`: map the reviewed DOCX's comments to the units of `source`, write the
+    /// worklist to the trace dir and limit the run to the commented units (they are
+    /// retranslated, then patched with `run_review_patch`).
+    pub(super) fn load_review_worklist(
+        &mut self,
+        stem: &str,
+        source: &PureTextJson,
+    ) -> anyhow::Result<Vec<ReviewItem>> {
+        let Some(reviewed_path) = self.cfg.review_docx.clone() else {
+            return Ok(Vec::new());
+        };
+        let session = DocumentSession::open_with(
+            &reviewed_path,
+            &self.cfg.package,
+            &self.cfg.placeholder_prefix,
+        )
+        .kind(ErrorKind::InputDocx, "read reviewed docx")?;
+        self.report_parse_fixes(session.parse_fixes());
+        let reviewed =
+            pure_text_from_session(&session).kind(ErrorKind::InputDocx, "read reviewed docx")?;
+        let comments = read_comments(&session);
+        let worklist = review_worklist(&reviewed_path, source, &reviewed, &comments);
+        let path = self
+            .trace
+            .dir()
+            .join(format!("{stem}.review_worklist.json"));
+        write_review_worklist(&path, &worklist)?;
+        self.progress.info(tr_args(
+            "pipeline.review_worklist",
+            &[
+                ("comments", &worklist.comments),
+                ("units", &worklist.items.len()),
+                ("unmapped", &worklist.unmapped.len()),
+                ("path", &path.display()),
+            ],
+        ));
+        if worklist.items.is_empty() {
+            return Err(anyhow!(
+                "no comment in {} maps to a paragraph of the input; nothing to patch",
+                reviewed_path.display()
+            ))
+            .kind(ErrorKind::Usage, "bad arguments");
+        }
+        let ids: HashSet<usize> = worklist.items.iter().map(|item| item.tu_id).collect();
+        self.cfg
+            .unit_filter
+            .get_or_insert_with(UnitFilter::default)
+            .units = Some(ids);
+        Ok(worklist.items)
+    }
+
+    /// One patch pass (trace files `patch0`) over the units of a review worklist, with the
+    /// reviewers' comments as rewrite instructions.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn run_review_patch(
+        &mut self,
+        patch_backend: &ResolvedBackend,
+        source_lang: &str,
+        target_lang: &str,
+        tus: &mut [TranslationUnit],
+        notes: &HashMap<usize, ParaNotes>,
+        text_final: &mut PureTextJson,
+        slots_by_tu: &HashMap<usize, Vec<usize>>,
+        items: &[ReviewItem],
+    ) -> anyhow::Result<()> {
+        let issues: Vec<StitchIssue> = items
+            .iter()
+            .map(|item| StitchIssue {
+                tu_id: item.tu_id,
+                problem: "reviewer comment".to_string(),
+                rewrite_instructions: item.instruction.clone(),
+            })
+            .collect();
+        self.progress.info(tr_args(
+            "pipeline.review_patch",
+            &[("count", &issues.len())],
+        ));
+        self.run_patch_round(
+            patch_backend,
+            source_lang,
+            target_lang,
+            tus,
+            notes,
+            text_final,
+            slots_by_tu,
+            &issues,
+            0,
+        )
+        .map(|_| ())
     }
 
     fn run_stitch_audit_round(
@@ -93,10 +378,14 @@ impl TranslatorPipeline {
         tus: &[TranslationUnit],
         round: usize,
     ) -> anyhow::Result<Vec<StitchIssue>> {
-        let paras: Vec<&TranslationUnit> = tus
+        let all_paras = tus
             .iter()
-            .filter(|tu| tu.scope_key.contains("#w:p") || tu.scope_key.contains("#a:p"))
+            .filter(|tu| tu.scope_key.contains("#w:p") || tu.scope_key.contains("#a:p"));
+        let total = all_paras.clone().count();
+        let paras: Vec<&TranslationUnit> = all_paras
+            .filter(|tu| self.in_stage_scope("stitch_audit", tu))
             .collect();
+        self.report_stage_scope("stitch_audit", paras.len(), total);
         if paras.is_empty() {
             return Ok(vec![]);
         }
@@ -125,11 +414,13 @@ impl TranslatorPipeline {
             chunks.push(cur);
         }
 
-        let mut model = load_model(&self.cfg, agent_backend)?;
+        self.emit_stage_started("stitch_audit");
+        let mut model = load_controller_model(&self.cfg, agent_backend)?;
         let prompts = self.cfg.prompts.for_backend(&agent_backend.name);
         let mut all: Vec<StitchIssue> = Vec::new();
 
         for (ci, chunk) in chunks.iter().enumerate() {
+            self.cfg.cancel.check()?;
             let first = chunk.first().map(|t| t.tu_id).unwrap_or(0);
             let last = chunk.last().map(|t| t.tu_id).unwrap_or(0);
 
@@ -169,7 +460,7 @@ impl TranslatorPipeline {
             all.extend(resp.issues);
         }
 
-        Ok(all)
+        Ok(merge_issues(all))
     }
 
     fn run_patch_round(
@@ -183,7 +474,8 @@ impl TranslatorPipeline {
         slots_by_tu: &HashMap<usize, Vec<usize>>,
         issues: &[StitchIssue],
         round: usize,
-    ) -> anyhow::Result<()> {
+    ) -> anyhow::Result<HashSet<usize>> {
+        self.emit_stage_started("patch");
         let mut model = load_model(&self.cfg, patch_backend)?;
         let (patch_tmpl, repair_tmpl) = {
             let prompts = self.cfg.prompts.for_backend(&patch_backend.name);
@@ -195,133 +487,247 @@ impl TranslatorPipeline {
             .map(|(i, tu)| (tu.tu_id, i))
             .collect();
 
-        for issue in issues {
-            let Some(&idx) = idx_by_id.get(&issue.tu_id) else {
-                continue;
-            };
-            let before = collect_neighbor_block(tus, notes, idx, -1);
-            let after = collect_neighbor_block(tus, notes, idx, 1);
-
-            let source = tus[idx].frozen_surface.clone();
-            let current = tus[idx]
-                .final_translation
-                .clone()
-                .or_else(|| tus[idx].draft_translation.clone())
-                .unwrap_or_else(|| source.clone());
+        // Two rewrites of one unit would both start from its current text, the later undoing the
+        // earlier: same-unit issues become one rewrite with their instructions joined.
+        let targets: HashSet<usize> = issues.iter().map(|issue| issue.tu_id).collect();
+        let conflicts = issues.len() - targets.len();
+        if conflicts > 0 {
+            self.progress.info(tr_args(
+                "pipeline.patch_conflicts",
+                &[("count", &conflicts)],
+            ));
+        }
+        let issues: Vec<(usize, StitchIssue)> = merge_issues(issues.to_vec())
+            .into_iter()
+            .filter_map(|issue| Some((*idx_by_id.get(&issue.tu_id)?, issue)))
+            .collect();
+        let batches = patch_batches(&issues);
 
-            let prompt = render_template(
-                &patch_tmpl,
+        let (done_tx, done_rx) = mpsc::channel::<(usize, anyhow::Result<String>)>();
+        let widest = batches.iter().map(Vec::len).max().unwrap_or(0);
+        let mut workers = Vec::new();
+        for _ in 1..self.cfg.patch_workers.min(widest) {
+            let (jobs_tx, jobs_rx) = mpsc::channel::<(usize, String)>();
+            let (cfg, backend, done) = (self.cfg.clone(), patch_backend.clone(), done_tx.clone());
+            let handle = std::thread::spawn(move || {
+                let mut model = load_model(&cfg, &backend);
+                for (job, prompt) in jobs_rx {
+                    let raw = match &mut model {
+                        Ok(model) => patch_chat(model, &prompt),
+                        Err(err) => Err(anyhow!("load patch model: {err:#}")),
+                    };
+                    if done.send((job, raw)).is_err() {
+                        break;
+                    }
+                }
+            });
+            workers.push((jobs_tx, handle));
+        }
+        drop(done_tx);
+        if !workers.is_empty() {
+            self.progress.info(tr_args(
+                "pipeline.patch_batches",
                 &[
-                    ("source_lang", source_lang),
-                    ("target_lang", target_lang),
-                    ("instructions", &issue.rewrite_instructions),
-                    ("before", &before),
-                    ("source", &source),
-                    ("current", &current),
-                    ("after", &after),
+                    ("count", &issues.len()),
+                    ("batches", &batches.len()),
+                    ("workers", &(workers.len() + 1)),
                 ],
-            );
-            let _ = self.trace.write_tu_text(
-                tus[idx].tu_id,
-                &format!("patch{round}"),
-                "prompt",
-                &prompt,
-            );
+            ));
+        }
 
-            let raw = model.chat(None, &prompt, 1200, 0.2, 0.9, Some(40), Some(1.05), false)?;
-            let mut out = cleanup_model_text(&raw);
-            if validate_translation(&tus[idx], &out).is_err() {
-                let must_keep_tokens = crate::sentinels::must_keep_tokens(&source);
-                let validation_error = validate_translation(&tus[idx], &out)
-                    .err()
-                    .map(|e| e.to_string())
-                    .unwrap_or_else(|| "patch_invalid".to_string());
-                let nt_map = crate::freezer::render_nt_map_for_prompt(&tus[idx].nt_map);
-                let repaired = self.repair_translation(
-                    &mut model,
-                    &repair_tmpl,
-                    source_lang,
-                    target_lang,
-                    &source,
-                    &out,
-                    &must_keep_tokens,
-                    &validation_error,
-                    &nt_map,
-                )?;
-                out = repaired;
-            }
-            if validate_translation(&tus[idx], &out).is_err() {
-                continue;
+        let mut patched: HashSet<usize> = HashSet::new();
+        for batch in &batches {
+            self.cfg.cancel.check()?;
+            // Every prompt of the batch is built before any of its patches lands; the units are
+            // non-adjacent, so none is in another's before/after context.
+            let mut prompts: Vec<String> = Vec::with_capacity(batch.len());
+            for &pos in batch {
+                let (idx, issue) = (issues[pos].0, &issues[pos].1);
+                let before = collect_neighbor_block(tus, notes, idx, -1);
+                let after = collect_neighbor_block(tus, notes, idx, 1);
+
+                let source = tus[idx].frozen_surface.clone();
+                let current = tus[idx]
+                    .final_translation
+                    .clone()
+                    .or_else(|| tus[idx].draft_translation.clone())
+                    .unwrap_or_else(|| source.clone());
+
+                let prompt = render_template(
+                    &patch_tmpl,
+                    &[
+                        ("source_lang", source_lang),
+                        ("target_lang", target_lang),
+                        ("instructions", &issue.rewrite_instructions),
+                        ("before", &before),
+                        ("source", &source),
+                        ("current", &current),
+                        ("after", &after),
+                    ],
+                );
+                let _ = self.trace.write_tu_text(
+                    tus[idx].tu_id,
+                    &format!("patch{round}"),
+                    "prompt",
+                    &prompt,
+                );
+                prompts.push(prompt);
             }
 
-            let slots = slots_by_tu
-                .get(&tus[idx].tu_id)
-                .cloned()
-                .unwrap_or_default();
-            if slots.is_empty() {
-                continue;
+            // Job `i` goes to worker `i % n`, worker 0 being this thread's model; a worker
+            // that has gone away leaves its jobs to this thread too.
+            let n = workers.len() + 1;
+            let mut raws: Vec<Option<anyhow::Result<String>>> = Vec::new();
+            raws.resize_with(prompts.len(), || None);
+            let mut local: Vec<usize> = Vec::new();
+            for (job, prompt) in prompts.iter().enumerate() {
+                let sent =
+                    job % n > 0 && workers[job % n - 1].0.send((job, prompt.clone())).is_ok();
+                if !sent {
+                    local.push(job);
+                }
+            }
+            let mut pending = prompts.len() - local.len();
+            for job in local {
+                self.cfg.cancel.check()?;
+                raws[job] = Some(patch_chat(&mut model, &prompts[job]));
+            }
+            while pending > 0 {
+                let (job, raw) = done_rx
+                    .recv()
+                    .map_err(|_| anyhow!("patch worker stopped"))?;
+                raws[job] = Some(raw);
+                pending -= 1;
             }
 
-            let mut applied = false;
-            for attempt in 0..=1 {
+            for (job, &pos) in batch.iter().enumerate() {
+                let idx = issues[pos].0;
+                let raw = raws[job]
+                    .take()
+                    .unwrap_or_else(|| Err(anyhow!("patch_missing")))?;
+                let mut out = cleanup_model_text(&raw);
                 if validate_translation(&tus[idx], &out).is_err() {
-                    if attempt == 0 {
-                        let must_keep_tokens = crate::sentinels::must_keep_tokens(&source);
-                        let validation_error = validate_translation(&tus[idx], &out)
-                            .err()
-                            .map(|e| e.to_string())
-                            .unwrap_or_else(|| "patch_invalid".to_string());
-                        let nt_map = crate::freezer::render_nt_map_for_prompt(&tus[idx].nt_map);
-                        let repaired = self.repair_translation(
-                            &mut model,
-                            &repair_tmpl,
-                            source_lang,
-                            target_lang,
-                            &source,
-                            &out,
-                            &must_keep_tokens,
-                            &validation_error,
-                            &nt_map,
-                        )?;
-                        out = repaired;
-                        continue;
-                    }
-                    break;
+                    let validation_error = validate_translation(&tus[idx], &out)
+                        .err()
+                        .map(|e| e.to_string())
+                        .unwrap_or_else(|| "patch_invalid".to_string());
+                    let repaired = self.repair_translation(
+                        &mut model,
+                        &repair_tmpl,
+                        source_lang,
+                        target_lang,
+                        &tus[idx],
+                        &out,
+                        &validation_error,
+                    )?;
+                    out = repaired;
+                }
+                if validate_translation(&tus[idx], &out).is_err() {
+                    continue;
+                }
+
+                let slots = slots_by_tu
+                    .get(&tus[idx].tu_id)
+                    .cloned()
+                    .unwrap_or_default();
+                if slots.is_empty() {
+                    continue;
                 }
-                match self.apply_slot_translation(text_final, &slots, &tus[idx], &out) {
-                    Ok(()) => {
-                        applied = true;
+
+                let mut applied = false;
+                for attempt in 0..=1 {
+                    if validate_translation(&tus[idx], &out).is_err() {
+                        if attempt == 0 {
+                            let validation_error = validate_translation(&tus[idx], &out)
+                                .err()
+                                .map(|e| e.to_string())
+                                .unwrap_or_else(|| "patch_invalid".to_string());
+                            let repaired = self.repair_translation(
+                                &mut model,
+                                &repair_tmpl,
+                                source_lang,
+                                target_lang,
+                                &tus[idx],
+                                &out,
+                                &validation_error,
+                            )?;
+                            out = repaired;
+                            continue;
+                        }
                         break;
                     }
-                    Err(_) if attempt == 0 => {
-                        let must_keep_tokens = crate::sentinels::must_keep_tokens(&source);
-                        let reason = "slot_projection_failed".to_string();
-                        let nt_map = crate::freezer::render_nt_map_for_prompt(&tus[idx].nt_map);
-                        let repaired = self.repair_translation(
-                            &mut model,
-                            &repair_tmpl,
-                            source_lang,
-                            target_lang,
-                            &source,
-                            &out,
-                            &must_keep_tokens,
-                            &reason,
-                            &nt_map,
-                        )?;
-                        out = repaired;
+                    match self.apply_slot_translation(text_final, &slots, &tus[idx], &out) {
+                        Ok(()) => {
+                            applied = true;
+                            break;
+                        }
+                        Err(err) if attempt == 0 => {
+                            self.trace_projection_failure(
+                                "patch",
+                                &slots,
+                                &mut tus[idx],
+                                &out,
+                                &err,
+                            );
+                            let reason = format!("slot_projection_failed: {err:#}");
+                            let repaired = self.repair_translation(
+                                &mut model,
+                                &repair_tmpl,
+                                source_lang,
+                                target_lang,
+                                &tus[idx],
+                                &out,
+                                &reason,
+                            )?;
+                            out = repaired;
+                        }
+                        Err(err) => {
+                            self.trace_projection_failure(
+                                "patch.repaired",
+                                &slots,
+                                &mut tus[idx],
+                                &out,
+                                &err,
+                            );
+                            break;
+                        }
                     }
-                    Err(_) => break,
                 }
+                if !applied {
+                    continue;
+                }
+
+                tus[idx].final_translation = Some(out.clone());
+                patched.insert(tus[idx].tu_id);
             }
-            if !applied {
-                continue;
-            }
+        }
 
-            tus[idx].final_translation = Some(out.clone());
+        for (jobs_tx, handle) in workers {
+            drop(jobs_tx);
+            let _ = handle.join();
         }
+        Ok(patched)
+    }
+}
+
+fn patch_chat(model: &mut NativeChatModel, prompt: &str) -> anyhow::Result<String> {
+    model.chat(None, prompt, 1200, 0.2, 0.9, Some(40), Some(1.05), false)
+}
 
-        Ok(())
+/// Groups `(unit index, issue)` positions into batches, in issue order, so that no two units of
+/// a batch are the same or adjacent (first fit: the most severe issues land in the first batches).
+fn patch_batches(issues: &[(usize, StitchIssue)]) -> Vec<Vec<usize>> {
+    let mut batches: Vec<Vec<usize>> = Vec::new();
+    for (pos, &(idx, _)) in issues.iter().enumerate() {
+        let free = batches
+            .iter_mut()
+            .find(|batch| batch.iter().all(|&p| issues[p].0.abs_diff(idx) > 1));
+        match free {
+            Some(batch) => batch.push(pos),
+            None => batches.push(vec![pos]),
+        }
     }
+    batches
 }
 
 fn render_ctx_item(
@@ -338,14 +744,10 @@ fn render_ctx_item(
         .as_deref()
         .unwrap_or(&tu.frozen_surface);
     let b = tu.alt_translation.as_deref().unwrap_or(a);
-    let n = notes.get(&tu.tu_id).cloned().unwrap_or_default();
     let mut block = String::new();
     block.push_str(&format!("TU#{} SOURCE:\n{}\n", tu.tu_id, tu.frozen_surface));
-    if let Some(u) = n.understanding.as_ref() {
-        let u = u.trim();
-        if !u.is_empty() {
-            block.push_str(&format!("TU#{} NOTE:\n{}\n", tu.tu_id, u));
-        }
+    if let Some(u) = notes.get(&tu.tu_id).and_then(|n| n.prompt_note()) {
+        block.push_str(&format!("TU#{} NOTE:\n{}\n", tu.tu_id, u));
     }
     block.push_str(&format!(
         "TU#{} A:\n{}\nTU#{} B:\n{}\n\n",
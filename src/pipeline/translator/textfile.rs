@@ -0,0 +1,227 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::Context;
+use sha2::{Digest, Sha256};
+
+use crate::errors::{ErrorKind, ResultExt};
+use crate::freezer::unfreeze_text;
+use crate::i18n::{tr, tr_args};
+use crate::ir::TranslationUnit;
+use crate::textdoc::{TextDocKind, TextDocument};
+use crate::textutil::strip_sentinels;
+
+use super::{current_translation, write_file_atomic, TranslatorPipeline};
+
+impl TranslatorPipeline {
+    /// `.txt` / `.md` inputs: one TU per paragraph / Markdown line block, translated with the
+    /// basic segmented translate + repair loop, written back with the original structure.
+    pub(super) fn translate_text_file(
+        &mut self,
+        input: &Path,
+        output: &Path,
+        kind: TextDocKind,
+    ) -> anyhow::Result<()> {
+        self.progress
+            .info(tr_args("pipeline.read_text", &[("path", &input.display())]));
+        if self.cfg.resume {
+            self.progress.info(tr("pipeline.continue_text_mode"));
+        }
+        fs::create_dir_all(self.trace.dir())
+            .with_context(|| format!("create trace dir: {}", self.trace.dir().display()))?;
+
+        let bytes = fs::read(input).with_context(|| format!("read input: {}", input.display()))?;
+        let source = String::from_utf8(bytes)
+            .map_err(|_| anyhow::anyhow!("input is not UTF-8: {}", input.display()))
+            .kind(ErrorKind::InputFile, "read input text")?;
+        let doc = TextDocument::parse(&source, kind);
+        let part_name = input
+            .file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or("input.txt")
+            .to_string();
+
+        let stem = output
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("output");
+        let (tus, translations) = self.translate_text_document(&doc, &part_name, Some(stem))?;
+        self.check_source_fallbacks(&tus).stage("translate_a")?;
+        let pairs_json: Vec<serde_json::Value> = tus
+            .iter()
+            .map(|tu| {
+                serde_json::json!({
+                    "tu_id": tu.tu_id,
+                    "source": tu.source_surface,
+                    "translation": translations.get(tu.tu_id - 1).cloned().flatten(),
+                })
+            })
+            .collect();
+        let _ = self.trace.write_named_text(
+            &format!("{stem}.text_units.json"),
+            &serde_json::to_string_pretty(&pairs_json).unwrap_or_default(),
+        );
+
+        self.progress.info(tr_args(
+            "pipeline.write_output",
+            &[("path", &output.display())],
+        ));
+        write_file_atomic(output, doc.render(&translations).as_bytes())
+            .kind(ErrorKind::Merge, "write output text")?;
+
+        let doc_key = format!(
+            "text:{}",
+            &hex::encode(Sha256::digest(source.as_bytes()))[..16]
+        );
+        let pairs: Vec<(usize, String, String)> = tus
+            .iter()
+            .filter_map(|tu| {
+                let t = translations.get(tu.tu_id - 1)?.clone()?;
+                Some((tu.tu_id, tu.source_surface.clone(), t))
+            })
+            .collect();
+        self.save_project_memory(stem, &doc_key, input, &pairs)?;
+        self.write_aligned_corpus(stem, &tus, None);
+
+        self.progress.info(tr("pipeline.done"));
+        Ok(())
+    }
+
+    /// `translate-text` (stdin -> stdout): same freezing / validation / repair as `.txt` inputs,
+    /// without trace artifacts, output files or project-memory writes.
+    pub fn translate_text(&mut self, source: &str) -> anyhow::Result<String> {
+        let doc = TextDocument::parse(source, TextDocKind::Plain);
+        let (_tus, translations) = self.translate_text_document(&doc, "stdin", None)?;
+        Ok(doc.render(&translations))
+    }
+
+    /// Translate the units of `doc`; with `stem` (file inputs) `--qa-sample` runs on them. Custom
+    /// stages run either way (`stdin` as the stem for stdin text).
+    fn translate_text_document(
+        &mut self,
+        doc: &TextDocument,
+        part_name: &str,
+        stem: Option<&str>,
+    ) -> anyhow::Result<(Vec<TranslationUnit>, Vec<Option<String>>)> {
+        let mut tus: Vec<TranslationUnit> = Vec::new();
+        for (idx, unit) in doc.units().enumerate() {
+            let fr = doc.freeze_unit(unit, &self.cfg.acronyms);
+            tus.push(TranslationUnit {
+                tu_id: idx + 1,
+                part_name: part_name.to_string(),
+                scope_key: "text".to_string(),
+                para_style: None,
+                atoms: Vec::new(),
+                spans: Vec::new(),
+                source_surface: unit.text.clone(),
+                frozen_surface: fr.text,
+                nt_map: fr.nt_map,
+                nt_mask: fr.mask,
+                draft_translation: None,
+                final_translation: None,
+                alt_translation: None,
+                draft_translation_model: None,
+                alt_translation_model: None,
+                qe_score: None,
+                qe_flags: Vec::new(),
+            });
+        }
+        self.progress.info(tr_args(
+            "pipeline.extracted_paragraphs",
+            &[("count", &tus.len())],
+        ));
+        if let Some(max_tus) = self.cfg.max_tus {
+            let keep = max_tus.max(1).min(tus.len());
+            tus.truncate(keep);
+            self.progress
+                .info(tr_args("pipeline.max_tus", &[("count", &keep)]));
+        }
+
+        let (source_lang, target_lang) =
+            match (self.cfg.source_lang.clone(), self.cfg.target_lang.clone()) {
+                (Some(s), Some(t)) => (s, t),
+                _ => {
+                    let excerpts: Vec<String> = tus
+                        .iter()
+                        .map(|tu| strip_sentinels(&tu.source_surface))
+                        .filter(|s| !s.trim().is_empty())
+                        .take(20)
+                        .collect();
+                    self.auto_lang_pair(&excerpts)
+                }
+            };
+        self.progress.info(tr_args(
+            "pipeline.language",
+            &[("src", &source_lang), ("tgt", &target_lang)],
+        ));
+        self.route_translate_backend(&source_lang, &target_lang);
+        self.open_project_memory(&source_lang, &target_lang)?;
+        let pivot = self.pivot_for(&source_lang, &target_lang);
+        let mut pivot_originals: Vec<Vec<TranslationUnit>> = Vec::new();
+        if let Some(pivot) = pivot.as_ref() {
+            pivot_originals = self.run_pivot_hop(
+                pivot,
+                &source_lang,
+                &target_lang,
+                &mut [("pivot(text)", &mut tus)],
+            )?;
+        }
+        let hop_source_lang = pivot.map_or_else(|| source_lang.clone(), |p| p.via);
+
+        self.progress.info(tr_args(
+            "pipeline.translate_backend",
+            &[("name", &self.cfg.translate_backend.name)],
+        ));
+        let (mut model, mut translate_backend) = self.load_translate_model()?;
+
+        let mut translations: Vec<Option<String>> = vec![None; doc.units().count()];
+        loop {
+            let translate_prompts = self.cfg.prompts.for_backend(&translate_backend.name);
+            let prompt_translate_a = translate_prompts.translate_a.clone();
+            let prompt_translate_repair = translate_prompts.translate_repair.clone();
+            let result = self.translate_units_segmented_basic(
+                &mut model,
+                &translate_backend,
+                &hop_source_lang,
+                &target_lang,
+                "translate_a(text)",
+                &prompt_translate_a,
+                &prompt_translate_repair,
+                &mut tus,
+                &mut |tu, out_unfrozen, _processed, _total| {
+                    if let Some(slot) = translations.get_mut(tu.tu_id - 1) {
+                        *slot = Some(out_unfrozen.to_string());
+                    }
+                    Ok(())
+                },
+            );
+            match result {
+                Ok(()) => break,
+                Err(err) => {
+                    self.fall_back_translate_backend(err).stage("translate_a")?;
+                    (model, translate_backend) = self.load_translate_model()?;
+                }
+            }
+        }
+        if let Some(originals) = pivot_originals.pop() {
+            for (tu_id, text) in self.finish_pivot(originals, &mut tus) {
+                if let Some(slot) = translations.get_mut(tu_id - 1) {
+                    *slot = Some(text);
+                }
+            }
+        }
+        let stage_stem = stem.unwrap_or("stdin");
+        for idx in self.run_custom_stages(stage_stem, &source_lang, &target_lang, &mut tus)? {
+            let tu = &tus[idx];
+            if let (Some(t), Some(slot)) =
+                (current_translation(tu), translations.get_mut(tu.tu_id - 1))
+            {
+                *slot = Some(unfreeze_text(t, &tu.nt_map));
+            }
+        }
+        if let Some(stem) = stem {
+            self.run_qa_sample(stem, &source_lang, &target_lang, &mut tus)?;
+        }
+        Ok((tus, translations))
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::ir::TranslationUnit;
+use crate::quality::is_hard_quality_flag;
+
+/// `<stem>.validation.json`: the flagged paragraphs of a DOCX run, input of `--from-report`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ValidationReport {
+    pub units: Vec<ValidationUnit>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ValidationUnit {
+    /// Paragraph id (the id `--range` uses).
+    pub tu_id: usize,
+    pub scope_key: String,
+    pub qe_flags: Vec<String>,
+    /// Stitch audit issues no patch round resolved (`stitch_unresolved`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub issues: Vec<String>,
+}
+
+impl ValidationUnit {
+    /// Paragraph `tu` with the flags of the units translated for it (`tu` itself in full mode,
+    /// its slots in basic mode), deduplicated in order. `None` without flags.
+    pub fn from_units<'a>(
+        tu_id: usize,
+        scope_key: &str,
+        units: impl IntoIterator<Item = &'a TranslationUnit>,
+    ) -> Option<Self> {
+        let mut qe_flags: Vec<String> = Vec::new();
+        for flag in units.into_iter().flat_map(|u| u.qe_flags.iter()) {
+            if !qe_flags.contains(flag) {
+                qe_flags.push(flag.clone());
+            }
+        }
+        (!qe_flags.is_empty()).then(|| Self {
+            tu_id,
+            scope_key: scope_key.to_string(),
+            qe_flags,
+            issues: Vec::new(),
+        })
+    }
+}
+
+/// Source fallbacks and hard quality flags (what `highlight_low_confidence` marks).
+pub fn is_hard_flag(flag: &str) -> bool {
+    flag == "source_fallback" || is_hard_quality_flag(flag)
+}
+
+/// Write the report; `keep` decides which entries of an existing report at `path` are carried
+/// over (paragraphs this run did not translate).
+pub fn write_validation_report(
+    path: &Path,
+    mut units: Vec<ValidationUnit>,
+    keep: impl Fn(&ValidationUnit) -> bool,
+) -> anyhow::Result<usize> {
+    if let Ok(previous) = read_validation_report(path) {
+        units.extend(previous.units.into_iter().filter(|u| keep(u)));
+        units.sort_by_key(|u| u.tu_id);
+    }
+    let report = ValidationReport { units };
+    fs::write(
+        path,
+        serde_json::to_vec_pretty(&report).context("serialize validation report")?,
+    )
+    .with_context(|| format!("write validation report: {}", path.display()))?;
+    Ok(report.units.len())
+}
+
+pub fn read_validation_report(path: &Path) -> anyhow::Result<ValidationReport> {
+    let bytes =
+        fs::read(path).with_context(|| format!("read validation report: {}", path.display()))?;
+    serde_json::from_slice(&bytes)
+        .with_context(|| format!("parse validation report: {}", path.display()))
+}
+
+/// `--from-report` / `--only`: ids of the paragraphs in the report with a matching flag.
+/// `only` is `hard_flags`, `all` or a comma-separated list of flags (a trailing `*` matches a
+/// prefix, e.g. `target_script_missing_*`).
+pub fn flagged_unit_ids(path: &Path, only: &str) -> anyhow::Result<HashSet<usize>> {
+    let report = read_validation_report(path)?;
+    let only = only.trim();
+    let wanted: Vec<&str> = only
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect();
+    if wanted.is_empty() {
+        return Err(anyhow!(
+            "invalid --only {only:?} (expected \"hard_flags\", \"all\" or flag names)"
+        ));
+    }
+    let matches = |flag: &str| {
+        wanted.iter().any(|w| match *w {
+            "all" => true,
+            "hard_flags" => is_hard_flag(flag),
+            w => match w.strip_suffix('*') {
+                Some(prefix) => flag.starts_with(prefix),
+                None => flag == w,
+            },
+        })
+    };
+    Ok(report
+        .units
+        .iter()
+        .filter(|u| u.qe_flags.iter().any(|f| matches(f)))
+        .map(|u| u.tu_id)
+        .collect())
+}
@@ -0,0 +1,117 @@
+//! `--workdir`: one directory per document for the intermediate artifacts (mask, offsets,
+//! blobs, text/structure JSON, autosaves) with a `manifest.json` naming each, so merge,
+//! `--continue` and `--from-review` find everything from that directory alone.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+const MANIFEST_VERSION: u32 = 1;
+
+/// Artifact kinds recorded in the manifest.
+pub const ARTIFACT_MASK: &str = "mask";
+pub const ARTIFACT_OFFSETS: &str = "offsets";
+pub const ARTIFACT_BLOBS: &str = "blobs";
+pub const ARTIFACT_TEXT: &str = "text";
+pub const ARTIFACT_SOURCE_TEXT: &str = "source_text";
+pub const ARTIFACT_STRUCTURE: &str = "structure";
+pub const ARTIFACT_AUTOSAVE_TEXT: &str = "autosave_text";
+pub const ARTIFACT_FINAL_TEXT: &str = "final_text";
+pub const ARTIFACT_OUTPUT: &str = "output";
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct WorkdirManifest {
+    pub version: u32,
+    /// Document the artifacts were extracted from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input: Option<PathBuf>,
+    /// Artifact kind -> file name inside the workdir (or an absolute path outside it).
+    #[serde(default)]
+    pub artifacts: BTreeMap<String, String>,
+}
+
+impl WorkdirManifest {
+    pub fn load(dir: &Path) -> anyhow::Result<Self> {
+        let path = dir.join(MANIFEST_FILE);
+        let bytes = fs::read(&path).with_context(|| {
+            format!(
+                "read workdir manifest: {} (extract or translate with --workdir first)",
+                path.display()
+            )
+        })?;
+        let manifest: Self = serde_json::from_slice(&bytes)
+            .with_context(|| format!("parse workdir manifest: {}", path.display()))?;
+        if manifest.version > MANIFEST_VERSION {
+            return Err(anyhow!(
+                "workdir manifest version {} is newer than supported ({MANIFEST_VERSION}): {}",
+                manifest.version,
+                path.display()
+            ));
+        }
+        Ok(manifest)
+    }
+
+    /// Path of artifact `kind` when the manifest records it.
+    pub fn artifact(&self, dir: &Path, kind: &str) -> Option<PathBuf> {
+        self.artifacts.get(kind).map(|name| dir.join(name))
+    }
+
+    /// First artifact among `kinds` that is recorded and still on disk.
+    pub fn first_artifact(&self, dir: &Path, kinds: &[&str]) -> Option<PathBuf> {
+        kinds
+            .iter()
+            .filter_map(|kind| self.artifact(dir, kind))
+            .find(|path| path.exists())
+    }
+}
+
+/// `<input dir>/<stem>.mtwork`, the workdir of a bare `--workdir`.
+pub fn default_workdir_for(input: &Path) -> PathBuf {
+    let stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("docx");
+    let dir = input.parent().unwrap_or_else(|| Path::new("."));
+    dir.join(format!("{stem}.mtwork"))
+}
+
+/// A relative `path` placed inside `dir` (by file name), as `--output-dir` places outputs.
+pub fn in_workdir(dir: &Path, path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+    dir.join(path.file_name().map(PathBuf::from).unwrap_or_default())
+}
+
+/// Record written artifacts in `dir/manifest.json`, keeping what earlier commands recorded.
+pub fn record_artifacts(
+    dir: &Path,
+    input: Option<&Path>,
+    artifacts: &[(&str, &Path)],
+) -> anyhow::Result<()> {
+    let path = dir.join(MANIFEST_FILE);
+    let mut manifest = if path.exists() {
+        WorkdirManifest::load(dir)?
+    } else {
+        WorkdirManifest::default()
+    };
+    manifest.version = MANIFEST_VERSION;
+    if let Some(input) = input {
+        manifest.input = Some(input.to_path_buf());
+    }
+    for (kind, file) in artifacts {
+        let name = file
+            .strip_prefix(dir)
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|_| file.to_path_buf());
+        manifest
+            .artifacts
+            .insert(kind.to_string(), name.to_string_lossy().into_owned());
+    }
+    let data = serde_json::to_vec_pretty(&manifest).context("serialize workdir manifest")?;
+    let tmp = dir.join(format!("{MANIFEST_FILE}.tmp"));
+    fs::write(&tmp, data).with_context(|| format!("write {}", tmp.display()))?;
+    fs::rename(&tmp, &path).with_context(|| format!("replace {}", path.display()))?;
+    Ok(())
+}
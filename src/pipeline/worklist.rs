@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::Context;
+use serde::Serialize;
+
+use crate::docx::comments::DocComment;
+use crate::docx::pure_text::{PureParagraph, PureTextJson};
+
+/// Paragraphs the alignment looks ahead to resynchronize after the reviewer added or removed
+/// some.
+const ALIGN_LOOKAHEAD: usize = 32;
+
+/// `<stem>.review_worklist.json`: a reviewer's Word comments on a translated DOCX, mapped to the
+/// units they are about; the patch stage takes each item's instruction.
+#[derive(Debug, Default, Serialize)]
+pub struct ReviewWorklist {
+    /// The reviewed DOCX.
+    pub reviewed: String,
+    pub comments: usize,
+    pub items: Vec<ReviewItem>,
+    /// Ids of comments on paragraphs without a counterpart in the source.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unmapped: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ReviewItem {
+    pub tu_id: usize,
+    /// The unit's comments, one per line.
+    pub instruction: String,
+    pub comment_ids: Vec<String>,
+}
+
+/// Map the comments of `reviewed` (the text of a translated DOCX) to the units of `source` by
+/// aligning the paragraphs of each part in order: paragraphs match on their container, table
+/// position, style and list membership, so paragraphs the reviewer inserted or deleted are
+/// skipped. Comments on one unit are merged into one item.
+pub fn review_worklist(
+    reviewed_path: &Path,
+    source: &PureTextJson,
+    reviewed: &PureTextJson,
+    comments: &[DocComment],
+) -> ReviewWorklist {
+    let aligned = align_paragraphs(source, reviewed);
+    let mut worklist = ReviewWorklist {
+        reviewed: reviewed_path.display().to_string(),
+        comments: comments.len(),
+        ..ReviewWorklist::default()
+    };
+    let mut by_tu: HashMap<usize, usize> = HashMap::new();
+    for c in comments {
+        let Some(&tu_id) = aligned.get(&(c.part_name.clone(), c.para_event_index)) else {
+            worklist.unmapped.push(c.id.clone());
+            continue;
+        };
+        let line = match c.author.as_deref().filter(|a| !a.trim().is_empty()) {
+            Some(author) => format!("{} ({author})", c.text.replace('\n', " ")),
+            None => c.text.replace('\n', " "),
+        };
+        match by_tu.get(&tu_id) {
+            Some(&i) => {
+                let item = &mut worklist.items[i];
+                item.instruction.push('\n');
+                item.instruction.push_str(&line);
+                item.comment_ids.push(c.id.clone());
+            }
+            None => {
+                by_tu.insert(tu_id, worklist.items.len());
+                worklist.items.push(ReviewItem {
+                    tu_id,
+                    instruction: line,
+                    comment_ids: vec![c.id.clone()],
+                });
+            }
+        }
+    }
+    worklist.items.sort_by_key(|item| item.tu_id);
+    worklist
+}
+
+pub fn write_review_worklist(path: &Path, worklist: &ReviewWorklist) -> anyhow::Result<()> {
+    fs::write(
+        path,
+        serde_json::to_vec_pretty(worklist).context("serialize review worklist")?,
+    )
+    .with_context(|| format!("write review worklist: {}", path.display()))
+}
+
+/// `(part, xml_event_index)` of each reviewed paragraph -> id of the source paragraph it
+/// corresponds to.
+fn align_paragraphs(
+    source: &PureTextJson,
+    reviewed: &PureTextJson,
+) -> HashMap<(String, usize), usize> {
+    let mut by_part: HashMap<&str, (Vec<&PureParagraph>, Vec<&PureParagraph>)> = HashMap::new();
+    for p in &source.paragraphs {
+        by_part.entry(p.part_name.as_str()).or_default().0.push(p);
+    }
+    for p in &reviewed.paragraphs {
+        by_part.entry(p.part_name.as_str()).or_default().1.push(p);
+    }
+    let mut aligned = HashMap::new();
+    for (src, rev) in by_part.values() {
+        let (mut i, mut j) = (0usize, 0usize);
+        while i < src.len() && j < rev.len() {
+            if !same_shape(src[i], rev[j]) {
+                let resync = (1..=ALIGN_LOOKAHEAD).find_map(|k| {
+                    if src.get(i + k).is_some_and(|s| same_shape(s, rev[j])) {
+                        Some((i + k, j))
+                    } else if rev.get(j + k).is_some_and(|r| same_shape(src[i], r)) {
+                        Some((i, j + k))
+                    } else {
+                        None
+                    }
+                });
+                // No resync: the paragraph itself changed shape; keep the pairing.
+                if let Some((si, rj)) = resync {
+                    (i, j) = (si, rj);
+                }
+            }
+            aligned.insert(
+                (rev[j].part_name.clone(), rev[j].xml_event_index),
+                src[i].para_id,
+            );
+            i += 1;
+            j += 1;
+        }
+    }
+    aligned
+}
+
+fn same_shape(a: &PureParagraph, b: &PureParagraph) -> bool {
+    a.container as u8 == b.container as u8
+        && a.table_index.is_some() == b.table_index.is_some()
+        && (a.row_index, a.cell_index) == (b.row_index, b.cell_index)
+        && a.p_style == b.p_style
+        && a.num_id.is_some() == b.num_id.is_some()
+}
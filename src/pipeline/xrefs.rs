@@ -0,0 +1,344 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::docx::pure_text::PureTextJson;
+use crate::docx::structure::heading_level;
+
+use super::casing::rewrite_paragraph;
+use super::docmap::{ParaSlotUnit, RefField};
+use super::quotes::quote_pairs;
+
+/// Cross-references of a document: bookmark targets, `REF` fields, table-of-contents entries
+/// and the paragraphs that may quote a heading.
+#[derive(Debug, Default)]
+pub struct CrossRefs {
+    /// Bookmark name -> paragraph id.
+    targets: HashMap<String, usize>,
+    /// Paragraph id and slot ids of every paragraph with text.
+    paras: Vec<(usize, Vec<usize>)>,
+    fields: Vec<RefField>,
+    /// Paragraphs in TOC styles outside a `TOC` field: typed or pasted entries Word won't update.
+    toc_entries: Vec<TocEntry>,
+    toc_field: bool,
+}
+
+#[derive(Debug)]
+struct TocEntry {
+    tu_id: usize,
+    slot_ids: Vec<usize>,
+    /// Bookmark the entry links to (`w:hyperlink w:anchor`).
+    anchor: Option<String>,
+}
+
+/// A heading paragraph with its source title (normalized, lower-cased) and final translation.
+struct Heading {
+    id: usize,
+    title: String,
+    text: String,
+}
+
+/// Outcome of `CrossRefs::harmonize`.
+#[derive(Debug, Default)]
+pub struct CrossRefCheck {
+    /// `REF` field results rewritten to the translated target text.
+    pub fields: usize,
+    /// Paragraphs whose quoted heading titles were aligned with the translated headings.
+    pub quoted: usize,
+}
+
+/// Outcome of `CrossRefs::check_toc`.
+#[derive(Debug, Default)]
+pub struct TocCheck {
+    /// Entries rewritten to the translated heading.
+    pub fixed: usize,
+    /// Entries that don't show their translated heading and could not be rewritten.
+    pub mismatches: Vec<TocMismatch>,
+}
+
+#[derive(Debug)]
+pub struct TocMismatch {
+    pub tu_id: usize,
+    pub entry: String,
+    pub heading: String,
+}
+
+impl CrossRefs {
+    pub fn collect(units: &[ParaSlotUnit]) -> Self {
+        let mut refs = CrossRefs::default();
+        for u in units {
+            for b in &u.bookmarks {
+                refs.targets.entry(b.clone()).or_insert(u.tu_id);
+            }
+            refs.fields.extend(u.ref_fields.iter().cloned());
+            if u.slot_ids.is_empty() {
+                continue;
+            }
+            refs.paras.push((u.tu_id, u.slot_ids.clone()));
+            refs.toc_field |= u.in_toc_field;
+            if !u.in_toc_field && u.para_style.as_deref().is_some_and(is_toc_style) {
+                refs.toc_entries.push(TocEntry {
+                    tu_id: u.tu_id,
+                    slot_ids: u.slot_ids.clone(),
+                    anchor: u.slot_styles.iter().find_map(|style| link_anchor(style)),
+                });
+            }
+        }
+        refs
+    }
+
+    /// The document has a field-generated table of contents (Word rebuilds it on update).
+    pub fn has_toc_field(&self) -> bool {
+        self.toc_field
+    }
+
+    fn slot_map(&self) -> HashMap<usize, &[usize]> {
+        self.paras
+            .iter()
+            .map(|(id, slot_ids)| (*id, slot_ids.as_slice()))
+            .collect()
+    }
+
+    fn headings(&self, source: &PureTextJson, output: &PureTextJson) -> Vec<Heading> {
+        let slots = self.slot_map();
+        source
+            .paragraphs
+            .iter()
+            .filter(|p| heading_level(p).is_some())
+            .filter_map(|p| {
+                let slot_ids = slots.get(&p.para_id)?;
+                Some(Heading {
+                    id: p.para_id,
+                    title: normalized(&joined(source, slot_ids)).to_lowercase(),
+                    text: joined(output, slot_ids).trim().to_string(),
+                })
+            })
+            .collect()
+    }
+
+    /// Post-pass on the final slot texts: the cached result of a `REF` field showing a bookmarked
+    /// paragraph's text gets that paragraph's translation (`fields`: only where every slot holds
+    /// its own text, i.e. marker projection or per-slot units), and a heading title quoted in
+    /// another paragraph ("see Section 5 'Termination'") is replaced by the translated heading
+    /// when the translation kept the paragraph's quote pairs.
+    pub fn harmonize(
+        &self,
+        source: &PureTextJson,
+        output: &mut PureTextJson,
+        fields: bool,
+    ) -> CrossRefCheck {
+        let mut check = CrossRefCheck::default();
+        let slots = self.slot_map();
+
+        if fields {
+            for field in &self.fields {
+                let Some(&target) = self.targets.get(&field.bookmark) else {
+                    continue;
+                };
+                let Some(target_slots) = slots.get(&target) else {
+                    continue;
+                };
+                let result = joined(source, &field.slot_ids);
+                if normalized(&result) != normalized(&joined(source, target_slots)) {
+                    continue;
+                }
+                let text = joined(output, target_slots).trim().to_string();
+                if text.is_empty()
+                    || normalized(&joined(output, &field.slot_ids)) == normalized(&text)
+                {
+                    continue;
+                }
+                if replace_slots(output, &field.slot_ids, &text) {
+                    check.fields += 1;
+                }
+            }
+        }
+
+        // Source heading title -> translated heading.
+        let all_headings = self.headings(source, output);
+        let heading_ids: HashSet<usize> = all_headings.iter().map(|h| h.id).collect();
+        let mut headings: HashMap<String, String> = HashMap::new();
+        for h in all_headings {
+            if !h.title.is_empty() && !h.text.is_empty() {
+                headings.entry(h.title).or_insert(h.text);
+            }
+        }
+        if headings.is_empty() {
+            return check;
+        }
+        for (id, slot_ids) in &self.paras {
+            if heading_ids.contains(id) {
+                continue;
+            }
+            let src: Vec<char> = joined(source, slot_ids).chars().collect();
+            let src_pairs = quoted_spans(&src);
+            let quoted: Vec<Option<&String>> = src_pairs
+                .iter()
+                .map(|&(open, close)| {
+                    let inner: String = src[open + 1..close].iter().collect();
+                    headings.get(&normalized(&inner).to_lowercase())
+                })
+                .collect();
+            if quoted.iter().all(Option::is_none) {
+                continue;
+            }
+            let rewritten = rewrite_paragraph(output, slot_ids, |chars| {
+                let mut out = vec![None; chars.len()];
+                let pairs = quoted_spans(chars);
+                if pairs.len() != src_pairs.len() {
+                    return out;
+                }
+                for (&(open, close), heading) in pairs.iter().zip(&quoted) {
+                    let Some(heading) = heading else {
+                        continue;
+                    };
+                    let inner: String = chars[open + 1..close].iter().collect();
+                    if inner.trim() == heading.as_str() {
+                        continue;
+                    }
+                    out[open] = Some(format!("{}{heading}", chars[open]));
+                    for r in &mut out[open + 1..close] {
+                        *r = Some(String::new());
+                    }
+                }
+                out
+            });
+            if rewritten {
+                check.quoted += 1;
+            }
+        }
+        check
+    }
+
+    /// Literal table-of-contents entries against the final headings: an entry must show the
+    /// translation of the heading it names (its hyperlink's bookmark, else the longest heading
+    /// title in its source text). An entry whose title fills slots of its own gets the translated
+    /// heading there (`fields`, as in `harmonize`); the others are reported.
+    pub fn check_toc(
+        &self,
+        source: &PureTextJson,
+        output: &mut PureTextJson,
+        fields: bool,
+    ) -> TocCheck {
+        let mut check = TocCheck::default();
+        if self.toc_entries.is_empty() {
+            return check;
+        }
+        let headings = self.headings(source, output);
+        for entry in &self.toc_entries {
+            let src = normalized(&joined(source, &entry.slot_ids)).to_lowercase();
+            let linked = entry
+                .anchor
+                .as_ref()
+                .and_then(|a| self.targets.get(a))
+                .and_then(|id| headings.iter().find(|h| h.id == *id));
+            let named = || {
+                headings
+                    .iter()
+                    .filter(|h| !h.title.is_empty() && src.contains(&h.title))
+                    .max_by_key(|h| h.title.len())
+            };
+            let Some(heading) = linked.or_else(named) else {
+                continue;
+            };
+            let text = normalized(&heading.text);
+            let shown = normalized(&joined(output, &entry.slot_ids));
+            if text.is_empty() || shown.to_lowercase().contains(&text.to_lowercase()) {
+                continue;
+            }
+            if fields {
+                // Slots whose source text is exactly the heading title.
+                let title_slots = (0..entry.slot_ids.len()).find_map(|start| {
+                    (start + 1..=entry.slot_ids.len())
+                        .map(|end| &entry.slot_ids[start..end])
+                        .find(|ids| {
+                            normalized(&joined(source, ids)).to_lowercase() == heading.title
+                        })
+                });
+                if title_slots.is_some_and(|ids| replace_slots(output, ids, &text)) {
+                    check.fixed += 1;
+                    continue;
+                }
+            }
+            check.mismatches.push(TocMismatch {
+                tu_id: entry.tu_id,
+                entry: shown,
+                heading: text,
+            });
+        }
+        check
+    }
+}
+
+/// `(open, close)` char positions of the quoted spans in `chars`: the pairs `quote_pairs` finds,
+/// plus straight single quotes and German `‚...‘` pairs, which it leaves alone (apostrophes).
+fn quoted_spans(chars: &[char]) -> Vec<(usize, usize)> {
+    let mut spans: Vec<(usize, usize)> = quote_pairs(chars)
+        .into_iter()
+        .map(|(open, close, _)| (open, close))
+        .collect();
+    let mut open: Option<usize> = None;
+    for (i, &c) in chars.iter().enumerate() {
+        let after_word = i > 0 && chars[i - 1].is_alphanumeric();
+        let before_word = chars.get(i + 1).is_some_and(|c| c.is_alphanumeric());
+        match c {
+            '\'' | '\u{201A}' if open.is_none() && !after_word => open = Some(i),
+            '\'' | '\u{2018}' if !before_word => spans.extend(open.take().map(|o| (o, i))),
+            _ => {}
+        }
+    }
+    spans.sort_unstable();
+    spans
+}
+
+/// Put `text` into `slot_ids` (the first one; the others are emptied), keeping the whitespace
+/// around their current text. `false` when a slot was collapsed by the projection.
+fn replace_slots(output: &mut PureTextJson, slot_ids: &[usize], text: &str) -> bool {
+    if slot_ids
+        .iter()
+        .any(|id| output.collapsed_slots.contains(id))
+    {
+        return false;
+    }
+    let current = joined(output, slot_ids);
+    let lead = &current[..current.len() - current.trim_start().len()];
+    let trail = &current[current.trim_end().len()..];
+    let text = format!("{lead}{text}{trail}");
+    for (i, &id) in slot_ids.iter().enumerate() {
+        if let Some(slot) = id.checked_sub(1).and_then(|i| output.slot_texts.get_mut(i)) {
+            *slot = if i == 0 { text.clone() } else { String::new() };
+        }
+    }
+    true
+}
+
+/// Word's table-of-contents entry styles: `TOC1`..`TOC9` (`toc 1` by name).
+fn is_toc_style(style: &str) -> bool {
+    let compact: String = style
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    compact
+        .strip_prefix("toc")
+        .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Bookmark of the hyperlink in a run style from `build_para_slot_units` (`link(w:anchor=...)|`).
+fn link_anchor(style: &str) -> Option<String> {
+    let link = style.strip_prefix("link(")?.split(")|").next()?;
+    link.split(' ')
+        .find_map(|t| t.strip_prefix("w:anchor="))
+        .map(str::to_string)
+}
+
+fn joined(texts: &PureTextJson, slot_ids: &[usize]) -> String {
+    slot_ids
+        .iter()
+        .filter_map(|id| texts.slot_texts.get(id.checked_sub(1)?))
+        .map(String::as_str)
+        .collect()
+}
+
+/// Whitespace runs collapsed to one space, trimmed.
+fn normalized(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -1,9 +1,24 @@
 use std::io::{self, Write};
 use std::time::Instant;
 
+use crate::async_api::EventSender;
+
+/// A console message, as delivered to a `with_sink` receiver.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProgressEvent {
+    Info(String),
+    Progress {
+        label: String,
+        current: usize,
+        total: usize,
+    },
+}
+
+#[derive(Clone)]
 pub struct ConsoleProgress {
     enabled: bool,
     t0: Instant,
+    sink: Option<EventSender<ProgressEvent>>,
 }
 
 impl ConsoleProgress {
@@ -11,10 +26,20 @@ impl ConsoleProgress {
         Self {
             enabled,
             t0: Instant::now(),
+            sink: None,
         }
     }
 
+    /// Also deliver every message to `sink` (whether or not the console output is enabled).
+    pub fn with_sink(mut self, sink: EventSender<ProgressEvent>) -> Self {
+        self.sink = Some(sink);
+        self
+    }
+
     pub fn info(&self, msg: impl AsRef<str>) {
+        if let Some(sink) = &self.sink {
+            sink.send(ProgressEvent::Info(msg.as_ref().to_string()));
+        }
         if !self.enabled {
             return;
         }
@@ -24,6 +49,13 @@ impl ConsoleProgress {
     }
 
     pub fn progress(&self, label: &str, current: usize, total: usize) {
+        if let Some(sink) = &self.sink {
+            sink.send(ProgressEvent::Progress {
+                label: label.to_string(),
+                current,
+                total,
+            });
+        }
         if !self.enabled {
             return;
         }
@@ -79,6 +79,17 @@ impl QualityHeuristics {
     }
 }
 
+/// Whether a stored `qe_flags` entry is one of `quality_heuristics`' hard flags.
+#[must_use]
+pub fn is_hard_quality_flag(flag: &str) -> bool {
+    flag == "output_identical_to_source"
+        || flag.starts_with("target_script_missing_")
+        || flag == "contains_ellipsis_placeholder"
+        || flag == "len_ratio_too_short_extreme"
+        || flag == "len_ratio_too_long_extreme"
+        || flag.starts_with("missing_brackets_")
+}
+
 pub fn validate_translation(tu: &TranslationUnit, translated: &str) -> anyhow::Result<()> {
     if translated.trim().is_empty() {
         return Err(anyhow!("empty_output"));
@@ -289,9 +300,13 @@ pub fn quality_heuristics(
     let mut hard_flags: Vec<String> = Vec::new();
     let mut soft_flags: Vec<String> = Vec::new();
 
+    let tgt_lang = target_lang.trim().to_ascii_lowercase();
+    let src_lang = source_lang.trim().to_ascii_lowercase();
+    // Proofreading (same language) leaves correct text as it is.
+    let same_lang = !src_lang.is_empty() && src_lang == tgt_lang;
     let src_norm = normalize_for_similarity(&src_plain);
     let tgt_norm = normalize_for_similarity(&tgt_plain);
-    if !src_norm.is_empty() && src_norm == tgt_norm && src_norm.len() >= 24 {
+    if !same_lang && !src_norm.is_empty() && src_norm == tgt_norm && src_norm.len() >= 24 {
         hard_flags.push("output_identical_to_source".to_string());
     }
 
@@ -307,8 +322,6 @@ pub fn quality_heuristics(
         }
     }
 
-    let tgt_lang = target_lang.trim().to_ascii_lowercase();
-    let src_lang = source_lang.trim().to_ascii_lowercase();
     let lang_diff = !src_lang.is_empty() && !tgt_lang.is_empty() && src_lang != tgt_lang;
     if lang_diff && tgt_chars >= 20 {
         if tgt_lang.starts_with("zh") {
@@ -333,8 +346,9 @@ pub fn quality_heuristics(
         }
     }
 
-    let bracket_notes =
+    let mut bracket_notes =
         bracket_mismatch_notes(&src_plain, &tgt_plain, &mut hard_flags, &mut soft_flags);
+    bracket_notes.extend(quote_balance_notes(&src_plain, &tgt_plain, &mut soft_flags));
 
     let src_ellipsis = has_short_ellipsis(&src_plain);
     let tgt_ellipsis = has_short_ellipsis(&tgt_plain);
@@ -500,6 +514,29 @@ fn is_hangul(ch: char) -> bool {
         || (0x3130..=0x318F).contains(&u)
 }
 
+/// Character-bigram Dice similarity (0..=1) of two texts, ignoring whitespace, case and
+/// full-width punctuation.
+pub fn text_similarity(a: &str, b: &str) -> f64 {
+    let bigrams = |text: &str| -> HashMap<(char, char), usize> {
+        let chars: Vec<char> = normalize_for_similarity(text).chars().collect();
+        let mut out: HashMap<(char, char), usize> = HashMap::new();
+        for w in chars.windows(2) {
+            *out.entry((w[0], w[1])).or_default() += 1;
+        }
+        out
+    };
+    let (a, b) = (bigrams(a), bigrams(b));
+    let total: usize = a.values().sum::<usize>() + b.values().sum::<usize>();
+    if total == 0 {
+        return 1.0;
+    }
+    let common: usize = a
+        .iter()
+        .map(|(k, n)| (*n).min(b.get(k).copied().unwrap_or(0)))
+        .sum();
+    (2 * common) as f64 / total as f64
+}
+
 fn normalize_for_similarity(text: &str) -> String {
     let mut out = String::with_capacity(text.len());
     for ch in text.chars() {
@@ -596,6 +633,58 @@ fn bracket_mismatch_notes(
     notes
 }
 
+/// Quote families whose marks do not pair up in `text`: `(display, opening, closing)` counts.
+fn unbalanced_quotes(text: &str) -> Vec<(&'static str, usize, usize)> {
+    let count = |set: &[char]| text.chars().filter(|c| set.contains(c)).count();
+    let straight = count(&['"', '\u{FF02}']);
+    // German „...“ closes with the mark English opens with.
+    let (curly_open, curly_close) = if text.contains('\u{201E}') {
+        (count(&['\u{201E}']), count(&['\u{201C}', '\u{201D}']))
+    } else {
+        (count(&['\u{201C}']), count(&['\u{201D}']))
+    };
+    let mut out = Vec::new();
+    if straight % 2 == 1 {
+        out.push(("\"\"", straight.div_ceil(2), straight / 2));
+    }
+    for (display, open, close) in [
+        ("\u{201C}\u{201D}", curly_open, curly_close),
+        ("\u{AB}\u{BB}", count(&['\u{AB}']), count(&['\u{BB}'])),
+        (
+            "\u{300C}\u{300D}",
+            count(&['\u{300C}']),
+            count(&['\u{300D}']),
+        ),
+        (
+            "\u{300E}\u{300F}",
+            count(&['\u{300E}']),
+            count(&['\u{300F}']),
+        ),
+    ] {
+        if open != close {
+            out.push((display, open, close));
+        }
+    }
+    out
+}
+
+/// Quote pairs broken by the translation (a family unbalanced in `tgt` but not in `src`, which
+/// may legitimately leave a quotation open across paragraphs).
+fn quote_balance_notes(src: &str, tgt: &str, soft_flags: &mut Vec<String>) -> Vec<String> {
+    let src_unbalanced = !unbalanced_quotes(src).is_empty();
+    if src_unbalanced {
+        return Vec::new();
+    }
+    let notes: Vec<String> = unbalanced_quotes(tgt)
+        .into_iter()
+        .map(|(display, open, close)| format!("quotes {display}:open={open} close={close}"))
+        .collect();
+    if !notes.is_empty() {
+        soft_flags.push("quote_pairs_unbalanced".to_string());
+    }
+    notes
+}
+
 pub fn must_extract_json_obj(text: &str) -> anyhow::Result<serde_json::Value> {
     let start = text.find('{').context("no_json_object_start")?;
     let slice = &text[start..];
@@ -6,6 +6,7 @@ use std::collections::HashMap;
 pub const SEG_ID_WIDTH: usize = 6;
 pub const NT_ID_WIDTH: usize = 4;
 pub const SLOT_ID_WIDTH: usize = 6;
+pub const EM_ID_WIDTH: usize = 2;
 
 pub const TAB: &str = "<<MT_TAB>>";
 pub const BR: &str = "<<MT_BR>>";
@@ -39,8 +40,10 @@ pub fn seg_end(seg_id: usize) -> String {
 }
 
 pub static ANY_SENTINEL_RE: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"<<MT_(?:TAB|BR|NBH|SHY|NT:\d{4}|SEG:\d{6}|END:\d{6}|SLOT:\d{6})>>")
-        .expect("sentinel regex")
+    Regex::new(
+        r"<<MT_(?:TAB|BR|NBH|SHY|NT:\d{4}|SEG:\d{6}|END:\d{6}|SLOT:\d{6}|EM:\d{2}|EM_END:\d{2})>>",
+    )
+    .expect("sentinel regex")
 });
 
 // Any token that looks like our internal markers. This is broader than ANY_SENTINEL_RE and is used
@@ -54,6 +57,18 @@ pub fn slot_token(slot_id: usize) -> String {
     format!("<<MT_SLOT:{slot_id:0SLOT_ID_WIDTH$}>>")
 }
 
+static EM_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"<<MT_EM(_END)?:(\d{2})>>").expect("em regex"));
+
+/// Opens a range of text in run style `style_id` (span projection).
+pub fn em_start(style_id: usize) -> String {
+    format!("<<MT_EM:{style_id:0EM_ID_WIDTH$}>>")
+}
+
+pub fn em_end(style_id: usize) -> String {
+    format!("<<MT_EM_END:{style_id:0EM_ID_WIDTH$}>>")
+}
+
 pub fn sentinel_sequence(text: &str) -> Vec<String> {
     if text.is_empty() {
         return Vec::new();
@@ -125,6 +140,33 @@ pub fn parse_segmented_output(
     Ok(segments)
 }
 
+/// Split span-projection output into `(style_id, text)` pieces in order: text outside the
+/// `<<MT_EM:nn>>`...`<<MT_EM_END:nn>>` pairs has no style id. Nested or unpaired markers are an
+/// error.
+pub fn parse_em_output(text: &str) -> anyhow::Result<Vec<(Option<usize>, String)>> {
+    let mut pieces: Vec<(Option<usize>, String)> = Vec::new();
+    let mut open: Option<usize> = None;
+    let mut pos = 0usize;
+    for caps in EM_RE.captures_iter(text) {
+        let m = caps.get(0).unwrap();
+        let style_id: usize = caps[2].parse().unwrap_or(0);
+        let is_end = caps.get(1).is_some();
+        pieces.push((open, text[pos..m.start()].to_string()));
+        pos = m.end();
+        open = match (open, is_end) {
+            (None, false) => Some(style_id),
+            (Some(id), true) if id == style_id => None,
+            (Some(id), false) => return Err(anyhow!("em_marker_nested open={id} got={style_id}")),
+            (_, true) => return Err(anyhow!("em_marker_unpaired end={style_id}")),
+        };
+    }
+    if let Some(id) = open {
+        return Err(anyhow!("em_marker_unclosed id={id}"));
+    }
+    pieces.push((None, text[pos..].to_string()));
+    Ok(pieces)
+}
+
 pub fn parse_slot_output(
     text: &str,
     expected_ids: &[usize],
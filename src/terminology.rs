@@ -107,6 +107,15 @@ impl TermMemory {
         events
     }
 
+    /// Insert a previously persisted decision as-is (keeps `seen`, replaces any existing entry).
+    pub fn insert_decision(&mut self, decision: TermDecision) {
+        self.terms.insert(decision.src.clone(), decision);
+    }
+
+    pub fn decisions(&self) -> impl Iterator<Item = &TermDecision> {
+        self.terms.values()
+    }
+
     #[must_use]
     pub fn relevant_for_text<'a>(&'a self, text: &str, max_items: usize) -> Vec<&'a TermDecision> {
         if self.terms.is_empty() || text.is_empty() || max_items == 0 {
@@ -122,6 +131,7 @@ impl TermMemory {
                 .len()
                 .cmp(&a.src.len())
                 .then_with(|| b.seen.cmp(&a.seen))
+                .then_with(|| a.src.cmp(&b.src))
         });
         items.truncate(max_items);
         items
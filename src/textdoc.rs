@@ -0,0 +1,469 @@
+//! Plain-text / Markdown documents: segmentation into translatable units and re-assembly.
+//!
+//! A document is a flat list of blocks whose concatenation reproduces the source exactly.
+//! Structure (blank lines, code fences, list/heading/quote markers, table separators) stays in
+//! verbatim blocks or unit prefixes; only unit text is sent to the model. Line breaks inside a
+//! paragraph are carried as `<<MT_BR>>` so the validators keep them in place.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::freezer::{freeze_text_with, AcronymRules, FreezeResult};
+use crate::sentinels::{nt_token, ANY_SENTINEL_RE, BR, NT_RE};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextDocKind {
+    Plain,
+    Markdown,
+}
+
+impl TextDocKind {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "txt" | "text" => Some(Self::Plain),
+            "md" | "markdown" => Some(Self::Markdown),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum TextBlock {
+    Verbatim(String),
+    Unit(TextUnit),
+}
+
+#[derive(Clone, Debug)]
+pub struct TextUnit {
+    /// Line prefix kept outside the translation (indent, `> `, `- `, `## `, leading `|`).
+    pub prefix: String,
+    /// Translatable text; inner line breaks are `<<MT_BR>>`.
+    pub text: String,
+    /// Trailing part kept as is (trailing `|`, line ending).
+    pub suffix: String,
+    /// Markdown table row: cell separators are frozen.
+    pub table_row: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct TextDocument {
+    pub kind: TextDocKind,
+    pub blocks: Vec<TextBlock>,
+    bom: bool,
+    crlf: bool,
+}
+
+static MD_FENCE_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^ {0,3}(`{3,}|~{3,})").expect("md fence regex"));
+static MD_PREFIX_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^[ \t]*(?:>[ \t]?)*[ \t]*(?:#{1,6}[ \t]+|[-*+][ \t]+(?:\[[ xX]\][ \t]+)?|\d{1,9}[.)][ \t]+(?:\[[ xX]\][ \t]+)?)?",
+    )
+    .expect("md prefix regex")
+});
+static MD_THEMATIC_BREAK_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^ {0,3}(?:(?:-[ \t]*){3,}|(?:\*[ \t]*){3,}|(?:_[ \t]*){3,})$")
+        .expect("md hr regex")
+});
+static MD_SETEXT_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^ {0,3}=+[ \t]*$").expect("md setext regex"));
+static MD_TABLE_SEP_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^[ \t]*\|?[ \t]*:?-{3,}:?[ \t]*(?:\|[ \t]*:?-{3,}:?[ \t]*)*\|?[ \t]*$")
+        .expect("md table sep regex")
+});
+static MD_LINK_DEF_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^ {0,3}\[[^\]]+\]:[ \t]*\S+").expect("md link def regex"));
+static MD_HTML_LINE_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^[ \t]*</?[A-Za-z][^>]*>[ \t]*$").expect("md html line regex"));
+/// Inline Markdown syntax that must survive translation verbatim.
+static MD_INLINE_RE: Lazy<Regex> = Lazy::new(|| {
+    let code = r"``[^`\n]+``|`[^`\n]+`";
+    let link_target = r#"\]\([^()\s]*(?:\([^()\s]*\)[^()\s]*)*(?:[ \t]+"[^"\n]*")?\)"#;
+    let link_ref = r"\]\[[^\[\]\n]*\]";
+    let autolink = r"<(?:https?|mailto|ftp):[^<>\s]+>";
+    let html_tag = r"</?[A-Za-z][A-Za-z0-9\-]*(?:\s[^<>]*)?/?>";
+    Regex::new(&format!(
+        "{code}|{link_target}|{link_ref}|{autolink}|{html_tag}"
+    ))
+    .expect("md inline regex")
+});
+static MD_TABLE_PIPE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\\?\|").expect("md pipe regex"));
+
+impl TextDocument {
+    pub fn parse(text: &str, kind: TextDocKind) -> Self {
+        let bom = text.starts_with('\u{FEFF}');
+        let body = text.trim_start_matches('\u{FEFF}');
+        let crlf = body.contains("\r\n");
+        let body = if crlf {
+            body.replace("\r\n", "\n")
+        } else {
+            body.to_string()
+        };
+        let blocks = match kind {
+            TextDocKind::Plain => parse_plain(&body),
+            TextDocKind::Markdown => parse_markdown(&body),
+        };
+        Self {
+            kind,
+            blocks,
+            bom,
+            crlf,
+        }
+    }
+
+    pub fn units(&self) -> impl Iterator<Item = &TextUnit> {
+        self.blocks.iter().filter_map(|b| match b {
+            TextBlock::Unit(u) => Some(u),
+            TextBlock::Verbatim(_) => None,
+        })
+    }
+
+    /// Freeze a unit's text for the model: the standard freezer with the configured `acronyms`,
+    /// plus inline Markdown syntax (code spans, link targets, HTML tags, table pipes) for
+    /// Markdown documents.
+    pub fn freeze_unit(&self, unit: &TextUnit, acronyms: &AcronymRules) -> FreezeResult {
+        let fr = freeze_text_with(&unit.text, acronyms);
+        match self.kind {
+            TextDocKind::Plain => fr,
+            TextDocKind::Markdown => freeze_markdown_inline(fr, unit.table_row),
+        }
+    }
+
+    /// Re-assemble the document. `translations[i]` replaces the text of the i-th unit
+    /// (`None` keeps the source text).
+    pub fn render(&self, translations: &[Option<String>]) -> String {
+        let mut out = String::new();
+        if self.bom {
+            out.push('\u{FEFF}');
+        }
+        let mut unit_idx = 0usize;
+        for block in &self.blocks {
+            match block {
+                TextBlock::Verbatim(s) => out.push_str(s),
+                TextBlock::Unit(u) => {
+                    let text = translations
+                        .get(unit_idx)
+                        .and_then(|t| t.as_deref())
+                        .unwrap_or(&u.text);
+                    unit_idx += 1;
+                    out.push_str(&u.prefix);
+                    out.push_str(&render_unit_text(text));
+                    out.push_str(&u.suffix);
+                }
+            }
+        }
+        if self.crlf {
+            out.replace('\n', "\r\n")
+        } else {
+            out
+        }
+    }
+}
+
+fn render_unit_text(text: &str) -> String {
+    // Models occasionally put line breaks around sentinels; the sentinel is the line break.
+    let text = text
+        .replace(&format!("{BR}\n"), BR)
+        .replace(&format!("\n{BR}"), BR);
+    text.replace('\n', " ").replace(BR, "\n")
+}
+
+fn parse_plain(body: &str) -> Vec<TextBlock> {
+    let mut blocks: Vec<TextBlock> = Vec::new();
+    let mut open: Option<TextUnit> = None;
+    for line in body.split_inclusive('\n') {
+        let (content, eol) = split_eol(line);
+        if content.trim().is_empty() {
+            flush(&mut blocks, &mut open);
+            push_verbatim(&mut blocks, line);
+            continue;
+        }
+        match open.as_mut() {
+            Some(u) => continue_unit(u, content, eol),
+            None => open = Some(line_unit(content, eol, leading_ws_len(content), false)),
+        }
+    }
+    flush(&mut blocks, &mut open);
+    blocks
+}
+
+fn parse_markdown(body: &str) -> Vec<TextBlock> {
+    let mut blocks: Vec<TextBlock> = Vec::new();
+    let mut open: Option<TextUnit> = None;
+    let mut fence: Option<String> = None;
+    let mut front_matter = false;
+    let mut prev_blank = true;
+
+    for (line_no, line) in body.split_inclusive('\n').enumerate() {
+        let (content, eol) = split_eol(line);
+
+        if line_no == 0 && content.trim_end() == "---" {
+            front_matter = true;
+            push_verbatim(&mut blocks, line);
+            continue;
+        }
+        if front_matter {
+            if matches!(content.trim_end(), "---" | "...") {
+                front_matter = false;
+            }
+            push_verbatim(&mut blocks, line);
+            continue;
+        }
+
+        if let Some(marker) = fence.as_ref() {
+            let closes = MD_FENCE_RE
+                .captures(content)
+                .and_then(|c| c.get(1))
+                .is_some_and(|m| {
+                    m.as_str().starts_with(&marker[..1]) && m.as_str().len() >= marker.len()
+                });
+            if closes {
+                fence = None;
+            }
+            push_verbatim(&mut blocks, line);
+            continue;
+        }
+        if let Some(m) = MD_FENCE_RE.captures(content).and_then(|c| c.get(1)) {
+            flush(&mut blocks, &mut open);
+            fence = Some(m.as_str().to_string());
+            push_verbatim(&mut blocks, line);
+            prev_blank = false;
+            continue;
+        }
+
+        let blank = content.trim().is_empty();
+        let indented_code = prev_blank
+            && open.is_none()
+            && (content.starts_with("    ") || content.starts_with('\t'));
+        if blank
+            || indented_code
+            || MD_THEMATIC_BREAK_RE.is_match(content)
+            || MD_SETEXT_RE.is_match(content)
+            || (MD_TABLE_SEP_RE.is_match(content) && content.contains('|'))
+            || MD_LINK_DEF_RE.is_match(content)
+            || MD_HTML_LINE_RE.is_match(content)
+        {
+            flush(&mut blocks, &mut open);
+            push_verbatim(&mut blocks, line);
+            prev_blank = blank;
+            continue;
+        }
+        prev_blank = false;
+
+        let trimmed = content.trim_start();
+        if trimmed.starts_with('|') {
+            flush(&mut blocks, &mut open);
+            let row = &content[content.len() - trimmed.len() + 1..];
+            let (row, close) = match row.trim_end().strip_suffix('|') {
+                Some(rest) if !rest.ends_with('\\') => (rest, &row[rest.len()..]),
+                _ => (row, ""),
+            };
+            let text = row.trim();
+            let lead = row.len() - row.trim_start().len();
+            blocks.push(TextBlock::Unit(TextUnit {
+                prefix: content[..content.len() - row.len() - close.len()].to_string()
+                    + &row[..lead],
+                text: text.to_string(),
+                suffix: format!("{}{close}{eol}", &row[lead + text.len()..]),
+                table_row: true,
+            }));
+            continue;
+        }
+
+        let prefix_len = MD_PREFIX_RE.find(content).map(|m| m.end()).unwrap_or(0);
+        let has_marker = content[..prefix_len]
+            .trim()
+            .chars()
+            .any(|c| !c.is_whitespace());
+        if let (Some(u), false) = (open.as_mut(), has_marker) {
+            // Paragraph continuation (including lazy list/quote continuation lines).
+            continue_unit(u, content, eol);
+            continue;
+        }
+        flush(&mut blocks, &mut open);
+        let is_heading = content[..prefix_len].contains('#');
+        let unit = line_unit(content, eol, prefix_len, false);
+        if is_heading {
+            blocks.push(TextBlock::Unit(unit));
+        } else {
+            open = Some(unit);
+        }
+    }
+    flush(&mut blocks, &mut open);
+    blocks
+}
+
+fn line_unit(content: &str, eol: &str, prefix_len: usize, table_row: bool) -> TextUnit {
+    let (text, trail) = split_trailing_ws(&content[prefix_len..]);
+    TextUnit {
+        prefix: content[..prefix_len].to_string(),
+        text: text.to_string(),
+        suffix: format!("{trail}{eol}"),
+        table_row,
+    }
+}
+
+/// Append one more source line to an open unit; the previous line's trailing whitespace moves
+/// into the text so the unit still reproduces the source exactly.
+fn continue_unit(u: &mut TextUnit, content: &str, eol: &str) {
+    let prev_trail = u.suffix.strip_suffix('\n').unwrap_or(&u.suffix);
+    u.text.push_str(prev_trail);
+    u.text.push_str(BR);
+    let (text, trail) = split_trailing_ws(content);
+    u.text.push_str(text);
+    u.suffix = format!("{trail}{eol}");
+}
+
+fn flush(blocks: &mut Vec<TextBlock>, open: &mut Option<TextUnit>) {
+    if let Some(u) = open.take() {
+        blocks.push(TextBlock::Unit(u));
+    }
+}
+
+fn push_verbatim(blocks: &mut Vec<TextBlock>, s: &str) {
+    if let Some(TextBlock::Verbatim(prev)) = blocks.last_mut() {
+        prev.push_str(s);
+    } else {
+        blocks.push(TextBlock::Verbatim(s.to_string()));
+    }
+}
+
+fn split_eol(line: &str) -> (&str, &str) {
+    match line.strip_suffix('\n') {
+        Some(content) => (content, "\n"),
+        None => (line, ""),
+    }
+}
+
+fn split_trailing_ws(s: &str) -> (&str, &str) {
+    let text = s.trim_end();
+    (text, &s[text.len()..])
+}
+
+fn leading_ws_len(s: &str) -> usize {
+    s.len() - s.trim_start().len()
+}
+
+/// Second freezing pass over already-frozen text. Spans may contain NT tokens from the first
+/// pass; they are resolved into the new token's original, and tokens swallowed that way are
+/// dropped from the map.
+fn freeze_markdown_inline(fr: FreezeResult, table_row: bool) -> FreezeResult {
+    let FreezeResult {
+        text,
+        mut nt_map,
+        mut mask,
+    } = fr;
+    let mut next_id = nt_map
+        .keys()
+        .filter_map(|t| NT_RE.captures(t)?.get(1)?.as_str().parse::<usize>().ok())
+        .max()
+        .unwrap_or(0)
+        + 1;
+    let mut rev: HashMap<String, String> = HashMap::new();
+
+    let mut out = String::with_capacity(text.len());
+    let mut pos = 0usize;
+    let mut freeze_piece = |piece: &str, out: &mut String| {
+        let mut cursor = 0usize;
+        let mut spans: Vec<(usize, usize)> = MD_INLINE_RE
+            .find_iter(piece)
+            .map(|m| (m.start(), m.end()))
+            .collect();
+        if table_row {
+            spans.extend(
+                MD_TABLE_PIPE_RE
+                    .find_iter(piece)
+                    .map(|m| (m.start(), m.end())),
+            );
+            spans.sort_unstable();
+        }
+        for (start, end) in spans {
+            if start < cursor {
+                continue;
+            }
+            out.push_str(&piece[cursor..start]);
+            let original = crate::freezer::unfreeze_text(&piece[start..end], &nt_map);
+            let token = rev
+                .entry(original.clone())
+                .or_insert_with(|| {
+                    let tok = nt_token(next_id);
+                    next_id += 1;
+                    tok
+                })
+                .clone();
+            out.push_str(&token);
+            cursor = end;
+            push_mask_span(&mut mask, &token, &original);
+        }
+        out.push_str(&piece[cursor..]);
+    };
+    for m in ANY_SENTINEL_RE.find_iter(&text) {
+        if NT_RE.is_match(m.as_str()) {
+            continue;
+        }
+        freeze_piece(&text[pos..m.start()], &mut out);
+        out.push_str(m.as_str());
+        pos = m.end();
+    }
+    freeze_piece(&text[pos..], &mut out);
+
+    for (original, token) in rev {
+        nt_map.insert(token, original);
+    }
+    nt_map.retain(|tok, _| out.contains(tok.as_str()));
+    mask.retain(|span| out.contains(span.token.as_str()));
+    FreezeResult {
+        text: out,
+        nt_map,
+        mask,
+    }
+}
+
+/// Markdown spans are recorded without source offsets (they refer to the frozen text).
+fn push_mask_span(mask: &mut Vec<crate::ir::FreezeMaskSpan>, token: &str, original: &str) {
+    if mask.iter().any(|s| s.token == token) {
+        return;
+    }
+    mask.push(crate::ir::FreezeMaskSpan {
+        src_start: 0,
+        src_end: 0,
+        token: token.to_string(),
+        original: original.to_string(),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TextDocKind, TextDocument};
+    use crate::freezer::{unfreeze_text, AcronymRules};
+
+    #[test]
+    fn markdown_roundtrip_and_freeze() {
+        let src = "# Title\r\n\r\nSome `code` and a [link](https://example.com/a_1).\r\nSecond line.\r\n\r\n```rust\r\nlet x = 1;\r\n```\r\n\r\n| a | b |\r\n|---|---|\r\n| c | d |\r\n- item one\r\n";
+        let doc = TextDocument::parse(src, TextDocKind::Markdown);
+        assert_eq!(doc.render(&[]), src);
+
+        let units: Vec<_> = doc.units().collect();
+        assert_eq!(units.len(), 5);
+        assert_eq!(units[0].text, "Title");
+        assert_eq!(
+            units[1].text,
+            "Some `code` and a [link](https://example.com/a_1).<<MT_BR>>Second line."
+        );
+
+        let fr = doc.freeze_unit(units[1], &AcronymRules::default());
+        assert!(!fr.text.contains('`'));
+        assert!(!fr.text.contains("https"));
+        assert!(fr.text.contains("<<MT_BR>>"));
+        assert_eq!(unfreeze_text(&fr.text, &fr.nt_map), units[1].text);
+
+        assert_eq!(units[2].text, "a | b");
+        let fr = doc.freeze_unit(units[2], &AcronymRules::default());
+        assert!(!fr.text.contains('|'));
+        assert_eq!(units[4].prefix, "- ");
+    }
+}
@@ -47,3 +47,102 @@ pub fn lang_label(code: &str) -> String {
         code.trim().to_string()
     }
 }
+
+/// Letter runs a numeric table cell may carry besides digits: ISO currency codes and
+/// magnitude suffixes (`12k`, `3.5M`, `EUR 1.2bn`).
+const NUMERIC_CELL_WORDS: &[&str] = &[
+    "USD", "EUR", "GBP", "JPY", "CNY", "RMB", "HKD", "TWD", "CHF", "CAD", "AUD", "NZD", "SGD",
+    "KRW", "INR", "RUB", "BRL", "MXN", "SEK", "NOK", "DKK", "PLN", "k", "K", "M", "B", "bn", "mn",
+];
+
+static LETTER_RUN_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\p{L}+").expect("letter run"));
+static NUMBER_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\d[\d.,\u{A0}\u{202F}]*\d|\d").expect("number"));
+
+/// Cells such as `1,234.50`, `2024-03-31`, `12.5%`, `USD 1,200` or `€3.2bn`: digits plus
+/// punctuation, symbols, currency codes and magnitude suffixes only, so there is nothing for a
+/// model to translate.
+pub fn is_numeric_only_text(text: &str) -> bool {
+    let plain = strip_sentinels(text);
+    plain.chars().any(|c| c.is_ascii_digit())
+        && LETTER_RUN_RE
+            .find_iter(&plain)
+            .all(|m| NUMERIC_CELL_WORDS.contains(&m.as_str()))
+}
+
+/// Languages writing `1.234,5` / `1 234,5` rather than `1,234.5`, with their group separator.
+fn decimal_comma_grouping(lang: &str) -> Option<char> {
+    let lang = lang.trim().to_ascii_lowercase();
+    let base = lang.split(['-', '_']).next().unwrap_or("");
+    match base {
+        "de" | "es" | "it" | "pt" | "nl" | "tr" | "id" | "da" | "ro" | "el" => Some('.'),
+        "fr" | "ru" | "pl" | "cs" | "sk" | "sv" | "fi" | "nb" | "no" | "uk" | "hu" => {
+            Some('\u{A0}')
+        }
+        _ => None,
+    }
+}
+
+/// Rewrite the decimal and group separators of the numbers in `text` from the conventions of
+/// `source_lang` to those of `target_lang`. Numbers that do not parse unambiguously in the
+/// source convention (`1.2.3`, `31.03.2024`) and plain integers are left as they are.
+pub fn localize_numbers(text: &str, source_lang: &str, target_lang: &str) -> String {
+    let from = decimal_comma_grouping(source_lang);
+    let to = decimal_comma_grouping(target_lang);
+    if from == to {
+        return text.to_string();
+    }
+    let (from_decimal, to_decimal) = (
+        if from.is_some() { ',' } else { '.' },
+        if to.is_some() { ',' } else { '.' },
+    );
+    let to_group = to.unwrap_or(',');
+    NUMBER_RE
+        .replace_all(text, |caps: &regex::Captures| {
+            let num = &caps[0];
+            let Some((int, frac, grouped)) = split_number(num, from_decimal) else {
+                return num.to_string();
+            };
+            let mut out = if grouped {
+                group_digits(int.as_str(), to_group)
+            } else {
+                int
+            };
+            if let Some(frac) = frac {
+                out.push(to_decimal);
+                out.push_str(&frac);
+            }
+            out
+        })
+        .into_owned()
+}
+
+/// `(integer digits, fraction digits, had group separators)` of `num` written with
+/// `decimal` as the decimal separator and groups of three; `None` for plain integers and
+/// anything that is not a well-formed number in that convention.
+fn split_number(num: &str, decimal: char) -> Option<(String, Option<String>, bool)> {
+    let (int, frac) = match num.split_once(decimal) {
+        Some((int, frac)) if frac.chars().all(|c| c.is_ascii_digit()) => (int, Some(frac)),
+        Some(_) => return None,
+        None => (num, None),
+    };
+    let groups: Vec<&str> = int.split(|c: char| !c.is_ascii_digit()).collect();
+    let grouped = groups.len() > 1;
+    let well_formed = !grouped
+        || ((1..=3).contains(&groups[0].len()) && groups[1..].iter().all(|g| g.len() == 3));
+    if !well_formed || (!grouped && frac.is_none()) {
+        return None;
+    }
+    Some((groups.concat(), frac.map(str::to_string), grouped))
+}
+
+fn group_digits(digits: &str, sep: char) -> String {
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(sep);
+        }
+        out.push(c);
+    }
+    out
+}